@@ -0,0 +1,71 @@
+use anyhow::Result;
+use bench::{Bench, Big, PointParam};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use wasmer::{Module, Store};
+use wasmer_wasi::WasiState;
+
+wai_bindgen_wasmer::import!("bench.wai");
+
+fn instantiate() -> Result<(Store, Bench)> {
+    let mut store = Store::default();
+    let module = Module::from_file(&store, env!("BENCH_WASM"))?;
+    let wasi_env = WasiState::new("bench").finalize(&mut store)?;
+    let mut imports = wasi_env.import_object(&mut store, &module)?;
+    let (exports, instance) = Bench::instantiate(&mut store, &module, &mut imports)?;
+    let memory = instance.exports.get_memory("memory")?;
+    wasi_env.data_mut(&mut store).set_memory(memory.clone());
+    Ok((store, exports))
+}
+
+fn benches(c: &mut Criterion) {
+    let (mut store, exports) = instantiate().unwrap();
+
+    let mut group = c.benchmark_group("wasmer/echo-string");
+    for len in [16, 4096] {
+        let s = "x".repeat(len);
+        group.throughput(Throughput::Bytes(len as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(len), &s, |b, s| {
+            b.iter(|| exports.echo_string(&mut store, black_box(s)).unwrap())
+        });
+    }
+    group.finish();
+
+    let labels = (0..100).map(|i| format!("point {}", i)).collect::<Vec<_>>();
+    let points = labels
+        .iter()
+        .enumerate()
+        .map(|(i, label)| PointParam {
+            x: i as f64,
+            y: 1.0,
+            label,
+        })
+        .collect::<Vec<_>>();
+    let mut group = c.benchmark_group("wasmer/list-of-records");
+    group.throughput(Throughput::Elements(points.len() as u64));
+    group.bench_function("sum-points", |b| {
+        b.iter(|| exports.sum_points(&mut store, black_box(&points)).unwrap())
+    });
+    group.bench_function("echo-points", |b| {
+        b.iter(|| exports.echo_points(&mut store, black_box(&points)).unwrap())
+    });
+    group.finish();
+
+    c.bench_function("wasmer/big-flags", |b| {
+        b.iter(|| {
+            exports
+                .invert_flags(&mut store, black_box(Big::B0 | Big::B63))
+                .unwrap()
+        })
+    });
+
+    c.bench_function("wasmer/resource-churn", |b| {
+        b.iter(|| {
+            let counter = exports.counter_new(&mut store, black_box(1)).unwrap();
+            exports.counter_get(&mut store, &counter).unwrap();
+            exports.drop_counter(&mut store, counter).unwrap();
+        })
+    });
+}
+
+criterion_group!(wasmer, benches);
+criterion_main!(wasmer);
@@ -0,0 +1,82 @@
+use anyhow::Result;
+use bench::{Bench, BenchData, Big, PointParam};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use wasmtime::{Engine, Linker, Module, Store};
+use wasmtime_wasi::preview1::{add_to_linker_sync, WasiP1Ctx};
+
+wai_bindgen_wasmtime::import!("bench.wai");
+
+struct Context {
+    wasi: WasiP1Ctx,
+    exports: BenchData,
+}
+
+fn instantiate() -> Result<(Store<Context>, Bench<Context>)> {
+    let engine = Engine::default();
+    let module = Module::from_file(&engine, env!("BENCH_WASM"))?;
+    let mut linker = Linker::new(&engine);
+    add_to_linker_sync(&mut linker, |cx: &mut Context| &mut cx.wasi)?;
+    let mut store = Store::new(
+        &engine,
+        Context {
+            wasi: wasmtime_wasi::WasiCtxBuilder::new().build_p1(),
+            exports: BenchData::default(),
+        },
+    );
+    let (exports, _instance) =
+        Bench::instantiate(&mut store, &module, &mut linker, |cx| &mut cx.exports)?;
+    Ok((store, exports))
+}
+
+fn benches(c: &mut Criterion) {
+    let (mut store, exports) = instantiate().unwrap();
+
+    let mut group = c.benchmark_group("wasmtime/echo-string");
+    for len in [16, 4096] {
+        let s = "x".repeat(len);
+        group.throughput(Throughput::Bytes(len as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(len), &s, |b, s| {
+            b.iter(|| exports.echo_string(&mut store, black_box(s)).unwrap())
+        });
+    }
+    group.finish();
+
+    let labels = (0..100).map(|i| format!("point {}", i)).collect::<Vec<_>>();
+    let points = labels
+        .iter()
+        .enumerate()
+        .map(|(i, label)| PointParam {
+            x: i as f64,
+            y: 1.0,
+            label,
+        })
+        .collect::<Vec<_>>();
+    let mut group = c.benchmark_group("wasmtime/list-of-records");
+    group.throughput(Throughput::Elements(points.len() as u64));
+    group.bench_function("sum-points", |b| {
+        b.iter(|| exports.sum_points(&mut store, black_box(&points)).unwrap())
+    });
+    group.bench_function("echo-points", |b| {
+        b.iter(|| exports.echo_points(&mut store, black_box(&points)).unwrap())
+    });
+    group.finish();
+
+    c.bench_function("wasmtime/big-flags", |b| {
+        b.iter(|| {
+            exports
+                .invert_flags(&mut store, black_box(Big::B0 | Big::B63))
+                .unwrap()
+        })
+    });
+
+    c.bench_function("wasmtime/resource-churn", |b| {
+        b.iter(|| {
+            let counter = exports.counter_new(&mut store, black_box(1)).unwrap();
+            exports.counter_get(&mut store, &counter).unwrap();
+            exports.drop_counter(&mut store, counter).unwrap();
+        })
+    });
+}
+
+criterion_group!(wasmtime, benches);
+criterion_main!(wasmtime);
@@ -0,0 +1,22 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+// Compiles the guest the benchmarks call into, passing its path to them as
+// `BENCH_WASM`.
+fn main() {
+    let out_dir = PathBuf::from(std::env::var_os("OUT_DIR").unwrap());
+    let status = Command::new("cargo")
+        .arg("build")
+        .arg("--release")
+        .arg("--target=wasm32-wasi")
+        .current_dir("guest")
+        .env("CARGO_TARGET_DIR", &out_dir)
+        .env_remove("CARGO_ENCODED_RUSTFLAGS")
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let wasm = out_dir.join("wasm32-wasi/release/wai-bindgen-bench-guest.wasm");
+    println!("cargo:rustc-env=BENCH_WASM={}", wasm.display());
+    println!("cargo:rerun-if-changed=bench.wai");
+    println!("cargo:rerun-if-changed=guest");
+}
@@ -0,0 +1,38 @@
+wai_bindgen_rust::export!("../bench.wai");
+
+use bench::{Big, Point};
+use wai_bindgen_rust::Handle;
+
+struct Bench;
+
+impl bench::Bench for Bench {
+    fn echo_string(s: String) -> String {
+        s
+    }
+
+    fn sum_points(points: Vec<Point>) -> f64 {
+        points.iter().map(|p| p.x + p.y).sum()
+    }
+
+    fn echo_points(points: Vec<Point>) -> Vec<Point> {
+        points
+    }
+
+    fn invert_flags(f: Big) -> Big {
+        !f
+    }
+}
+
+pub struct Counter(u32);
+
+impl bench::Counter for Counter {
+    fn new(start: u32) -> Handle<Counter> {
+        Counter(start).into()
+    }
+
+    fn get(&self) -> u32 {
+        self.0
+    }
+}
+
+fn main() {}
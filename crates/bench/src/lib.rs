@@ -0,0 +1,2 @@
+//! The benchmarks live in `benches/`, this crate only builds the guest they
+//! call into.
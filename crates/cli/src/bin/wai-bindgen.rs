@@ -1,7 +1,9 @@
-use anyhow::{Context, Result};
-use std::path::PathBuf;
+use anyhow::{bail, Context, Result};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use structopt::StructOpt;
-use wai_bindgen_gen_core::{wai_parser, Files, Generator};
+use wai_bindgen_gen_core::{parse_interface_arg_with_cfg, wai_parser, Files, Filter, Generator};
 use wai_parser::Interface;
 
 #[derive(Debug, StructOpt)]
@@ -18,6 +20,12 @@ enum Command {
         #[structopt(flatten)]
         common: Common,
     },
+    RustTypes {
+        #[structopt(flatten)]
+        opts: wai_bindgen_gen_rust_types::Opts,
+        #[structopt(flatten)]
+        common: Common,
+    },
     Wasmtime {
         #[structopt(flatten)]
         opts: wai_bindgen_gen_wasmtime::Opts,
@@ -67,6 +75,104 @@ enum Command {
         #[structopt(flatten)]
         common: Common,
     },
+    PythonWasm {
+        #[structopt(flatten)]
+        opts: wai_bindgen_gen_python_wasm::Opts,
+        #[structopt(flatten)]
+        common: Common,
+    },
+    Nim {
+        #[structopt(flatten)]
+        opts: wai_bindgen_gen_nim::Opts,
+        #[structopt(flatten)]
+        common: Common,
+    },
+    KotlinWasm {
+        #[structopt(flatten)]
+        opts: wai_bindgen_gen_kotlin_wasm::Opts,
+        #[structopt(flatten)]
+        common: Common,
+    },
+    D {
+        #[structopt(flatten)]
+        opts: wai_bindgen_gen_d::Opts,
+        #[structopt(flatten)]
+        common: Common,
+    },
+    #[structopt(name = "ocaml")]
+    OCaml {
+        #[structopt(flatten)]
+        opts: wai_bindgen_gen_ocaml::Opts,
+        #[structopt(flatten)]
+        common: Common,
+    },
+    Haskell {
+        #[structopt(flatten)]
+        opts: wai_bindgen_gen_haskell::Opts,
+        #[structopt(flatten)]
+        common: Common,
+    },
+    Conformance {
+        #[structopt(flatten)]
+        opts: wai_bindgen_gen_conformance::Opts,
+        #[structopt(flatten)]
+        common: Common,
+    },
+    RestGateway {
+        #[structopt(flatten)]
+        opts: wai_bindgen_gen_rest_gateway::Opts,
+        #[structopt(flatten)]
+        common: Common,
+    },
+    Rpc {
+        #[structopt(flatten)]
+        opts: wai_bindgen_gen_rpc::Opts,
+        #[structopt(flatten)]
+        common: Common,
+    },
+    JsonSchema {
+        #[structopt(flatten)]
+        opts: wai_bindgen_gen_json_schema::Opts,
+        #[structopt(flatten)]
+        common: Common,
+    },
+    Proto {
+        #[structopt(flatten)]
+        opts: wai_bindgen_gen_proto::Opts,
+        #[structopt(flatten)]
+        common: Common,
+    },
+    #[structopt(name = "webidl")]
+    WebIdl {
+        #[structopt(flatten)]
+        opts: wai_bindgen_gen_webidl::Opts,
+        #[structopt(flatten)]
+        common: Common,
+    },
+    /// Check `*.wai` files for likely problems, failing if any are found.
+    Lint {
+        /// The `*.wai` files to check.
+        #[structopt(parse(from_os_str))]
+        files: Vec<PathBuf>,
+
+        /// Don't check this rule. Can be specified multiple times. The rules
+        /// are `kebab-case`, `large-record`, `enum-unknown-case`,
+        /// `too-many-params` and `missing-docs`.
+        #[structopt(long = "allow")]
+        allow: Vec<String>,
+
+        /// The most fields a record can have.
+        #[structopt(long = "max-record-fields", default_value = "16")]
+        max_record_fields: usize,
+
+        /// The most parameters a function can take.
+        #[structopt(long = "max-params", default_value = "8")]
+        max_params: usize,
+
+        /// How to print lints and errors, either `human` or `json`.
+        #[structopt(long = "message-format", default_value = "human")]
+        message_format: MessageFormat,
+    },
 }
 
 #[derive(Debug, StructOpt)]
@@ -76,20 +182,195 @@ struct Common {
     out_dir: Option<PathBuf>,
 
     /// Generate import bindings for the given `*.wai` interface. Can be
-    /// specified multiple times.
+    /// specified multiple times, and as `NAME=PATH` to name the interface
+    /// `NAME` instead of after its file. Built-in interfaces, like
+    /// `wai-logging`, are given as `builtin:NAME`.
     #[structopt(long = "import", short)]
-    imports: Vec<PathBuf>,
+    imports: Vec<String>,
 
     /// Generate export bindings for the given `*.wai` interface. Can be
-    /// specified multiple times.
+    /// specified multiple times, and as `NAME=PATH` to name the interface
+    /// `NAME` instead of after its file, such as to export the same
+    /// interface under several names. Built-in interfaces are given as
+    /// `builtin:NAME`.
     #[structopt(long = "export", short)]
-    exports: Vec<PathBuf>,
+    exports: Vec<String>,
+
+    /// How to print generated files and errors, either `human` or `json`.
+    ///
+    /// With `json` each message is printed to stdout as a JSON object on its
+    /// own line.
+    #[structopt(long = "message-format", default_value = "human")]
+    message_format: MessageFormat,
+
+    /// Don't write any files, instead fail if the files on disk differ from
+    /// what would be generated.
+    #[structopt(long)]
+    check: bool,
+
+    /// Like `--check`, but also print a unified diff of each file which
+    /// differs.
+    #[structopt(long)]
+    diff: bool,
+
+    /// Generate a file per interface, named after the interface, for
+    /// generators which otherwise put all interfaces in one `bindings.*` file.
+    #[structopt(long = "separate-files")]
+    separate_files: bool,
+
+    /// Only generate bindings for interfaces or functions matching this glob
+    /// pattern. Functions are matched by `name` or `interface.name`. Can be
+    /// specified multiple times.
+    #[structopt(long = "only")]
+    only: Vec<String>,
+
+    /// Don't generate bindings for interfaces or functions matching this glob
+    /// pattern, taking precedence over `--only`. Can be specified multiple
+    /// times.
+    #[structopt(long = "skip")]
+    skip: Vec<String>,
+
+    /// Keep the items annotated with a `@cfg` naming this `KEY=VALUE`
+    /// setting, such as `host=wasmer` or `feature=gpu`. Can be specified
+    /// multiple times.
+    #[structopt(long = "cfg")]
+    cfg: Vec<String>,
+}
+
+impl Command {
+    fn message_format(&self) -> MessageFormat {
+        match self {
+            Command::Lint { message_format, .. } => *message_format,
+            Command::RustWasm { common, .. }
+            | Command::RustTypes { common, .. }
+            | Command::Wasmtime { common, .. }
+            | Command::WasmtimePy { common, .. }
+            | Command::Js { common, .. }
+            | Command::C { common, .. }
+            | Command::Markdown { common, .. }
+            | Command::SpiderMonkey { common, .. }
+            | Command::Wasmer { common, .. }
+            | Command::WasmerPy { common, .. }
+            | Command::PythonWasm { common, .. }
+            | Command::Nim { common, .. }
+            | Command::KotlinWasm { common, .. }
+            | Command::D { common, .. }
+            | Command::OCaml { common, .. }
+            | Command::Haskell { common, .. }
+            | Command::Conformance { common, .. }
+            | Command::RestGateway { common, .. }
+            | Command::Rpc { common, .. }
+            | Command::JsonSchema { common, .. }
+            | Command::Proto { common, .. }
+            | Command::WebIdl { common, .. } => common.message_format,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MessageFormat {
+    Human,
+    Json,
+}
+
+impl FromStr for MessageFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<MessageFormat> {
+        match s {
+            "human" => Ok(MessageFormat::Human),
+            "json" => Ok(MessageFormat::Json),
+            _ => bail!("unknown message format `{}`, expected `human` or `json`", s),
+        }
+    }
+}
+
+impl MessageFormat {
+    fn report_generated(self, path: &Path) {
+        match self {
+            MessageFormat::Human => println!("Generating {:?}", path),
+            MessageFormat::Json => println!(
+                "{}",
+                json!({
+                    "reason": "generated-file",
+                    "path": path,
+                })
+            ),
+        }
+    }
+
+    fn report_stale(self, path: &Path, diff: Option<&str>) {
+        match self {
+            MessageFormat::Human => match diff {
+                Some(diff) => print!("{}", diff),
+                None => println!("{:?} is out of date", path),
+            },
+            MessageFormat::Json => println!(
+                "{}",
+                json!({
+                    "reason": "stale-file",
+                    "path": path,
+                    "diff": diff,
+                })
+            ),
+        }
+    }
+
+    fn report_lint(self, lint: &wai_parser::Diagnostic) {
+        match self {
+            MessageFormat::Human => println!("warning[{}]: {}\n", lint.code, lint),
+            MessageFormat::Json => println!("{}", diagnostic_json("lint", lint)),
+        }
+    }
+
+    fn report_error(self, err: &anyhow::Error) {
+        match self {
+            MessageFormat::Human => eprintln!("Error: {:?}", err),
+            MessageFormat::Json => {
+                let msg = match err.downcast_ref::<wai_parser::Diagnostic>() {
+                    Some(diag) => diagnostic_json("diagnostic", diag),
+                    None => json!({
+                        "reason": "diagnostic",
+                        "file": null,
+                        "span": null,
+                        "code": null,
+                        "message": format!("{:#}", err),
+                    }),
+                };
+                println!("{}", msg);
+            }
+        }
+    }
+}
+
+fn diagnostic_json(reason: &str, diag: &wai_parser::Diagnostic) -> serde_json::Value {
+    json!({
+        "reason": reason,
+        "file": diag.file,
+        "span": {
+            "start": diag.span.start,
+            "end": diag.span.end,
+            "line": diag.line,
+            "column": diag.column,
+        },
+        "code": diag.code,
+        "message": diag.message,
+    })
 }
 
-fn main() -> Result<()> {
+fn main() {
     let opt = Opt::from_args();
+    let message_format = opt.command.message_format();
+    if let Err(e) = run(opt) {
+        message_format.report_error(&e);
+        std::process::exit(1);
+    }
+}
+
+fn run(opt: Opt) -> Result<()> {
     let (mut generator, common): (Box<dyn Generator>, _) = match opt.command {
         Command::RustWasm { opts, common } => (Box::new(opts.build()), common),
+        Command::RustTypes { opts, common } => (Box::new(opts.build()), common),
         Command::Wasmtime { opts, common } => (Box::new(opts.build()), common),
         Command::WasmtimePy { opts, common } => (Box::new(opts.build()), common),
         Command::Js { opts, common } => (Box::new(opts.build()), common),
@@ -100,30 +381,96 @@ fn main() -> Result<()> {
                 .with_context(|| format!("failed to read {}", opts.js.display()))?;
             (Box::new(opts.build(js_source)), common)
         }
-        Command::Wasmer { opts, common } => (Box::new(opts.build()), common),
+        Command::Wasmer { opts, common } => {
+            opts.validate().map_err(anyhow::Error::msg)?;
+            (Box::new(opts.build()), common)
+        }
         Command::WasmerPy { opts, common } => (Box::new(opts.build()), common),
+        Command::PythonWasm { opts, common } => (Box::new(opts.build()), common),
+        Command::Nim { opts, common } => (Box::new(opts.build()), common),
+        Command::KotlinWasm { opts, common } => (Box::new(opts.build()), common),
+        Command::D { opts, common } => (Box::new(opts.build()), common),
+        Command::OCaml { opts, common } => (Box::new(opts.build()), common),
+        Command::Haskell { opts, common } => (Box::new(opts.build()), common),
+        Command::Conformance { opts, common } => (Box::new(opts.build()), common),
+        Command::RestGateway { opts, common } => (Box::new(opts.build()), common),
+        Command::Rpc { opts, common } => (Box::new(opts.build()), common),
+        Command::JsonSchema { opts, common } => (Box::new(opts.build()), common),
+        Command::Proto { opts, common } => (Box::new(opts.build()), common),
+        Command::WebIdl { opts, common } => (Box::new(opts.build()), common),
+        Command::Lint {
+            files,
+            allow,
+            max_record_fields,
+            max_params,
+            message_format,
+        } => {
+            let config = wai_parser::lint::Config {
+                allow,
+                max_record_fields,
+                max_params,
+            };
+            return lint(&files, &config, message_format);
+        }
     };
 
-    let imports = common
-        .imports
-        .iter()
-        .map(|wai| Interface::parse_file(wai))
-        .collect::<Result<Vec<_>>>()?;
-    let exports = common
-        .exports
-        .iter()
-        .map(|wai| Interface::parse_file(wai))
-        .collect::<Result<Vec<_>>>()?;
+    let mut cfg = wai_parser::Cfg::new();
+    for setting in common.cfg.iter() {
+        match setting.split_once('=') {
+            Some((key, value)) => cfg.set(key, value),
+            None => bail!("`--cfg {}` should be of the form `KEY=VALUE`", setting),
+        };
+    }
+    let parse = |args: &[String]| -> Result<Vec<Interface>> {
+        let mut interfaces = Vec::new();
+        for arg in args {
+            let parsed = parse_interface_arg_with_cfg(Path::new(""), arg, &cfg)?;
+            interfaces.extend(parsed.into_iter().map(|(_, iface)| iface));
+        }
+        Ok(interfaces)
+    };
+    let mut imports = parse(&common.imports)?;
+    let mut exports = parse(&common.exports)?;
+
+    let filter = Filter {
+        only: common.only.clone(),
+        skip: common.skip.clone(),
+    };
+    filter.apply(&mut imports);
+    filter.apply(&mut exports);
 
     let mut files = Files::default();
+    files.split_per_interface(common.separate_files);
     generator.generate_all(&imports, &exports, &mut files);
 
+    let mut stale = 0;
     for (name, contents) in files.iter() {
         let dst = match &common.out_dir {
             Some(path) => path.join(name),
             None => name.into(),
         };
-        println!("Generating {:?}", dst);
+        if common.check || common.diff {
+            let prev = match std::fs::read(&dst) {
+                Ok(prev) => prev,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+                Err(e) => return Err(e).with_context(|| format!("failed to read {:?}", dst)),
+            };
+            if prev != contents {
+                stale += 1;
+                let diff = if common.diff {
+                    Some(unified_diff(
+                        &dst,
+                        &String::from_utf8_lossy(&prev),
+                        &String::from_utf8_lossy(contents),
+                    ))
+                } else {
+                    None
+                };
+                common.message_format.report_stale(&dst, diff.as_deref());
+            }
+            continue;
+        }
+        common.message_format.report_generated(&dst);
         if let Some(parent) = dst.parent() {
             std::fs::create_dir_all(parent)
                 .with_context(|| format!("failed to create {:?}", parent))?;
@@ -131,5 +478,92 @@ fn main() -> Result<()> {
         std::fs::write(&dst, contents).with_context(|| format!("failed to write {:?}", dst))?;
     }
 
+    if stale > 0 {
+        bail!("{} generated file(s) are out of date", stale);
+    }
+
     Ok(())
 }
+
+fn lint(
+    files: &[PathBuf],
+    config: &wai_parser::lint::Config,
+    message_format: MessageFormat,
+) -> Result<()> {
+    let mut count = 0;
+    for file in files {
+        let contents =
+            std::fs::read_to_string(file).with_context(|| format!("failed to read {:?}", file))?;
+        let lints = wai_parser::lint::lint(&file.display().to_string(), &contents, config)?;
+        for lint in lints.iter() {
+            message_format.report_lint(lint);
+        }
+        count += lints.len();
+    }
+    if count > 0 {
+        bail!("{} lint(s) found", count);
+    }
+    Ok(())
+}
+
+/// Renders a unified diff from `old` to `new`, with three lines of context
+/// around each change.
+fn unified_diff(path: &Path, old: &str, new: &str) -> String {
+    const CONTEXT: usize = 3;
+
+    let lines = diff::lines(old, new);
+
+    // The number of lines of `old` and `new` before each entry in `lines`,
+    // used for the line numbers in hunk headers.
+    let mut before = vec![(0, 0)];
+    for line in lines.iter() {
+        let (o, n) = *before.last().unwrap();
+        before.push(match line {
+            diff::Result::Left(_) => (o + 1, n),
+            diff::Result::Both(..) => (o + 1, n + 1),
+            diff::Result::Right(_) => (o, n + 1),
+        });
+    }
+    let changes = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, diff::Result::Both(..)))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    let mut ret = format!("--- a/{0}\n+++ b/{0}\n", path.display());
+    let mut i = 0;
+    while i < changes.len() {
+        // Changes close enough together that their context would overlap are
+        // put in the same hunk.
+        let start = changes[i].saturating_sub(CONTEXT);
+        let mut last = changes[i];
+        i += 1;
+        while i < changes.len() && changes[i] - last <= 2 * CONTEXT {
+            last = changes[i];
+            i += 1;
+        }
+        let end = (last + 1 + CONTEXT).min(lines.len());
+
+        let range = |start: usize, end: usize| match end - start {
+            0 => format!("{},0", start),
+            len => format!("{},{}", start + 1, len),
+        };
+        ret.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(before[start].0, before[end].0),
+            range(before[start].1, before[end].1),
+        ));
+        for line in lines[start..end].iter() {
+            let (prefix, line) = match line {
+                diff::Result::Left(l) => ('-', l),
+                diff::Result::Both(l, _) => (' ', l),
+                diff::Result::Right(l) => ('+', l),
+            };
+            ret.push(prefix);
+            ret.push_str(line);
+            ret.push('\n');
+        }
+    }
+    ret
+}
@@ -5,7 +5,10 @@ use std::mem;
 use wai_bindgen_gen_core::wai_parser::abi::{
     AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmType,
 };
-use wai_bindgen_gen_core::{uwrite, uwriteln, wai_parser::*, Direction, Files, Generator, Ns};
+use wai_bindgen_gen_core::{
+    abi_descriptor, uwrite, uwriteln, wai_parser::*, Direction, Files, Generator, Ns,
+    ABI_DESCRIPTOR_EXPORT,
+};
 
 #[derive(Default)]
 pub struct C {
@@ -34,6 +37,10 @@ pub struct C {
     types: HashMap<TypeId, wai_bindgen_gen_core::Source>,
 
     needs_string: bool,
+
+    // The fields of the dispatch table of the exports of the current
+    // interface, when generating one.
+    dispatch_fields: Vec<String>,
 }
 
 struct Func {
@@ -43,7 +50,27 @@ struct Func {
 #[derive(Default, Debug, Clone)]
 #[cfg_attr(feature = "structopt", derive(structopt::StructOpt))]
 pub struct Opts {
-    // ...
+    /// Whether or not exported functions and resource destructors call
+    /// implementations registered in a table of function pointers with
+    /// `<interface>_set_exports`, rather than functions of fixed names the
+    /// guest defines, so that one compiled object can hold several
+    /// implementations. Applicable for export bindings.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub dispatch_table: bool,
+
+    /// Whether or not to generate code which doesn't use libc, for guests
+    /// built with `-nostdlib`. Only the freestanding `stddef.h`, `stdint.h`
+    /// and `stdbool.h` headers are included, and the guest defines
+    /// `canonical_abi_realloc` and `canonical_abi_free` itself, which the
+    /// bindings allocate and free memory with.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub freestanding: bool,
+
+    /// Whether or not to export a `wai_abi_descriptor:<name>` function for
+    /// each exported interface, describing the core signatures of its
+    /// functions along with its hash. Applicable for export bindings.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub export_abi_descriptor: bool,
 }
 
 impl Opts {
@@ -408,6 +435,29 @@ impl C {
     }
 
     fn print_intrinsics(&mut self) {
+        // Freestanding guests can't allocate with libc, so they define these
+        // intrinsics themselves and the header only declares them. Their
+        // declarations carry the export names onto the guest's definitions.
+        if self.opts.freestanding {
+            self.src.h("
+                __attribute__((export_name(\"canonical_abi_realloc\")))
+                void *canonical_abi_realloc(
+                    void *ptr,
+                    size_t orig_size,
+                    size_t align,
+                    size_t new_size
+                );
+
+                __attribute__((export_name(\"canonical_abi_free\")))
+                void canonical_abi_free(
+                    void *ptr,
+                    size_t size,
+                    size_t align
+                );
+            ");
+            return;
+        }
+
         // Note that these intrinsics are declared as `weak` so they can be
         // overridden from some other symbol.
         self.src.c("
@@ -777,15 +827,37 @@ impl Generator for C {
         self.src.h(&name.to_snake_case());
         self.src.h("_t;\n");
 
+        let prefix = format!(
+            "{}_{}",
+            iface.name.to_shouty_snake_case(),
+            name.to_shouty_snake_case()
+        );
+        let ty = format!("{}_{}_t", iface.name.to_snake_case(), name.to_snake_case());
+        let mut flag_names = BTreeSet::new();
         for (i, flag) in flags.flags.iter().enumerate() {
-            uwriteln!(
-                self.src.h,
-                "#define {}_{}_{} (1 << {})",
-                iface.name.to_shouty_snake_case(),
-                name.to_shouty_snake_case(),
-                flag.name.to_shouty_snake_case(),
-                i,
-            );
+            let flag = flag.name.to_shouty_snake_case();
+            uwriteln!(self.src.h, "#define {prefix}_{flag} (({ty}) 1 << {i})");
+            flag_names.insert(flag);
+        }
+
+        // Masks and helpers to manipulate the flags without bit math, each
+        // skipped if a flag already took its name.
+        let helpers = [
+            (
+                "ALL",
+                format!(" (({ty}) {:#x})", (1u128 << flags.flags.len()) - 1),
+            ),
+            (
+                "HAS",
+                "(flags, flag) (((flags) & (flag)) == (flag))".to_string(),
+            ),
+            ("SET", "(flags, flag) ((flags) |= (flag))".to_string()),
+            ("CLEAR", "(flags, flag) ((flags) &= ~(flag))".to_string()),
+        ];
+        for (helper, def) in helpers {
+            if !flag_names.contains(helper) {
+                uwriteln!(self.src.h, "#define {prefix}_{helper}{def}");
+            }
         }
 
         self.types.insert(id, mem::replace(&mut self.src.h, prev));
@@ -1059,7 +1131,21 @@ impl Generator for C {
 
         // Print the actual header for this function into the header file, and
         // it's what we'll be calling.
-        let c_sig = self.print_sig(iface, func);
+        let start = self.src.h.len();
+        let mut c_sig = self.print_sig(iface, func);
+
+        // With a dispatch table the header instead gets a field of the table,
+        // which is what's called.
+        if self.opts.dispatch_table {
+            self.src.h.as_mut_string().truncate(start);
+            let field = field_name(&func.name.to_snake_case());
+            self.dispatch_fields.push(c_sig.sig.replacen(
+                &format!(" {}(", c_sig.name),
+                &format!(" (*{})(", field),
+                1,
+            ));
+            c_sig.name = format!("{}_exports->{}", iface.name.to_snake_case(), field);
+        }
 
         // Generate, in the C source file, the raw wasm signature that has the
         // canonical ABI.
@@ -1130,17 +1216,56 @@ impl Generator for C {
             ",
             iface.name.to_shouty_snake_case(),
         );
-        uwrite!(
-            self.src.c,
-            "\
-                #include <stdlib.h>
-                #include <{}.h>
-            ",
-            iface.name.to_kebab_case(),
-        );
+        if self.opts.freestanding {
+            self.src.h("#include <stddef.h>\n");
+        } else {
+            self.src.c("#include <stdlib.h>\n");
+        }
+        uwriteln!(self.src.c, "#include <{}.h>", iface.name.to_kebab_case());
 
         self.print_intrinsics();
 
+        let dispatch_table = self.opts.dispatch_table && !self.in_import;
+        if dispatch_table {
+            uwrite!(
+                self.src.c,
+                "
+                    static const {0}_exports_t *{0}_exports;
+
+                    void {0}_set_exports(const {0}_exports_t *exports) {{
+                        {0}_exports = exports;
+                    }}
+                ",
+                iface.name.to_snake_case(),
+            );
+        }
+
+        if self.opts.export_abi_descriptor && !self.in_import {
+            let descriptor = abi_descriptor(iface, |func| func.name.clone());
+            let bytes = descriptor
+                .iter()
+                .map(|b| b.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            uwrite!(
+                self.src.c,
+                "
+                    static const uint8_t __wai_abi_descriptor[] = {{ {bytes} }};
+                    static const struct {{
+                        const uint8_t *ptr;
+                        size_t len;
+                    }} __wai_abi_descriptor_ret = {{ __wai_abi_descriptor, sizeof(__wai_abi_descriptor) }};
+
+                    __attribute__((export_name(\"{ABI_DESCRIPTOR_EXPORT}:{name}\")))
+                    int32_t __wasm_export_{ns}_abi_descriptor(void) {{
+                        return (int32_t) &__wai_abi_descriptor_ret;
+                    }}
+                ",
+                name = iface.name,
+                ns = iface.name.to_snake_case(),
+            );
+        }
+
         for (_, resource) in iface.resources.iter() {
             let ns = iface.name.to_snake_case();
             let name = resource.name.to_snake_case();
@@ -1188,13 +1313,26 @@ impl Generator for C {
                     "\
                         {ns}_{name}_t {ns}_{name}_new(void *data);
                         void* {ns}_{name}_get({ns}_{name}_t *ptr);
-
-                        __attribute__((weak))
-                        void {ns}_{name}_dtor(void *data);
                     ",
                     ns = ns,
                     name = name,
                 );
+                // The destructor is optional, either as a weak symbol or as a
+                // field of the dispatch table which may be `NULL`.
+                let dtor = if dispatch_table {
+                    self.dispatch_fields
+                        .push(format!("void (*{}_dtor)(void *data)", name));
+                    format!("{ns}_exports->{name}_dtor")
+                } else {
+                    uwrite!(
+                        self.src.h,
+                        "
+                            __attribute__((weak))
+                            void {ns}_{name}_dtor(void *data);
+                        ",
+                    );
+                    format!("{ns}_{name}_dtor")
+                };
                 uwrite!(
                     self.src.c,
                     "
@@ -1214,13 +1352,14 @@ impl Generator for C {
 
                         __attribute__((export_name(\"canonical_abi_drop_{name_orig}\")))
                         void __resource_{name}_dtor(uint32_t val) {{
-                            if ({ns}_{name}_dtor)
-                                {ns}_{name}_dtor((void*) val);
+                            if ({dtor})
+                                {dtor}((void*) val);
                         }}
                     ",
                     ns = ns,
                     name = name,
                     name_orig = resource.name,
+                    dtor = dtor,
                 );
             }
         }
@@ -1270,19 +1409,28 @@ impl Generator for C {
                 ",
                 iface.name.to_snake_case(),
             );
-            self.src.c("#include <string.h>\n");
+            // Without libc the length and copy are computed by hand.
+            let (strlen, memcpy) = if self.opts.freestanding {
+                (
+                    "ret->len = 0;\nwhile (s[ret->len]) ret->len++;",
+                    "for (size_t i = 0; i < ret->len; i++) ret->ptr[i] = s[i];",
+                )
+            } else {
+                self.src.c("#include <string.h>\n");
+                ("ret->len = strlen(s);", "memcpy(ret->ptr, s, ret->len);")
+            };
             uwrite!(
                 self.src.c,
                 "
                     void {0}_string_set({0}_string_t *ret, const char *s) {{
                         ret->ptr = (char*) s;
-                        ret->len = strlen(s);
+                        {strlen}
                     }}
 
                     void {0}_string_dup({0}_string_t *ret, const char *s) {{
-                        ret->len = strlen(s);
+                        {strlen}
                         ret->ptr = canonical_abi_realloc(NULL, 0, 1, ret->len);
-                        memcpy(ret->ptr, s, ret->len);
+                        {memcpy}
                     }}
 
                     void {0}_string_free({0}_string_t *ret) {{
@@ -1320,6 +1468,25 @@ impl Generator for C {
             );
         }
 
+        if dispatch_table {
+            self.src.h("\ntypedef struct {\n");
+            for field in mem::take(&mut self.dispatch_fields) {
+                uwriteln!(self.src.h, "{};", field);
+            }
+            uwrite!(
+                self.src.h,
+                "\
+                    }} {0}_exports_t;
+
+                    // Sets the implementations the exports of this interface
+                    // call, which must be done before any of them is called.
+                    // The table must outlive every call.
+                    void {0}_set_exports(const {0}_exports_t *exports);
+                ",
+                iface.name.to_snake_case(),
+            );
+        }
+
         for (_module, funcs) in mem::take(&mut self.funcs) {
             for func in funcs {
                 self.src.h(&func.src.h);
@@ -2207,8 +2374,19 @@ impl Bindgen for FunctionBindgen<'_> {
                 self.load_ext("int16_t", *offset, operands, results)
             }
 
-            Instruction::Free { .. } => {
-                uwriteln!(self.src, "free((void*) ({}));", operands[0]);
+            Instruction::Free { free, size, align } => {
+                if self.gen.opts.freestanding {
+                    uwriteln!(
+                        self.src,
+                        "{}((void*) ({}), {}, {});",
+                        free,
+                        operands[0],
+                        size,
+                        align
+                    );
+                } else {
+                    uwriteln!(self.src, "free((void*) ({}));", operands[0]);
+                }
             }
 
             i => unimplemented!("{:?}", i),
@@ -2231,6 +2409,21 @@ impl Source {
     }
 }
 
+/// Returns `name` as the name of a struct field, which can't be a C keyword.
+fn field_name(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "auto", "bool", "break", "case", "char", "const", "continue", "default", "do", "double",
+        "else", "enum", "extern", "false", "float", "for", "goto", "if", "inline", "int", "long",
+        "register", "restrict", "return", "short", "signed", "sizeof", "static", "struct",
+        "switch", "true", "typedef", "union", "unsigned", "void", "volatile", "while",
+    ];
+    if KEYWORDS.contains(&name) {
+        format!("{}_", name)
+    } else {
+        name.to_string()
+    }
+}
+
 fn wasm_type(ty: WasmType) -> &'static str {
     match ty {
         WasmType::I32 => "int32_t",
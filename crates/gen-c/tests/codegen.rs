@@ -26,6 +26,43 @@ mod exports {
     );
 }
 
+mod exports_dispatch_table {
+    test_helpers::codegen_c_export_dispatch_table!(
+        "*.wai"
+
+        // TODO: implement async support
+        "!async-functions.wai"
+
+        // TODO: these use push/pull buffer in exports which isn't implemented
+        // yet
+        "!wasi-next.wai"
+        "!host.wai"
+    );
+}
+
+mod imports_freestanding {
+    test_helpers::codegen_c_import_freestanding!(
+        "*.wai"
+
+        // TODO: implement async support
+        "!async-functions.wai"
+    );
+}
+
+mod exports_freestanding {
+    test_helpers::codegen_c_export_freestanding!(
+        "*.wai"
+
+        // TODO: implement async support
+        "!async-functions.wai"
+
+        // TODO: these use push/pull buffer in exports which isn't implemented
+        // yet
+        "!wasi-next.wai"
+        "!host.wai"
+    );
+}
+
 fn verify(dir: &str, name: &str) {
     let dir = Path::new(dir);
     let path = PathBuf::from(env::var_os("WASI_SDK_PATH").unwrap());
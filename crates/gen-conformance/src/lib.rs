@@ -0,0 +1,445 @@
+use heck::*;
+use std::mem;
+use wai_bindgen_gen_core::{wai_parser::*, Direction, Files, Generator, Source, TypeInfo, Types};
+use wai_bindgen_gen_rust::{to_rust_ident, RustGenerator, TypeMode};
+
+/// Number of calls made to each function, each one with a different set of
+/// boundary values.
+const ROUNDS: usize = 3;
+
+/// Generates a guest (`{name}/wasm.rs`, for `wai-bindgen-rust`) and host
+/// (`{name}/host-wasmer.rs`, for `wai-bindgen-wasmer`) test pair for each
+/// interface, in the layout of `tests/runtime/*`.
+///
+/// Every exported function is called `ROUNDS` times with boundary values
+/// (empty lists, min/max integers, NaN, huge strings, ...). The guest checks
+/// that it received what the host sent and returns a boundary value of its
+/// own which the host checks in turn.
+#[derive(Default)]
+pub struct Conformance {
+    opts: Opts,
+    types: Types,
+    guest: Source,
+    host: Source,
+}
+
+#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "structopt", derive(structopt::StructOpt))]
+pub struct Opts {
+    /// Path of the `*.wai` file as referenced from the generated `export!`
+    /// and `import!` macro invocations.
+    #[cfg_attr(feature = "structopt", structopt(long = "wai-path"))]
+    pub wai_path: Option<String>,
+}
+
+impl Opts {
+    pub fn build(self) -> Conformance {
+        let mut r = Conformance::new();
+        r.opts = self;
+        r
+    }
+}
+
+/// Which flavor of Rust value is being constructed.
+#[derive(Copy, Clone, PartialEq)]
+enum Side {
+    /// Owned values in `wai-bindgen-rust` export bindings.
+    Guest,
+    /// Borrowed arguments passed to `wai-bindgen-wasmer` import bindings.
+    HostParam,
+    /// Owned results returned from `wai-bindgen-wasmer` import bindings.
+    HostResult,
+}
+
+impl Conformance {
+    pub fn new() -> Conformance {
+        Conformance::default()
+    }
+
+    /// Returns whether values of `ty` can be synthesized at all.
+    fn supported(iface: &Interface, ty: &Type) -> bool {
+        match ty {
+            Type::Handle(_) => false,
+            Type::Id(id) => match &iface.types[*id].kind {
+                TypeDefKind::Record(r) => r.fields.iter().all(|f| Self::supported(iface, &f.ty)),
+                TypeDefKind::Tuple(t) => t.types.iter().all(|t| Self::supported(iface, t)),
+                TypeDefKind::Variant(v) => v.cases.iter().all(|c| Self::supported(iface, &c.ty)),
+                TypeDefKind::Union(u) => u.cases.iter().all(|c| Self::supported(iface, &c.ty)),
+                TypeDefKind::Flags(_) | TypeDefKind::Enum(_) => true,
+                TypeDefKind::Option(t) | TypeDefKind::List(t) | TypeDefKind::Type(t) => {
+                    Self::supported(iface, t)
+                }
+                TypeDefKind::Expected(e) => {
+                    Self::supported(iface, &e.ok) && Self::supported(iface, &e.err)
+                }
+                TypeDefKind::Future(_) | TypeDefKind::Stream(_) => false,
+            },
+            _ => true,
+        }
+    }
+
+    /// Returns a Rust expression for the `round`-th boundary value of `ty`.
+    fn value(&self, iface: &Interface, ty: &Type, round: usize, side: Side) -> String {
+        let owned = side != Side::HostParam;
+        match ty {
+            Type::Unit => "()".to_string(),
+            Type::Bool => ["false", "true", "false"][round].to_string(),
+            Type::U8 | Type::U16 | Type::U32 | Type::U64 => [
+                "0".to_string(),
+                format!("{}::MAX", int(ty)),
+                "1".to_string(),
+            ][round]
+                .clone(),
+            Type::S8 | Type::S16 | Type::S32 | Type::S64 => [
+                format!("{}::MIN", int(ty)),
+                format!("{}::MAX", int(ty)),
+                "0".to_string(),
+            ][round]
+                .clone(),
+            Type::Float32 => ["-0.0", "f32::NAN", "f32::INFINITY"][round].to_string(),
+            Type::Float64 => ["-0.0", "f64::NAN", "f64::NEG_INFINITY"][round].to_string(),
+            Type::Char => ["'\\0'", "char::MAX", "'\\u{1f600}'"][round].to_string(),
+            Type::String => {
+                let s = ["\"\"", "\"x\".repeat(1 << 16)", "\"\\u{1f600}\""][round];
+                match (owned, round) {
+                    (true, 1) => s.to_string(),
+                    (true, _) => format!("{s}.to_string()"),
+                    (false, 1) => format!("{s}.as_str()"),
+                    (false, _) => s.to_string(),
+                }
+            }
+            Type::Handle(_) => unreachable!(),
+            Type::Id(id) => self.value_id(iface, *id, round, side),
+        }
+    }
+
+    fn value_id(&self, iface: &Interface, id: TypeId, round: usize, side: Side) -> String {
+        let module = iface.name.to_snake_case();
+        let name = || format!("{module}::{}", self.type_name(iface, id, side));
+        match &iface.types[id].kind {
+            TypeDefKind::Type(t) => self.value(iface, t, round, side),
+            TypeDefKind::List(t) => {
+                let elems = match round {
+                    0 => Vec::new(),
+                    1 => (0..ROUNDS).map(|i| self.value(iface, t, i, side)).collect(),
+                    _ => vec![self.value(iface, t, round, side)],
+                };
+                if side == Side::HostParam {
+                    format!("&[{}]", elems.join(", "))
+                } else {
+                    format!("vec![{}]", elems.join(", "))
+                }
+            }
+            TypeDefKind::Option(t) => match round {
+                0 => "None".to_string(),
+                _ => format!("Some({})", self.value(iface, t, round, side)),
+            },
+            TypeDefKind::Expected(e) => match round {
+                1 => format!("Err({})", self.value(iface, &e.err, round, side)),
+                _ => format!("Ok({})", self.value(iface, &e.ok, round, side)),
+            },
+            TypeDefKind::Tuple(t) => {
+                let elems = t
+                    .types
+                    .iter()
+                    .map(|t| format!("{}, ", self.value(iface, t, round, side)))
+                    .collect::<String>();
+                format!("({elems})")
+            }
+            TypeDefKind::Record(r) => {
+                let fields = r
+                    .fields
+                    .iter()
+                    .map(|f| {
+                        format!(
+                            "{}: {}, ",
+                            to_rust_ident(&f.name),
+                            self.value(iface, &f.ty, round, side)
+                        )
+                    })
+                    .collect::<String>();
+                format!("{} {{ {fields}}}", name())
+            }
+            TypeDefKind::Flags(f) => match (round, f.flags.first()) {
+                (1, _) => format!("{}::all()", name()),
+                (2, Some(flag)) => format!("{}::{}", name(), flag.name.to_shouty_snake_case()),
+                _ => format!("{}::empty()", name()),
+            },
+            TypeDefKind::Enum(e) => {
+                let case = &e.cases[round % e.cases.len()];
+                format!("{}::{}", name(), case.name.to_camel_case())
+            }
+            TypeDefKind::Variant(v) => {
+                let case = &v.cases[round % v.cases.len()];
+                let payload = match case.ty {
+                    Type::Unit => String::new(),
+                    ty => format!("({})", self.value(iface, &ty, round, side)),
+                };
+                format!("{}::{}{payload}", name(), case.name.to_camel_case())
+            }
+            TypeDefKind::Union(u) => {
+                let namer = Namer::new(&self.types, side);
+                let names = namer.union_case_names(iface, u);
+                let idx = round % u.cases.len();
+                let payload = self.value(iface, &u.cases[idx].ty, round, side);
+                format!("{}::{}({payload})", name(), names[idx])
+            }
+            TypeDefKind::Future(_) | TypeDefKind::Stream(_) => unreachable!(),
+        }
+    }
+
+    fn type_name(&self, iface: &Interface, id: TypeId, side: Side) -> String {
+        let namer = Namer::new(&self.types, side);
+        match side {
+            Side::Guest | Side::HostResult => namer.result_name(iface, id),
+            Side::HostParam => namer.param_name(iface, id),
+        }
+    }
+
+    fn wai_path(&self, iface: &Interface) -> String {
+        match &self.opts.wai_path {
+            Some(path) => path.clone(),
+            None => format!("{}.wai", iface.name),
+        }
+    }
+}
+
+fn int(ty: &Type) -> &'static str {
+    match ty {
+        Type::U8 => "u8",
+        Type::U16 => "u16",
+        Type::U32 => "u32",
+        Type::U64 => "u64",
+        Type::S8 => "i8",
+        Type::S16 => "i16",
+        Type::S32 => "i32",
+        Type::S64 => "i64",
+        _ => unreachable!(),
+    }
+}
+
+impl Generator for Conformance {
+    fn preprocess_one(&mut self, iface: &Interface, dir: Direction) {
+        assert!(
+            dir == Direction::Export,
+            "conformance suites can only be generated for exported interfaces"
+        );
+        assert!(
+            iface.resources.len() == 0,
+            "conformance suites don't support resources yet"
+        );
+        self.types = Types::default();
+        self.types.analyze(iface);
+
+        let snake = iface.name.to_snake_case();
+        let camel = iface.name.to_camel_case();
+        let path = self.wai_path(iface);
+        self.guest.push_str(&format!(
+            "
+                wai_bindgen_rust::export!(\"{path}\");
+
+                use {snake}::*;
+
+                use std::sync::atomic::{{AtomicUsize, Ordering::SeqCst}};
+
+                fn check<T: std::fmt::Debug>(actual: T, expected: T) {{
+                    assert_eq!(format!(\"{{:?}}\", actual), format!(\"{{:?}}\", expected));
+                }}
+
+                struct {camel};
+
+                impl {snake}::{camel} for {camel} {{
+            "
+        ));
+        self.host.push_str(&format!(
+            "
+                use anyhow::Result;
+
+                wai_bindgen_wasmer::import!(\"{path}\");
+
+                fn check<T: std::fmt::Debug>(actual: T, expected: T) {{
+                    assert_eq!(format!(\"{{:?}}\", actual), format!(\"{{:?}}\", expected));
+                }}
+
+                fn run(wasm: &str) -> Result<()> {{
+                    use wasmer::AsStoreMut as _;
+
+                    let mut store = wasmer::Store::default();
+                    let exports = crate::instantiate(
+                        wasm,
+                        &mut store,
+                        |_store, _imports| {{
+                            |_instance: &wasmer::Instance, _store: &dyn wasmer::AsStoreRef| {{
+                                Ok::<(), anyhow::Error>(())
+                            }}
+                        }},
+                        |store, module, imports| {{
+                            {snake}::{camel}::instantiate(&mut store.as_store_mut(), module, imports)
+                        }},
+                    )?;
+            "
+        ));
+    }
+
+    fn type_record(&mut self, _: &Interface, _: TypeId, _: &str, _: &Record, _: &Docs) {}
+    fn type_flags(&mut self, _: &Interface, _: TypeId, _: &str, _: &Flags, _: &Docs) {}
+    fn type_tuple(&mut self, _: &Interface, _: TypeId, _: &str, _: &Tuple, _: &Docs) {}
+    fn type_variant(&mut self, _: &Interface, _: TypeId, _: &str, _: &Variant, _: &Docs) {}
+    fn type_option(&mut self, _: &Interface, _: TypeId, _: &str, _: &Type, _: &Docs) {}
+    fn type_expected(&mut self, _: &Interface, _: TypeId, _: &str, _: &Expected, _: &Docs) {}
+    fn type_union(&mut self, _: &Interface, _: TypeId, _: &str, _: &Union, _: &Docs) {}
+    fn type_enum(&mut self, _: &Interface, _: TypeId, _: &str, _: &Enum, _: &Docs) {}
+    fn type_resource(&mut self, _: &Interface, _: ResourceId) {}
+    fn type_alias(&mut self, _: &Interface, _: TypeId, _: &str, _: &Type, _: &Docs) {}
+    fn type_list(&mut self, _: &Interface, _: TypeId, _: &str, _: &Type, _: &Docs) {}
+    fn type_builtin(&mut self, _: &Interface, _: TypeId, _: &str, _: &Type, _: &Docs) {}
+
+    fn import(&mut self, _iface: &Interface, _func: &Function) {
+        unreachable!()
+    }
+
+    fn export(&mut self, iface: &Interface, func: &Function) {
+        assert!(!func.is_async, "async not supported yet");
+        let name = to_rust_ident(&func.name);
+        let counter = format!("ROUND_{}", func.name.to_shouty_snake_case());
+        let supported = func
+            .params
+            .iter()
+            .map(|(_, ty)| ty)
+            .chain(Some(&func.result))
+            .all(|ty| Self::supported(iface, ty));
+
+        // Guest side: an implementation checking its arguments and returning
+        // the boundary value for the current round.
+        let mut guest = Namer::new(&self.types, Side::Guest);
+        guest.push_str("fn ");
+        guest.push_str(&name);
+        guest.push_str("(");
+        for (param, ty) in func.params.iter() {
+            guest.push_str(&to_rust_ident(param));
+            guest.push_str(": ");
+            guest.print_ty(iface, ty, TypeMode::Owned);
+            guest.push_str(", ");
+        }
+        guest.push_str(") -> ");
+        guest.print_ty(iface, &func.result, TypeMode::Owned);
+        guest.push_str(" {\n");
+        let sig = String::from(guest.src);
+        self.guest.push_str(&sig);
+        if !supported {
+            self.guest.push_str("unimplemented!()\n}\n");
+            return;
+        }
+        self.guest.push_str(&format!(
+            "
+                static {counter}: AtomicUsize = AtomicUsize::new(0);
+                match {counter}.fetch_add(1, SeqCst) % {ROUNDS} {{
+            "
+        ));
+        for round in 0..ROUNDS {
+            let pattern = if round == ROUNDS - 1 {
+                "_".to_string()
+            } else {
+                round.to_string()
+            };
+            self.guest.push_str(&format!("{pattern} => {{\n"));
+            for (param, ty) in func.params.iter() {
+                let expected = self.value(iface, ty, round, Side::Guest);
+                self.guest
+                    .push_str(&format!("check({}, {expected});\n", to_rust_ident(param)));
+            }
+            let result = self.value(iface, &func.result, round, Side::Guest);
+            self.guest.push_str(&result);
+            self.guest.push_str("\n}\n");
+        }
+        self.guest.push_str("}\n}\n");
+
+        // Host side: call the export once per round and check the result.
+        for round in 0..ROUNDS {
+            let args = func
+                .params
+                .iter()
+                .map(|(_, ty)| format!(", {}", self.value(iface, ty, round, Side::HostParam)))
+                .collect::<String>();
+            let expected = self.value(iface, &func.result, round, Side::HostResult);
+            self.host.push_str(&format!(
+                "check(exports.{name}(&mut store{args})?, {expected});\n"
+            ));
+        }
+    }
+
+    fn finish_one(&mut self, iface: &Interface, files: &mut Files) {
+        self.guest.push_str("}\n");
+        self.host.push_str("Ok(())\n}\n");
+        let guest = mem::take(&mut self.guest);
+        let host = mem::take(&mut self.host);
+        files.push(&format!("{}/wasm.rs", iface.name), guest.as_bytes());
+        files.push(&format!("{}/host-wasmer.rs", iface.name), host.as_bytes());
+    }
+}
+
+/// Minimal `RustGenerator` used to compute type names and print types the
+/// same way the Rust generators do.
+struct Namer<'a> {
+    types: &'a Types,
+    side: Side,
+    src: Source,
+}
+
+impl<'a> Namer<'a> {
+    fn new(types: &'a Types, side: Side) -> Namer<'a> {
+        Namer {
+            types,
+            side,
+            src: Source::default(),
+        }
+    }
+}
+
+impl RustGenerator for Namer<'_> {
+    fn push_str(&mut self, s: &str) {
+        self.src.push_str(s);
+    }
+
+    fn info(&self, ty: TypeId) -> TypeInfo {
+        self.types.get(ty)
+    }
+
+    fn types_mut(&mut self) -> &mut Types {
+        unreachable!()
+    }
+
+    fn print_borrowed_slice(
+        &mut self,
+        iface: &Interface,
+        mutbl: bool,
+        ty: &Type,
+        lifetime: &'static str,
+    ) {
+        self.print_rust_slice(iface, mutbl, ty, lifetime);
+    }
+
+    fn print_borrowed_str(&mut self, lifetime: &'static str) {
+        self.push_str("&");
+        if lifetime != "'_" {
+            self.push_str(lifetime);
+            self.push_str(" ");
+        }
+        self.push_str(" str");
+    }
+
+    fn default_param_mode(&self) -> TypeMode {
+        match self.side {
+            Side::Guest => TypeMode::Owned,
+            Side::HostParam | Side::HostResult => TypeMode::AllBorrowed("'a"),
+        }
+    }
+
+    fn handle_projection(&self) -> Option<(&'static str, String)> {
+        None
+    }
+
+    fn handle_wrapper(&self) -> Option<&'static str> {
+        None
+    }
+}
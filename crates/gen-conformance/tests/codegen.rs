@@ -0,0 +1,55 @@
+#![allow(dead_code)]
+
+use anyhow::Result;
+use std::path::Path;
+use wai_bindgen_gen_core::{wai_parser::Interface, Files, Generator};
+use wasmer::{Imports, Instance, Module, Store};
+
+#[test]
+fn runtime_interfaces() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/runtime");
+    for entry in std::fs::read_dir(&root).unwrap() {
+        let path = entry.unwrap().path().join("exports.wai");
+        if !path.exists() {
+            continue;
+        }
+        let iface = Interface::parse_file(&path).unwrap();
+        if iface.resources.len() > 0 || iface.functions.iter().any(|f| f.is_async) {
+            continue;
+        }
+
+        let mut files = Files::default();
+        wai_bindgen_gen_conformance::Opts::default()
+            .build()
+            .generate_all(&[], &[iface.clone()], &mut files);
+        let names = files.iter().map(|(name, _)| name).collect::<Vec<_>>();
+        let expected = [
+            format!("{}/host-wasmer.rs", iface.name),
+            format!("{}/wasm.rs", iface.name),
+        ];
+        assert_eq!(names, expected, "{}", path.display());
+    }
+}
+
+// The generated hosts are only compiled here, running them needs the guests
+// built from the generated `wasm.rs` files.
+mod hosts {
+    test_helpers::codegen_conformance!();
+}
+
+pub fn instantiate<T, I>(
+    wasm: &str,
+    store: &mut Store,
+    add_imports: impl FnOnce(&mut Store, &mut Imports) -> I,
+    mk_exports: impl FnOnce(&mut Store, &Module, &mut Imports) -> Result<(T, Instance)>,
+) -> Result<T>
+where
+    I: FnOnce(&Instance, &dyn wasmer::AsStoreRef) -> Result<()>,
+{
+    let module = Module::from_file(&*store, wasm)?;
+    let mut imports = Imports::new();
+    let initializer = add_imports(store, &mut imports);
+    let (exports, instance) = mk_exports(store, &module, &mut imports)?;
+    initializer(&instance, store)?;
+    Ok(exports)
+}
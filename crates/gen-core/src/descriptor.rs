@@ -0,0 +1,67 @@
+use crate::interface_hash;
+use wai_parser::abi::{AbiVariant, WasmType};
+use wai_parser::*;
+
+/// The name of the function guests export to describe the core ABI of an
+/// interface they export, followed by `:` and the name of the interface.
+///
+/// The function takes no parameters and returns a pointer to the pointer
+/// and length of the descriptor, like an export returning a `list<u8>`,
+/// except that the descriptor is static and mustn't be freed.
+pub const ABI_DESCRIPTOR_EXPORT: &str = "wai_abi_descriptor";
+
+/// The revision of the format [`abi_descriptor`] encodes.
+pub const ABI_DESCRIPTOR_VERSION: u8 = 1;
+
+/// Returns the ABI descriptor of `iface`, exported by a guest which exports
+/// each of its functions under the name `export_name` returns.
+///
+/// The descriptor is made up of:
+///
+/// * a byte holding [`ABI_DESCRIPTOR_VERSION`],
+/// * the [`interface_hash`] of `iface` as 8 little-endian bytes,
+/// * the number of functions as 4 little-endian bytes,
+/// * and for each function, the length of its export name as 4 little-endian
+///   bytes followed by the name in UTF-8, and the number of parameters of its
+///   core signature as a byte followed by their types, and likewise for its
+///   results. Types are written as their byte in the binary format of wasm,
+///   so `0x7f` for `i32`, `0x7e` for `i64`, `0x7d` for `f32` and `0x7c` for
+///   `f64`.
+pub fn abi_descriptor(iface: &Interface, export_name: impl Fn(&Function) -> String) -> Vec<u8> {
+    let mut ret = vec![ABI_DESCRIPTOR_VERSION];
+    ret.extend_from_slice(&interface_hash(iface).to_le_bytes());
+    ret.extend_from_slice(&(iface.functions.len() as u32).to_le_bytes());
+    for func in iface.functions.iter() {
+        let name = export_name(func);
+        ret.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        ret.extend_from_slice(name.as_bytes());
+        let sig = iface.wasm_signature(AbiVariant::GuestExport, func);
+        for types in [&sig.params, &sig.results] {
+            ret.push(types.len() as u8);
+            ret.extend(types.iter().map(|ty| match ty {
+                WasmType::I32 => 0x7f,
+                WasmType::I64 => 0x7e,
+                WasmType::F32 => 0x7d,
+                WasmType::F64 => 0x7c,
+            }));
+        }
+    }
+    ret
+}
+
+#[test]
+fn test_abi_descriptor() {
+    let iface = Interface::parse("foo", "a: func(x: u64) -> float32\nb: func(s: string)").unwrap();
+    let descriptor = abi_descriptor(&iface, |f| format!("ns#{}", f.name));
+
+    let mut expected = vec![ABI_DESCRIPTOR_VERSION];
+    expected.extend_from_slice(&interface_hash(&iface).to_le_bytes());
+    expected.extend_from_slice(&[2, 0, 0, 0]);
+    expected.extend_from_slice(&[4, 0, 0, 0]);
+    expected.extend_from_slice(b"ns#a");
+    expected.extend_from_slice(&[1, 0x7e, 1, 0x7d]);
+    expected.extend_from_slice(&[4, 0, 0, 0]);
+    expected.extend_from_slice(b"ns#b");
+    expected.extend_from_slice(&[2, 0x7f, 0x7f, 0]);
+    assert_eq!(descriptor, expected);
+}
@@ -0,0 +1,359 @@
+use anyhow::{Context, Result};
+use std::collections::{btree_map::Entry, BTreeMap};
+use std::path::Path;
+
+/// The files output by a [`Generator`](crate::Generator), keyed by their
+/// path relative to the output directory.
+///
+/// Besides their contents, the metadata of each file is recorded in a
+/// [`FileInfo`], and functions registered with [`Files::post_process`] are
+/// run over the files as they're written out to a [`FileSink`].
+#[derive(Default)]
+pub struct Files {
+    files: BTreeMap<String, File>,
+    split_per_interface: bool,
+    interface: Option<String>,
+    post_processors: Vec<Box<PostProcessor>>,
+}
+
+type PostProcessor = dyn Fn(&FileInfo, &mut Vec<u8>) -> Result<()>;
+
+struct File {
+    info: FileInfo,
+    contents: Vec<u8>,
+}
+
+/// The metadata of one of the [`Files`] output by a generator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileInfo {
+    /// The path of the file relative to the output directory.
+    pub name: String,
+    /// The language the file is written in, such as `rust` or `python`, or
+    /// `None` for binary files and files of unknown types.
+    pub language: Option<&'static str>,
+    /// The interfaces which contributed to the file, which is empty for
+    /// files shared by all interfaces, such as runtime support code.
+    pub interfaces: Vec<String>,
+    /// What kind of file it is.
+    pub kind: FileKind,
+}
+
+/// What kind of file one of the [`Files`] output by a generator is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileKind {
+    /// Source code or a schema, the bulk of the output of most generators.
+    Source,
+    /// Declarations for other source files, such as C headers or TypeScript
+    /// `.d.ts` files.
+    Header,
+    /// A build or packaging file, such as `Cargo.toml` or `pyproject.toml`.
+    Manifest,
+    /// Documentation, such as Markdown or HTML.
+    Documentation,
+    /// A compiled binary, such as a wasm module.
+    Binary,
+}
+
+/// A destination [`Files::write_to`] writes files to.
+///
+/// This is implemented for `BTreeMap<String, Vec<u8>>`, collecting the files
+/// in memory, and for closures taking the [`FileInfo`] and contents of each
+/// file.
+pub trait FileSink {
+    fn write(&mut self, info: &FileInfo, contents: &[u8]) -> Result<()>;
+}
+
+impl<F> FileSink for F
+where
+    F: FnMut(&FileInfo, &[u8]) -> Result<()>,
+{
+    fn write(&mut self, info: &FileInfo, contents: &[u8]) -> Result<()> {
+        self(info, contents)
+    }
+}
+
+impl FileSink for BTreeMap<String, Vec<u8>> {
+    fn write(&mut self, info: &FileInfo, contents: &[u8]) -> Result<()> {
+        self.insert(info.name.clone(), contents.to_vec());
+        Ok(())
+    }
+}
+
+impl Files {
+    /// Configures whether [`Files::push_for_interface`] puts each interface
+    /// in its own file rather than appending them all to one `bindings.*`
+    /// file.
+    pub fn split_per_interface(&mut self, split: bool) {
+        self.split_per_interface = split;
+    }
+
+    /// Sets the interface files pushed from now on are recorded as generated
+    /// for, which [`Generator::generate_one`](crate::Generator::generate_one)
+    /// does around its call to `finish_one`.
+    pub fn set_interface(&mut self, name: Option<&str>) {
+        self.interface = name.map(|s| s.to_string());
+    }
+
+    /// Adds the output generated for one interface to a file with the given
+    /// extension, for generators which would otherwise emit the output of all
+    /// interfaces into a single file.
+    ///
+    /// The file is `bindings.{ext}` by default, or `{stem}.{ext}` if output is
+    /// split per interface, where `stem` is the interface's name formatted as
+    /// appropriate for the target language.
+    pub fn push_for_interface(&mut self, stem: &str, ext: &str, contents: &[u8]) {
+        let stem = if self.split_per_interface {
+            stem
+        } else {
+            "bindings"
+        };
+        self.push(&format!("{}.{}", stem, ext), contents);
+    }
+
+    pub fn push(&mut self, name: &str, contents: &[u8]) {
+        let file = match self.files.entry(name.to_owned()) {
+            Entry::Vacant(entry) => entry.insert(File {
+                info: FileInfo::new(name),
+                contents: Vec::new(),
+            }),
+            Entry::Occupied(entry) => entry.into_mut(),
+        };
+        file.contents.extend_from_slice(contents);
+        if let Some(iface) = &self.interface {
+            if !file.info.interfaces.contains(iface) {
+                file.info.interfaces.push(iface.clone());
+            }
+        }
+    }
+
+    /// Registers a function to run over the contents of each file when the
+    /// files are written with [`Files::write_to`], such as to format them or
+    /// add a license header with [`prepend_header`].
+    ///
+    /// Post-processors run in the order they're registered.
+    pub fn post_process(&mut self, f: impl Fn(&FileInfo, &mut Vec<u8>) -> Result<()> + 'static) {
+        self.post_processors.push(Box::new(f));
+    }
+
+    /// Iterates over the names and contents of the files as generated,
+    /// without running post-processors.
+    pub fn iter(&self) -> impl Iterator<Item = (&'_ str, &'_ [u8])> {
+        self.files
+            .iter()
+            .map(|(name, file)| (name.as_str(), file.contents.as_slice()))
+    }
+
+    /// Iterates over the metadata and contents of the files as generated,
+    /// without running post-processors.
+    pub fn entries(&self) -> impl Iterator<Item = (&'_ FileInfo, &'_ [u8])> {
+        self.files
+            .values()
+            .map(|file| (&file.info, file.contents.as_slice()))
+    }
+
+    /// Runs the registered post-processors over each file and writes the
+    /// result to `sink`.
+    pub fn write_to(&self, sink: &mut impl FileSink) -> Result<()> {
+        for file in self.files.values() {
+            let mut contents = file.contents.clone();
+            for post_process in self.post_processors.iter() {
+                post_process(&file.info, &mut contents)
+                    .with_context(|| format!("failed to post-process `{}`", file.info.name))?;
+            }
+            sink.write(&file.info, &contents)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the files to `dir` with [`Files::write_to`], creating any
+    /// directories they're in.
+    pub fn write_to_dir(&self, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        self.write_to(&mut |info: &FileInfo, contents: &[u8]| {
+            let dst = dir.join(&info.name);
+            if let Some(parent) = dst.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("failed to create {:?}", parent))?;
+            }
+            std::fs::write(&dst, contents).with_context(|| format!("failed to write {:?}", dst))
+        })
+    }
+}
+
+impl FileInfo {
+    fn new(name: &str) -> FileInfo {
+        let file_name = name.rsplit('/').next().unwrap();
+        let ext = match file_name.strip_suffix(".d.ts") {
+            Some(_) => "d.ts",
+            None => file_name.rsplit_once('.').map_or("", |(_, ext)| ext),
+        };
+        let language = match ext {
+            "rs" => Some("rust"),
+            "c" | "h" => Some("c"),
+            "py" => Some("python"),
+            "js" => Some("javascript"),
+            "ts" | "d.ts" => Some("typescript"),
+            "d" => Some("d"),
+            "hs" => Some("haskell"),
+            "kt" => Some("kotlin"),
+            "nim" => Some("nim"),
+            "ml" => Some("ocaml"),
+            "json" => Some("json"),
+            "proto" => Some("protobuf"),
+            "webidl" => Some("webidl"),
+            "md" => Some("markdown"),
+            "html" => Some("html"),
+            "toml" => Some("toml"),
+            "mk" => Some("make"),
+            _ => None,
+        };
+        let kind = match ext {
+            _ if file_name == "py.typed" => FileKind::Manifest,
+            "h" | "d.ts" => FileKind::Header,
+            "toml" | "mk" => FileKind::Manifest,
+            "md" | "html" => FileKind::Documentation,
+            "wasm" => FileKind::Binary,
+            _ => FileKind::Source,
+        };
+        FileInfo {
+            name: name.to_string(),
+            language,
+            interfaces: Vec::new(),
+            kind,
+        }
+    }
+
+    /// Returns what a one-line comment starts and ends with in the language
+    /// of the file, or `None` if it doesn't have comments.
+    pub fn line_comment(&self) -> Option<(&'static str, &'static str)> {
+        match self.language? {
+            "rust" | "c" | "javascript" | "typescript" | "d" | "kotlin" | "protobuf" | "webidl" => {
+                Some(("//", ""))
+            }
+            "python" | "nim" | "toml" | "make" => Some(("#", "")),
+            "haskell" => Some(("--", "")),
+            "ocaml" => Some(("(*", " *)")),
+            "markdown" | "html" => Some(("<!--", " -->")),
+            _ => None,
+        }
+    }
+}
+
+/// Returns a post-processor for [`Files::post_process`] which prepends
+/// `header`, such as a license notice, to each file as a comment.
+///
+/// Files in languages without comments, like JSON and binaries, are left
+/// as they are.
+pub fn prepend_header(header: &str) -> impl Fn(&FileInfo, &mut Vec<u8>) -> Result<()> {
+    let header = header.to_string();
+    move |info, contents| {
+        let (start, end) = match info.line_comment() {
+            Some(comment) => comment,
+            None => return Ok(()),
+        };
+        let mut prefix = String::new();
+        for line in header.lines() {
+            prefix.push_str(start);
+            if !line.is_empty() {
+                prefix.push(' ');
+                prefix.push_str(line);
+            }
+            prefix.push_str(end);
+            prefix.push('\n');
+        }
+        prefix.push('\n');
+        contents.splice(0..0, prefix.into_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata() {
+        let mut files = Files::default();
+        files.set_interface(Some("a"));
+        files.push_for_interface("a", "h", b"a");
+        files.push("pkg/a.d.ts", b"");
+        files.set_interface(Some("b"));
+        files.push_for_interface("b", "h", b"b");
+        files.set_interface(None);
+        files.push("runtime.c", b"");
+        files.push("pkg/py.typed", b"");
+        files.push("guest.wasm", b"\0asm");
+
+        let infos = files.entries().map(|(info, _)| info).collect::<Vec<_>>();
+        let summary = infos
+            .iter()
+            .map(|i| (i.name.as_str(), i.language, i.kind, i.interfaces.join(",")))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                ("bindings.h", Some("c"), FileKind::Header, "a,b".to_string()),
+                ("guest.wasm", None, FileKind::Binary, String::new()),
+                (
+                    "pkg/a.d.ts",
+                    Some("typescript"),
+                    FileKind::Header,
+                    "a".to_string()
+                ),
+                ("pkg/py.typed", None, FileKind::Manifest, String::new()),
+                ("runtime.c", Some("c"), FileKind::Source, String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn post_process() {
+        let mut files = Files::default();
+        files.push("a.rs", b"fn a() {}\n");
+        files.push("a.ml", b"let a = 1\n");
+        files.push("a.json", b"{}");
+        files.post_process(prepend_header("SPDX-License-Identifier: MIT\n\nHi"));
+        files.post_process(|info, contents| {
+            if info.language == Some("rust") {
+                contents.extend_from_slice(b"// end\n");
+            }
+            Ok(())
+        });
+
+        let mut out = BTreeMap::new();
+        files.write_to(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out.remove("a.rs").unwrap()).unwrap(),
+            "// SPDX-License-Identifier: MIT\n//\n// Hi\n\nfn a() {}\n// end\n"
+        );
+        assert_eq!(
+            String::from_utf8(out.remove("a.ml").unwrap()).unwrap(),
+            "(* SPDX-License-Identifier: MIT *)\n(* *)\n(* Hi *)\n\nlet a = 1\n"
+        );
+        assert_eq!(out.remove("a.json").unwrap(), b"{}");
+
+        // The files themselves are left as generated.
+        assert_eq!(
+            files.iter().collect::<Vec<_>>(),
+            [
+                ("a.json", &b"{}"[..]),
+                ("a.ml", b"let a = 1\n"),
+                ("a.rs", b"fn a() {}\n")
+            ]
+        );
+
+        let mut names = Vec::new();
+        files.post_process(|info, _| {
+            anyhow::ensure!(info.name != "a.ml", "bad");
+            Ok(())
+        });
+        let err = files
+            .write_to(&mut |info: &FileInfo, _: &[u8]| {
+                names.push(info.name.clone());
+                Ok(())
+            })
+            .unwrap_err();
+        assert_eq!(err.to_string(), "failed to post-process `a.ml`");
+        assert_eq!(names, ["a.json"]);
+    }
+}
@@ -0,0 +1,135 @@
+use wai_parser::{Function, Interface};
+
+/// Selects which interfaces and functions bindings are generated for.
+///
+/// Each entry is a glob pattern where `*` matches any run of characters and
+/// `?` matches any single character. A pattern matches an interface by its
+/// name, and a function by either its name (`resource::method` for methods)
+/// or by `interface.function`.
+#[derive(Default, Debug, Clone)]
+pub struct Filter {
+    /// If non-empty, only interfaces and functions matching one of these
+    /// patterns are kept. The functions of a matching interface are all kept.
+    pub only: Vec<String>,
+    /// Interfaces and functions matching any of these patterns are removed,
+    /// even if they also match `only`.
+    pub skip: Vec<String>,
+}
+
+impl Filter {
+    pub fn is_empty(&self) -> bool {
+        self.only.is_empty() && self.skip.is_empty()
+    }
+
+    /// Removes the interfaces and functions from `ifaces` which this filter
+    /// doesn't select.
+    ///
+    /// With `only` set, interfaces where neither the interface nor any of its
+    /// functions match are removed entirely.
+    pub fn apply(&self, ifaces: &mut Vec<Interface>) {
+        if self.is_empty() {
+            return;
+        }
+        ifaces.retain_mut(|iface| {
+            if matches_any(&self.skip, &iface.name) {
+                return false;
+            }
+            let all = self.only.is_empty() || matches_any(&self.only, &iface.name);
+            iface
+                .functions
+                .retain(|func| self.keeps_function(iface.name.as_str(), func, all));
+            all || !iface.functions.is_empty()
+        });
+    }
+
+    fn keeps_function(&self, iface: &str, func: &Function, all: bool) -> bool {
+        let qualified = format!("{}.{}", iface, func.name);
+        let matches = |patterns: &[String]| {
+            matches_any(patterns, &func.name) || matches_any(patterns, &qualified)
+        };
+        (all || matches(&self.only)) && !matches(&self.skip)
+    }
+}
+
+fn matches_any(patterns: &[String], name: &str) -> bool {
+    patterns
+        .iter()
+        .any(|p| glob_match(p.as_bytes(), name.as_bytes()))
+}
+
+pub(crate) fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|i| glob_match(rest, &name[i..])),
+        Some((b'?', rest)) => match std::str::from_utf8(name) {
+            // Skip a whole character rather than a single byte.
+            Ok(s) => match s.chars().next() {
+                Some(c) => glob_match(rest, &name[c.len_utf8()..]),
+                None => false,
+            },
+            Err(_) => false,
+        },
+        Some((c, rest)) => name.first() == Some(c) && glob_match(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Filter;
+    use wai_parser::Interface;
+
+    fn ifaces() -> Vec<Interface> {
+        let a = Interface::parse("a", "get-x: func()\nget-y: func()\nset-x: func()").unwrap();
+        let b = Interface::parse("b", "get-x: func()\nrun: func()").unwrap();
+        vec![a, b]
+    }
+
+    fn names(filter: Filter) -> Vec<String> {
+        let mut ifaces = ifaces();
+        filter.apply(&mut ifaces);
+        ifaces
+            .iter()
+            .flat_map(|i| {
+                i.functions
+                    .iter()
+                    .map(move |f| format!("{}.{}", i.name, f.name))
+            })
+            .collect()
+    }
+
+    fn filter(only: &[&str], skip: &[&str]) -> Filter {
+        Filter {
+            only: only.iter().map(|s| s.to_string()).collect(),
+            skip: skip.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(names(filter(&[], &[])).len(), 5);
+    }
+
+    #[test]
+    fn skip() {
+        assert_eq!(names(filter(&[], &["get-*"])), ["a.set-x", "b.run"]);
+        assert_eq!(
+            names(filter(&[], &["a.get-?"])),
+            ["a.set-x", "b.get-x", "b.run"]
+        );
+        assert_eq!(names(filter(&[], &["a"])), ["b.get-x", "b.run"]);
+    }
+
+    #[test]
+    fn only() {
+        assert_eq!(
+            names(filter(&["*-x"], &[])),
+            ["a.get-x", "a.set-x", "b.get-x"]
+        );
+        assert_eq!(names(filter(&["b"], &[])), ["b.get-x", "b.run"]);
+        assert_eq!(names(filter(&["b.run"], &[])), ["b.run"]);
+        assert_eq!(
+            names(filter(&["*"], &["b.*", "set-x"])),
+            ["a.get-x", "a.get-y"]
+        );
+    }
+}
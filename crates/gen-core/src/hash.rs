@@ -0,0 +1,248 @@
+use std::fmt::Write;
+use wai_parser::*;
+
+/// The name of the custom sections holding the hash of an interface a module
+/// was built against, followed by `:` and the name of the interface.
+///
+/// The section holds the hash as 8 little-endian bytes.
+pub const INTERFACE_HASH_SECTION: &str = "wai-interface-hash";
+
+/// Returns a hash of the contents of `iface`, which guest and host bindings
+/// generated from the same interface agree on.
+///
+/// The hash covers the names and signatures of the functions of the
+/// interface and the definitions of the types they use, but not docs or the
+/// order of the functions, so it only changes along with what crosses the
+/// boundary between the two sides. It's stable across versions of the
+/// generators.
+pub fn interface_hash(iface: &Interface) -> u64 {
+    let mut funcs = iface
+        .functions
+        .iter()
+        .map(|func| {
+            let mut s = String::new();
+            write_function(iface, func, &mut s);
+            s
+        })
+        .collect::<Vec<_>>();
+    funcs.sort();
+
+    // 64-bit FNV-1a, which unlike `DefaultHasher` is specified.
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for byte in funcs.join("\n").bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+fn write_function(iface: &Interface, func: &Function, dst: &mut String) {
+    match &func.kind {
+        FunctionKind::Freestanding => {}
+        FunctionKind::Static { resource, .. } => {
+            write!(dst, "static {} ", iface.resources[*resource].name).unwrap();
+        }
+        FunctionKind::Method { resource, .. } => {
+            write!(dst, "method {} ", iface.resources[*resource].name).unwrap();
+        }
+    }
+    dst.push_str(&func.name);
+    dst.push_str(": ");
+    if func.is_async {
+        dst.push_str("async ");
+    }
+    dst.push_str("func(");
+    for (i, (name, ty)) in func.params.iter().enumerate() {
+        if i > 0 {
+            dst.push_str(", ");
+        }
+        dst.push_str(name);
+        dst.push_str(": ");
+        write_type(iface, ty, dst);
+    }
+    dst.push_str(") -> ");
+    write_type(iface, &func.result, dst);
+}
+
+fn write_type(iface: &Interface, ty: &Type, dst: &mut String) {
+    let id = match ty {
+        Type::Unit => return dst.push_str("unit"),
+        Type::Bool => return dst.push_str("bool"),
+        Type::U8 => return dst.push_str("u8"),
+        Type::U16 => return dst.push_str("u16"),
+        Type::U32 => return dst.push_str("u32"),
+        Type::U64 => return dst.push_str("u64"),
+        Type::S8 => return dst.push_str("s8"),
+        Type::S16 => return dst.push_str("s16"),
+        Type::S32 => return dst.push_str("s32"),
+        Type::S64 => return dst.push_str("s64"),
+        Type::Float32 => return dst.push_str("float32"),
+        Type::Float64 => return dst.push_str("float64"),
+        Type::Char => return dst.push_str("char"),
+        Type::String => return dst.push_str("string"),
+        Type::Handle(r) => {
+            dst.push_str("handle ");
+            return dst.push_str(&iface.resources[*r].name);
+        }
+        Type::Id(id) => *id,
+    };
+    let ty = &iface.types[id];
+    if let TypeDefKind::Type(t) = &ty.kind {
+        // Aliases are interchangeable with what they name.
+        return write_type(iface, t, dst);
+    }
+    if let Some(name) = &ty.name {
+        dst.push_str(name);
+        dst.push(' ');
+    }
+    match &ty.kind {
+        TypeDefKind::Record(r) => {
+            dst.push_str("record {");
+            for (i, field) in r.fields.iter().enumerate() {
+                if i > 0 {
+                    dst.push_str(", ");
+                }
+                dst.push_str(&field.name);
+                dst.push_str(": ");
+                write_type(iface, &field.ty, dst);
+            }
+            dst.push('}');
+        }
+        TypeDefKind::Flags(f) => {
+            dst.push_str("flags {");
+            for (i, flag) in f.flags.iter().enumerate() {
+                if i > 0 {
+                    dst.push_str(", ");
+                }
+                dst.push_str(&flag.name);
+            }
+            dst.push('}');
+        }
+        TypeDefKind::Tuple(t) => {
+            dst.push_str("tuple<");
+            write_types(iface, &t.types, dst);
+            dst.push('>');
+        }
+        TypeDefKind::Variant(v) => {
+            dst.push_str("variant {");
+            for (i, case) in v.cases.iter().enumerate() {
+                if i > 0 {
+                    dst.push_str(", ");
+                }
+                dst.push_str(&case.name);
+                dst.push('(');
+                write_type(iface, &case.ty, dst);
+                dst.push(')');
+            }
+            dst.push('}');
+        }
+        TypeDefKind::Enum(e) => {
+            dst.push_str("enum {");
+            for (i, case) in e.cases.iter().enumerate() {
+                if i > 0 {
+                    dst.push_str(", ");
+                }
+                dst.push_str(&case.name);
+            }
+            dst.push('}');
+        }
+        TypeDefKind::Union(u) => {
+            dst.push_str("union {");
+            for (i, case) in u.cases.iter().enumerate() {
+                if i > 0 {
+                    dst.push_str(", ");
+                }
+                write_type(iface, &case.ty, dst);
+            }
+            dst.push('}');
+        }
+        TypeDefKind::Option(t) => {
+            dst.push_str("option<");
+            write_type(iface, t, dst);
+            dst.push('>');
+        }
+        TypeDefKind::Expected(e) => {
+            dst.push_str("expected<");
+            write_types(iface, &[e.ok, e.err], dst);
+            dst.push('>');
+        }
+        TypeDefKind::List(t) => {
+            dst.push_str("list<");
+            write_type(iface, t, dst);
+            dst.push('>');
+        }
+        TypeDefKind::Future(t) => {
+            dst.push_str("future<");
+            write_type(iface, t, dst);
+            dst.push('>');
+        }
+        TypeDefKind::Stream(s) => {
+            dst.push_str("stream<");
+            write_types(iface, &[s.element, s.end], dst);
+            dst.push('>');
+        }
+        TypeDefKind::Type(_) => unreachable!(),
+    }
+}
+
+fn write_types(iface: &Interface, types: &[Type], dst: &mut String) {
+    for (i, ty) in types.iter().enumerate() {
+        if i > 0 {
+            dst.push_str(", ");
+        }
+        write_type(iface, ty, dst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::interface_hash;
+    use wai_parser::Interface;
+
+    fn hash(src: &str) -> u64 {
+        interface_hash(&Interface::parse("x", src).unwrap())
+    }
+
+    #[test]
+    fn stable() {
+        let a = hash(
+            "
+                record point { x: u32, y: u32 }
+                /// Moves a point.
+                move: func(p: point, by: u32) -> point
+                name: func() -> string
+            ",
+        );
+        // Docs and the order of functions don't matter.
+        let b = hash(
+            "
+                record point { x: u32, y: u32 }
+                name: func() -> string
+                type dist = u32
+                move: func(p: point, by: dist) -> point
+            ",
+        );
+        assert_eq!(a, b);
+
+        assert_ne!(
+            a,
+            hash(
+                "
+                    record point { x: u32, y: u64 }
+                    move: func(p: point, by: u32) -> point
+                    name: func() -> string
+                "
+            )
+        );
+        assert_ne!(
+            a,
+            hash(
+                "
+                    record point { x: u32, y: u32 }
+                    move: func(p: point, distance: u32) -> point
+                    name: func() -> string
+                "
+            )
+        );
+    }
+}
@@ -1,14 +1,31 @@
 use anyhow::Result;
-use std::collections::{btree_map::Entry, BTreeMap, HashMap};
+use std::collections::HashMap;
 use std::fmt::{self, Write};
 use std::ops::Deref;
 use std::path::Path;
 use wai_parser::*;
 
 pub use wai_parser;
+mod descriptor;
+mod files;
+mod filter;
+mod hash;
+mod namespace;
 mod ns;
-
+mod paths;
+mod unions;
+
+pub use descriptor::{abi_descriptor, ABI_DESCRIPTOR_EXPORT, ABI_DESCRIPTOR_VERSION};
+pub use files::{prepend_header, FileInfo, FileKind, FileSink, Files};
+pub use filter::Filter;
+pub use hash::{interface_hash, INTERFACE_HASH_SECTION};
+pub use namespace::NamespaceMap;
 pub use ns::Ns;
+pub use paths::{
+    builtin_interface_path, expand_path, parse_interface_arg, parse_interface_arg_with_cfg,
+    BUILTIN_INTERFACES,
+};
+pub use unions::union_case_names;
 
 /// This is the direction from the user's perspective. Are we importing
 /// functions to call, or defining functions and exporting them to be called?
@@ -146,7 +163,9 @@ pub trait Generator {
 
         self.finish_functions(iface, dir);
 
-        self.finish_one(iface, files)
+        files.set_interface(Some(&iface.name));
+        self.finish_one(iface, files);
+        files.set_interface(None);
     }
 
     fn generate_all(&mut self, imports: &[Interface], exports: &[Interface], files: &mut Files) {
@@ -184,6 +203,10 @@ pub struct TypeInfo {
 
     /// Whether or not this type (transitively) has a handle.
     pub has_handle: bool,
+
+    /// Whether or not this type is the error of an `expected`, in which case
+    /// bindings implement the language's error conventions for it.
+    pub error: bool,
 }
 
 impl std::ops::BitOrAssign for TypeInfo {
@@ -206,6 +229,11 @@ impl Types {
             }
             self.set_param_result_ty(iface, &f.result, false, true);
         }
+        for (_, ty) in iface.types.iter() {
+            if let TypeDefKind::Expected(e) = &ty.kind {
+                self.set_error(iface, &e.err);
+            }
+        }
     }
 
     pub fn get(&self, id: TypeId) -> TypeInfo {
@@ -277,6 +305,21 @@ impl Types {
         info
     }
 
+    fn set_error(&mut self, iface: &Interface, ty: &Type) {
+        if let Type::Id(id) = ty {
+            match &iface.types[*id].kind {
+                TypeDefKind::Type(ty) => self.set_error(iface, ty),
+                TypeDefKind::Record(_)
+                | TypeDefKind::Variant(_)
+                | TypeDefKind::Enum(_)
+                | TypeDefKind::Union(_) => {
+                    self.type_info.get_mut(id).unwrap().error = true;
+                }
+                _ => {}
+            }
+        }
+    }
+
     fn set_param_result_id(&mut self, iface: &Interface, ty: TypeId, param: bool, result: bool) {
         match &iface.types[ty].kind {
             TypeDefKind::Record(r) => {
@@ -332,28 +375,6 @@ impl Types {
     }
 }
 
-#[derive(Default)]
-pub struct Files {
-    files: BTreeMap<String, Vec<u8>>,
-}
-
-impl Files {
-    pub fn push(&mut self, name: &str, contents: &[u8]) {
-        match self.files.entry(name.to_owned()) {
-            Entry::Vacant(entry) => {
-                entry.insert(contents.to_owned());
-            }
-            Entry::Occupied(ref mut entry) => {
-                entry.get_mut().extend_from_slice(contents);
-            }
-        }
-    }
-
-    pub fn iter(&self) -> impl Iterator<Item = (&'_ str, &'_ [u8])> {
-        self.files.iter().map(|p| (p.0.as_str(), p.1.as_slice()))
-    }
-}
-
 pub fn load(path: impl AsRef<Path>) -> Result<Interface> {
     Interface::parse_file(path)
 }
@@ -458,7 +479,8 @@ macro_rules! uwriteln {
 
 #[cfg(test)]
 mod tests {
-    use super::{Generator, Source};
+    use super::{Files, Generator, Source, Types};
+    use wai_parser::Interface;
 
     #[test]
     fn simple_append() {
@@ -506,6 +528,51 @@ mod tests {
         assert_eq!(s.s, "function() {\n  x\n}");
     }
 
+    #[test]
+    fn split_per_interface() {
+        let mut files = Files::default();
+        files.push_for_interface("a", "rs", b"a");
+        files.push_for_interface("b", "rs", b"b");
+        assert_eq!(
+            files.iter().collect::<Vec<_>>(),
+            [("bindings.rs", &b"ab"[..])]
+        );
+
+        let mut files = Files::default();
+        files.split_per_interface(true);
+        files.push_for_interface("a", "rs", b"a");
+        files.push_for_interface("b", "rs", b"b");
+        assert_eq!(
+            files.iter().collect::<Vec<_>>(),
+            [("a.rs", &b"a"[..]), ("b.rs", &b"b"[..])]
+        );
+    }
+
+    #[test]
+    fn error_types() {
+        let iface = Interface::parse(
+            "a",
+            "
+                enum e { a }
+                record r { x: u32 }
+                type alias = r
+                variant v { a(u32) }
+                f: func(x: list<expected<u32, e>>) -> option<expected<alias, v>>
+                g: func() -> expected<v, string>
+            ",
+        )
+        .unwrap();
+        let mut types = Types::default();
+        types.analyze(&iface);
+        let errors = iface
+            .types
+            .iter()
+            .filter(|(id, _)| types.get(*id).error)
+            .map(|(_, ty)| ty.name.as_deref().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(errors, ["e", "v"]);
+    }
+
     #[test]
     fn generator_is_object_safe() {
         fn _assert(_: &dyn Generator) {}
@@ -0,0 +1,66 @@
+use std::str::FromStr;
+use wai_parser::Interface;
+
+/// Maps the interface `module` to the wasm import/export namespace
+/// `namespace`, given on the command line as `module=namespace`.
+///
+/// This has the same effect as setting [`Interface::module`]: the functions
+/// of a mapped interface are imported from the module `namespace` and
+/// exported as `namespace#<func>`, whatever the interface's file is named.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamespaceMap {
+    pub module: String,
+    pub namespace: String,
+}
+
+impl NamespaceMap {
+    /// Returns the namespace `iface` is mapped to by the first matching entry
+    /// of `maps`, or else its [`Interface::module`] if it has one.
+    pub fn lookup<'a>(maps: &'a [NamespaceMap], iface: &'a Interface) -> Option<&'a str> {
+        maps.iter()
+            .find(|map| map.module == iface.name)
+            .map(|map| map.namespace.as_str())
+            .or(iface.module.as_deref())
+    }
+}
+
+impl FromStr for NamespaceMap {
+    type Err = String;
+    fn from_str(s: &str) -> Result<NamespaceMap, String> {
+        match s.split_once('=') {
+            Some((module, namespace)) if !module.is_empty() && !namespace.is_empty() => {
+                Ok(NamespaceMap {
+                    module: module.to_string(),
+                    namespace: namespace.to_string(),
+                })
+            }
+            _ => Err(format!("expected `module=namespace`, found `{}`", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NamespaceMap;
+    use wai_parser::Interface;
+
+    #[test]
+    fn lookup() {
+        let maps = ["imports=env", "other=x"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect::<Vec<NamespaceMap>>();
+        let mut iface = Interface {
+            name: "imports".to_string(),
+            ..Interface::default()
+        };
+        assert_eq!(NamespaceMap::lookup(&maps, &iface), Some("env"));
+        assert_eq!(NamespaceMap::lookup(&[], &iface), None);
+        iface.module = Some("host".to_string());
+        assert_eq!(NamespaceMap::lookup(&[], &iface), Some("host"));
+        assert_eq!(NamespaceMap::lookup(&maps, &iface), Some("env"));
+
+        assert!("imports".parse::<NamespaceMap>().is_err());
+        assert!("=env".parse::<NamespaceMap>().is_err());
+    }
+}
@@ -0,0 +1,173 @@
+use crate::filter::glob_match;
+use anyhow::{bail, Result};
+use std::io;
+use std::path::{Path, PathBuf};
+use wai_parser::{Cfg, Interface};
+
+/// Expands `path` into the `*.wai` files it refers to.
+///
+/// A directory expands to the `*.wai` files directly inside of it, and a path
+/// whose file name contains `*` or `?` to the files in its parent directory
+/// matching that glob pattern, both sorted by name. Any other path is returned
+/// as-is.
+pub fn expand_path(path: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    let (dir, pattern) = match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) if name.contains(['*', '?']) => (path.parent().unwrap_or(Path::new("")), name),
+        _ if path.is_dir() => (path, "*.wai"),
+        _ => return Ok(vec![path.to_path_buf()]),
+    };
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let mut paths = Vec::new();
+    for entry in dir.read_dir()? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let matches = match name.to_str() {
+            Some(name) => glob_match(pattern.as_bytes(), name.as_bytes()),
+            None => false,
+        };
+        if matches && entry.file_type()?.is_file() {
+            paths.push(dir.join(name));
+        }
+    }
+    if paths.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no `*.wai` files found matching `{}`", path.display()),
+        ));
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// The interfaces shipped with wai-bindgen, which arguments refer to as
+/// `builtin:<name>`.
+pub const BUILTIN_INTERFACES: &[&str] = &["wai-logging"];
+
+/// Returns the path of the built-in interface `name`.
+pub fn builtin_interface_path(name: &str) -> Result<PathBuf> {
+    if !BUILTIN_INTERFACES.contains(&name) {
+        bail!(
+            "unknown built-in interface `{}`, expected one of: {}",
+            name,
+            BUILTIN_INTERFACES.join(", ")
+        );
+    }
+    Ok(Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("wai")
+        .join(format!("{}.wai", name)))
+}
+
+/// Parses the interfaces an argument of the form `[NAME=]PATH` refers to,
+/// along with the files they were parsed from.
+///
+/// `PATH` is relative to `dir` and expanded with [`expand_path`], unless it's
+/// of the form `builtin:<name>`, which refers to one of the
+/// [`BUILTIN_INTERFACES`]. If `NAME` is
+/// given, the single interface `PATH` refers to is named `NAME` rather than
+/// after its file, and uses `NAME` as its module so its exports are named
+/// `NAME#<func>`. That way the same interface can be imported or exported
+/// several times under different names.
+pub fn parse_interface_arg(dir: &Path, arg: &str) -> Result<Vec<(PathBuf, Interface)>> {
+    parse_interface_arg_with_cfg(dir, arg, &Cfg::default())
+}
+
+/// Like [`parse_interface_arg`], but keeps the items annotated with `@cfg`
+/// whose settings are set in `cfg`.
+pub fn parse_interface_arg_with_cfg(
+    dir: &Path,
+    arg: &str,
+    cfg: &Cfg,
+) -> Result<Vec<(PathBuf, Interface)>> {
+    let (name, path) = split_interface_arg(arg);
+    let paths = match path.strip_prefix("builtin:") {
+        Some(builtin) => vec![builtin_interface_path(builtin)?],
+        None => expand_path(dir.join(path))?,
+    };
+    if let Some(name) = name {
+        if paths.len() > 1 {
+            bail!(
+                "`{}` refers to several interfaces, so it can't be named `{}`",
+                path,
+                name
+            );
+        }
+    }
+    paths
+        .into_iter()
+        .map(|path| {
+            let mut iface = Interface::parse_file_with_cfg(&path, cfg)?;
+            if let Some(name) = name {
+                iface.name = name.to_string();
+                iface.module = Some(name.to_string());
+            }
+            Ok((path, iface))
+        })
+        .collect()
+}
+
+fn split_interface_arg(arg: &str) -> (Option<&str>, &str) {
+    match arg.split_once('=') {
+        Some((name, path)) if !name.is_empty() && !name.contains(['/', '\\']) => (Some(name), path),
+        _ => (None, arg),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand_path, parse_interface_arg, split_interface_arg, BUILTIN_INTERFACES};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn expand() {
+        let dir = std::env::temp_dir().join(format!("wai-expand-path-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        for name in ["b.wai", "a.wai", "c.txt", "sub/d.wai"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        let names = |pattern: &str| {
+            expand_path(dir.join(pattern))
+                .unwrap()
+                .iter()
+                .map(|p| p.strip_prefix(&dir).unwrap().to_path_buf())
+                .collect::<Vec<_>>()
+        };
+        let paths = |names: &[&str]| names.iter().map(PathBuf::from).collect::<Vec<_>>();
+        assert_eq!(names(""), paths(&["a.wai", "b.wai"]));
+        assert_eq!(names("*"), paths(&["a.wai", "b.wai", "c.txt"]));
+        assert_eq!(names("?.wai"), paths(&["a.wai", "b.wai"]));
+        assert_eq!(names("sub"), paths(&["sub/d.wai"]));
+        assert_eq!(names("missing.wai"), paths(&["missing.wai"]));
+        assert!(expand_path(dir.join("*.md")).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn interface_args() {
+        assert_eq!(split_interface_arg("a.wai"), (None, "a.wai"));
+        assert_eq!(
+            split_interface_arg("logger-a=logger.wai"),
+            (Some("logger-a"), "logger.wai")
+        );
+        assert_eq!(split_interface_arg("=a.wai"), (None, "=a.wai"));
+        assert_eq!(split_interface_arg("dir/a=b.wai"), (None, "dir/a=b.wai"));
+    }
+
+    #[test]
+    fn builtins() {
+        for name in BUILTIN_INTERFACES {
+            let ifaces = parse_interface_arg(Path::new("missing"), &format!("builtin:{}", name));
+            assert_eq!(ifaces.unwrap()[0].1.name, *name);
+        }
+        let ifaces = parse_interface_arg(Path::new(""), "log=builtin:wai-logging").unwrap();
+        assert_eq!(ifaces[0].1.module.as_deref(), Some("log"));
+        assert!(parse_interface_arg(Path::new(""), "builtin:missing").is_err());
+    }
+}
@@ -0,0 +1,140 @@
+use heck::*;
+use std::collections::HashMap;
+use std::fmt::Write;
+use wai_parser::*;
+
+/// Returns the names of the cases of `union`, in camel case.
+///
+/// Cases are named after their types, such as `U32` or `StringList`, with a
+/// suffix of their index among the cases of the same type when several are.
+/// Every generator names the cases of unions the same way, so that the
+/// bindings of different languages line up.
+pub fn union_case_names(iface: &Interface, union: &Union) -> Vec<String> {
+    enum UsedState<'a> {
+        /// This name has been used once before.
+        ///
+        /// Contains a reference to the name given to the first usage so that a suffix can be added to it.
+        Once(&'a mut String),
+        /// This name has already been used multiple times.
+        ///
+        /// Contains the number of times this has already been used.
+        Multiple(usize),
+    }
+
+    // A `Vec` of the names we're assigning each of the union's cases in order.
+    let mut case_names = vec![String::new(); union.cases.len()];
+    // A map from case names to their `UsedState`.
+    let mut used = HashMap::new();
+    for (case, name) in union.cases.iter().zip(case_names.iter_mut()) {
+        write_name(iface, &case.ty, name);
+
+        match used.get_mut(name.as_str()) {
+            None => {
+                // Initialise this name's `UsedState`, with a mutable reference to this name
+                // in case we have to add a suffix to it later.
+                used.insert(name.clone(), UsedState::Once(name));
+                // Since this is the first (and potentially only) usage of this name,
+                // we don't need to add a suffix here.
+            }
+            Some(state) => match state {
+                UsedState::Multiple(n) => {
+                    // Add a suffix of the index of this usage.
+                    write!(name, "{n}").unwrap();
+                    // Add one to the number of times this type has been used.
+                    *n += 1;
+                }
+                UsedState::Once(first) => {
+                    // Add a suffix of 0 to the first usage.
+                    first.push('0');
+                    // We now get a suffix of 1.
+                    name.push('1');
+                    // Then update the state.
+                    *state = UsedState::Multiple(2);
+                }
+            },
+        }
+    }
+
+    case_names
+}
+
+fn write_name(iface: &Interface, ty: &Type, out: &mut String) {
+    match ty {
+        Type::Unit => out.push_str("Unit"),
+        Type::Bool => out.push_str("Bool"),
+        Type::U8 => out.push_str("U8"),
+        Type::U16 => out.push_str("U16"),
+        Type::U32 => out.push_str("U32"),
+        Type::U64 => out.push_str("U64"),
+        Type::S8 => out.push_str("I8"),
+        Type::S16 => out.push_str("I16"),
+        Type::S32 => out.push_str("I32"),
+        Type::S64 => out.push_str("I64"),
+        Type::Float32 => out.push_str("F32"),
+        Type::Float64 => out.push_str("F64"),
+        Type::Char => out.push_str("Char"),
+        Type::String => out.push_str("String"),
+        Type::Handle(id) => out.push_str(&iface.resources[*id].name.to_camel_case()),
+        Type::Id(id) => {
+            let ty = &iface.types[*id];
+            match &ty.name {
+                Some(name) => out.push_str(&name.to_camel_case()),
+                None => match &ty.kind {
+                    TypeDefKind::Option(ty) => {
+                        out.push_str("Optional");
+                        write_name(iface, ty, out);
+                    }
+                    TypeDefKind::Expected(_) => out.push_str("Result"),
+                    TypeDefKind::Tuple(_) => out.push_str("Tuple"),
+                    TypeDefKind::List(ty) => {
+                        write_name(iface, ty, out);
+                        out.push_str("List")
+                    }
+                    TypeDefKind::Future(ty) => {
+                        write_name(iface, ty, out);
+                        out.push_str("Future");
+                    }
+                    TypeDefKind::Stream(s) => {
+                        write_name(iface, &s.element, out);
+                        write_name(iface, &s.end, out);
+                        out.push_str("Stream");
+                    }
+
+                    TypeDefKind::Type(ty) => write_name(iface, ty, out),
+                    TypeDefKind::Record(_) => out.push_str("Record"),
+                    TypeDefKind::Flags(_) => out.push_str("Flags"),
+                    TypeDefKind::Variant(_) => out.push_str("Variant"),
+                    TypeDefKind::Enum(_) => out.push_str("Enum"),
+                    TypeDefKind::Union(_) => out.push_str("Union"),
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::union_case_names;
+    use wai_parser::{Interface, TypeDefKind};
+
+    #[test]
+    fn case_names() {
+        let iface = Interface::parse(
+            "a",
+            "union u { s32, string, s32, list<u8>, option<float64>, s32 }",
+        )
+        .unwrap();
+        let union = iface
+            .types
+            .iter()
+            .find_map(|(_, ty)| match &ty.kind {
+                TypeDefKind::Union(u) => Some(u),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(
+            union_case_names(&iface, union),
+            ["I320", "String", "I321", "U8List", "OptionalF64", "I322"]
+        );
+    }
+}
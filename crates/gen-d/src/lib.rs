@@ -0,0 +1,1874 @@
+//! Bindings for D, either for guests compiled to wasm with LDC or for hosts.
+//!
+//! For each interface this emits a `<interface>.d` module with records as
+//! structs, variants and unions as `std.sumtype` sum types of a struct per
+//! case, and the functions of the interface:
+//!
+//! * For guests, functions lifting and lowering the canonical ABI around the
+//!   raw wasm imports, and for exports an `Exports` interface to implement
+//!   and pass to `setExports`.
+//! * For hosts, functions calling into an instance, and for imports a `Host`
+//!   interface to implement along with `addToImports` to define an
+//!   implementation as the imports of a module.
+//!
+//! All of them build on a shared `wai.d`. For guests it holds the memory
+//! management of the bindings, and for hosts the `Instance` and `Imports`
+//! interfaces, which are all the bindings need from a wasm runtime.
+//!
+//! Values of the interface are always owned by the GC. Strings and lists of
+//! numbers are passed to the imports of guests without copying them, and
+//! everything lifted is copied into GC arrays, freeing the memory of the
+//! canonical ABI right away.
+
+use heck::*;
+use std::fmt::Write;
+use std::mem;
+use wai_bindgen_gen_core::wai_parser::abi::{
+    AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmType,
+};
+use wai_bindgen_gen_core::{
+    union_case_names, uwrite, uwriteln, wai_parser::*, Direction, Files, Generator, Ns, Source,
+};
+
+#[derive(Default)]
+pub struct D {
+    opts: Opts,
+    sizes: SizeAlign,
+    in_import: bool,
+    // The types of the current interface.
+    types: Source,
+    // The functions of the current interface.
+    funcs: Source,
+    // The methods of the `Exports` interface of guests, or of the `Host`
+    // interface of hosts, along with the body of `addToImports`.
+    exports: Source,
+    defines: Source,
+    return_pointer_area_size: usize,
+    return_pointer_area_align: usize,
+}
+
+#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "structopt", derive(structopt::StructOpt))]
+pub struct Opts {
+    /// Generate bindings for hosts embedding wasm, instead of for guests
+    /// compiled to wasm.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub host: bool,
+}
+
+impl Opts {
+    pub fn build(&self) -> D {
+        let mut r = D::new();
+        r.opts = self.clone();
+        r
+    }
+}
+
+impl D {
+    pub fn new() -> D {
+        D::default()
+    }
+
+    fn type_name(&self, iface: &Interface, ty: &Type) -> String {
+        match ty {
+            Type::Unit => "Unit".to_string(),
+            Type::Bool => "bool".to_string(),
+            Type::U8 => "ubyte".to_string(),
+            Type::U16 => "ushort".to_string(),
+            Type::U32 => "uint".to_string(),
+            Type::U64 => "ulong".to_string(),
+            Type::S8 => "byte".to_string(),
+            Type::S16 => "short".to_string(),
+            Type::S32 => "int".to_string(),
+            Type::S64 => "long".to_string(),
+            Type::Float32 => "float".to_string(),
+            Type::Float64 => "double".to_string(),
+            Type::Char => "dchar".to_string(),
+            Type::String => "string".to_string(),
+            Type::Handle(_) => unimplemented!("resources not supported yet by D bindings"),
+            Type::Id(id) => {
+                let ty = &iface.types[*id];
+                match &ty.name {
+                    Some(name) => d_type_ident(name),
+                    None => self.type_def_name(iface, &ty.kind),
+                }
+            }
+        }
+    }
+
+    /// The structure of the type `kind`, ignoring its name.
+    fn type_def_name(&self, iface: &Interface, kind: &TypeDefKind) -> String {
+        match kind {
+            TypeDefKind::Type(t) => self.type_name(iface, t),
+            TypeDefKind::List(t) => format!("{}[]", self.type_name(iface, t)),
+            TypeDefKind::Tuple(t) if t.types.is_empty() => "Unit".to_string(),
+            TypeDefKind::Tuple(t) => {
+                let types = t
+                    .types
+                    .iter()
+                    .map(|t| self.type_name(iface, t))
+                    .collect::<Vec<_>>();
+                format!("Tuple!({})", types.join(", "))
+            }
+            TypeDefKind::Option(t) => format!("Nullable!({})", self.type_name(iface, t)),
+            TypeDefKind::Expected(e) => format!(
+                "Expected!({}, {})",
+                self.type_name(iface, &e.ok),
+                self.type_name(iface, &e.err)
+            ),
+            TypeDefKind::Record(_)
+            | TypeDefKind::Flags(_)
+            | TypeDefKind::Variant(_)
+            | TypeDefKind::Enum(_)
+            | TypeDefKind::Union(_) => unreachable!("unnamed {:?}", kind),
+            TypeDefKind::Future(_) => todo!("type_def_name for future"),
+            TypeDefKind::Stream(_) => todo!("type_def_name for stream"),
+        }
+    }
+
+    /// The return type of functions returning `ty`, which is `void` instead
+    /// of `Unit`.
+    fn result_name(&self, iface: &Interface, ty: &Type) -> String {
+        match ty {
+            Type::Unit => "void".to_string(),
+            ty => self.type_name(iface, ty),
+        }
+    }
+
+    /// The signature of `func`, taking `params` after the parameters in
+    /// `leading`.
+    fn print_signature(
+        &self,
+        iface: &Interface,
+        leading: &[&str],
+        params: &[String],
+        func: &Function,
+    ) -> String {
+        let params = leading
+            .iter()
+            .map(|param| param.to_string())
+            .chain(
+                params
+                    .iter()
+                    .zip(&func.params)
+                    .map(|(param, (_, ty))| format!("{} {}", self.type_name(iface, ty), param)),
+            )
+            .collect::<Vec<_>>();
+        format!(
+            "{} {}({})",
+            self.result_name(iface, &func.result),
+            d_ident(&func.name.to_mixed_case()),
+            params.join(", ")
+        )
+    }
+
+    fn print_struct(&mut self, name: &str, fields: &[(String, String, &Docs)], docs: &Docs) {
+        ddoc(&mut self.types, docs);
+        if fields.is_empty() {
+            self.types.push_str(&format!("struct {} {{}}\n\n", name));
+            return;
+        }
+        uwriteln!(self.types, "struct {} {{", name);
+        for (field, ty, docs) in fields {
+            ddoc(&mut self.types, docs);
+            uwriteln!(self.types, "{} {};", ty, field);
+        }
+        self.types.push_str("}\n\n");
+    }
+
+    /// Prints a sum type named `name` of a struct for each of `cases`, with
+    /// their payload as its `value`.
+    fn print_sum_type(
+        &mut self,
+        iface: &Interface,
+        name: &str,
+        cases: &[(String, &Type, &Docs)],
+        docs: &Docs,
+    ) {
+        let no_docs = Docs::default();
+        let mut structs = Vec::new();
+        for (case, ty, docs) in cases {
+            let case = case_struct(name, case);
+            let fields = match ty {
+                Type::Unit => Vec::new(),
+                ty => vec![("value".to_string(), self.type_name(iface, ty), &no_docs)],
+            };
+            self.print_struct(&case, &fields, docs);
+            structs.push(case);
+        }
+        ddoc(&mut self.types, docs);
+        uwriteln!(
+            self.types,
+            "alias {} = SumType!({});\n",
+            d_type_ident(name),
+            structs.join(", ")
+        );
+    }
+
+    fn print_alias(&mut self, name: &str, target: &str, docs: &Docs) {
+        ddoc(&mut self.types, docs);
+        uwriteln!(self.types, "alias {} = {};\n", d_type_ident(name), target);
+    }
+
+    fn guest_import(&mut self, iface: &Interface, func: &Function) {
+        let sig = iface.wasm_signature(AbiVariant::GuestImport, func);
+
+        uwriteln!(
+            self.funcs,
+            "pragma(mangle, \"__wai_import_{}_{}\")",
+            iface.name.to_snake_case(),
+            func.name.to_snake_case()
+        );
+        uwriteln!(
+            self.funcs,
+            "@llvmAttr(\"wasm-import-module\", \"{}\") @llvmAttr(\"wasm-import-name\", \"{}\")",
+            iface.name,
+            func.name
+        );
+        let params = sig
+            .params
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| format!("{} arg{}", wasm_type(*ty), i))
+            .collect::<Vec<_>>();
+        uwriteln!(
+            self.funcs,
+            "private extern(C) {} wasmImport{}({});\n",
+            wasm_result(&sig.results),
+            func.name.to_camel_case(),
+            params.join(", ")
+        );
+
+        let mut locals = FunctionBindgen::locals();
+        let params = func
+            .params
+            .iter()
+            .map(|(name, _)| locals.tmp(&d_ident(&name.to_mixed_case())))
+            .collect::<Vec<_>>();
+        ddoc(&mut self.funcs, &func.docs);
+        let signature = self.print_signature(iface, &[], &params, func);
+        uwriteln!(self.funcs, "{} {{", signature);
+        let mut f = FunctionBindgen::new(self, locals, params);
+        iface.call(
+            AbiVariant::GuestImport,
+            LiftLower::LowerArgsLiftResults,
+            func,
+            &mut f,
+        );
+        let body = f.finish();
+        self.funcs.push_str(&body);
+        self.funcs.push_str("}\n\n");
+    }
+
+    fn guest_export(&mut self, iface: &Interface, func: &Function) {
+        let sig = iface.wasm_signature(AbiVariant::GuestExport, func);
+
+        // The implementation of the export is a method of `Exports`.
+        let params = func
+            .params
+            .iter()
+            .map(|(name, _)| d_ident(&name.to_mixed_case()))
+            .collect::<Vec<_>>();
+        ddoc(&mut self.exports, &func.docs);
+        let signature = self.print_signature(iface, &[], &params, func);
+        uwriteln!(self.exports, "{};", signature);
+
+        uwriteln!(self.funcs, "pragma(mangle, \"{}\")", func.name);
+        let params = sig
+            .params
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| format!("{} arg{}", wasm_type(*ty), i))
+            .collect::<Vec<_>>();
+        uwriteln!(
+            self.funcs,
+            "export extern(C) {} wasmExport{}({}) {{",
+            wasm_result(&sig.results),
+            func.name.to_camel_case(),
+            params.join(", ")
+        );
+        let mut locals = FunctionBindgen::locals();
+        let params = (0..sig.params.len())
+            .map(|i| locals.tmp(&format!("arg{}", i)))
+            .collect::<Vec<_>>();
+        let mut f = FunctionBindgen::new(self, locals, params);
+        iface.call(
+            AbiVariant::GuestExport,
+            LiftLower::LiftArgsLowerResults,
+            func,
+            &mut f,
+        );
+        let body = f.finish();
+        self.funcs.push_str(&body);
+        self.funcs.push_str("}\n\n");
+    }
+
+    // Hosts use a reversed mapping of directions to ABI variants, as they
+    // call the exports of guests with the `GuestExport` ABI and implement
+    // their imports with the `GuestImport` ABI.
+    fn host_import(&mut self, iface: &Interface, func: &Function) {
+        let mut locals = FunctionBindgen::locals();
+        let params = func
+            .params
+            .iter()
+            .map(|(name, _)| locals.tmp(&d_ident(&name.to_mixed_case())))
+            .collect::<Vec<_>>();
+        ddoc(&mut self.funcs, &func.docs);
+        let signature = self.print_signature(iface, &["Instance inst"], &params, func);
+        uwriteln!(self.funcs, "{} {{", signature);
+        let mut f = FunctionBindgen::new(self, locals, params);
+        iface.call(
+            AbiVariant::GuestExport,
+            LiftLower::LowerArgsLiftResults,
+            func,
+            &mut f,
+        );
+        let body = f.finish();
+        self.funcs.push_str(&body);
+        self.funcs.push_str("}\n\n");
+    }
+
+    fn host_export(&mut self, iface: &Interface, func: &Function) {
+        let sig = iface.wasm_signature(AbiVariant::GuestImport, func);
+
+        let params = func
+            .params
+            .iter()
+            .map(|(name, _)| d_ident(&name.to_mixed_case()))
+            .collect::<Vec<_>>();
+        ddoc(&mut self.exports, &func.docs);
+        let signature = self.print_signature(iface, &[], &params, func);
+        uwriteln!(self.exports, "{};", signature);
+
+        let val_types = |tys: &[WasmType]| {
+            tys.iter()
+                .map(|ty| format!("ValType.{}", wasm_val_type(*ty)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        uwriteln!(
+            self.defines,
+            "imports.define(\"{}\", \"{}\", [{}], [{}], (Instance inst, Value[] args) {{",
+            iface.name,
+            func.name,
+            val_types(&sig.params),
+            val_types(&sig.results)
+        );
+        let mut locals = FunctionBindgen::locals();
+        let mut params = Vec::new();
+        let mut src = Source::default();
+        for (i, ty) in sig.params.iter().enumerate() {
+            let arg = locals.tmp("arg");
+            uwriteln!(src, "auto {} = args[{}].{};", arg, i, wasm_val_type(*ty));
+            params.push(arg);
+        }
+        let mut f = FunctionBindgen::new(self, locals, params);
+        f.src = src;
+        iface.call(
+            AbiVariant::GuestImport,
+            LiftLower::LiftArgsLowerResults,
+            func,
+            &mut f,
+        );
+        let body = f.finish();
+        self.defines.push_str(&body);
+        self.defines.push_str("});\n");
+    }
+
+    fn print_guest_runtime(&self) -> String {
+        let mut src = Source::default();
+        src.push_str(
+            "
+                // Generated by `wai-bindgen`: the runtime support of the guest bindings of
+                // all interfaces.
+                module wai;
+
+                public import std.sumtype : SumType, match;
+                public import std.typecons : Nullable, Tuple;
+
+                import core.stdc.stdlib : free, realloc;
+                import core.stdc.string : memcpy;
+            ",
+        );
+        src.push_str(COMMON);
+        uwrite!(
+            src,
+            "
+                pragma(mangle, \"canonical_abi_realloc\")
+                export extern(C) int canonicalAbiRealloc(int ptr, int origSize, int align_, int newSize) {{
+                  // Empty allocations get a dangling pointer which is never freed.
+                  if (newSize == 0) {{
+                    return align_;
+                  }}
+                  auto ret = realloc(origSize == 0 ? null : cast(void*) ptr, newSize);
+                  if (ret is null) {{
+                    assert(0, \"out of memory\");
+                  }}
+                  return cast(int) ret;
+                }}
+
+                pragma(mangle, \"canonical_abi_free\")
+                export extern(C) void canonicalAbiFree(int ptr, int size, int align_) {{
+                  if (size != 0) {{
+                    free(cast(void*) ptr);
+                  }}
+                }}
+
+                int abiAlloc(int size, int align_) {{
+                  return canonicalAbiRealloc(0, 0, align_, size);
+                }}
+
+                void abiFree(int address, int size, int align_) {{
+                  canonicalAbiFree(address, size, align_);
+                }}
+
+                /// An allocation freed once an import returns.
+                struct Allocation {{
+                  int address;
+                  int size;
+                  int align_;
+                }}
+
+                private align({align}) __gshared ubyte[{size}] retAreaStorage;
+
+                /// The space for the arguments of imports and the results of exports,
+                /// which are read right away.
+                int retArea() {{
+                  return cast(int) retAreaStorage.ptr;
+                }}
+
+                int loadU8(int address, int offset) {{
+                  return *cast(ubyte*) (address + offset);
+                }}
+
+                int loadS8(int address, int offset) {{
+                  return *cast(byte*) (address + offset);
+                }}
+
+                int loadU16(int address, int offset) {{
+                  return *cast(ushort*) (address + offset);
+                }}
+
+                int loadS16(int address, int offset) {{
+                  return *cast(short*) (address + offset);
+                }}
+
+                int loadI32(int address, int offset) {{
+                  return *cast(int*) (address + offset);
+                }}
+
+                long loadI64(int address, int offset) {{
+                  return *cast(long*) (address + offset);
+                }}
+
+                float loadF32(int address, int offset) {{
+                  return *cast(float*) (address + offset);
+                }}
+
+                double loadF64(int address, int offset) {{
+                  return *cast(double*) (address + offset);
+                }}
+
+                void storeI8(int address, int offset, int value) {{
+                  *cast(byte*) (address + offset) = cast(byte) value;
+                }}
+
+                void storeI16(int address, int offset, int value) {{
+                  *cast(short*) (address + offset) = cast(short) value;
+                }}
+
+                void storeI32(int address, int offset, int value) {{
+                  *cast(int*) (address + offset) = value;
+                }}
+
+                void storeI64(int address, int offset, long value) {{
+                  *cast(long*) (address + offset) = value;
+                }}
+
+                void storeF32(int address, int offset, float value) {{
+                  *cast(float*) (address + offset) = value;
+                }}
+
+                void storeF64(int address, int offset, double value) {{
+                  *cast(double*) (address + offset) = value;
+                }}
+
+                /// Copies `array` into memory which the receiver frees.
+                int lowerArray(T)(const(T)[] array) {{
+                  auto size = cast(int) (array.length * T.sizeof);
+                  auto address = abiAlloc(size, T.alignof);
+                  if (size != 0) {{
+                    memcpy(cast(void*) address, array.ptr, size);
+                  }}
+                  return address;
+                }}
+
+                /// Copies the `len` elements at `address` into an array owned by the GC,
+                /// freeing them if they are `owned`.
+                T[] liftArray(T)(int address, int len, bool owned) {{
+                  auto array = (cast(T*) address)[0 .. len].dup;
+                  if (owned) {{
+                    abiFree(address, cast(int) (len * T.sizeof), T.alignof);
+                  }}
+                  return array;
+                }}
+
+                string liftString(int address, int len, bool owned) {{
+                  auto str = (cast(const(char)*) address)[0 .. len].idup;
+                  if (owned) {{
+                    abiFree(address, len, 1);
+                  }}
+                  return str;
+                }}
+            ",
+            align = self.return_pointer_area_align.max(1),
+            size = self.return_pointer_area_size,
+        );
+        src.into()
+    }
+
+    fn print_host_runtime(&self) -> String {
+        let mut src = Source::default();
+        src.push_str(
+            "
+                // Generated by `wai-bindgen`: the runtime interface of the host bindings of
+                // all interfaces.
+                module wai;
+
+                public import std.sumtype : SumType, match;
+                public import std.typecons : Nullable, Tuple;
+
+                import std.utf : validate;
+            ",
+        );
+        src.push_str(COMMON);
+        src.push_str(
+            "
+                /// The type of a wasm value.
+                enum ValType {
+                  i32,
+                  i64,
+                  f32,
+                  f64,
+                }
+
+                /// A wasm value.
+                struct Value {
+                  ValType type;
+                  union {
+                    int i32;
+                    long i64;
+                    float f32;
+                    double f64;
+                  }
+
+                  this(int value) {
+                    type = ValType.i32;
+                    i32 = value;
+                  }
+
+                  this(long value) {
+                    type = ValType.i64;
+                    i64 = value;
+                  }
+
+                  this(float value) {
+                    type = ValType.f32;
+                    f32 = value;
+                  }
+
+                  this(double value) {
+                    type = ValType.f64;
+                    f64 = value;
+                  }
+                }
+
+                /// What the bindings need from an instance of a wasm module, which is
+                /// also what imports are called with.
+                ///
+                /// Addresses are offsets into the memory exported as `memory` by the
+                /// instance. Loads of narrow integers extend them to 32 bits, and stores
+                /// truncate values to their width.
+                interface Instance {
+                  int loadU8(int address);
+                  int loadS8(int address);
+                  int loadU16(int address);
+                  int loadS16(int address);
+                  int loadI32(int address);
+                  long loadI64(int address);
+                  float loadF32(int address);
+                  double loadF64(int address);
+                  /// Reads the `len` bytes at `address`.
+                  const(ubyte)[] loadBytes(int address, int len);
+                  void storeI8(int address, int value);
+                  void storeI16(int address, int value);
+                  void storeI32(int address, int value);
+                  void storeI64(int address, long value);
+                  void storeF32(int address, float value);
+                  void storeF64(int address, double value);
+                  void storeBytes(int address, const(ubyte)[] bytes);
+                  /// Calls the export `name` of the instance.
+                  Value[] call(string name, Value[] args);
+                }
+
+                /// The imports a module is instantiated with.
+                interface Imports {
+                  /// Defines the import `name` of `module_` as `f`, which is passed the
+                  /// calling instance.
+                  void define(
+                    string module_,
+                    string name,
+                    ValType[] params,
+                    ValType[] results,
+                    Value[] delegate(Instance, Value[]) f,
+                  );
+                }
+
+                int abiAlloc(Instance inst, string realloc, int size, int align_) {
+                  auto ret = inst.call(realloc, [Value(0), Value(0), Value(align_), Value(size)]);
+                  return ret[0].i32;
+                }
+
+                void abiFree(Instance inst, string free, int address, int size, int align_) {
+                  inst.call(free, [Value(address), Value(size), Value(align_)]);
+                }
+
+                /// Copies the string at `address` into a string owned by the GC.
+                string liftString(Instance inst, int address, int len) {
+                  auto str = cast(string) inst.loadBytes(address, len).idup;
+                  validate(str);
+                  return str;
+                }
+
+                int lowerString(Instance inst, string realloc, string str) {
+                  auto address = abiAlloc(inst, realloc, cast(int) str.length, 1);
+                  inst.storeBytes(address, cast(const(ubyte)[]) str);
+                  return address;
+                }
+
+                dchar[] liftChars(Instance inst, int address, int len) {
+                  auto chars = new dchar[len];
+                  foreach (i; 0 .. len) {
+                    chars[i] = cast(dchar) inst.loadI32(address + i * 4);
+                  }
+                  return chars;
+                }
+            ",
+        );
+        src.into()
+    }
+}
+
+/// The definitions shared by the runtimes of guests and hosts.
+const COMMON: &str = "
+    /// The value of `unit`, and of empty tuples.
+    struct Unit {}
+
+    /// The value of an `expected<T, E>` which is a success.
+    struct Ok(T) {
+      T value;
+    }
+
+    /// The value of an `expected<T, E>` which is an error.
+    struct Err(E) {
+      E error;
+    }
+
+    alias Expected(T, E) = SumType!(Ok!T, Err!E);
+
+    float i32ToF32(int bits) {
+      return *cast(float*) &bits;
+    }
+
+    int f32ToI32(float value) {
+      return *cast(int*) &value;
+    }
+
+    double i64ToF64(long bits) {
+      return *cast(double*) &bits;
+    }
+
+    long f64ToI64(double value) {
+      return *cast(long*) &value;
+    }
+";
+
+impl Generator for D {
+    fn preprocess_one(&mut self, iface: &Interface, dir: Direction) {
+        assert!(
+            iface.resources.len() == 0,
+            "resources not supported yet by D bindings"
+        );
+        self.sizes.fill(iface);
+        self.in_import = dir == Direction::Import;
+    }
+
+    fn type_record(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        record: &Record,
+        docs: &Docs,
+    ) {
+        let fields = record
+            .fields
+            .iter()
+            .map(|f| {
+                (
+                    d_ident(&f.name.to_mixed_case()),
+                    self.type_name(iface, &f.ty),
+                    &f.docs,
+                )
+            })
+            .collect::<Vec<_>>();
+        self.print_struct(&d_type_ident(name), &fields, docs);
+    }
+
+    fn type_tuple(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        name: &str,
+        _tuple: &Tuple,
+        docs: &Docs,
+    ) {
+        let target = self.type_def_name(iface, &iface.types[id].kind);
+        self.print_alias(name, &target, docs);
+    }
+
+    fn type_flags(
+        &mut self,
+        _iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        flags: &Flags,
+        docs: &Docs,
+    ) {
+        // Flags are structs of whether each flag is set.
+        let fields = flags
+            .flags
+            .iter()
+            .map(|f| {
+                (
+                    d_ident(&f.name.to_mixed_case()),
+                    "bool".to_string(),
+                    &f.docs,
+                )
+            })
+            .collect::<Vec<_>>();
+        self.print_struct(&d_type_ident(name), &fields, docs);
+    }
+
+    fn type_variant(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        variant: &Variant,
+        docs: &Docs,
+    ) {
+        let cases = variant
+            .cases
+            .iter()
+            .map(|c| (c.name.clone(), &c.ty, &c.docs))
+            .collect::<Vec<_>>();
+        self.print_sum_type(iface, name, &cases, docs);
+    }
+
+    fn type_union(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        union: &Union,
+        docs: &Docs,
+    ) {
+        let cases = union_case_names(iface, union)
+            .into_iter()
+            .zip(&union.cases)
+            .map(|(name, c)| (name, &c.ty, &c.docs))
+            .collect::<Vec<_>>();
+        self.print_sum_type(iface, name, &cases, docs);
+    }
+
+    fn type_option(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        name: &str,
+        _payload: &Type,
+        docs: &Docs,
+    ) {
+        let target = self.type_def_name(iface, &iface.types[id].kind);
+        self.print_alias(name, &target, docs);
+    }
+
+    fn type_expected(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        name: &str,
+        _expected: &Expected,
+        docs: &Docs,
+    ) {
+        let target = self.type_def_name(iface, &iface.types[id].kind);
+        self.print_alias(name, &target, docs);
+    }
+
+    fn type_enum(
+        &mut self,
+        _iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        enum_: &Enum,
+        docs: &Docs,
+    ) {
+        ddoc(&mut self.types, docs);
+        uwriteln!(self.types, "enum {} {{", d_type_ident(name));
+        for case in enum_.cases.iter() {
+            ddoc(&mut self.types, &case.docs);
+            uwriteln!(self.types, "{},", d_ident(&case.name.to_mixed_case()));
+        }
+        self.types.push_str("}\n\n");
+    }
+
+    fn type_resource(&mut self, _iface: &Interface, _ty: ResourceId) {
+        unimplemented!("resources not supported yet by D bindings")
+    }
+
+    fn type_alias(&mut self, iface: &Interface, _id: TypeId, name: &str, ty: &Type, docs: &Docs) {
+        let target = self.type_name(iface, ty);
+        self.print_alias(name, &target, docs);
+    }
+
+    fn type_list(&mut self, iface: &Interface, id: TypeId, name: &str, _ty: &Type, docs: &Docs) {
+        let target = self.type_def_name(iface, &iface.types[id].kind);
+        self.print_alias(name, &target, docs);
+    }
+
+    fn type_builtin(&mut self, iface: &Interface, id: TypeId, name: &str, ty: &Type, docs: &Docs) {
+        self.type_alias(iface, id, name, ty, docs);
+    }
+
+    fn import(&mut self, iface: &Interface, func: &Function) {
+        assert!(!func.is_async, "async not supported yet");
+        if self.opts.host {
+            self.host_import(iface, func);
+        } else {
+            self.guest_import(iface, func);
+        }
+    }
+
+    fn export(&mut self, iface: &Interface, func: &Function) {
+        assert!(!func.is_async, "async not supported yet");
+        if self.opts.host {
+            self.host_export(iface, func);
+        } else {
+            self.guest_export(iface, func);
+        }
+    }
+
+    fn finish_one(&mut self, iface: &Interface, files: &mut Files) {
+        let module = d_ident(&iface.name.to_snake_case());
+        let mut src = Source::default();
+        uwrite!(
+            src,
+            "
+                // Generated by `wai-bindgen` from the `{}` interface.
+                module {};
+
+                public import wai : Unit, Ok, Err, Expected, SumType, match, Nullable, Tuple;
+
+                import wai;
+            ",
+            iface.name,
+            module,
+        );
+        if !self.opts.host && self.in_import {
+            src.push_str("import ldc.attributes : llvmAttr;\n");
+        }
+        src.push_str("\n");
+        src.push_str(&mem::take(&mut self.types));
+
+        let exports = mem::take(&mut self.exports);
+        let defines = mem::take(&mut self.defines);
+        match (self.opts.host, self.in_import) {
+            (false, false) => {
+                src.push_str("/// The implementation of the exports of this interface.\n");
+                src.push_str("interface Exports {\n");
+                src.push_str(&exports);
+                src.push_str("}\n");
+                src.push_str(
+                    "
+                        private __gshared Exports exportsImpl;
+
+                        /// Registers the implementation of the exports of this interface.
+                        void setExports(Exports impl) {
+                          exportsImpl = impl;
+                        }
+
+                    ",
+                );
+            }
+            (true, false) => {
+                uwriteln!(
+                    src,
+                    "/// The functions of the `{}` interface, implemented by the host.",
+                    iface.name
+                );
+                src.push_str("interface Host {\n");
+                src.push_str(&exports);
+                src.push_str("}\n\n");
+                src.push_str("/// Defines the functions of `host` as the imports of `imports`.\n");
+                src.push_str("void addToImports(Imports imports, Host host) {\n");
+                src.push_str(&defines);
+                src.push_str("}\n\n");
+            }
+            _ => {}
+        }
+
+        src.push_str(&mem::take(&mut self.funcs));
+        let src = format!("{}\n", src.trim());
+        files.push(&format!("{}.d", module), src.as_bytes());
+    }
+
+    fn finish_all(&mut self, files: &mut Files) {
+        let src = if self.opts.host {
+            self.print_host_runtime()
+        } else {
+            self.print_guest_runtime()
+        };
+        let src = format!("{}\n", src.trim());
+        files.push("wai.d", src.as_bytes());
+    }
+}
+
+struct FunctionBindgen<'a> {
+    gen: &'a mut D,
+    locals: Ns,
+    src: Source,
+    block_storage: Vec<Source>,
+    blocks: Vec<(String, Vec<String>)>,
+    params: Vec<String>,
+    payloads: Vec<String>,
+    // Whether memory allocated to lower the arguments of an import of a
+    // guest needs to be freed after the call.
+    needs_cleanup: bool,
+}
+
+impl<'a> FunctionBindgen<'a> {
+    /// The names of locals, reserving those the generated code relies on.
+    fn locals() -> Ns {
+        let mut locals = Ns::default();
+        for name in [
+            "inst",
+            "args",
+            "imports",
+            "host",
+            "cleanup",
+            "allocation",
+            "exportsImpl",
+            "match",
+            "retArea",
+            "abiAlloc",
+            "abiFree",
+            "lowerArray",
+            "liftArray",
+            "liftString",
+            "lowerString",
+            "liftChars",
+        ] {
+            locals.insert(name).unwrap();
+        }
+        locals
+    }
+
+    fn new(gen: &'a mut D, locals: Ns, params: Vec<String>) -> FunctionBindgen<'a> {
+        FunctionBindgen {
+            gen,
+            locals,
+            src: Source::default(),
+            block_storage: Vec::new(),
+            blocks: Vec::new(),
+            params,
+            payloads: Vec::new(),
+            needs_cleanup: false,
+        }
+    }
+
+    fn finish(self) -> String {
+        let mut body = String::new();
+        if self.needs_cleanup {
+            body.push_str("Allocation[] cleanup;\n");
+        }
+        body.push_str(&self.src);
+        body
+    }
+
+    fn host(&self) -> bool {
+        self.gen.opts.host
+    }
+
+    fn load(&mut self, ty: &str, offset: i32, operands: &[String], results: &mut Vec<String>) {
+        let tmp = self.locals.tmp("load");
+        if self.host() {
+            uwriteln!(
+                self.src,
+                "auto {} = inst.load{}({} + {});",
+                tmp,
+                ty,
+                operands[0],
+                offset
+            );
+        } else {
+            uwriteln!(
+                self.src,
+                "auto {} = load{}({}, {});",
+                tmp,
+                ty,
+                operands[0],
+                offset
+            );
+        }
+        results.push(tmp);
+    }
+
+    fn store(&mut self, ty: &str, offset: i32, operands: &[String]) {
+        if self.host() {
+            uwriteln!(
+                self.src,
+                "inst.store{}({} + {}, {});",
+                ty,
+                operands[1],
+                offset,
+                operands[0]
+            );
+        } else {
+            uwriteln!(
+                self.src,
+                "store{}({}, {}, {});",
+                ty,
+                operands[1],
+                offset,
+                operands[0]
+            );
+        }
+    }
+
+    fn alloc(&self, size: &str, align: usize, realloc: &str) -> String {
+        if self.host() {
+            format!("abiAlloc(inst, \"{}\", {}, {})", realloc, size, align)
+        } else {
+            format!("abiAlloc({}, {})", size, align)
+        }
+    }
+
+    fn free(&mut self, address: &str, size: &str, align: usize, free: &str) {
+        if self.host() {
+            uwriteln!(
+                self.src,
+                "abiFree(inst, \"{}\", {}, {}, {});",
+                free,
+                address,
+                size,
+                align
+            );
+        } else {
+            uwriteln!(self.src, "abiFree({}, {}, {});", address, size, align);
+        }
+    }
+
+    /// Frees the allocation at `address` once the import it's lowered for
+    /// returns, unless it's lowered for the other side to own with
+    /// `realloc`.
+    fn cleanup(&mut self, address: &str, size: &str, align: usize, realloc: Option<&str>) {
+        if realloc.is_none() {
+            self.needs_cleanup = true;
+            uwriteln!(
+                self.src,
+                "cleanup ~= Allocation({}, {}, {});",
+                address,
+                size,
+                align
+            );
+        }
+    }
+
+    /// Prints the body of a branch, assigning its results to `results`.
+    fn branch(&mut self, block: &str, block_results: &[String], results: &[String]) {
+        self.src.push_str(block);
+        for (result, value) in results.iter().zip(block_results) {
+            uwriteln!(self.src, "{} = {};", result, value);
+        }
+    }
+
+    fn declare_results(&mut self, types: &[WasmType]) -> Vec<String> {
+        types
+            .iter()
+            .map(|ty| {
+                let result = self.locals.tmp("variant");
+                uwriteln!(self.src, "{} {} = 0;", wasm_type(*ty), result);
+                result
+            })
+            .collect()
+    }
+
+    /// Lowers the sum type `op` with a `match` over the structs of its
+    /// cases, along with the field of their payload if they have one.
+    fn lower_cases(
+        &mut self,
+        op: &str,
+        cases: &[(String, Option<&str>)],
+        result_types: &[WasmType],
+    ) -> Vec<String> {
+        let blocks = self
+            .blocks
+            .drain(self.blocks.len() - cases.len()..)
+            .collect::<Vec<_>>();
+        let payloads = self
+            .payloads
+            .drain(self.payloads.len() - cases.len()..)
+            .collect::<Vec<_>>();
+        let results = self.declare_results(result_types);
+        for (i, ((ty, field), ((block, block_results), payload))) in cases
+            .iter()
+            .zip(blocks.into_iter().zip(payloads))
+            .enumerate()
+        {
+            let case = self.locals.tmp("arm");
+            if i == 0 {
+                uwriteln!(self.src, "{}.match!(({} {}) {{", op, ty, case);
+            } else {
+                uwriteln!(self.src, "}}, ({} {}) {{", ty, case);
+            }
+            if let Some(field) = field {
+                uwriteln!(self.src, "auto {} = {}.{};", payload, case, field);
+            }
+            self.branch(&block, &block_results, &results);
+        }
+        self.src.push_str("});\n");
+        results
+    }
+
+    /// Lifts the discriminant `op` into a value of `ty`, assigning it what
+    /// `ctor` returns for the index of each case and its payload, if
+    /// anything.
+    fn lift_cases(
+        &mut self,
+        op: &str,
+        ty: &str,
+        cases: usize,
+        ctor: impl Fn(usize, &str) -> Option<String>,
+    ) -> String {
+        let blocks = self
+            .blocks
+            .drain(self.blocks.len() - cases..)
+            .collect::<Vec<_>>();
+        let result = self.locals.tmp("variant");
+        uwriteln!(self.src, "{} {};", ty, result);
+        for (i, (block, block_results)) in blocks.into_iter().enumerate() {
+            // The other side is trusted to pass a valid discriminant, so the
+            // last case takes all others.
+            if cases == 1 {
+                self.src.push_str("{\n");
+            } else if i == 0 {
+                uwriteln!(self.src, "if ({} == 0) {{", op);
+            } else if i == cases - 1 {
+                self.src.push_str("} else {\n");
+            } else {
+                uwriteln!(self.src, "}} else if ({} == {}) {{", op, i);
+            }
+            let values = ctor(i, &block_results[0]).into_iter().collect::<Vec<_>>();
+            let results = if values.is_empty() {
+                Vec::new()
+            } else {
+                vec![result.clone()]
+            };
+            self.branch(&block, &values, &results);
+        }
+        self.src.push_str("}\n");
+        result
+    }
+
+    /// The structs of the cases of the sum type `name`, along with the
+    /// field of their payload if they have one.
+    fn sum_cases<'b>(
+        name: &str,
+        cases: impl Iterator<Item = (String, &'b Type)>,
+    ) -> Vec<(String, Option<&'static str>)> {
+        cases
+            .map(|(case, ty)| {
+                let field = if *ty == Type::Unit {
+                    None
+                } else {
+                    Some("value")
+                };
+                (case_struct(name, &case), field)
+            })
+            .collect()
+    }
+
+    fn lift_sum(
+        &mut self,
+        iface: &Interface,
+        op: &str,
+        ty: TypeId,
+        cases: &[(String, Option<&str>)],
+    ) -> String {
+        let ty = self.gen.type_name(iface, &Type::Id(ty));
+        self.lift_cases(op, &ty, cases.len(), |i, payload| match &cases[i] {
+            (case, Some(_)) => Some(format!("{}({})", case, payload)),
+            (case, None) => Some(format!("{}()", case)),
+        })
+    }
+}
+
+impl Bindgen for FunctionBindgen<'_> {
+    type Operand = String;
+
+    fn sizes(&self) -> &SizeAlign {
+        &self.gen.sizes
+    }
+
+    fn push_block(&mut self) {
+        let prev = mem::take(&mut self.src);
+        self.block_storage.push(prev);
+    }
+
+    fn finish_block(&mut self, operands: &mut Vec<String>) {
+        let to_restore = self.block_storage.pop().unwrap();
+        let src = mem::replace(&mut self.src, to_restore);
+        self.blocks.push((src.into(), mem::take(operands)));
+    }
+
+    fn return_pointer(&mut self, _iface: &Interface, size: usize, align: usize) -> String {
+        assert!(!self.host());
+        self.gen.return_pointer_area_size = self.gen.return_pointer_area_size.max(size);
+        self.gen.return_pointer_area_align = self.gen.return_pointer_area_align.max(align);
+        let address = self.locals.tmp("address");
+        uwriteln!(self.src, "auto {} = retArea();", address);
+        address
+    }
+
+    fn is_list_canonical(&self, _iface: &Interface, ty: &Type) -> bool {
+        // Arrays of numbers and `dchar`s have the layout of the canonical
+        // ABI in the memory of guests.
+        !self.host()
+            && matches!(
+                ty,
+                Type::U8
+                    | Type::U16
+                    | Type::U32
+                    | Type::U64
+                    | Type::S8
+                    | Type::S16
+                    | Type::S32
+                    | Type::S64
+                    | Type::Float32
+                    | Type::Float64
+                    | Type::Char
+            )
+    }
+
+    fn emit(
+        &mut self,
+        iface: &Interface,
+        inst: &Instruction<'_>,
+        operands: &mut Vec<String>,
+        results: &mut Vec<String>,
+    ) {
+        match inst {
+            Instruction::GetArg { nth } => results.push(self.params[*nth].clone()),
+            Instruction::I32Const { val } => results.push(val.to_string()),
+            Instruction::ConstZero { tys } => {
+                for ty in tys.iter() {
+                    match ty {
+                        WasmType::I32 => results.push("0".to_string()),
+                        WasmType::I64 => results.push("0L".to_string()),
+                        WasmType::F32 => results.push("0.0f".to_string()),
+                        WasmType::F64 => results.push("0.0".to_string()),
+                    }
+                }
+            }
+
+            Instruction::I32FromU8
+            | Instruction::I32FromS8
+            | Instruction::I32FromU16
+            | Instruction::I32FromS16
+            | Instruction::I32FromU32
+            | Instruction::I32FromChar => results.push(format!("cast(int) {}", operands[0])),
+            Instruction::I64FromU64 => results.push(format!("cast(long) {}", operands[0])),
+            Instruction::I32FromS32
+            | Instruction::I64FromS64
+            | Instruction::S32FromI32
+            | Instruction::S64FromI64
+            | Instruction::Float32FromF32
+            | Instruction::Float64FromF64
+            | Instruction::F32FromFloat32
+            | Instruction::F64FromFloat64 => results.push(operands.pop().unwrap()),
+
+            // Only the low bits of integers coming from wasm are meaningful.
+            Instruction::U8FromI32 => results.push(format!("cast(ubyte) {}", operands[0])),
+            Instruction::S8FromI32 => results.push(format!("cast(byte) {}", operands[0])),
+            Instruction::U16FromI32 => results.push(format!("cast(ushort) {}", operands[0])),
+            Instruction::S16FromI32 => results.push(format!("cast(short) {}", operands[0])),
+            Instruction::U32FromI32 => results.push(format!("cast(uint) {}", operands[0])),
+            Instruction::U64FromI64 => results.push(format!("cast(ulong) {}", operands[0])),
+            Instruction::CharFromI32 => results.push(format!("cast(dchar) {}", operands[0])),
+
+            Instruction::Bitcasts { casts } => {
+                for (cast, op) in casts.iter().zip(operands) {
+                    let op = match cast {
+                        Bitcast::I32ToF32 => format!("i32ToF32({})", op),
+                        Bitcast::F32ToI32 => format!("f32ToI32({})", op),
+                        Bitcast::I64ToF64 => format!("i64ToF64({})", op),
+                        Bitcast::F64ToI64 => format!("f64ToI64({})", op),
+                        Bitcast::I32ToI64 => format!("cast(long) {}", op),
+                        Bitcast::I64ToI32 => format!("cast(int) {}", op),
+                        Bitcast::F32ToI64 => format!("cast(long) f32ToI32({})", op),
+                        Bitcast::I64ToF32 => format!("i32ToF32(cast(int) {})", op),
+                        Bitcast::None => op.clone(),
+                    };
+                    results.push(op);
+                }
+            }
+
+            Instruction::UnitLower => {}
+            Instruction::UnitLift => results.push("Unit()".to_string()),
+            Instruction::BoolFromI32 => results.push(format!("({} != 0)", operands[0])),
+            Instruction::I32FromBool => results.push(format!("({} ? 1 : 0)", operands[0])),
+
+            Instruction::I32FromBorrowedHandle { .. }
+            | Instruction::I32FromOwnedHandle { .. }
+            | Instruction::HandleOwnedFromI32 { .. }
+            | Instruction::HandleBorrowedFromI32 { .. } => {
+                unimplemented!("resources not supported yet by D bindings")
+            }
+
+            Instruction::RecordLower { record, .. } => {
+                if !record.fields.is_empty() {
+                    let op = self.locals.tmp("record");
+                    uwriteln!(self.src, "auto {} = {};", op, operands[0]);
+                    for field in record.fields.iter() {
+                        results.push(format!("{}.{}", op, d_ident(&field.name.to_mixed_case())));
+                    }
+                }
+            }
+            Instruction::RecordLift { name, .. } => {
+                results.push(format!("{}({})", d_type_ident(name), operands.join(", ")));
+            }
+
+            Instruction::TupleLower { tuple, .. } => {
+                if !tuple.types.is_empty() {
+                    let op = self.locals.tmp("tuple");
+                    uwriteln!(self.src, "auto {} = {};", op, operands[0]);
+                    for i in 0..tuple.types.len() {
+                        results.push(format!("{}[{}]", op, i));
+                    }
+                }
+            }
+            Instruction::TupleLift { ty, .. } => {
+                let ty = self.gen.type_name(iface, &Type::Id(*ty));
+                if operands.is_empty() {
+                    results.push("Unit()".to_string());
+                } else {
+                    results.push(format!("{}({})", ty, operands.join(", ")));
+                }
+            }
+
+            Instruction::FlagsLower { flags, .. } => {
+                let op = self.locals.tmp("flags");
+                uwriteln!(self.src, "auto {} = {};", op, operands[0]);
+                for word in 0..flags.repr().count() {
+                    let bits = flags
+                        .flags
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| i / 32 == word)
+                        .map(|(i, flag)| {
+                            format!(
+                                "({}.{} ? 1 << {} : 0)",
+                                op,
+                                d_ident(&flag.name.to_mixed_case()),
+                                i % 32
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    if bits.is_empty() {
+                        results.push("0".to_string());
+                    } else {
+                        results.push(bits.join(" | "));
+                    }
+                }
+            }
+            Instruction::FlagsLift { flags, name, .. } => {
+                let fields = flags
+                    .flags
+                    .iter()
+                    .enumerate()
+                    .map(|(i, _)| format!("({} & (1 << {})) != 0", operands[i / 32], i % 32))
+                    .collect::<Vec<_>>();
+                results.push(format!("{}({})", d_type_ident(name), fields.join(", ")));
+            }
+
+            Instruction::VariantPayloadName => {
+                let name = self.locals.tmp("payload");
+                results.push(name.clone());
+                self.payloads.push(name);
+            }
+
+            Instruction::VariantLower {
+                variant,
+                name,
+                results: result_types,
+                ..
+            } => {
+                let cases =
+                    Self::sum_cases(name, variant.cases.iter().map(|c| (c.name.clone(), &c.ty)));
+                let op = self.locals.tmp("variant");
+                uwriteln!(self.src, "auto {} = {};", op, operands[0]);
+                results.extend(self.lower_cases(&op, &cases, result_types));
+            }
+            Instruction::VariantLift {
+                variant, name, ty, ..
+            } => {
+                let cases =
+                    Self::sum_cases(name, variant.cases.iter().map(|c| (c.name.clone(), &c.ty)));
+                let result = self.lift_sum(iface, &operands[0], *ty, &cases);
+                results.push(result);
+            }
+
+            Instruction::UnionLower {
+                union,
+                name,
+                results: result_types,
+                ..
+            } => {
+                let cases = Self::sum_cases(
+                    name,
+                    union_case_names(iface, union)
+                        .into_iter()
+                        .zip(union.cases.iter().map(|c| &c.ty)),
+                );
+                let op = self.locals.tmp("union");
+                uwriteln!(self.src, "auto {} = {};", op, operands[0]);
+                results.extend(self.lower_cases(&op, &cases, result_types));
+            }
+            Instruction::UnionLift { union, name, ty } => {
+                let cases = Self::sum_cases(
+                    name,
+                    union_case_names(iface, union)
+                        .into_iter()
+                        .zip(union.cases.iter().map(|c| &c.ty)),
+                );
+                let result = self.lift_sum(iface, &operands[0], *ty, &cases);
+                results.push(result);
+            }
+
+            Instruction::EnumLower { .. } => results.push(format!("cast(int) {}", operands[0])),
+            Instruction::EnumLift { name, .. } => {
+                results.push(format!("cast({}) {}", d_type_ident(name), operands[0]))
+            }
+
+            Instruction::OptionLower {
+                results: result_types,
+                ..
+            } => {
+                let (some, some_results) = self.blocks.pop().unwrap();
+                let (none, none_results) = self.blocks.pop().unwrap();
+                let some_payload = self.payloads.pop().unwrap();
+                let _none_payload = self.payloads.pop().unwrap();
+                let op = self.locals.tmp("option");
+                uwriteln!(self.src, "auto {} = {};", op, operands[0]);
+                let option_results = self.declare_results(result_types);
+                uwriteln!(self.src, "if ({}.isNull) {{", op);
+                self.branch(&none, &none_results, &option_results);
+                self.src.push_str("} else {\n");
+                uwriteln!(self.src, "auto {} = {}.get;", some_payload, op);
+                self.branch(&some, &some_results, &option_results);
+                self.src.push_str("}\n");
+                results.extend(option_results);
+            }
+            Instruction::OptionLift { ty, .. } => {
+                let ty = self.gen.type_name(iface, &Type::Id(*ty));
+                let result = self.lift_cases(&operands[0], &ty, 2, |i, payload| match i {
+                    // The declared value is already null.
+                    0 => None,
+                    _ => Some(payload.to_string()),
+                });
+                results.push(result);
+            }
+
+            Instruction::ExpectedLower {
+                expected,
+                results: result_types,
+                ..
+            } => {
+                let cases = [
+                    (
+                        format!("Ok!({})", self.gen.type_name(iface, &expected.ok)),
+                        Some("value"),
+                    ),
+                    (
+                        format!("Err!({})", self.gen.type_name(iface, &expected.err)),
+                        Some("error"),
+                    ),
+                ];
+                let op = self.locals.tmp("expected");
+                uwriteln!(self.src, "auto {} = {};", op, operands[0]);
+                results.extend(self.lower_cases(&op, &cases, result_types));
+            }
+            Instruction::ExpectedLift { expected, ty } => {
+                let ty = self.gen.type_name(iface, &Type::Id(*ty));
+                let ok = self.gen.type_name(iface, &expected.ok);
+                let err = self.gen.type_name(iface, &expected.err);
+                let result = self.lift_cases(&operands[0], &ty, 2, |i, payload| match i {
+                    0 => Some(format!("Ok!({})({})", ok, payload)),
+                    _ => Some(format!("Err!({})({})", err, payload)),
+                });
+                results.push(result);
+            }
+
+            // Guests pass the arrays of imports as they are, and copy the
+            // ones they return.
+            Instruction::ListCanonLower { realloc, .. } | Instruction::StringLower { realloc }
+                if !self.host() =>
+            {
+                let vec = self.locals.tmp("vec");
+                uwriteln!(self.src, "auto {} = {};", vec, operands[0]);
+                match realloc {
+                    None => results.push(format!("cast(int) {}.ptr", vec)),
+                    Some(_) => results.push(format!("lowerArray({})", vec)),
+                }
+                results.push(format!("cast(int) {}.length", vec));
+            }
+            Instruction::ListCanonLower { .. } => unreachable!("no lists are canonical"),
+            Instruction::StringLower { realloc } => {
+                let str = self.locals.tmp("str");
+                let address = self.locals.tmp("address");
+                uwriteln!(self.src, "auto {} = {};", str, operands[0]);
+                uwriteln!(
+                    self.src,
+                    "auto {} = lowerString(inst, \"{}\", {});",
+                    address,
+                    realloc.unwrap(),
+                    str
+                );
+                results.push(address);
+                results.push(format!("cast(int) {}.length", str));
+            }
+            Instruction::ListLower { element, realloc } => {
+                let (body, body_results) = self.blocks.pop().unwrap();
+                assert!(body_results.is_empty());
+                let depth = self.block_storage.len() + 1;
+                let vec = self.locals.tmp("vec");
+                let address = self.locals.tmp("address");
+                let size = self.gen.sizes.size(element);
+                let align = self.gen.sizes.align(element);
+                let bytes = format!("cast(int) ({}.length * {})", vec, size);
+                uwriteln!(self.src, "auto {} = {};", vec, operands[0]);
+                let alloc = self.alloc(&bytes, align, realloc.unwrap_or_default());
+                uwriteln!(self.src, "auto {} = {};", address, alloc);
+                self.cleanup(&address, &bytes, align, *realloc);
+                uwriteln!(self.src, "foreach (i{0}, e{0}; {1}) {{", depth, vec);
+                uwriteln!(
+                    self.src,
+                    "auto base{0} = {1} + cast(int) i{0} * {2};",
+                    depth,
+                    address,
+                    size
+                );
+                self.src.push_str(&body);
+                self.src.push_str("}\n");
+                results.push(address);
+                results.push(format!("cast(int) {}.length", vec));
+            }
+
+            Instruction::ListCanonLift { element, free, .. } if !self.host() => {
+                let result = self.locals.tmp("items");
+                uwriteln!(
+                    self.src,
+                    "auto {} = liftArray!({})({}, {}, {});",
+                    result,
+                    self.gen.type_name(iface, element),
+                    operands[0],
+                    operands[1],
+                    free.is_some()
+                );
+                results.push(result);
+            }
+            // Lists of chars are always lifted as canonical lists.
+            Instruction::ListCanonLift { element, free, .. } => {
+                assert_eq!(**element, Type::Char);
+                let address = self.locals.tmp("address");
+                let len = self.locals.tmp("len");
+                let result = self.locals.tmp("items");
+                uwriteln!(self.src, "auto {} = {};", address, operands[0]);
+                uwriteln!(self.src, "auto {} = {};", len, operands[1]);
+                uwriteln!(
+                    self.src,
+                    "auto {} = liftChars(inst, {}, {});",
+                    result,
+                    address,
+                    len
+                );
+                if let Some(free) = free {
+                    self.free(&address, &format!("{} * 4", len), 4, free);
+                }
+                results.push(result);
+            }
+            Instruction::StringLift { free } if !self.host() => {
+                let result = self.locals.tmp("str");
+                uwriteln!(
+                    self.src,
+                    "auto {} = liftString({}, {}, {});",
+                    result,
+                    operands[0],
+                    operands[1],
+                    free.is_some()
+                );
+                results.push(result);
+            }
+            Instruction::StringLift { free } => {
+                let address = self.locals.tmp("address");
+                let len = self.locals.tmp("len");
+                let result = self.locals.tmp("str");
+                uwriteln!(self.src, "auto {} = {};", address, operands[0]);
+                uwriteln!(self.src, "auto {} = {};", len, operands[1]);
+                uwriteln!(
+                    self.src,
+                    "auto {} = liftString(inst, {}, {});",
+                    result,
+                    address,
+                    len
+                );
+                if let Some(free) = free {
+                    self.free(&address, &len, 1, free);
+                }
+                results.push(result);
+            }
+            Instruction::ListLift { element, free, .. } => {
+                let (body, body_results) = self.blocks.pop().unwrap();
+                let depth = self.block_storage.len() + 1;
+                let address = self.locals.tmp("address");
+                let len = self.locals.tmp("len");
+                let result = self.locals.tmp("items");
+                let size = self.gen.sizes.size(element);
+                let align = self.gen.sizes.align(element);
+                let ty = self.gen.type_name(iface, element);
+                uwriteln!(self.src, "auto {} = {};", address, operands[0]);
+                uwriteln!(self.src, "auto {} = {};", len, operands[1]);
+                uwriteln!(self.src, "auto {} = new {}[{}];", result, ty, len);
+                uwriteln!(self.src, "foreach (i{}; 0 .. {}) {{", depth, len);
+                uwriteln!(
+                    self.src,
+                    "auto base{0} = {1} + i{0} * {2};",
+                    depth,
+                    address,
+                    size
+                );
+                self.src.push_str(&body);
+                uwriteln!(self.src, "{}[i{}] = {};", result, depth, body_results[0]);
+                self.src.push_str("}\n");
+                if let Some(free) = free {
+                    self.free(&address, &format!("{} * {}", len, size), align, free);
+                }
+                results.push(result);
+            }
+
+            Instruction::IterElem { .. } => {
+                results.push(format!("e{}", self.block_storage.len()));
+            }
+            Instruction::IterBasePointer => {
+                results.push(format!("base{}", self.block_storage.len()));
+            }
+
+            Instruction::CallWasm { name, sig, .. } if self.host() => {
+                let args = operands
+                    .iter()
+                    .map(|op| format!("Value({})", op))
+                    .collect::<Vec<_>>();
+                let call = format!("inst.call(\"{}\", [{}])", name, args.join(", "));
+                if sig.results.is_empty() {
+                    uwriteln!(self.src, "{};", call);
+                    return;
+                }
+                let ret = self.locals.tmp("ret");
+                uwriteln!(self.src, "auto {} = {};", ret, call);
+                for (i, ty) in sig.results.iter().enumerate() {
+                    results.push(format!("{}[{}].{}", ret, i, wasm_val_type(*ty)));
+                }
+            }
+            Instruction::CallWasm { name, sig, .. } => {
+                let call = format!(
+                    "wasmImport{}({})",
+                    name.to_camel_case(),
+                    operands.join(", ")
+                );
+                match sig.results.len() {
+                    0 => uwriteln!(self.src, "{};", call),
+                    1 => {
+                        let ret = self.locals.tmp("ret");
+                        uwriteln!(self.src, "auto {} = {};", ret, call);
+                        results.push(ret);
+                    }
+                    _ => unimplemented!("multi-value return not supported"),
+                }
+                if self.needs_cleanup {
+                    self.src.push_str("foreach (allocation; cleanup) {\n");
+                    self.src.push_str(
+                        "abiFree(allocation.address, allocation.size, allocation.align_);\n",
+                    );
+                    self.src.push_str("}\n");
+                }
+            }
+
+            Instruction::CallInterface { func, .. } => {
+                let call = format!(
+                    "{}.{}({})",
+                    if self.host() { "host" } else { "exportsImpl" },
+                    d_ident(&func.name.to_mixed_case()),
+                    operands.join(", ")
+                );
+                if func.result == Type::Unit {
+                    uwriteln!(self.src, "{};", call);
+                    results.push("Unit()".to_string());
+                } else {
+                    let ret = self.locals.tmp("ret");
+                    uwriteln!(self.src, "auto {} = {};", ret, call);
+                    results.push(ret);
+                }
+            }
+
+            // The imports of hosts return their results as values.
+            Instruction::Return { .. } if self.host() && !self.gen.in_import => {
+                let values = operands
+                    .iter()
+                    .map(|op| format!("Value({})", op))
+                    .collect::<Vec<_>>();
+                if values.is_empty() {
+                    self.src.push_str("return null;\n");
+                } else {
+                    uwriteln!(self.src, "return [{}];", values.join(", "));
+                }
+            }
+            Instruction::Return { amt, func } => match amt {
+                0 => {}
+                // Functions return `void` instead of `Unit`.
+                1 if func.result == Type::Unit && self.gen.in_import => {}
+                1 => uwriteln!(self.src, "return {};", operands[0]),
+                _ => unimplemented!("multi-value return not supported"),
+            },
+
+            Instruction::I32Load { offset } => self.load("I32", *offset, operands, results),
+            Instruction::I32Load8U { offset } => self.load("U8", *offset, operands, results),
+            Instruction::I32Load8S { offset } => self.load("S8", *offset, operands, results),
+            Instruction::I32Load16U { offset } => self.load("U16", *offset, operands, results),
+            Instruction::I32Load16S { offset } => self.load("S16", *offset, operands, results),
+            Instruction::I64Load { offset } => self.load("I64", *offset, operands, results),
+            Instruction::F32Load { offset } => self.load("F32", *offset, operands, results),
+            Instruction::F64Load { offset } => self.load("F64", *offset, operands, results),
+            Instruction::I32Store { offset } => self.store("I32", *offset, operands),
+            Instruction::I32Store8 { offset } => self.store("I8", *offset, operands),
+            Instruction::I32Store16 { offset } => self.store("I16", *offset, operands),
+            Instruction::I64Store { offset } => self.store("I64", *offset, operands),
+            Instruction::F32Store { offset } => self.store("F32", *offset, operands),
+            Instruction::F64Store { offset } => self.store("F64", *offset, operands),
+
+            Instruction::Malloc {
+                realloc,
+                size,
+                align,
+            } => {
+                let address = self.locals.tmp("address");
+                let alloc = self.alloc(&size.to_string(), *align, realloc);
+                uwriteln!(self.src, "auto {} = {};", address, alloc);
+                results.push(address);
+            }
+            Instruction::Free { free, size, align } => {
+                let address = operands[0].clone();
+                self.free(&address, &size.to_string(), *align, free);
+            }
+
+            Instruction::CallWasmAsyncImport { .. }
+            | Instruction::CallWasmAsyncExport { .. }
+            | Instruction::ReturnAsyncExport { .. }
+            | Instruction::ReturnAsyncImport { .. } => {
+                unimplemented!("async not supported yet")
+            }
+        }
+    }
+}
+
+fn ddoc(src: &mut Source, docs: &Docs) {
+    if let Some(docs) = &docs.contents {
+        for line in docs.trim().lines() {
+            uwriteln!(src, "/// {}", line.trim());
+        }
+    }
+}
+
+/// The struct of the case `case` of the sum type `name`.
+fn case_struct(name: &str, case: &str) -> String {
+    format!("{}{}", name.to_camel_case(), case.to_camel_case())
+}
+
+/// Appends an underscore to names which are keywords in D.
+fn d_ident(name: &str) -> String {
+    match name {
+        "abstract" | "alias" | "align" | "asm" | "assert" | "auto" | "body" | "bool" | "break"
+        | "byte" | "case" | "cast" | "catch" | "char" | "class" | "const" | "continue"
+        | "dchar" | "debug" | "default" | "delegate" | "delete" | "deprecated" | "do"
+        | "double" | "else" | "enum" | "export" | "extern" | "false" | "final" | "finally"
+        | "float" | "for" | "foreach" | "function" | "goto" | "if" | "immutable" | "import"
+        | "in" | "inout" | "int" | "interface" | "invariant" | "is" | "lazy" | "long" | "macro"
+        | "mixin" | "module" | "new" | "nothrow" | "null" | "out" | "override" | "package"
+        | "pragma" | "private" | "protected" | "public" | "pure" | "real" | "ref" | "return"
+        | "scope" | "shared" | "short" | "static" | "struct" | "super" | "switch"
+        | "synchronized" | "template" | "this" | "throw" | "true" | "try" | "typeid" | "typeof"
+        | "ubyte" | "uint" | "ulong" | "union" | "unittest" | "ushort" | "version" | "void"
+        | "wchar" | "while" | "with" => format!("{}_", name),
+        _ => name.to_string(),
+    }
+}
+
+/// The name of the type `name`, renamed if it would shadow one of the types
+/// the bindings rely on.
+fn d_type_ident(name: &str) -> String {
+    let name = name.to_camel_case();
+    match name.as_str() {
+        "Unit" | "Ok" | "Err" | "Expected" | "SumType" | "Nullable" | "Tuple" | "Exports"
+        | "Host" | "Instance" | "Imports" | "Value" | "ValType" | "Allocation" => {
+            format!("{}Type", name)
+        }
+        _ => name,
+    }
+}
+
+fn wasm_type(ty: WasmType) -> &'static str {
+    match ty {
+        WasmType::I32 => "int",
+        WasmType::I64 => "long",
+        WasmType::F32 => "float",
+        WasmType::F64 => "double",
+    }
+}
+
+fn wasm_result(results: &[WasmType]) -> &'static str {
+    match results {
+        [] => "void",
+        [ty] => wasm_type(*ty),
+        _ => unimplemented!("multi-value return not supported"),
+    }
+}
+
+/// The member of `ValType`, and field of `Value`, of `ty`.
+fn wasm_val_type(ty: WasmType) -> &'static str {
+    match ty {
+        WasmType::I32 => "i32",
+        WasmType::I64 => "i64",
+        WasmType::F32 => "f32",
+        WasmType::F64 => "f64",
+    }
+}
@@ -0,0 +1,172 @@
+use std::path::Path;
+use wai_bindgen_gen_core::{wai_parser::Interface, Files, Generator};
+
+fn generate(host: bool, imports: &[Interface], exports: &[Interface]) -> Files {
+    let mut files = Files::default();
+    wai_bindgen_gen_d::Opts { host }
+        .build()
+        .generate_all(imports, exports, &mut files);
+    files
+}
+
+fn file<'a>(files: &'a Files, name: &str) -> &'a str {
+    let (_, contents) = files.iter().find(|(n, _)| *n == name).unwrap();
+    std::str::from_utf8(contents).unwrap()
+}
+
+/// The interface the tests below generate bindings for.
+fn greeter() -> Interface {
+    Interface::parse(
+        "greeter",
+        "
+            record point { x: u32, y: u32 }
+            variant shape { dot(point), empty }
+            greet: func(name: string, at: shape) -> list<string>
+        ",
+    )
+    .unwrap()
+}
+
+#[test]
+fn codegen_interfaces() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/codegen");
+    for entry in std::fs::read_dir(&root).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|s| s.to_str()) != Some("wai") {
+            continue;
+        }
+        let iface = Interface::parse_file(&path).unwrap();
+        if iface.resources.len() > 0 || iface.functions.iter().any(|f| f.is_async) {
+            continue;
+        }
+        for host in [false, true] {
+            for (imports, exports) in [(vec![iface.clone()], vec![]), (vec![], vec![iface.clone()])]
+            {
+                let files = generate(host, &imports, &exports);
+                let names = files.iter().map(|(n, _)| n).collect::<Vec<_>>();
+                assert_eq!(names.len(), 2, "{}: {:?}", path.display(), names);
+                assert!(file(&files, "wai.d").contains("module wai;"));
+            }
+        }
+    }
+}
+
+#[test]
+fn guest() {
+    let iface = greeter();
+
+    let files = generate(false, std::slice::from_ref(&iface), &[]);
+    let d = file(&files, "greeter.d");
+    assert!(d.contains("module greeter;"), "{d}");
+    assert!(d.contains("struct Point {"), "{d}");
+    assert!(d.contains("struct ShapeDot {"), "{d}");
+    assert!(
+        d.contains("alias Shape = SumType!(ShapeDot, ShapeEmpty);"),
+        "{d}"
+    );
+    assert!(
+        d.contains("@llvmAttr(\"wasm-import-module\", \"greeter\") @llvmAttr(\"wasm-import-name\", \"greet\")"),
+        "{d}"
+    );
+    assert!(d.contains("string[] greet(string name, Shape at) {"), "{d}");
+    // Strings are passed to imports without copying them.
+    assert!(d.contains("cast(int) vec.ptr"), "{d}");
+    assert!(d.contains("liftString("), "{d}");
+    assert!(!d.contains("setExports"), "{d}");
+    assert!(file(&files, "wai.d").contains("canonicalAbiRealloc"));
+
+    let files = generate(false, &[], &[iface]);
+    let d = file(&files, "greeter.d");
+    assert!(d.contains("interface Exports {"), "{d}");
+    assert!(d.contains("void setExports(Exports impl) {"), "{d}");
+    assert!(d.contains("pragma(mangle, \"greet\")"), "{d}");
+    assert!(d.contains("exportsImpl.greet("), "{d}");
+    assert!(d.contains("lowerArray("), "{d}");
+}
+
+#[test]
+fn host() {
+    let iface = Interface::parse(
+        "greeter",
+        "
+            greet: func(name: string, times: option<u32>) -> expected<string, u32>
+        ",
+    )
+    .unwrap();
+
+    let files = generate(true, std::slice::from_ref(&iface), &[]);
+    let d = file(&files, "greeter.d");
+    assert!(
+        d.contains(
+            "Expected!(string, uint) greet(Instance inst, string name, Nullable!(uint) times) {"
+        ),
+        "{d}"
+    );
+    assert!(d.contains("inst.call(\"greet\", ["), "{d}");
+    assert!(!d.contains("interface Host"), "{d}");
+    assert!(file(&files, "wai.d").contains("interface Instance {"));
+
+    let files = generate(true, &[], &[iface]);
+    let d = file(&files, "greeter.d");
+    assert!(d.contains("interface Host {"), "{d}");
+    assert!(
+        d.contains("void addToImports(Imports imports, Host host) {"),
+        "{d}"
+    );
+    assert!(
+        d.contains("imports.define(\"greeter\", \"greet\", [ValType.i32, ValType.i32, ValType.i32, ValType.i32, ValType.i32], [], (Instance inst, Value[] args) {"),
+        "{d}"
+    );
+    assert!(d.contains("host.greet("), "{d}");
+}
+
+/// Checks the bindings generated for `greeter` against the files in
+/// `tests/golden`. Run the test with the environment variable `BLESS` set to
+/// update them.
+#[test]
+fn golden() {
+    let iface = greeter();
+    for (host, mode) in [(false, "guest"), (true, "host")] {
+        let files = generate(host, std::slice::from_ref(&iface), &[]);
+        check_golden(&format!("{mode}-import"), &files);
+        let files = generate(host, &[], std::slice::from_ref(&iface));
+        check_golden(&format!("{mode}-export"), &files);
+    }
+}
+
+fn check_golden(dir: &str, files: &Files) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(dir);
+    let bless = std::env::var_os("BLESS").is_some();
+    if bless {
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+    }
+    for (name, contents) in files.iter() {
+        let path = dir.join(name);
+        if bless {
+            std::fs::write(&path, contents).unwrap();
+            continue;
+        }
+        let expected = std::fs::read(&path).unwrap_or_else(|e| {
+            panic!(
+                "failed to read {}: {}\nthis can be fixed with BLESS=1",
+                path.display(),
+                e
+            )
+        });
+        assert_eq!(
+            String::from_utf8_lossy(&expected),
+            String::from_utf8_lossy(contents),
+            "{} doesn't match the generated file\nthis can be fixed with BLESS=1",
+            path.display()
+        );
+    }
+    assert_eq!(
+        std::fs::read_dir(&dir).unwrap().count(),
+        files.iter().count(),
+        "{} has files which aren't generated anymore\nthis can be fixed with BLESS=1",
+        dir.display()
+    );
+}
@@ -0,0 +1,1513 @@
+//! Bindings for Haskell hosts.
+//!
+//! For each interface this emits a `<Interface>.hs` module with the Haskell
+//! definitions of its types, and either functions in `IO` calling into an
+//! instance, or a `Host` typeclass for the host to implement along with
+//! `addToImports` to define an implementation as the imports of a module.
+//!
+//! The generated code is generic over the typeclasses of `WaiRuntime.hs`,
+//! which are all the bindings need from a wasm runtime: access to the memory
+//! of an instance, calls of its exports and the definition of imports.
+//! Implementing them for the Haskell bindings of a runtime takes a handful of
+//! lines.
+//!
+//! Strings are `Text`s and `list<u8>`s `ByteString`s, so the bindings depend
+//! on the `text` and `bytestring` packages.
+
+use heck::*;
+use std::fmt::Write;
+use std::mem;
+use wai_bindgen_gen_core::wai_parser::abi::{
+    AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmType,
+};
+use wai_bindgen_gen_core::{
+    union_case_names, uwrite, uwriteln, wai_parser::*, Direction, Files, Generator, Ns,
+};
+
+#[derive(Default)]
+pub struct Haskell {
+    opts: Opts,
+    sizes: SizeAlign,
+    in_import: bool,
+    // The documented declarations of the types of the current interface.
+    types: Vec<String>,
+    // The functions calling into an instance, for imports.
+    funcs: HsSource,
+    // The methods of the `Host` class and the body of `addToImports`, for
+    // exports.
+    host_class: HsSource,
+    defines: HsSource,
+}
+
+#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "structopt", derive(structopt::StructOpt))]
+pub struct Opts {
+    // ...
+}
+
+impl Opts {
+    pub fn build(&self) -> Haskell {
+        let mut r = Haskell::new();
+        r.opts = self.clone();
+        r
+    }
+}
+
+impl Haskell {
+    pub fn new() -> Haskell {
+        Haskell::default()
+    }
+
+    fn abi_variant(dir: Direction) -> AbiVariant {
+        // This generator uses a reversed mapping! In the Haskell host-side
+        // bindings, we don't use any extra adapter layer between guest wasm
+        // modules and the host. When the guest imports functions using the
+        // `GuestImport` ABI, the host directly implements the `GuestImport`
+        // ABI, even though the host is *exporting* functions. Similarly, when
+        // the guest exports functions using the `GuestExport` ABI, the host
+        // directly imports them with the `GuestExport` ABI, even though the
+        // host is *importing* functions.
+        match dir {
+            Direction::Import => AbiVariant::GuestExport,
+            Direction::Export => AbiVariant::GuestImport,
+        }
+    }
+
+    fn push_type(&mut self, docs: &Docs, decl: &str) {
+        let mut src = HsSource::default();
+        src.doc(docs);
+        src.push_str(decl);
+        self.types.push(src.into());
+    }
+
+    /// Prints a record type with the fields `fields`, or a type with a
+    /// single nullary constructor without any.
+    fn push_record(&mut self, name: &str, docs: &Docs, fields: &[(String, String, &Docs)]) {
+        let name = hs_type(name);
+        let mut decl = HsSource::default();
+        uwrite!(decl, "data {} = {}", name, name);
+        if !fields.is_empty() {
+            decl.indent();
+            for (i, (field, ty, docs)) in fields.iter().enumerate() {
+                decl.newline();
+                uwrite!(
+                    decl,
+                    "{} {} :: {}",
+                    if i == 0 { "{" } else { "," },
+                    field,
+                    ty
+                );
+                decl.doc_after(docs);
+            }
+            decl.newline();
+            decl.push_str("}");
+            decl.dedent();
+        }
+        decl.indent();
+        decl.newline();
+        decl.push_str("deriving (Show, Eq)");
+        decl.dedent();
+        self.push_type(docs, &decl);
+    }
+
+    /// Prints a sum type with the constructors `cases`, which take an
+    /// argument of the type of their payload if they have one.
+    fn push_variant(
+        &mut self,
+        name: &str,
+        docs: &Docs,
+        cases: &[(String, Option<String>, &Docs)],
+        deriving: &str,
+    ) {
+        let mut decl = HsSource::default();
+        uwrite!(decl, "data {}", hs_type(name));
+        decl.indent();
+        for (i, (case, payload, docs)) in cases.iter().enumerate() {
+            decl.newline();
+            decl.push_str(if i == 0 { "= " } else { "| " });
+            decl.push_str(case);
+            if let Some(ty) = payload {
+                uwrite!(decl, " {}", ty);
+            }
+            decl.doc_after(docs);
+        }
+        decl.newline();
+        uwrite!(decl, "deriving ({})", deriving);
+        decl.dedent();
+        self.push_type(docs, &decl);
+    }
+
+    fn push_alias(&mut self, name: &str, docs: &Docs, ty: &str) {
+        self.push_type(docs, &format!("type {} = {}", hs_type(name), ty));
+    }
+}
+
+impl Generator for Haskell {
+    fn preprocess_one(&mut self, iface: &Interface, dir: Direction) {
+        assert!(
+            iface.resources.len() == 0,
+            "resources not supported yet by Haskell hosts"
+        );
+        let variant = Self::abi_variant(dir);
+        self.sizes.fill(iface);
+        self.in_import = variant == AbiVariant::GuestExport;
+    }
+
+    fn type_record(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        record: &Record,
+        docs: &Docs,
+    ) {
+        let fields = record
+            .fields
+            .iter()
+            .map(|f| (hs_field(name, &f.name), ty_name(iface, &f.ty), &f.docs))
+            .collect::<Vec<_>>();
+        self.push_record(name, docs, &fields);
+    }
+
+    fn type_tuple(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        tuple: &Tuple,
+        docs: &Docs,
+    ) {
+        self.push_alias(name, docs, &tuple_name(iface, &tuple.types));
+    }
+
+    fn type_flags(
+        &mut self,
+        _iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        flags: &Flags,
+        docs: &Docs,
+    ) {
+        // Flags are records of whether each flag is set.
+        let fields = flags
+            .flags
+            .iter()
+            .map(|f| (hs_field(name, &f.name), "Bool".to_string(), &f.docs))
+            .collect::<Vec<_>>();
+        self.push_record(name, docs, &fields);
+    }
+
+    fn type_variant(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        variant: &Variant,
+        docs: &Docs,
+    ) {
+        let cases = variant
+            .cases
+            .iter()
+            .map(|c| {
+                let payload = match c.ty {
+                    Type::Unit => None,
+                    ty => Some(ty_name(iface, &ty)),
+                };
+                (hs_ctor(name, &c.name), payload, &c.docs)
+            })
+            .collect::<Vec<_>>();
+        self.push_variant(name, docs, &cases, "Show, Eq");
+    }
+
+    fn type_union(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        union: &Union,
+        docs: &Docs,
+    ) {
+        let cases = union
+            .cases
+            .iter()
+            .zip(union_case_names(iface, union))
+            .map(|(c, case_name)| {
+                (
+                    hs_ctor(name, &case_name),
+                    Some(ty_name(iface, &c.ty)),
+                    &c.docs,
+                )
+            })
+            .collect::<Vec<_>>();
+        self.push_variant(name, docs, &cases, "Show, Eq");
+    }
+
+    fn type_option(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        payload: &Type,
+        docs: &Docs,
+    ) {
+        let ty = format!("(Maybe {})", ty_name(iface, payload));
+        self.push_alias(name, docs, &ty);
+    }
+
+    fn type_expected(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        expected: &Expected,
+        docs: &Docs,
+    ) {
+        let ty = format!(
+            "(Either {} {})",
+            ty_name(iface, &expected.err),
+            ty_name(iface, &expected.ok)
+        );
+        self.push_alias(name, docs, &ty);
+    }
+
+    fn type_enum(
+        &mut self,
+        _iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        enum_: &Enum,
+        docs: &Docs,
+    ) {
+        let cases = enum_
+            .cases
+            .iter()
+            .map(|c| (hs_ctor(name, &c.name), None, &c.docs))
+            .collect::<Vec<_>>();
+        self.push_variant(name, docs, &cases, "Show, Eq, Enum, Bounded");
+    }
+
+    fn type_resource(&mut self, _iface: &Interface, _ty: ResourceId) {
+        unimplemented!("resources not supported yet by Haskell hosts")
+    }
+
+    fn type_alias(&mut self, iface: &Interface, _id: TypeId, name: &str, ty: &Type, docs: &Docs) {
+        self.push_alias(name, docs, &ty_name(iface, ty));
+    }
+
+    fn type_list(&mut self, iface: &Interface, _id: TypeId, name: &str, ty: &Type, docs: &Docs) {
+        self.push_alias(name, docs, &list_name(iface, ty));
+    }
+
+    fn type_builtin(&mut self, iface: &Interface, id: TypeId, name: &str, ty: &Type, docs: &Docs) {
+        self.type_alias(iface, id, name, ty, docs);
+    }
+
+    // As with `abi_variant` above, we're generating host-side bindings here
+    // so a user "import" uses the "export" ABI variant on the inside of
+    // this `Generator` implementation.
+    fn import(&mut self, iface: &Interface, func: &Function) {
+        assert!(!func.is_async, "async not supported yet");
+        let name = hs_ident(&func.name.to_mixed_case());
+        let params = func
+            .params
+            .iter()
+            .map(|(name, _)| hs_ident(&name.to_mixed_case()))
+            .collect::<Vec<_>>();
+
+        self.funcs.newline();
+        self.funcs.doc(&func.docs);
+        uwrite!(self.funcs, "{} :: W.Instance i => i", name);
+        for (_, ty) in func.params.iter() {
+            uwrite!(self.funcs, " -> {}", ty_name(iface, ty));
+        }
+        uwriteln!(self.funcs, " -> IO {}", ty_name(iface, &func.result));
+        uwrite!(self.funcs, "{} inst", name);
+        for param in params.iter() {
+            uwrite!(self.funcs, " {}", param);
+        }
+        self.funcs.push_str(" = do");
+        self.funcs.indent();
+
+        let mut f = FunctionBindgen::new(self, params);
+        iface.call(
+            AbiVariant::GuestExport,
+            LiftLower::LowerArgsLiftResults,
+            func,
+            &mut f,
+        );
+        let FunctionBindgen { src, .. } = f;
+        self.funcs.push_lines(&src);
+        self.funcs.dedent();
+        self.funcs.newline();
+    }
+
+    fn export(&mut self, iface: &Interface, func: &Function) {
+        assert!(!func.is_async, "async not supported yet");
+        let sig = iface.wasm_signature(AbiVariant::GuestImport, func);
+        let name = hs_ident(&func.name.to_mixed_case());
+
+        self.host_class.newline();
+        self.host_class.doc(&func.docs);
+        uwrite!(self.host_class, "{} :: h", name);
+        for (_, ty) in func.params.iter() {
+            uwrite!(self.host_class, " -> {}", ty_name(iface, ty));
+        }
+        uwrite!(self.host_class, " -> IO {}", ty_name(iface, &func.result));
+
+        let wasm_types = |tys: &[WasmType]| {
+            tys.iter()
+                .map(|ty| wasm_ty_ctor(*ty))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        self.defines.newline();
+        uwrite!(
+            self.defines,
+            "W.define imports \"{}\" \"{}\" [{}] [{}] $ \\inst args -> do",
+            iface.name,
+            func.name,
+            wasm_types(&sig.params),
+            wasm_types(&sig.results)
+        );
+        self.defines.indent();
+
+        let mut f = FunctionBindgen::new(self, Vec::new());
+        for (i, ty) in sig.params.iter().enumerate() {
+            let arg = f.locals.tmp("arg");
+            uwriteln!(
+                f.src,
+                "let {} = {} (args !! {})",
+                arg,
+                wasm_ty_getter(*ty),
+                i
+            );
+            f.params.push(arg);
+        }
+        f.results = sig.results.clone();
+        f.module = iface.name.to_camel_case();
+        iface.call(
+            AbiVariant::GuestImport,
+            LiftLower::LiftArgsLowerResults,
+            func,
+            &mut f,
+        );
+        let FunctionBindgen { src, .. } = f;
+        self.defines.push_lines(&src);
+        self.defines.dedent();
+    }
+
+    fn finish_one(&mut self, iface: &Interface, files: &mut Files) {
+        let module = iface.name.to_camel_case();
+        let mut src = HsSource::default();
+        uwriteln!(
+            src,
+            "-- Generated by `wai-bindgen` from the `{}` interface.",
+            iface.name
+        );
+        src.push_str(
+            "{-# OPTIONS_GHC -Wno-unused-imports -Wno-unused-matches -Wno-unused-local-binds \
+             -Wno-name-shadowing #-}\n",
+        );
+        uwriteln!(src, "\nmodule {} where", module);
+        src.push_str(IMPORTS);
+
+        for decl in mem::take(&mut self.types) {
+            src.push_str("\n");
+            src.push_str(&decl);
+            src.push_str("\n");
+        }
+
+        if self.in_import {
+            let funcs = mem::take(&mut self.funcs);
+            src.push_str(&funcs);
+        } else {
+            src.newline();
+            uwrite!(
+                src,
+                "-- | The functions of the `{}` interface, implemented by the host.",
+                iface.name
+            );
+            src.newline();
+            src.push_str("class Host h where");
+            src.indent();
+            src.push_lines(mem::take(&mut self.host_class).trim_start_matches('\n'));
+            src.dedent();
+            src.newline();
+            src.newline();
+            src.push_str("-- | Defines the functions of @host@ as the imports of @imports@.");
+            src.newline();
+            src.push_str("addToImports :: (W.Imports l, Host h) => l -> h -> IO ()");
+            src.newline();
+            src.push_str("addToImports imports host = do");
+            src.indent();
+            src.push_lines(mem::take(&mut self.defines).trim_start_matches('\n'));
+            src.newline();
+            src.push_str("return ()");
+            src.dedent();
+            src.newline();
+        }
+
+        files.push(&format!("{}.hs", module), src.as_bytes());
+    }
+
+    fn finish_all(&mut self, files: &mut Files) {
+        files.push("WaiRuntime.hs", RUNTIME.as_bytes());
+    }
+}
+
+/// The imports of the module of each interface.
+const IMPORTS: &str = "
+import Control.Monad (forM, forM_)
+import Data.Bits ((.|.), bit, testBit)
+import Data.ByteString (ByteString)
+import Data.Char (chr, ord)
+import Data.Int (Int8, Int16, Int32, Int64)
+import Data.Text (Text)
+import Data.Word (Word8, Word16, Word32, Word64)
+import GHC.Float (castDoubleToWord64, castFloatToWord32, castWord32ToFloat, castWord64ToDouble)
+import qualified WaiRuntime as W
+";
+
+/// The runtime support shared by the bindings of all interfaces.
+const RUNTIME: &str = r#"-- Generated by `wai-bindgen`: the runtime interface of the Haskell bindings.
+{-# LANGUAGE FlexibleContexts #-}
+{-# LANGUAGE TypeFamilies #-}
+
+module WaiRuntime
+  ( Value (..)
+  , ValType (..)
+  , asI32
+  , asI64
+  , asF32
+  , asF64
+  , Instance (..)
+  , Imports (..)
+  , alloc
+  , free
+  , liftText
+  , lowerBytes
+  , lowerText
+  ) where
+
+import Data.ByteString (ByteString)
+import qualified Data.ByteString as B
+import Data.Int (Int32, Int64)
+import Data.Text (Text)
+import Data.Text.Encoding (decodeUtf8', encodeUtf8)
+
+-- | A wasm value.
+data Value = I32 Int32 | I64 Int64 | F32 Float | F64 Double
+  deriving (Show, Eq)
+
+-- | The type of a wasm value.
+data ValType = TI32 | TI64 | TF32 | TF64
+  deriving (Show, Eq)
+
+asI32 :: Value -> Int32
+asI32 (I32 v) = v
+asI32 _ = error "expected an i32"
+
+asI64 :: Value -> Int64
+asI64 (I64 v) = v
+asI64 _ = error "expected an i64"
+
+asF32 :: Value -> Float
+asF32 (F32 v) = v
+asF32 _ = error "expected an f32"
+
+asF64 :: Value -> Double
+asF64 (F64 v) = v
+asF64 _ = error "expected an f64"
+
+-- | What the bindings need from an instance of a wasm module, which is also
+-- what imports are called with.
+--
+-- Addresses are offsets into the memory exported as @memory@ by the
+-- instance. Loads of narrow integers extend them to 32 bits, and stores
+-- truncate values to their width.
+class Instance i where
+  loadU8 :: i -> Int32 -> IO Int32
+  loadS8 :: i -> Int32 -> IO Int32
+  loadU16 :: i -> Int32 -> IO Int32
+  loadS16 :: i -> Int32 -> IO Int32
+  loadI32 :: i -> Int32 -> IO Int32
+  loadI64 :: i -> Int32 -> IO Int64
+  loadF32 :: i -> Int32 -> IO Float
+  loadF64 :: i -> Int32 -> IO Double
+  -- | @loadBytes inst addr len@ reads @len@ bytes at @addr@.
+  loadBytes :: i -> Int32 -> Int32 -> IO ByteString
+  storeI8 :: i -> Int32 -> Int32 -> IO ()
+  storeI16 :: i -> Int32 -> Int32 -> IO ()
+  storeI32 :: i -> Int32 -> Int32 -> IO ()
+  storeI64 :: i -> Int32 -> Int64 -> IO ()
+  storeF32 :: i -> Int32 -> Float -> IO ()
+  storeF64 :: i -> Int32 -> Double -> IO ()
+  storeBytes :: i -> Int32 -> ByteString -> IO ()
+  -- | @call inst name args@ calls the export @name@ of @inst@.
+  call :: i -> String -> [Value] -> IO [Value]
+
+-- | The imports a module is instantiated with.
+class Instance (Caller l) => Imports l where
+  -- | The instances calling the imports.
+  type Caller l
+  -- | @define imports module_ name params results f@ defines the import
+  -- @name@ of @module_@ as @f@, which is passed the calling instance.
+  define
+    :: l
+    -> String
+    -> String
+    -> [ValType]
+    -> [ValType]
+    -> (Caller l -> [Value] -> IO [Value])
+    -> IO ()
+
+alloc :: Instance i => i -> String -> Int32 -> Int32 -> IO Int32
+alloc inst realloc align size = do
+  ret <- call inst realloc [I32 0, I32 0, I32 align, I32 size]
+  case ret of
+    [I32 ptr] -> return ptr
+    _ -> fail (realloc ++ " didn't return a pointer")
+
+free :: Instance i => i -> String -> Int32 -> Int32 -> Int32 -> IO ()
+free inst free' ptr size align = do
+  _ <- call inst free' [I32 ptr, I32 size, I32 align]
+  return ()
+
+liftText :: Instance i => i -> Int32 -> Int32 -> IO Text
+liftText inst ptr len = do
+  bytes <- loadBytes inst ptr len
+  case decodeUtf8' bytes of
+    Right s -> return s
+    Left _ -> fail "invalid UTF-8 string"
+
+lowerBytes :: Instance i => i -> String -> ByteString -> IO (Int32, Int32)
+lowerBytes inst realloc bytes = do
+  let len = fromIntegral (B.length bytes)
+  ptr <- alloc inst realloc 1 len
+  storeBytes inst ptr bytes
+  return (ptr, len)
+
+lowerText :: Instance i => i -> String -> Text -> IO (Int32, Int32)
+lowerText inst realloc s = lowerBytes inst realloc (encodeUtf8 s)
+"#;
+
+struct FunctionBindgen<'a> {
+    gen: &'a mut Haskell,
+    locals: Ns,
+    src: HsSource,
+    block_storage: Vec<HsSource>,
+    blocks: Vec<(String, Vec<String>)>,
+    params: Vec<String>,
+    payloads: Vec<String>,
+    // The wasm results of an export, which are returned as values, and the
+    // module qualifying the methods of `Host` it calls.
+    results: Vec<WasmType>,
+    module: String,
+}
+
+impl<'a> FunctionBindgen<'a> {
+    fn new(gen: &'a mut Haskell, params: Vec<String>) -> FunctionBindgen<'a> {
+        let mut locals = Ns::default();
+        for name in ["inst", "args", "imports", "host"] {
+            locals.insert(name).unwrap();
+        }
+        for param in params.iter() {
+            locals.insert(param).unwrap();
+        }
+        FunctionBindgen {
+            gen,
+            locals,
+            src: HsSource::default(),
+            block_storage: Vec::new(),
+            blocks: Vec::new(),
+            params,
+            payloads: Vec::new(),
+            results: Vec::new(),
+            module: String::new(),
+        }
+    }
+
+    /// Binds `expr` to a new local named after `name`.
+    fn bind(&mut self, name: &str, expr: &str) -> String {
+        let tmp = self.locals.tmp(name);
+        uwriteln!(self.src, "let {} = {}", tmp, expr);
+        tmp
+    }
+
+    /// Binds the result of the action `expr` to a new local named after
+    /// `name`.
+    fn bind_io(&mut self, name: &str, expr: &str) -> String {
+        let tmp = self.locals.tmp(name);
+        uwriteln!(self.src, "{} <- {}", tmp, expr);
+        tmp
+    }
+
+    fn load(&mut self, ty: &str, offset: i32, operands: &[String], results: &mut Vec<String>) {
+        let expr = format!("W.load{} inst {}", ty, addr(&operands[0], offset));
+        results.push(self.bind_io("load", &expr));
+    }
+
+    fn store(&mut self, ty: &str, offset: i32, operands: &[String]) {
+        uwriteln!(
+            self.src,
+            "W.store{} inst {} {}",
+            ty,
+            addr(&operands[1], offset),
+            operands[0]
+        );
+    }
+
+    /// Prints a `case` of `scrutinee` with `arms` of a pattern, a block and
+    /// the action ending the block, binding the results of the arms to new
+    /// locals named after `name`.
+    fn print_case(
+        &mut self,
+        name: &str,
+        scrutinee: &str,
+        arms: Vec<(String, String, String)>,
+        nresults: usize,
+    ) -> Vec<String> {
+        let results = (0..nresults)
+            .map(|_| self.locals.tmp(name))
+            .collect::<Vec<_>>();
+        match nresults {
+            0 => {}
+            1 => uwrite!(self.src, "{} <- ", results[0]),
+            _ => uwrite!(self.src, "({}) <- ", results.join(", ")),
+        }
+        uwrite!(self.src, "case {} of", scrutinee);
+        self.src.indent();
+        for (pattern, block, action) in arms {
+            self.src.newline();
+            if block.is_empty() {
+                uwrite!(self.src, "{} -> {}", pattern, action);
+                continue;
+            }
+            uwrite!(self.src, "{} -> do", pattern);
+            self.src.indent();
+            self.src.push_lines(&block);
+            self.src.newline();
+            self.src.push_str(&action);
+            self.src.dedent();
+        }
+        self.src.dedent();
+        self.src.newline();
+        results
+    }
+
+    /// Lowers `op` of a sum type with the constructors `ctors`, which have
+    /// a payload unless they're `false`.
+    fn lower_cases(
+        &mut self,
+        op: &str,
+        ctors: Vec<(String, bool)>,
+        result_types: &[WasmType],
+    ) -> Vec<String> {
+        let blocks = self
+            .blocks
+            .drain(self.blocks.len() - ctors.len()..)
+            .collect::<Vec<_>>();
+        let payloads = self
+            .payloads
+            .drain(self.payloads.len() - ctors.len()..)
+            .collect::<Vec<_>>();
+        let arms = ctors
+            .into_iter()
+            .zip(blocks)
+            .zip(payloads)
+            .map(|(((ctor, has_payload), (block, block_results)), payload)| {
+                let pattern = if has_payload {
+                    format!("{} {}", ctor, payload)
+                } else {
+                    ctor
+                };
+                (pattern, block, format!("return {}", tuple(&block_results)))
+            })
+            .collect();
+        self.print_case("variant", op, arms, result_types.len())
+    }
+
+    /// Lifts a value of the type `ty` with the constructors `ctors` from the
+    /// discriminant `op`.
+    fn lift_cases(&mut self, op: &str, ctors: Vec<(String, bool)>, ty: &str) -> String {
+        let blocks = self
+            .blocks
+            .drain(self.blocks.len() - ctors.len()..)
+            .collect::<Vec<_>>();
+        let arms = ctors
+            .into_iter()
+            .zip(blocks)
+            .enumerate()
+            .map(|(i, ((ctor, has_payload), (block, block_results)))| {
+                let value = if has_payload {
+                    format!("({} {})", ctor, block_results[0])
+                } else {
+                    ctor
+                };
+                (i.to_string(), block, format!("return {}", value))
+            })
+            .chain(Some(invalid_discriminant(ty)))
+            .collect();
+        self.print_case("variant", op, arms, 1).remove(0)
+    }
+}
+
+impl Bindgen for FunctionBindgen<'_> {
+    type Operand = String;
+
+    fn sizes(&self) -> &SizeAlign {
+        &self.gen.sizes
+    }
+
+    fn push_block(&mut self) {
+        let prev = mem::take(&mut self.src);
+        self.block_storage.push(prev);
+    }
+
+    fn finish_block(&mut self, operands: &mut Vec<String>) {
+        let to_restore = self.block_storage.pop().unwrap();
+        let src = mem::replace(&mut self.src, to_restore);
+        self.blocks.push((src.into(), mem::take(operands)));
+    }
+
+    fn return_pointer(&mut self, _iface: &Interface, _size: usize, _align: usize) -> String {
+        unimplemented!()
+    }
+
+    fn is_list_canonical(&self, _iface: &Interface, ty: &Type) -> bool {
+        // Only `list<u8>` has a representation of its own, `ByteString`.
+        matches!(ty, Type::U8)
+    }
+
+    fn emit(
+        &mut self,
+        iface: &Interface,
+        inst: &Instruction<'_>,
+        operands: &mut Vec<String>,
+        results: &mut Vec<String>,
+    ) {
+        match inst {
+            Instruction::GetArg { nth } => results.push(self.params[*nth].clone()),
+            Instruction::I32Const { val } if *val < 0 => results.push(format!("({})", val)),
+            Instruction::I32Const { val } => results.push(val.to_string()),
+            Instruction::ConstZero { tys } => {
+                for _ in tys.iter() {
+                    results.push("0".to_string());
+                }
+            }
+
+            // Conversions between integers of different widths keep their low
+            // bits, which is all that's meaningful of integers from wasm.
+            Instruction::I32FromU8
+            | Instruction::I32FromS8
+            | Instruction::I32FromU16
+            | Instruction::I32FromS16
+            | Instruction::I32FromU32 => results.push(convert(&operands[0], "Int32")),
+            Instruction::I64FromU64 => results.push(convert(&operands[0], "Int64")),
+            Instruction::U8FromI32 => results.push(convert(&operands[0], "Word8")),
+            Instruction::S8FromI32 => results.push(convert(&operands[0], "Int8")),
+            Instruction::U16FromI32 => results.push(convert(&operands[0], "Word16")),
+            Instruction::S16FromI32 => results.push(convert(&operands[0], "Int16")),
+            Instruction::U32FromI32 => results.push(convert(&operands[0], "Word32")),
+            Instruction::U64FromI64 => results.push(convert(&operands[0], "Word64")),
+            Instruction::I32FromS32
+            | Instruction::I64FromS64
+            | Instruction::S32FromI32
+            | Instruction::S64FromI64
+            | Instruction::Float32FromF32
+            | Instruction::Float64FromF64
+            | Instruction::F32FromFloat32
+            | Instruction::F64FromFloat64 => results.push(operands.pop().unwrap()),
+
+            Instruction::CharFromI32 => {
+                results.push(format!("(chr (fromIntegral {}))", operands[0]))
+            }
+            Instruction::I32FromChar => {
+                results.push(convert(&format!("(ord {})", operands[0]), "Int32"))
+            }
+
+            Instruction::Bitcasts { casts } => {
+                for (cast, op) in casts.iter().zip(operands) {
+                    let op = match cast {
+                        Bitcast::I32ToF32 | Bitcast::I64ToF32 => {
+                            format!("(castWord32ToFloat (fromIntegral {}))", op)
+                        }
+                        Bitcast::F32ToI32 => {
+                            convert(&format!("(castFloatToWord32 {})", op), "Int32")
+                        }
+                        Bitcast::F32ToI64 => {
+                            convert(&format!("(castFloatToWord32 {})", op), "Int64")
+                        }
+                        Bitcast::I64ToF64 => format!("(castWord64ToDouble (fromIntegral {}))", op),
+                        Bitcast::F64ToI64 => {
+                            convert(&format!("(castDoubleToWord64 {})", op), "Int64")
+                        }
+                        Bitcast::I32ToI64 => convert(op, "Int64"),
+                        Bitcast::I64ToI32 => convert(op, "Int32"),
+                        Bitcast::None => op.clone(),
+                    };
+                    results.push(op);
+                }
+            }
+
+            Instruction::UnitLower => {}
+            Instruction::UnitLift => results.push("()".to_string()),
+            Instruction::BoolFromI32 => results.push(format!("({} /= 0)", operands[0])),
+            Instruction::I32FromBool => results.push(format!("(if {} then 1 else 0)", operands[0])),
+
+            Instruction::I32FromBorrowedHandle { .. }
+            | Instruction::I32FromOwnedHandle { .. }
+            | Instruction::HandleOwnedFromI32 { .. }
+            | Instruction::HandleBorrowedFromI32 { .. } => {
+                unimplemented!("resources not supported yet by Haskell hosts")
+            }
+
+            Instruction::RecordLower { record, name, .. } => {
+                if record.fields.is_empty() {
+                    return;
+                }
+                let names = record
+                    .fields
+                    .iter()
+                    .map(|_| self.locals.tmp("field"))
+                    .collect::<Vec<_>>();
+                uwriteln!(
+                    self.src,
+                    "let ({} {}) = {}",
+                    hs_type(name),
+                    names.join(" "),
+                    operands[0]
+                );
+                results.extend(names);
+            }
+            Instruction::RecordLift { name, .. } => results.push(apply(&hs_type(name), operands)),
+
+            Instruction::TupleLower { tuple, .. } => match tuple.types.len() {
+                0 => {}
+                1 => results.push(operands[0].clone()),
+                _ => {
+                    let names = tuple
+                        .types
+                        .iter()
+                        .map(|_| self.locals.tmp("tuple"))
+                        .collect::<Vec<_>>();
+                    uwriteln!(self.src, "let ({}) = {}", names.join(", "), operands[0]);
+                    results.extend(names);
+                }
+            },
+            Instruction::TupleLift { .. } => results.push(tuple(operands)),
+
+            Instruction::FlagsLower { flags, name, .. } => {
+                if flags.flags.is_empty() {
+                    return results.push("0".to_string());
+                }
+                let names = flags
+                    .flags
+                    .iter()
+                    .map(|_| self.locals.tmp("flag"))
+                    .collect::<Vec<_>>();
+                uwriteln!(
+                    self.src,
+                    "let ({} {}) = {}",
+                    hs_type(name),
+                    names.join(" "),
+                    operands[0]
+                );
+                for chunk in names.chunks(32) {
+                    let bits = chunk
+                        .iter()
+                        .enumerate()
+                        .map(|(i, name)| format!("(if {} then bit {} else 0)", name, i))
+                        .collect::<Vec<_>>();
+                    let expr = format!("{} :: Int32", bits.join(" .|. "));
+                    results.push(self.bind("flags", &expr));
+                }
+            }
+            Instruction::FlagsLift { flags, name, .. } => {
+                let fields = (0..flags.flags.len())
+                    .map(|i| format!("(testBit {} {})", operands[i / 32], i % 32))
+                    .collect::<Vec<_>>();
+                results.push(apply(&hs_type(name), &fields));
+            }
+
+            Instruction::VariantPayloadName => {
+                let name = self.locals.tmp("payload");
+                results.push(name.clone());
+                self.payloads.push(name);
+            }
+
+            Instruction::VariantLower {
+                variant,
+                name,
+                results: result_types,
+                ..
+            } => {
+                let ctors = variant
+                    .cases
+                    .iter()
+                    .map(|c| (hs_ctor(name, &c.name), c.ty != Type::Unit))
+                    .collect();
+                results.extend(self.lower_cases(&operands[0], ctors, result_types));
+            }
+            Instruction::VariantLift { variant, name, .. } => {
+                let ctors = variant
+                    .cases
+                    .iter()
+                    .map(|c| (hs_ctor(name, &c.name), c.ty != Type::Unit))
+                    .collect();
+                let result = self.lift_cases(&operands[0], ctors, name);
+                results.push(result);
+            }
+
+            Instruction::UnionLower {
+                union,
+                name,
+                results: result_types,
+                ..
+            } => {
+                let ctors = union_case_names(iface, union)
+                    .iter()
+                    .map(|case| (hs_ctor(name, case), true))
+                    .collect();
+                results.extend(self.lower_cases(&operands[0], ctors, result_types));
+            }
+            Instruction::UnionLift { union, name, .. } => {
+                let ctors = union_case_names(iface, union)
+                    .iter()
+                    .map(|case| (hs_ctor(name, case), true))
+                    .collect();
+                let result = self.lift_cases(&operands[0], ctors, name);
+                results.push(result);
+            }
+
+            Instruction::EnumLower { .. } => {
+                results.push(convert(&format!("(fromEnum {})", operands[0]), "Int32"))
+            }
+            Instruction::EnumLift { enum_, name, .. } => {
+                let arms = enum_
+                    .cases
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| {
+                        let action = format!("return {}", hs_ctor(name, &c.name));
+                        (i.to_string(), String::new(), action)
+                    })
+                    .chain(Some(invalid_discriminant(name)))
+                    .collect();
+                results.extend(self.print_case("enum", &operands[0], arms, 1));
+            }
+
+            Instruction::OptionLower {
+                results: result_types,
+                ..
+            } => {
+                let ctors = vec![("Nothing".to_string(), false), ("Just".to_string(), true)];
+                results.extend(self.lower_cases(&operands[0], ctors, result_types));
+            }
+            Instruction::OptionLift { .. } => {
+                let ctors = vec![("Nothing".to_string(), false), ("Just".to_string(), true)];
+                let result = self.lift_cases(&operands[0], ctors, "option");
+                results.push(result);
+            }
+
+            Instruction::ExpectedLower {
+                results: result_types,
+                ..
+            } => {
+                let ctors = vec![("Right".to_string(), true), ("Left".to_string(), true)];
+                results.extend(self.lower_cases(&operands[0], ctors, result_types));
+            }
+            Instruction::ExpectedLift { .. } => {
+                let ctors = vec![("Right".to_string(), true), ("Left".to_string(), true)];
+                let result = self.lift_cases(&operands[0], ctors, "expected");
+                results.push(result);
+            }
+
+            Instruction::ListCanonLower { realloc, .. } => {
+                // Lowering only happens when we're passing lists into wasm,
+                // which forces us to always allocate, so this should always be
+                // `Some`.
+                let realloc = realloc.unwrap();
+                let ptr = self.locals.tmp("ptr");
+                let len = self.locals.tmp("len");
+                uwriteln!(
+                    self.src,
+                    "({}, {}) <- W.lowerBytes inst \"{}\" {}",
+                    ptr,
+                    len,
+                    realloc,
+                    operands[0]
+                );
+                results.push(ptr);
+                results.push(len);
+            }
+            Instruction::StringLower { realloc } => {
+                let realloc = realloc.unwrap();
+                let ptr = self.locals.tmp("ptr");
+                let len = self.locals.tmp("len");
+                uwriteln!(
+                    self.src,
+                    "({}, {}) <- W.lowerText inst \"{}\" {}",
+                    ptr,
+                    len,
+                    realloc,
+                    operands[0]
+                );
+                results.push(ptr);
+                results.push(len);
+            }
+            Instruction::ListLower { element, realloc } => {
+                let realloc = realloc.unwrap();
+                let (body, body_results) = self.blocks.pop().unwrap();
+                assert!(body_results.is_empty());
+                let size = self.gen.sizes.size(element);
+                let align = self.gen.sizes.align(element);
+                let vec = self.bind("vec", &operands[0]);
+                let len = self.bind("len", &format!("fromIntegral (length {}) :: Int32", vec));
+                let ptr = self.bind_io(
+                    "ptr",
+                    &format!(
+                        "W.alloc inst \"{}\" {} ({} * {})",
+                        realloc, align, len, size
+                    ),
+                );
+                uwrite!(self.src, "forM_ (zip [0 ..] {}) $ \\(i', e') -> do", vec);
+                self.src.indent();
+                self.src.newline();
+                uwrite!(self.src, "let base' = {} + i' * {}", ptr, size);
+                self.src.push_lines(&body);
+                self.src.newline();
+                self.src.push_str("return ()");
+                self.src.dedent();
+                self.src.newline();
+                results.push(ptr);
+                results.push(len);
+            }
+
+            Instruction::ListCanonLift { free, .. } => {
+                let ptr = self.bind("ptr", &operands[0]);
+                let len = self.bind("len", &operands[1]);
+                let result = self.bind_io("bytes", &format!("W.loadBytes inst {} {}", ptr, len));
+                if let Some(free) = free {
+                    uwriteln!(self.src, "W.free inst \"{}\" {} {} 1", free, ptr, len);
+                }
+                results.push(result);
+            }
+            Instruction::StringLift { free } => {
+                let ptr = self.bind("ptr", &operands[0]);
+                let len = self.bind("len", &operands[1]);
+                let result = self.bind_io("string", &format!("W.liftText inst {} {}", ptr, len));
+                if let Some(free) = free {
+                    uwriteln!(self.src, "W.free inst \"{}\" {} {} 1", free, ptr, len);
+                }
+                results.push(result);
+            }
+            Instruction::ListLift { element, free, .. } => {
+                let (body, body_results) = self.blocks.pop().unwrap();
+                let size = self.gen.sizes.size(element);
+                let align = self.gen.sizes.align(element);
+                let ptr = self.bind("ptr", &operands[0]);
+                let len = self.bind("len", &operands[1]);
+                let result = self.locals.tmp("items");
+                uwrite!(
+                    self.src,
+                    "{} <- forM [0 .. {} - 1] $ \\i' -> do",
+                    result,
+                    len
+                );
+                self.src.indent();
+                self.src.newline();
+                uwrite!(self.src, "let base' = {} + i' * {}", ptr, size);
+                self.src.push_lines(&body);
+                self.src.newline();
+                uwrite!(self.src, "return {}", body_results[0]);
+                self.src.dedent();
+                self.src.newline();
+                if let Some(free) = free {
+                    uwriteln!(
+                        self.src,
+                        "W.free inst \"{}\" {} ({} * {}) {}",
+                        free,
+                        ptr,
+                        len,
+                        size,
+                        align
+                    );
+                }
+                results.push(result);
+            }
+
+            // The elements and base pointers of nested lists shadow those of
+            // the enclosing lists in the lambdas iterating over them.
+            Instruction::IterElem { .. } => results.push("e'".to_string()),
+            Instruction::IterBasePointer => results.push("base'".to_string()),
+
+            Instruction::CallWasm { name, sig, .. } => {
+                let args = sig
+                    .params
+                    .iter()
+                    .zip(operands.iter())
+                    .map(|(ty, op)| wasm_value(*ty, op))
+                    .collect::<Vec<_>>();
+                let call = format!("W.call inst \"{}\" [{}]", name, args.join(", "));
+                if sig.results.is_empty() {
+                    uwriteln!(self.src, "_ <- {}", call);
+                    return;
+                }
+                let ret = self.bind_io("ret", &call);
+                for (i, ty) in sig.results.iter().enumerate() {
+                    let expr = format!("{} ({} !! {})", wasm_ty_getter(*ty), ret, i);
+                    results.push(self.bind("ret", &expr));
+                }
+            }
+
+            Instruction::CallInterface { func, .. } => {
+                let mut call = format!(
+                    "{}.{} host",
+                    self.module,
+                    hs_ident(&func.name.to_mixed_case())
+                );
+                for op in operands.iter() {
+                    call.push(' ');
+                    call.push_str(op);
+                }
+                results.push(self.bind_io("ret", &call));
+            }
+
+            Instruction::Return { amt, .. } => {
+                if self.gen.in_import {
+                    assert_eq!(*amt, 1);
+                    uwrite!(self.src, "return {}", operands[0]);
+                } else {
+                    let values = self
+                        .results
+                        .iter()
+                        .zip(operands.iter())
+                        .map(|(ty, op)| wasm_value(*ty, op))
+                        .collect::<Vec<_>>();
+                    uwrite!(self.src, "return [{}]", values.join(", "));
+                }
+            }
+
+            Instruction::I32Load { offset } => self.load("I32", *offset, operands, results),
+            Instruction::I32Load8U { offset } => self.load("U8", *offset, operands, results),
+            Instruction::I32Load8S { offset } => self.load("S8", *offset, operands, results),
+            Instruction::I32Load16U { offset } => self.load("U16", *offset, operands, results),
+            Instruction::I32Load16S { offset } => self.load("S16", *offset, operands, results),
+            Instruction::I64Load { offset } => self.load("I64", *offset, operands, results),
+            Instruction::F32Load { offset } => self.load("F32", *offset, operands, results),
+            Instruction::F64Load { offset } => self.load("F64", *offset, operands, results),
+            Instruction::I32Store { offset } => self.store("I32", *offset, operands),
+            Instruction::I32Store8 { offset } => self.store("I8", *offset, operands),
+            Instruction::I32Store16 { offset } => self.store("I16", *offset, operands),
+            Instruction::I64Store { offset } => self.store("I64", *offset, operands),
+            Instruction::F32Store { offset } => self.store("F32", *offset, operands),
+            Instruction::F64Store { offset } => self.store("F64", *offset, operands),
+
+            Instruction::Malloc {
+                realloc,
+                size,
+                align,
+            } => {
+                let expr = format!("W.alloc inst \"{}\" {} {}", realloc, align, size);
+                results.push(self.bind_io("ptr", &expr));
+            }
+            Instruction::Free { .. } => unreachable!(),
+
+            Instruction::CallWasmAsyncImport { .. }
+            | Instruction::CallWasmAsyncExport { .. }
+            | Instruction::ReturnAsyncExport { .. }
+            | Instruction::ReturnAsyncImport { .. } => {
+                unimplemented!("async not supported yet")
+            }
+        }
+    }
+}
+
+/// Haskell source, indented by blocks of two spaces.
+///
+/// Unlike `wai_bindgen_gen_core::Source` the level is explicit, and applies
+/// to the current line unless something was written to it already.
+#[derive(Default)]
+struct HsSource {
+    s: String,
+    indent: usize,
+}
+
+impl HsSource {
+    /// Appends `src`, indenting all of its lines but the first at the
+    /// current level.
+    fn push_str(&mut self, src: &str) {
+        let lines = src.lines().collect::<Vec<_>>();
+        for (i, line) in lines.iter().enumerate() {
+            self.s.push_str(line);
+            if i != lines.len() - 1 || src.ends_with('\n') {
+                self.newline();
+            }
+        }
+    }
+
+    /// Appends each line of `src` on a line of its own.
+    fn push_lines(&mut self, src: &str) {
+        for line in src.lines() {
+            self.newline();
+            self.s.push_str(line);
+        }
+    }
+
+    fn indent(&mut self) {
+        self.indent += 2;
+        self.reindent();
+    }
+
+    fn dedent(&mut self) {
+        self.indent -= 2;
+        self.reindent();
+    }
+
+    /// Moves the current line to the current level if nothing was written
+    /// to it yet.
+    fn reindent(&mut self) {
+        let start = self.s.rfind('\n').map_or(0, |i| i + 1);
+        if self.s[start..].bytes().all(|b| b == b' ') {
+            self.s.truncate(start);
+            for _ in 0..self.indent {
+                self.s.push(' ');
+            }
+        }
+    }
+
+    fn newline(&mut self) {
+        // Blank lines don't keep the indentation.
+        while self.s.ends_with(' ') {
+            self.s.pop();
+        }
+        self.s.push('\n');
+        for _ in 0..self.indent {
+            self.s.push(' ');
+        }
+    }
+
+    /// Prints `docs` as a Haddock comment of what follows.
+    fn doc(&mut self, docs: &Docs) {
+        if let Some(docs) = &docs.contents {
+            for (i, line) in docs.trim().lines().enumerate() {
+                self.s.push_str(if i == 0 { "-- |" } else { "--" });
+                if !line.trim().is_empty() {
+                    self.s.push(' ');
+                    self.s.push_str(line.trim());
+                }
+                self.newline();
+            }
+        }
+    }
+
+    /// Prints `docs` as a Haddock comment of what precedes, on lines of
+    /// their own.
+    fn doc_after(&mut self, docs: &Docs) {
+        if let Some(docs) = &docs.contents {
+            self.indent();
+            for (i, line) in docs.trim().lines().enumerate() {
+                self.newline();
+                self.s.push_str(if i == 0 { "-- ^" } else { "--" });
+                if !line.trim().is_empty() {
+                    self.s.push(' ');
+                    self.s.push_str(line.trim());
+                }
+            }
+            self.dedent();
+        }
+    }
+}
+
+impl std::fmt::Write for HsSource {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl std::ops::Deref for HsSource {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.s
+    }
+}
+
+impl From<HsSource> for String {
+    fn from(s: HsSource) -> String {
+        s.s
+    }
+}
+
+/// Returns the Haskell type of `ty`, which is parenthesized unless it's a
+/// single word, a list or a tuple.
+fn ty_name(iface: &Interface, ty: &Type) -> String {
+    match ty {
+        Type::Unit => "()".to_string(),
+        Type::Bool => "Bool".to_string(),
+        Type::U8 => "Word8".to_string(),
+        Type::S8 => "Int8".to_string(),
+        Type::U16 => "Word16".to_string(),
+        Type::S16 => "Int16".to_string(),
+        Type::U32 => "Word32".to_string(),
+        Type::S32 => "Int32".to_string(),
+        Type::U64 => "Word64".to_string(),
+        Type::S64 => "Int64".to_string(),
+        Type::Float32 => "Float".to_string(),
+        Type::Float64 => "Double".to_string(),
+        Type::Char => "Char".to_string(),
+        Type::String => "Text".to_string(),
+        Type::Handle(_) => unimplemented!("resources not supported yet by Haskell hosts"),
+        Type::Id(id) => {
+            let ty = &iface.types[*id];
+            if let Some(name) = &ty.name {
+                return hs_type(name);
+            }
+            match &ty.kind {
+                TypeDefKind::Type(t) => ty_name(iface, t),
+                TypeDefKind::List(t) => list_name(iface, t),
+                TypeDefKind::Tuple(t) => tuple_name(iface, &t.types),
+                TypeDefKind::Option(t) => format!("(Maybe {})", ty_name(iface, t)),
+                TypeDefKind::Expected(e) => format!(
+                    "(Either {} {})",
+                    ty_name(iface, &e.err),
+                    ty_name(iface, &e.ok)
+                ),
+                TypeDefKind::Record(_)
+                | TypeDefKind::Flags(_)
+                | TypeDefKind::Variant(_)
+                | TypeDefKind::Enum(_)
+                | TypeDefKind::Union(_) => unreachable!("anonymous named type"),
+                TypeDefKind::Future(_) => todo!("generate for future"),
+                TypeDefKind::Stream(_) => todo!("generate for stream"),
+            }
+        }
+    }
+}
+
+fn list_name(iface: &Interface, ty: &Type) -> String {
+    match ty {
+        Type::U8 => "ByteString".to_string(),
+        ty => format!("[{}]", ty_name(iface, ty)),
+    }
+}
+
+/// Tuples of one type are that type, and empty tuples `()`.
+fn tuple_name(iface: &Interface, types: &[Type]) -> String {
+    let types = types
+        .iter()
+        .map(|ty| ty_name(iface, ty))
+        .collect::<Vec<_>>();
+    tuple(&types)
+}
+
+/// Returns a tuple of `items`, which is the item itself if there's only one.
+fn tuple(items: &[String]) -> String {
+    match items {
+        [item] => item.clone(),
+        _ => format!("({})", items.join(", ")),
+    }
+}
+
+/// Returns the application of the constructor `ctor` to `args`.
+fn apply(ctor: &str, args: &[String]) -> String {
+    if args.is_empty() {
+        return ctor.to_string();
+    }
+    format!("({} {})", ctor, args.join(" "))
+}
+
+/// Returns `op` converted to the integer type `ty`.
+fn convert(op: &str, ty: &str) -> String {
+    format!("(fromIntegral {} :: {})", op, ty)
+}
+
+/// Returns the arm of a `case` failing on the discriminants not of `ty`.
+fn invalid_discriminant(ty: &str) -> (String, String, String) {
+    let action = format!("fail \"invalid discriminant of {}\"", ty);
+    ("_".to_string(), String::new(), action)
+}
+
+/// Escapes names of values which are keywords in Haskell, functions of the
+/// `Prelude` and the modules the generated code imports, or locals of the
+/// generated functions.
+fn hs_ident(name: &str) -> String {
+    let mut ret = name.to_string();
+    match name {
+        "case" | "class" | "data" | "default" | "deriving" | "do" | "else" | "foreign" | "if"
+        | "import" | "in" | "infix" | "infixl" | "infixr" | "instance" | "let" | "module"
+        | "newtype" | "of" | "then" | "type" | "where" | "forall" | "return" | "fail"
+        | "fromIntegral" | "fromEnum" | "toEnum" | "length" | "zip" | "error" | "not" | "map"
+        | "show" | "id" | "print" | "pure" | "maybe" | "either" | "fst" | "snd" | "head"
+        | "tail" | "last" | "init" | "null" | "elem" | "lookup" | "filter" | "reverse"
+        | "concat" | "and" | "or" | "any" | "all" | "sum" | "product" | "max" | "min" | "div"
+        | "mod" | "rem" | "quot" | "seq" | "read" | "lines" | "words" | "take" | "drop"
+        | "forM" | "forM_" | "bit" | "testBit" | "chr" | "ord" | "inst" | "args" | "imports"
+        | "host" | "addToImports" => ret.push('\''),
+        _ => {}
+    }
+    ret
+}
+
+/// Returns the name of the type named `name`, escaping those the generated
+/// code relies on.
+fn hs_type(name: &str) -> String {
+    let mut ret = name.to_camel_case();
+    match ret.as_str() {
+        "Bool" | "Char" | "Double" | "Float" | "Int" | "Integer" | "Int8" | "Int16" | "Int32"
+        | "Int64" | "Word" | "Word8" | "Word16" | "Word32" | "Word64" | "Text" | "ByteString"
+        | "String" | "Maybe" | "Either" | "IO" | "Host" | "Just" | "Nothing" | "Left" | "Right"
+        | "True" | "False" => ret.push('\''),
+        _ => {}
+    }
+    ret
+}
+
+/// Returns the name of the constructor of the case `case` of the type
+/// `ty`, which is prefixed with the type as constructors share a namespace.
+fn hs_ctor(ty: &str, case: &str) -> String {
+    format!("{}{}", ty.to_camel_case(), case.to_camel_case())
+}
+
+/// Returns the name of the field `field` of the record `ty`, which is
+/// prefixed with the record as fields share a namespace.
+fn hs_field(ty: &str, field: &str) -> String {
+    format!("{}{}", ty.to_mixed_case(), field.to_camel_case())
+}
+
+/// Returns the address `offset` bytes after `ptr`.
+fn addr(ptr: &str, offset: i32) -> String {
+    match offset {
+        0 => ptr.to_string(),
+        _ => format!("({} + {})", ptr, offset),
+    }
+}
+
+fn wasm_ty_ctor(ty: WasmType) -> &'static str {
+    match ty {
+        WasmType::I32 => "W.TI32",
+        WasmType::I64 => "W.TI64",
+        WasmType::F32 => "W.TF32",
+        WasmType::F64 => "W.TF64",
+    }
+}
+
+fn wasm_ty_getter(ty: WasmType) -> &'static str {
+    match ty {
+        WasmType::I32 => "W.asI32",
+        WasmType::I64 => "W.asI64",
+        WasmType::F32 => "W.asF32",
+        WasmType::F64 => "W.asF64",
+    }
+}
+
+/// Wraps `val` of `ty` in a `W.Value`.
+fn wasm_value(ty: WasmType, val: &str) -> String {
+    match ty {
+        WasmType::I32 => format!("W.I32 {}", val),
+        WasmType::I64 => format!("W.I64 {}", val),
+        WasmType::F32 => format!("W.F32 {}", val),
+        WasmType::F64 => format!("W.F64 {}", val),
+    }
+}
@@ -0,0 +1,138 @@
+use std::path::Path;
+use wai_bindgen_gen_core::{wai_parser::Interface, Files, Generator};
+
+fn generate(imports: &[Interface], exports: &[Interface]) -> Files {
+    let mut files = Files::default();
+    wai_bindgen_gen_haskell::Opts::default()
+        .build()
+        .generate_all(imports, exports, &mut files);
+    files
+}
+
+fn file<'a>(files: &'a Files, name: &str) -> &'a str {
+    let (_, contents) = files.iter().find(|(n, _)| *n == name).unwrap();
+    std::str::from_utf8(contents).unwrap()
+}
+
+/// The interface the tests below generate bindings for.
+fn greeter() -> Interface {
+    Interface::parse(
+        "greeter",
+        "
+            record point { x: u32, y: u32 }
+            variant shape { circle(float64), none }
+            greet: func(name: string, at: point) -> list<u8>
+            area: func(s: shape) -> option<float64>
+        ",
+    )
+    .unwrap()
+}
+
+#[test]
+fn codegen_interfaces() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/codegen");
+    for entry in std::fs::read_dir(&root).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|s| s.to_str()) != Some("wai") {
+            continue;
+        }
+        let iface = Interface::parse_file(&path).unwrap();
+        if iface.resources.len() > 0 || iface.functions.iter().any(|f| f.is_async) {
+            continue;
+        }
+        for (imports, exports) in [(vec![iface.clone()], vec![]), (vec![], vec![iface])] {
+            let files = generate(&imports, &exports);
+            let names = files.iter().map(|(n, _)| n).collect::<Vec<_>>();
+            assert_eq!(names.len(), 2, "{}: {:?}", path.display(), names);
+            assert!(file(&files, "WaiRuntime.hs").contains("class Instance i where"));
+        }
+    }
+}
+
+#[test]
+fn imports_and_exports() {
+    let iface = greeter();
+
+    let files = generate(std::slice::from_ref(&iface), &[]);
+    let hs = file(&files, "Greeter.hs");
+    assert!(hs.contains("module Greeter where"), "{hs}");
+    assert!(
+        hs.contains("data Point = Point\n  { pointX :: Word32\n  , pointY :: Word32\n  }"),
+        "{hs}"
+    );
+    assert!(
+        hs.contains("data Shape\n  = ShapeCircle Double\n  | ShapeNone\n"),
+        "{hs}"
+    );
+    assert!(
+        hs.contains("greet :: W.Instance i => i -> Text -> Point -> IO ByteString"),
+        "{hs}"
+    );
+    assert!(hs.contains("greet inst name at = do"), "{hs}");
+    assert!(hs.contains("W.call inst \"greet\" ["), "{hs}");
+    assert!(!hs.contains("class Host"), "{hs}");
+
+    let files = generate(&[], &[iface]);
+    let hs = file(&files, "Greeter.hs");
+    assert!(hs.contains("class Host h where"), "{hs}");
+    assert!(
+        hs.contains("  greet :: h -> Text -> Point -> IO ByteString"),
+        "{hs}"
+    );
+    assert!(
+        hs.contains("  area :: h -> Shape -> IO (Maybe Double)"),
+        "{hs}"
+    );
+    assert!(
+        hs.contains("W.define imports \"greeter\" \"greet\""),
+        "{hs}"
+    );
+    assert!(hs.contains("<- Greeter.greet host "), "{hs}");
+}
+
+/// Checks the bindings generated for `greeter` against the files in
+/// `tests/golden`. Run the test with the environment variable `BLESS` set to
+/// update them.
+#[test]
+fn golden() {
+    let iface = greeter();
+    check_golden("import", &generate(std::slice::from_ref(&iface), &[]));
+    check_golden("export", &generate(&[], &[iface]));
+}
+
+fn check_golden(dir: &str, files: &Files) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(dir);
+    let bless = std::env::var_os("BLESS").is_some();
+    if bless {
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+    }
+    for (name, contents) in files.iter() {
+        let path = dir.join(name);
+        if bless {
+            std::fs::write(&path, contents).unwrap();
+            continue;
+        }
+        let expected = std::fs::read(&path).unwrap_or_else(|e| {
+            panic!(
+                "failed to read {}: {}\nthis can be fixed with BLESS=1",
+                path.display(),
+                e
+            )
+        });
+        assert_eq!(
+            String::from_utf8_lossy(&expected),
+            String::from_utf8_lossy(contents),
+            "{} doesn't match the generated file\nthis can be fixed with BLESS=1",
+            path.display()
+        );
+    }
+    assert_eq!(
+        std::fs::read_dir(&dir).unwrap().count(),
+        files.iter().count(),
+        "{} has files which aren't generated anymore\nthis can be fixed with BLESS=1",
+        dir.display()
+    );
+}
@@ -0,0 +1,67 @@
+-- Generated by `wai-bindgen` from the `greeter` interface.
+{-# OPTIONS_GHC -Wno-unused-imports -Wno-unused-matches -Wno-unused-local-binds -Wno-name-shadowing #-}
+
+module Greeter where
+
+import Control.Monad (forM, forM_)
+import Data.Bits ((.|.), bit, testBit)
+import Data.ByteString (ByteString)
+import Data.Char (chr, ord)
+import Data.Int (Int8, Int16, Int32, Int64)
+import Data.Text (Text)
+import Data.Word (Word8, Word16, Word32, Word64)
+import GHC.Float (castDoubleToWord64, castFloatToWord32, castWord32ToFloat, castWord64ToDouble)
+import qualified WaiRuntime as W
+
+data Point = Point
+  { pointX :: Word32
+  , pointY :: Word32
+  }
+  deriving (Show, Eq)
+
+data Shape
+  = ShapeCircle Double
+  | ShapeNone
+  deriving (Show, Eq)
+
+-- | The functions of the `greeter` interface, implemented by the host.
+class Host h where
+  greet :: h -> Text -> Point -> IO ByteString
+  area :: h -> Shape -> IO (Maybe Double)
+
+-- | Defines the functions of @host@ as the imports of @imports@.
+addToImports :: (W.Imports l, Host h) => l -> h -> IO ()
+addToImports imports host = do
+  W.define imports "greeter" "greet" [W.TI32, W.TI32, W.TI32, W.TI32, W.TI32] [] $ \inst args -> do
+    let arg = W.asI32 (args !! 0)
+    let arg0 = W.asI32 (args !! 1)
+    let arg1 = W.asI32 (args !! 2)
+    let arg2 = W.asI32 (args !! 3)
+    let arg3 = W.asI32 (args !! 4)
+    let ptr = arg
+    let len = arg0
+    string <- W.liftText inst ptr len
+    ret <- Greeter.greet host string (Point (fromIntegral arg1 :: Word32) (fromIntegral arg2 :: Word32))
+    (ptr4, len5) <- W.lowerBytes inst "canonical_abi_realloc" ret
+    W.storeI32 inst (arg3 + 4) len5
+    W.storeI32 inst arg3 ptr4
+    return []
+  W.define imports "greeter" "area" [W.TI32, W.TF64, W.TI32] [] $ \inst args -> do
+    let arg = W.asI32 (args !! 0)
+    let arg0 = W.asF64 (args !! 1)
+    let arg1 = W.asI32 (args !! 2)
+    variant <- case arg of
+      0 -> return (ShapeCircle arg0)
+      1 -> return ShapeNone
+      _ -> fail "invalid discriminant of shape"
+    ret <- Greeter.area host variant
+    case ret of
+      Nothing -> do
+        W.storeI8 inst arg1 0
+        return ()
+      Just payload2 -> do
+        W.storeI8 inst arg1 1
+        W.storeF64 inst (arg1 + 8) payload2
+        return ()
+    return []
+  return ()
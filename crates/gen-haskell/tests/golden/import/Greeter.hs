@@ -0,0 +1,55 @@
+-- Generated by `wai-bindgen` from the `greeter` interface.
+{-# OPTIONS_GHC -Wno-unused-imports -Wno-unused-matches -Wno-unused-local-binds -Wno-name-shadowing #-}
+
+module Greeter where
+
+import Control.Monad (forM, forM_)
+import Data.Bits ((.|.), bit, testBit)
+import Data.ByteString (ByteString)
+import Data.Char (chr, ord)
+import Data.Int (Int8, Int16, Int32, Int64)
+import Data.Text (Text)
+import Data.Word (Word8, Word16, Word32, Word64)
+import GHC.Float (castDoubleToWord64, castFloatToWord32, castWord32ToFloat, castWord64ToDouble)
+import qualified WaiRuntime as W
+
+data Point = Point
+  { pointX :: Word32
+  , pointY :: Word32
+  }
+  deriving (Show, Eq)
+
+data Shape
+  = ShapeCircle Double
+  | ShapeNone
+  deriving (Show, Eq)
+
+greet :: W.Instance i => i -> Text -> Point -> IO ByteString
+greet inst name at = do
+  (ptr, len) <- W.lowerText inst "canonical_abi_realloc" name
+  let (Point field field0) = at
+  ret <- W.call inst "greet" [W.I32 ptr, W.I32 len, W.I32 (fromIntegral field :: Int32), W.I32 (fromIntegral field0 :: Int32)]
+  let ret1 = W.asI32 (ret !! 0)
+  load <- W.loadI32 inst ret1
+  load2 <- W.loadI32 inst (ret1 + 4)
+  let ptr3 = load
+  let len4 = load2
+  bytes <- W.loadBytes inst ptr3 len4
+  W.free inst "canonical_abi_free" ptr3 len4 1
+  return bytes
+
+area :: W.Instance i => i -> Shape -> IO (Maybe Double)
+area inst s = do
+  (variant, variant1) <- case s of
+    ShapeCircle payload -> return (0, payload)
+    ShapeNone -> return (1, 0)
+  ret <- W.call inst "area" [W.I32 variant, W.F64 variant1]
+  let ret2 = W.asI32 (ret !! 0)
+  load <- W.loadU8 inst ret2
+  variant4 <- case load of
+    0 -> return Nothing
+    1 -> do
+      load3 <- W.loadF64 inst (ret2 + 8)
+      return (Just load3)
+    _ -> fail "invalid discriminant of option"
+  return variant4
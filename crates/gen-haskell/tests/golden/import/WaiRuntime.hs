@@ -0,0 +1,120 @@
+-- Generated by `wai-bindgen`: the runtime interface of the Haskell bindings.
+{-# LANGUAGE FlexibleContexts #-}
+{-# LANGUAGE TypeFamilies #-}
+
+module WaiRuntime
+  ( Value (..)
+  , ValType (..)
+  , asI32
+  , asI64
+  , asF32
+  , asF64
+  , Instance (..)
+  , Imports (..)
+  , alloc
+  , free
+  , liftText
+  , lowerBytes
+  , lowerText
+  ) where
+
+import Data.ByteString (ByteString)
+import qualified Data.ByteString as B
+import Data.Int (Int32, Int64)
+import Data.Text (Text)
+import Data.Text.Encoding (decodeUtf8', encodeUtf8)
+
+-- | A wasm value.
+data Value = I32 Int32 | I64 Int64 | F32 Float | F64 Double
+  deriving (Show, Eq)
+
+-- | The type of a wasm value.
+data ValType = TI32 | TI64 | TF32 | TF64
+  deriving (Show, Eq)
+
+asI32 :: Value -> Int32
+asI32 (I32 v) = v
+asI32 _ = error "expected an i32"
+
+asI64 :: Value -> Int64
+asI64 (I64 v) = v
+asI64 _ = error "expected an i64"
+
+asF32 :: Value -> Float
+asF32 (F32 v) = v
+asF32 _ = error "expected an f32"
+
+asF64 :: Value -> Double
+asF64 (F64 v) = v
+asF64 _ = error "expected an f64"
+
+-- | What the bindings need from an instance of a wasm module, which is also
+-- what imports are called with.
+--
+-- Addresses are offsets into the memory exported as @memory@ by the
+-- instance. Loads of narrow integers extend them to 32 bits, and stores
+-- truncate values to their width.
+class Instance i where
+  loadU8 :: i -> Int32 -> IO Int32
+  loadS8 :: i -> Int32 -> IO Int32
+  loadU16 :: i -> Int32 -> IO Int32
+  loadS16 :: i -> Int32 -> IO Int32
+  loadI32 :: i -> Int32 -> IO Int32
+  loadI64 :: i -> Int32 -> IO Int64
+  loadF32 :: i -> Int32 -> IO Float
+  loadF64 :: i -> Int32 -> IO Double
+  -- | @loadBytes inst addr len@ reads @len@ bytes at @addr@.
+  loadBytes :: i -> Int32 -> Int32 -> IO ByteString
+  storeI8 :: i -> Int32 -> Int32 -> IO ()
+  storeI16 :: i -> Int32 -> Int32 -> IO ()
+  storeI32 :: i -> Int32 -> Int32 -> IO ()
+  storeI64 :: i -> Int32 -> Int64 -> IO ()
+  storeF32 :: i -> Int32 -> Float -> IO ()
+  storeF64 :: i -> Int32 -> Double -> IO ()
+  storeBytes :: i -> Int32 -> ByteString -> IO ()
+  -- | @call inst name args@ calls the export @name@ of @inst@.
+  call :: i -> String -> [Value] -> IO [Value]
+
+-- | The imports a module is instantiated with.
+class Instance (Caller l) => Imports l where
+  -- | The instances calling the imports.
+  type Caller l
+  -- | @define imports module_ name params results f@ defines the import
+  -- @name@ of @module_@ as @f@, which is passed the calling instance.
+  define
+    :: l
+    -> String
+    -> String
+    -> [ValType]
+    -> [ValType]
+    -> (Caller l -> [Value] -> IO [Value])
+    -> IO ()
+
+alloc :: Instance i => i -> String -> Int32 -> Int32 -> IO Int32
+alloc inst realloc align size = do
+  ret <- call inst realloc [I32 0, I32 0, I32 align, I32 size]
+  case ret of
+    [I32 ptr] -> return ptr
+    _ -> fail (realloc ++ " didn't return a pointer")
+
+free :: Instance i => i -> String -> Int32 -> Int32 -> Int32 -> IO ()
+free inst free' ptr size align = do
+  _ <- call inst free' [I32 ptr, I32 size, I32 align]
+  return ()
+
+liftText :: Instance i => i -> Int32 -> Int32 -> IO Text
+liftText inst ptr len = do
+  bytes <- loadBytes inst ptr len
+  case decodeUtf8' bytes of
+    Right s -> return s
+    Left _ -> fail "invalid UTF-8 string"
+
+lowerBytes :: Instance i => i -> String -> ByteString -> IO (Int32, Int32)
+lowerBytes inst realloc bytes = do
+  let len = fromIntegral (B.length bytes)
+  ptr <- alloc inst realloc 1 len
+  storeBytes inst ptr bytes
+  return (ptr, len)
+
+lowerText :: Instance i => i -> String -> Text -> IO (Int32, Int32)
+lowerText inst realloc s = lowerBytes inst realloc (encodeUtf8 s)
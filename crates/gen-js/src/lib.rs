@@ -5,7 +5,7 @@ use std::mem;
 use wai_bindgen_gen_core::wai_parser::abi::{
     AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmType,
 };
-use wai_bindgen_gen_core::{wai_parser::*, Direction, Files, Generator};
+use wai_bindgen_gen_core::{union_case_names, wai_parser::*, Direction, Files, Generator};
 
 #[derive(Default)]
 pub struct Js {
@@ -41,6 +41,15 @@ struct Exports {
 pub struct Opts {
     #[cfg_attr(feature = "structopt", structopt(long = "no-typescript"))]
     pub no_typescript: bool,
+
+    /// Represent `char`s as their code points, numbers which may be unpaired
+    /// surrogates, rather than as strings of one character.
+    ///
+    /// JS strings can contain unpaired surrogates, which aren't valid
+    /// `char`s, so by default they're rejected when passed to or from wasm.
+    /// With this option only the range of code points is checked.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub char_as_code_point: bool,
 }
 
 impl Opts {
@@ -59,6 +68,7 @@ enum Intrinsic {
     DataView,
     ValidateGuestChar,
     ValidateHostChar,
+    ValidateCodePoint,
     ValidateFlags,
     ValidateFlags64,
     /// Implementation of https://tc39.es/ecma262/#sec-tostring.
@@ -70,6 +80,9 @@ enum Intrinsic {
     Utf8Decoder,
     Utf8Encode,
     Utf8EncodedLen,
+    Utf16Decoder,
+    Utf16Encode,
+    CompactUtf16Decode,
     Slab,
     Promises,
     WithCurrentPromise,
@@ -85,6 +98,7 @@ impl Intrinsic {
             Intrinsic::DataView => "data_view",
             Intrinsic::ValidateGuestChar => "validate_guest_char",
             Intrinsic::ValidateHostChar => "validate_host_char",
+            Intrinsic::ValidateCodePoint => "validate_code_point",
             Intrinsic::ValidateFlags => "validate_flags",
             Intrinsic::ValidateFlags64 => "validate_flags64",
             Intrinsic::ToString => "to_string",
@@ -95,6 +109,9 @@ impl Intrinsic {
             Intrinsic::Utf8Decoder => "UTF8_DECODER",
             Intrinsic::Utf8Encode => "utf8_encode",
             Intrinsic::Utf8EncodedLen => "utf8_encoded_len",
+            Intrinsic::Utf16Decoder => "UTF16_DECODER",
+            Intrinsic::Utf16Encode => "utf16_encode",
+            Intrinsic::CompactUtf16Decode => "compact_utf16_decode",
             Intrinsic::Slab => "Slab",
             Intrinsic::Promises => "PROMISES",
             Intrinsic::WithCurrentPromise => "with_current_promise",
@@ -159,6 +176,7 @@ impl Js {
             | Type::Float32
             | Type::Float64 => self.src.ts("number"),
             Type::U64 | Type::S64 => self.src.ts("bigint"),
+            Type::Char if self.opts.char_as_code_point => self.src.ts("number"),
             Type::Char => self.src.ts("string"),
             Type::Handle(id) => self.src.ts(&iface.resources[*id].name.to_camel_case()),
             Type::String => self.src.ts("string"),
@@ -232,10 +250,45 @@ impl Js {
     }
 
     fn docs(&mut self, docs: &Docs) {
-        match &docs.contents {
-            Some(docs) => self.docs_raw(docs),
-            None => (),
+        let examples = docs
+            .examples
+            .iter()
+            .filter(|e| e.lang.is_none() || e.is_in(&["js", "javascript", "ts", "typescript"]))
+            .collect::<Vec<_>>();
+        if examples.is_empty() {
+            if let Some(docs) = &docs.contents {
+                self.docs_raw(docs);
+            }
+            return;
         }
+        let mut raw = docs
+            .contents
+            .as_deref()
+            .unwrap_or("")
+            .trim_end()
+            .to_string();
+        for example in examples {
+            if !raw.is_empty() {
+                raw.push_str("\n\n");
+            }
+            raw.push_str("@example\n");
+            raw.push_str(example.code.trim_end());
+        }
+        raw.push('\n');
+        self.docs_raw(&raw);
+    }
+
+    /// Declares the case which discriminants of the `@non-exhaustive` type
+    /// `name` that these bindings don't know about are lifted into.
+    fn unknown_case(&mut self, name: &str) {
+        self.docs_raw("A case added by a newer version of the interface, along with its\ndiscriminant. It can't be passed back to wasm.\n");
+        self.src.ts(&format!(
+            "export interface {} {{\n",
+            format!("{}_unknown", name).to_camel_case()
+        ));
+        self.src.ts("tag: \"unknown\",\n");
+        self.src.ts("val: number,\n");
+        self.src.ts("}\n");
     }
 
     fn ts_func(&mut self, iface: &Interface, func: &Function) {
@@ -403,21 +456,14 @@ impl Generator for Js {
         docs: &Docs,
     ) {
         self.docs(docs);
-        let repr = js_flags_repr(flags);
-        let ty = repr.ty();
-        let suffix = repr.suffix();
         self.src
-            .ts(&format!("export type {} = {ty};\n", name.to_camel_case()));
-        let name = name.to_shouty_snake_case();
-        for (i, flag) in flags.flags.iter().enumerate() {
-            let flag = flag.name.to_shouty_snake_case();
-            let ident = format!("{name}_{flag}");
-            self.src
-                .js(&format!("const {ident} = {}{suffix};\n", 1u128 << i));
+            .ts(&format!("export interface {} {{\n", name.to_camel_case()));
+        for flag in flags.flags.iter() {
+            self.docs(&flag.docs);
             self.src
-                .ts(&format!("export const {ident} = {}{suffix};\n", 1u128 << i));
-            self.src.export(ident);
+                .ts(&format!("{}?: boolean,\n", flag.name.to_mixed_case()));
         }
+        self.src.ts("}\n");
     }
 
     fn type_variant(
@@ -438,6 +484,12 @@ impl Generator for Js {
             self.src
                 .ts(&format!("{}_{}", name, case.name).to_camel_case());
         }
+        if variant.non_exhaustive {
+            self.src.ts(&format!(
+                " | {}",
+                format!("{}_unknown", name).to_camel_case()
+            ));
+        }
         self.src.ts(";\n");
         for case in variant.cases.iter() {
             self.docs(&case.docs);
@@ -455,6 +507,9 @@ impl Generator for Js {
             }
             self.src.ts("}\n");
         }
+        if variant.non_exhaustive {
+            self.unknown_case(name);
+        }
     }
 
     fn type_union(
@@ -467,23 +522,42 @@ impl Generator for Js {
     ) {
         self.docs(docs);
         let name = name.to_camel_case();
+        let case_names = union_case_names(iface, union);
         self.src.ts(&format!("export type {name} = "));
-        for i in 0..union.cases.len() {
+        for (i, case_name) in case_names.iter().enumerate() {
             if i > 0 {
                 self.src.ts(" | ");
             }
-            self.src.ts(&format!("{name}{i}"));
+            self.src.ts(&format!("{name}{case_name}"));
         }
         self.src.ts(";\n");
-        for (i, case) in union.cases.iter().enumerate() {
+        for (i, (case, case_name)) in union.cases.iter().zip(&case_names).enumerate() {
             self.docs(&case.docs);
-            self.src.ts(&format!("export interface {name}{i} {{\n"));
+            self.src
+                .ts(&format!("export interface {name}{case_name} {{\n"));
             self.src.ts(&format!("tag: {i},\n"));
             self.src.ts("val: ");
             self.print_ty(iface, &case.ty);
             self.src.ts(",\n");
             self.src.ts("}\n");
         }
+
+        // An object with a constructor for each case, named after it, so
+        // that cases don't have to be built from their index.
+        self.src.js(&format!("const {name} = Object.freeze({{\n"));
+        self.src.ts(&format!("export const {name}: {{\n"));
+        for (i, (case, case_name)) in union.cases.iter().zip(&case_names).enumerate() {
+            let ctor = case_name.to_mixed_case();
+            self.src
+                .js(&format!("{ctor}: (val) => ({{ tag: {i}, val }}),\n"));
+            self.docs(&case.docs);
+            self.src.ts(&format!("{ctor}(val: "));
+            self.print_ty(iface, &case.ty);
+            self.src.ts(&format!("): {name}{case_name},\n"));
+        }
+        self.src.js("});\n");
+        self.src.ts("};\n");
+        self.src.export(name);
     }
 
     fn type_option(
@@ -566,7 +640,16 @@ impl Generator for Js {
             }
             self.src.ts(&format!("\"{}\"", case.name));
         }
+        if enum_.non_exhaustive {
+            self.src.ts(&format!(
+                " | {}",
+                format!("{}_unknown", name).to_camel_case()
+            ));
+        }
         self.src.ts(";\n");
+        if enum_.non_exhaustive {
+            self.unknown_case(name);
+        }
     }
 
     fn type_resource(&mut self, _iface: &Interface, ty: ResourceId) {
@@ -610,6 +693,7 @@ impl Generator for Js {
         self.ts_func(iface, func);
 
         let mut f = FunctionBindgen::new(self, false, params);
+        f.string_encoding = iface.string_encoding_of(func).unwrap_or_default();
         iface.call(
             AbiVariant::GuestImport,
             LiftLower::LiftArgsLowerResults,
@@ -711,6 +795,7 @@ impl Generator for Js {
         }
         let mut f = FunctionBindgen::new(self, false, params);
         f.src_object = src_object;
+        f.string_encoding = iface.string_encoding_of(func).unwrap_or_default();
         iface.call(
             AbiVariant::GuestExport,
             LiftLower::LowerArgsLiftResults,
@@ -1111,6 +1196,13 @@ impl Generator for Js {
 
         let exports = mem::take(&mut self.src);
 
+        if self.opts.char_as_code_point {
+            let note = "// `char`s are represented as their code points, numbers which may be\n\
+                        // unpaired surrogates, rather than as strings of one character.\n";
+            self.src.js(note);
+            self.src.ts(note);
+        }
+
         if mem::take(&mut self.needs_ty_option) {
             self.src
                 .ts("export type Option<T> = { tag: \"none\" } | { tag: \"some\", val; T };\n");
@@ -1180,6 +1272,7 @@ struct FunctionBindgen<'a> {
     needs_free: Option<String>,
     params: Vec<String>,
     src_object: String,
+    string_encoding: StringEncoding,
 }
 
 impl FunctionBindgen<'_> {
@@ -1196,6 +1289,7 @@ impl FunctionBindgen<'_> {
             needs_free: None,
             params,
             src_object: "this".to_string(),
+            string_encoding: StringEncoding::UTF8,
         }
     }
 
@@ -1363,6 +1457,15 @@ impl Bindgen for FunctionBindgen<'_> {
                 results.push(format!("+{}", operands[0]));
             }
 
+            // With `char_as_code_point`, chars are only checked to be in the
+            // range of code points, which allows unpaired surrogates.
+            Instruction::CharFromI32 | Instruction::I32FromChar
+                if self.gen.opts.char_as_code_point =>
+            {
+                let validate = self.gen.intrinsic(Intrinsic::ValidateCodePoint);
+                results.push(format!("{}({})", validate, operands[0]));
+            }
+
             // Validate that i32 values coming from wasm are indeed valid code
             // points.
             Instruction::CharFromI32 => {
@@ -1531,27 +1634,44 @@ impl Bindgen for FunctionBindgen<'_> {
             }
 
             Instruction::FlagsLower { flags, .. } => {
+                // Flags are represented as objects with a boolean for each
+                // flag, where missing flags are unset.
                 let repr = js_flags_repr(flags);
-                let validate = match repr {
-                    JsFlagsRepr::Number => self.gen.intrinsic(Intrinsic::ValidateFlags),
-                    JsFlagsRepr::Bigint => self.gen.intrinsic(Intrinsic::ValidateFlags64),
-                };
-                let op0 = &operands[0];
-                let len = flags.flags.len();
                 let n = repr.suffix();
                 let tmp = self.tmp();
-                let mask = (1u128 << len) - 1;
                 self.src.js(&format!(
-                    "const flags{tmp} = {validate}({op0}, {mask}{n});\n"
+                    "const flags{tmp} = {};
+                    if (typeof flags{tmp} !== 'object' || flags{tmp} === null) {{
+                        throw new TypeError('flags were not an object');
+                    }}
+                    ",
+                    operands[0],
                 ));
+                let mut bits = flags
+                    .flags
+                    .iter()
+                    .enumerate()
+                    .map(|(i, flag)| {
+                        format!(
+                            "(flags{tmp}.{} ? {}{n} : 0{n})",
+                            flag.name.to_mixed_case(),
+                            1u128 << i
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" | ");
+                if bits.is_empty() {
+                    bits = format!("0{n}");
+                }
+                self.src.js(&format!("const bits{tmp} = {bits};\n"));
                 match repr {
                     JsFlagsRepr::Number => {
-                        results.push(format!("flags{}", tmp));
+                        results.push(format!("bits{}", tmp));
                     }
                     JsFlagsRepr::Bigint => {
                         for i in 0..flags.repr().count() {
                             let i = 32 * i;
-                            results.push(format!("Number((flags{tmp} >> {i}n) & 0xffffffffn)",));
+                            results.push(format!("Number((bits{tmp} >> {i}n) & 0xffffffffn)",));
                         }
                     }
                 }
@@ -1579,7 +1699,19 @@ impl Bindgen for FunctionBindgen<'_> {
                 };
                 let len = flags.flags.len();
                 let mask = (1u128 << len) - 1;
-                results.push(format!("{validate}({operand}, {mask}{n})"));
+                self.src.js(&format!(
+                    "const bits{tmp} = {validate}({operand}, {mask}{n});\n"
+                ));
+                let mut result = "Object.freeze({\n".to_string();
+                for (i, flag) in flags.flags.iter().enumerate() {
+                    result.push_str(&format!(
+                        "{}: Boolean(bits{tmp} & {}{n}),\n",
+                        flag.name.to_mixed_case(),
+                        1u128 << i
+                    ));
+                }
+                result.push_str("})");
+                results.push(result);
             }
 
             Instruction::VariantPayloadName => results.push("e".to_string()),
@@ -1637,8 +1769,17 @@ impl Bindgen for FunctionBindgen<'_> {
 
                 let tmp = self.tmp();
 
+                // Unknown discriminants are kept, so they're only evaluated
+                // once.
+                let tag = if variant.non_exhaustive {
+                    self.src
+                        .js(&format!("const tag{} = {};\n", tmp, operands[0]));
+                    format!("tag{}", tmp)
+                } else {
+                    operands[0].clone()
+                };
                 self.src.js(&format!("let variant{};\n", tmp));
-                self.src.js(&format!("switch ({}) {{\n", operands[0]));
+                self.src.js(&format!("switch ({}) {{\n", tag));
                 for (i, (case, (block, block_results))) in
                     variant.cases.iter().zip(blocks).enumerate()
                 {
@@ -1658,10 +1799,17 @@ impl Bindgen for FunctionBindgen<'_> {
                 }
                 let variant_name = name.to_camel_case();
                 self.src.js("default:\n");
-                self.src.js(&format!(
-                    "throw new RangeError(\"invalid variant discriminant for {}\");\n",
-                    variant_name
-                ));
+                if variant.non_exhaustive {
+                    self.src.js(&format!(
+                        "variant{} = {{ tag: \"unknown\", val: {} }};\n",
+                        tmp, tag
+                    ));
+                } else {
+                    self.src.js(&format!(
+                        "throw new RangeError(\"invalid variant discriminant for {}\");\n",
+                        variant_name
+                    ));
+                }
                 self.src.js("}\n");
                 results.push(format!("variant{}", tmp));
             }
@@ -1960,9 +2108,16 @@ impl Bindgen for FunctionBindgen<'_> {
             Instruction::EnumLift { name, enum_, .. } => {
                 let tmp = self.tmp();
 
+                let tag = if enum_.non_exhaustive {
+                    self.src.js(&format!("const tag{tmp} = {};\n", operands[0]));
+                    format!("tag{tmp}")
+                } else {
+                    operands[0].clone()
+                };
+
                 self.src.js(&format!("let enum{tmp};\n"));
 
-                self.src.js(&format!("switch ({}) {{\n", operands[0]));
+                self.src.js(&format!("switch ({tag}) {{\n"));
                 for (i, case) in enum_.cases.iter().enumerate() {
                     self.src.js(&format!(
                         "\
@@ -1974,15 +2129,26 @@ impl Bindgen for FunctionBindgen<'_> {
                         case = case.name
                     ));
                 }
-                self.src.js(&format!(
-                    "\
-                        default: {{
-                            throw new RangeError(\"invalid discriminant specified for {name}\");
+                if enum_.non_exhaustive {
+                    self.src.js(&format!(
+                        "\
+                            default: {{
+                                enum{tmp} = {{ tag: \"unknown\", val: {tag} }};
+                            }}
                         }}
-                    }}
-                    ",
-                    name = name.to_camel_case()
-                ));
+                        "
+                    ));
+                } else {
+                    self.src.js(&format!(
+                        "\
+                            default: {{
+                                throw new RangeError(\"invalid discriminant specified for {name}\");
+                            }}
+                        }}
+                        ",
+                        name = name.to_camel_case()
+                    ));
+                }
 
                 results.push(format!("enum{tmp}"));
             }
@@ -2051,13 +2217,33 @@ impl Bindgen for FunctionBindgen<'_> {
                 self.needs_realloc = Some(realloc.to_string());
                 let tmp = self.tmp();
 
-                let encode = self.gen.intrinsic(Intrinsic::Utf8Encode);
-                self.src.js(&format!(
-                    "const ptr{} = {}({}, realloc, memory);\n",
-                    tmp, encode, operands[0],
-                ));
-                let encoded_len = self.gen.intrinsic(Intrinsic::Utf8EncodedLen);
-                self.src.js(&format!("const len{tmp} = {encoded_len}();\n"));
+                match self.string_encoding {
+                    StringEncoding::UTF8 => {
+                        let encode = self.gen.intrinsic(Intrinsic::Utf8Encode);
+                        self.src.js(&format!(
+                            "const ptr{} = {}({}, realloc, memory);\n",
+                            tmp, encode, operands[0],
+                        ));
+                        let encoded_len = self.gen.intrinsic(Intrinsic::Utf8EncodedLen);
+                        self.src.js(&format!("const len{tmp} = {encoded_len}();\n"));
+                    }
+                    // Compact UTF-16 strings can always be written as UTF-16,
+                    // which is flagged by the high bit of their length.
+                    encoding => {
+                        let encode = self.gen.intrinsic(Intrinsic::Utf16Encode);
+                        let flag = if encoding == StringEncoding::CompactUTF16 {
+                            " | 0x80000000"
+                        } else {
+                            ""
+                        };
+                        self.src.js(&format!(
+                            "const str{tmp} = {};\n\
+                             const ptr{tmp} = {encode}(str{tmp}, realloc, memory);\n\
+                             const len{tmp} = str{tmp}.length{flag};\n",
+                            operands[0],
+                        ));
+                    }
+                }
                 results.push(format!("ptr{}", tmp));
                 results.push(format!("len{}", tmp));
             }
@@ -2068,16 +2254,48 @@ impl Bindgen for FunctionBindgen<'_> {
                     .js(&format!("const ptr{} = {};\n", tmp, operands[0]));
                 self.src
                     .js(&format!("const len{} = {};\n", tmp, operands[1]));
-                let decoder = self.gen.intrinsic(Intrinsic::Utf8Decoder);
-                let result = format!(
-                    "{}.decode(new Uint8Array(memory.buffer, ptr{}, len{1}))",
-                    decoder, tmp,
-                );
+                let (result, size, align) = match self.string_encoding {
+                    StringEncoding::UTF8 => {
+                        let decoder = self.gen.intrinsic(Intrinsic::Utf8Decoder);
+                        (
+                            format!(
+                                "{}.decode(new Uint8Array(memory.buffer, ptr{}, len{1}))",
+                                decoder, tmp,
+                            ),
+                            format!("len{}", tmp),
+                            1,
+                        )
+                    }
+                    StringEncoding::UTF16 => {
+                        let decoder = self.gen.intrinsic(Intrinsic::Utf16Decoder);
+                        (
+                            format!(
+                                "{}.decode(new Uint16Array(memory.buffer, ptr{}, len{1}))",
+                                decoder, tmp,
+                            ),
+                            format!("len{} * 2", tmp),
+                            2,
+                        )
+                    }
+                    StringEncoding::CompactUTF16 => {
+                        self.gen.intrinsic(Intrinsic::Utf16Decoder);
+                        let decode = self.gen.intrinsic(Intrinsic::CompactUtf16Decode);
+                        (
+                            format!("{}(memory, ptr{}, len{1})", decode, tmp),
+                            format!(
+                                "len{0} & 0x80000000 ? (len{0} & 0x7fffffff) * 2 : len{0}",
+                                tmp
+                            ),
+                            2,
+                        )
+                    }
+                };
                 match free {
                     Some(free) => {
                         self.needs_free = Some(free.to_string());
                         self.src.js(&format!("const list{} = {};\n", tmp, result));
-                        self.src.js(&format!("free(ptr{}, len{0}, 1);\n", tmp));
+                        self.src
+                            .js(&format!("free(ptr{}, {}, {});\n", tmp, size, align));
                         results.push(format!("list{}", tmp));
                     }
                     None => results.push(result),
@@ -2448,6 +2666,14 @@ impl Js {
                 }
             "),
 
+            Intrinsic::ValidateCodePoint => self.src.js("
+                function validate_code_point(i) {
+                    if (!Number.isInteger(i) || i < 0 || i > 0x10ffff) \
+                        throw new RangeError(`not a valid code point`);
+                    return i;
+                }
+            "),
+
             Intrinsic::ValidateFlags => self.src.js("
                 function validate_flags(flags, mask) {
                     if (!Number.isInteger(flags)) \
@@ -2551,6 +2777,45 @@ impl Js {
                 }
             "),
 
+            Intrinsic::Utf16Decoder => self
+                .src
+                .js("const UTF16_DECODER = new TextDecoder('utf-16le');\n"),
+
+            Intrinsic::Utf16Encode => self.src.js("
+                function utf16_encode(s, realloc, memory) {
+                    if (typeof s !== 'string') \
+                        throw new TypeError('expected a string');
+
+                    if (s.length === 0) \
+                        return 2;
+
+                    const ptr = realloc(0, 0, 2, s.length * 2);
+                    const dst = new Uint16Array(memory.buffer, ptr, s.length);
+                    for (let i = 0; i < s.length; i++) {
+                        dst[i] = s.charCodeAt(i);
+                    }
+                    return ptr;
+                }
+            "),
+
+            Intrinsic::CompactUtf16Decode => self.src.js("
+                function compact_utf16_decode(memory, ptr, len) {
+                    if (len & 0x80000000) {
+                        const units = len & 0x7fffffff;
+                        return UTF16_DECODER.decode(new Uint16Array(memory.buffer, ptr, units));
+                    }
+
+                    // Otherwise the string is latin1, whose bytes are the
+                    // code points of its characters.
+                    const bytes = new Uint8Array(memory.buffer, ptr, len);
+                    let s = '';
+                    for (let i = 0; i < len; i += 0x1000) {
+                        s += String.fromCharCode(...bytes.subarray(i, i + 0x1000));
+                    }
+                    return s;
+                }
+            "),
+
             Intrinsic::Slab => self.src.js("
                 class Slab {
                     constructor() {
@@ -2650,12 +2915,6 @@ enum JsFlagsRepr {
 }
 
 impl JsFlagsRepr {
-    fn ty(&self) -> &'static str {
-        match self {
-            JsFlagsRepr::Number => "number",
-            JsFlagsRepr::Bigint => "bigint",
-        }
-    }
     fn suffix(&self) -> &'static str {
         match self {
             JsFlagsRepr::Number => "",
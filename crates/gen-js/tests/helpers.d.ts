@@ -1,5 +1,7 @@
 export function getWasm(): Uint8Array;
 
+export function getTranscript(): string;
+
 export interface Wasi {
   start(instance: WebAssembly.Instance): void;
 }
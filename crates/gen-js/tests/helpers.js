@@ -1,10 +1,15 @@
 const { readFileSync } = require('fs');
+const { join } = require('path');
 const { WASI } = require('wasi');
 
 function getWasm() {
   return readFileSync(process.argv[2]);
 }
 
+function getTranscript() {
+  return readFileSync(join(__dirname, 'transcript.txt'), 'utf8');
+}
+
 class MyWasi {
   constructor(wasi) {
     this.wasi = wasi;
@@ -25,4 +30,4 @@ function addWasiToImports(importObj) {
   return new MyWasi(wasi);
 }
 
-module.exports = { getWasm, addWasiToImports };
+module.exports = { getWasm, getTranscript, addWasiToImports };
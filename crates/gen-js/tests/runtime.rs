@@ -36,6 +36,10 @@ fn execute(name: &str, wasm: &Path, ts: &Path, imports: &Path, exports: &Path) {
     fs::copy(ts, dir.join("host.ts")).unwrap();
     fs::copy("tests/helpers.d.ts", dir.join("helpers.d.ts")).unwrap();
     fs::copy("tests/helpers.js", dir.join("helpers.js")).unwrap();
+    let transcript = ts.with_file_name("transcript.txt");
+    if transcript.exists() {
+        fs::copy(&transcript, dir.join("transcript.txt")).unwrap();
+    }
     let config = dir.join("tsconfig.json");
     fs::write(
         &config,
@@ -0,0 +1,316 @@
+use serde_json::{json, Map, Value};
+use wai_bindgen_gen_core::{wai_parser::*, Files, Generator};
+
+const DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Generates a JSON Schema describing the types of an interface, so that
+/// JSON-speaking consumers such as web frontends and validation layers can
+/// check plugin data without restating its definitions.
+///
+/// Every named type becomes an entry of `$defs`, in the JSON encoding the
+/// `rest-gateway` bindings use:
+///
+/// * records are objects with a property per field, where fields of option
+///   types may be left out;
+/// * tuples are arrays with an element per field;
+/// * flags are arrays of the names of the flags which are set;
+/// * enums are the name of their case;
+/// * variants are an object with a single property, named after the case
+///   and holding its payload, or `null` for cases without one. These cases
+///   may also be given as just their name;
+/// * unions are like variants named after the index of their case;
+/// * `expected` is a variant with `ok` and `err` cases, and `option` is
+///   `null` for `none`.
+///
+/// With `--openapi` the definitions are emitted as the component schemas
+/// of an OpenAPI 3.1 document instead.
+#[derive(Default)]
+pub struct JsonSchema {
+    opts: Opts,
+    defs: Map<String, Value>,
+}
+
+#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "structopt", derive(structopt::StructOpt))]
+pub struct Opts {
+    /// Emit OpenAPI `components.schemas` rather than a JSON Schema with
+    /// `$defs`.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub openapi: bool,
+}
+
+impl Opts {
+    pub fn build(self) -> JsonSchema {
+        let mut r = JsonSchema::new();
+        r.opts = self;
+        r
+    }
+}
+
+impl JsonSchema {
+    pub fn new() -> JsonSchema {
+        JsonSchema::default()
+    }
+
+    fn reference(&self, name: &str) -> Value {
+        let defs = if self.opts.openapi {
+            "#/components/schemas"
+        } else {
+            "#/$defs"
+        };
+        json!({ "$ref": format!("{}/{}", defs, name) })
+    }
+
+    fn schema(&self, iface: &Interface, ty: &Type) -> Value {
+        match ty {
+            Type::Unit => json!({ "type": "null" }),
+            Type::Bool => json!({ "type": "boolean" }),
+            Type::U8 => int(u8::MIN, u8::MAX),
+            Type::U16 => int(u16::MIN, u16::MAX),
+            Type::U32 => int(u32::MIN, u32::MAX),
+            Type::U64 => int(u64::MIN, u64::MAX),
+            Type::S8 => int(i8::MIN, i8::MAX),
+            Type::S16 => int(i16::MIN, i16::MAX),
+            Type::S32 => int(i32::MIN, i32::MAX),
+            Type::S64 => int(i64::MIN, i64::MAX),
+            Type::Float32 | Type::Float64 => json!({ "type": "number" }),
+            Type::Char => json!({ "type": "string", "minLength": 1, "maxLength": 1 }),
+            Type::String => json!({ "type": "string" }),
+            Type::Handle(r) => json!({
+                "description": format!(
+                    "A `{}` resource, which has no JSON encoding.",
+                    iface.resources[*r].name
+                ),
+                "not": {},
+            }),
+            Type::Id(id) => match &iface.types[*id].name {
+                Some(name) => self.reference(name),
+                None => self.schema_of_kind(iface, &iface.types[*id].kind),
+            },
+        }
+    }
+
+    fn schema_of_kind(&self, iface: &Interface, kind: &TypeDefKind) -> Value {
+        match kind {
+            TypeDefKind::Type(t) => self.schema(iface, t),
+            TypeDefKind::List(t) => json!({
+                "type": "array",
+                "items": self.schema(iface, t),
+            }),
+            TypeDefKind::Option(t) => json!({
+                "anyOf": [{ "type": "null" }, self.schema(iface, t)],
+            }),
+            TypeDefKind::Expected(e) => json!({
+                "oneOf": [
+                    tagged("ok", self.schema(iface, &e.ok)),
+                    tagged("err", self.schema(iface, &e.err)),
+                ],
+            }),
+            TypeDefKind::Tuple(t) => json!({
+                "type": "array",
+                "prefixItems": t.types.iter().map(|t| self.schema(iface, t)).collect::<Vec<_>>(),
+                "items": false,
+                "minItems": t.types.len(),
+            }),
+            TypeDefKind::Record(r) => {
+                let mut properties = Map::new();
+                let mut required = Vec::new();
+                for field in r.fields.iter() {
+                    let mut schema = self.schema(iface, &field.ty);
+                    describe(&mut schema, &field.docs);
+                    properties.insert(field.name.clone(), schema);
+                    if !nullable(iface, &field.ty) {
+                        required.push(field.name.clone());
+                    }
+                }
+                json!({
+                    "type": "object",
+                    "properties": properties,
+                    "required": required,
+                })
+            }
+            TypeDefKind::Flags(f) => json!({
+                "type": "array",
+                "items": {
+                    "enum": f.flags.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(),
+                },
+                "uniqueItems": true,
+            }),
+            TypeDefKind::Enum(e) => json!({
+                "enum": e.cases.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(),
+            }),
+            TypeDefKind::Variant(v) => {
+                let cases = v
+                    .cases
+                    .iter()
+                    .flat_map(|case| {
+                        let mut schema = tagged(&case.name, self.schema(iface, &case.ty));
+                        describe(&mut schema, &case.docs);
+                        let name = match case.ty {
+                            Type::Unit => Some(json!({ "const": case.name })),
+                            _ => None,
+                        };
+                        name.into_iter().chain(Some(schema))
+                    })
+                    .collect::<Vec<_>>();
+                json!({ "oneOf": cases })
+            }
+            TypeDefKind::Union(u) => {
+                let cases = u
+                    .cases
+                    .iter()
+                    .enumerate()
+                    .map(|(i, case)| {
+                        let mut schema = tagged(&i.to_string(), self.schema(iface, &case.ty));
+                        describe(&mut schema, &case.docs);
+                        schema
+                    })
+                    .collect::<Vec<_>>();
+                json!({ "oneOf": cases })
+            }
+            TypeDefKind::Future(_) | TypeDefKind::Stream(_) => {
+                unimplemented!("json schema for futures and streams")
+            }
+        }
+    }
+
+    fn define(&mut self, iface: &Interface, id: TypeId, name: &str, docs: &Docs) {
+        let mut def = Map::new();
+        def.insert("title".to_string(), Value::from(name));
+        match self.schema_of_kind(iface, &iface.types[id].kind) {
+            Value::Object(schema) => def.extend(schema),
+            _ => unreachable!(),
+        }
+        let mut def = Value::Object(def);
+        describe(&mut def, docs);
+        self.defs.insert(name.to_string(), def);
+    }
+}
+
+fn int<T: Into<Value>>(min: T, max: T) -> Value {
+    json!({ "type": "integer", "minimum": min.into(), "maximum": max.into() })
+}
+
+/// The schema of a case named `name`, an object with a single property.
+fn tagged(name: &str, payload: Value) -> Value {
+    json!({
+        "type": "object",
+        "properties": { name: payload },
+        "required": [name],
+        "additionalProperties": false,
+    })
+}
+
+/// Adds `docs` to `schema` as its description.
+fn describe(schema: &mut Value, docs: &Docs) {
+    if let Some(docs) = &docs.contents {
+        if let Value::Object(schema) = schema {
+            schema.insert("description".to_string(), Value::from(docs.trim()));
+        }
+    }
+}
+
+/// Returns whether `null` is a valid encoding of `ty`, in which case a
+/// record field of this type may be left out.
+fn nullable(iface: &Interface, ty: &Type) -> bool {
+    match ty {
+        Type::Unit => true,
+        Type::Id(id) => match &iface.types[*id].kind {
+            TypeDefKind::Type(t) => nullable(iface, t),
+            TypeDefKind::Option(_) => true,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+impl Generator for JsonSchema {
+    fn type_record(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        name: &str,
+        _record: &Record,
+        docs: &Docs,
+    ) {
+        self.define(iface, id, name, docs);
+    }
+
+    fn type_tuple(&mut self, iface: &Interface, id: TypeId, name: &str, _: &Tuple, docs: &Docs) {
+        self.define(iface, id, name, docs);
+    }
+
+    fn type_flags(&mut self, iface: &Interface, id: TypeId, name: &str, _: &Flags, docs: &Docs) {
+        self.define(iface, id, name, docs);
+    }
+
+    fn type_variant(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        name: &str,
+        _variant: &Variant,
+        docs: &Docs,
+    ) {
+        self.define(iface, id, name, docs);
+    }
+
+    fn type_union(&mut self, iface: &Interface, id: TypeId, name: &str, _: &Union, docs: &Docs) {
+        self.define(iface, id, name, docs);
+    }
+
+    fn type_option(&mut self, iface: &Interface, id: TypeId, name: &str, _: &Type, docs: &Docs) {
+        self.define(iface, id, name, docs);
+    }
+
+    fn type_expected(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        name: &str,
+        _expected: &Expected,
+        docs: &Docs,
+    ) {
+        self.define(iface, id, name, docs);
+    }
+
+    fn type_enum(&mut self, iface: &Interface, id: TypeId, name: &str, _: &Enum, docs: &Docs) {
+        self.define(iface, id, name, docs);
+    }
+
+    fn type_resource(&mut self, _iface: &Interface, _ty: ResourceId) {
+        // Resources are opaque, and have no JSON encoding to describe.
+    }
+
+    fn type_alias(&mut self, iface: &Interface, id: TypeId, name: &str, _: &Type, docs: &Docs) {
+        self.define(iface, id, name, docs);
+    }
+
+    fn type_list(&mut self, iface: &Interface, id: TypeId, name: &str, _: &Type, docs: &Docs) {
+        self.define(iface, id, name, docs);
+    }
+
+    fn type_builtin(&mut self, iface: &Interface, id: TypeId, name: &str, _: &Type, docs: &Docs) {
+        self.define(iface, id, name, docs);
+    }
+
+    fn import(&mut self, _iface: &Interface, _func: &Function) {}
+
+    fn export(&mut self, _iface: &Interface, _func: &Function) {}
+
+    fn finish_one(&mut self, iface: &Interface, files: &mut Files) {
+        let defs = Value::Object(std::mem::take(&mut self.defs));
+        let doc = if self.opts.openapi {
+            json!({ "components": { "schemas": defs } })
+        } else {
+            json!({
+                "$schema": DRAFT,
+                "title": iface.name,
+                "$defs": defs,
+            })
+        };
+        let mut contents = serde_json::to_string_pretty(&doc).unwrap();
+        contents.push('\n');
+        files.push_for_interface(&iface.name, "json", contents.as_bytes());
+    }
+}
@@ -0,0 +1,79 @@
+use serde_json::{json, Value};
+use std::path::Path;
+use wai_bindgen_gen_core::{wai_parser::Interface, Files, Generator};
+
+fn generate(iface: Interface, opts: wai_bindgen_gen_json_schema::Opts) -> Value {
+    let mut files = Files::default();
+    opts.build().generate_all(&[iface], &[], &mut files);
+    let (_, contents) = files.iter().next().unwrap();
+    serde_json::from_slice(contents).unwrap()
+}
+
+#[test]
+fn runtime_interfaces() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/runtime");
+    for entry in std::fs::read_dir(&root).unwrap() {
+        let path = entry.unwrap().path().join("exports.wai");
+        if !path.exists() {
+            continue;
+        }
+        let iface = Interface::parse_file(&path).unwrap();
+        let named = iface.types.iter().filter(|(_, t)| t.name.is_some()).count();
+        let schema = generate(iface, Default::default());
+        let defs = schema["$defs"].as_object().unwrap();
+        assert_eq!(defs.len(), named, "{}", path.display());
+    }
+}
+
+#[test]
+fn optional_fields() {
+    let iface = Interface::parse(
+        "api",
+        "
+            /// A point.
+            record point { x: u8, label: option<string> }
+            type points = list<point>
+        ",
+    )
+    .unwrap();
+
+    let schema = generate(iface, Default::default());
+    assert_eq!(
+        schema["$defs"]["point"],
+        json!({
+            "title": "point",
+            "type": "object",
+            "properties": {
+                "x": { "type": "integer", "minimum": 0, "maximum": 255 },
+                "label": { "anyOf": [{ "type": "null" }, { "type": "string" }] },
+            },
+            "required": ["x"],
+            "description": "A point.",
+        })
+    );
+    assert_eq!(
+        schema["$defs"]["points"]["items"],
+        json!({ "$ref": "#/$defs/point" })
+    );
+}
+
+#[test]
+fn openapi_components() {
+    let iface = Interface::parse(
+        "api",
+        "
+            enum color { red, green }
+            type palette = list<color>
+        ",
+    )
+    .unwrap();
+
+    let opts = wai_bindgen_gen_json_schema::Opts { openapi: true };
+    let schema = generate(iface, opts);
+    let schemas = &schema["components"]["schemas"];
+    assert_eq!(schemas["color"]["enum"], json!(["red", "green"]));
+    assert_eq!(
+        schemas["palette"]["items"],
+        json!({ "$ref": "#/components/schemas/color" })
+    );
+}
@@ -0,0 +1,1387 @@
+//! Bindings for Kotlin compiled to wasm with Kotlin/Wasm.
+//!
+//! For each interface this emits a `<Interface>.kt` file in a package named
+//! after the interface, with records as data classes, variants and unions as
+//! sealed interfaces, and functions lifting and lowering the canonical ABI
+//! around the raw wasm imports and exports, which are `external` functions
+//! annotated with `@WasmImport` and functions annotated with `@WasmExport`.
+//! Exports are implemented by passing an implementation of the `Exports`
+//! interface to `setExports`. All of them build on a shared `Wai.kt`, in the
+//! `wai` package, with the `Option`, `Expected` and tuple types and the
+//! memory accessors of the bindings.
+//!
+//! Kotlin/Wasm only hands out linear memory through scoped allocators, so
+//! each call allocates the memory it passes around in the scope of the call.
+//! Memory which the host allocates with `canonical_abi_realloc` is reserved
+//! on top of the current scope until the call it's for claims it, and
+//! `canonical_abi_free` is a no-op. The results of an export are only valid
+//! until the guest is called again, which is the case with hosts reading
+//! them right after the call.
+
+use heck::*;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::mem;
+use wai_bindgen_gen_core::wai_parser::abi::{
+    AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmType,
+};
+use wai_bindgen_gen_core::{
+    union_case_names, uwrite, uwriteln, wai_parser::*, Direction, Files, Generator, Ns, Source,
+};
+
+#[derive(Default)]
+pub struct KotlinWasm {
+    opts: Opts,
+    sizes: SizeAlign,
+    in_import: bool,
+    // The types of the current interface.
+    types: Source,
+    // The functions of the current interface.
+    funcs: Source,
+    // The functions of the `Exports` interface of the current interface.
+    exports: Source,
+    // The arities of the tuples without a type of their own in Kotlin.
+    tuples: BTreeSet<usize>,
+}
+
+#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "structopt", derive(structopt::StructOpt))]
+pub struct Opts {
+    // ...
+}
+
+impl Opts {
+    pub fn build(&self) -> KotlinWasm {
+        let mut r = KotlinWasm::new();
+        r.opts = self.clone();
+        r
+    }
+}
+
+impl KotlinWasm {
+    pub fn new() -> KotlinWasm {
+        KotlinWasm::default()
+    }
+
+    fn abi_variant(dir: Direction) -> AbiVariant {
+        // This generator uses the obvious direction to ABI variant mapping.
+        match dir {
+            Direction::Export => AbiVariant::GuestExport,
+            Direction::Import => AbiVariant::GuestImport,
+        }
+    }
+
+    fn type_name(&mut self, iface: &Interface, ty: &Type) -> String {
+        match ty {
+            Type::Unit => "Unit".to_string(),
+            Type::Bool => "Boolean".to_string(),
+            Type::U8 => "UByte".to_string(),
+            Type::U16 => "UShort".to_string(),
+            Type::U32 => "UInt".to_string(),
+            Type::U64 => "ULong".to_string(),
+            Type::S8 => "Byte".to_string(),
+            Type::S16 => "Short".to_string(),
+            Type::S32 => "Int".to_string(),
+            Type::S64 => "Long".to_string(),
+            Type::Float32 => "Float".to_string(),
+            Type::Float64 => "Double".to_string(),
+            // Chars are code points.
+            Type::Char => "Int".to_string(),
+            Type::String => "String".to_string(),
+            Type::Handle(_) => unimplemented!("resources not supported yet by Kotlin guests"),
+            Type::Id(id) => {
+                let ty = &iface.types[*id];
+                match &ty.name {
+                    Some(name) => kotlin_type_ident(name),
+                    None => self.type_def_name(iface, &ty.kind),
+                }
+            }
+        }
+    }
+
+    /// The structure of the type `kind`, ignoring its name.
+    fn type_def_name(&mut self, iface: &Interface, kind: &TypeDefKind) -> String {
+        match kind {
+            TypeDefKind::Type(t) => self.type_name(iface, t),
+            TypeDefKind::List(t) => format!("List<{}>", self.type_name(iface, t)),
+            TypeDefKind::Tuple(t) => self.tuple_name(iface, &t.types),
+            TypeDefKind::Option(t) => format!("Option<{}>", self.type_name(iface, t)),
+            TypeDefKind::Expected(e) => format!(
+                "Expected<{}, {}>",
+                self.type_name(iface, &e.ok),
+                self.type_name(iface, &e.err)
+            ),
+            TypeDefKind::Record(_)
+            | TypeDefKind::Flags(_)
+            | TypeDefKind::Variant(_)
+            | TypeDefKind::Enum(_)
+            | TypeDefKind::Union(_) => unreachable!("unnamed {:?}", kind),
+            TypeDefKind::Future(_) => todo!("type_def_name for future"),
+            TypeDefKind::Stream(_) => todo!("type_def_name for stream"),
+        }
+    }
+
+    fn tuple_name(&mut self, iface: &Interface, types: &[Type]) -> String {
+        if types.is_empty() {
+            return "Unit".to_string();
+        }
+        let types = types
+            .iter()
+            .map(|t| self.type_name(iface, t))
+            .collect::<Vec<_>>();
+        format!("{}<{}>", self.tuple_class(types.len()), types.join(", "))
+    }
+
+    /// The class of tuples of `arity` elements, which is one of the `wai`
+    /// package unless it's a `Pair` or a `Triple`.
+    fn tuple_class(&mut self, arity: usize) -> String {
+        match arity {
+            2 => "Pair".to_string(),
+            3 => "Triple".to_string(),
+            n => {
+                self.tuples.insert(n);
+                format!("Tuple{}", n)
+            }
+        }
+    }
+
+    fn print_params(&mut self, iface: &Interface, params: &[String], func: &Function) -> String {
+        let mut src = String::new();
+        for (i, (param, (_, ty))) in params.iter().zip(&func.params).enumerate() {
+            if i > 0 {
+                src.push_str(", ");
+            }
+            uwrite!(src, "{}: {}", param, self.type_name(iface, ty));
+        }
+        src
+    }
+
+    /// Prints a sealed interface named `name` with a class for each of
+    /// `cases`, which is an object unless it has a payload.
+    fn print_sealed_interface(
+        &mut self,
+        iface: &Interface,
+        name: &str,
+        cases: &[(String, &Type, &Docs)],
+        docs: &Docs,
+    ) {
+        let name = kotlin_type_ident(name);
+        kdoc(&mut self.types, docs);
+        uwriteln!(self.types, "sealed interface {} {{", name);
+        for (case, ty, docs) in cases {
+            kdoc(&mut self.types, docs);
+            if **ty == Type::Unit {
+                uwriteln!(
+                    self.types,
+                    "data object {} : {}",
+                    case.to_camel_case(),
+                    name
+                );
+            } else {
+                let ty = self.type_name(iface, ty);
+                uwriteln!(
+                    self.types,
+                    "data class {}(val value: {}) : {}",
+                    case.to_camel_case(),
+                    ty,
+                    name
+                );
+            }
+        }
+        self.types.push_str("}\n\n");
+    }
+
+    fn print_typealias(&mut self, name: &str, target: &str, docs: &Docs) {
+        kdoc(&mut self.types, docs);
+        uwriteln!(
+            self.types,
+            "typealias {} = {}\n",
+            kotlin_type_ident(name),
+            target
+        );
+    }
+
+    fn print_runtime(&self) -> String {
+        let mut src = Source::default();
+        src.push_str(
+            "
+                // Generated by `wai-bindgen`: the runtime support of the bindings of all
+                // interfaces.
+                @file:OptIn(UnsafeWasmMemoryApi::class)
+
+                package wai
+
+                import kotlin.wasm.WasmExport
+                import kotlin.wasm.unsafe.MemoryAllocator
+                import kotlin.wasm.unsafe.Pointer
+                import kotlin.wasm.unsafe.UnsafeWasmMemoryApi
+                import kotlin.wasm.unsafe.withScopedMemoryAllocator
+
+                /** The value of an `option<T>`. */
+                sealed interface Option<out T> {
+                    data class Some<out T>(val value: T) : Option<T>
+                    data object None : Option<Nothing>
+                }
+
+                /** The value of an `expected<T, E>`. */
+                sealed interface Expected<out T, out E> {
+                    data class Ok<out T>(val value: T) : Expected<T, Nothing>
+                    data class Err<out E>(val error: E) : Expected<Nothing, E>
+                }
+            ",
+        );
+        for arity in self.tuples.iter() {
+            let params = (1..=*arity)
+                .map(|i| format!("out T{}", i))
+                .collect::<Vec<_>>();
+            let fields = (1..=*arity)
+                .map(|i| format!("val value{0}: T{0}", i))
+                .collect::<Vec<_>>();
+            uwriteln!(
+                src,
+                "\ndata class Tuple{}<{}>({})",
+                arity,
+                params.join(", "),
+                fields.join(", ")
+            );
+        }
+        src.push_str(
+            "
+                // The bytes handed out by `canonical_abi_realloc` on top of the current
+                // scope which haven't been claimed by the call they are for yet.
+                private var pending = 0
+
+                @WasmExport(\"canonical_abi_realloc\")
+                fun canonicalAbiRealloc(ptr: Int, origSize: Int, align: Int, newSize: Int): Int {
+                    // Empty allocations get a dangling pointer which is never freed.
+                    if (newSize == 0) {
+                        return align
+                    }
+                    val offset = (pending + align - 1) / align * align
+                    val ret = withScopedMemoryAllocator { it.alloc(offset + newSize) } + offset
+                    for (i in 0 until minOf(origSize, newSize)) {
+                        storeI8(ret, i, loadU8(ptr, i))
+                    }
+                    pending = offset + newSize
+                    return ret
+                }
+
+                @WasmExport(\"canonical_abi_free\")
+                fun canonicalAbiFree(ptr: Int, size: Int, align: Int) {
+                    // Memory is reclaimed at the end of the scope it's allocated in.
+                }
+
+                /** Claims the memory handed out by `canonical_abi_realloc` for the scope of `allocator`. */
+                internal fun claimPending(allocator: MemoryAllocator) {
+                    if (pending > 0) {
+                        allocator.alloc(pending)
+                    }
+                    pending = 0
+                }
+
+                /** Gives up the memory handed out by `canonical_abi_realloc` once it has been read. */
+                internal fun releasePending() {
+                    pending = 0
+                }
+
+                internal fun MemoryAllocator.alloc(size: Int): Int = allocate(size).address.toInt()
+
+                private fun at(address: Int, offset: Int): Pointer = Pointer((address + offset).toUInt())
+
+                internal fun loadU8(address: Int, offset: Int): Int = at(address, offset).loadByte().toInt() and 0xff
+                internal fun loadS8(address: Int, offset: Int): Int = at(address, offset).loadByte().toInt()
+                internal fun loadU16(address: Int, offset: Int): Int = at(address, offset).loadShort().toInt() and 0xffff
+                internal fun loadS16(address: Int, offset: Int): Int = at(address, offset).loadShort().toInt()
+                internal fun loadI32(address: Int, offset: Int): Int = at(address, offset).loadInt()
+                internal fun loadI64(address: Int, offset: Int): Long = at(address, offset).loadLong()
+                internal fun loadF32(address: Int, offset: Int): Float = Float.fromBits(loadI32(address, offset))
+                internal fun loadF64(address: Int, offset: Int): Double = Double.fromBits(loadI64(address, offset))
+
+                internal fun storeI8(address: Int, offset: Int, value: Int) = at(address, offset).storeByte(value.toByte())
+                internal fun storeI16(address: Int, offset: Int, value: Int) = at(address, offset).storeShort(value.toShort())
+                internal fun storeI32(address: Int, offset: Int, value: Int) = at(address, offset).storeInt(value)
+                internal fun storeI64(address: Int, offset: Int, value: Long) = at(address, offset).storeLong(value)
+                internal fun storeF32(address: Int, offset: Int, value: Float) = storeI32(address, offset, value.toRawBits())
+                internal fun storeF64(address: Int, offset: Int, value: Double) = storeI64(address, offset, value.toRawBits())
+
+                /** Copies `bytes` into memory allocated in the scope of `this`. */
+                internal fun MemoryAllocator.lowerBytes(bytes: ByteArray): Int {
+                    val address = alloc(bytes.size)
+                    for (i in bytes.indices) {
+                        at(address, i).storeByte(bytes[i])
+                    }
+                    return address
+                }
+
+                internal fun liftString(address: Int, len: Int): String =
+                    ByteArray(len) { at(address, it).loadByte() }.decodeToString()
+
+                /** The bits of the 32 flags starting at `32 * word` in `flags`. */
+                internal fun <T : Enum<T>> flagsToBits(flags: Set<T>, word: Int): Int =
+                    flags.filter { it.ordinal / 32 == word }.fold(0) { bits, flag -> bits or (1 shl (flag.ordinal % 32)) }
+
+                internal fun <T : Enum<T>> flagsFromBits(all: List<T>, vararg words: Int): Set<T> =
+                    all.filter { (words[it.ordinal / 32] ushr (it.ordinal % 32)) and 1 != 0 }.toSet()
+            ",
+        );
+        src.into()
+    }
+}
+
+impl Generator for KotlinWasm {
+    fn preprocess_one(&mut self, iface: &Interface, dir: Direction) {
+        assert!(
+            iface.resources.len() == 0,
+            "resources not supported yet by Kotlin guests"
+        );
+        let variant = Self::abi_variant(dir);
+        self.sizes.fill(iface);
+        self.in_import = variant == AbiVariant::GuestImport;
+    }
+
+    fn type_record(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        record: &Record,
+        docs: &Docs,
+    ) {
+        let name = kotlin_type_ident(name);
+        kdoc(&mut self.types, docs);
+        if record.fields.is_empty() {
+            uwriteln!(self.types, "data object {}\n", name);
+            return;
+        }
+        uwrite!(self.types, "data class {}(", name);
+        self.types.indent(1);
+        for field in record.fields.iter() {
+            self.types.push_str("\n");
+            kdoc(&mut self.types, &field.docs);
+            let ty = self.type_name(iface, &field.ty);
+            uwrite!(
+                self.types,
+                "val {}: {},",
+                kotlin_ident(&field.name.to_mixed_case()),
+                ty
+            );
+        }
+        self.types.deindent(1);
+        self.types.push_str("\n)\n\n");
+    }
+
+    fn type_tuple(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        tuple: &Tuple,
+        docs: &Docs,
+    ) {
+        let target = self.tuple_name(iface, &tuple.types);
+        self.print_typealias(name, &target, docs);
+    }
+
+    fn type_flags(
+        &mut self,
+        _iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        flags: &Flags,
+        docs: &Docs,
+    ) {
+        let name = kotlin_type_ident(name);
+        uwriteln!(self.types, "enum class {}Flag {{", name);
+        for flag in flags.flags.iter() {
+            kdoc(&mut self.types, &flag.docs);
+            uwriteln!(self.types, "{},", flag.name.to_shouty_snake_case());
+        }
+        self.types.push_str("}\n\n");
+        kdoc(&mut self.types, docs);
+        uwriteln!(self.types, "typealias {0} = Set<{0}Flag>\n", name);
+    }
+
+    fn type_variant(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        variant: &Variant,
+        docs: &Docs,
+    ) {
+        let cases = variant
+            .cases
+            .iter()
+            .map(|c| (c.name.clone(), &c.ty, &c.docs))
+            .collect::<Vec<_>>();
+        self.print_sealed_interface(iface, name, &cases, docs);
+    }
+
+    fn type_union(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        union: &Union,
+        docs: &Docs,
+    ) {
+        let cases = union_case_names(iface, union)
+            .into_iter()
+            .zip(&union.cases)
+            .map(|(name, c)| (name, &c.ty, &c.docs))
+            .collect::<Vec<_>>();
+        self.print_sealed_interface(iface, name, &cases, docs);
+    }
+
+    fn type_option(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        name: &str,
+        _payload: &Type,
+        docs: &Docs,
+    ) {
+        let target = self.type_def_name(iface, &iface.types[id].kind);
+        self.print_typealias(name, &target, docs);
+    }
+
+    fn type_expected(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        name: &str,
+        _expected: &Expected,
+        docs: &Docs,
+    ) {
+        let target = self.type_def_name(iface, &iface.types[id].kind);
+        self.print_typealias(name, &target, docs);
+    }
+
+    fn type_enum(
+        &mut self,
+        _iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        enum_: &Enum,
+        docs: &Docs,
+    ) {
+        kdoc(&mut self.types, docs);
+        uwriteln!(self.types, "enum class {} {{", kotlin_type_ident(name));
+        for case in enum_.cases.iter() {
+            kdoc(&mut self.types, &case.docs);
+            uwriteln!(self.types, "{},", case.name.to_shouty_snake_case());
+        }
+        self.types.push_str("}\n\n");
+    }
+
+    fn type_resource(&mut self, _iface: &Interface, _ty: ResourceId) {
+        unimplemented!("resources not supported yet by Kotlin guests")
+    }
+
+    fn type_alias(&mut self, iface: &Interface, _id: TypeId, name: &str, ty: &Type, docs: &Docs) {
+        let target = self.type_name(iface, ty);
+        self.print_typealias(name, &target, docs);
+    }
+
+    fn type_list(&mut self, iface: &Interface, id: TypeId, name: &str, _ty: &Type, docs: &Docs) {
+        let target = self.type_def_name(iface, &iface.types[id].kind);
+        self.print_typealias(name, &target, docs);
+    }
+
+    fn type_builtin(&mut self, iface: &Interface, id: TypeId, name: &str, ty: &Type, docs: &Docs) {
+        self.type_alias(iface, id, name, ty, docs);
+    }
+
+    fn import(&mut self, iface: &Interface, func: &Function) {
+        assert!(!func.is_async, "async not supported yet");
+        let sig = iface.wasm_signature(AbiVariant::GuestImport, func);
+
+        uwriteln!(
+            self.funcs,
+            "@WasmImport(\"{}\", \"{}\")",
+            iface.name,
+            func.name
+        );
+        uwrite!(
+            self.funcs,
+            "private external fun wasmImport{}(",
+            func.name.to_camel_case()
+        );
+        for (i, param) in sig.params.iter().enumerate() {
+            if i > 0 {
+                self.funcs.push_str(", ");
+            }
+            uwrite!(self.funcs, "arg{}: {}", i, wasm_type(*param));
+        }
+        self.funcs.push_str(")");
+        match sig.results.len() {
+            0 => {}
+            1 => uwrite!(self.funcs, ": {}", wasm_type(sig.results[0])),
+            _ => unimplemented!("multi-value return not supported"),
+        }
+        self.funcs.push_str("\n\n");
+
+        let mut locals = FunctionBindgen::locals();
+        let params = func
+            .params
+            .iter()
+            .map(|(name, _)| locals.tmp(&kotlin_ident(&name.to_mixed_case())))
+            .collect::<Vec<_>>();
+        kdoc(&mut self.funcs, &func.docs);
+        let params_src = self.print_params(iface, &params, func);
+        uwrite!(
+            self.funcs,
+            "fun {}({})",
+            kotlin_ident(&func.name.to_mixed_case()),
+            params_src
+        );
+        let has_result = func.result != Type::Unit;
+        if has_result {
+            let result = self.type_name(iface, &func.result);
+            uwrite!(self.funcs, ": {}", result);
+        }
+        self.funcs.push_str(" {\n");
+        let mut f = FunctionBindgen::new(self, locals, params);
+        iface.call(
+            AbiVariant::GuestImport,
+            LiftLower::LowerArgsLiftResults,
+            func,
+            &mut f,
+        );
+        let body = f.src;
+        print_scoped(&mut self.funcs, &body, has_result);
+        self.funcs.push_str("}\n\n");
+    }
+
+    fn export(&mut self, iface: &Interface, func: &Function) {
+        assert!(!func.is_async, "async not supported yet");
+        let sig = iface.wasm_signature(AbiVariant::GuestExport, func);
+
+        // The implementation of the export is a function of `Exports`.
+        let params = func
+            .params
+            .iter()
+            .map(|(name, _)| kotlin_ident(&name.to_mixed_case()))
+            .collect::<Vec<_>>();
+        kdoc(&mut self.exports, &func.docs);
+        let params_src = self.print_params(iface, &params, func);
+        uwrite!(
+            self.exports,
+            "fun {}({})",
+            kotlin_ident(&func.name.to_mixed_case()),
+            params_src
+        );
+        if func.result != Type::Unit {
+            let result = self.type_name(iface, &func.result);
+            uwrite!(self.exports, ": {}", result);
+        }
+        self.exports.push_str("\n");
+
+        uwriteln!(self.funcs, "@WasmExport(\"{}\")", func.name);
+        uwrite!(self.funcs, "fun wasmExport{}(", func.name.to_camel_case());
+        for (i, param) in sig.params.iter().enumerate() {
+            if i > 0 {
+                self.funcs.push_str(", ");
+            }
+            uwrite!(self.funcs, "arg{}: {}", i, wasm_type(*param));
+        }
+        self.funcs.push_str(")");
+        match sig.results.len() {
+            0 => {}
+            1 => uwrite!(self.funcs, ": {}", wasm_type(sig.results[0])),
+            _ => unimplemented!("multi-value return not supported"),
+        }
+        self.funcs.push_str(" {\n");
+        let mut locals = FunctionBindgen::locals();
+        let params = (0..sig.params.len())
+            .map(|i| locals.tmp(&format!("arg{}", i)))
+            .collect::<Vec<_>>();
+        let mut f = FunctionBindgen::new(self, locals, params);
+        f.src.push_str("claimPending(allocator)\n");
+        iface.call(
+            AbiVariant::GuestExport,
+            LiftLower::LiftArgsLowerResults,
+            func,
+            &mut f,
+        );
+        let body = f.src;
+        print_scoped(&mut self.funcs, &body, !sig.results.is_empty());
+        self.funcs.push_str("}\n\n");
+    }
+
+    fn finish_one(&mut self, iface: &Interface, files: &mut Files) {
+        let mut src = Source::default();
+        uwrite!(
+            src,
+            "
+                // Generated by `wai-bindgen` from the `{}` interface.
+                @file:OptIn(UnsafeWasmMemoryApi::class)
+
+                package {}
+
+                import kotlin.wasm.WasmExport
+                import kotlin.wasm.WasmImport
+                import kotlin.wasm.unsafe.UnsafeWasmMemoryApi
+                import kotlin.wasm.unsafe.withScopedMemoryAllocator
+                import wai.*
+
+            ",
+            iface.name,
+            kotlin_package(&iface.name),
+        );
+        // The types and functions are already indented, and the constructors
+        // of records and the lambdas of functions don't open with a brace for
+        // `Source` to track.
+        src.as_mut_string().push_str(&mem::take(&mut self.types));
+
+        let exports = mem::take(&mut self.exports);
+        if !self.in_import {
+            src.push_str("/** The implementation of the exports of this interface. */\n");
+            src.push_str("interface Exports {\n");
+            src.push_str(&exports);
+            src.push_str("}\n");
+            src.push_str(
+                "
+                    private lateinit var exportsImpl: Exports
+
+                    /** Registers the implementation of the exports of this interface. */
+                    fun setExports(impl: Exports) {
+                        exportsImpl = impl
+                    }
+
+                ",
+            );
+        }
+
+        src.as_mut_string().push_str(&mem::take(&mut self.funcs));
+        let src = format!("{}\n", src.trim());
+        files.push(
+            &format!("{}.kt", iface.name.to_camel_case()),
+            src.as_bytes(),
+        );
+    }
+
+    fn finish_all(&mut self, files: &mut Files) {
+        let src = self.print_runtime();
+        let src = format!("{}\n", src.trim());
+        files.push("Wai.kt", src.as_bytes());
+    }
+}
+
+/// Prints `body` in the scope of an `allocator` for the memory of the call,
+/// returning its last expression if `has_result`.
+fn print_scoped(src: &mut Source, body: &str, has_result: bool) {
+    if has_result {
+        src.push_str("return ");
+    }
+    src.push_str("withScopedMemoryAllocator { allocator ->");
+    src.indent(1);
+    src.push_str("\n");
+    src.push_str(body);
+    src.push_str("}\n");
+}
+
+struct FunctionBindgen<'a> {
+    gen: &'a mut KotlinWasm,
+    locals: Ns,
+    src: Source,
+    block_storage: Vec<Source>,
+    blocks: Vec<(String, Vec<String>)>,
+    params: Vec<String>,
+    payloads: Vec<String>,
+}
+
+impl<'a> FunctionBindgen<'a> {
+    /// The names of locals, reserving those the generated code relies on.
+    fn locals() -> Ns {
+        let mut locals = Ns::default();
+        for name in [
+            "allocator",
+            "it",
+            "exportsImpl",
+            "claimPending",
+            "releasePending",
+            "liftString",
+            "flagsToBits",
+            "flagsFromBits",
+        ] {
+            locals.insert(name).unwrap();
+        }
+        locals
+    }
+
+    fn new(gen: &'a mut KotlinWasm, locals: Ns, params: Vec<String>) -> FunctionBindgen<'a> {
+        FunctionBindgen {
+            gen,
+            locals,
+            src: Source::default(),
+            block_storage: Vec::new(),
+            blocks: Vec::new(),
+            params,
+            payloads: Vec::new(),
+        }
+    }
+
+    fn load(&mut self, ty: &str, offset: i32, operands: &[String], results: &mut Vec<String>) {
+        let tmp = self.locals.tmp("load");
+        uwriteln!(
+            self.src,
+            "val {} = load{}({}, {})",
+            tmp,
+            ty,
+            operands[0],
+            offset
+        );
+        results.push(tmp);
+    }
+
+    fn store(&mut self, ty: &str, offset: i32, operands: &[String]) {
+        uwriteln!(
+            self.src,
+            "store{}({}, {}, {})",
+            ty,
+            operands[1],
+            offset,
+            operands[0]
+        );
+    }
+
+    /// Prints the body of a branch, assigning its results to `results`.
+    fn branch(&mut self, block: &str, block_results: &[String], results: &[String]) {
+        self.src.push_str(block);
+        for (result, value) in results.iter().zip(block_results) {
+            uwriteln!(self.src, "{} = {}", result, value);
+        }
+        self.src.push_str("}\n");
+    }
+
+    fn declare_results(&mut self, prefix: &str, types: &[WasmType]) -> Vec<String> {
+        types
+            .iter()
+            .map(|ty| {
+                let result = self.locals.tmp(prefix);
+                let zero = match ty {
+                    WasmType::I32 => "0",
+                    WasmType::I64 => "0L",
+                    WasmType::F32 => "0f",
+                    WasmType::F64 => "0.0",
+                };
+                uwriteln!(self.src, "var {} = {}", result, zero);
+                result
+            })
+            .collect()
+    }
+
+    /// Lowers `op` with a `when` over its classes, given with whether they
+    /// have a payload, which is their `value`.
+    fn lower_cases(
+        &mut self,
+        op: &str,
+        cases: &[(String, bool)],
+        result_types: &[WasmType],
+    ) -> Vec<String> {
+        let blocks = self
+            .blocks
+            .drain(self.blocks.len() - cases.len()..)
+            .collect::<Vec<_>>();
+        let payloads = self
+            .payloads
+            .drain(self.payloads.len() - cases.len()..)
+            .collect::<Vec<_>>();
+        let results = self.declare_results("variant", result_types);
+        uwriteln!(self.src, "when ({}) {{", op);
+        for ((class, has_payload), ((block, block_results), payload)) in
+            cases.iter().zip(blocks.into_iter().zip(payloads))
+        {
+            uwriteln!(self.src, "is {} -> {{", class);
+            if *has_payload {
+                uwriteln!(self.src, "val {} = {}.value", payload, op);
+            }
+            self.branch(&block, &block_results, &results);
+        }
+        self.src.push_str("}\n");
+        results
+    }
+
+    /// Lifts the discriminant `op` into a value of `ty`, built by `ctor`
+    /// from the index of each case and its payload.
+    fn lift_cases(
+        &mut self,
+        op: &str,
+        ty: &str,
+        cases: usize,
+        ctor: impl Fn(usize, &str) -> String,
+    ) -> String {
+        let blocks = self
+            .blocks
+            .drain(self.blocks.len() - cases..)
+            .collect::<Vec<_>>();
+        let result = self.locals.tmp("variant");
+        if let [(block, block_results)] = &blocks[..] {
+            self.src.push_str(block);
+            uwriteln!(self.src, "val {} = {}", result, ctor(0, &block_results[0]));
+            return result;
+        }
+        uwriteln!(self.src, "val {}: {}", result, ty);
+        uwriteln!(self.src, "when ({}) {{", op);
+        for (i, (block, block_results)) in blocks.into_iter().enumerate() {
+            // The host is trusted to pass a valid discriminant, so the last
+            // case takes all others.
+            if i == cases - 1 {
+                self.src.push_str("else -> {\n");
+            } else {
+                uwriteln!(self.src, "{} -> {{", i);
+            }
+            let value = ctor(i, &block_results[0]);
+            self.branch(&block, &[value], std::slice::from_ref(&result));
+        }
+        self.src.push_str("}\n");
+        result
+    }
+
+    /// The classes of the cases of the sealed interface `name`, along with
+    /// whether they have a payload.
+    fn sealed_cases<'b>(
+        name: &str,
+        cases: impl Iterator<Item = (String, &'b Type)>,
+    ) -> Vec<(String, bool)> {
+        let name = kotlin_type_ident(name);
+        cases
+            .map(|(case, ty)| {
+                (
+                    format!("{}.{}", name, case.to_camel_case()),
+                    *ty != Type::Unit,
+                )
+            })
+            .collect()
+    }
+
+    fn lift_sealed(&mut self, op: &str, name: &str, cases: &[(String, bool)]) -> String {
+        let ty = kotlin_type_ident(name);
+        self.lift_cases(op, &ty, cases.len(), |i, payload| match &cases[i] {
+            (class, true) => format!("{}({})", class, payload),
+            (class, false) => class.clone(),
+        })
+    }
+}
+
+impl Bindgen for FunctionBindgen<'_> {
+    type Operand = String;
+
+    fn sizes(&self) -> &SizeAlign {
+        &self.gen.sizes
+    }
+
+    fn push_block(&mut self) {
+        let prev = mem::take(&mut self.src);
+        self.block_storage.push(prev);
+    }
+
+    fn finish_block(&mut self, operands: &mut Vec<String>) {
+        let to_restore = self.block_storage.pop().unwrap();
+        let src = mem::replace(&mut self.src, to_restore);
+        self.blocks.push((src.into(), mem::take(operands)));
+    }
+
+    fn return_pointer(&mut self, _iface: &Interface, size: usize, _align: usize) -> String {
+        let address = self.locals.tmp("address");
+        uwriteln!(self.src, "val {} = allocator.alloc({})", address, size);
+        address
+    }
+
+    fn is_list_canonical(&self, _iface: &Interface, _ty: &Type) -> bool {
+        // Lists are always copied element by element.
+        false
+    }
+
+    fn emit(
+        &mut self,
+        iface: &Interface,
+        inst: &Instruction<'_>,
+        operands: &mut Vec<String>,
+        results: &mut Vec<String>,
+    ) {
+        match inst {
+            Instruction::GetArg { nth } => results.push(self.params[*nth].clone()),
+            Instruction::I32Const { val } => results.push(val.to_string()),
+            Instruction::ConstZero { tys } => {
+                for ty in tys.iter() {
+                    match ty {
+                        WasmType::I32 => results.push("0".to_string()),
+                        WasmType::I64 => results.push("0L".to_string()),
+                        WasmType::F32 => results.push("0f".to_string()),
+                        WasmType::F64 => results.push("0.0".to_string()),
+                    }
+                }
+            }
+
+            Instruction::I32FromU8
+            | Instruction::I32FromS8
+            | Instruction::I32FromU16
+            | Instruction::I32FromS16
+            | Instruction::I32FromU32 => results.push(format!("{}.toInt()", operands[0])),
+            Instruction::I64FromU64 => results.push(format!("{}.toLong()", operands[0])),
+            Instruction::I32FromS32
+            | Instruction::I64FromS64
+            | Instruction::S32FromI32
+            | Instruction::S64FromI64
+            | Instruction::Float32FromF32
+            | Instruction::Float64FromF64
+            | Instruction::F32FromFloat32
+            | Instruction::F64FromFloat64
+            | Instruction::CharFromI32
+            | Instruction::I32FromChar => results.push(operands.pop().unwrap()),
+
+            // Only the low bits of integers coming from wasm are meaningful.
+            Instruction::U8FromI32 => results.push(format!("{}.toUByte()", operands[0])),
+            Instruction::S8FromI32 => results.push(format!("{}.toByte()", operands[0])),
+            Instruction::U16FromI32 => results.push(format!("{}.toUShort()", operands[0])),
+            Instruction::S16FromI32 => results.push(format!("{}.toShort()", operands[0])),
+            Instruction::U32FromI32 => results.push(format!("{}.toUInt()", operands[0])),
+            Instruction::U64FromI64 => results.push(format!("{}.toULong()", operands[0])),
+
+            Instruction::Bitcasts { casts } => {
+                for (cast, op) in casts.iter().zip(operands) {
+                    let op = match cast {
+                        Bitcast::I32ToF32 => format!("Float.fromBits({})", op),
+                        Bitcast::F32ToI32 => format!("{}.toRawBits()", op),
+                        Bitcast::I64ToF64 => format!("Double.fromBits({})", op),
+                        Bitcast::F64ToI64 => format!("{}.toRawBits()", op),
+                        Bitcast::I32ToI64 => format!("{}.toLong()", op),
+                        Bitcast::I64ToI32 => format!("{}.toInt()", op),
+                        Bitcast::F32ToI64 => format!("{}.toRawBits().toLong()", op),
+                        Bitcast::I64ToF32 => format!("Float.fromBits({}.toInt())", op),
+                        Bitcast::None => op.clone(),
+                    };
+                    results.push(op);
+                }
+            }
+
+            Instruction::UnitLower => {}
+            Instruction::UnitLift => results.push("Unit".to_string()),
+            Instruction::BoolFromI32 => results.push(format!("({} != 0)", operands[0])),
+            Instruction::I32FromBool => results.push(format!("(if ({}) 1 else 0)", operands[0])),
+
+            Instruction::I32FromBorrowedHandle { .. }
+            | Instruction::I32FromOwnedHandle { .. }
+            | Instruction::HandleOwnedFromI32 { .. }
+            | Instruction::HandleBorrowedFromI32 { .. } => {
+                unimplemented!("resources not supported yet by Kotlin guests")
+            }
+
+            Instruction::RecordLower { record, .. } => {
+                if !record.fields.is_empty() {
+                    let op = self.locals.tmp("record");
+                    uwriteln!(self.src, "val {} = {}", op, operands[0]);
+                    for field in record.fields.iter() {
+                        results.push(format!(
+                            "{}.{}",
+                            op,
+                            kotlin_ident(&field.name.to_mixed_case())
+                        ));
+                    }
+                }
+            }
+            Instruction::RecordLift { record, name, .. } => {
+                if record.fields.is_empty() {
+                    results.push(kotlin_type_ident(name));
+                } else {
+                    results.push(format!(
+                        "{}({})",
+                        kotlin_type_ident(name),
+                        operands.join(", ")
+                    ));
+                }
+            }
+
+            Instruction::TupleLower { tuple, .. } => {
+                if !tuple.types.is_empty() {
+                    let op = self.locals.tmp("tuple");
+                    uwriteln!(self.src, "val {} = {}", op, operands[0]);
+                    for i in 0..tuple.types.len() {
+                        results.push(format!("{}.component{}()", op, i + 1));
+                    }
+                }
+            }
+            Instruction::TupleLift { .. } => {
+                if operands.is_empty() {
+                    results.push("Unit".to_string());
+                } else {
+                    let class = self.gen.tuple_class(operands.len());
+                    results.push(format!("{}({})", class, operands.join(", ")));
+                }
+            }
+
+            Instruction::FlagsLower { flags, .. } => {
+                let op = self.locals.tmp("flags");
+                uwriteln!(self.src, "val {} = {}", op, operands[0]);
+                for i in 0..flags.repr().count() {
+                    results.push(format!("flagsToBits({}, {})", op, i));
+                }
+            }
+            Instruction::FlagsLift { name, .. } => {
+                results.push(format!(
+                    "flagsFromBits({}Flag.entries, {})",
+                    kotlin_type_ident(name),
+                    operands.join(", ")
+                ));
+            }
+
+            Instruction::VariantPayloadName => {
+                let name = self.locals.tmp("payload");
+                results.push(name.clone());
+                self.payloads.push(name);
+            }
+
+            Instruction::VariantLower {
+                variant,
+                name,
+                results: result_types,
+                ..
+            } => {
+                let cases =
+                    Self::sealed_cases(name, variant.cases.iter().map(|c| (c.name.clone(), &c.ty)));
+                let op = self.locals.tmp("variant");
+                uwriteln!(self.src, "val {} = {}", op, operands[0]);
+                results.extend(self.lower_cases(&op, &cases, result_types));
+            }
+            Instruction::VariantLift { variant, name, .. } => {
+                let cases =
+                    Self::sealed_cases(name, variant.cases.iter().map(|c| (c.name.clone(), &c.ty)));
+                let result = self.lift_sealed(&operands[0], name, &cases);
+                results.push(result);
+            }
+
+            Instruction::UnionLower {
+                union,
+                name,
+                results: result_types,
+                ..
+            } => {
+                let cases = Self::sealed_cases(
+                    name,
+                    union_case_names(iface, union)
+                        .into_iter()
+                        .zip(union.cases.iter().map(|c| &c.ty)),
+                );
+                let op = self.locals.tmp("union");
+                uwriteln!(self.src, "val {} = {}", op, operands[0]);
+                results.extend(self.lower_cases(&op, &cases, result_types));
+            }
+            Instruction::UnionLift { union, name, .. } => {
+                let cases = Self::sealed_cases(
+                    name,
+                    union_case_names(iface, union)
+                        .into_iter()
+                        .zip(union.cases.iter().map(|c| &c.ty)),
+                );
+                let result = self.lift_sealed(&operands[0], name, &cases);
+                results.push(result);
+            }
+
+            Instruction::EnumLower { .. } => results.push(format!("{}.ordinal", operands[0])),
+            Instruction::EnumLift { name, .. } => results.push(format!(
+                "{}.entries[{}]",
+                kotlin_type_ident(name),
+                operands[0]
+            )),
+
+            Instruction::OptionLower {
+                results: result_types,
+                ..
+            } => {
+                let op = self.locals.tmp("option");
+                uwriteln!(self.src, "val {} = {}", op, operands[0]);
+                let cases = [
+                    ("Option.None".to_string(), false),
+                    ("Option.Some".to_string(), true),
+                ];
+                results.extend(self.lower_cases(&op, &cases, result_types));
+            }
+            Instruction::OptionLift { payload, .. } => {
+                let ty = format!("Option<{}>", self.gen.type_name(iface, payload));
+                let result = self.lift_cases(&operands[0], &ty, 2, |i, payload| match i {
+                    0 => "Option.None".to_string(),
+                    _ => format!("Option.Some({})", payload),
+                });
+                results.push(result);
+            }
+
+            Instruction::ExpectedLower {
+                results: result_types,
+                ..
+            } => {
+                let (err, err_results) = self.blocks.pop().unwrap();
+                let (ok, ok_results) = self.blocks.pop().unwrap();
+                let err_payload = self.payloads.pop().unwrap();
+                let ok_payload = self.payloads.pop().unwrap();
+                let op = self.locals.tmp("expected");
+                uwriteln!(self.src, "val {} = {}", op, operands[0]);
+                let expected_results = self.declare_results("expected", result_types);
+                uwriteln!(self.src, "when ({}) {{", op);
+                self.src.push_str("is Expected.Ok -> {\n");
+                uwriteln!(self.src, "val {} = {}.value", ok_payload, op);
+                self.branch(&ok, &ok_results, &expected_results);
+                self.src.push_str("is Expected.Err -> {\n");
+                uwriteln!(self.src, "val {} = {}.error", err_payload, op);
+                self.branch(&err, &err_results, &expected_results);
+                self.src.push_str("}\n");
+                results.extend(expected_results);
+            }
+            Instruction::ExpectedLift { expected, .. } => {
+                let ty = format!(
+                    "Expected<{}, {}>",
+                    self.gen.type_name(iface, &expected.ok),
+                    self.gen.type_name(iface, &expected.err)
+                );
+                let result = self.lift_cases(&operands[0], &ty, 2, |i, payload| match i {
+                    0 => format!("Expected.Ok({})", payload),
+                    _ => format!("Expected.Err({})", payload),
+                });
+                results.push(result);
+            }
+
+            Instruction::ListCanonLower { .. } => unreachable!("no lists are canonical"),
+            // Lists of chars are always lifted as canonical lists.
+            Instruction::ListCanonLift { element, .. } => {
+                assert_eq!(**element, Type::Char);
+                let result = self.locals.tmp("items");
+                uwriteln!(
+                    self.src,
+                    "val {} = List({}) {{ loadI32({}, it * 4) }}",
+                    result,
+                    operands[1],
+                    operands[0]
+                );
+                results.push(result);
+            }
+            Instruction::StringLower { .. } => {
+                let bytes = self.locals.tmp("bytes");
+                let address = self.locals.tmp("address");
+                uwriteln!(
+                    self.src,
+                    "val {} = {}.encodeToByteArray()",
+                    bytes,
+                    operands[0]
+                );
+                uwriteln!(
+                    self.src,
+                    "val {} = allocator.lowerBytes({})",
+                    address,
+                    bytes
+                );
+                results.push(address);
+                results.push(format!("{}.size", bytes));
+            }
+            Instruction::ListLower { element, .. } => {
+                let (body, body_results) = self.blocks.pop().unwrap();
+                assert!(body_results.is_empty());
+                let depth = self.block_storage.len() + 1;
+                let vec = self.locals.tmp("vec");
+                let address = self.locals.tmp("address");
+                let size = self.gen.sizes.size(element);
+                uwriteln!(self.src, "val {} = {}", vec, operands[0]);
+                uwriteln!(
+                    self.src,
+                    "val {} = allocator.alloc({}.size * {})",
+                    address,
+                    vec,
+                    size
+                );
+                uwriteln!(
+                    self.src,
+                    "for ((i{0}, e{0}) in {1}.withIndex()) {{",
+                    depth,
+                    vec
+                );
+                uwriteln!(
+                    self.src,
+                    "val base{} = {} + i{} * {}",
+                    depth,
+                    address,
+                    depth,
+                    size
+                );
+                self.src.push_str(&body);
+                self.src.push_str("}\n");
+                results.push(address);
+                results.push(format!("{}.size", vec));
+            }
+
+            Instruction::StringLift { .. } => {
+                let result = self.locals.tmp("str");
+                uwriteln!(
+                    self.src,
+                    "val {} = liftString({}, {})",
+                    result,
+                    operands[0],
+                    operands[1]
+                );
+                results.push(result);
+            }
+            Instruction::ListLift { element, .. } => {
+                let (body, body_results) = self.blocks.pop().unwrap();
+                let depth = self.block_storage.len() + 1;
+                let address = self.locals.tmp("address");
+                let count = self.locals.tmp("count");
+                let result = self.locals.tmp("items");
+                let size = self.gen.sizes.size(element);
+                let ty = self.gen.type_name(iface, element);
+                uwriteln!(self.src, "val {} = {}", address, operands[0]);
+                uwriteln!(self.src, "val {} = {}", count, operands[1]);
+                uwriteln!(self.src, "val {} = ArrayList<{}>({})", result, ty, count);
+                uwriteln!(self.src, "for (i{} in 0 until {}) {{", depth, count);
+                uwriteln!(
+                    self.src,
+                    "val base{} = {} + i{} * {}",
+                    depth,
+                    address,
+                    depth,
+                    size
+                );
+                self.src.push_str(&body);
+                uwriteln!(self.src, "{}.add({})", result, body_results[0]);
+                self.src.push_str("}\n");
+                results.push(result);
+            }
+
+            Instruction::IterElem { .. } => {
+                results.push(format!("e{}", self.block_storage.len()));
+            }
+            Instruction::IterBasePointer => {
+                results.push(format!("base{}", self.block_storage.len()));
+            }
+
+            Instruction::CallWasm { name, sig, .. } => {
+                let call = format!(
+                    "wasmImport{}({})",
+                    name.to_camel_case(),
+                    operands.join(", ")
+                );
+                match sig.results.len() {
+                    0 => uwriteln!(self.src, "{}", call),
+                    1 => {
+                        let ret = self.locals.tmp("ret");
+                        uwriteln!(self.src, "val {} = {}", ret, call);
+                        results.push(ret);
+                    }
+                    _ => unimplemented!("multi-value return not supported"),
+                }
+            }
+
+            Instruction::CallInterface { func, .. } => {
+                let call = format!(
+                    "exportsImpl.{}({})",
+                    kotlin_ident(&func.name.to_mixed_case()),
+                    operands.join(", ")
+                );
+                if func.result == Type::Unit {
+                    uwriteln!(self.src, "{}", call);
+                    results.push("Unit".to_string());
+                } else {
+                    let ret = self.locals.tmp("ret");
+                    uwriteln!(self.src, "val {} = {}", ret, call);
+                    results.push(ret);
+                }
+            }
+
+            Instruction::Return { amt, func } => {
+                if self.gen.in_import {
+                    // The memory the host allocated for the results has been
+                    // read by now.
+                    self.src.push_str("releasePending()\n");
+                }
+                match amt {
+                    0 => {}
+                    // Functions don't return `Unit` explicitly.
+                    1 if func.result == Type::Unit && self.gen.in_import => {}
+                    1 => uwriteln!(self.src, "{}", operands[0]),
+                    _ => unimplemented!("multi-value return not supported"),
+                }
+            }
+
+            Instruction::I32Load { offset } => self.load("I32", *offset, operands, results),
+            Instruction::I32Load8U { offset } => self.load("U8", *offset, operands, results),
+            Instruction::I32Load8S { offset } => self.load("S8", *offset, operands, results),
+            Instruction::I32Load16U { offset } => self.load("U16", *offset, operands, results),
+            Instruction::I32Load16S { offset } => self.load("S16", *offset, operands, results),
+            Instruction::I64Load { offset } => self.load("I64", *offset, operands, results),
+            Instruction::F32Load { offset } => self.load("F32", *offset, operands, results),
+            Instruction::F64Load { offset } => self.load("F64", *offset, operands, results),
+            Instruction::I32Store { offset } => self.store("I32", *offset, operands),
+            Instruction::I32Store8 { offset } => self.store("I8", *offset, operands),
+            Instruction::I32Store16 { offset } => self.store("I16", *offset, operands),
+            Instruction::I64Store { offset } => self.store("I64", *offset, operands),
+            Instruction::F32Store { offset } => self.store("F32", *offset, operands),
+            Instruction::F64Store { offset } => self.store("F64", *offset, operands),
+
+            Instruction::Malloc { size, .. } => {
+                let address = self.locals.tmp("address");
+                uwriteln!(self.src, "val {} = allocator.alloc({})", address, size);
+                results.push(address);
+            }
+            // Memory is reclaimed at the end of the scope of the call.
+            Instruction::Free { .. } => {}
+
+            Instruction::CallWasmAsyncImport { .. }
+            | Instruction::CallWasmAsyncExport { .. }
+            | Instruction::ReturnAsyncExport { .. }
+            | Instruction::ReturnAsyncImport { .. } => {
+                unimplemented!("async not supported yet")
+            }
+        }
+    }
+}
+
+fn kdoc(src: &mut Source, docs: &Docs) {
+    if let Some(docs) = &docs.contents {
+        src.push_str("/**\n");
+        for line in docs.trim().lines() {
+            uwriteln!(src, " * {}", line.trim());
+        }
+        src.push_str(" */\n");
+    }
+}
+
+/// Quotes names which are hard keywords in Kotlin.
+fn kotlin_ident(name: &str) -> String {
+    match name {
+        "as" | "break" | "class" | "continue" | "do" | "else" | "false" | "for" | "fun" | "if"
+        | "in" | "interface" | "is" | "null" | "object" | "package" | "return" | "super"
+        | "this" | "throw" | "true" | "try" | "typealias" | "typeof" | "val" | "var" | "when"
+        | "while" => format!("`{}`", name),
+        _ => name.to_string(),
+    }
+}
+
+/// The name of the type `name`, renamed if it would shadow one of the types
+/// the bindings rely on.
+fn kotlin_type_ident(name: &str) -> String {
+    let name = name.to_camel_case();
+    match name.as_str() {
+        "Unit" | "Option" | "Expected" | "Pair" | "Triple" | "Exports" | "Int" | "Long"
+        | "Float" | "Double" | "String" | "Boolean" | "List" | "Set" => format!("{}Type", name),
+        n if n.starts_with("Tuple") && n[5..].parse::<usize>().is_ok() => {
+            format!("{}Type", name)
+        }
+        _ => name,
+    }
+}
+
+/// The package of the interface `name`, whose segments can't be keywords.
+fn kotlin_package(name: &str) -> String {
+    let name = name.to_snake_case();
+    match kotlin_ident(&name) {
+        quoted if quoted != name => format!("{}_", name),
+        _ => name,
+    }
+}
+
+fn wasm_type(ty: WasmType) -> &'static str {
+    match ty {
+        WasmType::I32 => "Int",
+        WasmType::I64 => "Long",
+        WasmType::F32 => "Float",
+        WasmType::F64 => "Double",
+    }
+}
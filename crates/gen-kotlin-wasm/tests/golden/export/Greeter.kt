@@ -0,0 +1,84 @@
+// Generated by `wai-bindgen` from the `greeter` interface.
+@file:OptIn(UnsafeWasmMemoryApi::class)
+
+package greeter
+
+import kotlin.wasm.WasmExport
+import kotlin.wasm.WasmImport
+import kotlin.wasm.unsafe.UnsafeWasmMemoryApi
+import kotlin.wasm.unsafe.withScopedMemoryAllocator
+import wai.*
+
+data class Point(
+  val x: UInt,
+  val y: UInt,
+)
+
+sealed interface Shape {
+  data class Dot(val value: Point) : Shape
+  data object Empty : Shape
+}
+
+/** The implementation of the exports of this interface. */
+interface Exports {
+  fun greet(name: String, at: Shape): List<Tuple4<UByte, UByte, UByte, UByte>>
+  fun spell(word: String): List<Int>
+}
+
+private lateinit var exportsImpl: Exports
+
+/** Registers the implementation of the exports of this interface. */
+fun setExports(impl: Exports) {
+  exportsImpl = impl
+}
+
+@WasmExport("greet")
+fun wasmExportGreet(arg0: Int, arg1: Int, arg2: Int, arg3: Int, arg4: Int): Int {
+  return withScopedMemoryAllocator { allocator ->
+    claimPending(allocator)
+    val str = liftString(arg0, arg1)
+    val variant: Shape
+    when (arg2) {
+      0 -> {
+        variant = Shape.Dot(Point(arg3.toUInt(), arg4.toUInt()))
+      }
+      else -> {
+        variant = Shape.Empty
+      }
+    }
+    val ret = exportsImpl.greet(str, variant)
+    val address = allocator.alloc(8)
+    val vec = ret
+    val address0 = allocator.alloc(vec.size * 4)
+    for ((i1, e1) in vec.withIndex()) {
+      val base1 = address0 + i1 * 4
+      val tuple = e1
+      storeI8(base1, 0, tuple.component1().toInt())
+      storeI8(base1, 1, tuple.component2().toInt())
+      storeI8(base1, 2, tuple.component3().toInt())
+      storeI8(base1, 3, tuple.component4().toInt())
+    }
+    storeI32(address, 4, vec.size)
+    storeI32(address, 0, address0)
+    address
+  }
+}
+
+@WasmExport("spell")
+fun wasmExportSpell(arg0: Int, arg1: Int): Int {
+  return withScopedMemoryAllocator { allocator ->
+    claimPending(allocator)
+    val str = liftString(arg0, arg1)
+    val ret = exportsImpl.spell(str)
+    val address = allocator.alloc(8)
+    val vec = ret
+    val address0 = allocator.alloc(vec.size * 4)
+    for ((i1, e1) in vec.withIndex()) {
+      val base1 = address0 + i1 * 4
+      storeI32(base1, 0, e1)
+    }
+    storeI32(address, 4, vec.size)
+    storeI32(address, 0, address0)
+    address
+  }
+}
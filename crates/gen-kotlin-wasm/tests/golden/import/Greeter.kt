@@ -0,0 +1,82 @@
+// Generated by `wai-bindgen` from the `greeter` interface.
+@file:OptIn(UnsafeWasmMemoryApi::class)
+
+package greeter
+
+import kotlin.wasm.WasmExport
+import kotlin.wasm.WasmImport
+import kotlin.wasm.unsafe.UnsafeWasmMemoryApi
+import kotlin.wasm.unsafe.withScopedMemoryAllocator
+import wai.*
+
+data class Point(
+  val x: UInt,
+  val y: UInt,
+)
+
+sealed interface Shape {
+  data class Dot(val value: Point) : Shape
+  data object Empty : Shape
+}
+
+@WasmImport("greeter", "greet")
+private external fun wasmImportGreet(arg0: Int, arg1: Int, arg2: Int, arg3: Int, arg4: Int, arg5: Int)
+
+fun greet(name: String, at: Shape): List<Tuple4<UByte, UByte, UByte, UByte>> {
+  return withScopedMemoryAllocator { allocator ->
+    val bytes = name.encodeToByteArray()
+    val address = allocator.lowerBytes(bytes)
+    val variant = at
+    var variant1 = 0
+    var variant2 = 0
+    var variant3 = 0
+    when (variant) {
+      is Shape.Dot -> {
+        val payload = variant.value
+        val record = payload
+        variant1 = 0
+        variant2 = record.x.toInt()
+        variant3 = record.y.toInt()
+      }
+      is Shape.Empty -> {
+        variant1 = 1
+        variant2 = 0
+        variant3 = 0
+      }
+    }
+    val address4 = allocator.alloc(8)
+    wasmImportGreet(address, bytes.size, variant1, variant2, variant3, address4)
+    val load = loadI32(address4, 0)
+    val load5 = loadI32(address4, 4)
+    val address10 = load
+    val count = load5
+    val items = ArrayList<Tuple4<UByte, UByte, UByte, UByte>>(count)
+    for (i1 in 0 until count) {
+      val base1 = address10 + i1 * 4
+      val load6 = loadU8(base1, 0)
+      val load7 = loadU8(base1, 1)
+      val load8 = loadU8(base1, 2)
+      val load9 = loadU8(base1, 3)
+      items.add(Tuple4(load6.toUByte(), load7.toUByte(), load8.toUByte(), load9.toUByte()))
+    }
+    releasePending()
+    items
+  }
+}
+
+@WasmImport("greeter", "spell")
+private external fun wasmImportSpell(arg0: Int, arg1: Int, arg2: Int)
+
+fun spell(word: String): List<Int> {
+  return withScopedMemoryAllocator { allocator ->
+    val bytes = word.encodeToByteArray()
+    val address = allocator.lowerBytes(bytes)
+    val address0 = allocator.alloc(8)
+    wasmImportSpell(address, bytes.size, address0)
+    val load = loadI32(address0, 0)
+    val load1 = loadI32(address0, 4)
+    val items = List(load1) { loadI32(load, it * 4) }
+    releasePending()
+    items
+  }
+}
@@ -0,0 +1,101 @@
+// Generated by `wai-bindgen`: the runtime support of the bindings of all
+// interfaces.
+@file:OptIn(UnsafeWasmMemoryApi::class)
+
+package wai
+
+import kotlin.wasm.WasmExport
+import kotlin.wasm.unsafe.MemoryAllocator
+import kotlin.wasm.unsafe.Pointer
+import kotlin.wasm.unsafe.UnsafeWasmMemoryApi
+import kotlin.wasm.unsafe.withScopedMemoryAllocator
+
+/** The value of an `option<T>`. */
+sealed interface Option<out T> {
+  data class Some<out T>(val value: T) : Option<T>
+  data object None : Option<Nothing>
+}
+
+/** The value of an `expected<T, E>`. */
+sealed interface Expected<out T, out E> {
+  data class Ok<out T>(val value: T) : Expected<T, Nothing>
+  data class Err<out E>(val error: E) : Expected<Nothing, E>
+}
+
+data class Tuple4<out T1, out T2, out T3, out T4>(val value1: T1, val value2: T2, val value3: T3, val value4: T4)
+
+// The bytes handed out by `canonical_abi_realloc` on top of the current
+// scope which haven't been claimed by the call they are for yet.
+private var pending = 0
+
+@WasmExport("canonical_abi_realloc")
+fun canonicalAbiRealloc(ptr: Int, origSize: Int, align: Int, newSize: Int): Int {
+  // Empty allocations get a dangling pointer which is never freed.
+  if (newSize == 0) {
+    return align
+  }
+  val offset = (pending + align - 1) / align * align
+  val ret = withScopedMemoryAllocator { it.alloc(offset + newSize) } + offset
+  for (i in 0 until minOf(origSize, newSize)) {
+    storeI8(ret, i, loadU8(ptr, i))
+  }
+  pending = offset + newSize
+  return ret
+}
+
+@WasmExport("canonical_abi_free")
+fun canonicalAbiFree(ptr: Int, size: Int, align: Int) {
+  // Memory is reclaimed at the end of the scope it's allocated in.
+}
+
+/** Claims the memory handed out by `canonical_abi_realloc` for the scope of `allocator`. */
+internal fun claimPending(allocator: MemoryAllocator) {
+  if (pending > 0) {
+    allocator.alloc(pending)
+  }
+  pending = 0
+}
+
+/** Gives up the memory handed out by `canonical_abi_realloc` once it has been read. */
+internal fun releasePending() {
+  pending = 0
+}
+
+internal fun MemoryAllocator.alloc(size: Int): Int = allocate(size).address.toInt()
+
+private fun at(address: Int, offset: Int): Pointer = Pointer((address + offset).toUInt())
+
+internal fun loadU8(address: Int, offset: Int): Int = at(address, offset).loadByte().toInt() and 0xff
+internal fun loadS8(address: Int, offset: Int): Int = at(address, offset).loadByte().toInt()
+internal fun loadU16(address: Int, offset: Int): Int = at(address, offset).loadShort().toInt() and 0xffff
+internal fun loadS16(address: Int, offset: Int): Int = at(address, offset).loadShort().toInt()
+internal fun loadI32(address: Int, offset: Int): Int = at(address, offset).loadInt()
+internal fun loadI64(address: Int, offset: Int): Long = at(address, offset).loadLong()
+internal fun loadF32(address: Int, offset: Int): Float = Float.fromBits(loadI32(address, offset))
+internal fun loadF64(address: Int, offset: Int): Double = Double.fromBits(loadI64(address, offset))
+
+internal fun storeI8(address: Int, offset: Int, value: Int) = at(address, offset).storeByte(value.toByte())
+internal fun storeI16(address: Int, offset: Int, value: Int) = at(address, offset).storeShort(value.toShort())
+internal fun storeI32(address: Int, offset: Int, value: Int) = at(address, offset).storeInt(value)
+internal fun storeI64(address: Int, offset: Int, value: Long) = at(address, offset).storeLong(value)
+internal fun storeF32(address: Int, offset: Int, value: Float) = storeI32(address, offset, value.toRawBits())
+internal fun storeF64(address: Int, offset: Int, value: Double) = storeI64(address, offset, value.toRawBits())
+
+/** Copies `bytes` into memory allocated in the scope of `this`. */
+internal fun MemoryAllocator.lowerBytes(bytes: ByteArray): Int {
+  val address = alloc(bytes.size)
+  for (i in bytes.indices) {
+    at(address, i).storeByte(bytes[i])
+  }
+  return address
+}
+
+internal fun liftString(address: Int, len: Int): String =
+ByteArray(len) { at(address, it).loadByte() }.decodeToString()
+
+/** The bits of the 32 flags starting at `32 * word` in `flags`. */
+internal fun <T : Enum<T>> flagsToBits(flags: Set<T>, word: Int): Int =
+flags.filter { it.ordinal / 32 == word }.fold(0) { bits, flag -> bits or (1 shl (flag.ordinal % 32)) }
+
+internal fun <T : Enum<T>> flagsFromBits(all: List<T>, vararg words: Int): Set<T> =
+all.filter { (words[it.ordinal / 32] ushr (it.ordinal % 32)) and 1 != 0 }.toSet()
@@ -1,6 +1,7 @@
 use heck::*;
 use pulldown_cmark::{html, Event, LinkType, Parser, Tag};
 use std::collections::HashMap;
+use std::mem;
 use wai_bindgen_gen_core::{wai_parser, Direction, Files, Generator, Source};
 use wai_parser::*;
 
@@ -120,13 +121,25 @@ impl Markdown {
     }
 
     fn docs(&mut self, docs: &Docs) {
-        let docs = match &docs.contents {
-            Some(docs) => docs,
-            None => return,
-        };
-        for line in docs.lines() {
-            self.src.push_str(line.trim());
+        if let Some(contents) = &docs.contents {
+            for line in contents.lines() {
+                self.src.push_str(line.trim());
+                self.src.push_str("\n");
+            }
+        }
+        for (i, example) in docs.examples.iter().enumerate() {
+            if i == 0 && docs.contents.is_some() {
+                self.src.push_str("\n");
+            }
+            self.src.push_str("```");
+            self.src.push_str(example.lang.as_deref().unwrap_or(""));
             self.src.push_str("\n");
+            for line in example.code.lines() {
+                // Written as is, since code isn't indented by its braces.
+                self.src.as_mut_string().push_str(line);
+                self.src.push_str("\n");
+            }
+            self.src.push_str("```\n\n");
         }
     }
 
@@ -417,6 +430,12 @@ impl Generator for Markdown {
                     p = name.to_snake_case(),
                 ));
                 self.print_ty(iface, ty, false);
+                if let Some(range) = func.range(name) {
+                    self.src.push_str(&format!(
+                        ", between {} and {} inclusive",
+                        range.min, range.max
+                    ));
+                }
                 self.src.push_str("\n");
             }
         }
@@ -442,8 +461,9 @@ impl Generator for Markdown {
         self.import(iface, func);
     }
 
-    fn finish_one(&mut self, _iface: &Interface, files: &mut Files) {
-        let parser = Parser::new(&self.src);
+    fn finish_one(&mut self, iface: &Interface, files: &mut Files) {
+        let src = mem::take(&mut self.src);
+        let parser = Parser::new(&src);
         let mut events = Vec::new();
         for event in parser {
             if let Event::Code(code) = &event {
@@ -460,7 +480,8 @@ impl Generator for Markdown {
         let mut html_output = String::new();
         html::push_html(&mut html_output, events.into_iter());
 
-        files.push("bindings.md", self.src.as_bytes());
-        files.push("bindings.html", html_output.as_bytes());
+        let stem = iface.name.to_kebab_case();
+        files.push_for_interface(&stem, "md", src.as_bytes());
+        files.push_for_interface(&stem, "html", html_output.as_bytes());
     }
 }
@@ -0,0 +1,1594 @@
+//! Bindings for Nim compiled to wasm.
+//!
+//! For each interface this emits a `<interface>.nim` module with its types,
+//! records as objects and variants as object variants, and procs which lift
+//! and lower the canonical ABI around the raw wasm imports and exports, which
+//! are `{.importc.}` and `{.exportc.}` procs placed in the right wasm import
+//! module or under the right export name through `codegenDecl`. Exports are
+//! implemented by passing an `Exports` object of procs to `setExports`.
+//! All modules build on a shared `wai_abi.nim` with the `Unit` and
+//! `Expected` types, the allocator and the return area of the bindings.
+//!
+//! The bindings are meant to be compiled with the C backend, and the
+//! embedding is expected to call `NimMain` before any export is called when
+//! building with `--noMain`.
+
+use heck::*;
+use std::fmt::Write;
+use std::mem;
+use wai_bindgen_gen_core::wai_parser::abi::{
+    AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmType,
+};
+use wai_bindgen_gen_core::{
+    union_case_names, uwrite, uwriteln, wai_parser::*, Direction, Files, Generator, Ns,
+};
+
+#[derive(Default)]
+pub struct Nim {
+    opts: Opts,
+    sizes: SizeAlign,
+    in_import: bool,
+    // The entries of the `type` section of the current interface.
+    types: NimSource,
+    // The procs of the current interface.
+    funcs: NimSource,
+    // The fields of the `Exports` object of the current interface.
+    exports: NimSource,
+    return_pointer_area_size: usize,
+    return_pointer_area_align: usize,
+}
+
+#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "structopt", derive(structopt::StructOpt))]
+pub struct Opts {
+    // ...
+}
+
+impl Opts {
+    pub fn build(&self) -> Nim {
+        let mut r = Nim::new();
+        r.opts = self.clone();
+        r
+    }
+}
+
+impl Nim {
+    pub fn new() -> Nim {
+        Nim::default()
+    }
+
+    fn abi_variant(dir: Direction) -> AbiVariant {
+        // This generator uses the obvious direction to ABI variant mapping.
+        match dir {
+            Direction::Export => AbiVariant::GuestExport,
+            Direction::Import => AbiVariant::GuestImport,
+        }
+    }
+
+    fn type_name(&self, iface: &Interface, ty: &Type) -> String {
+        match ty {
+            Type::Unit => "Unit".to_string(),
+            Type::Bool => "bool".to_string(),
+            Type::U8 => "uint8".to_string(),
+            Type::U16 => "uint16".to_string(),
+            Type::U32 => "uint32".to_string(),
+            Type::U64 => "uint64".to_string(),
+            Type::S8 => "int8".to_string(),
+            Type::S16 => "int16".to_string(),
+            Type::S32 => "int32".to_string(),
+            Type::S64 => "int64".to_string(),
+            Type::Float32 => "float32".to_string(),
+            Type::Float64 => "float64".to_string(),
+            Type::Char => "Rune".to_string(),
+            Type::String => "string".to_string(),
+            Type::Handle(_) => unimplemented!("resources not supported yet by Nim guests"),
+            Type::Id(id) => {
+                let ty = &iface.types[*id];
+                match &ty.name {
+                    Some(name) => nim_type_ident(name),
+                    None => self.type_def_name(iface, &ty.kind),
+                }
+            }
+        }
+    }
+
+    /// The structure of the type `kind`, ignoring its name.
+    fn type_def_name(&self, iface: &Interface, kind: &TypeDefKind) -> String {
+        match kind {
+            TypeDefKind::Type(t) => self.type_name(iface, t),
+            TypeDefKind::List(t) => format!("seq[{}]", self.type_name(iface, t)),
+            TypeDefKind::Tuple(t) => self.tuple_name(iface, &t.types),
+            TypeDefKind::Option(t) => format!("Option[{}]", self.type_name(iface, t)),
+            TypeDefKind::Expected(e) => format!(
+                "Expected[{}, {}]",
+                self.type_name(iface, &e.ok),
+                self.type_name(iface, &e.err)
+            ),
+            TypeDefKind::Record(_)
+            | TypeDefKind::Flags(_)
+            | TypeDefKind::Variant(_)
+            | TypeDefKind::Enum(_)
+            | TypeDefKind::Union(_) => unreachable!("unnamed {:?}", kind),
+            TypeDefKind::Future(_) => todo!("type_def_name for future"),
+            TypeDefKind::Stream(_) => todo!("type_def_name for stream"),
+        }
+    }
+
+    fn tuple_name(&self, iface: &Interface, types: &[Type]) -> String {
+        let types = types
+            .iter()
+            .map(|t| self.type_name(iface, t))
+            .collect::<Vec<_>>();
+        match types.len() {
+            0 => "Unit".to_string(),
+            1 => format!("({},)", types[0]),
+            _ => format!("({})", types.join(", ")),
+        }
+    }
+
+    fn print_alias(&mut self, name: &str, target: &str, docs: &Docs) {
+        uwriteln!(self.types, "{}* = {}", nim_type_ident(name), target);
+        self.types.indent();
+        self.types.doc_comment(docs);
+        self.types.dedent();
+    }
+
+    /// Prints the signature of a proc taking `params`, without the trailing
+    /// `=`.
+    fn print_signature(
+        &mut self,
+        iface: &Interface,
+        name: &str,
+        params: &[String],
+        func: &Function,
+    ) {
+        uwrite!(self.funcs, "proc {}*(", name);
+        for (i, (param, (_, ty))) in params.iter().zip(&func.params).enumerate() {
+            if i > 0 {
+                self.funcs.push_str(", ");
+            }
+            uwrite!(self.funcs, "{}: {}", param, self.type_name(iface, ty));
+        }
+        self.funcs.push_str(")");
+        if func.result != Type::Unit {
+            uwrite!(self.funcs, ": {}", self.type_name(iface, &func.result));
+        }
+    }
+
+    /// Prints an object variant named `name` with a case for each of
+    /// `cases`, which have a field of their name unless their payload is
+    /// `unit`.
+    fn print_object_variant(
+        &mut self,
+        iface: &Interface,
+        name: &str,
+        cases: &[(String, &Type, &Docs)],
+        docs: &Docs,
+    ) {
+        let name = nim_type_ident(name);
+        let kind = format!("{}Kind", name);
+        uwriteln!(self.types, "{}* {{.pure.}} = enum", kind);
+        self.types.indent();
+        for (case, _, docs) in cases {
+            uwriteln!(self.types, "{}", nim_ident(&case.to_mixed_case()));
+            self.types.doc_comment(docs);
+        }
+        self.types.dedent();
+        uwriteln!(self.types, "{}* = object", name);
+        self.types.indent();
+        self.types.doc_comment(docs);
+        uwriteln!(self.types, "case kind*: {}", kind);
+        for (case, ty, _) in cases {
+            uwriteln!(
+                self.types,
+                "of {}.{}:",
+                kind,
+                nim_ident(&case.to_mixed_case())
+            );
+            self.types.indent();
+            if **ty == Type::Unit {
+                self.types.push_str("discard\n");
+            } else {
+                uwriteln!(
+                    self.types,
+                    "{}*: {}",
+                    case_field(case),
+                    self.type_name(iface, ty)
+                );
+            }
+            self.types.dedent();
+        }
+        self.types.dedent();
+    }
+
+    fn print_intrinsics(&self) -> String {
+        let mut src = NimSource::default();
+        uwrite!(
+            src,
+            "
+                # Generated by `wai-bindgen`: the runtime support of the bindings of all
+                # interfaces.
+
+                type
+                  Unit* = object
+                    ## The value of `unit` and of `tuple<>`.
+
+                  Expected*[T, E] = object
+                    ## The value of an `expected<T, E>`: either a `value` or an `error`.
+                    case isOk*: bool
+                    of true:
+                      value*: T
+                    of false:
+                      error*: E
+
+                proc ok*[T, E](value: T): Expected[T, E] =
+                  Expected[T, E](isOk: true, value: value)
+
+                proc err*[T, E](error: E): Expected[T, E] =
+                  Expected[T, E](isOk: false, error: error)
+
+                # Note that these intrinsics are declared as `weak` so they can be
+                # overridden from some other symbol.
+                proc canonicalAbiRealloc(address: pointer, origSize, align, newSize: uint32): pointer {{.
+                    exportc: \"canonical_abi_realloc\", cdecl,
+                    codegenDecl: \"__attribute__((weak, export_name(\\\"canonical_abi_realloc\\\"))) $# $#$#\".}} =
+                  # Empty allocations get a dangling pointer which is never freed.
+                  if newSize == 0:
+                    return cast[pointer](align)
+                  if origSize == 0:
+                    result = allocShared(newSize)
+                  else:
+                    result = reallocShared(address, newSize)
+
+                proc canonicalAbiFree(address: pointer, size, align: uint32) {{.
+                    exportc: \"canonical_abi_free\", cdecl,
+                    codegenDecl: \"__attribute__((weak, export_name(\\\"canonical_abi_free\\\"))) $# $#$#\".}} =
+                  if size != 0:
+                    deallocShared(address)
+
+                var retAreaStorage {{.align({align}).}}: array[{size}, uint8]
+
+                proc retArea*(): int32 =
+                  cast[int32](addr retAreaStorage)
+
+                proc abiAlloc*(size, align: int32): int32 =
+                  cast[int32](canonicalAbiRealloc(nil, 0, uint32(align), uint32(size)))
+
+                proc abiFree*(address, size, align: int32) =
+                  canonicalAbiFree(cast[pointer](address), uint32(size), uint32(align))
+
+                template load*(T: typedesc, address, offset: int32): untyped =
+                  cast[ptr T](uint(cast[uint32](address)) + uint(offset))[]
+
+                template store*(T: typedesc, address, offset: int32, value: T) =
+                  cast[ptr T](uint(cast[uint32](address)) + uint(offset))[] = value
+
+                proc lowerList*[T](items: openArray[T]): int32 =
+                  ## Copies `items` into a new allocation, given away to the caller.
+                  result = abiAlloc(int32(items.len * sizeof(T)), int32(alignof(T)))
+                  if items.len > 0:
+                    copyMem(cast[pointer](result), unsafeAddr items[0], items.len * sizeof(T))
+
+                proc liftList*[T](address, len: int32): seq[T] =
+                  result = newSeq[T](len)
+                  if len > 0:
+                    copyMem(addr result[0], cast[pointer](address), int(len) * sizeof(T))
+
+                proc liftString*(address, len: int32): string =
+                  result = newString(len)
+                  if len > 0:
+                    copyMem(addr result[0], cast[pointer](address), len)
+
+                proc flagsToBits*[T: enum](flags: set[T], word: int): int32 =
+                  ## The bits of the 32 flags starting at `32 * word` in `flags`.
+                  var bits = 0'u32
+                  for flag in flags:
+                    if ord(flag) div 32 == word:
+                      bits = bits or (1'u32 shl (ord(flag) mod 32))
+                  cast[int32](bits)
+
+                proc flagsFromBits*[T: enum](words: openArray[int32]): set[T] =
+                  for flag in T:
+                    if ((cast[uint32](words[ord(flag) div 32]) shr (ord(flag) mod 32)) and 1) != 0:
+                      result.incl flag
+            ",
+            size = self.return_pointer_area_size,
+            align = self.return_pointer_area_align.max(1),
+        );
+        src.into()
+    }
+}
+
+impl Generator for Nim {
+    fn preprocess_one(&mut self, iface: &Interface, dir: Direction) {
+        assert!(
+            iface.resources.len() == 0,
+            "resources not supported yet by Nim guests"
+        );
+        let variant = Self::abi_variant(dir);
+        self.sizes.fill(iface);
+        self.in_import = variant == AbiVariant::GuestImport;
+    }
+
+    fn type_record(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        record: &Record,
+        docs: &Docs,
+    ) {
+        uwriteln!(self.types, "{}* = object", nim_type_ident(name));
+        self.types.indent();
+        self.types.doc_comment(docs);
+        for field in record.fields.iter() {
+            uwriteln!(
+                self.types,
+                "{}*: {}",
+                nim_ident(&field.name.to_mixed_case()),
+                self.type_name(iface, &field.ty)
+            );
+            self.types.doc_comment(&field.docs);
+        }
+        self.types.dedent();
+    }
+
+    fn type_tuple(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        tuple: &Tuple,
+        docs: &Docs,
+    ) {
+        let target = self.tuple_name(iface, &tuple.types);
+        self.print_alias(name, &target, docs);
+    }
+
+    fn type_flags(
+        &mut self,
+        _iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        flags: &Flags,
+        docs: &Docs,
+    ) {
+        let name = nim_type_ident(name);
+        if flags.flags.is_empty() {
+            // There's no empty enum to make a set of.
+            uwriteln!(self.types, "{}* = object", name);
+            self.types.indent();
+            self.types.doc_comment(docs);
+            self.types.dedent();
+            return;
+        }
+        uwriteln!(self.types, "{}Flag* {{.pure.}} = enum", name);
+        self.types.indent();
+        for flag in flags.flags.iter() {
+            uwriteln!(self.types, "{}", nim_ident(&flag.name.to_mixed_case()));
+            self.types.doc_comment(&flag.docs);
+        }
+        self.types.dedent();
+        uwriteln!(self.types, "{0}* = set[{0}Flag]", name);
+        self.types.indent();
+        self.types.doc_comment(docs);
+        self.types.dedent();
+    }
+
+    fn type_variant(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        variant: &Variant,
+        docs: &Docs,
+    ) {
+        let cases = variant
+            .cases
+            .iter()
+            .map(|c| (c.name.clone(), &c.ty, &c.docs))
+            .collect::<Vec<_>>();
+        self.print_object_variant(iface, name, &cases, docs);
+    }
+
+    fn type_union(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        union: &Union,
+        docs: &Docs,
+    ) {
+        let cases = union_case_names(iface, union)
+            .into_iter()
+            .zip(&union.cases)
+            .map(|(name, c)| (name, &c.ty, &c.docs))
+            .collect::<Vec<_>>();
+        self.print_object_variant(iface, name, &cases, docs);
+    }
+
+    fn type_option(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        name: &str,
+        _payload: &Type,
+        docs: &Docs,
+    ) {
+        let target = self.type_def_name(iface, &iface.types[id].kind);
+        self.print_alias(name, &target, docs);
+    }
+
+    fn type_expected(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        name: &str,
+        _expected: &Expected,
+        docs: &Docs,
+    ) {
+        let target = self.type_def_name(iface, &iface.types[id].kind);
+        self.print_alias(name, &target, docs);
+    }
+
+    fn type_enum(
+        &mut self,
+        _iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        enum_: &Enum,
+        docs: &Docs,
+    ) {
+        uwriteln!(self.types, "{}* {{.pure.}} = enum", nim_type_ident(name));
+        self.types.indent();
+        self.types.doc_comment(docs);
+        for case in enum_.cases.iter() {
+            uwriteln!(self.types, "{}", nim_ident(&case.name.to_mixed_case()));
+            self.types.doc_comment(&case.docs);
+        }
+        self.types.dedent();
+    }
+
+    fn type_resource(&mut self, _iface: &Interface, _ty: ResourceId) {
+        unimplemented!("resources not supported yet by Nim guests")
+    }
+
+    fn type_alias(&mut self, iface: &Interface, _id: TypeId, name: &str, ty: &Type, docs: &Docs) {
+        let target = self.type_name(iface, ty);
+        self.print_alias(name, &target, docs);
+    }
+
+    fn type_list(&mut self, iface: &Interface, id: TypeId, name: &str, _ty: &Type, docs: &Docs) {
+        let target = self.type_def_name(iface, &iface.types[id].kind);
+        self.print_alias(name, &target, docs);
+    }
+
+    fn type_builtin(&mut self, iface: &Interface, id: TypeId, name: &str, ty: &Type, docs: &Docs) {
+        self.type_alias(iface, id, name, ty, docs);
+    }
+
+    fn import(&mut self, iface: &Interface, func: &Function) {
+        assert!(!func.is_async, "async not supported yet");
+        let sig = iface.wasm_signature(AbiVariant::GuestImport, func);
+        let raw_name = format!("wasmImport{}", func.name.to_camel_case());
+
+        uwrite!(self.funcs, "proc {}(", raw_name);
+        for (i, param) in sig.params.iter().enumerate() {
+            if i > 0 {
+                self.funcs.push_str(", ");
+            }
+            uwrite!(self.funcs, "arg{}: {}", i, wasm_type(*param));
+        }
+        self.funcs.push_str(")");
+        match sig.results.len() {
+            0 => {}
+            1 => uwrite!(self.funcs, ": {}", wasm_type(sig.results[0])),
+            _ => unimplemented!("multi-value return not supported"),
+        }
+        uwriteln!(
+            self.funcs,
+            " {{.importc: \"__wasm_import_{}_{}\", cdecl,",
+            iface.name.to_snake_case(),
+            func.name.to_snake_case()
+        );
+        uwriteln!(
+            self.funcs,
+            "    codegenDecl: \"__attribute__((import_module(\\\"{}\\\"), import_name(\\\"{}\\\"))) $# $#$#\".}}\n",
+            iface.name,
+            func.name
+        );
+
+        let mut locals = FunctionBindgen::locals();
+        let params = func
+            .params
+            .iter()
+            .map(|(name, _)| locals.tmp(&nim_ident(&name.to_mixed_case())))
+            .collect::<Vec<_>>();
+        self.print_signature(iface, &nim_ident(&func.name.to_mixed_case()), &params, func);
+        self.funcs.push_str(" =\n");
+        self.funcs.indent();
+        self.funcs.doc_comment(&func.docs);
+        let mut f = FunctionBindgen::new(self, locals, params);
+        iface.call(
+            AbiVariant::GuestImport,
+            LiftLower::LowerArgsLiftResults,
+            func,
+            &mut f,
+        );
+        let body = f.finish();
+        self.funcs.push_str(&body);
+        self.funcs.dedent();
+        self.funcs.push_str("\n");
+    }
+
+    fn export(&mut self, iface: &Interface, func: &Function) {
+        assert!(!func.is_async, "async not supported yet");
+        let sig = iface.wasm_signature(AbiVariant::GuestExport, func);
+
+        // The implementation of the export is a field of `Exports`.
+        let params = func
+            .params
+            .iter()
+            .map(|(name, ty)| {
+                format!(
+                    "{}: {}",
+                    nim_ident(&name.to_mixed_case()),
+                    self.type_name(iface, ty)
+                )
+            })
+            .collect::<Vec<_>>();
+        uwrite!(
+            self.exports,
+            "{}*: proc ({})",
+            nim_ident(&func.name.to_mixed_case()),
+            params.join(", ")
+        );
+        if func.result != Type::Unit {
+            uwrite!(self.exports, ": {}", self.type_name(iface, &func.result));
+        }
+        self.exports.push_str(" {.nimcall.}\n");
+        self.exports.indent();
+        self.exports.doc_comment(&func.docs);
+        self.exports.dedent();
+
+        uwrite!(self.funcs, "proc wasmExport{}(", func.name.to_camel_case());
+        for (i, param) in sig.params.iter().enumerate() {
+            if i > 0 {
+                self.funcs.push_str(", ");
+            }
+            uwrite!(self.funcs, "arg{}: {}", i, wasm_type(*param));
+        }
+        self.funcs.push_str(")");
+        match sig.results.len() {
+            0 => {}
+            1 => uwrite!(self.funcs, ": {}", wasm_type(sig.results[0])),
+            _ => unimplemented!("multi-value return not supported"),
+        }
+        uwriteln!(
+            self.funcs,
+            " {{.exportc: \"__wasm_export_{}_{}\", cdecl,",
+            iface.name.to_snake_case(),
+            func.name.to_snake_case()
+        );
+        uwriteln!(
+            self.funcs,
+            "    codegenDecl: \"__attribute__((export_name(\\\"{}\\\"))) $# $#$#\".}} =",
+            func.name
+        );
+        self.funcs.indent();
+        let mut locals = FunctionBindgen::locals();
+        let params = (0..sig.params.len())
+            .map(|i| locals.tmp(&format!("arg{}", i)))
+            .collect::<Vec<_>>();
+        let mut f = FunctionBindgen::new(self, locals, params);
+        iface.call(
+            AbiVariant::GuestExport,
+            LiftLower::LiftArgsLowerResults,
+            func,
+            &mut f,
+        );
+        let body = f.finish();
+        self.funcs.push_str(&body);
+        self.funcs.dedent();
+        self.funcs.push_str("\n");
+    }
+
+    fn finish_one(&mut self, iface: &Interface, files: &mut Files) {
+        let mut src = NimSource::default();
+        uwriteln!(
+            src,
+            "# Generated by `wai-bindgen` from the `{}` interface.\n",
+            iface.name
+        );
+        src.push_str("import std/options, std/unicode\n");
+        src.push_str("import wai_abi\n\n");
+        src.push_str("export options, Rune, Unit, Expected, ok, err\n\n");
+
+        let types = mem::take(&mut self.types);
+        if !types.trim().is_empty() {
+            src.push_str("type\n");
+            src.indent();
+            src.push_str(&types);
+            src.dedent();
+            src.push_str("\n");
+        }
+
+        let exports = mem::take(&mut self.exports);
+        if !self.in_import {
+            src.push_str("type\n");
+            src.indent();
+            src.push_str("Exports* = object\n");
+            src.indent();
+            src.push_str("## The implementation of the exports of this interface.\n");
+            src.push_str(&exports);
+            src.dedent();
+            src.dedent();
+            src.push_str(
+                "
+                    var exportsImpl: Exports
+
+                    proc setExports*(impl: Exports) =
+                      ## Registers the implementation of the exports of this interface.
+                      exportsImpl = impl
+
+                ",
+            );
+        }
+
+        src.push_str(&mem::take(&mut self.funcs));
+        let src = format!("{}\n", src.trim_end());
+        files.push(
+            &format!("{}.nim", iface.name.to_snake_case()),
+            src.as_bytes(),
+        );
+    }
+
+    fn finish_all(&mut self, files: &mut Files) {
+        let src = self.print_intrinsics();
+        let src = format!("{}\n", src.trim());
+        files.push("wai_abi.nim", src.as_bytes());
+    }
+}
+
+struct FunctionBindgen<'a> {
+    gen: &'a mut Nim,
+    locals: Ns,
+    src: NimSource,
+    block_storage: Vec<NimSource>,
+    blocks: Vec<(String, Vec<String>)>,
+    params: Vec<String>,
+    payloads: Vec<String>,
+    // Whether memory allocated to lower the arguments of an import needs to
+    // be freed after the call.
+    needs_cleanup: bool,
+}
+
+impl<'a> FunctionBindgen<'a> {
+    /// The names of locals, reserving those the generated code relies on.
+    fn locals() -> Ns {
+        let mut locals = Ns::default();
+        for name in [
+            "result",
+            "cleanup",
+            "allocation",
+            "exportsImpl",
+            "len",
+            "some",
+            "none",
+            "load",
+            "store",
+            "retArea",
+            "abiAlloc",
+            "abiFree",
+            "lowerList",
+            "liftList",
+            "liftString",
+            "flagsToBits",
+            "flagsFromBits",
+        ] {
+            locals.insert(name).unwrap();
+        }
+        locals
+    }
+
+    fn new(gen: &'a mut Nim, locals: Ns, params: Vec<String>) -> FunctionBindgen<'a> {
+        FunctionBindgen {
+            gen,
+            locals,
+            src: NimSource::default(),
+            block_storage: Vec::new(),
+            blocks: Vec::new(),
+            params,
+            payloads: Vec::new(),
+            needs_cleanup: false,
+        }
+    }
+
+    fn finish(self) -> String {
+        let mut body = String::new();
+        if self.needs_cleanup {
+            body.push_str("var cleanup: seq[tuple[address, size, align: int32]]\n");
+        }
+        body.push_str(&self.src);
+        if body.trim().is_empty() {
+            body.push_str("discard\n");
+        }
+        body
+    }
+
+    fn load(&mut self, ty: &str, offset: i32, operands: &[String], results: &mut Vec<String>) {
+        let tmp = self.locals.tmp("load");
+        if ty == "int32" || ty == "int64" || ty == "float32" || ty == "float64" {
+            uwriteln!(
+                self.src,
+                "let {} = load({}, {}, {})",
+                tmp,
+                ty,
+                operands[0],
+                offset
+            );
+        } else {
+            uwriteln!(
+                self.src,
+                "let {} = int32(load({}, {}, {}))",
+                tmp,
+                ty,
+                operands[0],
+                offset
+            );
+        }
+        results.push(tmp);
+    }
+
+    fn store(&mut self, ty: &str, offset: i32, operands: &[String]) {
+        let value = match ty {
+            "uint8" => format!("uint8({} and 0xff)", operands[0]),
+            "uint16" => format!("uint16({} and 0xffff)", operands[0]),
+            _ => operands[0].clone(),
+        };
+        uwriteln!(
+            self.src,
+            "store({}, {}, {}, {})",
+            ty,
+            operands[1],
+            offset,
+            value
+        );
+    }
+
+    /// Records `address` to be freed after the call of an import unless it's
+    /// given away with `realloc`.
+    fn cleanup(&mut self, address: &str, size: String, align: usize, realloc: Option<&str>) {
+        if realloc.is_none() {
+            self.needs_cleanup = true;
+            uwriteln!(
+                self.src,
+                "cleanup.add(({}, {}, {}'i32))",
+                address,
+                size,
+                align
+            );
+        }
+    }
+
+    /// Prints the body of a branch, assigning its results to `results`.
+    fn branch(&mut self, block: &str, block_results: &[String], results: &[String]) {
+        self.src.indent();
+        self.src.push_str(block);
+        for (result, value) in results.iter().zip(block_results) {
+            uwriteln!(self.src, "{} = {}", result, value);
+        }
+        if block.trim().is_empty() && results.is_empty() {
+            self.src.push_str("discard\n");
+        }
+        self.src.dedent();
+    }
+
+    fn declare_results(&mut self, prefix: &str, types: &[WasmType]) -> Vec<String> {
+        types
+            .iter()
+            .map(|ty| {
+                let result = self.locals.tmp(prefix);
+                uwriteln!(self.src, "var {}: {}", result, wasm_type(*ty));
+                result
+            })
+            .collect()
+    }
+
+    /// Lowers `op` with a `case` on its `kind`, given the name of each case
+    /// and its payload field, if any.
+    fn lower_cases(
+        &mut self,
+        op: &str,
+        kind: &str,
+        cases: &[(String, Option<String>)],
+        result_types: &[WasmType],
+    ) -> Vec<String> {
+        let blocks = self
+            .blocks
+            .drain(self.blocks.len() - cases.len()..)
+            .collect::<Vec<_>>();
+        let payloads = self
+            .payloads
+            .drain(self.payloads.len() - cases.len()..)
+            .collect::<Vec<_>>();
+        let results = self.declare_results("variant", result_types);
+        uwriteln!(self.src, "case {}.kind", op);
+        for ((case, field), ((block, block_results), payload)) in
+            cases.iter().zip(blocks.into_iter().zip(payloads))
+        {
+            uwriteln!(self.src, "of {}.{}:", kind, case);
+            if let Some(field) = field {
+                self.src.indent();
+                uwriteln!(self.src, "let {} = {}.{}", payload, op, field);
+                self.src.dedent();
+            }
+            self.branch(&block, &block_results, &results);
+        }
+        results
+    }
+
+    /// Lifts the discriminant `op` into a value of `ty`, built by `ctor`
+    /// from the index of each case and its payload.
+    fn lift_cases(
+        &mut self,
+        op: &str,
+        ty: &str,
+        cases: usize,
+        ctor: impl Fn(usize, &str) -> String,
+    ) -> String {
+        let blocks = self
+            .blocks
+            .drain(self.blocks.len() - cases..)
+            .collect::<Vec<_>>();
+        let result = self.locals.tmp("variant");
+        if let [(block, block_results)] = &blocks[..] {
+            self.src.push_str(block);
+            uwriteln!(self.src, "let {} = {}", result, ctor(0, &block_results[0]));
+            return result;
+        }
+        uwriteln!(self.src, "var {}: {}", result, ty);
+        uwriteln!(self.src, "case {}", op);
+        for (i, (block, block_results)) in blocks.into_iter().enumerate() {
+            // The host is trusted to pass a valid discriminant, so the last
+            // case takes all others.
+            if i == cases - 1 {
+                self.src.push_str("else:\n");
+            } else {
+                uwriteln!(self.src, "of {}:", i);
+            }
+            let value = ctor(i, &block_results[0]);
+            self.branch(&block, &[value], std::slice::from_ref(&result));
+        }
+        result
+    }
+
+    /// The enum of the kinds of the object variant `name`, and the names
+    /// of its cases along with their payload field.
+    fn variant_cases<'b>(
+        name: &str,
+        cases: impl Iterator<Item = (String, &'b Type)>,
+    ) -> (String, Vec<(String, Option<String>)>) {
+        let kind = format!("{}Kind", nim_type_ident(name));
+        let cases = cases
+            .map(|(case, ty)| {
+                let field = if *ty == Type::Unit {
+                    None
+                } else {
+                    Some(case_field(&case))
+                };
+                (nim_ident(&case.to_mixed_case()), field)
+            })
+            .collect();
+        (kind, cases)
+    }
+}
+
+impl Bindgen for FunctionBindgen<'_> {
+    type Operand = String;
+
+    fn sizes(&self) -> &SizeAlign {
+        &self.gen.sizes
+    }
+
+    fn push_block(&mut self) {
+        let prev = mem::take(&mut self.src);
+        self.block_storage.push(prev);
+    }
+
+    fn finish_block(&mut self, operands: &mut Vec<String>) {
+        let to_restore = self.block_storage.pop().unwrap();
+        let src = mem::replace(&mut self.src, to_restore);
+        self.blocks.push((src.into(), mem::take(operands)));
+    }
+
+    fn return_pointer(&mut self, _iface: &Interface, size: usize, align: usize) -> String {
+        self.gen.return_pointer_area_size = self.gen.return_pointer_area_size.max(size);
+        self.gen.return_pointer_area_align = self.gen.return_pointer_area_align.max(align);
+        let address = self.locals.tmp("address");
+        uwriteln!(self.src, "let {} = retArea()", address);
+        address
+    }
+
+    fn is_list_canonical(&self, _iface: &Interface, ty: &Type) -> bool {
+        // Sequences of numbers and runes have the layout of the canonical ABI.
+        matches!(
+            ty,
+            Type::U8
+                | Type::U16
+                | Type::U32
+                | Type::U64
+                | Type::S8
+                | Type::S16
+                | Type::S32
+                | Type::S64
+                | Type::Float32
+                | Type::Float64
+                | Type::Char
+        )
+    }
+
+    fn emit(
+        &mut self,
+        iface: &Interface,
+        inst: &Instruction<'_>,
+        operands: &mut Vec<String>,
+        results: &mut Vec<String>,
+    ) {
+        match inst {
+            Instruction::GetArg { nth } => results.push(self.params[*nth].clone()),
+            Instruction::I32Const { val } => results.push(format!("{}'i32", val)),
+            Instruction::ConstZero { tys } => {
+                for ty in tys.iter() {
+                    match ty {
+                        WasmType::I32 => results.push("0'i32".to_string()),
+                        WasmType::I64 => results.push("0'i64".to_string()),
+                        WasmType::F32 => results.push("0'f32".to_string()),
+                        WasmType::F64 => results.push("0'f64".to_string()),
+                    }
+                }
+            }
+
+            Instruction::I32FromU8
+            | Instruction::I32FromS8
+            | Instruction::I32FromU16
+            | Instruction::I32FromS16 => results.push(format!("int32({})", operands[0])),
+            Instruction::I32FromU32 => results.push(format!("cast[int32]({})", operands[0])),
+            Instruction::I64FromU64 => results.push(format!("cast[int64]({})", operands[0])),
+            Instruction::I32FromS32
+            | Instruction::I64FromS64
+            | Instruction::S32FromI32
+            | Instruction::S64FromI64
+            | Instruction::Float32FromF32
+            | Instruction::Float64FromF64
+            | Instruction::F32FromFloat32
+            | Instruction::F64FromFloat64 => results.push(operands.pop().unwrap()),
+
+            // Only the low bits of integers coming from wasm are meaningful.
+            Instruction::U8FromI32 => results.push(format!("uint8({} and 0xff)", operands[0])),
+            Instruction::S8FromI32 => {
+                results.push(format!("cast[int8](uint8({} and 0xff))", operands[0]))
+            }
+            Instruction::U16FromI32 => results.push(format!("uint16({} and 0xffff)", operands[0])),
+            Instruction::S16FromI32 => {
+                results.push(format!("cast[int16](uint16({} and 0xffff))", operands[0]))
+            }
+            Instruction::U32FromI32 => results.push(format!("cast[uint32]({})", operands[0])),
+            Instruction::U64FromI64 => results.push(format!("cast[uint64]({})", operands[0])),
+
+            Instruction::CharFromI32 => results.push(format!("Rune({})", operands[0])),
+            Instruction::I32FromChar => results.push(format!("int32({})", operands[0])),
+
+            Instruction::Bitcasts { casts } => {
+                for (cast, op) in casts.iter().zip(operands) {
+                    let op = match cast {
+                        Bitcast::I32ToF32 => format!("cast[float32]({})", op),
+                        Bitcast::F32ToI32 => format!("cast[int32]({})", op),
+                        Bitcast::I64ToF64 => format!("cast[float64]({})", op),
+                        Bitcast::F64ToI64 => format!("cast[int64]({})", op),
+                        Bitcast::I32ToI64 => format!("int64({})", op),
+                        Bitcast::I64ToI32 => {
+                            format!("cast[int32](uint32({} and 0xffffffff'i64))", op)
+                        }
+                        Bitcast::F32ToI64 => format!("int64(cast[int32]({}))", op),
+                        Bitcast::I64ToF32 => {
+                            format!("cast[float32](uint32({} and 0xffffffff'i64))", op)
+                        }
+                        Bitcast::None => op.clone(),
+                    };
+                    results.push(op);
+                }
+            }
+
+            Instruction::UnitLower => {}
+            Instruction::UnitLift => results.push("Unit()".to_string()),
+            Instruction::BoolFromI32 => results.push(format!("({} != 0)", operands[0])),
+            Instruction::I32FromBool => results.push(format!("int32(ord({}))", operands[0])),
+
+            Instruction::I32FromBorrowedHandle { .. }
+            | Instruction::I32FromOwnedHandle { .. }
+            | Instruction::HandleOwnedFromI32 { .. }
+            | Instruction::HandleBorrowedFromI32 { .. } => {
+                unimplemented!("resources not supported yet by Nim guests")
+            }
+
+            Instruction::RecordLower { record, .. } => {
+                let op = self.locals.tmp("record");
+                uwriteln!(self.src, "let {} = {}", op, operands[0]);
+                for field in record.fields.iter() {
+                    results.push(format!("{}.{}", op, nim_ident(&field.name.to_mixed_case())));
+                }
+            }
+            Instruction::RecordLift { record, name, .. } => {
+                let fields = record
+                    .fields
+                    .iter()
+                    .zip(operands.iter())
+                    .map(|(field, op)| {
+                        format!("{}: {}", nim_ident(&field.name.to_mixed_case()), op)
+                    })
+                    .collect::<Vec<_>>();
+                results.push(format!("{}({})", nim_type_ident(name), fields.join(", ")));
+            }
+
+            Instruction::TupleLower { tuple, .. } => {
+                if !tuple.types.is_empty() {
+                    let op = self.locals.tmp("tuple");
+                    uwriteln!(self.src, "let {} = {}", op, operands[0]);
+                    for i in 0..tuple.types.len() {
+                        results.push(format!("{}[{}]", op, i));
+                    }
+                }
+            }
+            Instruction::TupleLift { .. } => match operands.len() {
+                0 => results.push("Unit()".to_string()),
+                1 => results.push(format!("({},)", operands[0])),
+                _ => results.push(format!("({})", operands.join(", "))),
+            },
+
+            Instruction::FlagsLower { flags, .. } => {
+                let count = flags.repr().count();
+                if flags.flags.is_empty() {
+                    results.extend((0..count).map(|_| "0'i32".to_string()));
+                } else {
+                    let op = self.locals.tmp("flags");
+                    uwriteln!(self.src, "let {} = {}", op, operands[0]);
+                    for i in 0..count {
+                        results.push(format!("flagsToBits({}, {})", op, i));
+                    }
+                }
+            }
+            Instruction::FlagsLift { flags, name, .. } => {
+                let name = nim_type_ident(name);
+                if flags.flags.is_empty() {
+                    results.push(format!("{}()", name));
+                } else {
+                    results.push(format!(
+                        "flagsFromBits[{}Flag]([{}])",
+                        name,
+                        operands.join(", ")
+                    ));
+                }
+            }
+
+            Instruction::VariantPayloadName => {
+                let name = self.locals.tmp("payload");
+                results.push(name.clone());
+                self.payloads.push(name);
+            }
+
+            Instruction::VariantLower {
+                variant,
+                name,
+                results: result_types,
+                ..
+            } => {
+                let (kind, cases) = Self::variant_cases(
+                    name,
+                    variant.cases.iter().map(|c| (c.name.clone(), &c.ty)),
+                );
+                let op = self.locals.tmp("variant");
+                uwriteln!(self.src, "let {} = {}", op, operands[0]);
+                results.extend(self.lower_cases(&op, &kind, &cases, result_types));
+            }
+            Instruction::VariantLift { variant, name, .. } => {
+                let (kind, cases) = Self::variant_cases(
+                    name,
+                    variant.cases.iter().map(|c| (c.name.clone(), &c.ty)),
+                );
+                let ty = nim_type_ident(name);
+                let result = self.lift_cases(&operands[0], &ty, cases.len(), |i, payload| {
+                    let (case, field) = &cases[i];
+                    match field {
+                        Some(field) => {
+                            format!("{}(kind: {}.{}, {}: {})", ty, kind, case, field, payload)
+                        }
+                        None => format!("{}(kind: {}.{})", ty, kind, case),
+                    }
+                });
+                results.push(result);
+            }
+
+            Instruction::UnionLower {
+                union,
+                name,
+                results: result_types,
+                ..
+            } => {
+                let (kind, cases) = Self::variant_cases(
+                    name,
+                    union_case_names(iface, union)
+                        .into_iter()
+                        .zip(union.cases.iter().map(|c| &c.ty)),
+                );
+                let op = self.locals.tmp("union");
+                uwriteln!(self.src, "let {} = {}", op, operands[0]);
+                results.extend(self.lower_cases(&op, &kind, &cases, result_types));
+            }
+            Instruction::UnionLift { union, name, .. } => {
+                let (kind, cases) = Self::variant_cases(
+                    name,
+                    union_case_names(iface, union)
+                        .into_iter()
+                        .zip(union.cases.iter().map(|c| &c.ty)),
+                );
+                let ty = nim_type_ident(name);
+                let result = self.lift_cases(&operands[0], &ty, cases.len(), |i, payload| {
+                    let (case, field) = &cases[i];
+                    match field {
+                        Some(field) => {
+                            format!("{}(kind: {}.{}, {}: {})", ty, kind, case, field, payload)
+                        }
+                        None => format!("{}(kind: {}.{})", ty, kind, case),
+                    }
+                });
+                results.push(result);
+            }
+
+            Instruction::EnumLower { .. } => results.push(format!("int32(ord({}))", operands[0])),
+            Instruction::EnumLift { name, .. } => {
+                results.push(format!("{}({})", nim_type_ident(name), operands[0]))
+            }
+
+            Instruction::OptionLower {
+                results: result_types,
+                ..
+            } => {
+                let (some, some_results) = self.blocks.pop().unwrap();
+                let (none, none_results) = self.blocks.pop().unwrap();
+                let some_payload = self.payloads.pop().unwrap();
+                let _none_payload = self.payloads.pop().unwrap();
+                let op = self.locals.tmp("option");
+                uwriteln!(self.src, "let {} = {}", op, operands[0]);
+                let option_results = self.declare_results("option", result_types);
+                uwriteln!(self.src, "if {}.isNone:", op);
+                self.branch(&none, &none_results, &option_results);
+                self.src.push_str("else:\n");
+                self.src.indent();
+                uwriteln!(self.src, "let {} = {}.get", some_payload, op);
+                self.src.dedent();
+                self.branch(&some, &some_results, &option_results);
+                results.extend(option_results);
+            }
+            Instruction::OptionLift { payload, .. } => {
+                let (some, some_results) = self.blocks.pop().unwrap();
+                let (none, none_results) = self.blocks.pop().unwrap();
+                assert!(none_results.len() == 1 && some_results.len() == 1);
+                let ty = self.gen.type_name(iface, payload);
+                let result = self.locals.tmp("option");
+                uwriteln!(self.src, "var {}: Option[{}]", result, ty);
+                uwriteln!(self.src, "if {} == 0:", operands[0]);
+                self.branch(
+                    &none,
+                    &[format!("none({})", ty)],
+                    std::slice::from_ref(&result),
+                );
+                self.src.push_str("else:\n");
+                self.branch(
+                    &some,
+                    &[format!("some({})", some_results[0])],
+                    std::slice::from_ref(&result),
+                );
+                results.push(result);
+            }
+
+            Instruction::ExpectedLower {
+                results: result_types,
+                ..
+            } => {
+                let (err, err_results) = self.blocks.pop().unwrap();
+                let (ok, ok_results) = self.blocks.pop().unwrap();
+                let err_payload = self.payloads.pop().unwrap();
+                let ok_payload = self.payloads.pop().unwrap();
+                let op = self.locals.tmp("expected");
+                uwriteln!(self.src, "let {} = {}", op, operands[0]);
+                let expected_results = self.declare_results("expected", result_types);
+                uwriteln!(self.src, "if {}.isOk:", op);
+                self.src.indent();
+                uwriteln!(self.src, "let {} = {}.value", ok_payload, op);
+                self.src.dedent();
+                self.branch(&ok, &ok_results, &expected_results);
+                self.src.push_str("else:\n");
+                self.src.indent();
+                uwriteln!(self.src, "let {} = {}.error", err_payload, op);
+                self.src.dedent();
+                self.branch(&err, &err_results, &expected_results);
+                results.extend(expected_results);
+            }
+            Instruction::ExpectedLift { expected, .. } => {
+                let ty = format!(
+                    "Expected[{}, {}]",
+                    self.gen.type_name(iface, &expected.ok),
+                    self.gen.type_name(iface, &expected.err)
+                );
+                let result = self.lift_cases(&operands[0], &ty, 2, |i, payload| match i {
+                    0 => format!("{}(isOk: true, value: {})", ty, payload),
+                    _ => format!("{}(isOk: false, error: {})", ty, payload),
+                });
+                results.push(result);
+            }
+
+            Instruction::ListCanonLower { element, realloc } => {
+                let val = self.locals.tmp("vec");
+                let address = self.locals.tmp("address");
+                let size = self.gen.sizes.size(element);
+                let align = self.gen.sizes.align(element);
+                uwriteln!(self.src, "let {} = {}", val, operands[0]);
+                uwriteln!(self.src, "let {} = lowerList({})", address, val);
+                self.cleanup(
+                    &address,
+                    format!("int32({}.len * {})", val, size),
+                    align,
+                    *realloc,
+                );
+                results.push(address);
+                results.push(format!("int32({}.len)", val));
+            }
+            Instruction::StringLower { realloc } => {
+                let val = self.locals.tmp("str");
+                let address = self.locals.tmp("address");
+                uwriteln!(self.src, "let {} = {}", val, operands[0]);
+                uwriteln!(self.src, "let {} = lowerList({})", address, val);
+                self.cleanup(&address, format!("int32({}.len)", val), 1, *realloc);
+                results.push(address);
+                results.push(format!("int32({}.len)", val));
+            }
+            Instruction::ListLower { element, realloc } => {
+                let (body, body_results) = self.blocks.pop().unwrap();
+                assert!(body_results.is_empty());
+                let depth = self.block_storage.len() + 1;
+                let vec = self.locals.tmp("vec");
+                let address = self.locals.tmp("address");
+                let size = self.gen.sizes.size(element);
+                let align = self.gen.sizes.align(element);
+                uwriteln!(self.src, "let {} = {}", vec, operands[0]);
+                uwriteln!(
+                    self.src,
+                    "let {} = abiAlloc(int32({}.len * {}), {})",
+                    address,
+                    vec,
+                    size,
+                    align
+                );
+                self.cleanup(
+                    &address,
+                    format!("int32({}.len * {})", vec, size),
+                    align,
+                    *realloc,
+                );
+                uwriteln!(self.src, "for i{}, e{} in {}:", depth, depth, vec);
+                self.src.indent();
+                uwriteln!(
+                    self.src,
+                    "let base{} = {} + int32(i{}) * {}",
+                    depth,
+                    address,
+                    depth,
+                    size
+                );
+                self.src.push_str(&body);
+                if body.trim().is_empty() {
+                    self.src.push_str("discard\n");
+                }
+                self.src.dedent();
+                results.push(address);
+                results.push(format!("int32({}.len)", vec));
+            }
+
+            Instruction::ListCanonLift { element, free, .. } => {
+                let address = self.locals.tmp("address");
+                let count = self.locals.tmp("count");
+                let result = self.locals.tmp("items");
+                uwriteln!(self.src, "let {} = {}", address, operands[0]);
+                uwriteln!(self.src, "let {} = {}", count, operands[1]);
+                uwriteln!(
+                    self.src,
+                    "let {} = liftList[{}]({}, {})",
+                    result,
+                    self.gen.type_name(iface, element),
+                    address,
+                    count
+                );
+                if free.is_some() {
+                    uwriteln!(
+                        self.src,
+                        "abiFree({}, {} * {}, {})",
+                        address,
+                        count,
+                        self.gen.sizes.size(element),
+                        self.gen.sizes.align(element)
+                    );
+                }
+                results.push(result);
+            }
+            Instruction::StringLift { free } => {
+                let address = self.locals.tmp("address");
+                let count = self.locals.tmp("count");
+                let result = self.locals.tmp("str");
+                uwriteln!(self.src, "let {} = {}", address, operands[0]);
+                uwriteln!(self.src, "let {} = {}", count, operands[1]);
+                uwriteln!(
+                    self.src,
+                    "let {} = liftString({}, {})",
+                    result,
+                    address,
+                    count
+                );
+                if free.is_some() {
+                    uwriteln!(self.src, "abiFree({}, {}, 1)", address, count);
+                }
+                results.push(result);
+            }
+            Instruction::ListLift { element, free, .. } => {
+                let (body, body_results) = self.blocks.pop().unwrap();
+                let depth = self.block_storage.len() + 1;
+                let address = self.locals.tmp("address");
+                let count = self.locals.tmp("count");
+                let result = self.locals.tmp("items");
+                let size = self.gen.sizes.size(element);
+                let align = self.gen.sizes.align(element);
+                uwriteln!(self.src, "let {} = {}", address, operands[0]);
+                uwriteln!(self.src, "let {} = {}", count, operands[1]);
+                uwriteln!(
+                    self.src,
+                    "var {} = newSeqOfCap[{}]({})",
+                    result,
+                    self.gen.type_name(iface, element),
+                    count
+                );
+                uwriteln!(self.src, "for i{} in 0'i32 ..< {}:", depth, count);
+                self.src.indent();
+                uwriteln!(
+                    self.src,
+                    "let base{} = {} + i{} * {}",
+                    depth,
+                    address,
+                    depth,
+                    size
+                );
+                self.src.push_str(&body);
+                uwriteln!(self.src, "{}.add({})", result, body_results[0]);
+                self.src.dedent();
+                if free.is_some() {
+                    uwriteln!(
+                        self.src,
+                        "abiFree({}, {} * {}, {})",
+                        address,
+                        count,
+                        size,
+                        align
+                    );
+                }
+                results.push(result);
+            }
+
+            Instruction::IterElem { .. } => {
+                results.push(format!("e{}", self.block_storage.len()));
+            }
+            Instruction::IterBasePointer => {
+                results.push(format!("base{}", self.block_storage.len()));
+            }
+
+            Instruction::CallWasm { name, sig, .. } => {
+                let call = format!(
+                    "wasmImport{}({})",
+                    name.to_camel_case(),
+                    operands.join(", ")
+                );
+                match sig.results.len() {
+                    0 => uwriteln!(self.src, "{}", call),
+                    1 => {
+                        let ret = self.locals.tmp("ret");
+                        uwriteln!(self.src, "let {} = {}", ret, call);
+                        results.push(ret);
+                    }
+                    _ => unimplemented!("multi-value return not supported"),
+                }
+                if self.needs_cleanup {
+                    self.src.push_str("for allocation in cleanup:\n");
+                    self.src.indent();
+                    self.src.push_str(
+                        "abiFree(allocation.address, allocation.size, allocation.align)\n",
+                    );
+                    self.src.dedent();
+                }
+            }
+
+            Instruction::CallInterface { func, .. } => {
+                let call = format!(
+                    "exportsImpl.{}({})",
+                    nim_ident(&func.name.to_mixed_case()),
+                    operands.join(", ")
+                );
+                if func.result == Type::Unit {
+                    uwriteln!(self.src, "{}", call);
+                    results.push("Unit()".to_string());
+                } else {
+                    let ret = self.locals.tmp("ret");
+                    uwriteln!(self.src, "let {} = {}", ret, call);
+                    results.push(ret);
+                }
+            }
+
+            Instruction::Return { amt, func } => match amt {
+                0 => {}
+                // Procs don't return `unit`.
+                1 if func.result == Type::Unit => {}
+                1 => uwriteln!(self.src, "return {}", operands[0]),
+                _ => unimplemented!("multi-value return not supported"),
+            },
+
+            Instruction::I32Load { offset } => self.load("int32", *offset, operands, results),
+            Instruction::I32Load8U { offset } => self.load("uint8", *offset, operands, results),
+            Instruction::I32Load8S { offset } => self.load("int8", *offset, operands, results),
+            Instruction::I32Load16U { offset } => self.load("uint16", *offset, operands, results),
+            Instruction::I32Load16S { offset } => self.load("int16", *offset, operands, results),
+            Instruction::I64Load { offset } => self.load("int64", *offset, operands, results),
+            Instruction::F32Load { offset } => self.load("float32", *offset, operands, results),
+            Instruction::F64Load { offset } => self.load("float64", *offset, operands, results),
+            Instruction::I32Store { offset } => self.store("int32", *offset, operands),
+            Instruction::I32Store8 { offset } => self.store("uint8", *offset, operands),
+            Instruction::I32Store16 { offset } => self.store("uint16", *offset, operands),
+            Instruction::I64Store { offset } => self.store("int64", *offset, operands),
+            Instruction::F32Store { offset } => self.store("float32", *offset, operands),
+            Instruction::F64Store { offset } => self.store("float64", *offset, operands),
+
+            Instruction::Malloc { size, align, .. } => {
+                let address = self.locals.tmp("address");
+                uwriteln!(self.src, "let {} = abiAlloc({}, {})", address, size, align);
+                results.push(address);
+            }
+            Instruction::Free { size, align, .. } => {
+                uwriteln!(self.src, "abiFree({}, {}, {})", operands[0], size, align);
+            }
+
+            Instruction::CallWasmAsyncImport { .. }
+            | Instruction::CallWasmAsyncExport { .. }
+            | Instruction::ReturnAsyncExport { .. }
+            | Instruction::ReturnAsyncImport { .. } => {
+                unimplemented!("async not supported yet")
+            }
+        }
+    }
+}
+
+/// Nim source, indented by blocks of two spaces.
+#[derive(Default)]
+struct NimSource {
+    s: String,
+    indent: usize,
+}
+
+impl NimSource {
+    /// Appends `src`, indenting its lines at the current level after
+    /// removing the indentation of its first line from all of them.
+    fn push_str(&mut self, src: &str) {
+        let lines = src.lines().collect::<Vec<_>>();
+        let mut trim = None;
+        for (i, line) in lines.iter().enumerate() {
+            if lines.len() == 1 {
+                self.s.push_str(line);
+            } else if !line.trim().is_empty() {
+                let trim = *trim.get_or_insert_with(|| line.len() - line.trim_start().len());
+                self.s
+                    .push_str(line.get(trim..).unwrap_or(line.trim_start()));
+            }
+            if i != lines.len() - 1 || src.ends_with('\n') {
+                self.newline();
+            }
+        }
+    }
+
+    fn indent(&mut self) {
+        self.indent += 2;
+        self.s.push_str("  ");
+    }
+
+    fn dedent(&mut self) {
+        self.indent -= 2;
+        assert!(self.s.ends_with("  "));
+        self.s.truncate(self.s.len() - 2);
+    }
+
+    fn newline(&mut self) {
+        // Blank lines don't keep the indentation.
+        while self.s.ends_with(' ') {
+            self.s.pop();
+        }
+        self.s.push('\n');
+        for _ in 0..self.indent {
+            self.s.push(' ');
+        }
+    }
+
+    fn doc_comment(&mut self, docs: &Docs) {
+        if let Some(docs) = &docs.contents {
+            for line in docs.trim().lines() {
+                self.push_str(&format!("## {}\n", line.trim()));
+            }
+        }
+    }
+}
+
+impl std::fmt::Write for NimSource {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl std::ops::Deref for NimSource {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.s
+    }
+}
+
+impl From<NimSource> for String {
+    fn from(s: NimSource) -> String {
+        s.s
+    }
+}
+
+/// Quotes names which are keywords in Nim.
+fn nim_ident(name: &str) -> String {
+    match name {
+        "addr" | "and" | "as" | "asm" | "bind" | "block" | "break" | "case" | "cast"
+        | "concept" | "const" | "continue" | "converter" | "defer" | "discard" | "distinct"
+        | "div" | "do" | "elif" | "else" | "end" | "enum" | "except" | "export" | "finally"
+        | "for" | "from" | "func" | "if" | "import" | "in" | "include" | "interface" | "is"
+        | "isnot" | "iterator" | "let" | "macro" | "method" | "mixin" | "mod" | "nil" | "not"
+        | "notin" | "object" | "of" | "or" | "out" | "proc" | "ptr" | "raise" | "ref"
+        | "return" | "shl" | "shr" | "static" | "template" | "try" | "tuple" | "type" | "using"
+        | "var" | "when" | "while" | "xor" | "yield" => format!("`{}`", name),
+        _ => name.to_string(),
+    }
+}
+
+/// The name of the type `name`, renamed if it would shadow one of the types
+/// the bindings rely on.
+fn nim_type_ident(name: &str) -> String {
+    let name = name.to_camel_case();
+    match name.as_str() {
+        "Unit" | "Expected" | "Option" | "Rune" | "Exports" => format!("{}Type", name),
+        _ => name,
+    }
+}
+
+/// The field of the payload of the case `case` of an object variant, which
+/// can't be named after its discriminator.
+fn case_field(case: &str) -> String {
+    match case.to_mixed_case().as_str() {
+        "kind" => "kindValue".to_string(),
+        name => nim_ident(name),
+    }
+}
+
+fn wasm_type(ty: WasmType) -> &'static str {
+    match ty {
+        WasmType::I32 => "int32",
+        WasmType::I64 => "int64",
+        WasmType::F32 => "float32",
+        WasmType::F64 => "float64",
+    }
+}
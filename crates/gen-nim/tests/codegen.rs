@@ -0,0 +1,131 @@
+use std::path::Path;
+use wai_bindgen_gen_core::{wai_parser::Interface, Files, Generator};
+
+fn generate(imports: &[Interface], exports: &[Interface]) -> Files {
+    let mut files = Files::default();
+    wai_bindgen_gen_nim::Opts::default()
+        .build()
+        .generate_all(imports, exports, &mut files);
+    files
+}
+
+fn file<'a>(files: &'a Files, name: &str) -> &'a str {
+    let (_, contents) = files.iter().find(|(n, _)| *n == name).unwrap();
+    std::str::from_utf8(contents).unwrap()
+}
+
+/// The interface the tests below generate bindings for.
+fn greeter() -> Interface {
+    Interface::parse(
+        "greeter",
+        "
+            record point { x: u32, y: u32 }
+            variant shape { dot(point), empty }
+            greet: func(name: string, at: shape) -> list<u8>
+        ",
+    )
+    .unwrap()
+}
+
+#[test]
+fn codegen_interfaces() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/codegen");
+    for entry in std::fs::read_dir(&root).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|s| s.to_str()) != Some("wai") {
+            continue;
+        }
+        let iface = Interface::parse_file(&path).unwrap();
+        if iface.resources.len() > 0 || iface.functions.iter().any(|f| f.is_async) {
+            continue;
+        }
+        for (imports, exports) in [(vec![iface.clone()], vec![]), (vec![], vec![iface])] {
+            let files = generate(&imports, &exports);
+            let names = files.iter().map(|(n, _)| n).collect::<Vec<_>>();
+            assert_eq!(names.len(), 2, "{}: {:?}", path.display(), names);
+            assert!(file(&files, "wai_abi.nim").contains("proc abiAlloc*"));
+        }
+    }
+}
+
+#[test]
+fn imports_and_exports() {
+    let iface = greeter();
+
+    let files = generate(std::slice::from_ref(&iface), &[]);
+    let nim = file(&files, "greeter.nim");
+    assert!(nim.contains("Point* = object"), "{nim}");
+    assert!(nim.contains("case kind*: ShapeKind"), "{nim}");
+    assert!(nim.contains("dot*: Point"), "{nim}");
+    assert!(
+        nim.contains("proc greet*(name: string, at: Shape): seq[uint8] ="),
+        "{nim}"
+    );
+    assert!(
+        nim.contains("codegenDecl: \"__attribute__((import_module(\\\"greeter\\\"), import_name(\\\"greet\\\"))) $# $#$#\""),
+        "{nim}"
+    );
+    assert!(nim.contains("abiFree(allocation.address"), "{nim}");
+    assert!(!nim.contains("setExports"), "{nim}");
+
+    let files = generate(&[], &[iface]);
+    let nim = file(&files, "greeter.nim");
+    assert!(
+        nim.contains("greet*: proc (name: string, at: Shape): seq[uint8] {.nimcall.}"),
+        "{nim}"
+    );
+    assert!(nim.contains("proc setExports*(impl: Exports) ="), "{nim}");
+    assert!(
+        nim.contains("{.exportc: \"__wasm_export_greeter_greet\", cdecl,"),
+        "{nim}"
+    );
+    assert!(nim.contains("exportsImpl.greet("), "{nim}");
+    assert!(!nim.contains("cleanup"), "{nim}");
+}
+
+/// Checks the bindings generated for `greeter` against the files in
+/// `tests/golden`. Run the test with the environment variable `BLESS` set to
+/// update them.
+#[test]
+fn golden() {
+    let iface = greeter();
+    check_golden("import", &generate(std::slice::from_ref(&iface), &[]));
+    check_golden("export", &generate(&[], &[iface]));
+}
+
+fn check_golden(dir: &str, files: &Files) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(dir);
+    let bless = std::env::var_os("BLESS").is_some();
+    if bless {
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+    }
+    for (name, contents) in files.iter() {
+        let path = dir.join(name);
+        if bless {
+            std::fs::write(&path, contents).unwrap();
+            continue;
+        }
+        let expected = std::fs::read(&path).unwrap_or_else(|e| {
+            panic!(
+                "failed to read {}: {}\nthis can be fixed with BLESS=1",
+                path.display(),
+                e
+            )
+        });
+        assert_eq!(
+            String::from_utf8_lossy(&expected),
+            String::from_utf8_lossy(contents),
+            "{} doesn't match the generated file\nthis can be fixed with BLESS=1",
+            path.display()
+        );
+    }
+    assert_eq!(
+        std::fs::read_dir(&dir).unwrap().count(),
+        files.iter().count(),
+        "{} has files which aren't generated anymore\nthis can be fixed with BLESS=1",
+        dir.display()
+    );
+}
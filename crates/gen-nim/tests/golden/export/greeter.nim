@@ -0,0 +1,51 @@
+# Generated by `wai-bindgen` from the `greeter` interface.
+
+import std/options, std/unicode
+import wai_abi
+
+export options, Rune, Unit, Expected, ok, err
+
+type
+  Point* = object
+    x*: uint32
+    y*: uint32
+  ShapeKind* {.pure.} = enum
+    dot
+    empty
+  Shape* = object
+    case kind*: ShapeKind
+    of ShapeKind.dot:
+      dot*: Point
+    of ShapeKind.empty:
+      discard
+
+type
+  Exports* = object
+    ## The implementation of the exports of this interface.
+    greet*: proc (name: string, at: Shape): seq[uint8] {.nimcall.}
+
+var exportsImpl: Exports
+
+proc setExports*(impl: Exports) =
+  ## Registers the implementation of the exports of this interface.
+  exportsImpl = impl
+
+proc wasmExportGreet(arg0: int32, arg1: int32, arg2: int32, arg3: int32, arg4: int32): int32 {.exportc: "__wasm_export_greeter_greet", cdecl,
+    codegenDecl: "__attribute__((export_name(\"greet\"))) $# $#$#".} =
+  let address = arg0
+  let count = arg1
+  let str = liftString(address, count)
+  abiFree(address, count, 1)
+  var variant: Shape
+  case arg2
+  of 0:
+    variant = Shape(kind: ShapeKind.dot, dot: Point(x: cast[uint32](arg3), y: cast[uint32](arg4)))
+  else:
+    variant = Shape(kind: ShapeKind.empty)
+  let ret = exportsImpl.greet(str, variant)
+  let address0 = retArea()
+  let vec = ret
+  let address1 = lowerList(vec)
+  store(int32, address0, 4, int32(vec.len))
+  store(int32, address0, 0, address1)
+  return address0
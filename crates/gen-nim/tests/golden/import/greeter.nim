@@ -0,0 +1,55 @@
+# Generated by `wai-bindgen` from the `greeter` interface.
+
+import std/options, std/unicode
+import wai_abi
+
+export options, Rune, Unit, Expected, ok, err
+
+type
+  Point* = object
+    x*: uint32
+    y*: uint32
+  ShapeKind* {.pure.} = enum
+    dot
+    empty
+  Shape* = object
+    case kind*: ShapeKind
+    of ShapeKind.dot:
+      dot*: Point
+    of ShapeKind.empty:
+      discard
+
+proc wasmImportGreet(arg0: int32, arg1: int32, arg2: int32, arg3: int32, arg4: int32, arg5: int32) {.importc: "__wasm_import_greeter_greet", cdecl,
+    codegenDecl: "__attribute__((import_module(\"greeter\"), import_name(\"greet\"))) $# $#$#".}
+
+proc greet*(name: string, at: Shape): seq[uint8] =
+  var cleanup: seq[tuple[address, size, align: int32]]
+  let str = name
+  let address = lowerList(str)
+  cleanup.add((address, int32(str.len), 1'i32))
+  let variant = at
+  var variant1: int32
+  var variant2: int32
+  var variant3: int32
+  case variant.kind
+  of ShapeKind.dot:
+    let payload = variant.dot
+    let record = payload
+    variant1 = 0'i32
+    variant2 = cast[int32](record.x)
+    variant3 = cast[int32](record.y)
+  of ShapeKind.empty:
+    variant1 = 1'i32
+    variant2 = 0'i32
+    variant3 = 0'i32
+  let address4 = retArea()
+  wasmImportGreet(address, int32(str.len), variant1, variant2, variant3, address4)
+  for allocation in cleanup:
+    abiFree(allocation.address, allocation.size, allocation.align)
+  let load5 = load(int32, address4, 0)
+  let load6 = load(int32, address4, 4)
+  let address7 = load5
+  let count = load6
+  let items = liftList[uint8](address7, count)
+  abiFree(address7, count * 1, 1)
+  return items
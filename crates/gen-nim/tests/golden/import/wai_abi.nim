@@ -0,0 +1,85 @@
+# Generated by `wai-bindgen`: the runtime support of the bindings of all
+# interfaces.
+
+type
+  Unit* = object
+    ## The value of `unit` and of `tuple<>`.
+
+  Expected*[T, E] = object
+    ## The value of an `expected<T, E>`: either a `value` or an `error`.
+    case isOk*: bool
+    of true:
+      value*: T
+    of false:
+      error*: E
+
+proc ok*[T, E](value: T): Expected[T, E] =
+  Expected[T, E](isOk: true, value: value)
+
+proc err*[T, E](error: E): Expected[T, E] =
+  Expected[T, E](isOk: false, error: error)
+
+# Note that these intrinsics are declared as `weak` so they can be
+# overridden from some other symbol.
+proc canonicalAbiRealloc(address: pointer, origSize, align, newSize: uint32): pointer {.
+    exportc: "canonical_abi_realloc", cdecl,
+    codegenDecl: "__attribute__((weak, export_name(\"canonical_abi_realloc\"))) $# $#$#".} =
+  # Empty allocations get a dangling pointer which is never freed.
+  if newSize == 0:
+    return cast[pointer](align)
+  if origSize == 0:
+    result = allocShared(newSize)
+  else:
+    result = reallocShared(address, newSize)
+
+proc canonicalAbiFree(address: pointer, size, align: uint32) {.
+    exportc: "canonical_abi_free", cdecl,
+    codegenDecl: "__attribute__((weak, export_name(\"canonical_abi_free\"))) $# $#$#".} =
+  if size != 0:
+    deallocShared(address)
+
+var retAreaStorage {.align(4).}: array[8, uint8]
+
+                proc retArea*(): int32 =
+                  cast[int32](addr retAreaStorage)
+
+                proc abiAlloc*(size, align: int32): int32 =
+                  cast[int32](canonicalAbiRealloc(nil, 0, uint32(align), uint32(size)))
+
+                proc abiFree*(address, size, align: int32) =
+                  canonicalAbiFree(cast[pointer](address), uint32(size), uint32(align))
+
+                template load*(T: typedesc, address, offset: int32): untyped =
+                  cast[ptr T](uint(cast[uint32](address)) + uint(offset))[]
+
+                template store*(T: typedesc, address, offset: int32, value: T) =
+                  cast[ptr T](uint(cast[uint32](address)) + uint(offset))[] = value
+
+                proc lowerList*[T](items: openArray[T]): int32 =
+                  ## Copies `items` into a new allocation, given away to the caller.
+                  result = abiAlloc(int32(items.len * sizeof(T)), int32(alignof(T)))
+                  if items.len > 0:
+                    copyMem(cast[pointer](result), unsafeAddr items[0], items.len * sizeof(T))
+
+                proc liftList*[T](address, len: int32): seq[T] =
+                  result = newSeq[T](len)
+                  if len > 0:
+                    copyMem(addr result[0], cast[pointer](address), int(len) * sizeof(T))
+
+                proc liftString*(address, len: int32): string =
+                  result = newString(len)
+                  if len > 0:
+                    copyMem(addr result[0], cast[pointer](address), len)
+
+                proc flagsToBits*[T: enum](flags: set[T], word: int): int32 =
+                  ## The bits of the 32 flags starting at `32 * word` in `flags`.
+                  var bits = 0'u32
+                  for flag in flags:
+                    if ord(flag) div 32 == word:
+                      bits = bits or (1'u32 shl (ord(flag) mod 32))
+                  cast[int32](bits)
+
+                proc flagsFromBits*[T: enum](words: openArray[int32]): set[T] =
+                  for flag in T:
+                    if ((cast[uint32](words[ord(flag) div 32]) shr (ord(flag) mod 32)) and 1) != 0:
+                      result.incl flag
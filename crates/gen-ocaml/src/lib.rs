@@ -0,0 +1,1505 @@
+//! Bindings for OCaml hosts.
+//!
+//! For each interface this emits a `<interface>.ml` module with the OCaml
+//! definitions of its types and a `Make` functor holding the functions of
+//! the interface: for exports, functions calling into an instance, and for
+//! imports, a `Host` module type to implement along with `add_to_imports`
+//! to define the implementation as the imports of a module.
+//!
+//! The functors are applied to a module of the `Wai_runtime.S` signature,
+//! emitted in `wai_runtime.ml`, which is all the bindings need from a wasm
+//! runtime: access to the memory of an instance, calls of its exports and
+//! the definition of imports. Implementing it for a runtime, like the OCaml
+//! bindings of Wasmer, takes a handful of lines.
+//!
+//! Integers up to 32 bits are `int`s, which assumes a 64-bit platform, and
+//! the generated code needs OCaml 4.14 or later.
+
+use heck::*;
+use std::fmt::Write;
+use std::mem;
+use wai_bindgen_gen_core::wai_parser::abi::{
+    AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmType,
+};
+use wai_bindgen_gen_core::{
+    union_case_names, uwrite, uwriteln, wai_parser::*, Direction, Files, Generator, Ns,
+};
+
+#[derive(Default)]
+pub struct OCaml {
+    opts: Opts,
+    sizes: SizeAlign,
+    in_import: bool,
+    // The docs and definitions of the types of the current interface, which
+    // are printed as one recursive group.
+    types: Vec<(String, String)>,
+    // The contents of the `Make` functor of the current interface.
+    funcs: MlSource,
+    // The `val`s of the `Host` module type and the definitions of
+    // `add_to_imports`, for imports.
+    host_sig: MlSource,
+    defines: MlSource,
+}
+
+#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "structopt", derive(structopt::StructOpt))]
+pub struct Opts {
+    // ...
+}
+
+impl Opts {
+    pub fn build(&self) -> OCaml {
+        let mut r = OCaml::new();
+        r.opts = self.clone();
+        r
+    }
+}
+
+impl OCaml {
+    pub fn new() -> OCaml {
+        OCaml::default()
+    }
+
+    fn abi_variant(dir: Direction) -> AbiVariant {
+        // This generator uses a reversed mapping! In the OCaml host-side
+        // bindings, we don't use any extra adapter layer between guest wasm
+        // modules and the host. When the guest imports functions using the
+        // `GuestImport` ABI, the host directly implements the `GuestImport`
+        // ABI, even though the host is *exporting* functions. Similarly, when
+        // the guest exports functions using the `GuestExport` ABI, the host
+        // directly imports them with the `GuestExport` ABI, even though the
+        // host is *importing* functions.
+        match dir {
+            Direction::Import => AbiVariant::GuestExport,
+            Direction::Export => AbiVariant::GuestImport,
+        }
+    }
+
+    fn push_type(&mut self, name: &str, docs: &Docs, def: &str) {
+        let mut doc = MlSource::default();
+        doc.doc(docs);
+        let def = format!("{} = {}", ml_ident(&name.to_snake_case()), def);
+        self.types.push((doc.into(), def));
+    }
+
+    /// Prints a record type with the fields `fields`, or `unit` without any.
+    fn push_record(&mut self, name: &str, docs: &Docs, fields: &[(String, String, &Docs)]) {
+        if fields.is_empty() {
+            return self.push_type(name, docs, "unit");
+        }
+        let mut def = MlSource::default();
+        def.push_str("{");
+        def.indent();
+        for (field, ty, docs) in fields {
+            def.newline();
+            def.doc(docs);
+            uwrite!(def, "{} : {};", field, ty);
+        }
+        def.dedent();
+        def.newline();
+        def.push_str("}");
+        self.push_type(name, docs, &def);
+    }
+
+    /// Prints a variant type with the constructors `cases`, which take an
+    /// argument of the type of their payload if they have one.
+    fn push_variant(&mut self, name: &str, docs: &Docs, cases: &[(String, Option<String>, &Docs)]) {
+        let mut def = MlSource::default();
+        def.indent();
+        for (case, payload, docs) in cases {
+            def.newline();
+            def.doc(docs);
+            match payload {
+                Some(ty) => uwrite!(def, "| {} of {}", case, ty),
+                None => uwrite!(def, "| {}", case),
+            }
+        }
+        def.dedent();
+        self.push_type(name, docs, &def);
+    }
+}
+
+impl Generator for OCaml {
+    fn preprocess_one(&mut self, iface: &Interface, dir: Direction) {
+        assert!(
+            iface.resources.len() == 0,
+            "resources not supported yet by OCaml hosts"
+        );
+        let variant = Self::abi_variant(dir);
+        self.sizes.fill(iface);
+        self.in_import = variant == AbiVariant::GuestExport;
+    }
+
+    fn type_record(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        record: &Record,
+        docs: &Docs,
+    ) {
+        let fields = record
+            .fields
+            .iter()
+            .map(|f| {
+                (
+                    ml_ident(&f.name.to_snake_case()),
+                    ty_name(iface, &f.ty),
+                    &f.docs,
+                )
+            })
+            .collect::<Vec<_>>();
+        self.push_record(name, docs, &fields);
+    }
+
+    fn type_tuple(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        tuple: &Tuple,
+        docs: &Docs,
+    ) {
+        self.push_type(name, docs, &tuple_name(iface, &tuple.types));
+    }
+
+    fn type_flags(
+        &mut self,
+        _iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        flags: &Flags,
+        docs: &Docs,
+    ) {
+        // Flags are records of whether each flag is set.
+        let fields = flags
+            .flags
+            .iter()
+            .map(|f| {
+                (
+                    ml_ident(&f.name.to_snake_case()),
+                    "bool".to_string(),
+                    &f.docs,
+                )
+            })
+            .collect::<Vec<_>>();
+        self.push_record(name, docs, &fields);
+    }
+
+    fn type_variant(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        variant: &Variant,
+        docs: &Docs,
+    ) {
+        let cases = variant
+            .cases
+            .iter()
+            .map(|c| {
+                let payload = match c.ty {
+                    Type::Unit => None,
+                    ty => Some(ty_name(iface, &ty)),
+                };
+                (ml_ctor(&c.name), payload, &c.docs)
+            })
+            .collect::<Vec<_>>();
+        self.push_variant(name, docs, &cases);
+    }
+
+    fn type_union(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        union: &Union,
+        docs: &Docs,
+    ) {
+        let cases = union
+            .cases
+            .iter()
+            .zip(union_case_names(iface, union))
+            .map(|(c, case_name)| (ml_ctor(&case_name), Some(ty_name(iface, &c.ty)), &c.docs))
+            .collect::<Vec<_>>();
+        self.push_variant(name, docs, &cases);
+    }
+
+    fn type_option(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        payload: &Type,
+        docs: &Docs,
+    ) {
+        self.push_type(name, docs, &format!("{} option", ty_name(iface, payload)));
+    }
+
+    fn type_expected(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        expected: &Expected,
+        docs: &Docs,
+    ) {
+        let def = format!(
+            "({}, {}) result",
+            ty_name(iface, &expected.ok),
+            ty_name(iface, &expected.err)
+        );
+        self.push_type(name, docs, &def);
+    }
+
+    fn type_enum(
+        &mut self,
+        _iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        enum_: &Enum,
+        docs: &Docs,
+    ) {
+        let cases = enum_
+            .cases
+            .iter()
+            .map(|c| (ml_ctor(&c.name), None, &c.docs))
+            .collect::<Vec<_>>();
+        self.push_variant(name, docs, &cases);
+    }
+
+    fn type_resource(&mut self, _iface: &Interface, _ty: ResourceId) {
+        unimplemented!("resources not supported yet by OCaml hosts")
+    }
+
+    fn type_alias(&mut self, iface: &Interface, _id: TypeId, name: &str, ty: &Type, docs: &Docs) {
+        self.push_type(name, docs, &ty_name(iface, ty));
+    }
+
+    fn type_list(&mut self, iface: &Interface, _id: TypeId, name: &str, ty: &Type, docs: &Docs) {
+        self.push_type(name, docs, &list_name(iface, ty));
+    }
+
+    fn type_builtin(&mut self, iface: &Interface, id: TypeId, name: &str, ty: &Type, docs: &Docs) {
+        self.type_alias(iface, id, name, ty, docs);
+    }
+
+    // As with `abi_variant` above, we're generating host-side bindings here
+    // so a user "import" uses the "export" ABI variant on the inside of
+    // this `Generator` implementation.
+    fn import(&mut self, iface: &Interface, func: &Function) {
+        assert!(!func.is_async, "async not supported yet");
+        let params = func
+            .params
+            .iter()
+            .map(|(name, _)| ml_ident(&name.to_snake_case()))
+            .collect::<Vec<_>>();
+
+        self.funcs.newline();
+        self.funcs.newline();
+        self.funcs.doc(&func.docs);
+        uwrite!(
+            self.funcs,
+            "let {} (inst : R.instance)",
+            ml_ident(&func.name.to_snake_case())
+        );
+        for ((_, ty), param) in func.params.iter().zip(&params) {
+            uwrite!(self.funcs, " ({} : {})", param, ty_name(iface, ty));
+        }
+        uwrite!(self.funcs, " : {} =", ty_name(iface, &func.result));
+        self.funcs.indent();
+
+        let mut f = FunctionBindgen::new(self, params);
+        iface.call(
+            AbiVariant::GuestExport,
+            LiftLower::LowerArgsLiftResults,
+            func,
+            &mut f,
+        );
+        let FunctionBindgen { src, .. } = f;
+        self.funcs.push_lines(&src);
+        self.funcs.dedent();
+    }
+
+    fn export(&mut self, iface: &Interface, func: &Function) {
+        assert!(!func.is_async, "async not supported yet");
+        let sig = iface.wasm_signature(AbiVariant::GuestImport, func);
+        let name = ml_ident(&func.name.to_snake_case());
+
+        self.host_sig.doc(&func.docs);
+        uwrite!(self.host_sig, "val {} : ", name);
+        if func.params.is_empty() {
+            self.host_sig.push_str("unit -> ");
+        }
+        for (_, ty) in func.params.iter() {
+            uwrite!(self.host_sig, "{} -> ", ty_name(iface, ty));
+        }
+        self.host_sig.push_str(&ty_name(iface, &func.result));
+        self.host_sig.push_str("\n");
+
+        let wasm_types = |tys: &[WasmType]| {
+            tys.iter()
+                .map(|ty| wasm_ty_ctor(*ty))
+                .collect::<Vec<_>>()
+                .join("; ")
+        };
+        uwriteln!(
+            self.defines,
+            "R.define imports ~module_:\"{}\" ~name:\"{}\"",
+            iface.name,
+            func.name
+        );
+        self.defines.indent();
+        uwriteln!(
+            self.defines,
+            "~params:[{}] ~results:[{}]",
+            wasm_types(&sig.params),
+            wasm_types(&sig.results)
+        );
+        self.defines.push_str("(fun inst args ->");
+        self.defines.indent();
+
+        let mut f = FunctionBindgen::new(self, Vec::new());
+        for (i, ty) in sig.params.iter().enumerate() {
+            let arg = f.locals.tmp("arg");
+            uwriteln!(
+                f.src,
+                "let {} = {} (List.nth args {}) in",
+                arg,
+                wasm_ty_getter(*ty),
+                i
+            );
+            f.params.push(arg);
+        }
+        f.results = sig.results.clone();
+        iface.call(
+            AbiVariant::GuestImport,
+            LiftLower::LiftArgsLowerResults,
+            func,
+            &mut f,
+        );
+        let FunctionBindgen { src, .. } = f;
+        self.defines.push_lines(&src);
+        self.defines.push_str(");\n");
+        self.defines.dedent();
+        self.defines.dedent();
+    }
+
+    fn finish_one(&mut self, iface: &Interface, files: &mut Files) {
+        let mut src = MlSource::default();
+        uwriteln!(
+            src,
+            "(* Generated by `wai-bindgen` from the `{}` interface. *)",
+            iface.name
+        );
+        // Constructors and fields of different types may share a name, which
+        // the generated code disambiguates with type annotations.
+        src.push_str("\n[@@@ocaml.warning \"-26-27-30-32-41-42\"]\n");
+
+        for (i, (doc, def)) in mem::take(&mut self.types).into_iter().enumerate() {
+            src.push_str("\n");
+            src.push_str(&doc);
+            src.push_str(if i == 0 { "type " } else { "and " });
+            src.push_str(&def);
+            src.push_str("\n");
+        }
+
+        src.push_str("\nmodule Make (R : Wai_runtime.S) = struct");
+        src.indent();
+        src.newline();
+        src.push_str("module Abi = Wai_runtime.Abi (R)");
+        if self.in_import {
+            let funcs = mem::take(&mut self.funcs);
+            if !funcs.is_empty() {
+                src.newline();
+                src.push_lines(funcs.trim_start_matches('\n'));
+            }
+        } else {
+            src.newline();
+            src.newline();
+            uwrite!(
+                src,
+                "(** The functions of the `{}` interface, implemented by the host. *)",
+                iface.name
+            );
+            src.newline();
+            src.push_str("module type Host = sig");
+            src.indent();
+            src.push_lines(&mem::take(&mut self.host_sig));
+            src.dedent();
+            src.newline();
+            src.push_str("end");
+            src.newline();
+            src.newline();
+            src.push_str("(** Defines the functions of [H] as the imports of [imports]. *)");
+            src.newline();
+            src.push_str("let add_to_imports (imports : R.imports) (module H : Host) : unit =");
+            src.indent();
+            src.push_lines(&mem::take(&mut self.defines));
+            src.newline();
+            src.push_str("()");
+            src.dedent();
+        }
+        src.dedent();
+        src.newline();
+        src.push_str("end\n");
+
+        let name = ml_ident(&iface.name.to_snake_case());
+        files.push(&format!("{}.ml", name), src.as_bytes());
+    }
+
+    fn finish_all(&mut self, files: &mut Files) {
+        files.push("wai_runtime.ml", RUNTIME.as_bytes());
+    }
+}
+
+/// The runtime support shared by the bindings of all interfaces.
+const RUNTIME: &str = r#"(* Generated by `wai-bindgen`: the runtime interface of the OCaml bindings. *)
+
+(** A wasm value. *)
+type value = I32 of int32 | I64 of int64 | F32 of float | F64 of float
+
+(** The type of a wasm value. *)
+type ty = T_i32 | T_i64 | T_f32 | T_f64
+
+let i32 v = I32 (Int32.of_int v)
+let as_i32 = function I32 v -> Int32.to_int v | _ -> invalid_arg "expected an i32"
+let as_i64 = function I64 v -> v | _ -> invalid_arg "expected an i64"
+let as_f32 = function F32 v -> v | _ -> invalid_arg "expected an f32"
+let as_f64 = function F64 v -> v | _ -> invalid_arg "expected an f64"
+
+(** What the bindings need from a wasm runtime.
+
+    Addresses are offsets into the memory exported as [memory] by an
+    instance. Stores truncate values to their width, and loads of [i32]s are
+    sign-extended. *)
+module type S = sig
+  (** An instance of a wasm module, which is also what imports are called
+      with. *)
+  type instance
+
+  (** The imports a module is instantiated with. *)
+  type imports
+
+  val load_u8 : instance -> int -> int
+  val load_s8 : instance -> int -> int
+  val load_u16 : instance -> int -> int
+  val load_s16 : instance -> int -> int
+  val load_i32 : instance -> int -> int
+  val load_i64 : instance -> int -> int64
+  val load_f32 : instance -> int -> float
+  val load_f64 : instance -> int -> float
+
+  (** [load_bytes inst addr len] reads [len] bytes at [addr]. *)
+  val load_bytes : instance -> int -> int -> bytes
+
+  val store_i8 : instance -> int -> int -> unit
+  val store_i16 : instance -> int -> int -> unit
+  val store_i32 : instance -> int -> int -> unit
+  val store_i64 : instance -> int -> int64 -> unit
+  val store_f32 : instance -> int -> float -> unit
+  val store_f64 : instance -> int -> float -> unit
+  val store_bytes : instance -> int -> bytes -> unit
+
+  (** [call inst name args] calls the export [name] of [inst]. *)
+  val call : instance -> string -> value list -> value list
+
+  (** [define imports ~module_ ~name ~params ~results f] defines the import
+      [name] of [module_] as [f], which is passed the calling instance. *)
+  val define :
+    imports ->
+    module_:string ->
+    name:string ->
+    params:ty list ->
+    results:ty list ->
+    (instance -> value list -> value list) ->
+    unit
+end
+
+(** The canonical ABI on top of a runtime, used by the generated code. *)
+module Abi (R : S) = struct
+  let alloc inst realloc align size =
+    as_i32 (List.hd (R.call inst realloc [ i32 0; i32 0; i32 align; i32 size ]))
+
+  let free inst free ptr size align =
+    ignore (R.call inst free [ i32 ptr; i32 size; i32 align ])
+
+  let load_string inst ptr len =
+    let s = Bytes.to_string (R.load_bytes inst ptr len) in
+    if not (String.is_valid_utf_8 s) then invalid_arg "invalid UTF-8 string";
+    s
+
+  let store_bytes inst realloc b =
+    let len = Bytes.length b in
+    let ptr = alloc inst realloc 1 len in
+    R.store_bytes inst ptr b;
+    (ptr, len)
+
+  let store_string inst realloc s = store_bytes inst realloc (Bytes.of_string s)
+end
+"#;
+
+struct FunctionBindgen<'a> {
+    gen: &'a mut OCaml,
+    locals: Ns,
+    src: MlSource,
+    block_storage: Vec<MlSource>,
+    blocks: Vec<(String, Vec<String>)>,
+    params: Vec<String>,
+    payloads: Vec<String>,
+    // The wasm results of an import, which are returned as values.
+    results: Vec<WasmType>,
+}
+
+impl<'a> FunctionBindgen<'a> {
+    fn new(gen: &'a mut OCaml, params: Vec<String>) -> FunctionBindgen<'a> {
+        let mut locals = Ns::default();
+        for name in ["inst", "args"] {
+            locals.insert(name).unwrap();
+        }
+        for param in params.iter() {
+            locals.insert(param).unwrap();
+        }
+        FunctionBindgen {
+            gen,
+            locals,
+            src: MlSource::default(),
+            block_storage: Vec::new(),
+            blocks: Vec::new(),
+            params,
+            payloads: Vec::new(),
+            results: Vec::new(),
+        }
+    }
+
+    /// Binds `expr` to a new local named after `name`.
+    fn bind(&mut self, name: &str, expr: &str) -> String {
+        let tmp = self.locals.tmp(name);
+        uwriteln!(self.src, "let {} = {} in", tmp, expr);
+        tmp
+    }
+
+    fn load(&mut self, ty: &str, offset: i32, operands: &[String], results: &mut Vec<String>) {
+        let expr = format!("R.load_{} inst {}", ty, addr(&operands[0], offset));
+        results.push(self.bind("load", &expr));
+    }
+
+    fn store(&mut self, ty: &str, offset: i32, operands: &[String]) {
+        uwriteln!(
+            self.src,
+            "R.store_{} inst {} {};",
+            ty,
+            addr(&operands[1], offset),
+            operands[0]
+        );
+    }
+
+    /// Prints a `match` of `scrutinee` with `arms` of a pattern, a block and
+    /// the results of the block, binding the results of the arms to new
+    /// locals named after `name`.
+    fn print_match(
+        &mut self,
+        name: &str,
+        scrutinee: &str,
+        arms: Vec<(String, String, Vec<String>)>,
+        nresults: usize,
+    ) -> Vec<String> {
+        let results = (0..nresults)
+            .map(|_| self.locals.tmp(name))
+            .collect::<Vec<_>>();
+        match nresults {
+            0 => uwriteln!(self.src, "begin match {} with", scrutinee),
+            1 => {
+                uwriteln!(self.src, "let {} =", results[0]);
+                self.src.indent();
+                uwriteln!(self.src, "match {} with", scrutinee);
+            }
+            _ => {
+                uwriteln!(self.src, "let ({}) =", results.join(", "));
+                self.src.indent();
+                uwriteln!(self.src, "match {} with", scrutinee);
+            }
+        }
+        for (pattern, block, block_results) in arms {
+            let value = match block_results.len() {
+                0 => "()".to_string(),
+                1 => block_results[0].clone(),
+                _ => format!("({})", block_results.join(", ")),
+            };
+            if block.is_empty() {
+                uwriteln!(self.src, "| {} -> {}", pattern, value);
+                continue;
+            }
+            uwrite!(self.src, "| {} ->", pattern);
+            self.src.indent();
+            self.src.newline();
+            self.src.push_str(&block);
+            self.src.push_str(&value);
+            self.src.dedent();
+            self.src.newline();
+        }
+        match nresults {
+            0 => self.src.push_str("end;\n"),
+            _ => {
+                self.src.dedent();
+                self.src.push_str("in\n");
+            }
+        }
+        results
+    }
+
+    /// Lowers `op` of a variant type with the constructors `ctors`, which
+    /// have a payload unless they're `None`.
+    fn lower_cases(
+        &mut self,
+        op: &str,
+        ctors: Vec<(String, bool)>,
+        result_types: &[WasmType],
+    ) -> Vec<String> {
+        let blocks = self
+            .blocks
+            .drain(self.blocks.len() - ctors.len()..)
+            .collect::<Vec<_>>();
+        let payloads = self
+            .payloads
+            .drain(self.payloads.len() - ctors.len()..)
+            .collect::<Vec<_>>();
+        let arms = ctors
+            .into_iter()
+            .zip(blocks)
+            .zip(payloads)
+            .map(|(((ctor, has_payload), (block, block_results)), payload)| {
+                let pattern = if has_payload {
+                    format!("{} {}", ctor, payload)
+                } else {
+                    ctor
+                };
+                (pattern, block, block_results)
+            })
+            .collect();
+        self.print_match("variant", op, arms, result_types.len())
+    }
+
+    /// Lifts a value of the type `ty` with the constructors `ctors` from the
+    /// discriminant `op`.
+    fn lift_cases(
+        &mut self,
+        op: &str,
+        ctors: Vec<(String, bool)>,
+        ty: &str,
+        annotate: bool,
+    ) -> String {
+        let blocks = self
+            .blocks
+            .drain(self.blocks.len() - ctors.len()..)
+            .collect::<Vec<_>>();
+        let mut arms = ctors
+            .into_iter()
+            .zip(blocks)
+            .enumerate()
+            .map(|(i, ((ctor, has_payload), (block, block_results)))| {
+                let mut value = if has_payload {
+                    format!("{} {}", ctor, block_results[0])
+                } else {
+                    ctor
+                };
+                if annotate {
+                    value = format!("({} : {})", value, ty);
+                }
+                (i.to_string(), block, vec![value])
+            })
+            .collect::<Vec<_>>();
+        arms.push((
+            "_".to_string(),
+            String::new(),
+            vec![format!("invalid_arg \"invalid discriminant of {}\"", ty)],
+        ));
+        self.print_match("variant", op, arms, 1).remove(0)
+    }
+}
+
+impl Bindgen for FunctionBindgen<'_> {
+    type Operand = String;
+
+    fn sizes(&self) -> &SizeAlign {
+        &self.gen.sizes
+    }
+
+    fn push_block(&mut self) {
+        let prev = mem::take(&mut self.src);
+        self.block_storage.push(prev);
+    }
+
+    fn finish_block(&mut self, operands: &mut Vec<String>) {
+        let to_restore = self.block_storage.pop().unwrap();
+        let src = mem::replace(&mut self.src, to_restore);
+        self.blocks.push((src.into(), mem::take(operands)));
+    }
+
+    fn return_pointer(&mut self, _iface: &Interface, _size: usize, _align: usize) -> String {
+        unimplemented!()
+    }
+
+    fn is_list_canonical(&self, _iface: &Interface, ty: &Type) -> bool {
+        // Only `list<u8>` has a representation of its own, `bytes`.
+        matches!(ty, Type::U8)
+    }
+
+    fn emit(
+        &mut self,
+        iface: &Interface,
+        inst: &Instruction<'_>,
+        operands: &mut Vec<String>,
+        results: &mut Vec<String>,
+    ) {
+        match inst {
+            Instruction::GetArg { nth } => results.push(self.params[*nth].clone()),
+            Instruction::I32Const { val } if *val < 0 => results.push(format!("({})", val)),
+            Instruction::I32Const { val } => results.push(val.to_string()),
+            Instruction::ConstZero { tys } => {
+                for ty in tys.iter() {
+                    match ty {
+                        WasmType::I32 => results.push("0".to_string()),
+                        WasmType::I64 => results.push("0L".to_string()),
+                        WasmType::F32 | WasmType::F64 => results.push("0.0".to_string()),
+                    }
+                }
+            }
+
+            // Values are truncated to the width of their wasm type by the
+            // runtime, so lowering integers is a no-op.
+            Instruction::I32FromU8
+            | Instruction::I32FromS8
+            | Instruction::I32FromU16
+            | Instruction::I32FromS16
+            | Instruction::I32FromU32
+            | Instruction::I32FromS32
+            | Instruction::I64FromU64
+            | Instruction::I64FromS64
+            | Instruction::S32FromI32
+            | Instruction::S64FromI64
+            | Instruction::U64FromI64
+            | Instruction::Float32FromF32
+            | Instruction::Float64FromF64
+            | Instruction::F32FromFloat32
+            | Instruction::F64FromFloat64 => results.push(operands.pop().unwrap()),
+
+            // Integers coming from wasm are signed, and only their low bits
+            // are meaningful.
+            Instruction::U8FromI32 => results.push(format!("({} land 0xff)", operands[0])),
+            Instruction::U16FromI32 => results.push(format!("({} land 0xffff)", operands[0])),
+            Instruction::U32FromI32 => results.push(format!("({} land 0xffffffff)", operands[0])),
+            Instruction::S8FromI32 => {
+                results.push(format!("((({} land 0xff) lxor 0x80) - 0x80)", operands[0]))
+            }
+            Instruction::S16FromI32 => results.push(format!(
+                "((({} land 0xffff) lxor 0x8000) - 0x8000)",
+                operands[0]
+            )),
+
+            Instruction::CharFromI32 => results.push(format!("(Uchar.of_int {})", operands[0])),
+            Instruction::I32FromChar => results.push(format!("(Uchar.to_int {})", operands[0])),
+
+            Instruction::Bitcasts { casts } => {
+                for (cast, op) in casts.iter().zip(operands) {
+                    let op = match cast {
+                        Bitcast::I32ToF32 => format!("(Int32.float_of_bits (Int32.of_int {}))", op),
+                        Bitcast::F32ToI32 => format!("(Int32.to_int (Int32.bits_of_float {}))", op),
+                        Bitcast::I64ToF64 => format!("(Int64.float_of_bits {})", op),
+                        Bitcast::F64ToI64 => format!("(Int64.bits_of_float {})", op),
+                        Bitcast::I32ToI64 => format!("(Int64.of_int {})", op),
+                        Bitcast::I64ToI32 => format!("(Int32.to_int (Int64.to_int32 {}))", op),
+                        Bitcast::F32ToI64 => {
+                            format!("(Int64.of_int32 (Int32.bits_of_float {}))", op)
+                        }
+                        Bitcast::I64ToF32 => {
+                            format!("(Int32.float_of_bits (Int64.to_int32 {}))", op)
+                        }
+                        Bitcast::None => op.clone(),
+                    };
+                    results.push(op);
+                }
+            }
+
+            Instruction::UnitLower => {}
+            Instruction::UnitLift => results.push("()".to_string()),
+            Instruction::BoolFromI32 => results.push(format!("({} <> 0)", operands[0])),
+            Instruction::I32FromBool => results.push(format!("(if {} then 1 else 0)", operands[0])),
+
+            Instruction::I32FromBorrowedHandle { .. }
+            | Instruction::I32FromOwnedHandle { .. }
+            | Instruction::HandleOwnedFromI32 { .. }
+            | Instruction::HandleBorrowedFromI32 { .. } => {
+                unimplemented!("resources not supported yet by OCaml hosts")
+            }
+
+            Instruction::RecordLower { record, ty, .. } => {
+                if record.fields.is_empty() {
+                    return;
+                }
+                let names = record
+                    .fields
+                    .iter()
+                    .map(|_| self.locals.tmp("field"))
+                    .collect::<Vec<_>>();
+                let fields = record
+                    .fields
+                    .iter()
+                    .zip(&names)
+                    .map(|(f, name)| format!("{} = {}", ml_ident(&f.name.to_snake_case()), name))
+                    .collect::<Vec<_>>();
+                uwriteln!(
+                    self.src,
+                    "let ({{ {} }} : {}) = {} in",
+                    fields.join("; "),
+                    ty_name(iface, &Type::Id(*ty)),
+                    operands[0]
+                );
+                results.extend(names);
+            }
+            Instruction::RecordLift { record, ty, .. } => {
+                if record.fields.is_empty() {
+                    return results.push("()".to_string());
+                }
+                let fields = record
+                    .fields
+                    .iter()
+                    .zip(operands.iter())
+                    .map(|(f, op)| format!("{} = {}", ml_ident(&f.name.to_snake_case()), op))
+                    .collect::<Vec<_>>();
+                results.push(format!(
+                    "({{ {} }} : {})",
+                    fields.join("; "),
+                    ty_name(iface, &Type::Id(*ty))
+                ));
+            }
+
+            Instruction::TupleLower { tuple, .. } => match tuple.types.len() {
+                0 => {}
+                1 => results.push(operands[0].clone()),
+                _ => {
+                    let names = tuple
+                        .types
+                        .iter()
+                        .map(|_| self.locals.tmp("tuple"))
+                        .collect::<Vec<_>>();
+                    uwriteln!(self.src, "let ({}) = {} in", names.join(", "), operands[0]);
+                    results.extend(names);
+                }
+            },
+            Instruction::TupleLift { .. } => match operands.len() {
+                0 => results.push("()".to_string()),
+                1 => results.push(operands[0].clone()),
+                _ => results.push(format!("({})", operands.join(", "))),
+            },
+
+            Instruction::FlagsLower { flags, ty, .. } => {
+                if flags.flags.is_empty() {
+                    return results.push("0".to_string());
+                }
+                let names = flags
+                    .flags
+                    .iter()
+                    .map(|_| self.locals.tmp("flag"))
+                    .collect::<Vec<_>>();
+                let fields = flags
+                    .flags
+                    .iter()
+                    .zip(&names)
+                    .map(|(f, name)| format!("{} = {}", ml_ident(&f.name.to_snake_case()), name))
+                    .collect::<Vec<_>>();
+                uwriteln!(
+                    self.src,
+                    "let ({{ {} }} : {}) = {} in",
+                    fields.join("; "),
+                    ty_name(iface, &Type::Id(*ty)),
+                    operands[0]
+                );
+                for chunk in names.chunks(32) {
+                    let bits = chunk
+                        .iter()
+                        .enumerate()
+                        .map(|(i, name)| format!("(if {} then 1 lsl {} else 0)", name, i))
+                        .collect::<Vec<_>>();
+                    results.push(self.bind("flags", &bits.join(" lor ")));
+                }
+            }
+            Instruction::FlagsLift { flags, ty, .. } => {
+                if flags.flags.is_empty() {
+                    return results.push("()".to_string());
+                }
+                let fields = flags
+                    .flags
+                    .iter()
+                    .enumerate()
+                    .map(|(i, f)| {
+                        format!(
+                            "{} = {} land (1 lsl {}) <> 0",
+                            ml_ident(&f.name.to_snake_case()),
+                            operands[i / 32],
+                            i % 32
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                results.push(format!(
+                    "({{ {} }} : {})",
+                    fields.join("; "),
+                    ty_name(iface, &Type::Id(*ty))
+                ));
+            }
+
+            Instruction::VariantPayloadName => {
+                let name = self.locals.tmp("payload");
+                results.push(name.clone());
+                self.payloads.push(name);
+            }
+
+            Instruction::VariantLower {
+                variant,
+                ty,
+                results: result_types,
+                ..
+            } => {
+                let ctors = variant
+                    .cases
+                    .iter()
+                    .map(|c| (ml_ctor(&c.name), c.ty != Type::Unit))
+                    .collect();
+                let op = format!("({} : {})", operands[0], ty_name(iface, &Type::Id(*ty)));
+                results.extend(self.lower_cases(&op, ctors, result_types));
+            }
+            Instruction::VariantLift { variant, ty, .. } => {
+                let ctors = variant
+                    .cases
+                    .iter()
+                    .map(|c| (ml_ctor(&c.name), c.ty != Type::Unit))
+                    .collect();
+                let ty = ty_name(iface, &Type::Id(*ty));
+                let result = self.lift_cases(&operands[0], ctors, &ty, true);
+                results.push(result);
+            }
+
+            Instruction::UnionLower {
+                union,
+                ty,
+                results: result_types,
+                ..
+            } => {
+                let ctors = union_case_names(iface, union)
+                    .iter()
+                    .map(|name| (ml_ctor(name), true))
+                    .collect();
+                let op = format!("({} : {})", operands[0], ty_name(iface, &Type::Id(*ty)));
+                results.extend(self.lower_cases(&op, ctors, result_types));
+            }
+            Instruction::UnionLift { union, ty, .. } => {
+                let ctors = union_case_names(iface, union)
+                    .iter()
+                    .map(|name| (ml_ctor(name), true))
+                    .collect();
+                let ty = ty_name(iface, &Type::Id(*ty));
+                let result = self.lift_cases(&operands[0], ctors, &ty, true);
+                results.push(result);
+            }
+
+            Instruction::EnumLower { enum_, ty, .. } => {
+                let cases = enum_
+                    .cases
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| format!("{} -> {}", ml_ctor(&c.name), i))
+                    .collect::<Vec<_>>();
+                results.push(format!(
+                    "(match ({} : {}) with {})",
+                    operands[0],
+                    ty_name(iface, &Type::Id(*ty)),
+                    cases.join(" | ")
+                ));
+            }
+            Instruction::EnumLift { enum_, ty, .. } => {
+                let ty = ty_name(iface, &Type::Id(*ty));
+                let arms = enum_
+                    .cases
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| {
+                        let value = format!("({} : {})", ml_ctor(&c.name), ty);
+                        (i.to_string(), String::new(), vec![value])
+                    })
+                    .chain(Some((
+                        "_".to_string(),
+                        String::new(),
+                        vec![format!("invalid_arg \"invalid discriminant of {}\"", ty)],
+                    )))
+                    .collect();
+                results.extend(self.print_match("enum", &operands[0], arms, 1));
+            }
+
+            Instruction::OptionLower {
+                results: result_types,
+                ..
+            } => {
+                let ctors = vec![("None".to_string(), false), ("Some".to_string(), true)];
+                results.extend(self.lower_cases(&operands[0], ctors, result_types));
+            }
+            Instruction::OptionLift { .. } => {
+                let ctors = vec![("None".to_string(), false), ("Some".to_string(), true)];
+                let result = self.lift_cases(&operands[0], ctors, "option", false);
+                results.push(result);
+            }
+
+            Instruction::ExpectedLower {
+                results: result_types,
+                ..
+            } => {
+                let ctors = vec![("Ok".to_string(), true), ("Error".to_string(), true)];
+                results.extend(self.lower_cases(&operands[0], ctors, result_types));
+            }
+            Instruction::ExpectedLift { .. } => {
+                let ctors = vec![("Ok".to_string(), true), ("Error".to_string(), true)];
+                let result = self.lift_cases(&operands[0], ctors, "expected", false);
+                results.push(result);
+            }
+
+            Instruction::ListCanonLower { realloc, .. } => {
+                // Lowering only happens when we're passing lists into wasm,
+                // which forces us to always allocate, so this should always be
+                // `Some`.
+                let realloc = realloc.unwrap();
+                let ptr = self.locals.tmp("ptr");
+                let len = self.locals.tmp("len");
+                uwriteln!(
+                    self.src,
+                    "let ({}, {}) = Abi.store_bytes inst \"{}\" {} in",
+                    ptr,
+                    len,
+                    realloc,
+                    operands[0]
+                );
+                results.push(ptr);
+                results.push(len);
+            }
+            Instruction::StringLower { realloc } => {
+                let realloc = realloc.unwrap();
+                let ptr = self.locals.tmp("ptr");
+                let len = self.locals.tmp("len");
+                uwriteln!(
+                    self.src,
+                    "let ({}, {}) = Abi.store_string inst \"{}\" {} in",
+                    ptr,
+                    len,
+                    realloc,
+                    operands[0]
+                );
+                results.push(ptr);
+                results.push(len);
+            }
+            Instruction::ListLower { element, realloc } => {
+                let realloc = realloc.unwrap();
+                let (body, body_results) = self.blocks.pop().unwrap();
+                assert!(body_results.is_empty());
+                let size = self.gen.sizes.size(element);
+                let align = self.gen.sizes.align(element);
+                let vec = self.bind("vec", &operands[0]);
+                let len = self.bind("len", &format!("List.length {}", vec));
+                let ptr = self.bind(
+                    "ptr",
+                    &format!(
+                        "Abi.alloc inst \"{}\" {} ({} * {})",
+                        realloc, align, len, size
+                    ),
+                );
+                self.src.push_str("List.iteri");
+                self.src.indent();
+                self.src.newline();
+                self.src.push_str("(fun i' e' ->");
+                self.src.indent();
+                self.src.newline();
+                uwriteln!(self.src, "let base' = {} + i' * {} in", ptr, size);
+                self.src.push_str(&body);
+                self.src.push_str("())");
+                self.src.dedent();
+                self.src.newline();
+                uwrite!(self.src, "{};", vec);
+                self.src.dedent();
+                self.src.newline();
+                results.push(ptr);
+                results.push(len);
+            }
+
+            Instruction::ListCanonLift { free, .. } => {
+                let ptr = self.bind("ptr", &operands[0]);
+                let len = self.bind("len", &operands[1]);
+                let result = self.bind("bytes", &format!("R.load_bytes inst {} {}", ptr, len));
+                if let Some(free) = free {
+                    uwriteln!(self.src, "Abi.free inst \"{}\" {} {} 1;", free, ptr, len);
+                }
+                results.push(result);
+            }
+            Instruction::StringLift { free } => {
+                let ptr = self.bind("ptr", &operands[0]);
+                let len = self.bind("len", &operands[1]);
+                let result = self.bind("string", &format!("Abi.load_string inst {} {}", ptr, len));
+                if let Some(free) = free {
+                    uwriteln!(self.src, "Abi.free inst \"{}\" {} {} 1;", free, ptr, len);
+                }
+                results.push(result);
+            }
+            Instruction::ListLift { element, free, .. } => {
+                let (body, body_results) = self.blocks.pop().unwrap();
+                let size = self.gen.sizes.size(element);
+                let align = self.gen.sizes.align(element);
+                let ptr = self.bind("ptr", &operands[0]);
+                let len = self.bind("len", &operands[1]);
+                let result = self.locals.tmp("items");
+                uwrite!(self.src, "let {} =", result);
+                self.src.indent();
+                self.src.newline();
+                uwrite!(self.src, "List.init {} (fun i' ->", len);
+                self.src.indent();
+                self.src.newline();
+                uwriteln!(self.src, "let base' = {} + i' * {} in", ptr, size);
+                self.src.push_str(&body);
+                uwrite!(self.src, "{})", body_results[0]);
+                self.src.dedent();
+                self.src.dedent();
+                self.src.newline();
+                self.src.push_str("in\n");
+                if let Some(free) = free {
+                    uwriteln!(
+                        self.src,
+                        "Abi.free inst \"{}\" {} ({} * {}) {};",
+                        free,
+                        ptr,
+                        len,
+                        size,
+                        align
+                    );
+                }
+                results.push(result);
+            }
+
+            // The elements and base pointers of nested lists shadow those of
+            // the enclosing lists in the closures iterating over them.
+            Instruction::IterElem { .. } => results.push("e'".to_string()),
+            Instruction::IterBasePointer => results.push("base'".to_string()),
+
+            Instruction::CallWasm { name, sig, .. } => {
+                let args = sig
+                    .params
+                    .iter()
+                    .zip(operands.iter())
+                    .map(|(ty, op)| wasm_value(*ty, op))
+                    .collect::<Vec<_>>();
+                let call = format!("R.call inst \"{}\" [{}]", name, args.join("; "));
+                if sig.results.is_empty() {
+                    uwriteln!(self.src, "ignore ({});", call);
+                    return;
+                }
+                let ret = self.bind("ret", &call);
+                for (i, ty) in sig.results.iter().enumerate() {
+                    let expr = format!("{} (List.nth {} {})", wasm_ty_getter(*ty), ret, i);
+                    results.push(self.bind("ret", &expr));
+                }
+            }
+
+            Instruction::CallInterface { func, .. } => {
+                let mut call = format!("H.{}", ml_ident(&func.name.to_snake_case()));
+                if operands.is_empty() {
+                    call.push_str(" ()");
+                }
+                for op in operands.iter() {
+                    call.push(' ');
+                    call.push_str(op);
+                }
+                results.push(self.bind("ret", &call));
+            }
+
+            Instruction::Return { amt, .. } => {
+                if self.gen.in_import {
+                    assert_eq!(*amt, 1);
+                    self.src.push_str(&operands[0]);
+                } else {
+                    let values = self
+                        .results
+                        .iter()
+                        .zip(operands.iter())
+                        .map(|(ty, op)| wasm_value(*ty, op))
+                        .collect::<Vec<_>>();
+                    uwrite!(self.src, "[{}]", values.join("; "));
+                }
+            }
+
+            Instruction::I32Load { offset } => self.load("i32", *offset, operands, results),
+            Instruction::I32Load8U { offset } => self.load("u8", *offset, operands, results),
+            Instruction::I32Load8S { offset } => self.load("s8", *offset, operands, results),
+            Instruction::I32Load16U { offset } => self.load("u16", *offset, operands, results),
+            Instruction::I32Load16S { offset } => self.load("s16", *offset, operands, results),
+            Instruction::I64Load { offset } => self.load("i64", *offset, operands, results),
+            Instruction::F32Load { offset } => self.load("f32", *offset, operands, results),
+            Instruction::F64Load { offset } => self.load("f64", *offset, operands, results),
+            Instruction::I32Store { offset } => self.store("i32", *offset, operands),
+            Instruction::I32Store8 { offset } => self.store("i8", *offset, operands),
+            Instruction::I32Store16 { offset } => self.store("i16", *offset, operands),
+            Instruction::I64Store { offset } => self.store("i64", *offset, operands),
+            Instruction::F32Store { offset } => self.store("f32", *offset, operands),
+            Instruction::F64Store { offset } => self.store("f64", *offset, operands),
+
+            Instruction::Malloc {
+                realloc,
+                size,
+                align,
+            } => {
+                let expr = format!("Abi.alloc inst \"{}\" {} {}", realloc, align, size);
+                results.push(self.bind("ptr", &expr));
+            }
+            Instruction::Free { .. } => unreachable!(),
+
+            Instruction::CallWasmAsyncImport { .. }
+            | Instruction::CallWasmAsyncExport { .. }
+            | Instruction::ReturnAsyncExport { .. }
+            | Instruction::ReturnAsyncImport { .. } => {
+                unimplemented!("async not supported yet")
+            }
+        }
+    }
+}
+
+/// OCaml source, indented by blocks of two spaces.
+///
+/// Unlike `wai_bindgen_gen_core::Source` the level is explicit, and applies
+/// to the current line unless something was written to it already.
+#[derive(Default)]
+struct MlSource {
+    s: String,
+    indent: usize,
+}
+
+impl MlSource {
+    /// Appends `src`, indenting all of its lines but the first at the
+    /// current level.
+    fn push_str(&mut self, src: &str) {
+        let lines = src.lines().collect::<Vec<_>>();
+        for (i, line) in lines.iter().enumerate() {
+            self.s.push_str(line);
+            if i != lines.len() - 1 || src.ends_with('\n') {
+                self.newline();
+            }
+        }
+    }
+
+    /// Appends each line of `src` on a line of its own.
+    fn push_lines(&mut self, src: &str) {
+        for line in src.lines() {
+            self.newline();
+            self.s.push_str(line);
+        }
+    }
+
+    fn indent(&mut self) {
+        self.indent += 2;
+        self.reindent();
+    }
+
+    fn dedent(&mut self) {
+        self.indent -= 2;
+        self.reindent();
+    }
+
+    /// Moves the current line to the current level if nothing was written
+    /// to it yet.
+    fn reindent(&mut self) {
+        let start = self.s.rfind('\n').map_or(0, |i| i + 1);
+        if self.s[start..].bytes().all(|b| b == b' ') {
+            self.s.truncate(start);
+            for _ in 0..self.indent {
+                self.s.push(' ');
+            }
+        }
+    }
+
+    fn newline(&mut self) {
+        // Blank lines don't keep the indentation.
+        while self.s.ends_with(' ') {
+            self.s.pop();
+        }
+        self.s.push('\n');
+        for _ in 0..self.indent {
+            self.s.push(' ');
+        }
+    }
+
+    /// Prints `docs` as a documentation comment followed by a new line.
+    fn doc(&mut self, docs: &Docs) {
+        if let Some(docs) = &docs.contents {
+            let docs = docs.trim().replace("*)", "* )");
+            self.s.push_str("(** ");
+            for (i, line) in docs.lines().enumerate() {
+                if i > 0 {
+                    self.newline();
+                    self.s.push_str("    ");
+                }
+                self.s.push_str(line.trim());
+            }
+            self.s.push_str(" *)");
+            self.newline();
+        }
+    }
+}
+
+impl std::fmt::Write for MlSource {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl std::ops::Deref for MlSource {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.s
+    }
+}
+
+impl From<MlSource> for String {
+    fn from(s: MlSource) -> String {
+        s.s
+    }
+}
+
+/// Returns the OCaml type of `ty`.
+fn ty_name(iface: &Interface, ty: &Type) -> String {
+    match ty {
+        Type::Unit => "unit".to_string(),
+        Type::Bool => "bool".to_string(),
+        Type::U8 | Type::S8 | Type::U16 | Type::S16 | Type::U32 | Type::S32 => "int".to_string(),
+        Type::U64 | Type::S64 => "int64".to_string(),
+        Type::Float32 | Type::Float64 => "float".to_string(),
+        Type::Char => "Uchar.t".to_string(),
+        Type::String => "string".to_string(),
+        Type::Handle(_) => unimplemented!("resources not supported yet by OCaml hosts"),
+        Type::Id(id) => {
+            let ty = &iface.types[*id];
+            if let Some(name) = &ty.name {
+                return ml_ident(&name.to_snake_case());
+            }
+            match &ty.kind {
+                TypeDefKind::Type(t) => ty_name(iface, t),
+                TypeDefKind::List(t) => list_name(iface, t),
+                TypeDefKind::Tuple(t) => tuple_name(iface, &t.types),
+                TypeDefKind::Option(t) => format!("{} option", ty_name(iface, t)),
+                TypeDefKind::Expected(e) => format!(
+                    "({}, {}) result",
+                    ty_name(iface, &e.ok),
+                    ty_name(iface, &e.err)
+                ),
+                TypeDefKind::Record(_)
+                | TypeDefKind::Flags(_)
+                | TypeDefKind::Variant(_)
+                | TypeDefKind::Enum(_)
+                | TypeDefKind::Union(_) => unreachable!("anonymous named type"),
+                TypeDefKind::Future(_) => todo!("generate for future"),
+                TypeDefKind::Stream(_) => todo!("generate for stream"),
+            }
+        }
+    }
+}
+
+fn list_name(iface: &Interface, ty: &Type) -> String {
+    match ty {
+        Type::U8 => "bytes".to_string(),
+        ty => format!("{} list", ty_name(iface, ty)),
+    }
+}
+
+/// Tuples of one type are that type, and empty tuples `unit`.
+fn tuple_name(iface: &Interface, types: &[Type]) -> String {
+    match types {
+        [] => "unit".to_string(),
+        [ty] => ty_name(iface, ty),
+        _ => {
+            let types = types
+                .iter()
+                .map(|ty| ty_name(iface, ty))
+                .collect::<Vec<_>>();
+            format!("({})", types.join(" * "))
+        }
+    }
+}
+
+/// Escapes names which are keywords in OCaml, types or values of the
+/// standard library the generated code relies on, or locals of the generated
+/// functions, and characters outside of ASCII.
+fn ml_ident(name: &str) -> String {
+    let mut ret = String::new();
+    for c in name.chars() {
+        if c.is_ascii() {
+            ret.push(c);
+        } else {
+            write!(ret, "_{:x}", u32::from(c)).unwrap();
+        }
+    }
+    match ret.as_str() {
+        "and" | "as" | "assert" | "asr" | "begin" | "class" | "constraint" | "do" | "done"
+        | "downto" | "else" | "end" | "exception" | "external" | "false" | "for" | "fun"
+        | "function" | "functor" | "if" | "in" | "include" | "inherit" | "initializer" | "land"
+        | "lazy" | "let" | "lor" | "lsl" | "lsr" | "lxor" | "match" | "method" | "mod"
+        | "module" | "mutable" | "new" | "nonrec" | "object" | "of" | "open" | "or" | "private"
+        | "rec" | "sig" | "struct" | "then" | "to" | "true" | "try" | "type" | "val"
+        | "virtual" | "when" | "while" | "with" | "unit" | "bool" | "int" | "int64" | "float"
+        | "string" | "bytes" | "list" | "option" | "result" | "ignore" | "invalid_arg" | "not"
+        | "inst" | "args" | "imports" | "add_to_imports" => ret.push('_'),
+        _ => {}
+    }
+    ret
+}
+
+/// Returns the name of the constructor of a case named `name`, escaping
+/// those of the standard library.
+fn ml_ctor(name: &str) -> String {
+    let mut ret = ml_ident(&name.to_camel_case());
+    if !ret.starts_with(|c: char| c.is_ascii_uppercase()) {
+        ret.insert(0, 'C');
+    }
+    match ret.as_str() {
+        "None" | "Some" | "Ok" | "Error" => ret.push('_'),
+        _ => {}
+    }
+    ret
+}
+
+/// Returns the address `offset` bytes after `ptr`.
+fn addr(ptr: &str, offset: i32) -> String {
+    match offset {
+        0 => ptr.to_string(),
+        _ => format!("({} + {})", ptr, offset),
+    }
+}
+
+fn wasm_ty_ctor(ty: WasmType) -> &'static str {
+    match ty {
+        WasmType::I32 => "Wai_runtime.T_i32",
+        WasmType::I64 => "Wai_runtime.T_i64",
+        WasmType::F32 => "Wai_runtime.T_f32",
+        WasmType::F64 => "Wai_runtime.T_f64",
+    }
+}
+
+fn wasm_ty_getter(ty: WasmType) -> &'static str {
+    match ty {
+        WasmType::I32 => "Wai_runtime.as_i32",
+        WasmType::I64 => "Wai_runtime.as_i64",
+        WasmType::F32 => "Wai_runtime.as_f32",
+        WasmType::F64 => "Wai_runtime.as_f64",
+    }
+}
+
+/// Wraps `val` of `ty` in a `Wai_runtime.value`.
+fn wasm_value(ty: WasmType, val: &str) -> String {
+    match ty {
+        WasmType::I32 => format!("Wai_runtime.i32 {}", val),
+        WasmType::I64 => format!("Wai_runtime.I64 {}", val),
+        WasmType::F32 => format!("Wai_runtime.F32 {}", val),
+        WasmType::F64 => format!("Wai_runtime.F64 {}", val),
+    }
+}
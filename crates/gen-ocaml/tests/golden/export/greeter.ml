@@ -0,0 +1,65 @@
+(* Generated by `wai-bindgen` from the `greeter` interface. *)
+
+[@@@ocaml.warning "-26-27-30-32-41-42"]
+
+type point = {
+  x : int;
+  y : int;
+}
+
+and shape =
+  | Circle of float
+  | None_
+
+module Make (R : Wai_runtime.S) = struct
+  module Abi = Wai_runtime.Abi (R)
+
+  (** The functions of the `greeter` interface, implemented by the host. *)
+  module type Host = sig
+    val greet : string -> point -> bytes
+    val area : shape -> float option
+  end
+
+  (** Defines the functions of [H] as the imports of [imports]. *)
+  let add_to_imports (imports : R.imports) (module H : Host) : unit =
+    R.define imports ~module_:"greeter" ~name:"greet"
+      ~params:[Wai_runtime.T_i32; Wai_runtime.T_i32; Wai_runtime.T_i32; Wai_runtime.T_i32; Wai_runtime.T_i32] ~results:[]
+      (fun inst args ->
+        let arg = Wai_runtime.as_i32 (List.nth args 0) in
+        let arg0 = Wai_runtime.as_i32 (List.nth args 1) in
+        let arg1 = Wai_runtime.as_i32 (List.nth args 2) in
+        let arg2 = Wai_runtime.as_i32 (List.nth args 3) in
+        let arg3 = Wai_runtime.as_i32 (List.nth args 4) in
+        let ptr = arg in
+        let len = arg0 in
+        let string = Abi.load_string inst ptr len in
+        let ret = H.greet string ({ x = (arg1 land 0xffffffff); y = (arg2 land 0xffffffff) } : point) in
+        let (ptr4, len5) = Abi.store_bytes inst "canonical_abi_realloc" ret in
+        R.store_i32 inst (arg3 + 4) len5;
+        R.store_i32 inst arg3 ptr4;
+        []);
+    R.define imports ~module_:"greeter" ~name:"area"
+      ~params:[Wai_runtime.T_i32; Wai_runtime.T_f64; Wai_runtime.T_i32] ~results:[]
+      (fun inst args ->
+        let arg = Wai_runtime.as_i32 (List.nth args 0) in
+        let arg0 = Wai_runtime.as_f64 (List.nth args 1) in
+        let arg1 = Wai_runtime.as_i32 (List.nth args 2) in
+        let variant =
+          match arg with
+          | 0 -> (Circle arg0 : shape)
+          | 1 -> (None_ : shape)
+          | _ -> invalid_arg "invalid discriminant of shape"
+        in
+        let ret = H.area variant in
+        begin match ret with
+        | None ->
+          R.store_i8 inst arg1 0;
+          ()
+        | Some payload2 ->
+          R.store_i8 inst arg1 1;
+          R.store_f64 inst (arg1 + 8) payload2;
+          ()
+        end;
+        []);
+    ()
+end
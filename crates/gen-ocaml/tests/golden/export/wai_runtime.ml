@@ -0,0 +1,83 @@
+(* Generated by `wai-bindgen`: the runtime interface of the OCaml bindings. *)
+
+(** A wasm value. *)
+type value = I32 of int32 | I64 of int64 | F32 of float | F64 of float
+
+(** The type of a wasm value. *)
+type ty = T_i32 | T_i64 | T_f32 | T_f64
+
+let i32 v = I32 (Int32.of_int v)
+let as_i32 = function I32 v -> Int32.to_int v | _ -> invalid_arg "expected an i32"
+let as_i64 = function I64 v -> v | _ -> invalid_arg "expected an i64"
+let as_f32 = function F32 v -> v | _ -> invalid_arg "expected an f32"
+let as_f64 = function F64 v -> v | _ -> invalid_arg "expected an f64"
+
+(** What the bindings need from a wasm runtime.
+
+    Addresses are offsets into the memory exported as [memory] by an
+    instance. Stores truncate values to their width, and loads of [i32]s are
+    sign-extended. *)
+module type S = sig
+  (** An instance of a wasm module, which is also what imports are called
+      with. *)
+  type instance
+
+  (** The imports a module is instantiated with. *)
+  type imports
+
+  val load_u8 : instance -> int -> int
+  val load_s8 : instance -> int -> int
+  val load_u16 : instance -> int -> int
+  val load_s16 : instance -> int -> int
+  val load_i32 : instance -> int -> int
+  val load_i64 : instance -> int -> int64
+  val load_f32 : instance -> int -> float
+  val load_f64 : instance -> int -> float
+
+  (** [load_bytes inst addr len] reads [len] bytes at [addr]. *)
+  val load_bytes : instance -> int -> int -> bytes
+
+  val store_i8 : instance -> int -> int -> unit
+  val store_i16 : instance -> int -> int -> unit
+  val store_i32 : instance -> int -> int -> unit
+  val store_i64 : instance -> int -> int64 -> unit
+  val store_f32 : instance -> int -> float -> unit
+  val store_f64 : instance -> int -> float -> unit
+  val store_bytes : instance -> int -> bytes -> unit
+
+  (** [call inst name args] calls the export [name] of [inst]. *)
+  val call : instance -> string -> value list -> value list
+
+  (** [define imports ~module_ ~name ~params ~results f] defines the import
+      [name] of [module_] as [f], which is passed the calling instance. *)
+  val define :
+    imports ->
+    module_:string ->
+    name:string ->
+    params:ty list ->
+    results:ty list ->
+    (instance -> value list -> value list) ->
+    unit
+end
+
+(** The canonical ABI on top of a runtime, used by the generated code. *)
+module Abi (R : S) = struct
+  let alloc inst realloc align size =
+    as_i32 (List.hd (R.call inst realloc [ i32 0; i32 0; i32 align; i32 size ]))
+
+  let free inst free ptr size align =
+    ignore (R.call inst free [ i32 ptr; i32 size; i32 align ])
+
+  let load_string inst ptr len =
+    let s = Bytes.to_string (R.load_bytes inst ptr len) in
+    if not (String.is_valid_utf_8 s) then invalid_arg "invalid UTF-8 string";
+    s
+
+  let store_bytes inst realloc b =
+    let len = Bytes.length b in
+    let ptr = alloc inst realloc 1 len in
+    R.store_bytes inst ptr b;
+    (ptr, len)
+
+  let store_string inst realloc s = store_bytes inst realloc (Bytes.of_string s)
+end
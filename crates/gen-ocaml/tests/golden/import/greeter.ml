@@ -0,0 +1,48 @@
+(* Generated by `wai-bindgen` from the `greeter` interface. *)
+
+[@@@ocaml.warning "-26-27-30-32-41-42"]
+
+type point = {
+  x : int;
+  y : int;
+}
+
+and shape =
+  | Circle of float
+  | None_
+
+module Make (R : Wai_runtime.S) = struct
+  module Abi = Wai_runtime.Abi (R)
+
+  let greet (inst : R.instance) (name : string) (at : point) : bytes =
+    let (ptr, len) = Abi.store_string inst "canonical_abi_realloc" name in
+    let ({ x = field; y = field0 } : point) = at in
+    let ret = R.call inst "greet" [Wai_runtime.i32 ptr; Wai_runtime.i32 len; Wai_runtime.i32 field; Wai_runtime.i32 field0] in
+    let ret1 = Wai_runtime.as_i32 (List.nth ret 0) in
+    let load = R.load_i32 inst ret1 in
+    let load2 = R.load_i32 inst (ret1 + 4) in
+    let ptr3 = load in
+    let len4 = load2 in
+    let bytes = R.load_bytes inst ptr3 len4 in
+    Abi.free inst "canonical_abi_free" ptr3 len4 1;
+    bytes
+
+  let area (inst : R.instance) (s : shape) : float option =
+    let (variant, variant1) =
+      match (s : shape) with
+      | Circle payload -> (0, payload)
+      | None_ -> (1, 0.0)
+    in
+    let ret = R.call inst "area" [Wai_runtime.i32 variant; Wai_runtime.F64 variant1] in
+    let ret2 = Wai_runtime.as_i32 (List.nth ret 0) in
+    let load = R.load_u8 inst ret2 in
+    let variant4 =
+      match load with
+      | 0 -> None
+      | 1 ->
+        let load3 = R.load_f64 inst (ret2 + 8) in
+        Some load3
+      | _ -> invalid_arg "invalid discriminant of option"
+    in
+    variant4
+end
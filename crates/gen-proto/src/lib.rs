@@ -0,0 +1,463 @@
+use heck::*;
+use std::collections::BTreeMap;
+use std::mem;
+use wai_bindgen_gen_core::{wai_parser::*, Files, Generator, Source};
+
+/// Generates a proto3 schema for the types of an interface, so data passed
+/// to and from plugins can be persisted in protobuf-based pipelines with
+/// the `*.wai` file remaining its one definition.
+///
+/// The mapping is stable, so that data written with the schema generated
+/// for an interface stays readable as the interface grows:
+///
+/// * records and tuples are messages whose fields are numbered from 1 in
+///   order, so fields must only ever be appended to them;
+/// * variants, unions and `expected` are messages with a single `oneof`
+///   whose cases are numbered from 1 in order. Union cases are named
+///   `case0`, `case1`, and so on, and `expected` cases `ok` and `err`;
+/// * enums map to enums whose values are the index of their case;
+/// * flags are messages with a `bool` field per flag, numbered from 1;
+/// * `u8`, `u16`, `u32` and `char` map to `uint32`, `s8`, `s16` and `s32`
+///   to `int32`, and `unit` to `google.protobuf.Empty`;
+/// * `list<u8>` maps to `bytes`, other lists to `repeated` fields, and
+///   options to `optional` fields;
+/// * aliases, including named lists and options, are replaced with the
+///   type they stand for.
+///
+/// Types which can't be written as a field on their own, such as lists of
+/// lists or anonymous tuples, are wrapped in messages named after their
+/// structure, like `ListString` or `TupleU32String`, whose field is
+/// `value`, or `f0`, `f1`, ... for tuples.
+///
+/// With [`Opts::service`] the functions of the interface are also described
+/// as the methods of a `service` named after it:
+///
+/// * each function `f` is an `rpc F(FRequest) returns (FResponse)`, where
+///   `FRequest` has a field per parameter, numbered from 1 in order, and
+///   `FResponse` a `result` field unless the function returns `unit`;
+/// * a function returning a list, other than `list<u8>`, streams its
+///   response instead, with `FResponse` carrying one element as `result`;
+/// * likewise a function whose only parameter is such a list streams its
+///   request, with `FRequest` carrying one element per message.
+#[derive(Default)]
+pub struct Proto {
+    src: Source,
+    opts: Opts,
+    /// Messages wrapping anonymous types, by name.
+    wrappers: BTreeMap<String, String>,
+    /// Methods of the service describing the interface's functions.
+    rpcs: String,
+    uses_empty: bool,
+}
+
+#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "structopt", derive(structopt::StructOpt))]
+pub struct Opts {
+    /// The protobuf package to generate, which defaults to the name of the
+    /// interface.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub package: Option<String>,
+
+    /// Also generate a service with a method per function of the interface.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub service: bool,
+}
+
+impl Opts {
+    pub fn build(self) -> Proto {
+        let mut r = Proto::new();
+        r.opts = self;
+        r
+    }
+}
+
+/// How a type is declared as a message field.
+#[derive(Copy, Clone, PartialEq)]
+enum Label {
+    None,
+    Optional,
+    Repeated,
+}
+
+impl Proto {
+    pub fn new() -> Proto {
+        Proto::default()
+    }
+
+    /// Returns the protobuf type of a field of type `ty`, along with the
+    /// label it needs.
+    fn field_type(&mut self, iface: &Interface, ty: &Type) -> (Label, String) {
+        let name = match ty {
+            Type::Unit => {
+                self.uses_empty = true;
+                "google.protobuf.Empty"
+            }
+            Type::Bool => "bool",
+            Type::U8 | Type::U16 | Type::U32 | Type::Char => "uint32",
+            Type::U64 => "uint64",
+            Type::S8 | Type::S16 | Type::S32 => "int32",
+            Type::S64 => "int64",
+            Type::Float32 => "float",
+            Type::Float64 => "double",
+            Type::String => "string",
+            Type::Handle(_) => panic!("protobuf schemas don't support resources"),
+            Type::Id(id) => {
+                let ty = &iface.types[*id];
+                return match &ty.kind {
+                    TypeDefKind::Type(t) => self.field_type(iface, t),
+                    TypeDefKind::List(Type::U8) => (Label::None, "bytes".to_string()),
+                    TypeDefKind::List(t) => (Label::Repeated, self.plain_type(iface, t)),
+                    TypeDefKind::Option(t) => (Label::Optional, self.plain_type(iface, t)),
+                    _ => match &ty.name {
+                        Some(name) => (Label::None, name.to_camel_case()),
+                        None => (Label::None, self.anonymous_message(iface, &ty.kind)),
+                    },
+                };
+            }
+        };
+        (Label::None, name.to_string())
+    }
+
+    /// Returns the protobuf type of `ty` where it can't have a label, such
+    /// as in a `oneof` or as the element of a list, wrapping it in a
+    /// message if needed.
+    fn plain_type(&mut self, iface: &Interface, ty: &Type) -> String {
+        let (label, field_ty) = self.field_type(iface, ty);
+        if label == Label::None {
+            return field_ty;
+        }
+        let name = structural_name(iface, ty);
+        if !self.wrappers.contains_key(&name) {
+            let body = field(label, &field_ty, "value", 1);
+            self.wrappers.insert(name.clone(), message(&name, &body));
+        }
+        name
+    }
+
+    /// Returns the name of the message standing for an anonymous tuple or
+    /// `expected`, defining it if it's the first use.
+    fn anonymous_message(&mut self, iface: &Interface, kind: &TypeDefKind) -> String {
+        let name = match kind {
+            TypeDefKind::Tuple(t) => format!("Tuple{}", structural_names(iface, &t.types)),
+            TypeDefKind::Expected(e) => {
+                format!("Expected{}", structural_names(iface, &[e.ok, e.err]))
+            }
+            _ => unreachable!(),
+        };
+        if !self.wrappers.contains_key(&name) {
+            let body = self.message_body(iface, kind);
+            self.wrappers.insert(name.clone(), message(&name, &body));
+        }
+        name
+    }
+
+    /// Returns the fields of the message standing for a type of `kind`.
+    fn message_body(&mut self, iface: &Interface, kind: &TypeDefKind) -> String {
+        let mut body = String::new();
+        match kind {
+            TypeDefKind::Record(r) => {
+                for (i, f) in r.fields.iter().enumerate() {
+                    let (label, ty) = self.field_type(iface, &f.ty);
+                    body.push_str(&comment(&f.docs));
+                    body.push_str(&field(label, &ty, &f.name.to_snake_case(), i + 1));
+                }
+            }
+            TypeDefKind::Tuple(t) => {
+                for (i, ty) in t.types.iter().enumerate() {
+                    let (label, ty) = self.field_type(iface, ty);
+                    body.push_str(&field(label, &ty, &format!("f{i}"), i + 1));
+                }
+            }
+            TypeDefKind::Flags(f) => {
+                for (i, flag) in f.flags.iter().enumerate() {
+                    body.push_str(&comment(&flag.docs));
+                    body.push_str(&field(
+                        Label::None,
+                        "bool",
+                        &flag.name.to_snake_case(),
+                        i + 1,
+                    ));
+                }
+            }
+            TypeDefKind::Variant(v) => {
+                body.push_str("oneof case {\n");
+                for (i, case) in v.cases.iter().enumerate() {
+                    let ty = self.plain_type(iface, &case.ty);
+                    body.push_str(&comment(&case.docs));
+                    body.push_str(&field(Label::None, &ty, &case.name.to_snake_case(), i + 1));
+                }
+                body.push_str("}\n");
+            }
+            TypeDefKind::Union(u) => {
+                body.push_str("oneof case {\n");
+                for (i, case) in u.cases.iter().enumerate() {
+                    let ty = self.plain_type(iface, &case.ty);
+                    body.push_str(&comment(&case.docs));
+                    body.push_str(&field(Label::None, &ty, &format!("case{i}"), i + 1));
+                }
+                body.push_str("}\n");
+            }
+            TypeDefKind::Expected(e) => {
+                let ok = self.plain_type(iface, &e.ok);
+                let err = self.plain_type(iface, &e.err);
+                body.push_str("oneof result {\n");
+                body.push_str(&field(Label::None, &ok, "ok", 1));
+                body.push_str(&field(Label::None, &err, "err", 2));
+                body.push_str("}\n");
+            }
+            _ => unreachable!(),
+        }
+        body
+    }
+
+    /// Returns the element type of `ty` if it's a list which is streamed
+    /// when passed to or returned from a method.
+    fn streamed_element(iface: &Interface, ty: &Type) -> Option<Type> {
+        match ty {
+            Type::Id(id) => match &iface.types[*id].kind {
+                TypeDefKind::Type(t) => Proto::streamed_element(iface, t),
+                TypeDefKind::List(Type::U8) => None,
+                TypeDefKind::List(t) => Some(*t),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn print_rpc(&mut self, iface: &Interface, func: &Function) {
+        let name = func.name.to_camel_case();
+
+        let mut request = String::new();
+        let request_stream = match &func.params[..] {
+            [(param, ty)] => Proto::streamed_element(iface, ty).map(|elem| (param, elem)),
+            _ => None,
+        };
+        match request_stream {
+            Some((param, elem)) => {
+                let ty = self.plain_type(iface, &elem);
+                request.push_str(&field(Label::None, &ty, &param.to_snake_case(), 1));
+            }
+            None => {
+                for (i, (param, ty)) in func.params.iter().enumerate() {
+                    let (label, ty) = self.field_type(iface, ty);
+                    request.push_str(&field(label, &ty, &param.to_snake_case(), i + 1));
+                }
+            }
+        }
+
+        let mut response = String::new();
+        let response_stream = Proto::streamed_element(iface, &func.result);
+        match response_stream {
+            Some(elem) => {
+                let ty = self.plain_type(iface, &elem);
+                response.push_str(&field(Label::None, &ty, "result", 1));
+            }
+            None if func.result == Type::Unit => {}
+            None => {
+                let (label, ty) = self.field_type(iface, &func.result);
+                response.push_str(&field(label, &ty, "result", 1));
+            }
+        }
+
+        self.src
+            .push_str(&message(&format!("{name}Request"), &request));
+        self.src
+            .push_str(&message(&format!("{name}Response"), &response));
+
+        let stream = |streamed: bool| if streamed { "stream " } else { "" };
+        self.rpcs.push_str(&comment(&func.docs));
+        self.rpcs.push_str(&format!(
+            "rpc {name}({}{name}Request) returns ({}{name}Response);\n",
+            stream(request_stream.is_some()),
+            stream(response_stream.is_some()),
+        ));
+    }
+
+    fn print_message(&mut self, iface: &Interface, id: TypeId, name: &str, docs: &Docs) {
+        let body = self.message_body(iface, &iface.types[id].kind);
+        self.src.push_str(&comment(docs));
+        self.src.push_str(&message(&name.to_camel_case(), &body));
+    }
+}
+
+/// Returns the name a message wrapping `ty` gets.
+fn structural_name(iface: &Interface, ty: &Type) -> String {
+    match ty {
+        Type::Unit => "Unit".to_string(),
+        Type::Bool => "Bool".to_string(),
+        Type::U8 => "U8".to_string(),
+        Type::U16 => "U16".to_string(),
+        Type::U32 => "U32".to_string(),
+        Type::U64 => "U64".to_string(),
+        Type::S8 => "S8".to_string(),
+        Type::S16 => "S16".to_string(),
+        Type::S32 => "S32".to_string(),
+        Type::S64 => "S64".to_string(),
+        Type::Float32 => "Float32".to_string(),
+        Type::Float64 => "Float64".to_string(),
+        Type::Char => "Char".to_string(),
+        Type::String => "String".to_string(),
+        Type::Handle(_) => unreachable!(),
+        Type::Id(id) => {
+            let ty = &iface.types[*id];
+            if let Some(name) = &ty.name {
+                return name.to_camel_case();
+            }
+            match &ty.kind {
+                TypeDefKind::Type(t) => structural_name(iface, t),
+                TypeDefKind::List(t) => format!("List{}", structural_name(iface, t)),
+                TypeDefKind::Option(t) => format!("Option{}", structural_name(iface, t)),
+                TypeDefKind::Expected(e) => {
+                    format!("Expected{}", structural_names(iface, &[e.ok, e.err]))
+                }
+                TypeDefKind::Tuple(t) => format!("Tuple{}", structural_names(iface, &t.types)),
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+fn structural_names(iface: &Interface, tys: &[Type]) -> String {
+    tys.iter().map(|ty| structural_name(iface, ty)).collect()
+}
+
+fn field(label: Label, ty: &str, name: &str, number: usize) -> String {
+    let label = match label {
+        Label::None => "",
+        Label::Optional => "optional ",
+        Label::Repeated => "repeated ",
+    };
+    format!("{label}{ty} {name} = {number};\n")
+}
+
+fn message(name: &str, body: &str) -> String {
+    format!("message {name} {{\n{body}}}\n\n")
+}
+
+fn comment(docs: &Docs) -> String {
+    match &docs.contents {
+        Some(docs) => docs.trim().lines().map(|l| format!("// {l}\n")).collect(),
+        None => String::new(),
+    }
+}
+
+impl Generator for Proto {
+    fn type_record(&mut self, iface: &Interface, id: TypeId, name: &str, _: &Record, docs: &Docs) {
+        self.print_message(iface, id, name, docs);
+    }
+
+    fn type_tuple(&mut self, iface: &Interface, id: TypeId, name: &str, _: &Tuple, docs: &Docs) {
+        self.print_message(iface, id, name, docs);
+    }
+
+    fn type_flags(&mut self, iface: &Interface, id: TypeId, name: &str, _: &Flags, docs: &Docs) {
+        self.print_message(iface, id, name, docs);
+    }
+
+    fn type_variant(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        name: &str,
+        _variant: &Variant,
+        docs: &Docs,
+    ) {
+        self.print_message(iface, id, name, docs);
+    }
+
+    fn type_union(&mut self, iface: &Interface, id: TypeId, name: &str, _: &Union, docs: &Docs) {
+        self.print_message(iface, id, name, docs);
+    }
+
+    fn type_option(&mut self, _: &Interface, _: TypeId, _: &str, _: &Type, _: &Docs) {
+        // Options are `optional` fields of the type they wrap.
+    }
+
+    fn type_expected(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        name: &str,
+        _expected: &Expected,
+        docs: &Docs,
+    ) {
+        self.print_message(iface, id, name, docs);
+    }
+
+    fn type_enum(&mut self, _: &Interface, _: TypeId, name: &str, enum_: &Enum, docs: &Docs) {
+        let prefix = name.to_shouty_snake_case();
+        self.src.push_str(&comment(docs));
+        self.src
+            .push_str(&format!("enum {} {{\n", name.to_camel_case()));
+        for (i, case) in enum_.cases.iter().enumerate() {
+            self.src.push_str(&comment(&case.docs));
+            self.src.push_str(&format!(
+                "{prefix}_{} = {i};\n",
+                case.name.to_shouty_snake_case()
+            ));
+        }
+        self.src.push_str("}\n\n");
+    }
+
+    fn type_resource(&mut self, _: &Interface, _: ResourceId) {
+        panic!("protobuf schemas don't support resources");
+    }
+
+    fn type_alias(&mut self, _: &Interface, _: TypeId, _: &str, _: &Type, _: &Docs) {
+        // Protobuf has no aliases, so uses of this type are replaced by
+        // uses of the type it aliases.
+    }
+
+    fn type_list(&mut self, _: &Interface, _: TypeId, _: &str, _: &Type, _: &Docs) {
+        // Lists are `repeated` fields of their element type.
+    }
+
+    fn type_builtin(&mut self, _: &Interface, _: TypeId, _: &str, _: &Type, _: &Docs) {
+        // Like aliases.
+    }
+
+    fn import(&mut self, iface: &Interface, func: &Function) {
+        if self.opts.service {
+            self.print_rpc(iface, func);
+        }
+    }
+
+    fn export(&mut self, iface: &Interface, func: &Function) {
+        if self.opts.service {
+            self.print_rpc(iface, func);
+        }
+    }
+
+    fn finish_one(&mut self, iface: &Interface, files: &mut Files) {
+        let package = match &self.opts.package {
+            Some(package) => package.clone(),
+            None => iface.name.to_snake_case(),
+        };
+        let mut src = Source::default();
+        src.push_str(&format!(
+            "// Generated by `wai-bindgen` from the `{}` interface.\n\n",
+            iface.name
+        ));
+        src.push_str("syntax = \"proto3\";\n\n");
+        src.push_str(&format!("package {package};\n\n"));
+        if mem::take(&mut self.uses_empty) {
+            src.push_str("import \"google/protobuf/empty.proto\";\n\n");
+        }
+        src.push_str(&String::from(mem::take(&mut self.src)));
+        let rpcs = mem::take(&mut self.rpcs);
+        if !rpcs.is_empty() {
+            src.push_str(&format!(
+                "service {} {{\n{rpcs}}}\n\n",
+                iface.name.to_camel_case()
+            ));
+        }
+        for (_, wrapper) in mem::take(&mut self.wrappers) {
+            src.push_str(&wrapper);
+        }
+
+        let mut src = String::from(src);
+        src.truncate(src.trim_end().len());
+        src.push('\n');
+        files.push_for_interface(&iface.name.to_snake_case(), "proto", src.as_bytes());
+    }
+}
@@ -0,0 +1,105 @@
+use std::path::Path;
+use wai_bindgen_gen_core::{wai_parser::Interface, Files, Generator};
+
+fn generate(iface: Interface) -> String {
+    generate_with(wai_bindgen_gen_proto::Opts::default(), iface)
+}
+
+fn generate_with(opts: wai_bindgen_gen_proto::Opts, iface: Interface) -> String {
+    let mut files = Files::default();
+    opts.build().generate_all(&[iface], &[], &mut files);
+    let (_, contents) = files.iter().next().unwrap();
+    String::from_utf8(contents.to_vec()).unwrap()
+}
+
+#[test]
+fn runtime_interfaces() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/runtime");
+    for entry in std::fs::read_dir(&root).unwrap() {
+        let path = entry.unwrap().path().join("exports.wai");
+        if !path.exists() {
+            continue;
+        }
+        let iface = Interface::parse_file(&path).unwrap();
+        if iface.resources.len() > 0 {
+            continue;
+        }
+        let proto = generate(iface);
+        assert!(proto.contains("syntax = \"proto3\";"), "{}", path.display());
+    }
+}
+
+#[test]
+fn wrapped_types() {
+    let iface = Interface::parse(
+        "api",
+        "
+            record r {
+                a: option<list<string>>,
+                b: list<list<u8>>,
+                c: tuple<u32, char>,
+            }
+        ",
+    )
+    .unwrap();
+
+    let proto = generate(iface);
+    assert!(proto.contains("optional ListString a = 1;"), "{proto}");
+    assert!(proto.contains("repeated bytes b = 2;"), "{proto}");
+    assert!(proto.contains("TupleU32Char c = 3;"), "{proto}");
+    assert!(
+        proto.contains("message ListString {\n  repeated string value = 1;\n}"),
+        "{proto}"
+    );
+    assert!(
+        proto.contains("message TupleU32Char {\n  uint32 f0 = 1;\n  uint32 f1 = 2;\n}"),
+        "{proto}"
+    );
+}
+
+#[test]
+fn service() {
+    let iface = Interface::parse(
+        "greeter",
+        "
+            /// Greets someone.
+            greet: func(name: string, times: u32) -> list<string>
+            upload: func(chunks: list<list<u8>>)
+            checksum: func(data: list<u8>) -> u32
+        ",
+    )
+    .unwrap();
+
+    let opts = wai_bindgen_gen_proto::Opts {
+        service: true,
+        ..Default::default()
+    };
+    let proto = generate_with(opts, iface);
+    assert!(
+        proto.contains(
+            "service Greeter {\n  // Greets someone.\n  rpc Greet(GreetRequest) returns (stream GreetResponse);\n"
+        ),
+        "{proto}"
+    );
+    assert!(
+        proto.contains("message GreetRequest {\n  string name = 1;\n  uint32 times = 2;\n}"),
+        "{proto}"
+    );
+    assert!(
+        proto.contains("message GreetResponse {\n  string result = 1;\n}"),
+        "{proto}"
+    );
+    assert!(
+        proto.contains("rpc Upload(stream UploadRequest) returns (UploadResponse);"),
+        "{proto}"
+    );
+    assert!(
+        proto.contains("message UploadRequest {\n  bytes chunks = 1;\n}"),
+        "{proto}"
+    );
+    assert!(proto.contains("message UploadResponse {\n}"), "{proto}");
+    assert!(
+        proto.contains("rpc Checksum(ChecksumRequest) returns (ChecksumResponse);"),
+        "{proto}"
+    );
+}
@@ -0,0 +1,1653 @@
+//! Bindings for Python guests running on MicroPython compiled to wasm.
+//!
+//! For each interface this emits a `<interface>.py` module, which is plain
+//! Python doing all of the lifting and lowering of the canonical ABI, and a
+//! `_<interface>.c` MicroPython native module holding the raw wasm imports
+//! and exports the Python module is glued to. Both build on a shared `_wai`
+//! native module, in `_wai.c`, with the memory accessors, allocator and
+//! return area of the bindings. A `micropython.mk` lists the C files so that
+//! the output directory can be passed to a MicroPython build as
+//! `USER_C_MODULES`.
+//!
+//! The embedding port is expected to initialize the interpreter before any
+//! export is called, and to make the generated `*.py` modules importable,
+//! typically by freezing them into the firmware. It needs to be built with
+//! long integer support for 64-bit values.
+
+use heck::*;
+use std::fmt::Write;
+use std::mem;
+use wai_bindgen_gen_core::wai_parser::abi::{
+    AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmType,
+};
+use wai_bindgen_gen_core::{
+    union_case_names, uwrite, uwriteln, wai_parser::*, Direction, Files, Generator, Ns,
+};
+
+#[derive(Default)]
+pub struct PythonWasm {
+    opts: Opts,
+    sizes: SizeAlign,
+    in_import: bool,
+    // The classes of the types of the current interface.
+    types: PySource,
+    // The Python functions of the current interface.
+    funcs: PySource,
+    // The raw wasm imports and exports of the current interface, and the
+    // entries of the globals table of its native module.
+    c: wai_bindgen_gen_core::Source,
+    c_globals: Vec<String>,
+    // The native modules generated so far, for `micropython.mk`.
+    modules: Vec<String>,
+    return_pointer_area_size: usize,
+    return_pointer_area_align: usize,
+}
+
+#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "structopt", derive(structopt::StructOpt))]
+pub struct Opts {
+    // ...
+}
+
+impl Opts {
+    pub fn build(&self) -> PythonWasm {
+        let mut r = PythonWasm::new();
+        r.opts = self.clone();
+        r
+    }
+}
+
+impl PythonWasm {
+    pub fn new() -> PythonWasm {
+        PythonWasm::default()
+    }
+
+    fn abi_variant(dir: Direction) -> AbiVariant {
+        // This generator uses the obvious direction to ABI variant mapping.
+        match dir {
+            Direction::Export => AbiVariant::GuestExport,
+            Direction::Import => AbiVariant::GuestImport,
+        }
+    }
+
+    /// Prints a class holding the fields `fields`, with equality and a
+    /// `repr` through the `_Record` base class.
+    fn print_record_class(&mut self, name: &str, fields: &[String], docs: &Docs) {
+        uwriteln!(self.types, "class {}(_Record):", name);
+        self.types.indent();
+        self.types.docstring(docs);
+        let quoted = fields
+            .iter()
+            .map(|f| format!("\"{}\"", f))
+            .collect::<Vec<_>>();
+        match quoted.len() {
+            1 => uwriteln!(self.types, "_fields = ({},)\n", quoted[0]),
+            _ => uwriteln!(self.types, "_fields = ({})\n", quoted.join(", ")),
+        }
+        self.types.push_str("def __init__(self");
+        for field in fields {
+            uwrite!(self.types, ", {}", field);
+        }
+        if fields.len() == 1 && fields[0] == "value" {
+            // Cases without a payload are constructed without arguments.
+            self.types.push_str("=None");
+        }
+        self.types.push_str("):\n");
+        self.types.indent();
+        if fields.is_empty() {
+            self.types.push_str("pass\n");
+        }
+        for field in fields {
+            uwriteln!(self.types, "self.{0} = {0}", field);
+        }
+        self.types.dedent();
+        self.types.dedent();
+        self.types.push_str("\n\n");
+    }
+
+    /// Prints a class of integer constants named `names`.
+    fn print_constants(&mut self, name: &str, names: &[(String, String)], docs: &Docs) {
+        uwriteln!(self.types, "class {}:", name);
+        self.types.indent();
+        self.types.docstring(docs);
+        if names.is_empty() && docs.contents.is_none() {
+            self.types.push_str("pass\n");
+        }
+        for (name, value) in names {
+            uwriteln!(self.types, "{} = {}", name, value);
+        }
+        self.types.dedent();
+        self.types.push_str("\n\n");
+    }
+
+    fn print_intrinsics(&mut self) -> String {
+        let mut src = PySource::default();
+        src.push_str(
+            "
+                class _Record:
+                    _fields = ()
+
+                    def __eq__(self, other):
+                        return type(self) is type(other) and all(
+                            getattr(self, f) == getattr(other, f) for f in self._fields
+                        )
+
+                    def __repr__(self):
+                        return \"%s(%s)\" % (
+                            type(self).__name__,
+                            \", \".join(\"%s=%r\" % (f, getattr(self, f)) for f in self._fields),
+                        )
+
+
+                class Ok(_Record):
+                    _fields = (\"value\",)
+
+                    def __init__(self, value=None):
+                        self.value = value
+
+
+                class Err(_Record):
+                    _fields = (\"value\",)
+
+                    def __init__(self, value=None):
+                        self.value = value
+
+
+            ",
+        );
+        src.into()
+    }
+}
+
+impl Generator for PythonWasm {
+    fn preprocess_one(&mut self, iface: &Interface, dir: Direction) {
+        assert!(
+            iface.resources.len() == 0,
+            "resources not supported yet by Python guests"
+        );
+        let variant = Self::abi_variant(dir);
+        self.sizes.fill(iface);
+        self.in_import = variant == AbiVariant::GuestImport;
+    }
+
+    fn type_record(
+        &mut self,
+        _iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        record: &Record,
+        docs: &Docs,
+    ) {
+        let fields = record
+            .fields
+            .iter()
+            .map(|f| py_ident(&f.name.to_snake_case()))
+            .collect::<Vec<_>>();
+        self.print_record_class(&py_ident(&name.to_camel_case()), &fields, docs);
+    }
+
+    fn type_tuple(
+        &mut self,
+        _iface: &Interface,
+        _id: TypeId,
+        _name: &str,
+        _tuple: &Tuple,
+        _docs: &Docs,
+    ) {
+        // Tuples are Python tuples.
+    }
+
+    fn type_flags(
+        &mut self,
+        _iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        flags: &Flags,
+        docs: &Docs,
+    ) {
+        let names = flags
+            .flags
+            .iter()
+            .enumerate()
+            .map(|(i, f)| {
+                (
+                    py_ident(&f.name.to_shouty_snake_case()),
+                    format!("1 << {}", i),
+                )
+            })
+            .collect::<Vec<_>>();
+        self.print_constants(&py_ident(&name.to_camel_case()), &names, docs);
+    }
+
+    fn type_variant(
+        &mut self,
+        _iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        variant: &Variant,
+        docs: &Docs,
+    ) {
+        let name = name.to_camel_case();
+        self.types.comment(docs);
+        for case in variant.cases.iter() {
+            let case_name = py_ident(&format!("{}{}", name, case.name.to_camel_case()));
+            self.print_record_class(&case_name, &["value".to_string()], &case.docs);
+        }
+    }
+
+    fn type_union(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        union: &Union,
+        docs: &Docs,
+    ) {
+        let name = name.to_camel_case();
+        self.types.comment(docs);
+        for (case, case_name) in union.cases.iter().zip(union_case_names(iface, union)) {
+            let case_name = py_ident(&format!("{}{}", name, case_name.to_camel_case()));
+            self.print_record_class(&case_name, &["value".to_string()], &case.docs);
+        }
+    }
+
+    fn type_option(
+        &mut self,
+        _iface: &Interface,
+        _id: TypeId,
+        _name: &str,
+        _payload: &Type,
+        _docs: &Docs,
+    ) {
+        // Options are either `None` or their payload.
+    }
+
+    fn type_expected(
+        &mut self,
+        _iface: &Interface,
+        _id: TypeId,
+        _name: &str,
+        _expected: &Expected,
+        _docs: &Docs,
+    ) {
+        // Expected values are `Ok` or `Err`.
+    }
+
+    fn type_enum(
+        &mut self,
+        _iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        enum_: &Enum,
+        docs: &Docs,
+    ) {
+        let names = enum_
+            .cases
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (py_ident(&c.name.to_shouty_snake_case()), i.to_string()))
+            .collect::<Vec<_>>();
+        self.print_constants(&py_ident(&name.to_camel_case()), &names, docs);
+    }
+
+    fn type_resource(&mut self, _iface: &Interface, _ty: ResourceId) {
+        unimplemented!("resources not supported yet by Python guests")
+    }
+
+    fn type_alias(&mut self, iface: &Interface, _id: TypeId, name: &str, ty: &Type, docs: &Docs) {
+        // Only aliases of types with a class of their own need a name.
+        if let Type::Id(id) = ty {
+            let target = &iface.types[*id];
+            if let Some(target_name) = &target.name {
+                if let TypeDefKind::Record(_) | TypeDefKind::Flags(_) | TypeDefKind::Enum(_) =
+                    target.kind
+                {
+                    self.types.comment(docs);
+                    uwriteln!(
+                        self.types,
+                        "{} = {}\n\n",
+                        py_ident(&name.to_camel_case()),
+                        py_ident(&target_name.to_camel_case())
+                    );
+                }
+            }
+        }
+    }
+
+    fn type_list(
+        &mut self,
+        _iface: &Interface,
+        _id: TypeId,
+        _name: &str,
+        _ty: &Type,
+        _docs: &Docs,
+    ) {
+        // Lists are Python lists, or `bytes` for `list<u8>`.
+    }
+
+    fn type_builtin(&mut self, iface: &Interface, id: TypeId, name: &str, ty: &Type, docs: &Docs) {
+        self.type_alias(iface, id, name, ty, docs);
+    }
+
+    fn import(&mut self, iface: &Interface, func: &Function) {
+        assert!(!func.is_async, "async not supported yet");
+        let sig = iface.wasm_signature(AbiVariant::GuestImport, func);
+        let module = format!("_{}", py_ident(&iface.name.to_snake_case()));
+        let raw_name = py_ident(&func.name.to_snake_case());
+
+        // The native module wraps the actual wasm import, which has the raw
+        // wasm signature, in a function taking and returning Python objects.
+        uwriteln!(
+            self.c,
+            "__attribute__((import_module(\"{}\"), import_name(\"{}\")))",
+            iface.name,
+            func.name
+        );
+        let import_name = format!(
+            "__wasm_import_{}_{}",
+            py_ident(&iface.name.to_snake_case()),
+            py_ident(&func.name.to_snake_case())
+        );
+        match sig.results.len() {
+            0 => self.c.push_str("void"),
+            1 => self.c.push_str(wasm_type(sig.results[0])),
+            _ => unimplemented!("multi-value return not supported"),
+        }
+        uwrite!(self.c, " {}(", import_name);
+        for (i, param) in sig.params.iter().enumerate() {
+            if i > 0 {
+                self.c.push_str(", ");
+            }
+            self.c.push_str(wasm_type(*param));
+        }
+        if sig.params.is_empty() {
+            self.c.push_str("void");
+        }
+        self.c.push_str(");\n\n");
+
+        let wrapper = format!("{}_{}", py_ident(&iface.name.to_snake_case()), raw_name);
+        uwriteln!(
+            self.c,
+            "static mp_obj_t {}(size_t n_args, const mp_obj_t *args) {{",
+            wrapper
+        );
+        let mut call = format!("{}(", import_name);
+        for (i, param) in sig.params.iter().enumerate() {
+            if i > 0 {
+                call.push_str(", ");
+            }
+            call.push_str(&obj_to_wasm(*param, &format!("args[{}]", i)));
+        }
+        call.push(')');
+        match sig.results.first() {
+            None => {
+                uwriteln!(self.c, "{};", call);
+                self.c.push_str("return mp_const_none;\n");
+            }
+            Some(ty) => {
+                uwriteln!(self.c, "{} ret = {};", wasm_type(*ty), call);
+                uwriteln!(self.c, "return {};", wasm_to_obj(*ty, "ret"));
+            }
+        }
+        self.c.push_str("}\n");
+        uwriteln!(
+            self.c,
+            "static MP_DEFINE_CONST_FUN_OBJ_VAR_BETWEEN({0}_obj, {1}, {1}, {0});\n",
+            wrapper,
+            sig.params.len(),
+        );
+        self.c_globals.push(format!(
+            "{{ MP_ROM_QSTR(MP_QSTR_{}), MP_ROM_PTR(&{}_obj) }},",
+            raw_name, wrapper
+        ));
+
+        // The Python function lowers its arguments, calls the wrapper and
+        // lifts its results.
+        let params = func
+            .params
+            .iter()
+            .map(|(name, _)| py_ident(&name.to_snake_case()))
+            .collect::<Vec<_>>();
+        uwriteln!(
+            self.funcs,
+            "def {}({}):",
+            py_ident(&func.name.to_snake_case()),
+            params.join(", ")
+        );
+        self.funcs.indent();
+        self.funcs.docstring(&func.docs);
+        let mut f = FunctionBindgen::new(self, params, module);
+        iface.call(
+            AbiVariant::GuestImport,
+            LiftLower::LowerArgsLiftResults,
+            func,
+            &mut f,
+        );
+        let body = f.finish();
+        self.funcs.push_str(&body);
+        self.funcs.dedent();
+        self.funcs.push_str("\n\n");
+    }
+
+    fn export(&mut self, iface: &Interface, func: &Function) {
+        assert!(!func.is_async, "async not supported yet");
+        let sig = iface.wasm_signature(AbiVariant::GuestExport, func);
+        let entry = format!("_export_{}", py_ident(&func.name.to_snake_case()));
+
+        // The actual wasm export hands its arguments to the Python entry
+        // point of the function, which returns its wasm result.
+        uwriteln!(self.c, "__attribute__((export_name(\"{}\")))", func.name);
+        match sig.results.len() {
+            0 => self.c.push_str("void"),
+            1 => self.c.push_str(wasm_type(sig.results[0])),
+            _ => unimplemented!("multi-value return not supported"),
+        }
+        uwrite!(
+            self.c,
+            " __wasm_export_{}_{}(",
+            py_ident(&iface.name.to_snake_case()),
+            py_ident(&func.name.to_snake_case())
+        );
+        for (i, param) in sig.params.iter().enumerate() {
+            if i > 0 {
+                self.c.push_str(", ");
+            }
+            uwrite!(self.c, "{} arg{}", wasm_type(*param), i);
+        }
+        if sig.params.is_empty() {
+            self.c.push_str("void");
+        }
+        self.c.push_str(") {\n");
+        let args = if sig.params.is_empty() {
+            "NULL".to_string()
+        } else {
+            let args = sig
+                .params
+                .iter()
+                .enumerate()
+                .map(|(i, ty)| wasm_to_obj(*ty, &format!("arg{}", i)))
+                .collect::<Vec<_>>();
+            uwriteln!(self.c, "mp_obj_t args[] = {{ {} }};", args.join(", "));
+            "args".to_string()
+        };
+        let call = format!(
+            "wai_call_export(MP_QSTR_{}, MP_QSTR_{}, {}, {})",
+            py_ident(&iface.name.to_snake_case()),
+            entry,
+            sig.params.len(),
+            args
+        );
+        match sig.results.first() {
+            None => uwriteln!(self.c, "{};", call),
+            Some(ty) => {
+                uwriteln!(self.c, "mp_obj_t ret = {};", call);
+                uwriteln!(self.c, "return {};", obj_to_wasm(*ty, "ret"));
+            }
+        }
+        self.c.push_str("}\n\n");
+
+        let params = (0..sig.params.len())
+            .map(|i| format!("arg{}", i))
+            .collect::<Vec<_>>();
+        uwriteln!(self.funcs, "def {}({}):", entry, params.join(", "));
+        self.funcs.indent();
+        let mut f = FunctionBindgen::new(self, params, String::new());
+        iface.call(
+            AbiVariant::GuestExport,
+            LiftLower::LiftArgsLowerResults,
+            func,
+            &mut f,
+        );
+        let body = f.finish();
+        self.funcs.push_str(&body);
+        self.funcs.dedent();
+        self.funcs.push_str("\n\n");
+    }
+
+    fn finish_one(&mut self, iface: &Interface, files: &mut Files) {
+        let name = py_ident(&iface.name.to_snake_case());
+        let module = format!("_{}", name);
+        let has_module = self.in_import && !self.c_globals.is_empty();
+
+        let mut py = String::new();
+        uwriteln!(
+            py,
+            "# Generated by `wai-bindgen` from the `{}` interface.\n",
+            iface.name
+        );
+        py.push_str("import _wai\n");
+        if has_module {
+            uwriteln!(py, "import {}", module);
+        }
+        py.push_str("\n\n");
+        py.push_str(self.print_intrinsics().trim_start());
+        py.push_str(&mem::take(&mut self.types));
+        if !self.in_import {
+            py.push_str(
+                "\
+_exports = None
+
+
+def set_exports(impl):
+    \"\"\"Registers the implementation of the exports of this interface, any
+    object, like a module, with an attribute for each exported function.\"\"\"
+    global _exports
+    _exports = impl
+
+
+",
+            );
+        }
+        py.push_str(&mem::take(&mut self.funcs));
+        let py = format!("{}\n", py.trim_end());
+        files.push(&format!("{}.py", name), py.as_bytes());
+
+        let mut c = wai_bindgen_gen_core::Source::default();
+        uwrite!(
+            c,
+            "\
+                // Generated by `wai-bindgen` from the `{}` interface.
+                #include <stdint.h>
+                #include \"py/runtime.h\"
+
+                int64_t wai_obj_to_i64(mp_obj_t obj);
+                mp_obj_t wai_call_export(qstr module, qstr name, size_t n_args, const mp_obj_t *args);
+
+            ",
+            iface.name,
+        );
+        c.push_str(mem::take(&mut self.c).as_mut_string());
+        if has_module {
+            uwriteln!(
+                c,
+                "static const mp_rom_map_elem_t {}_globals_table[] = {{",
+                module
+            );
+            uwriteln!(
+                c,
+                "{{ MP_ROM_QSTR(MP_QSTR___name__), MP_ROM_QSTR(MP_QSTR_{}) }},",
+                module
+            );
+            for global in mem::take(&mut self.c_globals) {
+                uwriteln!(c, "{}", global);
+            }
+            c.push_str("};\n");
+            uwrite!(
+                c,
+                "
+                    static MP_DEFINE_CONST_DICT({0}_globals, {0}_globals_table);
+
+                    const mp_obj_module_t {0}_module = {{
+                        .base = {{ &mp_type_module }},
+                        .globals = (mp_obj_dict_t *)&{0}_globals,
+                    }};
+
+                    MP_REGISTER_MODULE(MP_QSTR_{0}, {0}_module);
+                ",
+                module,
+            );
+        }
+        self.c_globals.clear();
+        files.push(&format!("{}.c", module), c.as_mut_string().as_bytes());
+        self.modules.push(module);
+    }
+
+    fn finish_all(&mut self, files: &mut Files) {
+        let mut c = wai_bindgen_gen_core::Source::default();
+        uwrite!(
+            c,
+            "
+                // Generated by `wai-bindgen`: the runtime support of the
+                // bindings of all interfaces.
+                #include <stdint.h>
+                #include <stdlib.h>
+                #include <string.h>
+                #include \"py/runtime.h\"
+
+                // Note that these intrinsics are declared as `weak` so they can be
+                // overridden from some other symbol.
+                __attribute__((weak, export_name(\"canonical_abi_realloc\")))
+                void *canonical_abi_realloc(void *ptr, size_t orig_size, size_t align, size_t new_size) {{
+                    // Empty allocations get a dangling pointer which is never freed.
+                    if (new_size == 0) {{
+                        return (void *)align;
+                    }}
+                    void *ret = realloc(orig_size == 0 ? NULL : ptr, new_size);
+                    if (!ret) {{
+                        abort();
+                    }}
+                    return ret;
+                }}
+
+                __attribute__((weak, export_name(\"canonical_abi_free\")))
+                void canonical_abi_free(void *ptr, size_t size, size_t align) {{
+                    if (size != 0) {{
+                        free(ptr);
+                    }}
+                }}
+
+                __attribute__((aligned({align})))
+                static uint8_t RET_AREA[{size}];
+
+                int64_t wai_obj_to_i64(mp_obj_t obj) {{
+                    if (mp_obj_is_small_int(obj)) {{
+                        return MP_OBJ_SMALL_INT_VALUE(obj);
+                    }}
+                    mp_obj_t lo = mp_binary_op(MP_BINARY_OP_AND, obj, mp_obj_new_int_from_uint(0xffffffff));
+                    mp_obj_t hi = mp_binary_op(MP_BINARY_OP_RSHIFT, obj, MP_OBJ_NEW_SMALL_INT(32));
+                    uint64_t bits = (uint64_t)(uint32_t)mp_obj_get_int_truncated(hi) << 32;
+                    return (int64_t)(bits | (uint32_t)mp_obj_get_int_truncated(lo));
+                }}
+
+                // Calls the Python entry point `name` of an export in `module`, trapping
+                // if it raises since there's no way to report errors to the caller.
+                mp_obj_t wai_call_export(qstr module, qstr name, size_t n_args, const mp_obj_t *args) {{
+                    nlr_buf_t nlr;
+                    if (nlr_push(&nlr) == 0) {{
+                        mp_obj_t mod = mp_import_name(module, mp_const_none, MP_OBJ_NEW_SMALL_INT(0));
+                        mp_obj_t ret = mp_call_function_n_kw(mp_load_attr(mod, name), n_args, 0, args);
+                        nlr_pop();
+                        return ret;
+                    }}
+                    mp_obj_print_exception(&mp_plat_print, MP_OBJ_FROM_PTR(nlr.ret_val));
+                    __builtin_trap();
+                }}
+
+                static uintptr_t wai_ptr(mp_obj_t ptr, mp_obj_t offset) {{
+                    return (uintptr_t)mp_obj_get_int_truncated(ptr) + (uintptr_t)mp_obj_get_int(offset);
+                }}
+            ",
+            size = self.return_pointer_area_size,
+            align = self.return_pointer_area_align.max(1),
+        );
+
+        let mut globals = Vec::new();
+        let mut func =
+            |c: &mut wai_bindgen_gen_core::Source, name: &str, args: usize, body: &str| {
+                let params = match args {
+                    0 => String::from("void"),
+                    _ => (0..args)
+                        .map(|i| format!("mp_obj_t arg{}", i))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                };
+                uwrite!(
+                    c,
+                    "
+                    static mp_obj_t wai_{name}({params}) {{
+                        {body}
+                    }}
+                    static MP_DEFINE_CONST_FUN_OBJ_{args}(wai_{name}_obj, wai_{name});
+                "
+                );
+                globals.push(format!(
+                    "{{ MP_ROM_QSTR(MP_QSTR_{0}), MP_ROM_PTR(&wai_{0}_obj) }},",
+                    name
+                ));
+            };
+        for (name, ty, to_obj) in [
+            ("load_u8", "uint8_t", "mp_obj_new_int"),
+            ("load_s8", "int8_t", "mp_obj_new_int"),
+            ("load_u16", "uint16_t", "mp_obj_new_int"),
+            ("load_s16", "int16_t", "mp_obj_new_int"),
+            ("load_i32", "int32_t", "mp_obj_new_int"),
+            ("load_i64", "int64_t", "mp_obj_new_int_from_ll"),
+            ("load_f32", "float", "mp_obj_new_float"),
+            ("load_f64", "double", "mp_obj_new_float"),
+        ] {
+            let body = format!("return {}(*({} *)wai_ptr(arg0, arg1));", to_obj, ty);
+            func(&mut c, name, 2, &body);
+        }
+        for (name, ty, from_obj) in [
+            ("store_i8", "uint8_t", "mp_obj_get_int_truncated"),
+            ("store_i16", "uint16_t", "mp_obj_get_int_truncated"),
+            ("store_i32", "int32_t", "mp_obj_get_int_truncated"),
+            ("store_i64", "int64_t", "wai_obj_to_i64"),
+            ("store_f32", "float", "mp_obj_get_float"),
+            ("store_f64", "double", "mp_obj_get_float"),
+        ] {
+            let body = format!(
+                "*({ty} *)wai_ptr(arg0, arg1) = ({ty}){}(arg2);\nreturn mp_const_none;",
+                from_obj
+            );
+            func(&mut c, name, 3, &body);
+        }
+        func(
+            &mut c,
+            "load_bytes",
+            2,
+            "return mp_obj_new_bytes((const byte *)mp_obj_get_int_truncated(arg0), mp_obj_get_int(arg1));",
+        );
+        func(
+            &mut c,
+            "load_str",
+            2,
+            "return mp_obj_new_str((const char *)mp_obj_get_int_truncated(arg0), mp_obj_get_int(arg1));",
+        );
+        func(
+            &mut c,
+            "store_bytes",
+            2,
+            "mp_buffer_info_t buf;
+            mp_get_buffer_raise(arg1, &buf, MP_BUFFER_READ);
+            memcpy((void *)mp_obj_get_int_truncated(arg0), buf.buf, buf.len);
+            return mp_const_none;",
+        );
+        func(
+            &mut c,
+            "free",
+            3,
+            "canonical_abi_free((void *)mp_obj_get_int_truncated(arg0), mp_obj_get_int(arg1), mp_obj_get_int(arg2));
+            return mp_const_none;",
+        );
+        func(
+            &mut c,
+            "ret_area",
+            0,
+            "return mp_obj_new_int_from_uint((uintptr_t)&RET_AREA);",
+        );
+        func(
+            &mut c,
+            "f32_from_bits",
+            1,
+            "uint32_t bits = mp_obj_get_int_truncated(arg0);
+            float f;
+            memcpy(&f, &bits, sizeof(f));
+            return mp_obj_new_float(f);",
+        );
+        func(
+            &mut c,
+            "f32_to_bits",
+            1,
+            "float f = mp_obj_get_float(arg0);
+            int32_t bits;
+            memcpy(&bits, &f, sizeof(bits));
+            return mp_obj_new_int(bits);",
+        );
+        func(
+            &mut c,
+            "f64_from_bits",
+            1,
+            "int64_t bits = wai_obj_to_i64(arg0);
+            double f;
+            memcpy(&f, &bits, sizeof(f));
+            return mp_obj_new_float(f);",
+        );
+        func(
+            &mut c,
+            "f64_to_bits",
+            1,
+            "double f = mp_obj_get_float(arg0);
+            int64_t bits;
+            memcpy(&bits, &f, sizeof(bits));
+            return mp_obj_new_int_from_ll(bits);",
+        );
+
+        // `realloc` takes more arguments than fit a fixed-arity function.
+        c.push_str(
+            "
+                static mp_obj_t wai_realloc(size_t n_args, const mp_obj_t *args) {
+                    void *ptr = (void *)mp_obj_get_int_truncated(args[0]);
+                    size_t orig_size = mp_obj_get_int(args[1]);
+                    size_t align = mp_obj_get_int(args[2]);
+                    size_t new_size = mp_obj_get_int(args[3]);
+                    void *ret = canonical_abi_realloc(ptr, orig_size, align, new_size);
+                    return mp_obj_new_int_from_uint((uintptr_t)ret);
+                }
+                static MP_DEFINE_CONST_FUN_OBJ_VAR_BETWEEN(wai_realloc_obj, 4, 4, wai_realloc);
+
+                static const mp_rom_map_elem_t wai_globals_table[] = {
+                { MP_ROM_QSTR(MP_QSTR___name__), MP_ROM_QSTR(MP_QSTR__wai) },
+                { MP_ROM_QSTR(MP_QSTR_realloc), MP_ROM_PTR(&wai_realloc_obj) },
+            ",
+        );
+        for global in globals {
+            uwriteln!(c, "{}", global);
+        }
+        c.push_str(
+            "};
+                static MP_DEFINE_CONST_DICT(wai_globals, wai_globals_table);
+
+                const mp_obj_module_t wai_module = {
+                    .base = { &mp_type_module },
+                    .globals = (mp_obj_dict_t *)&wai_globals,
+                };
+
+                MP_REGISTER_MODULE(MP_QSTR__wai, wai_module);
+            ",
+        );
+        files.push("_wai.c", c.as_mut_string().as_bytes());
+
+        let mut mk = String::from("# Generated by `wai-bindgen`.\nWAI_MOD_DIR := $(USERMOD_DIR)\n");
+        mk.push_str("SRC_USERMOD_C += $(WAI_MOD_DIR)/_wai.c\n");
+        for module in mem::take(&mut self.modules) {
+            uwriteln!(mk, "SRC_USERMOD_C += $(WAI_MOD_DIR)/{}.c", module);
+        }
+        files.push("micropython.mk", mk.as_bytes());
+    }
+}
+
+struct FunctionBindgen<'a> {
+    gen: &'a mut PythonWasm,
+    locals: Ns,
+    src: PySource,
+    block_storage: Vec<PySource>,
+    blocks: Vec<(String, Vec<String>)>,
+    params: Vec<String>,
+    payloads: Vec<String>,
+    // The native module holding the raw imports called.
+    module: String,
+    // Whether memory allocated to lower the arguments of an import needs to
+    // be freed after the call.
+    needs_cleanup: bool,
+}
+
+impl<'a> FunctionBindgen<'a> {
+    fn new(gen: &'a mut PythonWasm, params: Vec<String>, module: String) -> FunctionBindgen<'a> {
+        let mut locals = Ns::default();
+        // Python built-ins used by the generated code.
+        for builtin in ["len", "range", "chr", "ord", "isinstance", "enumerate"] {
+            locals.insert(builtin).unwrap();
+        }
+        for param in params.iter() {
+            locals.insert(param).unwrap();
+        }
+        FunctionBindgen {
+            gen,
+            locals,
+            src: PySource::default(),
+            block_storage: Vec::new(),
+            blocks: Vec::new(),
+            params,
+            payloads: Vec::new(),
+            module,
+            needs_cleanup: false,
+        }
+    }
+
+    fn finish(self) -> String {
+        let mut body = String::new();
+        if self.needs_cleanup {
+            body.push_str("_cleanup = []\n");
+        }
+        body.push_str(&self.src);
+        if body.trim().is_empty() {
+            body.push_str("pass\n");
+        }
+        body
+    }
+
+    fn load(&mut self, ty: &str, offset: i32, operands: &[String], results: &mut Vec<String>) {
+        let tmp = self.locals.tmp("load");
+        uwriteln!(
+            self.src,
+            "{} = _wai.load_{}({}, {})",
+            tmp,
+            ty,
+            operands[0],
+            offset
+        );
+        results.push(tmp);
+    }
+
+    fn store(&mut self, ty: &str, offset: i32, operands: &[String]) {
+        uwriteln!(
+            self.src,
+            "_wai.store_{}({}, {}, {})",
+            ty,
+            operands[1],
+            offset,
+            operands[0]
+        );
+    }
+
+    /// Allocates `size` bytes aligned to `align`, which are freed after the
+    /// call of an import unless they're given away with `realloc`.
+    fn alloc(&mut self, size: &str, align: usize, realloc: Option<&str>) -> String {
+        let ptr = self.locals.tmp("ptr");
+        uwriteln!(
+            self.src,
+            "{} = _wai.realloc(0, 0, {}, {})",
+            ptr,
+            align,
+            size
+        );
+        if realloc.is_none() {
+            self.needs_cleanup = true;
+            uwriteln!(self.src, "_cleanup.append(({}, {}, {}))", ptr, size, align);
+        }
+        ptr
+    }
+
+    /// Prints the body of a branch, assigning its results to `results`.
+    fn branch(&mut self, block: &str, block_results: &[String], results: &[String]) {
+        self.src.indent();
+        self.src.push_str(block);
+        for (result, value) in results.iter().zip(block_results) {
+            uwriteln!(self.src, "{} = {}", result, value);
+        }
+        if block.trim().is_empty() && results.is_empty() {
+            self.src.push_str("pass\n");
+        }
+        self.src.dedent();
+    }
+
+    fn lower_cases(
+        &mut self,
+        op: &str,
+        classes: &[String],
+        result_types: &[WasmType],
+    ) -> Vec<String> {
+        let blocks = self
+            .blocks
+            .drain(self.blocks.len() - classes.len()..)
+            .collect::<Vec<_>>();
+        let payloads = self
+            .payloads
+            .drain(self.payloads.len() - classes.len()..)
+            .collect::<Vec<_>>();
+        let results = result_types
+            .iter()
+            .map(|_| self.locals.tmp("variant"))
+            .collect::<Vec<_>>();
+        for (i, ((class, (block, block_results)), payload)) in
+            classes.iter().zip(blocks).zip(payloads).enumerate()
+        {
+            let keyword = if i == 0 { "if" } else { "elif" };
+            uwriteln!(self.src, "{} isinstance({}, {}):", keyword, op, class);
+            self.src.indent();
+            uwriteln!(self.src, "{} = {}.value", payload, op);
+            self.src.dedent();
+            self.branch(&block, &block_results, &results);
+        }
+        self.src.push_str("else:\n");
+        self.src.indent();
+        uwriteln!(
+            self.src,
+            "raise TypeError(\"expected one of {}\")",
+            classes.join(", ")
+        );
+        self.src.dedent();
+        results
+    }
+
+    fn lift_cases(&mut self, op: &str, classes: &[String]) -> String {
+        let blocks = self
+            .blocks
+            .drain(self.blocks.len() - classes.len()..)
+            .collect::<Vec<_>>();
+        let result = self.locals.tmp("variant");
+        if let [(block, block_results)] = &blocks[..] {
+            self.src.push_str(block);
+            uwriteln!(
+                self.src,
+                "{} = {}({})",
+                result,
+                classes[0],
+                block_results[0]
+            );
+            return result;
+        }
+        for (i, (class, (block, block_results))) in classes.iter().zip(blocks).enumerate() {
+            // The host is trusted to pass a valid discriminant, so the last
+            // case takes all others.
+            if i == 0 {
+                uwriteln!(self.src, "if {} == 0:", op);
+            } else if i == classes.len() - 1 {
+                self.src.push_str("else:\n");
+            } else {
+                uwriteln!(self.src, "elif {} == {}:", op, i);
+            }
+            let value = format!("{}({})", class, block_results[0]);
+            self.branch(&block, &[value], std::slice::from_ref(&result));
+        }
+        result
+    }
+
+    fn case_classes(name: &str, cases: impl Iterator<Item = String>) -> Vec<String> {
+        let name = name.to_camel_case();
+        cases
+            .map(|case| py_ident(&format!("{}{}", name, case.to_camel_case())))
+            .collect()
+    }
+}
+
+impl Bindgen for FunctionBindgen<'_> {
+    type Operand = String;
+
+    fn sizes(&self) -> &SizeAlign {
+        &self.gen.sizes
+    }
+
+    fn push_block(&mut self) {
+        let prev = mem::take(&mut self.src);
+        self.block_storage.push(prev);
+    }
+
+    fn finish_block(&mut self, operands: &mut Vec<String>) {
+        let to_restore = self.block_storage.pop().unwrap();
+        let src = mem::replace(&mut self.src, to_restore);
+        self.blocks.push((src.into(), mem::take(operands)));
+    }
+
+    fn return_pointer(&mut self, _iface: &Interface, size: usize, align: usize) -> String {
+        self.gen.return_pointer_area_size = self.gen.return_pointer_area_size.max(size);
+        self.gen.return_pointer_area_align = self.gen.return_pointer_area_align.max(align);
+        let ptr = self.locals.tmp("ptr");
+        uwriteln!(self.src, "{} = _wai.ret_area()", ptr);
+        ptr
+    }
+
+    fn is_list_canonical(&self, _iface: &Interface, ty: &Type) -> bool {
+        // Only `list<u8>` has a representation of its own, `bytes`.
+        matches!(ty, Type::U8)
+    }
+
+    fn emit(
+        &mut self,
+        iface: &Interface,
+        inst: &Instruction<'_>,
+        operands: &mut Vec<String>,
+        results: &mut Vec<String>,
+    ) {
+        match inst {
+            Instruction::GetArg { nth } => results.push(self.params[*nth].clone()),
+            Instruction::I32Const { val } => results.push(val.to_string()),
+            Instruction::ConstZero { tys } => {
+                for ty in tys.iter() {
+                    match ty {
+                        WasmType::I32 | WasmType::I64 => results.push("0".to_string()),
+                        WasmType::F32 | WasmType::F64 => results.push("0.0".to_string()),
+                    }
+                }
+            }
+
+            // Values are truncated to the width of their wasm type by the
+            // native module, so lowering integers is a no-op.
+            Instruction::I32FromU8
+            | Instruction::I32FromS8
+            | Instruction::I32FromU16
+            | Instruction::I32FromS16
+            | Instruction::I32FromU32
+            | Instruction::I32FromS32
+            | Instruction::I64FromU64
+            | Instruction::I64FromS64
+            | Instruction::S32FromI32
+            | Instruction::S64FromI64
+            | Instruction::Float32FromF32
+            | Instruction::Float64FromF64
+            | Instruction::F32FromFloat32
+            | Instruction::F64FromFloat64 => results.push(operands.pop().unwrap()),
+
+            // Integers coming from wasm are signed, and only their low bits
+            // are meaningful.
+            Instruction::U8FromI32 => results.push(format!("({}) & 0xff", operands[0])),
+            Instruction::U16FromI32 => results.push(format!("({}) & 0xffff", operands[0])),
+            Instruction::U32FromI32 => results.push(format!("({}) & 0xffffffff", operands[0])),
+            Instruction::U64FromI64 => {
+                results.push(format!("({}) & 0xffffffffffffffff", operands[0]))
+            }
+            Instruction::S8FromI32 => {
+                results.push(format!("((({}) & 0xff) ^ 0x80) - 0x80", operands[0]))
+            }
+            Instruction::S16FromI32 => {
+                results.push(format!("((({}) & 0xffff) ^ 0x8000) - 0x8000", operands[0]))
+            }
+
+            Instruction::CharFromI32 => results.push(format!("chr({})", operands[0])),
+            Instruction::I32FromChar => results.push(format!("ord({})", operands[0])),
+
+            Instruction::Bitcasts { casts } => {
+                for (cast, op) in casts.iter().zip(operands) {
+                    let op = match cast {
+                        Bitcast::I32ToF32 => format!("_wai.f32_from_bits({})", op),
+                        Bitcast::F32ToI32 | Bitcast::F32ToI64 => {
+                            format!("_wai.f32_to_bits({})", op)
+                        }
+                        Bitcast::I64ToF64 => format!("_wai.f64_from_bits({})", op),
+                        Bitcast::F64ToI64 => format!("_wai.f64_to_bits({})", op),
+                        Bitcast::I64ToF32 => format!("_wai.f32_from_bits(({}) & 0xffffffff)", op),
+                        Bitcast::I32ToI64 | Bitcast::I64ToI32 | Bitcast::None => op.clone(),
+                    };
+                    results.push(op);
+                }
+            }
+
+            Instruction::UnitLower => {}
+            Instruction::UnitLift => results.push("None".to_string()),
+            Instruction::BoolFromI32 => results.push(format!("({}) != 0", operands[0])),
+            Instruction::I32FromBool => results.push(format!("1 if {} else 0", operands[0])),
+
+            Instruction::I32FromBorrowedHandle { .. }
+            | Instruction::I32FromOwnedHandle { .. }
+            | Instruction::HandleOwnedFromI32 { .. }
+            | Instruction::HandleBorrowedFromI32 { .. } => {
+                unimplemented!("resources not supported yet by Python guests")
+            }
+
+            Instruction::RecordLower { record, .. } => {
+                for field in record.fields.iter() {
+                    results.push(format!(
+                        "({}).{}",
+                        operands[0],
+                        py_ident(&field.name.to_snake_case())
+                    ));
+                }
+            }
+            Instruction::RecordLift { name, .. } => {
+                results.push(format!(
+                    "{}({})",
+                    py_ident(&name.to_camel_case()),
+                    operands.join(", ")
+                ));
+            }
+
+            Instruction::TupleLower { tuple, .. } => {
+                if !tuple.types.is_empty() {
+                    let names = tuple
+                        .types
+                        .iter()
+                        .map(|_| self.locals.tmp("tuple"))
+                        .collect::<Vec<_>>();
+                    uwriteln!(self.src, "({},) = {}", names.join(", "), operands[0]);
+                    results.extend(names);
+                }
+            }
+            Instruction::TupleLift { .. } => {
+                if operands.is_empty() {
+                    results.push("()".to_string());
+                } else {
+                    results.push(format!("({},)", operands.join(", ")));
+                }
+            }
+
+            Instruction::FlagsLower { flags, .. } => match flags.repr().count() {
+                1 => results.push(operands[0].clone()),
+                n => {
+                    let tmp = self.locals.tmp("flags");
+                    uwriteln!(self.src, "{} = {}", tmp, operands[0]);
+                    for i in 0..n {
+                        results.push(format!("({} >> {}) & 0xffffffff", tmp, 32 * i));
+                    }
+                }
+            },
+            Instruction::FlagsLift { .. } => match operands.len() {
+                0 => results.push("0".to_string()),
+                1 => results.push(operands[0].clone()),
+                _ => {
+                    let parts = operands
+                        .iter()
+                        .enumerate()
+                        .map(|(i, op)| format!("(({}) & 0xffffffff) << {}", op, 32 * i))
+                        .collect::<Vec<_>>();
+                    results.push(format!("({})", parts.join(" | ")));
+                }
+            },
+
+            Instruction::VariantPayloadName => {
+                let name = self.locals.tmp("payload");
+                results.push(name.clone());
+                self.payloads.push(name);
+            }
+
+            Instruction::VariantLower {
+                variant,
+                name,
+                results: result_types,
+                ..
+            } => {
+                let classes =
+                    Self::case_classes(name, variant.cases.iter().map(|c| c.name.clone()));
+                let op = self.locals.tmp("variant");
+                uwriteln!(self.src, "{} = {}", op, operands[0]);
+                results.extend(self.lower_cases(&op, &classes, result_types));
+            }
+            Instruction::VariantLift { variant, name, .. } => {
+                let classes =
+                    Self::case_classes(name, variant.cases.iter().map(|c| c.name.clone()));
+                let result = self.lift_cases(&operands[0], &classes);
+                results.push(result);
+            }
+
+            Instruction::UnionLower {
+                union,
+                name,
+                results: result_types,
+                ..
+            } => {
+                let classes = Self::case_classes(name, union_case_names(iface, union).into_iter());
+                let op = self.locals.tmp("union");
+                uwriteln!(self.src, "{} = {}", op, operands[0]);
+                results.extend(self.lower_cases(&op, &classes, result_types));
+            }
+            Instruction::UnionLift { union, name, .. } => {
+                let classes = Self::case_classes(name, union_case_names(iface, union).into_iter());
+                let result = self.lift_cases(&operands[0], &classes);
+                results.push(result);
+            }
+
+            Instruction::EnumLower { .. } | Instruction::EnumLift { .. } => {
+                results.push(operands.pop().unwrap())
+            }
+
+            Instruction::OptionLower {
+                results: result_types,
+                ..
+            } => {
+                let (some, some_results) = self.blocks.pop().unwrap();
+                let (none, none_results) = self.blocks.pop().unwrap();
+                let some_payload = self.payloads.pop().unwrap();
+                let _none_payload = self.payloads.pop().unwrap();
+                let op = self.locals.tmp("option");
+                uwriteln!(self.src, "{} = {}", op, operands[0]);
+                let option_results = result_types
+                    .iter()
+                    .map(|_| self.locals.tmp("option"))
+                    .collect::<Vec<_>>();
+                uwriteln!(self.src, "if {} is None:", op);
+                self.branch(&none, &none_results, &option_results);
+                self.src.push_str("else:\n");
+                self.src.indent();
+                uwriteln!(self.src, "{} = {}", some_payload, op);
+                self.src.dedent();
+                self.branch(&some, &some_results, &option_results);
+                results.extend(option_results);
+            }
+            Instruction::OptionLift { .. } => {
+                let (some, some_results) = self.blocks.pop().unwrap();
+                let (none, none_results) = self.blocks.pop().unwrap();
+                assert!(none_results.len() == 1 && some_results.len() == 1);
+                let result = self.locals.tmp("option");
+                uwriteln!(self.src, "if {} == 0:", operands[0]);
+                self.branch(&none, &["None".to_string()], std::slice::from_ref(&result));
+                self.src.push_str("else:\n");
+                self.branch(&some, &some_results, std::slice::from_ref(&result));
+                results.push(result);
+            }
+
+            Instruction::ExpectedLower {
+                results: result_types,
+                ..
+            } => {
+                let classes = ["Ok".to_string(), "Err".to_string()];
+                let op = self.locals.tmp("expected");
+                uwriteln!(self.src, "{} = {}", op, operands[0]);
+                results.extend(self.lower_cases(&op, &classes, result_types));
+            }
+            Instruction::ExpectedLift { .. } => {
+                let classes = ["Ok".to_string(), "Err".to_string()];
+                let result = self.lift_cases(&operands[0], &classes);
+                results.push(result);
+            }
+
+            Instruction::ListCanonLower { element, realloc } => {
+                let val = self.locals.tmp("val");
+                let len = self.locals.tmp("len");
+                uwriteln!(self.src, "{} = {}", val, operands[0]);
+                uwriteln!(self.src, "{} = len({})", len, val);
+                if let Type::U8 = element {
+                    let ptr = self.alloc(&len, 1, *realloc);
+                    uwriteln!(self.src, "_wai.store_bytes({}, {})", ptr, val);
+                    results.push(ptr);
+                } else {
+                    // Lists of chars are lists of strings of one character.
+                    let ptr = self.alloc(&format!("{} * 4", len), 4, *realloc);
+                    let i = self.locals.tmp("i");
+                    uwriteln!(self.src, "for {} in range({}):", i, len);
+                    self.src.indent();
+                    uwriteln!(
+                        self.src,
+                        "_wai.store_i32({}, {} * 4, ord({}[{}]))",
+                        ptr,
+                        i,
+                        val,
+                        i
+                    );
+                    self.src.dedent();
+                    results.push(ptr);
+                }
+                results.push(len);
+            }
+            Instruction::StringLower { realloc } => {
+                let val = self.locals.tmp("val");
+                let len = self.locals.tmp("len");
+                uwriteln!(self.src, "{} = ({}).encode(\"utf-8\")", val, operands[0]);
+                uwriteln!(self.src, "{} = len({})", len, val);
+                let ptr = self.alloc(&len, 1, *realloc);
+                uwriteln!(self.src, "_wai.store_bytes({}, {})", ptr, val);
+                results.push(ptr);
+                results.push(len);
+            }
+            Instruction::ListLower { element, realloc } => {
+                let (body, body_results) = self.blocks.pop().unwrap();
+                assert!(body_results.is_empty());
+                let depth = self.block_storage.len() + 1;
+                let vec = self.locals.tmp("vec");
+                let len = self.locals.tmp("len");
+                let i = self.locals.tmp("i");
+                let size = self.gen.sizes.size(element);
+                let align = self.gen.sizes.align(element);
+                uwriteln!(self.src, "{} = {}", vec, operands[0]);
+                uwriteln!(self.src, "{} = len({})", len, vec);
+                let ptr = self.alloc(&format!("{} * {}", len, size), align, *realloc);
+                uwriteln!(self.src, "for {} in range({}):", i, len);
+                self.src.indent();
+                uwriteln!(self.src, "_e{} = {}[{}]", depth, vec, i);
+                uwriteln!(self.src, "_base{} = {} + {} * {}", depth, ptr, i, size);
+                self.src.push_str(&body);
+                self.src.dedent();
+                results.push(ptr);
+                results.push(len);
+            }
+
+            Instruction::ListCanonLift { element, free, .. } => {
+                let ptr = self.locals.tmp("ptr");
+                let len = self.locals.tmp("len");
+                let result = self.locals.tmp("items");
+                uwriteln!(self.src, "{} = {}", ptr, operands[0]);
+                uwriteln!(self.src, "{} = {}", len, operands[1]);
+                let (size, align) = if let Type::U8 = element {
+                    uwriteln!(self.src, "{} = _wai.load_bytes({}, {})", result, ptr, len);
+                    (1, 1)
+                } else {
+                    let i = self.locals.tmp("i");
+                    uwriteln!(
+                        self.src,
+                        "{} = [chr(_wai.load_i32({}, {} * 4)) for {} in range({})]",
+                        result,
+                        ptr,
+                        i,
+                        i,
+                        len
+                    );
+                    (4, 4)
+                };
+                if free.is_some() {
+                    uwriteln!(
+                        self.src,
+                        "_wai.free({}, {} * {}, {})",
+                        ptr,
+                        len,
+                        size,
+                        align
+                    );
+                }
+                results.push(result);
+            }
+            Instruction::StringLift { free } => {
+                let result = self.locals.tmp("string");
+                uwriteln!(
+                    self.src,
+                    "{} = _wai.load_str({}, {})",
+                    result,
+                    operands[0],
+                    operands[1]
+                );
+                if free.is_some() {
+                    uwriteln!(self.src, "_wai.free({}, {}, 1)", operands[0], operands[1]);
+                }
+                results.push(result);
+            }
+            Instruction::ListLift { element, free, .. } => {
+                let (body, body_results) = self.blocks.pop().unwrap();
+                let depth = self.block_storage.len() + 1;
+                let ptr = self.locals.tmp("ptr");
+                let len = self.locals.tmp("len");
+                let i = self.locals.tmp("i");
+                let result = self.locals.tmp("items");
+                let size = self.gen.sizes.size(element);
+                let align = self.gen.sizes.align(element);
+                uwriteln!(self.src, "{} = {}", ptr, operands[0]);
+                uwriteln!(self.src, "{} = {}", len, operands[1]);
+                uwriteln!(self.src, "{} = []", result);
+                uwriteln!(self.src, "for {} in range({}):", i, len);
+                self.src.indent();
+                uwriteln!(self.src, "_base{} = {} + {} * {}", depth, ptr, i, size);
+                self.src.push_str(&body);
+                uwriteln!(self.src, "{}.append({})", result, body_results[0]);
+                self.src.dedent();
+                if free.is_some() {
+                    uwriteln!(
+                        self.src,
+                        "_wai.free({}, {} * {}, {})",
+                        ptr,
+                        len,
+                        size,
+                        align
+                    );
+                }
+                results.push(result);
+            }
+
+            Instruction::IterElem { .. } => {
+                results.push(format!("_e{}", self.block_storage.len()));
+            }
+            Instruction::IterBasePointer => {
+                results.push(format!("_base{}", self.block_storage.len()));
+            }
+
+            Instruction::CallWasm { name, sig, .. } => {
+                let call = format!(
+                    "{}.{}({})",
+                    self.module,
+                    py_ident(&name.to_snake_case()),
+                    operands.join(", ")
+                );
+                match sig.results.len() {
+                    0 => uwriteln!(self.src, "{}", call),
+                    1 => {
+                        let ret = self.locals.tmp("ret");
+                        uwriteln!(self.src, "{} = {}", ret, call);
+                        results.push(ret);
+                    }
+                    _ => unimplemented!("multi-value return not supported"),
+                }
+                if self.needs_cleanup {
+                    self.src.push_str("for _ptr in _cleanup:\n");
+                    self.src.indent();
+                    self.src.push_str("_wai.free(_ptr[0], _ptr[1], _ptr[2])\n");
+                    self.src.dedent();
+                }
+            }
+
+            Instruction::CallInterface { func, .. } => {
+                let ret = self.locals.tmp("ret");
+                uwriteln!(
+                    self.src,
+                    "{} = _exports.{}({})",
+                    ret,
+                    py_ident(&func.name.to_snake_case()),
+                    operands.join(", ")
+                );
+                results.push(ret);
+            }
+
+            Instruction::Return { amt, .. } => match amt {
+                0 => {}
+                1 => uwriteln!(self.src, "return {}", operands[0]),
+                _ => unimplemented!("multi-value return not supported"),
+            },
+
+            Instruction::I32Load { offset } => self.load("i32", *offset, operands, results),
+            Instruction::I32Load8U { offset } => self.load("u8", *offset, operands, results),
+            Instruction::I32Load8S { offset } => self.load("s8", *offset, operands, results),
+            Instruction::I32Load16U { offset } => self.load("u16", *offset, operands, results),
+            Instruction::I32Load16S { offset } => self.load("s16", *offset, operands, results),
+            Instruction::I64Load { offset } => self.load("i64", *offset, operands, results),
+            Instruction::F32Load { offset } => self.load("f32", *offset, operands, results),
+            Instruction::F64Load { offset } => self.load("f64", *offset, operands, results),
+            Instruction::I32Store { offset } => self.store("i32", *offset, operands),
+            Instruction::I32Store8 { offset } => self.store("i8", *offset, operands),
+            Instruction::I32Store16 { offset } => self.store("i16", *offset, operands),
+            Instruction::I64Store { offset } => self.store("i64", *offset, operands),
+            Instruction::F32Store { offset } => self.store("f32", *offset, operands),
+            Instruction::F64Store { offset } => self.store("f64", *offset, operands),
+
+            Instruction::Malloc { size, align, .. } => {
+                let ptr = self.locals.tmp("ptr");
+                uwriteln!(
+                    self.src,
+                    "{} = _wai.realloc(0, 0, {}, {})",
+                    ptr,
+                    align,
+                    size
+                );
+                results.push(ptr);
+            }
+            Instruction::Free { size, align, .. } => {
+                uwriteln!(self.src, "_wai.free({}, {}, {})", operands[0], size, align);
+            }
+
+            Instruction::CallWasmAsyncImport { .. }
+            | Instruction::CallWasmAsyncExport { .. }
+            | Instruction::ReturnAsyncExport { .. }
+            | Instruction::ReturnAsyncImport { .. } => {
+                unimplemented!("async not supported yet")
+            }
+        }
+    }
+}
+
+/// Python source, indented by blocks of four spaces.
+#[derive(Default)]
+struct PySource {
+    s: String,
+    indent: usize,
+}
+
+impl PySource {
+    /// Appends `src`, indenting its lines at the current level after
+    /// removing the indentation of its first line from all of them.
+    fn push_str(&mut self, src: &str) {
+        let lines = src.lines().collect::<Vec<_>>();
+        let mut trim = None;
+        for (i, line) in lines.iter().enumerate() {
+            if lines.len() == 1 {
+                self.s.push_str(line);
+            } else if !line.trim().is_empty() {
+                let trim = *trim.get_or_insert_with(|| line.len() - line.trim_start().len());
+                self.s
+                    .push_str(line.get(trim..).unwrap_or(line.trim_start()));
+            }
+            if i != lines.len() - 1 || src.ends_with('\n') {
+                self.newline();
+            }
+        }
+    }
+
+    fn indent(&mut self) {
+        self.indent += 4;
+        self.s.push_str("    ");
+    }
+
+    fn dedent(&mut self) {
+        self.indent -= 4;
+        assert!(self.s.ends_with("    "));
+        self.s.truncate(self.s.len() - 4);
+    }
+
+    fn newline(&mut self) {
+        // Blank lines don't keep the indentation.
+        while self.s.ends_with(' ') {
+            self.s.pop();
+        }
+        self.s.push('\n');
+        for _ in 0..self.indent {
+            self.s.push(' ');
+        }
+    }
+
+    fn comment(&mut self, docs: &Docs) {
+        if let Some(docs) = &docs.contents {
+            for line in docs.trim().lines() {
+                self.push_str(&format!("# {}\n", line.trim()));
+            }
+        }
+    }
+
+    fn docstring(&mut self, docs: &Docs) {
+        if let Some(docs) = &docs.contents {
+            let docs = docs.trim().replace("\"\"\"", "\\\"\\\"\\\"");
+            self.push_str("\"\"\"");
+            for (i, line) in docs.lines().enumerate() {
+                if i > 0 {
+                    self.newline();
+                }
+                self.push_str(line.trim_end());
+            }
+            self.push_str("\"\"\"\n");
+        }
+    }
+}
+
+impl std::fmt::Write for PySource {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl std::ops::Deref for PySource {
+    type Target = str;
+    fn deref(&self) -> &str {
+        &self.s
+    }
+}
+
+impl From<PySource> for String {
+    fn from(s: PySource) -> String {
+        s.s
+    }
+}
+
+/// Escapes names which are keywords in Python, or built-ins the generated
+/// code relies on, and characters outside of ASCII, which MicroPython
+/// doesn't support in names.
+fn py_ident(name: &str) -> String {
+    let mut ret = String::new();
+    for c in name.chars() {
+        if c.is_ascii() {
+            ret.push(c);
+        } else {
+            write!(ret, "_{:x}", u32::from(c)).unwrap();
+        }
+    }
+    match ret.as_str() {
+        "and" | "as" | "assert" | "async" | "await" | "break" | "class" | "continue" | "def"
+        | "del" | "elif" | "else" | "except" | "finally" | "for" | "from" | "global" | "if"
+        | "import" | "in" | "is" | "lambda" | "nonlocal" | "not" | "or" | "pass" | "raise"
+        | "return" | "try" | "while" | "with" | "yield" | "len" | "range" | "chr" | "ord"
+        | "isinstance" | "enumerate" | "type" | "getattr" | "all" | "set_exports" | "None"
+        | "True" | "False" => ret.push('_'),
+        _ => {}
+    }
+    ret
+}
+
+fn wasm_type(ty: WasmType) -> &'static str {
+    match ty {
+        WasmType::I32 => "int32_t",
+        WasmType::I64 => "int64_t",
+        WasmType::F32 => "float",
+        WasmType::F64 => "double",
+    }
+}
+
+/// Converts the Python object `obj` to a value of `ty`.
+fn obj_to_wasm(ty: WasmType, obj: &str) -> String {
+    match ty {
+        WasmType::I32 => format!("(int32_t)mp_obj_get_int_truncated({})", obj),
+        WasmType::I64 => format!("wai_obj_to_i64({})", obj),
+        WasmType::F32 => format!("(float)mp_obj_get_float({})", obj),
+        WasmType::F64 => format!("(double)mp_obj_get_float({})", obj),
+    }
+}
+
+/// Converts the value `val` of `ty` to a Python object.
+fn wasm_to_obj(ty: WasmType, val: &str) -> String {
+    match ty {
+        WasmType::I32 => format!("mp_obj_new_int({})", val),
+        WasmType::I64 => format!("mp_obj_new_int_from_ll({})", val),
+        WasmType::F32 | WasmType::F64 => format!("mp_obj_new_float({})", val),
+    }
+}
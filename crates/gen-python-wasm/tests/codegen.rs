@@ -0,0 +1,128 @@
+use std::path::Path;
+use wai_bindgen_gen_core::{wai_parser::Interface, Files, Generator};
+
+fn generate(imports: &[Interface], exports: &[Interface]) -> Files {
+    let mut files = Files::default();
+    wai_bindgen_gen_python_wasm::Opts::default()
+        .build()
+        .generate_all(imports, exports, &mut files);
+    files
+}
+
+fn file<'a>(files: &'a Files, name: &str) -> &'a str {
+    let (_, contents) = files.iter().find(|(n, _)| *n == name).unwrap();
+    std::str::from_utf8(contents).unwrap()
+}
+
+/// The interface the tests below generate bindings for.
+fn greeter() -> Interface {
+    Interface::parse(
+        "greeter",
+        "
+            record point { x: u32, y: u32 }
+            greet: func(name: string, at: point) -> list<u8>
+        ",
+    )
+    .unwrap()
+}
+
+#[test]
+fn codegen_interfaces() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/codegen");
+    for entry in std::fs::read_dir(&root).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|s| s.to_str()) != Some("wai") {
+            continue;
+        }
+        let iface = Interface::parse_file(&path).unwrap();
+        if iface.resources.len() > 0 || iface.functions.iter().any(|f| f.is_async) {
+            continue;
+        }
+        for (imports, exports) in [(vec![iface.clone()], vec![]), (vec![], vec![iface])] {
+            let files = generate(&imports, &exports);
+            let names = files.iter().map(|(n, _)| n).collect::<Vec<_>>();
+            assert_eq!(names.len(), 4, "{}: {:?}", path.display(), names);
+            assert!(file(&files, "micropython.mk").contains("/_wai.c"));
+        }
+    }
+}
+
+#[test]
+fn imports_and_exports() {
+    let iface = greeter();
+
+    let files = generate(std::slice::from_ref(&iface), &[]);
+    let py = file(&files, "greeter.py");
+    assert!(py.contains("class Point(_Record):"), "{py}");
+    assert!(py.contains("def greet(name, at):"), "{py}");
+    assert!(py.contains("_greeter.greet("), "{py}");
+    let c = file(&files, "_greeter.c");
+    assert!(
+        c.contains("__attribute__((import_module(\"greeter\"), import_name(\"greet\")))"),
+        "{c}"
+    );
+    assert!(
+        c.contains("MP_REGISTER_MODULE(MP_QSTR__greeter, _greeter_module);"),
+        "{c}"
+    );
+
+    let files = generate(&[], &[iface]);
+    let py = file(&files, "greeter.py");
+    assert!(py.contains("def set_exports(impl):"), "{py}");
+    assert!(
+        py.contains("def _export_greet(arg0, arg1, arg2, arg3):"),
+        "{py}"
+    );
+    assert!(py.contains("_exports.greet("), "{py}");
+    let c = file(&files, "_greeter.c");
+    assert!(c.contains("__attribute__((export_name(\"greet\")))"), "{c}");
+    assert!(c.contains("MP_QSTR__export_greet"), "{c}");
+    assert!(!c.contains("MP_REGISTER_MODULE"), "{c}");
+}
+
+/// Checks the bindings generated for `greeter` against the files in
+/// `tests/golden`. Run the test with the environment variable `BLESS` set to
+/// update them.
+#[test]
+fn golden() {
+    let iface = greeter();
+    check_golden("import", &generate(std::slice::from_ref(&iface), &[]));
+    check_golden("export", &generate(&[], &[iface]));
+}
+
+fn check_golden(dir: &str, files: &Files) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(dir);
+    let bless = std::env::var_os("BLESS").is_some();
+    if bless {
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+    }
+    for (name, contents) in files.iter() {
+        let path = dir.join(name);
+        if bless {
+            std::fs::write(&path, contents).unwrap();
+            continue;
+        }
+        let expected = std::fs::read(&path).unwrap_or_else(|e| {
+            panic!(
+                "failed to read {}: {}\nthis can be fixed with BLESS=1",
+                path.display(),
+                e
+            )
+        });
+        assert_eq!(
+            String::from_utf8_lossy(&expected),
+            String::from_utf8_lossy(contents),
+            "{} doesn't match the generated file\nthis can be fixed with BLESS=1",
+            path.display()
+        );
+    }
+    assert_eq!(
+        std::fs::read_dir(&dir).unwrap().count(),
+        files.iter().count(),
+        "{} has files which aren't generated anymore\nthis can be fixed with BLESS=1",
+        dir.display()
+    );
+}
@@ -0,0 +1,14 @@
+// Generated by `wai-bindgen` from the `greeter` interface.
+#include <stdint.h>
+#include "py/runtime.h"
+
+int64_t wai_obj_to_i64(mp_obj_t obj);
+mp_obj_t wai_call_export(qstr module, qstr name, size_t n_args, const mp_obj_t *args);
+
+__attribute__((export_name("greet")))
+int32_t __wasm_export_greeter_greet(int32_t arg0, int32_t arg1, int32_t arg2, int32_t arg3) {
+  mp_obj_t args[] = { mp_obj_new_int(arg0), mp_obj_new_int(arg1), mp_obj_new_int(arg2), mp_obj_new_int(arg3) };
+  mp_obj_t ret = wai_call_export(MP_QSTR_greeter, MP_QSTR__export_greet, 4, args);
+  return (int32_t)mp_obj_get_int_truncated(ret);
+}
+
@@ -0,0 +1,243 @@
+
+// Generated by `wai-bindgen`: the runtime support of the
+// bindings of all interfaces.
+#include <stdint.h>
+#include <stdlib.h>
+#include <string.h>
+#include "py/runtime.h"
+
+// Note that these intrinsics are declared as `weak` so they can be
+// overridden from some other symbol.
+__attribute__((weak, export_name("canonical_abi_realloc")))
+void *canonical_abi_realloc(void *ptr, size_t orig_size, size_t align, size_t new_size) {
+  // Empty allocations get a dangling pointer which is never freed.
+  if (new_size == 0) {
+    return (void *)align;
+  }
+  void *ret = realloc(orig_size == 0 ? NULL : ptr, new_size);
+  if (!ret) {
+    abort();
+  }
+  return ret;
+}
+
+__attribute__((weak, export_name("canonical_abi_free")))
+void canonical_abi_free(void *ptr, size_t size, size_t align) {
+  if (size != 0) {
+    free(ptr);
+  }
+}
+
+__attribute__((aligned(4)))
+static uint8_t RET_AREA[8];
+
+int64_t wai_obj_to_i64(mp_obj_t obj) {
+  if (mp_obj_is_small_int(obj)) {
+    return MP_OBJ_SMALL_INT_VALUE(obj);
+  }
+  mp_obj_t lo = mp_binary_op(MP_BINARY_OP_AND, obj, mp_obj_new_int_from_uint(0xffffffff));
+  mp_obj_t hi = mp_binary_op(MP_BINARY_OP_RSHIFT, obj, MP_OBJ_NEW_SMALL_INT(32));
+  uint64_t bits = (uint64_t)(uint32_t)mp_obj_get_int_truncated(hi) << 32;
+  return (int64_t)(bits | (uint32_t)mp_obj_get_int_truncated(lo));
+}
+
+// Calls the Python entry point `name` of an export in `module`, trapping
+// if it raises since there's no way to report errors to the caller.
+mp_obj_t wai_call_export(qstr module, qstr name, size_t n_args, const mp_obj_t *args) {
+  nlr_buf_t nlr;
+  if (nlr_push(&nlr) == 0) {
+    mp_obj_t mod = mp_import_name(module, mp_const_none, MP_OBJ_NEW_SMALL_INT(0));
+    mp_obj_t ret = mp_call_function_n_kw(mp_load_attr(mod, name), n_args, 0, args);
+    nlr_pop();
+    return ret;
+  }
+  mp_obj_print_exception(&mp_plat_print, MP_OBJ_FROM_PTR(nlr.ret_val));
+  __builtin_trap();
+}
+
+static uintptr_t wai_ptr(mp_obj_t ptr, mp_obj_t offset) {
+  return (uintptr_t)mp_obj_get_int_truncated(ptr) + (uintptr_t)mp_obj_get_int(offset);
+}
+
+static mp_obj_t wai_load_u8(mp_obj_t arg0, mp_obj_t arg1) {
+  return mp_obj_new_int(*(uint8_t *)wai_ptr(arg0, arg1));
+}
+static MP_DEFINE_CONST_FUN_OBJ_2(wai_load_u8_obj, wai_load_u8);
+
+static mp_obj_t wai_load_s8(mp_obj_t arg0, mp_obj_t arg1) {
+  return mp_obj_new_int(*(int8_t *)wai_ptr(arg0, arg1));
+}
+static MP_DEFINE_CONST_FUN_OBJ_2(wai_load_s8_obj, wai_load_s8);
+
+static mp_obj_t wai_load_u16(mp_obj_t arg0, mp_obj_t arg1) {
+  return mp_obj_new_int(*(uint16_t *)wai_ptr(arg0, arg1));
+}
+static MP_DEFINE_CONST_FUN_OBJ_2(wai_load_u16_obj, wai_load_u16);
+
+static mp_obj_t wai_load_s16(mp_obj_t arg0, mp_obj_t arg1) {
+  return mp_obj_new_int(*(int16_t *)wai_ptr(arg0, arg1));
+}
+static MP_DEFINE_CONST_FUN_OBJ_2(wai_load_s16_obj, wai_load_s16);
+
+static mp_obj_t wai_load_i32(mp_obj_t arg0, mp_obj_t arg1) {
+  return mp_obj_new_int(*(int32_t *)wai_ptr(arg0, arg1));
+}
+static MP_DEFINE_CONST_FUN_OBJ_2(wai_load_i32_obj, wai_load_i32);
+
+static mp_obj_t wai_load_i64(mp_obj_t arg0, mp_obj_t arg1) {
+  return mp_obj_new_int_from_ll(*(int64_t *)wai_ptr(arg0, arg1));
+}
+static MP_DEFINE_CONST_FUN_OBJ_2(wai_load_i64_obj, wai_load_i64);
+
+static mp_obj_t wai_load_f32(mp_obj_t arg0, mp_obj_t arg1) {
+  return mp_obj_new_float(*(float *)wai_ptr(arg0, arg1));
+}
+static MP_DEFINE_CONST_FUN_OBJ_2(wai_load_f32_obj, wai_load_f32);
+
+static mp_obj_t wai_load_f64(mp_obj_t arg0, mp_obj_t arg1) {
+  return mp_obj_new_float(*(double *)wai_ptr(arg0, arg1));
+}
+static MP_DEFINE_CONST_FUN_OBJ_2(wai_load_f64_obj, wai_load_f64);
+
+static mp_obj_t wai_store_i8(mp_obj_t arg0, mp_obj_t arg1, mp_obj_t arg2) {
+  *(uint8_t *)wai_ptr(arg0, arg1) = (uint8_t)mp_obj_get_int_truncated(arg2);
+  return mp_const_none;
+}
+static MP_DEFINE_CONST_FUN_OBJ_3(wai_store_i8_obj, wai_store_i8);
+
+static mp_obj_t wai_store_i16(mp_obj_t arg0, mp_obj_t arg1, mp_obj_t arg2) {
+  *(uint16_t *)wai_ptr(arg0, arg1) = (uint16_t)mp_obj_get_int_truncated(arg2);
+  return mp_const_none;
+}
+static MP_DEFINE_CONST_FUN_OBJ_3(wai_store_i16_obj, wai_store_i16);
+
+static mp_obj_t wai_store_i32(mp_obj_t arg0, mp_obj_t arg1, mp_obj_t arg2) {
+  *(int32_t *)wai_ptr(arg0, arg1) = (int32_t)mp_obj_get_int_truncated(arg2);
+  return mp_const_none;
+}
+static MP_DEFINE_CONST_FUN_OBJ_3(wai_store_i32_obj, wai_store_i32);
+
+static mp_obj_t wai_store_i64(mp_obj_t arg0, mp_obj_t arg1, mp_obj_t arg2) {
+  *(int64_t *)wai_ptr(arg0, arg1) = (int64_t)wai_obj_to_i64(arg2);
+  return mp_const_none;
+}
+static MP_DEFINE_CONST_FUN_OBJ_3(wai_store_i64_obj, wai_store_i64);
+
+static mp_obj_t wai_store_f32(mp_obj_t arg0, mp_obj_t arg1, mp_obj_t arg2) {
+  *(float *)wai_ptr(arg0, arg1) = (float)mp_obj_get_float(arg2);
+  return mp_const_none;
+}
+static MP_DEFINE_CONST_FUN_OBJ_3(wai_store_f32_obj, wai_store_f32);
+
+static mp_obj_t wai_store_f64(mp_obj_t arg0, mp_obj_t arg1, mp_obj_t arg2) {
+  *(double *)wai_ptr(arg0, arg1) = (double)mp_obj_get_float(arg2);
+  return mp_const_none;
+}
+static MP_DEFINE_CONST_FUN_OBJ_3(wai_store_f64_obj, wai_store_f64);
+
+static mp_obj_t wai_load_bytes(mp_obj_t arg0, mp_obj_t arg1) {
+  return mp_obj_new_bytes((const byte *)mp_obj_get_int_truncated(arg0), mp_obj_get_int(arg1));
+}
+static MP_DEFINE_CONST_FUN_OBJ_2(wai_load_bytes_obj, wai_load_bytes);
+
+static mp_obj_t wai_load_str(mp_obj_t arg0, mp_obj_t arg1) {
+  return mp_obj_new_str((const char *)mp_obj_get_int_truncated(arg0), mp_obj_get_int(arg1));
+}
+static MP_DEFINE_CONST_FUN_OBJ_2(wai_load_str_obj, wai_load_str);
+
+static mp_obj_t wai_store_bytes(mp_obj_t arg0, mp_obj_t arg1) {
+  mp_buffer_info_t buf;
+  mp_get_buffer_raise(arg1, &buf, MP_BUFFER_READ);
+  memcpy((void *)mp_obj_get_int_truncated(arg0), buf.buf, buf.len);
+  return mp_const_none;
+}
+static MP_DEFINE_CONST_FUN_OBJ_2(wai_store_bytes_obj, wai_store_bytes);
+
+static mp_obj_t wai_free(mp_obj_t arg0, mp_obj_t arg1, mp_obj_t arg2) {
+  canonical_abi_free((void *)mp_obj_get_int_truncated(arg0), mp_obj_get_int(arg1), mp_obj_get_int(arg2));
+  return mp_const_none;
+}
+static MP_DEFINE_CONST_FUN_OBJ_3(wai_free_obj, wai_free);
+
+static mp_obj_t wai_ret_area(void) {
+  return mp_obj_new_int_from_uint((uintptr_t)&RET_AREA);
+}
+static MP_DEFINE_CONST_FUN_OBJ_0(wai_ret_area_obj, wai_ret_area);
+
+static mp_obj_t wai_f32_from_bits(mp_obj_t arg0) {
+  uint32_t bits = mp_obj_get_int_truncated(arg0);
+  float f;
+  memcpy(&f, &bits, sizeof(f));
+  return mp_obj_new_float(f);
+}
+static MP_DEFINE_CONST_FUN_OBJ_1(wai_f32_from_bits_obj, wai_f32_from_bits);
+
+static mp_obj_t wai_f32_to_bits(mp_obj_t arg0) {
+  float f = mp_obj_get_float(arg0);
+  int32_t bits;
+  memcpy(&bits, &f, sizeof(bits));
+  return mp_obj_new_int(bits);
+}
+static MP_DEFINE_CONST_FUN_OBJ_1(wai_f32_to_bits_obj, wai_f32_to_bits);
+
+static mp_obj_t wai_f64_from_bits(mp_obj_t arg0) {
+  int64_t bits = wai_obj_to_i64(arg0);
+  double f;
+  memcpy(&f, &bits, sizeof(f));
+  return mp_obj_new_float(f);
+}
+static MP_DEFINE_CONST_FUN_OBJ_1(wai_f64_from_bits_obj, wai_f64_from_bits);
+
+static mp_obj_t wai_f64_to_bits(mp_obj_t arg0) {
+  double f = mp_obj_get_float(arg0);
+  int64_t bits;
+  memcpy(&bits, &f, sizeof(bits));
+  return mp_obj_new_int_from_ll(bits);
+}
+static MP_DEFINE_CONST_FUN_OBJ_1(wai_f64_to_bits_obj, wai_f64_to_bits);
+
+static mp_obj_t wai_realloc(size_t n_args, const mp_obj_t *args) {
+  void *ptr = (void *)mp_obj_get_int_truncated(args[0]);
+  size_t orig_size = mp_obj_get_int(args[1]);
+  size_t align = mp_obj_get_int(args[2]);
+  size_t new_size = mp_obj_get_int(args[3]);
+  void *ret = canonical_abi_realloc(ptr, orig_size, align, new_size);
+  return mp_obj_new_int_from_uint((uintptr_t)ret);
+}
+static MP_DEFINE_CONST_FUN_OBJ_VAR_BETWEEN(wai_realloc_obj, 4, 4, wai_realloc);
+
+static const mp_rom_map_elem_t wai_globals_table[] = {
+  { MP_ROM_QSTR(MP_QSTR___name__), MP_ROM_QSTR(MP_QSTR__wai) },
+  { MP_ROM_QSTR(MP_QSTR_realloc), MP_ROM_PTR(&wai_realloc_obj) },
+  { MP_ROM_QSTR(MP_QSTR_load_u8), MP_ROM_PTR(&wai_load_u8_obj) },
+  { MP_ROM_QSTR(MP_QSTR_load_s8), MP_ROM_PTR(&wai_load_s8_obj) },
+  { MP_ROM_QSTR(MP_QSTR_load_u16), MP_ROM_PTR(&wai_load_u16_obj) },
+  { MP_ROM_QSTR(MP_QSTR_load_s16), MP_ROM_PTR(&wai_load_s16_obj) },
+  { MP_ROM_QSTR(MP_QSTR_load_i32), MP_ROM_PTR(&wai_load_i32_obj) },
+  { MP_ROM_QSTR(MP_QSTR_load_i64), MP_ROM_PTR(&wai_load_i64_obj) },
+  { MP_ROM_QSTR(MP_QSTR_load_f32), MP_ROM_PTR(&wai_load_f32_obj) },
+  { MP_ROM_QSTR(MP_QSTR_load_f64), MP_ROM_PTR(&wai_load_f64_obj) },
+  { MP_ROM_QSTR(MP_QSTR_store_i8), MP_ROM_PTR(&wai_store_i8_obj) },
+  { MP_ROM_QSTR(MP_QSTR_store_i16), MP_ROM_PTR(&wai_store_i16_obj) },
+  { MP_ROM_QSTR(MP_QSTR_store_i32), MP_ROM_PTR(&wai_store_i32_obj) },
+  { MP_ROM_QSTR(MP_QSTR_store_i64), MP_ROM_PTR(&wai_store_i64_obj) },
+  { MP_ROM_QSTR(MP_QSTR_store_f32), MP_ROM_PTR(&wai_store_f32_obj) },
+  { MP_ROM_QSTR(MP_QSTR_store_f64), MP_ROM_PTR(&wai_store_f64_obj) },
+  { MP_ROM_QSTR(MP_QSTR_load_bytes), MP_ROM_PTR(&wai_load_bytes_obj) },
+  { MP_ROM_QSTR(MP_QSTR_load_str), MP_ROM_PTR(&wai_load_str_obj) },
+  { MP_ROM_QSTR(MP_QSTR_store_bytes), MP_ROM_PTR(&wai_store_bytes_obj) },
+  { MP_ROM_QSTR(MP_QSTR_free), MP_ROM_PTR(&wai_free_obj) },
+  { MP_ROM_QSTR(MP_QSTR_ret_area), MP_ROM_PTR(&wai_ret_area_obj) },
+  { MP_ROM_QSTR(MP_QSTR_f32_from_bits), MP_ROM_PTR(&wai_f32_from_bits_obj) },
+  { MP_ROM_QSTR(MP_QSTR_f32_to_bits), MP_ROM_PTR(&wai_f32_to_bits_obj) },
+  { MP_ROM_QSTR(MP_QSTR_f64_from_bits), MP_ROM_PTR(&wai_f64_from_bits_obj) },
+  { MP_ROM_QSTR(MP_QSTR_f64_to_bits), MP_ROM_PTR(&wai_f64_to_bits_obj) },
+};
+static MP_DEFINE_CONST_DICT(wai_globals, wai_globals_table);
+
+const mp_obj_module_t wai_module = {
+  .base = { &mp_type_module },
+  .globals = (mp_obj_dict_t *)&wai_globals,
+};
+
+MP_REGISTER_MODULE(MP_QSTR__wai, wai_module);
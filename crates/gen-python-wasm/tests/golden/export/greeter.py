@@ -0,0 +1,64 @@
+# Generated by `wai-bindgen` from the `greeter` interface.
+
+import _wai
+
+
+class _Record:
+    _fields = ()
+
+    def __eq__(self, other):
+        return type(self) is type(other) and all(
+            getattr(self, f) == getattr(other, f) for f in self._fields
+        )
+
+    def __repr__(self):
+        return "%s(%s)" % (
+            type(self).__name__,
+            ", ".join("%s=%r" % (f, getattr(self, f)) for f in self._fields),
+        )
+
+
+class Ok(_Record):
+    _fields = ("value",)
+
+    def __init__(self, value=None):
+        self.value = value
+
+
+class Err(_Record):
+    _fields = ("value",)
+
+    def __init__(self, value=None):
+        self.value = value
+
+
+class Point(_Record):
+    _fields = ("x", "y")
+
+    def __init__(self, x, y):
+        self.x = x
+        self.y = y
+
+
+_exports = None
+
+
+def set_exports(impl):
+    """Registers the implementation of the exports of this interface, any
+    object, like a module, with an attribute for each exported function."""
+    global _exports
+    _exports = impl
+
+
+def _export_greet(arg0, arg1, arg2, arg3):
+    string = _wai.load_str(arg0, arg1)
+    _wai.free(arg0, arg1, 1)
+    ret = _exports.greet(string, Point((arg2) & 0xffffffff, (arg3) & 0xffffffff))
+    ptr = _wai.ret_area()
+    val = ret
+    len0 = len(val)
+    ptr1 = _wai.realloc(0, 0, 1, len0)
+    _wai.store_bytes(ptr1, val)
+    _wai.store_i32(ptr, 4, len0)
+    _wai.store_i32(ptr, 0, ptr1)
+    return ptr
@@ -0,0 +1,4 @@
+# Generated by `wai-bindgen`.
+WAI_MOD_DIR := $(USERMOD_DIR)
+SRC_USERMOD_C += $(WAI_MOD_DIR)/_wai.c
+SRC_USERMOD_C += $(WAI_MOD_DIR)/_greeter.c
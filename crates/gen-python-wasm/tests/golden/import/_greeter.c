@@ -0,0 +1,29 @@
+// Generated by `wai-bindgen` from the `greeter` interface.
+#include <stdint.h>
+#include "py/runtime.h"
+
+int64_t wai_obj_to_i64(mp_obj_t obj);
+mp_obj_t wai_call_export(qstr module, qstr name, size_t n_args, const mp_obj_t *args);
+
+__attribute__((import_module("greeter"), import_name("greet")))
+void __wasm_import_greeter_greet(int32_t, int32_t, int32_t, int32_t, int32_t);
+
+static mp_obj_t greeter_greet(size_t n_args, const mp_obj_t *args) {
+  __wasm_import_greeter_greet((int32_t)mp_obj_get_int_truncated(args[0]), (int32_t)mp_obj_get_int_truncated(args[1]), (int32_t)mp_obj_get_int_truncated(args[2]), (int32_t)mp_obj_get_int_truncated(args[3]), (int32_t)mp_obj_get_int_truncated(args[4]));
+  return mp_const_none;
+}
+static MP_DEFINE_CONST_FUN_OBJ_VAR_BETWEEN(greeter_greet_obj, 5, 5, greeter_greet);
+
+static const mp_rom_map_elem_t _greeter_globals_table[] = {
+  { MP_ROM_QSTR(MP_QSTR___name__), MP_ROM_QSTR(MP_QSTR__greeter) },
+  { MP_ROM_QSTR(MP_QSTR_greet), MP_ROM_PTR(&greeter_greet_obj) },
+};
+
+static MP_DEFINE_CONST_DICT(_greeter_globals, _greeter_globals_table);
+
+const mp_obj_module_t _greeter_module = {
+  .base = { &mp_type_module },
+  .globals = (mp_obj_dict_t *)&_greeter_globals,
+};
+
+MP_REGISTER_MODULE(MP_QSTR__greeter, _greeter_module);
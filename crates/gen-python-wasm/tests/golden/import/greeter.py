@@ -0,0 +1,61 @@
+# Generated by `wai-bindgen` from the `greeter` interface.
+
+import _wai
+import _greeter
+
+
+class _Record:
+    _fields = ()
+
+    def __eq__(self, other):
+        return type(self) is type(other) and all(
+            getattr(self, f) == getattr(other, f) for f in self._fields
+        )
+
+    def __repr__(self):
+        return "%s(%s)" % (
+            type(self).__name__,
+            ", ".join("%s=%r" % (f, getattr(self, f)) for f in self._fields),
+        )
+
+
+class Ok(_Record):
+    _fields = ("value",)
+
+    def __init__(self, value=None):
+        self.value = value
+
+
+class Err(_Record):
+    _fields = ("value",)
+
+    def __init__(self, value=None):
+        self.value = value
+
+
+class Point(_Record):
+    _fields = ("x", "y")
+
+    def __init__(self, x, y):
+        self.x = x
+        self.y = y
+
+
+def greet(name, at):
+    _cleanup = []
+    val = (name).encode("utf-8")
+    len0 = len(val)
+    ptr = _wai.realloc(0, 0, 1, len0)
+    _cleanup.append((ptr, len0, 1))
+    _wai.store_bytes(ptr, val)
+    ptr1 = _wai.ret_area()
+    _greeter.greet(ptr, len0, (at).x, (at).y, ptr1)
+    for _ptr in _cleanup:
+        _wai.free(_ptr[0], _ptr[1], _ptr[2])
+    load = _wai.load_i32(ptr1, 0)
+    load2 = _wai.load_i32(ptr1, 4)
+    ptr3 = load
+    len4 = load2
+    items = _wai.load_bytes(ptr3, len4)
+    _wai.free(ptr3, len4 * 1, 1)
+    return items
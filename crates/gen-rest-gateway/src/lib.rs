@@ -0,0 +1,834 @@
+use heck::*;
+use wai_bindgen_gen_core::{wai_parser::*, Direction, Files, Generator, Source, TypeInfo, Types};
+use wai_bindgen_gen_rust::{to_rust_ident, RustGenerator, TypeMode};
+
+/// Generates an HTTP gateway (`gateway.rs`, for `axum` 0.7 and
+/// `wai-bindgen-wasmer`) serving the functions a module exports as JSON
+/// endpoints.
+///
+/// Every function `foo` becomes a `POST /foo` route taking its arguments as
+/// a JSON object keyed by parameter name and responding with its result.
+/// Values are mapped to JSON as follows:
+///
+/// * numbers and `bool`s as themselves, `char`s and `string`s as strings;
+/// * `list`s and `tuple`s as arrays, `record`s as objects;
+/// * `option`s as `null` or their value;
+/// * `enum`s as the name of their case and `flags` as an array of names;
+/// * `variant`s and `expected`s as an object with a single field named
+///   after the case (`ok` or `err` for `expected`), whose value is the
+///   payload, and `union`s likewise with the index of the case as the name.
+///
+/// Requests which don't match the signature of the function are rejected
+/// with `400 Bad Request`, and traps are reported as `500 Internal Server
+/// Error`. Functions taking or returning resources, or taking variants or
+/// lists which can't be borrowed from a single buffer, aren't exposed.
+#[derive(Default)]
+pub struct RestGateway {
+    opts: Opts,
+    types: Types,
+    src: Source,
+    /// `(path, handler)` of every route of the current interface.
+    routes: Vec<(String, String)>,
+    /// Functions of the current interface which aren't exposed.
+    skipped: Vec<String>,
+}
+
+#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "structopt", derive(structopt::StructOpt))]
+pub struct Opts {
+    /// Path of the `*.wai` file as referenced from the generated `import!`
+    /// macro invocation.
+    #[cfg_attr(feature = "structopt", structopt(long = "wai-path"))]
+    pub wai_path: Option<String>,
+}
+
+impl Opts {
+    pub fn build(self) -> RestGateway {
+        let mut r = RestGateway::new();
+        r.opts = self;
+        r
+    }
+}
+
+impl RestGateway {
+    pub fn new() -> RestGateway {
+        RestGateway::default()
+    }
+
+    /// Returns whether the bindings borrow data for values of `ty` in
+    /// parameter position.
+    fn borrows(&self, ty: &Type) -> bool {
+        match ty {
+            Type::String => true,
+            Type::Id(id) => self.types.get(*id).has_list,
+            _ => false,
+        }
+    }
+
+    /// Returns whether `ty` has no handles, which can't be sent as JSON.
+    fn has_handle(&self, ty: &Type) -> bool {
+        match ty {
+            Type::Handle(_) => true,
+            Type::Id(id) => self.types.get(*id).has_handle,
+            _ => false,
+        }
+    }
+
+    /// Returns whether a parameter of type `ty` can be borrowed from the
+    /// value decoded from JSON. Lists of borrowed values are collected into
+    /// a separate `Vec`, which is only possible outside of closures and
+    /// `match` arms, i.e. if `hoist` is set.
+    fn param_supported(&self, iface: &Interface, ty: &Type, hoist: bool) -> bool {
+        if self.has_handle(ty) {
+            return false;
+        }
+        if !self.borrows(ty) {
+            return true;
+        }
+        let id = match ty {
+            Type::Id(id) => *id,
+            _ => return true,
+        };
+        match &iface.types[id].kind {
+            TypeDefKind::Type(t) => self.param_supported(iface, t, hoist),
+            TypeDefKind::List(t) => {
+                (hoist || !self.borrows(t)) && self.param_supported(iface, t, false)
+            }
+            TypeDefKind::Option(t) => self.param_supported(iface, t, false),
+            TypeDefKind::Expected(e) => {
+                self.param_supported(iface, &e.ok, false)
+                    && self.param_supported(iface, &e.err, false)
+            }
+            TypeDefKind::Tuple(t) => t
+                .types
+                .iter()
+                .all(|t| self.param_supported(iface, t, hoist)),
+            TypeDefKind::Record(r) => r
+                .fields
+                .iter()
+                .all(|f| self.param_supported(iface, &f.ty, hoist)),
+            TypeDefKind::Flags(_) | TypeDefKind::Enum(_) => true,
+            TypeDefKind::Variant(_)
+            | TypeDefKind::Union(_)
+            | TypeDefKind::Future(_)
+            | TypeDefKind::Stream(_) => false,
+        }
+    }
+
+    /// Returns an expression decoding the `serde_json::Value` borrowed by
+    /// `v` into an owned value from which a parameter of type `ty` can be
+    /// borrowed, returning early with a `Rejection` if it doesn't match.
+    ///
+    /// This is a value of the parameter's type itself if it doesn't borrow
+    /// anything. Otherwise strings and lists are decoded into `String`s and
+    /// `Vec`s, and records into tuples of their fields.
+    fn decode(&self, iface: &Interface, ty: &Type) -> String {
+        match ty {
+            Type::Unit => "()".to_string(),
+            Type::Bool => "json::bool(v)?".to_string(),
+            Type::U8 | Type::U16 | Type::U32 | Type::U64 => {
+                format!("json::int::<{}>(v)?", int(ty))
+            }
+            Type::S8 | Type::S16 | Type::S32 | Type::S64 => {
+                format!("json::int::<{}>(v)?", int(ty))
+            }
+            Type::Float32 => "json::float(v)? as f32".to_string(),
+            Type::Float64 => "json::float(v)?".to_string(),
+            Type::Char => "json::char(v)?".to_string(),
+            Type::String => "json::str(v)?.to_string()".to_string(),
+            Type::Handle(_) => unreachable!(),
+            Type::Id(id) => self.decode_id(iface, *id),
+        }
+    }
+
+    fn decode_id(&self, iface: &Interface, id: TypeId) -> String {
+        let namer = Namer::new(&self.types);
+        let name = || namer.param_name(iface, id);
+        match &iface.types[id].kind {
+            TypeDefKind::Type(t) => self.decode(iface, t),
+            TypeDefKind::List(t) => format!(
+                "json::array(v)?
+                    .iter()
+                    .map(|v| Ok({}))
+                    .collect::<Result<Vec<_>, json::Rejection>>()?",
+                self.decode(iface, t)
+            ),
+            TypeDefKind::Option(t) => format!(
+                "if v.is_null() {{ None }} else {{ Some({}) }}",
+                self.decode(iface, t)
+            ),
+            TypeDefKind::Expected(e) => format!(
+                "match json::case(v)? {{
+                    (\"ok\", {}) => Ok({}),
+                    (\"err\", {}) => Err({}),
+                    (case, _) => return Err(json::unknown_case(case)),
+                }}",
+                payload_pattern(&e.ok),
+                self.decode(iface, &e.ok),
+                payload_pattern(&e.err),
+                self.decode(iface, &e.err),
+            ),
+            TypeDefKind::Tuple(t) => {
+                let elems = t
+                    .types
+                    .iter()
+                    .enumerate()
+                    .map(|(i, t)| format!("{{ let v = &a[{i}]; {} }}, ", self.decode(iface, t)))
+                    .collect::<String>();
+                format!(
+                    "{{ let a = json::tuple(v, {})?; ({elems}) }}",
+                    t.types.len()
+                )
+            }
+            TypeDefKind::Record(r) => {
+                let fields = r.fields.iter().map(|f| {
+                    let value = format!(
+                        "{{ let v = json::field(o, \"{}\"); {} }}",
+                        f.name,
+                        self.decode(iface, &f.ty)
+                    );
+                    (f, value)
+                });
+                let init = if self.types.get(id).has_list {
+                    let fields = fields
+                        .map(|(_, value)| format!("{value}, "))
+                        .collect::<String>();
+                    format!("({fields})")
+                } else {
+                    let fields = fields
+                        .map(|(f, value)| format!("{}: {value}, ", to_rust_ident(&f.name)))
+                        .collect::<String>();
+                    format!("{} {{ {fields}}}", name())
+                };
+                format!("{{ let o = json::object(v)?; {init} }}")
+            }
+            TypeDefKind::Flags(f) => {
+                let name = name();
+                let cases = f
+                    .flags
+                    .iter()
+                    .map(|flag| {
+                        format!(
+                            "\"{}\" => {name}::{},\n",
+                            flag.name,
+                            flag.name.to_shouty_snake_case()
+                        )
+                    })
+                    .collect::<String>();
+                format!(
+                    "{{
+                        let mut flags = {name}::empty();
+                        for v in json::array(v)? {{
+                            flags |= match json::str(v)? {{
+                                {cases}
+                                flag => return Err(json::unknown_case(flag)),
+                            }};
+                        }}
+                        flags
+                    }}"
+                )
+            }
+            TypeDefKind::Enum(e) => {
+                let name = name();
+                let cases = e
+                    .cases
+                    .iter()
+                    .map(|case| {
+                        format!(
+                            "\"{}\" => {name}::{},\n",
+                            case.name,
+                            case.name.to_camel_case()
+                        )
+                    })
+                    .collect::<String>();
+                format!(
+                    "match json::str(v)? {{
+                        {cases}
+                        case => return Err(json::unknown_case(case)),
+                    }}"
+                )
+            }
+            TypeDefKind::Variant(v) => {
+                let name = name();
+                let cases = v
+                    .cases
+                    .iter()
+                    .map(|case| {
+                        let payload = match case.ty {
+                            Type::Unit => String::new(),
+                            ty => format!("({})", self.decode(iface, &ty)),
+                        };
+                        format!(
+                            "(\"{}\", {}) => {name}::{}{payload},\n",
+                            case.name,
+                            payload_pattern(&case.ty),
+                            case.name.to_camel_case()
+                        )
+                    })
+                    .collect::<String>();
+                format!(
+                    "match json::case(v)? {{
+                        {cases}
+                        (case, _) => return Err(json::unknown_case(case)),
+                    }}"
+                )
+            }
+            TypeDefKind::Union(u) => {
+                let name = name();
+                let cases = namer
+                    .union_case_names(iface, u)
+                    .iter()
+                    .zip(&u.cases)
+                    .enumerate()
+                    .map(|(i, (case, ty))| {
+                        format!(
+                            "(\"{i}\", v) => {name}::{case}({}),\n",
+                            self.decode(iface, &ty.ty)
+                        )
+                    })
+                    .collect::<String>();
+                format!(
+                    "match json::case(v)? {{
+                        {cases}
+                        (case, _) => return Err(json::unknown_case(case)),
+                    }}"
+                )
+            }
+            TypeDefKind::Future(_) | TypeDefKind::Stream(_) => unreachable!(),
+        }
+    }
+
+    /// Returns an expression borrowing a parameter of type `ty` from the
+    /// place `p` holding the value `decode` returned for it. Lists of
+    /// borrowed values are collected into `Vec`s declared in `hoisted`.
+    fn view(&self, iface: &Interface, ty: &Type, p: &str, hoisted: &mut Vec<String>) -> String {
+        if !self.borrows(ty) {
+            return match ty {
+                Type::Unit => "()".to_string(),
+                _ => format!("{p}.clone()"),
+            };
+        }
+        let id = match ty {
+            Type::String => return format!("{p}.as_str()"),
+            Type::Id(id) => *id,
+            _ => unreachable!(),
+        };
+        match &iface.types[id].kind {
+            TypeDefKind::Type(t) => self.view(iface, t, p, hoisted),
+            TypeDefKind::List(t) if !self.borrows(t) => format!("{p}.as_slice()"),
+            TypeDefKind::List(t) => {
+                let tmp = format!("list{}", hoisted.len());
+                let elem = self.view(iface, t, "(*r)", &mut Vec::new());
+                hoisted.push(format!(
+                    "let {tmp} = {p}.iter().map(|r| {elem}).collect::<Vec<_>>();\n"
+                ));
+                format!("{tmp}.as_slice()")
+            }
+            TypeDefKind::Option(t) => format!(
+                "{p}.as_ref().map(|r| {})",
+                self.view(iface, t, "(*r)", &mut Vec::new())
+            ),
+            TypeDefKind::Expected(e) => format!(
+                "match &{p} {{ Ok(r) => Ok({}), Err(r) => Err({}) }}",
+                self.view(iface, &e.ok, "(*r)", &mut Vec::new()),
+                self.view(iface, &e.err, "(*r)", &mut Vec::new()),
+            ),
+            TypeDefKind::Tuple(t) => {
+                let elems = t
+                    .types
+                    .iter()
+                    .enumerate()
+                    .map(|(i, t)| {
+                        format!("{}, ", self.view(iface, t, &format!("{p}.{i}"), hoisted))
+                    })
+                    .collect::<String>();
+                format!("({elems})")
+            }
+            TypeDefKind::Record(r) => {
+                let fields = r
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .map(|(i, f)| {
+                        format!(
+                            "{}: {}, ",
+                            to_rust_ident(&f.name),
+                            self.view(iface, &f.ty, &format!("{p}.{i}"), hoisted)
+                        )
+                    })
+                    .collect::<String>();
+                let name = Namer::new(&self.types).param_name(iface, id);
+                format!("{name} {{ {fields}}}")
+            }
+            TypeDefKind::Flags(_)
+            | TypeDefKind::Enum(_)
+            | TypeDefKind::Variant(_)
+            | TypeDefKind::Union(_)
+            | TypeDefKind::Future(_)
+            | TypeDefKind::Stream(_) => unreachable!(),
+        }
+    }
+
+    /// Returns an expression encoding the result of type `ty` in the place
+    /// `p` as a `serde_json::Value`.
+    fn encode(&self, iface: &Interface, ty: &Type, p: &str) -> String {
+        match ty {
+            Type::Unit => "serde_json::Value::Null".to_string(),
+            Type::Bool
+            | Type::U8
+            | Type::U16
+            | Type::U32
+            | Type::U64
+            | Type::S8
+            | Type::S16
+            | Type::S32
+            | Type::S64
+            | Type::Float32
+            | Type::Float64 => format!("serde_json::Value::from({})", bare(p)),
+            Type::Char => format!("serde_json::Value::from({p}.to_string())"),
+            Type::String => format!("serde_json::Value::from({p}.as_str())"),
+            Type::Handle(_) => unreachable!(),
+            Type::Id(id) => self.encode_id(iface, *id, p),
+        }
+    }
+
+    fn encode_id(&self, iface: &Interface, id: TypeId, p: &str) -> String {
+        let namer = Namer::new(&self.types);
+        let name = || namer.result_name(iface, id);
+        match &iface.types[id].kind {
+            TypeDefKind::Type(t) => self.encode(iface, t, p),
+            TypeDefKind::List(t) => format!(
+                "serde_json::Value::Array({p}.iter().map(|r| {}).collect())",
+                self.encode(iface, t, "(*r)")
+            ),
+            TypeDefKind::Option(t) => format!(
+                "match &{p} {{ Some(r) => {}, None => serde_json::Value::Null }}",
+                self.encode(iface, t, "(*r)")
+            ),
+            TypeDefKind::Expected(e) => format!(
+                "match &{p} {{
+                    Ok(r) => json::tagged(\"ok\", {}),
+                    Err(r) => json::tagged(\"err\", {}),
+                }}",
+                self.encode(iface, &e.ok, "(*r)"),
+                self.encode(iface, &e.err, "(*r)"),
+            ),
+            TypeDefKind::Tuple(t) => {
+                let elems = t
+                    .types
+                    .iter()
+                    .enumerate()
+                    .map(|(i, t)| format!("{}, ", self.encode(iface, t, &format!("{p}.{i}"))))
+                    .collect::<String>();
+                format!("serde_json::Value::Array(vec![{elems}])")
+            }
+            TypeDefKind::Record(r) => {
+                let fields = r
+                    .fields
+                    .iter()
+                    .map(|f| {
+                        let field = format!("{p}.{}", to_rust_ident(&f.name));
+                        format!(
+                            "o.insert(\"{}\".to_string(), {});\n",
+                            f.name,
+                            self.encode(iface, &f.ty, &field)
+                        )
+                    })
+                    .collect::<String>();
+                format!(
+                    "{{
+                        let mut o = serde_json::Map::new();
+                        {fields}
+                        serde_json::Value::Object(o)
+                    }}"
+                )
+            }
+            TypeDefKind::Flags(f) => {
+                let name = name();
+                let flags = f
+                    .flags
+                    .iter()
+                    .map(|flag| {
+                        format!(
+                            "if {p}.contains({name}::{}) {{
+                                a.push(serde_json::Value::from(\"{}\"));
+                            }}\n",
+                            flag.name.to_shouty_snake_case(),
+                            flag.name,
+                        )
+                    })
+                    .collect::<String>();
+                format!(
+                    "{{
+                        let mut a = Vec::new();
+                        {flags}
+                        serde_json::Value::Array(a)
+                    }}"
+                )
+            }
+            TypeDefKind::Enum(e) => {
+                let name = name();
+                let cases = e
+                    .cases
+                    .iter()
+                    .map(|case| {
+                        format!(
+                            "{name}::{} => \"{}\",\n",
+                            case.name.to_camel_case(),
+                            case.name
+                        )
+                    })
+                    .collect::<String>();
+                format!("serde_json::Value::from(match {} {{\n{cases}}})", bare(p))
+            }
+            TypeDefKind::Variant(v) => {
+                let name = name();
+                let cases = v
+                    .cases
+                    .iter()
+                    .map(|case| {
+                        let (pattern, payload) = match case.ty {
+                            Type::Unit => (String::new(), "serde_json::Value::Null".to_string()),
+                            ty => ("(r)".to_string(), self.encode(iface, &ty, "(*r)")),
+                        };
+                        format!(
+                            "{name}::{}{pattern} => json::tagged(\"{}\", {payload}),\n",
+                            case.name.to_camel_case(),
+                            case.name,
+                        )
+                    })
+                    .collect::<String>();
+                format!("match &{p} {{\n{cases}}}")
+            }
+            TypeDefKind::Union(u) => {
+                let name = name();
+                let cases = namer
+                    .union_case_names(iface, u)
+                    .iter()
+                    .zip(&u.cases)
+                    .enumerate()
+                    .map(|(i, (case, ty))| {
+                        format!(
+                            "{name}::{case}(r) => json::tagged(\"{i}\", {}),\n",
+                            self.encode(iface, &ty.ty, "(*r)")
+                        )
+                    })
+                    .collect::<String>();
+                format!("match &{p} {{\n{cases}}}")
+            }
+            TypeDefKind::Future(_) | TypeDefKind::Stream(_) => unreachable!(),
+        }
+    }
+
+    fn wai_path(&self, iface: &Interface) -> String {
+        match &self.opts.wai_path {
+            Some(path) => path.clone(),
+            None => format!("{}.wai", iface.name),
+        }
+    }
+}
+
+fn int(ty: &Type) -> &'static str {
+    match ty {
+        Type::U8 => "u8",
+        Type::U16 => "u16",
+        Type::U32 => "u32",
+        Type::U64 => "u64",
+        Type::S8 => "i8",
+        Type::S16 => "i16",
+        Type::S32 => "i32",
+        Type::S64 => "i64",
+        _ => unreachable!(),
+    }
+}
+
+/// Strips the parentheses around a dereferenced place like `(*r)`, which
+/// are only needed to access its fields and methods.
+fn bare(p: &str) -> &str {
+    p.strip_prefix('(')
+        .and_then(|p| p.strip_suffix(')'))
+        .unwrap_or(p)
+}
+
+/// The pattern binding the payload `v` of a case of type `ty`, which is
+/// ignored for `unit`.
+fn payload_pattern(ty: &Type) -> &'static str {
+    match ty {
+        Type::Unit => "_",
+        _ => "v",
+    }
+}
+
+impl Generator for RestGateway {
+    fn preprocess_one(&mut self, iface: &Interface, dir: Direction) {
+        assert!(
+            dir == Direction::Import,
+            "gateways can only be generated for imported interfaces"
+        );
+        self.types.analyze(iface);
+
+        let snake = iface.name.to_snake_case();
+        let path = self.wai_path(iface);
+        self.src.push_str(&format!(
+            "
+                #[allow(clippy::all)]
+                pub mod {snake}_gateway {{
+                    wai_bindgen_wasmer::import!(\"{path}\");
+
+                    #[allow(unused_imports)]
+                    use {snake}::*;
+
+                    type Shared = std::sync::Arc<std::sync::Mutex<Gateway>>;
+
+                    /// The instance requests to the gateway are served by.
+                    pub struct Gateway {{
+                        pub store: wai_bindgen_wasmer::wasmer::Store,
+                        pub exports: {snake}::{camel},
+                    }}
+            ",
+            camel = iface.name.to_camel_case(),
+        ));
+    }
+
+    fn type_record(&mut self, _: &Interface, _: TypeId, _: &str, _: &Record, _: &Docs) {}
+    fn type_flags(&mut self, _: &Interface, _: TypeId, _: &str, _: &Flags, _: &Docs) {}
+    fn type_tuple(&mut self, _: &Interface, _: TypeId, _: &str, _: &Tuple, _: &Docs) {}
+    fn type_variant(&mut self, _: &Interface, _: TypeId, _: &str, _: &Variant, _: &Docs) {}
+    fn type_option(&mut self, _: &Interface, _: TypeId, _: &str, _: &Type, _: &Docs) {}
+    fn type_expected(&mut self, _: &Interface, _: TypeId, _: &str, _: &Expected, _: &Docs) {}
+    fn type_union(&mut self, _: &Interface, _: TypeId, _: &str, _: &Union, _: &Docs) {}
+    fn type_enum(&mut self, _: &Interface, _: TypeId, _: &str, _: &Enum, _: &Docs) {}
+    fn type_resource(&mut self, _: &Interface, _: ResourceId) {}
+    fn type_alias(&mut self, _: &Interface, _: TypeId, _: &str, _: &Type, _: &Docs) {}
+    fn type_list(&mut self, _: &Interface, _: TypeId, _: &str, _: &Type, _: &Docs) {}
+    fn type_builtin(&mut self, _: &Interface, _: TypeId, _: &str, _: &Type, _: &Docs) {}
+
+    fn import(&mut self, iface: &Interface, func: &Function) {
+        assert!(!func.is_async, "async not supported yet");
+        let supported = func
+            .params
+            .iter()
+            .all(|(_, ty)| self.param_supported(iface, ty, true))
+            && !self.has_handle(&func.result);
+        if !supported {
+            self.skipped.push(func.name.clone());
+            return;
+        }
+
+        let method = to_rust_ident(&func.name);
+        let handler = format!("handle_{}", func.name.to_snake_case());
+        let mut decoded = String::new();
+        let mut hoisted = Vec::new();
+        let mut args = String::new();
+        if !func.params.is_empty() {
+            decoded.push_str("let o = json::object(&body)?;\n");
+        }
+        for (i, (param, ty)) in func.params.iter().enumerate() {
+            decoded.push_str(&format!(
+                "let param{i} = {{ let v = json::field(o, \"{param}\"); {} }};\n",
+                self.decode(iface, ty)
+            ));
+            let arg = self.view(iface, ty, &format!("param{i}"), &mut hoisted);
+            args.push_str(&format!(", {arg}"));
+        }
+        let hoisted = hoisted.concat();
+        let result = self.encode(iface, &func.result, "result");
+        self.src.push_str(&format!(
+            "
+                async fn {handler}(
+                    axum::extract::State(gateway): axum::extract::State<Shared>,
+                    axum::Json(body): axum::Json<serde_json::Value>,
+                ) -> Result<axum::Json<serde_json::Value>, json::Rejection> {{
+                    {decoded}
+                    {hoisted}
+                    let result = {{
+                        let mut gateway = gateway.lock().unwrap();
+                        let Gateway {{ store, exports }} = &mut *gateway;
+                        exports.{method}(store{args}).map_err(json::trap)?
+                    }};
+                    Ok(axum::Json({result}))
+                }}
+            "
+        ));
+        self.routes.push((func.name.replace("::", "/"), handler));
+    }
+
+    fn export(&mut self, _iface: &Interface, _func: &Function) {
+        unreachable!()
+    }
+
+    fn finish_one(&mut self, _iface: &Interface, files: &mut Files) {
+        let mut docs = String::new();
+        for name in self.skipped.drain(..) {
+            docs.push_str(&format!(
+                "/// `{name}` isn't exposed since its signature can't be mapped to JSON.\n"
+            ));
+        }
+        if !docs.is_empty() {
+            docs.insert_str(0, "///\n");
+        }
+        self.src.push_str(&format!(
+            "
+                /// Returns a router serving every function exported by the
+                /// instance in `gateway` as a `POST` route named after it.
+                {docs}pub fn router(gateway: Gateway) -> axum::Router {{
+                    axum::Router::new()
+            "
+        ));
+        for (path, handler) in self.routes.drain(..) {
+            self.src.push_str(&format!(
+                ".route(\"/{path}\", axum::routing::post({handler}))\n"
+            ));
+        }
+        self.src.push_str(
+            ".with_state(std::sync::Arc::new(std::sync::Mutex::new(gateway)))
+            }
+            ",
+        );
+        self.src.push_str(JSON_HELPERS);
+        self.src.push_str("}\n");
+        files.push("gateway.rs", self.src.as_bytes());
+        self.src = Source::default();
+    }
+}
+
+/// Helpers for decoding `serde_json::Value`s which are emitted into every
+/// gateway.
+const JSON_HELPERS: &str = r#"
+    #[allow(dead_code)]
+    mod json {
+        use axum::http::StatusCode;
+        use serde_json::{Map, Value};
+
+        /// The response to a request which couldn't be served.
+        pub type Rejection = (StatusCode, String);
+
+        static NULL: Value = Value::Null;
+
+        pub fn trap(err: wai_bindgen_wasmer::wasmer::RuntimeError) -> Rejection {
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        }
+
+        pub fn unknown_case(case: &str) -> Rejection {
+            (StatusCode::BAD_REQUEST, format!("unknown case `{case}`"))
+        }
+
+        fn expected(what: &str, v: &Value) -> Rejection {
+            (StatusCode::BAD_REQUEST, format!("expected {what}, found `{v}`"))
+        }
+
+        pub fn object(v: &Value) -> Result<&Map<String, Value>, Rejection> {
+            v.as_object().ok_or_else(|| expected("an object", v))
+        }
+
+        /// Returns the field `name` of `o`, or `null` if it's missing.
+        pub fn field<'a>(o: &'a Map<String, Value>, name: &str) -> &'a Value {
+            o.get(name).unwrap_or(&NULL)
+        }
+
+        pub fn array(v: &Value) -> Result<&Vec<Value>, Rejection> {
+            v.as_array().ok_or_else(|| expected("an array", v))
+        }
+
+        pub fn tuple(v: &Value, len: usize) -> Result<&[Value], Rejection> {
+            match v.as_array() {
+                Some(a) if a.len() == len => Ok(a),
+                _ => Err(expected(&format!("an array of {len} elements"), v)),
+            }
+        }
+
+        pub fn str(v: &Value) -> Result<&str, Rejection> {
+            v.as_str().ok_or_else(|| expected("a string", v))
+        }
+
+        pub fn char(v: &Value) -> Result<char, Rejection> {
+            let mut chars = str(v)?.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(c),
+                _ => Err(expected("a single character", v)),
+            }
+        }
+
+        pub fn bool(v: &Value) -> Result<bool, Rejection> {
+            v.as_bool().ok_or_else(|| expected("a boolean", v))
+        }
+
+        pub fn int<T: TryFrom<i128>>(v: &Value) -> Result<T, Rejection> {
+            let n = match (v.as_i64(), v.as_u64()) {
+                (Some(n), _) => i128::from(n),
+                (None, Some(n)) => i128::from(n),
+                (None, None) => return Err(expected("an integer", v)),
+            };
+            T::try_from(n).map_err(|_| expected("an integer in range", v))
+        }
+
+        pub fn float(v: &Value) -> Result<f64, Rejection> {
+            v.as_f64().ok_or_else(|| expected("a number", v))
+        }
+
+        /// Splits the encoding of a variant into the name of its case and
+        /// its payload, which is `null` for cases given by name only.
+        pub fn case(v: &Value) -> Result<(&str, &Value), Rejection> {
+            match v {
+                Value::String(case) => Ok((case, &NULL)),
+                Value::Object(o) if o.len() == 1 => {
+                    let (case, payload) = o.iter().next().unwrap();
+                    Ok((case, payload))
+                }
+                _ => Err(expected("a case name or an object with a single field", v)),
+            }
+        }
+
+        pub fn tagged(case: &str, payload: Value) -> Value {
+            let mut o = Map::new();
+            o.insert(case.to_string(), payload);
+            Value::Object(o)
+        }
+    }
+"#;
+
+/// Minimal `RustGenerator` used to compute type names the same way
+/// `wai-bindgen-wasmer` does for the exports of a module.
+struct Namer<'a> {
+    types: &'a Types,
+}
+
+impl<'a> Namer<'a> {
+    fn new(types: &'a Types) -> Namer<'a> {
+        Namer { types }
+    }
+}
+
+impl RustGenerator for Namer<'_> {
+    fn push_str(&mut self, _: &str) {
+        unreachable!()
+    }
+
+    fn info(&self, ty: TypeId) -> TypeInfo {
+        self.types.get(ty)
+    }
+
+    fn types_mut(&mut self) -> &mut Types {
+        unreachable!()
+    }
+
+    fn print_borrowed_slice(&mut self, _: &Interface, _: bool, _: &Type, _: &'static str) {
+        unreachable!()
+    }
+
+    fn print_borrowed_str(&mut self, _: &'static str) {
+        unreachable!()
+    }
+
+    fn default_param_mode(&self) -> TypeMode {
+        TypeMode::AllBorrowed("'a")
+    }
+
+    fn handle_projection(&self) -> Option<(&'static str, String)> {
+        None
+    }
+
+    fn handle_wrapper(&self) -> Option<&'static str> {
+        None
+    }
+}
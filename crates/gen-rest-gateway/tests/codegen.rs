@@ -0,0 +1,48 @@
+use std::path::Path;
+use wai_bindgen_gen_core::{wai_parser::Interface, Files, Generator};
+
+#[test]
+fn runtime_interfaces() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/runtime");
+    for entry in std::fs::read_dir(&root).unwrap() {
+        let path = entry.unwrap().path().join("exports.wai");
+        if !path.exists() {
+            continue;
+        }
+        let iface = Interface::parse_file(&path).unwrap();
+        if iface.functions.iter().any(|f| f.is_async) {
+            continue;
+        }
+
+        let mut files = Files::default();
+        wai_bindgen_gen_rest_gateway::Opts::default()
+            .build()
+            .generate_all(&[iface], &[], &mut files);
+        let (name, contents) = files.iter().next().unwrap();
+        assert_eq!(name, "gateway.rs", "{}", path.display());
+        let contents = std::str::from_utf8(contents).unwrap();
+        assert!(contents.contains("pub fn router("), "{}", path.display());
+    }
+}
+
+#[test]
+fn skips_resources() {
+    let iface = Interface::parse(
+        "x",
+        "
+            resource y
+            make: func() -> y
+            len: func(a: string) -> u32
+        ",
+    )
+    .unwrap();
+    let mut files = Files::default();
+    wai_bindgen_gen_rest_gateway::Opts::default()
+        .build()
+        .generate_all(&[iface], &[], &mut files);
+    let (_, contents) = files.iter().next().unwrap();
+    let contents = std::str::from_utf8(contents).unwrap();
+    assert!(contents.contains(".route(\"/len\", axum::routing::post(handle_len))"));
+    assert!(!contents.contains("handle_make"));
+    assert!(contents.contains("`make` isn't exposed"));
+}
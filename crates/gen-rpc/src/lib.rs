@@ -0,0 +1,452 @@
+use heck::*;
+use std::io::{Read, Write};
+use std::mem;
+use std::process::{Command, Stdio};
+use wai_bindgen_gen_core::{wai_parser::*, Direction, Files, Generator, Source, TypeInfo, Types};
+use wai_bindgen_gen_rust::{to_rust_ident, FnSig, RustFlagsRepr, RustGenerator, TypeMode};
+
+/// Generates Rust bindings calling an interface over a byte stream, such as
+/// a socket or a child process' stdio, rather than across a wasm boundary.
+///
+/// For each interface this emits its types, a trait `X` with a method per
+/// function, a `serve` function answering the calls made over a stream
+/// with an implementation of `X`, and an `XClient` making them. This lets
+/// the two sides of an interface run natively in separate processes, for
+/// instance to debug a plugin before compiling it to wasm. The wire format
+/// is described in `wai_bindgen_rust::rpc`, which the bindings use with its
+/// `rpc` feature enabled.
+#[derive(Default)]
+pub struct Rpc {
+    src: Source,
+    opts: Opts,
+    types: Types,
+    /// `impl Wire` blocks for the types of the current interface.
+    wire: Source,
+    /// Method signatures of the trait of the current interface.
+    trait_methods: Vec<String>,
+    /// Methods of the client of the current interface.
+    client: Source,
+    /// Match arms of the `serve` function of the current interface.
+    dispatch: Source,
+    /// The number of functions of the current interface so far, which is
+    /// the index identifying the next one in calls.
+    funcs: u32,
+}
+
+#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "structopt", derive(structopt::StructOpt))]
+pub struct Opts {
+    /// Whether or not `rustfmt` is executed to format generated code.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub rustfmt: bool,
+}
+
+impl Opts {
+    pub fn build(self) -> Rpc {
+        let mut r = Rpc::new();
+        r.opts = self;
+        r
+    }
+}
+
+impl Rpc {
+    pub fn new() -> Rpc {
+        Rpc::default()
+    }
+
+    fn ty_string(&mut self, iface: &Interface, ty: &Type) -> String {
+        let prev = mem::take(&mut self.src);
+        self.print_ty(iface, ty, TypeMode::Owned);
+        String::from(mem::replace(&mut self.src, prev))
+    }
+
+    /// Emits `impl Wire for {name}`, whose bodies are `encode` and `decode`.
+    fn print_wire_impl(&mut self, name: &str, encode: &str, decode: &str) {
+        self.wire.push_str(&format!(
+            "
+                impl rpc::Wire for {name} {{
+                    fn encode(&self, out: &mut Vec<u8>) {{
+                        {encode}
+                    }}
+
+                    fn decode(input: &mut &[u8]) -> Result<Self, rpc::Error> {{
+                        {decode}
+                    }}
+                }}
+            "
+        ));
+    }
+
+    /// Emits the `Wire` implementation of a variant or union whose cases
+    /// are named `cases` and carry a payload of the given type.
+    fn print_wire_cases(&mut self, name: &str, cases: &[(String, Type)]) {
+        let mut encode = String::from("match self {\n");
+        let mut decode = String::from("match u32::decode(input)? {\n");
+        for (i, (case, ty)) in cases.iter().enumerate() {
+            match ty {
+                Type::Unit => {
+                    encode.push_str(&format!("{name}::{case} => {i}u32.encode(out),\n"));
+                    decode.push_str(&format!("{i} => Ok({name}::{case}),\n"));
+                }
+                _ => {
+                    encode.push_str(&format!(
+                        "{name}::{case}(e) => {{
+                            {i}u32.encode(out);
+                            e.encode(out);
+                        }}\n"
+                    ));
+                    decode.push_str(&format!(
+                        "{i} => Ok({name}::{case}(Wire::decode(input)?)),\n"
+                    ));
+                }
+            }
+        }
+        encode.push_str("}\n");
+        decode.push_str(&format!(
+            "n => Err(rpc::invalid_case(\"{name}\", n)),
+            }}\n"
+        ));
+        self.print_wire_impl(name, &encode, &decode);
+    }
+}
+
+impl RustGenerator for Rpc {
+    fn default_param_mode(&self) -> TypeMode {
+        TypeMode::Owned
+    }
+
+    fn handle_projection(&self) -> Option<(&'static str, String)> {
+        None
+    }
+
+    fn handle_wrapper(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn push_str(&mut self, s: &str) {
+        self.src.push_str(s);
+    }
+
+    fn info(&self, ty: TypeId) -> TypeInfo {
+        self.types.get(ty)
+    }
+
+    fn types_mut(&mut self) -> &mut Types {
+        &mut self.types
+    }
+
+    fn print_borrowed_slice(&mut self, _: &Interface, _: bool, _: &Type, _: &'static str) {
+        unreachable!()
+    }
+
+    fn print_borrowed_str(&mut self, _: &'static str) {
+        unreachable!()
+    }
+}
+
+impl Generator for Rpc {
+    fn preprocess_one(&mut self, iface: &Interface, _dir: Direction) {
+        assert!(
+            iface.resources.len() == 0,
+            "rpc bindings don't support resources yet"
+        );
+        self.types.analyze(iface);
+        self.src.push_str(&format!(
+            "
+                #[allow(clippy::all)]
+                pub mod {} {{
+                    #[allow(unused_imports)]
+                    use wai_bindgen_rust::rpc::{{self, Wire}};
+            ",
+            iface.name.to_snake_case(),
+        ));
+    }
+
+    fn type_record(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        name: &str,
+        record: &Record,
+        docs: &Docs,
+    ) {
+        self.print_typedef_record(iface, id, record, docs);
+
+        let name = name.to_camel_case();
+        let mut encode = String::new();
+        let mut decode = format!("Ok({name} {{\n");
+        for field in record.fields.iter() {
+            let field = to_rust_ident(&field.name);
+            encode.push_str(&format!("self.{field}.encode(out);\n"));
+            decode.push_str(&format!("{field}: Wire::decode(input)?,\n"));
+        }
+        decode.push_str("})\n");
+        self.print_wire_impl(&name, &encode, &decode);
+    }
+
+    fn type_tuple(&mut self, iface: &Interface, id: TypeId, _: &str, tuple: &Tuple, docs: &Docs) {
+        self.print_typedef_tuple(iface, id, tuple, docs);
+    }
+
+    fn type_flags(&mut self, _: &Interface, _: TypeId, name: &str, flags: &Flags, docs: &Docs) {
+        let name = name.to_camel_case();
+        let repr = RustFlagsRepr::new(flags);
+        self.src
+            .push_str("wai_bindgen_rust::bitflags::bitflags! {\n");
+        self.rustdoc(docs);
+        self.src
+            .push_str(&format!("pub struct {name}: {repr} {{\n"));
+        for (i, flag) in flags.flags.iter().enumerate() {
+            self.rustdoc(&flag.docs);
+            self.src.push_str(&format!(
+                "const {} = 1 << {};\n",
+                flag.name.to_shouty_snake_case(),
+                i,
+            ));
+        }
+        self.src.push_str("}\n");
+        self.src.push_str("}\n");
+
+        // Unknown bits are kept, as they are across the wasm boundary.
+        self.print_wire_impl(
+            &name,
+            "self.bits.encode(out);",
+            "Ok(Self { bits: Wire::decode(input)? })",
+        );
+    }
+
+    fn type_variant(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        name: &str,
+        variant: &Variant,
+        docs: &Docs,
+    ) {
+        self.print_typedef_variant(iface, id, variant, docs);
+        let cases = variant
+            .cases
+            .iter()
+            .map(|c| (c.name.to_camel_case(), c.ty))
+            .collect::<Vec<_>>();
+        self.print_wire_cases(&name.to_camel_case(), &cases);
+    }
+
+    fn type_union(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        name: &str,
+        union: &Union,
+        docs: &Docs,
+    ) {
+        self.print_typedef_union(iface, id, union, docs);
+        let cases = self
+            .union_case_names(iface, union)
+            .into_iter()
+            .zip(union.cases.iter().map(|c| c.ty))
+            .collect::<Vec<_>>();
+        self.print_wire_cases(&name.to_camel_case(), &cases);
+    }
+
+    fn type_option(&mut self, iface: &Interface, id: TypeId, _: &str, payload: &Type, docs: &Docs) {
+        self.print_typedef_option(iface, id, payload, docs);
+    }
+
+    fn type_expected(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        _: &str,
+        expected: &Expected,
+        docs: &Docs,
+    ) {
+        self.print_typedef_expected(iface, id, expected, docs);
+    }
+
+    fn type_enum(&mut self, _: &Interface, id: TypeId, name: &str, enum_: &Enum, docs: &Docs) {
+        self.print_typedef_enum(id, name, enum_, docs);
+
+        let name = name.to_camel_case();
+        let mut decode = String::from("match u32::decode(input)? {\n");
+        for (i, case) in enum_.cases.iter().enumerate() {
+            decode.push_str(&format!(
+                "{i} => Ok({name}::{}),\n",
+                case.name.to_camel_case()
+            ));
+        }
+        decode.push_str(&format!(
+            "n => Err(rpc::invalid_case(\"{name}\", n)),
+            }}\n"
+        ));
+        self.print_wire_impl(&name, "(*self as u32).encode(out);", &decode);
+    }
+
+    fn type_resource(&mut self, _: &Interface, _: ResourceId) {
+        unreachable!()
+    }
+
+    fn type_alias(&mut self, iface: &Interface, id: TypeId, _: &str, ty: &Type, docs: &Docs) {
+        self.print_typedef_alias(iface, id, ty, docs);
+    }
+
+    fn type_list(&mut self, iface: &Interface, id: TypeId, _: &str, ty: &Type, docs: &Docs) {
+        self.print_type_list(iface, id, ty, docs);
+    }
+
+    fn type_builtin(&mut self, iface: &Interface, _: TypeId, name: &str, ty: &Type, docs: &Docs) {
+        self.rustdoc(docs);
+        self.src
+            .push_str(&format!("pub type {}", name.to_camel_case()));
+        self.src.push_str(" = ");
+        self.print_ty(iface, ty, TypeMode::Owned);
+        self.src.push_str(";\n");
+    }
+
+    fn import(&mut self, iface: &Interface, func: &Function) {
+        self.export(iface, func);
+    }
+
+    fn export(&mut self, iface: &Interface, func: &Function) {
+        assert!(!func.is_async, "async not supported yet");
+        let index = self.funcs;
+        self.funcs += 1;
+        let prev = mem::take(&mut self.src);
+
+        let mut sig = FnSig {
+            private: true,
+            self_arg: Some("&mut self".to_string()),
+            ..Default::default()
+        };
+        let params = self.print_signature(iface, func, TypeMode::Owned, &sig);
+        self.src.push_str(";\n");
+        let method = mem::take(&mut self.src);
+        self.trait_methods.push(method.into());
+
+        sig.private = false;
+        self.print_docs_and_params(iface, func, TypeMode::Owned, &sig);
+        let result = self.ty_string(iface, &func.result);
+        self.src
+            .push_str(&format!(" -> Result<{result}, rpc::Error> {{\n"));
+        if params.is_empty() {
+            self.src
+                .push_str(&format!("self.client.call({index}, &[])\n}}\n"));
+        } else {
+            self.src.push_str("let mut args = Vec::new();\n");
+            for param in params.iter() {
+                self.src.push_str(&format!("{param}.encode(&mut args);\n"));
+            }
+            self.src
+                .push_str(&format!("self.client.call({index}, &args)\n}}\n"));
+        }
+        let client = mem::replace(&mut self.src, prev);
+        self.client.push_str(&client);
+
+        self.dispatch.push_str(&format!("{index} => {{\n"));
+        for (param, (_, ty)) in params.iter().zip(func.params.iter()) {
+            let ty = self.ty_string(iface, ty);
+            self.dispatch
+                .push_str(&format!("let {param}: {ty} = Wire::decode(args)?;\n"));
+        }
+        self.dispatch.push_str(&format!(
+            "rpc::finish(args)?;
+            host.{}({}).encode(reply);
+            }}\n",
+            to_rust_ident(&func.name),
+            params.join(", "),
+        ));
+    }
+
+    fn finish_one(&mut self, iface: &Interface, files: &mut Files) {
+        let mut src = mem::take(&mut self.src);
+        let camel = iface.name.to_camel_case();
+
+        src.push_str(&String::from(mem::take(&mut self.wire)));
+
+        src.push_str(&format!(
+            "
+                /// The functions of `{}`, implemented by the side serving
+                /// calls with `serve`.
+                pub trait {camel} {{
+            ",
+            iface.name,
+        ));
+        for method in mem::take(&mut self.trait_methods) {
+            src.push_str(&method);
+        }
+        src.push_str("}\n");
+
+        src.push_str(&format!(
+            "
+                /// Makes calls to an implementation of `{camel}` served on
+                /// the other end of a stream.
+                pub struct {camel}Client<S> {{
+                    client: rpc::Client<S>,
+                }}
+
+                impl<S: std::io::Read + std::io::Write> {camel}Client<S> {{
+                    pub fn new(stream: S) -> Self {{
+                        Self {{ client: rpc::Client::new(stream) }}
+                    }}
+
+                    /// Returns the stream the client was created with.
+                    pub fn into_inner(self) -> S {{
+                        self.client.into_inner()
+                    }}
+            "
+        ));
+        src.push_str(&String::from(mem::take(&mut self.client)));
+        src.push_str("}\n");
+
+        src.push_str(&format!(
+            "
+                /// Serves the calls made over `stream` with `host` until the
+                /// client closes it.
+                #[allow(unused_variables)]
+                pub fn serve<T: {camel}>(
+                    host: &mut T,
+                    stream: impl std::io::Read + std::io::Write,
+                ) -> Result<(), rpc::Error> {{
+                    rpc::serve(stream, |func, args, reply| {{
+                        match func {{
+            "
+        ));
+        src.push_str(&String::from(mem::take(&mut self.dispatch)));
+        src.push_str(
+            "                            _ => return Err(rpc::invalid_case(\"function\", func)),
+                        }
+                        Ok(())
+                    })
+                }
+            }
+            ",
+        );
+        self.funcs = 0;
+
+        if self.opts.rustfmt {
+            let mut child = Command::new("rustfmt")
+                .arg("--edition=2018")
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+                .expect("failed to spawn `rustfmt`");
+            child
+                .stdin
+                .take()
+                .unwrap()
+                .write_all(src.as_bytes())
+                .unwrap();
+            src.as_mut_string().truncate(0);
+            child
+                .stdout
+                .take()
+                .unwrap()
+                .read_to_string(src.as_mut_string())
+                .unwrap();
+            let status = child.wait().unwrap();
+            assert!(status.success());
+        }
+
+        files.push_for_interface(&iface.name.to_snake_case(), "rs", src.as_bytes());
+    }
+}
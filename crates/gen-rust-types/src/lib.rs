@@ -0,0 +1,339 @@
+use heck::*;
+use std::collections::BTreeSet;
+use std::io::{Read, Write};
+use std::mem;
+use std::process::{Command, Stdio};
+use wai_bindgen_gen_core::{wai_parser::*, Direction, Files, Generator, Source, TypeInfo, Types};
+use wai_bindgen_gen_rust::{RustFlagsRepr, RustGenerator, TypeMode};
+
+/// Generates a standalone crate of the plain Rust types of interfaces, with
+/// none of the glue to pass them across the canonical ABI.
+///
+/// Each interface becomes a module of the crate defining its records,
+/// variants, enums and other named types as owned values, the same way the
+/// `rust-wasm` bindings print them for exports. The crate only depends on
+/// `bitflags`, so it can be shared by a host and its guests, and application
+/// logic can be written against it without depending on a runtime or on the
+/// guest macros.
+///
+/// Resources, and the types which contain their handles, are skipped since
+/// handles only exist in the context of bindings.
+#[derive(Default)]
+pub struct RustTypes {
+    src: Source,
+    lib: Source,
+    opts: Opts,
+    types: Types,
+    /// The names of the interfaces which are already part of the crate.
+    modules: BTreeSet<String>,
+    /// Whether the current interface was already generated in the other
+    /// direction.
+    skip: bool,
+    needs_bitflags: bool,
+    crate_name: Option<String>,
+}
+
+#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "structopt", derive(structopt::StructOpt))]
+pub struct Opts {
+    /// Whether or not `rustfmt` is executed to format generated code.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub rustfmt: bool,
+
+    /// The name of the generated crate, which defaults to the name of the
+    /// first interface followed by `-types`.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub crate_name: Option<String>,
+}
+
+impl Opts {
+    pub fn build(self) -> RustTypes {
+        let mut r = RustTypes::new();
+        r.opts = self;
+        r
+    }
+}
+
+impl RustTypes {
+    pub fn new() -> RustTypes {
+        RustTypes::default()
+    }
+
+    /// Returns whether the type `id` is left out of the crate.
+    fn skipped(&self, id: TypeId) -> bool {
+        self.skip || self.info(id).has_handle
+    }
+}
+
+impl RustGenerator for RustTypes {
+    fn modes_of(&self, iface: &Interface, ty: TypeId) -> Vec<(String, TypeMode)> {
+        // Every type is defined once, whether or not functions use it.
+        let name = iface.types[ty].name.as_ref().unwrap().to_camel_case();
+        vec![(name, TypeMode::Owned)]
+    }
+
+    fn default_param_mode(&self) -> TypeMode {
+        TypeMode::Owned
+    }
+
+    fn handle_projection(&self) -> Option<(&'static str, String)> {
+        None
+    }
+
+    fn handle_wrapper(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn push_str(&mut self, s: &str) {
+        self.src.push_str(s);
+    }
+
+    fn info(&self, ty: TypeId) -> TypeInfo {
+        self.types.get(ty)
+    }
+
+    fn types_mut(&mut self) -> &mut Types {
+        &mut self.types
+    }
+
+    fn print_borrowed_slice(
+        &mut self,
+        _iface: &Interface,
+        _mutbl: bool,
+        _ty: &Type,
+        _lifetime: &'static str,
+    ) {
+        unreachable!("types are always owned")
+    }
+
+    fn print_borrowed_str(&mut self, _lifetime: &'static str) {
+        unreachable!("types are always owned")
+    }
+}
+
+impl Generator for RustTypes {
+    fn preprocess_one(&mut self, iface: &Interface, _dir: Direction) {
+        self.skip = !self.modules.insert(iface.name.clone());
+        self.types.analyze(iface);
+        if self.crate_name.is_none() {
+            let name = match &self.opts.crate_name {
+                Some(name) => name.clone(),
+                None => format!("{}-types", iface.name.to_kebab_case()),
+            };
+            self.crate_name = Some(name);
+        }
+    }
+
+    fn type_record(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        _name: &str,
+        record: &Record,
+        docs: &Docs,
+    ) {
+        if !self.skipped(id) {
+            self.print_typedef_record(iface, id, record, docs);
+        }
+    }
+
+    fn type_tuple(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        _name: &str,
+        tuple: &Tuple,
+        docs: &Docs,
+    ) {
+        if !self.skipped(id) {
+            self.print_typedef_tuple(iface, id, tuple, docs);
+        }
+    }
+
+    fn type_flags(
+        &mut self,
+        _iface: &Interface,
+        id: TypeId,
+        name: &str,
+        flags: &Flags,
+        docs: &Docs,
+    ) {
+        if self.skipped(id) {
+            return;
+        }
+        self.needs_bitflags = true;
+        self.src.push_str("bitflags::bitflags! {\n");
+        self.rustdoc(docs);
+        let repr = RustFlagsRepr::new(flags);
+        self.src
+            .push_str(&format!("pub struct {}: {repr} {{\n", name.to_camel_case()));
+        for (i, flag) in flags.flags.iter().enumerate() {
+            self.rustdoc(&flag.docs);
+            self.src.push_str(&format!(
+                "const {} = 1 << {};\n",
+                flag.name.to_shouty_snake_case(),
+                i,
+            ));
+        }
+        self.src.push_str("}\n");
+        self.src.push_str("}\n");
+
+        // Add a `from_bits_preserve` method, like the bindings do.
+        self.src
+            .push_str(&format!("impl {} {{\n", name.to_camel_case()));
+        self.src
+            .push_str("    /// Convert from a raw integer, preserving any unknown bits. See\n");
+        self.src.push_str(
+            "    /// <https://github.com/bitflags/bitflags/issues/263#issuecomment-957088321>\n",
+        );
+        self.src.push_str(&format!(
+            "    pub fn from_bits_preserve(bits: {repr}) -> Self {{\n",
+        ));
+        self.src.push_str("        Self { bits }\n");
+        self.src.push_str("    }\n");
+        self.src.push_str("}\n");
+    }
+
+    fn type_variant(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        _name: &str,
+        variant: &Variant,
+        docs: &Docs,
+    ) {
+        if !self.skipped(id) {
+            self.print_typedef_variant(iface, id, variant, docs);
+        }
+    }
+
+    fn type_union(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        _name: &str,
+        union: &Union,
+        docs: &Docs,
+    ) {
+        if !self.skipped(id) {
+            self.print_typedef_union(iface, id, union, docs);
+        }
+    }
+
+    fn type_option(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        _name: &str,
+        payload: &Type,
+        docs: &Docs,
+    ) {
+        if !self.skipped(id) {
+            self.print_typedef_option(iface, id, payload, docs);
+        }
+    }
+
+    fn type_expected(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        _name: &str,
+        expected: &Expected,
+        docs: &Docs,
+    ) {
+        if !self.skipped(id) {
+            self.print_typedef_expected(iface, id, expected, docs);
+        }
+    }
+
+    fn type_enum(&mut self, _iface: &Interface, id: TypeId, name: &str, enum_: &Enum, docs: &Docs) {
+        if !self.skipped(id) {
+            self.print_typedef_enum(id, name, enum_, docs);
+        }
+    }
+
+    fn type_resource(&mut self, _iface: &Interface, _ty: ResourceId) {
+        // Handles only exist in the context of bindings.
+    }
+
+    fn type_alias(&mut self, iface: &Interface, id: TypeId, _name: &str, ty: &Type, docs: &Docs) {
+        if !self.skipped(id) {
+            self.print_typedef_alias(iface, id, ty, docs);
+        }
+    }
+
+    fn type_list(&mut self, iface: &Interface, id: TypeId, _name: &str, ty: &Type, docs: &Docs) {
+        if !self.skipped(id) {
+            self.print_type_list(iface, id, ty, docs);
+        }
+    }
+
+    fn type_builtin(&mut self, iface: &Interface, id: TypeId, name: &str, ty: &Type, docs: &Docs) {
+        if self.skipped(id) {
+            return;
+        }
+        self.rustdoc(docs);
+        self.src
+            .push_str(&format!("pub type {}", name.to_camel_case()));
+        self.src.push_str(" = ");
+        self.print_ty(iface, ty, TypeMode::Owned);
+        self.src.push_str(";\n");
+    }
+
+    fn import(&mut self, _iface: &Interface, _func: &Function) {}
+
+    fn export(&mut self, _iface: &Interface, _func: &Function) {}
+
+    fn finish_one(&mut self, iface: &Interface, _files: &mut Files) {
+        let src = mem::take(&mut self.src);
+        if self.skip {
+            return;
+        }
+        self.lib.push_str(&format!(
+            "#[allow(clippy::all)]\npub mod {} {{\n",
+            iface.name.to_snake_case(),
+        ));
+        self.lib.push_str(&src);
+        self.lib.push_str("}\n");
+    }
+
+    fn finish_all(&mut self, files: &mut Files) {
+        let name = match self.crate_name.take() {
+            Some(name) => name,
+            None => return,
+        };
+
+        let mut manifest = format!(
+            "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\n"
+        );
+        if self.needs_bitflags {
+            manifest.push_str("bitflags = \"1.3\"\n");
+        }
+        files.push("Cargo.toml", manifest.as_bytes());
+
+        let mut src = mem::take(&mut self.lib);
+        if self.opts.rustfmt {
+            let mut child = Command::new("rustfmt")
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+                .expect("failed to spawn `rustfmt`");
+            child
+                .stdin
+                .take()
+                .unwrap()
+                .write_all(src.as_bytes())
+                .unwrap();
+            src.as_mut_string().truncate(0);
+            child
+                .stdout
+                .take()
+                .unwrap()
+                .read_to_string(src.as_mut_string())
+                .unwrap();
+            let status = child.wait().unwrap();
+            assert!(status.success());
+        }
+        files.push("src/lib.rs", src.as_bytes());
+    }
+}
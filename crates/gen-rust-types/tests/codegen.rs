@@ -0,0 +1,88 @@
+use std::path::Path;
+use wai_bindgen_gen_core::{wai_parser::Interface, Files, Generator};
+
+fn generate(imports: &[Interface], opts: wai_bindgen_gen_rust_types::Opts) -> (String, String) {
+    let mut files = Files::default();
+    opts.build().generate_all(imports, &[], &mut files);
+    let file = |name: &str| {
+        let (_, contents) = files.iter().find(|(n, _)| *n == name).unwrap();
+        String::from_utf8(contents.to_vec()).unwrap()
+    };
+    (file("Cargo.toml"), file("src/lib.rs"))
+}
+
+#[test]
+fn codegen_interfaces() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/codegen");
+    for entry in std::fs::read_dir(&root).unwrap() {
+        let path = entry.unwrap().path();
+        let iface = Interface::parse_file(&path).unwrap();
+        let name = iface.name.clone();
+        let (manifest, lib) = generate(&[iface], Default::default());
+        assert!(
+            manifest.contains(&format!("name = \"{}-types\"", name)),
+            "{}",
+            path.display()
+        );
+        assert!(lib.contains("pub mod "), "{}", path.display());
+    }
+}
+
+#[test]
+fn owned_types() {
+    let iface = Interface::parse(
+        "api",
+        "
+            resource file
+            flags perms { read, write }
+            /// A point.
+            record point { x: u8, label: option<string> }
+            record entry { file: file, perms: perms }
+            variant shape { dot(point), line(list<point>) }
+            draw: func(shapes: list<shape>) -> list<point>
+        ",
+    )
+    .unwrap();
+
+    let opts = wai_bindgen_gen_rust_types::Opts {
+        crate_name: Some("shapes".to_string()),
+        ..Default::default()
+    };
+    let (manifest, lib) = generate(&[iface.clone(), iface], opts);
+    assert!(manifest.contains("name = \"shapes\""));
+    assert!(manifest.contains("bitflags = "));
+    assert_eq!(lib.matches("pub mod api").count(), 1);
+    assert!(lib.contains("/// A point."));
+    assert!(lib.contains("pub struct Point {"));
+    assert!(lib.contains("pub label: Option<String>,"));
+    assert!(lib.contains("Line(Vec<Point>)"));
+    assert!(!lib.contains("Entry"));
+    assert!(!lib.contains("File"));
+    assert!(!lib.contains("'a"));
+}
+
+#[test]
+fn non_exhaustive() {
+    let iface = Interface::parse(
+        "api",
+        "
+            @non-exhaustive
+            enum color { red, green }
+            @non-exhaustive
+            variant event { key(char), close }
+            enum fixed { a, b }
+        ",
+    )
+    .unwrap();
+
+    let (_, lib) = generate(&[iface], Default::default());
+    assert_eq!(lib.matches("#[non_exhaustive]").count(), 2, "{lib}");
+    assert!(
+        lib.contains("#[non_exhaustive]\n  pub enum Color {"),
+        "{lib}"
+    );
+    assert!(
+        lib.contains("#[non_exhaustive]\n  pub enum Event{"),
+        "{lib}"
+    );
+}
@@ -1,14 +1,18 @@
 use heck::*;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::{Read, Write};
 use std::mem;
 use std::process::{Command, Stdio};
 use wai_bindgen_gen_core::wai_parser::abi::{
-    AbiVariant, Bindgen, Instruction, LiftLower, WasmType,
+    AbiVariant, Bindgen, Instruction, LiftLower, WasmType, ABI_VERSION,
+};
+use wai_bindgen_gen_core::{
+    abi_descriptor, interface_hash, wai_parser::*, Direction, Files, Generator, NamespaceMap,
+    Source, TypeInfo, Types, ABI_DESCRIPTOR_EXPORT, INTERFACE_HASH_SECTION,
 };
-use wai_bindgen_gen_core::{wai_parser::*, Direction, Files, Generator, Source, TypeInfo, Types};
 use wai_bindgen_gen_rust::{
-    int_repr, wasm_type, FnSig, RustFlagsRepr, RustFunctionGenerator, RustGenerator, TypeMode,
+    int_repr, is_list_canonical, to_rust_ident, wasm_type, FnSig, RustFlagsRepr,
+    RustFunctionGenerator, RustGenerator, TypeMode,
 };
 
 #[derive(Default)]
@@ -17,12 +21,16 @@ pub struct RustWasm {
     opts: Opts,
     types: Types,
     in_import: bool,
+    /// The names of the interfaces being generated in each direction.
+    import_names: BTreeSet<String>,
+    export_names: BTreeSet<String>,
     traits: BTreeMap<String, Trait>,
     in_trait: bool,
     trait_name: String,
     return_pointer_area_size: usize,
     return_pointer_area_align: usize,
     sizes: SizeAlign,
+    abi_version_exported: bool,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -59,6 +67,57 @@ pub struct Opts {
     /// Generate type definitions, even if they are never used.
     #[cfg_attr(feature = "structopt", structopt(long))]
     pub force_generate_structs: bool,
+
+    /// Whether or not to export a `wai_abi_version` function returning the
+    /// revision of the canonical ABI the bindings use, which hosts check
+    /// when instantiating the module.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub export_abi_version: bool,
+
+    /// Whether or not to embed the hash of each interface in a
+    /// `wai-interface-hash:<name>` custom section, which hosts check when
+    /// instantiating the module to catch bindings generated from different
+    /// versions of an interface.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub embed_interface_hash: bool,
+
+    /// Whether or not to export a `wai_abi_descriptor:<name>` function for
+    /// each exported interface, describing the core signatures of its
+    /// functions along with its hash, which hosts can read for diagnostics
+    /// or to call the functions dynamically.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub export_abi_descriptor: bool,
+
+    /// Whether or not the strings and lists returned from exported functions
+    /// are lowered into a reusable arena rather than a fresh allocation each,
+    /// which hosts can release at once with `canonical_abi_free_return_area`.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub return_arena: bool,
+
+    /// Whether or not exported functions install a panic hook which records
+    /// the message of each panic, so hosts can retrieve why a call trapped
+    /// through `canonical_abi_take_panic`.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub panic_hook: bool,
+
+    /// Whether or not records are also exported to JavaScript as
+    /// `#[wasm_bindgen]` classes, for guests which are linked with
+    /// `wasm-bindgen` bindings too.
+    ///
+    /// Both kinds of bindings allocate with the Rust global allocator, so
+    /// values can be handed from one to the other. Classes are named after
+    /// their interface, like `ApiPoint`, so that they don't clash with each
+    /// other or the guest's own classes, and records with fields
+    /// `wasm-bindgen` can't represent, such as variants, are left alone.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub wasm_bindgen: bool,
+
+    /// Imports the functions of the interface `module` from `namespace`, and
+    /// exports them as `namespace#<func>`, rather than using the name of the
+    /// interface, given as `module=namespace`. Can be specified multiple
+    /// times.
+    #[cfg_attr(feature = "structopt", structopt(long = "map"))]
+    pub map: Vec<NamespaceMap>,
 }
 
 #[derive(Default)]
@@ -80,6 +139,49 @@ impl RustWasm {
         RustWasm::default()
     }
 
+    /// Runs `f` with the `Bindgen` that `import` or `export` would drive for
+    /// `func`, depending on the direction last passed to `preprocess_one`.
+    ///
+    /// This lets the ABI fuzzer execute this generator's own lifting and
+    /// lowering decisions without compiling the generated source.
+    #[doc(hidden)]
+    pub fn with_function_bindgen<R>(
+        &mut self,
+        iface: &Interface,
+        func: &Function,
+        f: impl FnOnce(&mut dyn Bindgen<Operand = String>) -> R,
+    ) -> R {
+        // Guest imports lower their arguments from Rust values, guest exports
+        // lift them from wasm ones.
+        let params = if self.in_import {
+            func.params
+                .iter()
+                .map(|(name, _)| to_rust_ident(name))
+                .collect()
+        } else {
+            let sig = iface.wasm_signature(AbiVariant::GuestExport, func);
+            (0..sig.params.len()).map(|i| format!("arg{}", i)).collect()
+        };
+        f(&mut FunctionBindgen::new(self, params))
+    }
+
+    /// Returns the interface defining `resource` if it's generated alongside
+    /// the current one in the same direction.
+    fn resource_owner<'a>(&self, resource: &'a Resource) -> Option<&'a str> {
+        if self.opts.standalone {
+            return None;
+        }
+        let names = if self.in_import {
+            &self.import_names
+        } else {
+            &self.export_names
+        };
+        resource
+            .foreign_module
+            .as_deref()
+            .filter(|module| names.contains(*module))
+    }
+
     fn abi_variant(dir: Direction) -> AbiVariant {
         // This generator uses the obvious direction to ABI variant mapping.
         match dir {
@@ -88,6 +190,56 @@ impl RustWasm {
         }
     }
 
+    /// Returns whether the record `id` is exported as a `#[wasm_bindgen]`
+    /// class.
+    fn wasm_bindgen_class(&self, iface: &Interface, id: TypeId) -> bool {
+        let record = match &iface.types[id].kind {
+            TypeDefKind::Record(record) => record,
+            _ => return false,
+        };
+        let info = self.info(id);
+        // Classes can't have lifetimes, and must be a single type.
+        self.opts.wasm_bindgen
+            && !info.has_handle
+            && !(self.in_import && info.has_list)
+            && self.modes_of(iface, id).len() == 1
+            && record
+                .fields
+                .iter()
+                .all(|f| self.wasm_bindgen_field(iface, &f.ty))
+    }
+
+    /// Returns whether `ty` can be the type of a field of a `#[wasm_bindgen]`
+    /// class.
+    fn wasm_bindgen_field(&self, iface: &Interface, ty: &Type) -> bool {
+        match ty {
+            Type::Unit | Type::Handle(_) => false,
+            Type::Id(id) => match &iface.types[*id].kind {
+                TypeDefKind::Type(t) => self.wasm_bindgen_field(iface, t),
+                TypeDefKind::Record(_) => self.wasm_bindgen_class(iface, *id),
+                TypeDefKind::Option(t) => match t {
+                    Type::Id(_) => false,
+                    t => self.wasm_bindgen_field(iface, t),
+                },
+                TypeDefKind::List(t) => matches!(
+                    t,
+                    Type::U8
+                        | Type::U16
+                        | Type::U32
+                        | Type::U64
+                        | Type::S8
+                        | Type::S16
+                        | Type::S32
+                        | Type::S64
+                        | Type::Float32
+                        | Type::Float64
+                ),
+                _ => false,
+            },
+            _ => true,
+        }
+    }
+
     fn ret_area_name(iface: &Interface) -> String {
         format!("{}_RET_AREA", iface.name.to_shouty_snake_case())
     }
@@ -160,6 +312,11 @@ impl RustGenerator for RustWasm {
 }
 
 impl Generator for RustWasm {
+    fn preprocess_all(&mut self, imports: &[Interface], exports: &[Interface]) {
+        self.import_names = imports.iter().map(|i| i.name.clone()).collect();
+        self.export_names = exports.iter().map(|i| i.name.clone()).collect();
+    }
+
     fn preprocess_one(&mut self, iface: &Interface, dir: Direction) {
         let variant = Self::abi_variant(dir);
         self.in_import = variant == AbiVariant::GuestImport;
@@ -173,6 +330,67 @@ impl Generator for RustWasm {
             ));
         }
 
+        self.src.push_str(&format!(
+            "const _: () = assert!(
+                wai_bindgen_rust::ABI_VERSION == {ABI_VERSION},
+                \"these bindings were generated by wai-bindgen {} for revision \
+                 {ABI_VERSION} of the canonical ABI, which doesn't match the \
+                 version of `wai-bindgen-rust` in use\",
+            );
+            /// The hash of the interface these bindings were generated from.
+            pub const INTERFACE_HASH: u64 = {:#018x};\n",
+            env!("CARGO_PKG_VERSION"),
+            interface_hash(iface),
+        ));
+        if self.opts.embed_interface_hash {
+            self.src.push_str(&format!(
+                "
+                    #[cfg(target_arch = \"wasm32\")]
+                    #[link_section = \"{INTERFACE_HASH_SECTION}:{}\"]
+                    #[used]
+                    static __WAI_BINDGEN_INTERFACE_HASH: [u8; 8] = INTERFACE_HASH.to_le_bytes();
+                ",
+                iface.name,
+            ));
+        }
+        if self.opts.export_abi_version && !self.abi_version_exported {
+            self.abi_version_exported = true;
+            self.src.push_str(&format!(
+                "
+                    #[export_name = \"{}wai_abi_version\"]
+                    extern \"C\" fn __wai_bindgen_abi_version() -> u32 {{
+                        wai_bindgen_rust::ABI_VERSION
+                    }}
+                ",
+                self.opts.symbol_namespace,
+            ));
+        }
+        if self.opts.export_abi_descriptor && !self.in_import {
+            let descriptor = abi_descriptor(iface, |func| {
+                match NamespaceMap::lookup(&self.opts.map, iface) {
+                    Some(module) => format!("{}#{}", module, func.name),
+                    None => format!("{}{}", self.opts.symbol_namespace, func.name),
+                }
+            });
+            self.src.push_str(&format!(
+                "
+                    #[export_name = \"{}{ABI_DESCRIPTOR_EXPORT}:{}\"]
+                    extern \"C\" fn __wai_bindgen_abi_descriptor_{}() -> i32 {{
+                        const DESCRIPTOR: &[u8] = &{:?};
+                        #[repr(C)]
+                        struct Descriptor(*const u8, usize);
+                        unsafe impl Sync for Descriptor {{}}
+                        static RET: Descriptor = Descriptor(DESCRIPTOR.as_ptr(), DESCRIPTOR.len());
+                        &RET as *const Descriptor as i32
+                    }}
+                ",
+                self.opts.symbol_namespace,
+                iface.name,
+                iface.name.to_snake_case(),
+                descriptor,
+            ));
+        }
+
         self.sizes.fill(iface);
     }
 
@@ -180,10 +398,17 @@ impl Generator for RustWasm {
         &mut self,
         iface: &Interface,
         id: TypeId,
-        _name: &str,
+        name: &str,
         record: &Record,
         docs: &Docs,
     ) {
+        if self.wasm_bindgen_class(iface, id) {
+            self.src.push_str(&format!(
+                "#[wasm_bindgen::prelude::wasm_bindgen(js_name = {}{}, getter_with_clone)]\n",
+                iface.name.to_camel_case(),
+                name.to_camel_case(),
+            ));
+        }
         self.print_typedef_record(iface, id, record, docs);
     }
 
@@ -288,6 +513,19 @@ impl Generator for RustWasm {
     }
 
     fn type_resource(&mut self, iface: &Interface, ty: ResourceId) {
+        // Resources `use`d from an interface generated alongside this one are
+        // defined there, so that handles are interchangeable between both.
+        if let Some(owner) = self.resource_owner(&iface.resources[ty]) {
+            if self.in_import {
+                self.src.push_str(&format!(
+                    "pub use super::{}::{};\n",
+                    owner.to_snake_case(),
+                    iface.resources[ty].name.to_camel_case(),
+                ));
+            }
+            return;
+        }
+
         // For exported handles we synthesize some trait implementations
         // automatically for runtime-required traits.
         if !self.in_import {
@@ -391,8 +629,18 @@ impl Generator for RustWasm {
         self.rustdoc(&resource.docs);
         self.src.push_str("#[derive(Debug)]\n");
         self.src.push_str("#[repr(transparent)]\n");
-        self.src
-            .push_str(&format!("pub struct {}(i32);\n", name.to_camel_case()));
+        // The field is visible to sibling modules so that the bindings of
+        // other interfaces `use`ing this resource can pass its handles along.
+        let vis = if self.opts.standalone {
+            ""
+        } else {
+            "pub(super) "
+        };
+        self.src.push_str(&format!(
+            "pub struct {}({}i32);\n",
+            name.to_camel_case(),
+            vis
+        ));
         self.src.push_str("impl ");
         self.src.push_str(&name.to_camel_case());
         self.src.push_str(
@@ -532,7 +780,7 @@ impl Generator for RustWasm {
         let iface_name = iface.name.to_snake_case();
 
         self.src.push_str("#[export_name = \"");
-        match &iface.module {
+        match NamespaceMap::lookup(&self.opts.map, iface) {
             Some(module) => {
                 self.src.push_str(module);
                 self.src.push_str("#");
@@ -581,6 +829,11 @@ impl Generator for RustWasm {
             self.src.push_str("::*;\n");
         }
 
+        if self.opts.panic_hook {
+            self.src
+                .push_str("wai_bindgen_rust::install_panic_hook();\n");
+        }
+
         if func.is_async {
             self.src.push_str("let future = async move {\n");
         }
@@ -657,7 +910,7 @@ impl Generator for RustWasm {
         let mut src = mem::take(&mut self.src);
 
         let any_async = iface.functions.iter().any(|f| f.is_async);
-        for (name, trait_) in self.traits.iter() {
+        for (name, trait_) in mem::take(&mut self.traits).iter() {
             if any_async {
                 src.push_str("#[wai_bindgen_rust::async_trait(?Send)]\n");
             }
@@ -714,7 +967,7 @@ impl Generator for RustWasm {
             assert!(status.success());
         }
 
-        files.push("bindings.rs", src.as_bytes());
+        files.push_for_interface(&iface.name.to_snake_case(), "rs", src.as_bytes());
     }
 }
 
@@ -763,18 +1016,20 @@ impl FunctionBindgen<'_> {
         params: &[WasmType],
         results: &[WasmType],
     ) -> String {
-        let module = iface.module.as_deref().unwrap_or(&iface.name);
+        let module = NamespaceMap::lookup(&self.gen.opts.map, iface)
+            .unwrap_or(&iface.name)
+            .to_string();
 
         // Define the actual function we're calling inline
         self.push_str("#[link(wasm_import_module = \"");
-        self.push_str(module);
+        self.push_str(&module);
         self.push_str("\")]\n");
         self.push_str("extern \"C\" {\n");
         self.push_str("#[cfg_attr(target_arch = \"wasm32\", link_name = \"");
         self.push_str(name);
         self.push_str("\")]\n");
         self.push_str("#[cfg_attr(not(target_arch = \"wasm32\"), link_name = \"");
-        self.push_str(module);
+        self.push_str(&module);
         self.push_str("_");
         self.push_str(name);
         self.push_str("\")]\n");
@@ -876,7 +1131,7 @@ impl Bindgen for FunctionBindgen<'_> {
     }
 
     fn is_list_canonical(&self, iface: &Interface, ty: &Type) -> bool {
-        iface.all_bits_valid(ty)
+        is_list_canonical(iface, ty)
     }
 
     fn emit(
@@ -1290,15 +1545,25 @@ impl Bindgen for FunctionBindgen<'_> {
                 let val = format!("vec{}", tmp);
                 let ptr = format!("ptr{}", tmp);
                 let len = format!("len{}", tmp);
-                if realloc.is_none() {
+                // With a return arena the data is copied into it, otherwise
+                // ownership of the allocation is handed to the caller.
+                let arena = realloc.is_some() && self.gen.opts.return_arena;
+                if realloc.is_none() || arena {
                     self.push_str(&format!("let {} = {};\n", val, operands[0]));
                 } else {
                     let op0 = operands.pop().unwrap();
                     self.push_str(&format!("let {} = ({}).into_boxed_slice();\n", val, op0));
                 }
-                self.push_str(&format!("let {} = {}.as_ptr() as i32;\n", ptr, val));
+                if arena {
+                    self.push_str(&format!(
+                        "let {} = wai_bindgen_rust::rt::return_area_copy(&{}) as i32;\n",
+                        ptr, val
+                    ));
+                } else {
+                    self.push_str(&format!("let {} = {}.as_ptr() as i32;\n", ptr, val));
+                }
                 self.push_str(&format!("let {} = {}.len() as i32;\n", len, val));
-                if realloc.is_some() {
+                if realloc.is_some() && !arena {
                     self.push_str(&format!("core::mem::forget({});\n", val));
                 }
                 results.push(ptr);
@@ -1324,15 +1589,23 @@ impl Bindgen for FunctionBindgen<'_> {
                 let val = format!("vec{}", tmp);
                 let ptr = format!("ptr{}", tmp);
                 let len = format!("len{}", tmp);
-                if realloc.is_none() {
+                let arena = realloc.is_some() && self.gen.opts.return_arena;
+                if realloc.is_none() || arena {
                     self.push_str(&format!("let {} = {};\n", val, operands[0]));
                 } else {
                     let op0 = format!("{}.into_bytes()", operands[0]);
                     self.push_str(&format!("let {} = ({}).into_boxed_slice();\n", val, op0));
                 }
-                self.push_str(&format!("let {} = {}.as_ptr() as i32;\n", ptr, val));
+                if arena {
+                    self.push_str(&format!(
+                        "let {} = wai_bindgen_rust::rt::return_area_copy({}.as_bytes()) as i32;\n",
+                        ptr, val
+                    ));
+                } else {
+                    self.push_str(&format!("let {} = {}.as_ptr() as i32;\n", ptr, val));
+                }
                 self.push_str(&format!("let {} = {}.len() as i32;\n", len, val));
-                if realloc.is_some() {
+                if realloc.is_some() && !arena {
                     self.push_str(&format!("core::mem::forget({});\n", val));
                 }
                 results.push(ptr);
@@ -1368,18 +1641,25 @@ impl Bindgen for FunctionBindgen<'_> {
                 self.push_str(&format!("let {} = {}.len() as i32;\n", len, vec));
                 let size = self.gen.sizes.size(element);
                 let align = self.gen.sizes.align(element);
-                self.push_str(&format!(
-                    "let {} = core::alloc::Layout::from_size_align_unchecked({}.len() * {}, {});\n",
-                    layout, vec, size, align,
-                ));
-                self.push_str(&format!(
-                    "let {} = std::alloc::alloc({});\n",
-                    result, layout,
-                ));
-                self.push_str(&format!(
-                    "if {}.is_null() {{ std::alloc::handle_alloc_error({}); }}\n",
-                    result, layout,
-                ));
+                if realloc.is_some() && self.gen.opts.return_arena {
+                    self.push_str(&format!(
+                        "let {} = wai_bindgen_rust::rt::return_area_alloc({}.len() * {}, {});\n",
+                        result, vec, size, align,
+                    ));
+                } else {
+                    self.push_str(&format!(
+                        "let {} = core::alloc::Layout::from_size_align_unchecked({}.len() * {}, {});\n",
+                        layout, vec, size, align,
+                    ));
+                    self.push_str(&format!(
+                        "let {} = std::alloc::alloc({});\n",
+                        result, layout,
+                    ));
+                    self.push_str(&format!(
+                        "if {}.is_null() {{ std::alloc::handle_alloc_error({}); }}\n",
+                        result, layout,
+                    ));
+                }
                 self.push_str(&format!(
                     "for (i, e) in {}.into_iter().enumerate() {{\n",
                     vec
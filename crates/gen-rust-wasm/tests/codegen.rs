@@ -30,3 +30,104 @@ mod exports {
         "!host.wai"
     );
 }
+
+mod export_impl {
+    use wai_bindgen_rust::wai;
+
+    struct Api;
+
+    #[wai::export({
+        src["api"]: "
+            record point { x: u32, y: u32 }
+            resource counter {
+                get: func() -> u32
+            }
+            swap: func(p: point) -> point
+            count: func(c: counter) -> u32
+        ",
+    })]
+    impl Api {
+        pub fn swap(p: api::Point) -> api::Point {
+            api::Point { x: p.y, y: p.x }
+        }
+
+        fn count(c: wai_bindgen_rust::Handle<Counter>) -> u32 {
+            api::Counter::get(&*c)
+        }
+    }
+
+    pub struct Counter;
+
+    impl api::Counter for Counter {
+        fn get(&self) -> u32 {
+            0
+        }
+    }
+}
+
+mod multiple_inputs {
+    wai_bindgen_rust::import!({
+        paths: ["../../tests/codegen/simple-*.wai", "../../tests/codegen/char.wai"],
+        src["inline"]: "f: func()",
+    });
+
+    fn check() {
+        let _ = (simple_functions::f1, simple_lists::simple_list1);
+        let _ = (char::take_char, inline::f);
+    }
+}
+
+mod return_arena {
+    wai_bindgen_rust::export!({
+        src["api"]: "
+            record entry { key: string, values: list<u32> }
+            entries: func() -> list<entry>
+            name: func() -> string
+        ",
+        return_arena,
+    });
+
+    struct Api;
+
+    impl api::Api for Api {
+        fn entries() -> Vec<api::Entry> {
+            Vec::new()
+        }
+
+        fn name() -> String {
+            String::new()
+        }
+    }
+}
+
+mod wasm_bindgen_classes {
+    wai_bindgen_rust::export!({
+        src["api"]: "
+            record point { x: u32, y: float64, label: option<string> }
+            variant shape { point(point), none }
+            record drawing { shape: shape }
+            center: func(d: drawing) -> point
+        ",
+        wasm_bindgen,
+    });
+
+    struct Api;
+
+    impl api::Api for Api {
+        fn center(_: api::Drawing) -> api::Point {
+            api::Point {
+                x: 0,
+                y: 0.0,
+                label: None,
+            }
+        }
+    }
+
+    // `Point` is a class, which can be returned to JavaScript directly.
+    #[wasm_bindgen::prelude::wasm_bindgen]
+    pub fn origin() -> api::Point {
+        <Api as api::Api>::center(api::Drawing {
+            shape: api::Shape::None,
+        })
+    }
+}
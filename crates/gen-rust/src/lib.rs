@@ -1,6 +1,5 @@
 use heck::*;
-use std::collections::HashMap;
-use std::fmt::{self, Write};
+use std::fmt;
 use std::iter::zip;
 use wai_bindgen_gen_core::wai_parser::abi::{Bitcast, LiftLower, WasmType};
 use wai_bindgen_gen_core::{wai_parser::*, TypeInfo, Types};
@@ -39,14 +38,36 @@ pub trait RustGenerator {
     }
 
     fn rustdoc(&mut self, docs: &Docs) {
-        let docs = match &docs.contents {
-            Some(docs) => docs,
-            None => return,
-        };
-        for line in docs.trim().lines() {
-            self.push_str("/// ");
-            self.push_str(line);
-            self.push_str("\n");
+        let mut empty = true;
+        if let Some(contents) = &docs.contents {
+            for line in contents.trim().lines() {
+                self.push_str("/// ");
+                self.push_str(line);
+                self.push_str("\n");
+            }
+            empty = false;
+        }
+        for example in docs.examples.iter() {
+            // Rust examples can't know the path the bindings are generated
+            // at, so they're not run as doctests.
+            let fence = if example.is_in(&["rust", "rs"]) {
+                "ignore"
+            } else if example.lang.is_none() {
+                "text"
+            } else {
+                continue;
+            };
+            if !empty {
+                self.push_str("///\n");
+            }
+            self.push_str(&format!("/// ```{fence}\n"));
+            for line in example.code.lines() {
+                self.push_str("/// ");
+                self.push_str(line);
+                self.push_str("\n");
+            }
+            self.push_str("/// ```\n");
+            empty = false;
         }
     }
 
@@ -141,17 +162,30 @@ pub trait RustGenerator {
                 params.push("self".to_string());
                 continue;
             }
-            let name = to_rust_ident(name);
-            self.push_str(&name);
-            params.push(name);
+            let ident = to_rust_ident(name);
+            self.push_str(&ident);
+            params.push(ident);
             self.push_str(": ");
-            self.print_ty(iface, param, param_mode);
+            self.print_param_ty(iface, func, name, param, param_mode);
             self.push_str(",");
         }
         self.push_str(")");
         params
     }
 
+    /// Prints the type of the parameter `name` of `func`, which is `ty` in
+    /// `mode` unless a generator represents that parameter differently.
+    fn print_param_ty(
+        &mut self,
+        iface: &Interface,
+        _func: &Function,
+        _name: &str,
+        ty: &Type,
+        mode: TypeMode,
+    ) {
+        self.print_ty(iface, ty, mode);
+    }
+
     fn print_ty(&mut self, iface: &Interface, ty: &Type, mode: TypeMode) {
         match ty {
             Type::Id(t) => self.print_tyid(iface, *t, mode),
@@ -317,12 +351,10 @@ pub trait RustGenerator {
                 self.print_borrowed_slice(iface, false, ty, lt);
             }
             TypeMode::LeafBorrowed(lt) => {
-                if iface.all_bits_valid(ty) {
+                if is_list_canonical(iface, ty) {
                     self.print_borrowed_slice(iface, false, ty, lt);
                 } else {
-                    self.push_str("Vec<");
-                    self.print_ty(iface, ty, mode);
-                    self.push_str(">");
+                    self.print_lifted_list(iface, ty, mode);
                 }
             }
             TypeMode::HandlesBorrowed(_) | TypeMode::Owned => {
@@ -333,6 +365,15 @@ pub trait RustGenerator {
         }
     }
 
+    /// Prints the type of a list of `ty`s which can't be borrowed from
+    /// memory in `mode`, which is a `Vec` unless a generator lifts it
+    /// somewhere else.
+    fn print_lifted_list(&mut self, iface: &Interface, ty: &Type, mode: TypeMode) {
+        self.push_str("Vec<");
+        self.print_ty(iface, ty, mode);
+        self.push_str(">");
+    }
+
     fn print_rust_slice(
         &mut self,
         iface: &Interface,
@@ -400,108 +441,9 @@ pub trait RustGenerator {
     }
 
     /// Writes the camel-cased 'name' of the passed type to `out`, as used to name union variants.
-    fn write_name(&self, iface: &Interface, ty: &Type, out: &mut String) {
-        match ty {
-            Type::Unit => out.push_str("Unit"),
-            Type::Bool => out.push_str("Bool"),
-            Type::U8 => out.push_str("U8"),
-            Type::U16 => out.push_str("U16"),
-            Type::U32 => out.push_str("U32"),
-            Type::U64 => out.push_str("U64"),
-            Type::S8 => out.push_str("I8"),
-            Type::S16 => out.push_str("I16"),
-            Type::S32 => out.push_str("I32"),
-            Type::S64 => out.push_str("I64"),
-            Type::Float32 => out.push_str("F32"),
-            Type::Float64 => out.push_str("F64"),
-            Type::Char => out.push_str("Char"),
-            Type::String => out.push_str("String"),
-            Type::Handle(id) => out.push_str(&iface.resources[*id].name.to_camel_case()),
-            Type::Id(id) => {
-                let ty = &iface.types[*id];
-                match &ty.name {
-                    Some(name) => out.push_str(&name.to_camel_case()),
-                    None => match &ty.kind {
-                        TypeDefKind::Option(ty) => {
-                            out.push_str("Optional");
-                            self.write_name(iface, ty, out);
-                        }
-                        TypeDefKind::Expected(_) => out.push_str("Result"),
-                        TypeDefKind::Tuple(_) => out.push_str("Tuple"),
-                        TypeDefKind::List(ty) => {
-                            self.write_name(iface, ty, out);
-                            out.push_str("List")
-                        }
-                        TypeDefKind::Future(ty) => {
-                            self.write_name(iface, ty, out);
-                            out.push_str("Future");
-                        }
-                        TypeDefKind::Stream(s) => {
-                            self.write_name(iface, &s.element, out);
-                            self.write_name(iface, &s.end, out);
-                            out.push_str("Stream");
-                        }
-
-                        TypeDefKind::Type(ty) => self.write_name(iface, ty, out),
-                        TypeDefKind::Record(_) => out.push_str("Record"),
-                        TypeDefKind::Flags(_) => out.push_str("Flags"),
-                        TypeDefKind::Variant(_) => out.push_str("Variant"),
-                        TypeDefKind::Enum(_) => out.push_str("Enum"),
-                        TypeDefKind::Union(_) => out.push_str("Union"),
-                    },
-                }
-            }
-        }
-    }
-
     /// Returns the names for the cases of the passed union.
     fn union_case_names(&self, iface: &Interface, union: &Union) -> Vec<String> {
-        enum UsedState<'a> {
-            /// This name has been used once before.
-            ///
-            /// Contains a reference to the name given to the first usage so that a suffix can be added to it.
-            Once(&'a mut String),
-            /// This name has already been used multiple times.
-            ///
-            /// Contains the number of times this has already been used.
-            Multiple(usize),
-        }
-
-        // A `Vec` of the names we're assigning each of the union's cases in order.
-        let mut case_names = vec![String::new(); union.cases.len()];
-        // A map from case names to their `UsedState`.
-        let mut used = HashMap::new();
-        for (case, name) in union.cases.iter().zip(case_names.iter_mut()) {
-            self.write_name(iface, &case.ty, name);
-
-            match used.get_mut(name.as_str()) {
-                None => {
-                    // Initialise this name's `UsedState`, with a mutable reference to this name
-                    // in case we have to add a suffix to it later.
-                    used.insert(name.clone(), UsedState::Once(name));
-                    // Since this is the first (and potentially only) usage of this name,
-                    // we don't need to add a suffix here.
-                }
-                Some(state) => match state {
-                    UsedState::Multiple(n) => {
-                        // Add a suffix of the index of this usage.
-                        write!(name, "{n}").unwrap();
-                        // Add one to the number of times this type has been used.
-                        *n += 1;
-                    }
-                    UsedState::Once(first) => {
-                        // Add a suffix of 0 to the first usage.
-                        first.push('0');
-                        // We now get a suffix of 1.
-                        name.push('1');
-                        // Then update the state.
-                        *state = UsedState::Multiple(2);
-                    }
-                },
-            }
-        }
-
-        case_names
+        wai_bindgen_gen_core::union_case_names(iface, union)
     }
 
     fn print_typedef_record(
@@ -554,6 +496,10 @@ pub trait RustGenerator {
             self.push_str(".finish()");
             self.push_str("}\n");
             self.push_str("}\n");
+
+            if info.error {
+                self.print_error_impls(id, mode, &name);
+            }
         }
     }
 
@@ -590,6 +536,7 @@ pub trait RustGenerator {
                 .iter()
                 .map(|c| (c.name.to_camel_case(), &c.docs, &c.ty)),
             docs,
+            variant.non_exhaustive,
         );
     }
 
@@ -597,13 +544,49 @@ pub trait RustGenerator {
     where
         Self: Sized,
     {
+        let case_names = self.union_case_names(iface, union);
         self.print_rust_enum(
             iface,
             id,
-            zip(self.union_case_names(iface, union), &union.cases)
-                .map(|(name, case)| (name, &case.docs, &case.ty)),
+            zip(case_names.clone(), &union.cases).map(|(name, case)| (name, &case.docs, &case.ty)),
             docs,
+            false,
         );
+
+        // Accessors for the payload of each case, since matching on a union
+        // to get at one of its cases is verbose.
+        let info = self.info(id);
+        for (name, mode) in self.modes_of(iface, id) {
+            let name = name.to_camel_case();
+            let lt = self.lifetime_for(&info, mode);
+            self.push_str("impl");
+            self.print_generics(&info, lt, true);
+            self.push_str(&format!(" {name}"));
+            self.print_generics(&info, lt, false);
+            self.push_str(" {\n");
+            for (case_name, case) in zip(&case_names, &union.cases) {
+                if case.ty == Type::Unit {
+                    continue;
+                }
+                self.push_str(&format!(
+                    "/// Returns the payload of this union if it's the `{name}::{case_name}` case.\n"
+                ));
+                self.push_str(&format!(
+                    "pub fn as_{}(&self) -> Option<&",
+                    case_name.to_snake_case()
+                ));
+                self.print_ty(iface, &case.ty, mode);
+                self.push_str("> {\n");
+                self.push_str("match self {\n");
+                self.push_str(&format!("{name}::{case_name}(e) => Some(e),\n"));
+                if union.cases.len() > 1 {
+                    self.push_str("_ => None,\n");
+                }
+                self.push_str("}\n");
+                self.push_str("}\n");
+            }
+            self.push_str("}\n");
+        }
     }
 
     fn print_rust_enum<'a>(
@@ -612,6 +595,7 @@ pub trait RustGenerator {
         id: TypeId,
         cases: impl IntoIterator<Item = (String, &'a Docs, &'a Type)> + Clone,
         docs: &Docs,
+        non_exhaustive: bool,
     ) where
         Self: Sized,
     {
@@ -626,6 +610,9 @@ pub trait RustGenerator {
             } else if !info.has_handle {
                 self.push_str("#[derive(Clone)]\n");
             }
+            if non_exhaustive {
+                self.push_str("#[non_exhaustive]\n");
+            }
             self.push_str(&format!("pub enum {name}"));
             self.print_generics(&info, lt, true);
             self.push_str("{\n");
@@ -650,6 +637,10 @@ pub trait RustGenerator {
                     .into_iter()
                     .map(|(name, _docs, ty)| (name, ty)),
             );
+
+            if info.error {
+                self.print_error_impls(id, mode, &name);
+            }
         }
     }
 
@@ -692,6 +683,30 @@ pub trait RustGenerator {
         self.push_str("}\n");
     }
 
+    /// Implements `std::error::Error` for `name`, a type used as the error of
+    /// an `expected`, displaying it the same way as its `Debug` impl.
+    fn print_error_impls(&mut self, id: TypeId, mode: TypeMode, name: &str) {
+        let info = self.info(id);
+        let lt = self.lifetime_for(&info, mode);
+        self.push_str("impl");
+        self.print_generics(&info, lt, true);
+        self.push_str(" core::fmt::Display for ");
+        self.push_str(name);
+        self.print_generics(&info, lt, false);
+        self.push_str(" {\n");
+        self.push_str("fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {\n");
+        self.push_str("core::fmt::Debug::fmt(self, f)\n");
+        self.push_str("}\n");
+        self.push_str("}\n");
+        self.push_str("\n");
+        self.push_str("impl");
+        self.print_generics(&info, lt, true);
+        self.push_str(" std::error::Error for ");
+        self.push_str(name);
+        self.print_generics(&info, lt, false);
+        self.push_str(" {}\n");
+    }
+
     fn print_typedef_option(&mut self, iface: &Interface, id: TypeId, payload: &Type, docs: &Docs) {
         let info = self.info(id);
 
@@ -733,13 +748,16 @@ pub trait RustGenerator {
         Self: Sized,
     {
         // TODO: should this perhaps be an attribute in the wai file?
-        let is_error = name.contains("errno");
+        let is_error = name.contains("errno") || self.info(id).error;
 
         let name = name.to_camel_case();
         self.rustdoc(docs);
         self.push_str("#[repr(");
         self.int_repr(enum_.tag());
         self.push_str(")]\n#[derive(Clone, Copy, PartialEq, Eq)]\n");
+        if enum_.non_exhaustive {
+            self.push_str("#[non_exhaustive]\n");
+        }
         self.push_str(&format!("pub enum {} {{\n", name.to_camel_case()));
         for case in enum_.cases.iter() {
             self.rustdoc(&case.docs);
@@ -749,7 +767,8 @@ pub trait RustGenerator {
         self.push_str("}\n");
 
         // Auto-synthesize an implementation of the standard `Error` trait for
-        // error-looking types based on their name.
+        // error-looking types based on their name, and for the errors of
+        // `expected`s.
         if is_error {
             self.push_str("impl ");
             self.push_str(&name);
@@ -1079,6 +1098,31 @@ pub fn to_rust_ident(name: &str) -> String {
     }
 }
 
+/// Returns whether a list of `ty` has the same layout in Rust as in the
+/// canonical ABI, and can therefore be copied to and from linear memory in
+/// bulk.
+///
+/// This is `Interface::all_bits_valid` except for tuples of more than one
+/// element, which Rust is free to reorder since they aren't `#[repr(C)]`.
+pub fn is_list_canonical(iface: &Interface, ty: &Type) -> bool {
+    if !iface.all_bits_valid(ty) {
+        return false;
+    }
+    match ty {
+        Type::Id(id) => match &iface.types[*id].kind {
+            TypeDefKind::Type(t) => is_list_canonical(iface, t),
+            TypeDefKind::Record(r) => r.fields.iter().all(|f| is_list_canonical(iface, &f.ty)),
+            TypeDefKind::Tuple(t) => match t.types.as_slice() {
+                [] => true,
+                [t] => is_list_canonical(iface, t),
+                _ => false,
+            },
+            _ => true,
+        },
+        _ => true,
+    }
+}
+
 pub fn wasm_type(ty: WasmType) -> &'static str {
     match ty {
         WasmType::I32 => "i32",
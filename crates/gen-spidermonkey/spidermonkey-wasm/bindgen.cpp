@@ -5,7 +5,9 @@
 
 #include <assert.h>
 #include <cmath>
+#include <stdint.h>
 #include <stdlib.h>
+#include <vector>
 
 #include "smw/abort.h"
 #include "smw/cx.h"
@@ -14,10 +16,12 @@
 
 #include "mozilla/UniquePtr.h"
 #include "jsapi.h"
+#include "jsfriendapi.h"
 #include "js/Array.h"
 #include "js/Conversions.h"
 #include "js/ForOfIterator.h"
 #include "js/Modules.h"
+#include "js/Promise.h"
 
 #ifdef LOGGING
 #include "js/friend/DumpFunctions.h"
@@ -38,6 +42,13 @@ static PersistentRootedValueVector* ARGS;
 // Used for holding returns from Wasm calls.
 static PersistentRootedValueVector* RETS;
 
+// The promises for async imports which haven't completed yet, indexed by the
+// context passed to their completion callbacks. Free slots are `undefined`.
+static PersistentRootedValueVector* PROMISES;
+
+// Free slots in `PROMISES` to reuse.
+static std::vector<uint32_t> FREE_PROMISES;
+
 void init_operands(JSContext* cx) {
     assert(!OPERANDS && "OPERANDS must only be initialized once");
     OPERANDS = new PersistentRootedValueVector(cx, cx);
@@ -56,6 +67,12 @@ void init_operands(JSContext* cx) {
     if (!RETS) {
         abort(cx, "failed to allocate RETS");
     }
+
+    assert(!PROMISES && "PROMISES must only be initialized once");
+    PROMISES = new PersistentRootedValueVector(cx, cx);
+    if (!PROMISES) {
+        abort(cx, "failed to allocate PROMISES");
+    }
 }
 
 PersistentRootedValueVector& operands() {
@@ -101,6 +118,51 @@ PersistentRootedValueVector& rets() {
     return *RETS;
 }
 
+PersistentRootedValueVector& promises() {
+    assert(PROMISES && PROMISES->initialized() && "PROMISES must be initialized");
+    return *PROMISES;
+}
+
+/**
+ * The handle table for one resource type that is implemented in JS.
+ *
+ * Every JS object that is handed out to the host as a resource is given a slot
+ * in this table, and the slot's index is the representation passed to the
+ * host's `resource_new_*` intrinsic. The host creates a handle each time the
+ * object is handed out, and calls `canonical_abi_drop_*` with the
+ * representation once each of those is dropped, so slots count the handles
+ * that refer to them and are only freed once all of them are gone.
+ */
+struct ResourceTable {
+    // The object in each slot, or `undefined` for free slots.
+    PersistentRootedValueVector* objects;
+    // The number of host handles referring to each slot.
+    std::vector<uint32_t> refs;
+    // Free slots to reuse.
+    std::vector<uint32_t> free;
+};
+
+// Indexed by the `ResourceId` of each resource in the exported interface.
+static std::vector<ResourceTable> RESOURCES;
+
+ResourceTable& resource_table(size_t resource) {
+    JSContext* cx = get_js_context();
+    while (RESOURCES.size() <= resource) {
+        PersistentRootedValueVector* objects = new PersistentRootedValueVector(cx, cx);
+        if (!objects) {
+            abort(cx, "failed to allocate a resource table");
+        }
+        RESOURCES.push_back(ResourceTable { objects, {}, {} });
+    }
+    return RESOURCES[resource];
+}
+
+void check_resource_rep(ResourceTable& table, uint32_t rep) {
+    if (rep >= table.refs.size() || table.refs[rep] == 0) {
+        abort("invalid resource representation");
+    }
+}
+
 WASM_EXPORT
 void canonical_abi_free(void* ptr, size_t size, size_t align) {
     (void) size;
@@ -449,4 +511,154 @@ void SMW_array_push(size_t array, size_t elem) {
     }
 }
 
+WASM_EXPORT
+uint32_t SMW_new_promise(size_t dest) {
+    SMW_LOG("SMW_new_promise(dest = %zu)\n", dest);
+
+    JSContext* cx = get_js_context();
+    JS::RootedObject promise(cx, JS::NewPromiseObject(cx, nullptr));
+    if (!promise) {
+        abort(cx, "failed to create promise for async import");
+    }
+    JS::RootedValue promiseVal(cx, JS::ObjectValue(*promise));
+    save_operand(dest, promiseVal);
+
+    uint32_t ctx;
+    if (FREE_PROMISES.empty()) {
+        ctx = uint32_t(promises().length());
+        if (!promises().append(promiseVal)) {
+            abort(cx, "failed to save promise for async import");
+        }
+    } else {
+        ctx = FREE_PROMISES.back();
+        FREE_PROMISES.pop_back();
+        promises()[ctx].set(promiseVal);
+    }
+    return ctx;
+}
+
+WASM_EXPORT
+void SMW_resolve_promise(uint32_t ctx, size_t i) {
+    SMW_LOG("SMW_resolve_promise(ctx = %u, i = %zu)\n", ctx, i);
+
+    JSContext* cx = get_js_context();
+    if (ctx >= promises().length() || promises()[ctx].isUndefined()) {
+        abort(cx, "invalid async import context");
+    }
+    JS::RootedObject promise(cx, &promises()[ctx].toObject());
+    promises()[ctx].setUndefined();
+    FREE_PROMISES.push_back(ctx);
+
+    // Async imports without results resolve to `undefined`.
+    JS::RootedValue val(cx);
+    if (i != SIZE_MAX) {
+        val.set(operands()[i]);
+    }
+    if (!JS::ResolvePromise(cx, promise, val)) {
+        abort(cx, "failed to resolve promise for async import");
+    }
+
+    // Run the promise's reactions, which continue any JS awaiting it.
+    js::RunJobs(cx);
+}
+
+WASM_EXPORT
+uint32_t SMW_resource_rep(size_t resource, size_t i) {
+    SMW_LOG("SMW_resource_rep(resource = %zu, i = %zu)\n", resource, i);
+
+    JSContext* cx = get_js_context();
+    JS::RootedValue val(cx, operands()[i]);
+    if (!val.isObject()) {
+        abort(cx, "resource value is not an object");
+    }
+
+    ResourceTable& table = resource_table(resource);
+
+    // Hand out the same representation for an object which the host still
+    // holds handles to, so that it keeps its identity.
+    //
+    // TODO: use a side table rather than a linear search if this shows up in
+    // profiles.
+    for (uint32_t rep = 0; rep < table.refs.size(); rep++) {
+        if (table.refs[rep] == 0) {
+            continue;
+        }
+        const JS::Value& obj = (*table.objects)[rep];
+        if (&obj.toObject() == &val.toObject()) {
+            table.refs[rep]++;
+            return rep;
+        }
+    }
+
+    uint32_t rep;
+    if (table.free.empty()) {
+        rep = uint32_t(table.refs.size());
+        if (!table.objects->append(val)) {
+            abort(cx, "failed to grow resource table");
+        }
+        table.refs.push_back(1);
+    } else {
+        rep = table.free.back();
+        table.free.pop_back();
+        (*table.objects)[rep].set(val);
+        table.refs[rep] = 1;
+    }
+    return rep;
+}
+
+WASM_EXPORT
+void SMW_resource_get(uint32_t rep, size_t resource, size_t dest) {
+    SMW_LOG("SMW_resource_get(rep = %u, resource = %zu, dest = %zu)\n", rep, resource, dest);
+
+    JSContext* cx = get_js_context();
+    ResourceTable& table = resource_table(resource);
+    check_resource_rep(table, rep);
+    JS::RootedValue val(cx, (*table.objects)[rep]);
+    save_operand(dest, val);
+}
+
+WASM_EXPORT
+void SMW_resource_drop(uint32_t rep, size_t resource, char* hookName, size_t hookNameLen) {
+    SMW_LOG("SMW_resource_drop(rep = %u, resource = %zu)\n", rep, resource);
+
+    UniqueChars uniqHookName(hookName);
+
+    JSContext* cx = get_js_context();
+    ResourceTable& table = resource_table(resource);
+    check_resource_rep(table, rep);
+
+    table.refs[rep]--;
+    if (table.refs[rep] > 0) {
+        return;
+    }
+    JS::RootedValue val(cx, (*table.objects)[rep]);
+    (*table.objects)[rep].setUndefined();
+    table.free.push_back(rep);
+
+    // Let the user module clean up after the resource, if it exports a hook
+    // for doing so.
+    JS::RootedString hookNameAtom(cx, JS_AtomizeStringN(cx, uniqHookName.get(), hookNameLen));
+    if (!hookNameAtom) {
+        abort(cx, "failed to atomize drop hook name");
+    }
+    JS::RootedObject module(cx, get_user_module());
+    JS::RootedValue hookVal(cx);
+    bool hasHook = false;
+    if (!JS::GetModuleExport(cx, module, hookNameAtom, &hookVal, &hasHook)) {
+        abort(cx, "failed to get module export");
+    }
+    if (!hasHook) {
+        return;
+    }
+    JS::RootedFunction hook(cx, JS_ValueToFunction(cx, hookVal));
+    if (!hook) {
+        abort(cx, "exported resource drop hook is not a function");
+    }
+    JS::RootedObject thisObj(cx);
+    JS::RootedValue result(cx);
+    if (!JS::Call(cx, thisObj, hook, JS::HandleValueArray(val), &result)) {
+        abort(cx, "calling resource drop hook failed");
+    }
+}
+
 } // namespace smw
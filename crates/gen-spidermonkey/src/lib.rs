@@ -30,6 +30,10 @@ pub struct Opts {
     pub js: PathBuf,
     #[cfg_attr(feature = "structopt", structopt(long))]
     pub import_spidermonkey: bool,
+    /// Import the linear memory shared with `spidermonkey.wasm`, for builds
+    /// of it which import their memory rather than defining it.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub import_memory: bool,
 }
 
 #[allow(missing_docs)]
@@ -37,6 +41,7 @@ impl Opts {
     pub fn build<'a>(self, js_source: impl Into<Cow<'a, str>>) -> SpiderMonkeyWasm<'a> {
         let mut builder = SpiderMonkeyWasm::new(self.js, js_source);
         builder.import_spidermonkey(self.import_spidermonkey);
+        builder.import_memory(self.import_memory);
         builder
     }
 }
@@ -251,6 +256,51 @@ lazy_static! {
                 indirect_params: false,
             },
         ),
+        (
+            "SMW_resource_rep",
+            WasmSignature {
+                params: vec![WasmType::I32, WasmType::I32],
+                results: vec![WasmType::I32],
+                retptr: false,
+                indirect_params: false,
+            },
+        ),
+        (
+            "SMW_resource_get",
+            WasmSignature {
+                params: vec![WasmType::I32, WasmType::I32, WasmType::I32],
+                results: vec![],
+                retptr: false,
+                indirect_params: false,
+            },
+        ),
+        (
+            "SMW_resource_drop",
+            WasmSignature {
+                params: vec![WasmType::I32, WasmType::I32, WasmType::I32, WasmType::I32],
+                results: vec![],
+                retptr: false,
+                indirect_params: false,
+            },
+        ),
+        (
+            "SMW_new_promise",
+            WasmSignature {
+                params: vec![WasmType::I32],
+                results: vec![WasmType::I32],
+                retptr: false,
+                indirect_params: false,
+            },
+        ),
+        (
+            "SMW_resolve_promise",
+            WasmSignature {
+                params: vec![WasmType::I32, WasmType::I32],
+                results: vec![],
+                retptr: false,
+                indirect_params: false,
+            },
+        ),
         (
             "dump_i32",
             WasmSignature {
@@ -315,6 +365,14 @@ lazy_static! {
 ///   This is the actual Wasm function whose signature is described in WAI and
 ///   uses the canonical ABI.
 ///
+/// Async imports return a promise to the JS caller instead of their results.
+/// The import glue passes the imported function a completion callback, which
+/// is a synthesized function in the exported `__indirect_function_table`, and
+/// the host calls it with the results once the import finishes. The callback
+/// translates the results into SpiderMonkey values, resolves the promise with
+/// them, and runs the pending JS jobs so that code awaiting the promise
+/// continues.
+///
 /// ### Exports
 ///
 /// By the time an exported JS function that implements a WAI signature is
@@ -342,6 +400,18 @@ lazy_static! {
 ///   This is the user-written JavaScript function that is being exported. It
 ///   accepts and returns the JavaScript values that correspond to the interface
 ///   types used in the WAI signature.
+///
+/// ### Resources
+///
+/// Resources of the exported interface are implemented by JS objects. The
+/// first time an object is handed out to the caller it is entered into
+/// `spidermonkey.wasm`'s handle table for its resource, and its index in that
+/// table is what gets passed to the `canonical_abi.resource_new_*` import.
+/// Handing the same object out again reuses its entry, and handles given
+/// back to us are resolved through `canonical_abi.resource_get_*` and the
+/// table. Once every handle to an object is dropped, the exported
+/// `canonical_abi_drop_*` function removes it from the table and calls the JS
+/// module's `drop_*` export for the resource with it, if there is one.
 pub struct SpiderMonkeyWasm<'a> {
     /// The filename to use for the JS.
     js_name: PathBuf,
@@ -355,8 +425,13 @@ pub struct SpiderMonkeyWasm<'a> {
     num_import_functions: Option<u32>,
     num_export_functions: Option<u32>,
 
+    /// The resources defined by the exported interface and their names.
+    exported_resources: Vec<(ResourceId, String)>,
+
     import_spidermonkey: bool,
 
+    import_memory: bool,
+
     /// Function types that we use in this Wasm module.
     types: wasm_encoder::TypeSection,
 
@@ -381,6 +456,10 @@ pub struct SpiderMonkeyWasm<'a> {
     /// thus far, and their type index.
     export_glue_fns: Vec<(wasm_encoder::Function, u32)>,
 
+    /// The completion callbacks we've generated for async imports thus far,
+    /// and their type index.
+    async_callbacks: Vec<(wasm_encoder::Function, u32)>,
+
     data_segments: DataSegments,
 
     sizes: SizeAlign,
@@ -401,7 +480,9 @@ impl<'a> SpiderMonkeyWasm<'a> {
             return_pointer_area_align: 0,
             num_import_functions: None,
             num_export_functions: None,
+            exported_resources: Vec::new(),
             import_spidermonkey: false,
+            import_memory: false,
             types: wasm_encoder::TypeSection::new(),
             wasm_sig_to_index: Default::default(),
             imports: wasm_encoder::ImportSection::new(),
@@ -409,6 +490,7 @@ impl<'a> SpiderMonkeyWasm<'a> {
             import_fn_name_to_index: Default::default(),
             exports: wasm_encoder::ExportSection::new(),
             export_glue_fns: Default::default(),
+            async_callbacks: Default::default(),
             data_segments: DataSegments::new(1),
             sizes: Default::default(),
             function_names: Vec::new(),
@@ -429,6 +511,19 @@ impl<'a> SpiderMonkeyWasm<'a> {
         self.import_spidermonkey = import;
     }
 
+    /// Configure where the linear memory shared with `spidermonkey.wasm`
+    /// comes from.
+    ///
+    /// By default, the glue module uses the memory exported by the
+    /// `spidermonkey.wasm` instance.
+    ///
+    /// If `import` is `true`, then the glue module imports the memory as
+    /// `memory` instead, for builds of `spidermonkey.wasm` which import their
+    /// memory. Either way, the glue module exports it as `memory`.
+    pub fn import_memory(&mut self, import: bool) {
+        self.import_memory = import;
+    }
+
     fn intern_type(&mut self, wasm_sig: WasmSignature) -> u32 {
         if let Some(idx) = self.wasm_sig_to_index.get(&wasm_sig) {
             return *idx;
@@ -452,23 +547,37 @@ impl<'a> SpiderMonkeyWasm<'a> {
         instances: &mut wasm_encoder::InstanceSection,
         aliases: &mut wasm_encoder::AliasSection,
     ) {
+        let memory_type = wasm_encoder::MemoryType {
+            minimum: 0,
+            maximum: None,
+            memory64: false,
+        };
+        if self.import_memory {
+            // Import the memory before anything else, so that it is memory
+            // index `SM_MEMORY`.
+            self.imports.import(
+                "memory",
+                None,
+                wasm_encoder::EntityType::Memory(memory_type),
+            );
+        }
+
         if self.import_spidermonkey {
             // Import an instance that exports all the expected
-            // `spidermonkey.wasm` things.
+            // `spidermonkey.wasm` things. Builds of `spidermonkey.wasm` which
+            // import their memory don't export it.
+            let exported_memory = if self.import_memory {
+                None
+            } else {
+                Some(("memory", wasm_encoder::EntityType::Memory(memory_type)))
+            };
             let exports: Vec<_> = SMW_EXPORTS
                 .iter()
                 .map(|(name, sig)| {
                     let idx = self.intern_type(sig.clone());
                     (*name, wasm_encoder::EntityType::Function(idx))
                 })
-                .chain(Some((
-                    "memory",
-                    wasm_encoder::EntityType::Memory(wasm_encoder::MemoryType {
-                        minimum: 0,
-                        maximum: None,
-                        memory64: false,
-                    }),
-                )))
+                .chain(exported_memory)
                 .chain(Some((
                     "__indirect_function_table",
                     wasm_encoder::EntityType::Table(wasm_encoder::TableType {
@@ -495,10 +604,14 @@ impl<'a> SpiderMonkeyWasm<'a> {
 
         // Regardless whether we imported an instance or instantiated an embedded
         // module, we now have an instance of `spidermonkey.wasm`. Alias its
-        // exported functions and exported memory into this module's index
-        // spaces.
-        let instance_index = u32::try_from(self.import_fn_name_to_index.len()).unwrap();
-        aliases.instance_export(instance_index, wasm_encoder::ItemKind::Memory, "memory");
+        // exported functions and exported memory, unless we imported the
+        // memory ourselves, into this module's index spaces.
+        let num_import_modules =
+            self.import_fn_name_to_index.len() + usize::from(!self.exported_resources.is_empty());
+        let instance_index = u32::try_from(num_import_modules).unwrap();
+        if !self.import_memory {
+            aliases.instance_export(instance_index, wasm_encoder::ItemKind::Memory, "memory");
+        }
         aliases.instance_export(
             instance_index,
             wasm_encoder::ItemKind::Table,
@@ -589,6 +702,83 @@ impl<'a> SpiderMonkeyWasm<'a> {
         // []
     }
 
+    /// Define and export the `canonical_abi_drop_*` function for the given
+    /// exported resource, which is called once every handle to one of its
+    /// objects has been dropped:
+    ///
+    /// ```wat
+    /// (func (export "canonical_abi_drop_${name}") (param $rep i32)
+    ///   (local $hook_name i32)
+    ///   (local.set $hook_name (call $SMW_malloc (i32.const ${hook_name.len()})))
+    ///   ;; Copy `drop_${name}` into the malloc'd space...
+    ///   (call $SMW_resource_drop (local.get $rep)
+    ///                            (i32.const ${resource})
+    ///                            (local.get $hook_name)
+    ///                            (i32.const ${hook_name.len()})))
+    /// ```
+    fn define_resource_drop_glue(
+        &mut self,
+        funcs: &mut wasm_encoder::FunctionSection,
+        code: &mut wasm_encoder::CodeSection,
+        ty: ResourceId,
+        name: &str,
+    ) {
+        let drop_index = self.resource_drop_glue_fn(ty);
+        self.function_names
+            .push((drop_index, format!("canonical_abi_drop_{}", name)));
+
+        let ty_index = self.intern_type(WasmSignature {
+            params: vec![WasmType::I32],
+            results: vec![],
+            retptr: false,
+            indirect_params: false,
+        });
+        funcs.function(ty_index);
+
+        let rep_local = 0;
+        let hook_name_local = 1;
+        let mut local_names = wasm_encoder::NameMap::new();
+        local_names.append(rep_local, "rep");
+        local_names.append(hook_name_local, "hook_name");
+        self.local_names.push((drop_index, local_names));
+
+        let mut drop_glue = wasm_encoder::Function::new(vec![(1, wasm_encoder::ValType::I32)]);
+
+        // Malloc space for the name of the JS drop hook and copy it there.
+        let hook_name = format!("drop_{}", name.to_snake_case());
+        let hook_name_len = u32::try_from(hook_name.len()).unwrap();
+        self.malloc_static_size(&mut drop_glue, hook_name_len, hook_name_local);
+        let hook_name_offset = self.data_segments.add(hook_name.as_bytes().iter().copied());
+        self.copy_to_smw(
+            &mut drop_glue,
+            hook_name_offset,
+            hook_name_local,
+            hook_name_len,
+        );
+
+        drop_glue
+            // []
+            .instruction(&Instruction::LocalGet(rep_local))
+            // [i32]
+            .instruction(&Instruction::I32Const(ty.index() as i32))
+            // [i32 i32]
+            .instruction(&Instruction::LocalGet(hook_name_local))
+            // [i32 i32 i32]
+            .instruction(&Instruction::I32Const(hook_name_len as i32))
+            // [i32 i32 i32 i32]
+            .instruction(&Instruction::Call(
+                self.spidermonkey_import("SMW_resource_drop"),
+            ))
+            // []
+            .instruction(&Instruction::End);
+        code.function(&drop_glue);
+
+        self.exports.export(
+            &format!("canonical_abi_drop_{}", name),
+            wasm_encoder::Export::Function(drop_index),
+        );
+    }
+
     fn define_wizer_initialize(
         &mut self,
         funcs: &mut wasm_encoder::FunctionSection,
@@ -600,9 +790,7 @@ impl<'a> SpiderMonkeyWasm<'a> {
     ) {
         assert_eq!(funcs.len(), code.len());
 
-        let wizer_init_index = self.wai_import_functions_len()
-            + u32::try_from(SMW_EXPORTS.len()).unwrap()
-            + funcs.len();
+        let wizer_init_index = self.wai_import_glue_fns_start() + funcs.len();
         self.function_names
             .push((wizer_init_index, "wizer.initialize".to_string()));
 
@@ -819,6 +1007,65 @@ impl<'a> SpiderMonkeyWasm<'a> {
             // []
         }
 
+        // Put the async completion callbacks into the function table, and save
+        // the index of the first one in `ASYNC_CALLBACKS_GLOBAL` for the import
+        // glue to pass along to the host:
+        //
+        //     (table.grow (ref.null) (i32.const ${async_callbacks.len()}))
+        //     local.tee ${table_size}
+        //     i32.const -1
+        //     i32.eq
+        //     if
+        //       unreachable
+        //     end
+        //     (global.set ${ASYNC_CALLBACKS_GLOBAL} (local.get ${table_size}))
+        //     (table.set (i32.add (i32.const ${i}) (local.get ${table_size}))
+        //                (ref.func ${callback_index}))
+        //     ...
+        if !self.async_callbacks.is_empty() {
+            wizer_init
+                // []
+                .instruction(&Instruction::RefNull(wasm_encoder::ValType::FuncRef))
+                // [funcref]
+                .instruction(&Instruction::I32Const(
+                    i32::try_from(self.async_callbacks.len()).unwrap(),
+                ))
+                // [funcref i32]
+                .instruction(&Instruction::TableGrow { table: 0 })
+                // [i32]
+                .instruction(&Instruction::LocalTee(table_size_local))
+                // [i32]
+                .instruction(&Instruction::I32Const(-1))
+                // [i32 i32]
+                .instruction(&Instruction::I32Eq)
+                // [i32]
+                .instruction(&Instruction::If(wasm_encoder::BlockType::Empty))
+                // []
+                .instruction(&Instruction::Unreachable)
+                // []
+                .instruction(&Instruction::End)
+                // []
+                .instruction(&Instruction::LocalGet(table_size_local))
+                // [i32]
+                .instruction(&Instruction::GlobalSet(ASYNC_CALLBACKS_GLOBAL));
+            // []
+
+            for i in 0..u32::try_from(self.async_callbacks.len()).unwrap() {
+                wizer_init
+                    // []
+                    .instruction(&Instruction::I32Const(i as i32))
+                    // [i32]
+                    .instruction(&Instruction::LocalGet(table_size_local))
+                    // [i32 i32]
+                    .instruction(&Instruction::I32Add)
+                    // [i32]
+                    .instruction(&Instruction::RefFunc(self.async_callback_fn(i)))
+                    // [i32 funcref]
+                    .instruction(&Instruction::TableSet { table: 0 });
+                // []
+            }
+        }
+
         // Call `SMW_eval_module`, passing it the pointers to the JS file name
         // and JS source:
         //
@@ -850,8 +1097,11 @@ impl<'a> SpiderMonkeyWasm<'a> {
 // The generated glue module's function index space is laid out as follows:
 //
 // ```text
-// |wai imports...|spidermonkey.wasm imports...|import glue...|export glue...|wizer.initialize|
+// |wai imports...|resource imports...|spidermonkey.wasm imports...|import glue...|export glue...|resource drop glue...|async callbacks...|wizer.initialize|
 // ```
+//
+// Each exported resource has two resource imports, `resource_new_*` followed
+// by `resource_get_*`, and one drop glue function.
 impl SpiderMonkeyWasm<'_> {
     /// Get the number of imported WAI functions.
     fn wai_import_functions_len(&self) -> u32 {
@@ -864,9 +1114,27 @@ impl SpiderMonkeyWasm<'_> {
         i
     }
 
+    /// Get the number of `canonical_abi` resource intrinsics we import.
+    fn resource_imports_len(&self) -> u32 {
+        u32::try_from(self.exported_resources.len() * 2).unwrap()
+    }
+
+    /// Get the function index for the `resource_new_*` import of the given
+    /// exported resource.
+    fn resource_new_import(&self, ty: ResourceId) -> u32 {
+        self.wai_import_functions_len() + u32::try_from(ty.index() * 2).unwrap()
+    }
+
+    /// Get the function index for the `resource_get_*` import of the given
+    /// exported resource.
+    fn resource_get_import(&self, ty: ResourceId) -> u32 {
+        self.resource_new_import(ty) + 1
+    }
+
     /// Get the function index for the given spidermonkey function.
     fn spidermonkey_import(&self, name: &str) -> u32 {
         self.wai_import_functions_len()
+            + self.resource_imports_len()
             + u32::try_from(
                 SMW_EXPORTS
                     .iter()
@@ -878,7 +1146,9 @@ impl SpiderMonkeyWasm<'_> {
 
     /// Get the function index where WAI import glue functions start.
     fn wai_import_glue_fns_start(&self) -> u32 {
-        self.wai_import_functions_len() + u32::try_from(SMW_EXPORTS.len()).unwrap()
+        self.wai_import_functions_len()
+            + self.resource_imports_len()
+            + u32::try_from(SMW_EXPORTS.len()).unwrap()
     }
 
     /// Get the range of indices for our synthesized glue functions for WAI
@@ -917,6 +1187,21 @@ impl SpiderMonkeyWasm<'_> {
         assert!(i < self.wai_exports_len());
         self.wai_export_start() + i
     }
+
+    /// Get the function index for the `canonical_abi_drop_*` glue function of
+    /// the given exported resource.
+    fn resource_drop_glue_fn(&self, ty: ResourceId) -> u32 {
+        self.wai_export_start() + self.wai_exports_len() + u32::try_from(ty.index()).unwrap()
+    }
+
+    /// Get the function index for the i^th completion callback for an async
+    /// WAI import.
+    fn async_callback_fn(&self, i: u32) -> u32 {
+        self.wai_export_start()
+            + self.wai_exports_len()
+            + u32::try_from(self.exported_resources.len()).unwrap()
+            + i
+    }
 }
 
 impl Generator for SpiderMonkeyWasm<'_> {
@@ -933,6 +1218,13 @@ impl Generator for SpiderMonkeyWasm<'_> {
             Some(u32::try_from(imports.iter().map(|i| i.functions.len()).sum::<usize>()).unwrap());
         self.num_export_functions =
             Some(u32::try_from(exports.iter().map(|i| i.functions.len()).sum::<usize>()).unwrap());
+        // The resource imports come before the import glue functions, so we
+        // need to know about them before generating any bindings.
+        self.exported_resources = exports
+            .iter()
+            .flat_map(|i| i.resources.iter())
+            .map(|(id, r)| (id, r.name.clone()))
+            .collect();
     }
 
     fn preprocess_one(&mut self, iface: &Interface, _dir: Direction) {
@@ -1029,8 +1321,9 @@ impl Generator for SpiderMonkeyWasm<'_> {
     }
 
     fn type_resource(&mut self, iface: &Interface, ty: ResourceId) {
+        // Exported resources were recorded in `preprocess_all`, and their
+        // intrinsics are emitted in `finish_all`.
         let _ = (iface, ty);
-        todo!()
     }
 
     fn type_alias(&mut self, iface: &Interface, id: TypeId, name: &str, ty: &Type, docs: &Docs) {
@@ -1049,8 +1342,6 @@ impl Generator for SpiderMonkeyWasm<'_> {
     }
 
     fn import(&mut self, iface: &Interface, func: &Function) {
-        assert!(!func.is_async, "async not supported yet");
-
         // Add the raw Wasm import.
         let wasm_sig = iface.wasm_signature(AbiVariant::GuestImport, func);
         let type_index = self.intern_type(wasm_sig.clone());
@@ -1083,6 +1374,14 @@ impl Generator for SpiderMonkeyWasm<'_> {
         );
         let func_encoder = bindgen.finish();
         self.import_glue_fns.push(func_encoder);
+
+        if func.is_async {
+            let callback = u32::try_from(self.async_callbacks.len() - 1).unwrap();
+            self.function_names.push((
+                self.async_callback_fn(callback),
+                format!("{}.{}.callback", iface.name, func.name),
+            ));
+        }
     }
 
     fn export(&mut self, iface: &Interface, func: &Function) {
@@ -1123,6 +1422,30 @@ impl Generator for SpiderMonkeyWasm<'_> {
         let mut elems = wasm_encoder::ElementSection::new();
         let mut code = wasm_encoder::CodeSection::new();
 
+        // Import the intrinsics for creating and resolving handles to our
+        // exported resources. These come right after the WAI function imports.
+        let resource_intrinsic_type_index = self.intern_type(WasmSignature {
+            params: vec![WasmType::I32],
+            results: vec![WasmType::I32],
+            retptr: false,
+            indirect_params: false,
+        });
+        for (ty, name) in self.exported_resources.clone() {
+            for (intrinsic, index) in [
+                ("new", self.resource_new_import(ty)),
+                ("get", self.resource_get_import(ty)),
+            ] {
+                let intrinsic = format!("resource_{}_{}", intrinsic, name);
+                self.imports.import(
+                    "canonical_abi",
+                    Some(&intrinsic),
+                    wasm_encoder::EntityType::Function(resource_intrinsic_type_index),
+                );
+                self.function_names
+                    .push((index, format!("canonical_abi.{}", intrinsic)));
+            }
+        }
+
         self.link_spidermonkey_wasm(&mut modules, &mut instances, &mut aliases);
 
         // Define the return pointer global.
@@ -1133,6 +1456,22 @@ impl Generator for SpiderMonkeyWasm<'_> {
             },
             &Instruction::I32Const(0),
         );
+        if !self.async_callbacks.is_empty() {
+            // Define the global holding the function table index of the first
+            // async completion callback.
+            globals.global(
+                wasm_encoder::GlobalType {
+                    val_type: wasm_encoder::ValType::I32,
+                    mutable: true,
+                },
+                &Instruction::I32Const(0),
+            );
+
+            // Hosts look up the completion callbacks we give them in this
+            // table.
+            self.exports
+                .export("__indirect_function_table", wasm_encoder::Export::Table(0));
+        }
 
         // Re-export `spidermonkey.wasm`'s memory and canonical ABI functions.
         self.exports
@@ -1177,10 +1516,22 @@ impl Generator for SpiderMonkeyWasm<'_> {
             funcs.function(*ty_idx);
             code.function(f);
         }
+        for (ty, name) in self.exported_resources.clone() {
+            self.define_resource_drop_glue(&mut funcs, &mut code, ty, &name);
+        }
+        for (f, ty_idx) in &self.async_callbacks {
+            funcs.function(*ty_idx);
+            code.function(f);
+        }
 
         // We will use `ref.func` to get a reference to each of our synthesized
-        // import glue functions, so we need to declare them as reference-able.
-        let func_indices: Vec<u32> = self.wai_import_glue_fn_range().collect();
+        // import glue functions and async completion callbacks, so we need to
+        // declare them as reference-able.
+        let num_async_callbacks = u32::try_from(self.async_callbacks.len()).unwrap();
+        let func_indices: Vec<u32> = self
+            .wai_import_glue_fn_range()
+            .chain((0..num_async_callbacks).map(|i| self.async_callback_fn(i)))
+            .collect();
         if !func_indices.is_empty() {
             elems.declared(
                 wasm_encoder::ValType::FuncRef,
@@ -1284,6 +1635,7 @@ impl<'a> InstructionSink<'a> for Vec<wasm_encoder::Instruction<'a>> {
 }
 
 const RET_PTR_GLOBAL: u32 = 0;
+const ASYNC_CALLBACKS_GLOBAL: u32 = 1;
 
 const SM_MEMORY: u32 = 0;
 const GLUE_MEMORY: u32 = 1;
@@ -1317,6 +1669,15 @@ struct Bindgen<'a, 'b> {
     ///
     /// `(local holding pointer, local holding length, alignment)`
     to_free: Vec<(u32, u32, u32)>,
+
+    /// While lifting the results of an async import: the instructions and
+    /// locals of the import glue function which made the call, and the
+    /// signature of the completion callback that we are generating now.
+    async_import: Option<(
+        Vec<Instruction<'a>>,
+        Vec<wasm_encoder::ValType>,
+        WasmSignature,
+    )>,
 }
 
 impl<'a, 'b> Bindgen<'a, 'b> {
@@ -1358,6 +1719,7 @@ impl<'a, 'b> Bindgen<'a, 'b> {
             iter_elem: vec![],
             iter_base_pointer: vec![],
             to_free: vec![],
+            async_import: None,
         }
     }
 
@@ -1378,11 +1740,12 @@ impl<'a, 'b> Bindgen<'a, 'b> {
 
     /// Create a new Wasm local for this function and return its index.
     fn new_local(&mut self, ty: wasm_encoder::ValType) -> u32 {
-        let offset = match self.lift_lower {
-            abi::LiftLower::LiftArgsLowerResults => self.sig.params.len(),
+        let offset = match (&self.async_import, self.lift_lower) {
+            (Some((_, _, callback_sig)), _) => callback_sig.params.len(),
+            (None, abi::LiftLower::LiftArgsLowerResults) => self.sig.params.len(),
             // `JSNative` functions take three `i32` arguments: cx, argc, and
             // vp.
-            abi::LiftLower::LowerArgsLiftResults => 3,
+            (None, abi::LiftLower::LowerArgsLiftResults) => 3,
         };
         let idx = u32::try_from(self.locals.len() + offset).unwrap();
         self.locals.push(ty);
@@ -1396,24 +1759,59 @@ impl<'a, 'b> Bindgen<'a, 'b> {
         Operand::Js(js)
     }
 
-    /// Finish generating these bindings and return the encoded Wasm function.
-    fn finish(self) -> wasm_encoder::Function {
-        // TODO: Coalesce contiguous locals of the same type here into the
-        // compact encoding, like `[(i32, 3)]` rather than `[(i32, 1), (i32, 1),
-        // (i32, 1)]`.
-        let mut f = wasm_encoder::Function::new(self.locals.into_iter().map(|l| (1, l)));
+    /// Free the allocations for lowered arguments after a call.
+    fn free_after_call(&mut self) {
+        for (ptr, len, alignment) in std::mem::take(&mut self.to_free) {
+            // []
+            self.inst(Instruction::LocalGet(ptr));
+            // [i32]
+            self.inst(Instruction::LocalGet(len));
+            // [i32 i32]
+            self.inst(Instruction::I32Const(alignment as _));
+            // [i32 i32 i32]
+            self.inst(Instruction::Call(
+                self.gen.spidermonkey_import("canonical_abi_free"),
+            ));
+            // []
+        }
+    }
 
+    /// Finish generating these bindings and return the encoded Wasm function.
+    ///
+    /// For async imports, this is the import glue function, and the completion
+    /// callback is added to the generator's `async_callbacks`.
+    fn finish(mut self) -> wasm_encoder::Function {
         // By the time we get here, we should have finished all nested blocks.
         assert_eq!(self.blocks.len(), 1);
-
-        for inst in &self.blocks[0] {
-            f.instruction(inst);
+        let body = self.blocks.pop().unwrap();
+
+        match self.async_import.take() {
+            None => encode_function(self.locals, &body),
+            Some((glue, glue_locals, callback_sig)) => {
+                let callback = encode_function(self.locals, &body);
+                let type_index = self.gen.intern_type(callback_sig);
+                self.gen.async_callbacks.push((callback, type_index));
+                encode_function(glue_locals, &glue)
+            }
         }
-        f.instruction(&Instruction::End);
-        f
     }
 }
 
+fn encode_function(
+    locals: Vec<wasm_encoder::ValType>,
+    body: &[Instruction<'_>],
+) -> wasm_encoder::Function {
+    // TODO: Coalesce contiguous locals of the same type here into the
+    // compact encoding, like `[(i32, 3)]` rather than `[(i32, 1), (i32, 1),
+    // (i32, 1)]`.
+    let mut f = wasm_encoder::Function::new(locals.into_iter().map(|l| (1, l)));
+    for inst in body {
+        f.instruction(inst);
+    }
+    f.instruction(&Instruction::End);
+    f
+}
+
 /// Operands are locals that either hold the value directly or refer to an index
 /// in `bindgen.cpp`'s JS operand vector depending on if we're dealing with a JS
 /// or Wasm value:
@@ -1593,9 +1991,55 @@ impl abi::Bindgen for Bindgen<'_, '_> {
             abi::Instruction::Float32FromF32 => todo!(),
             abi::Instruction::Float64FromF64 => todo!(),
             abi::Instruction::I32FromBorrowedHandle { ty: _ } => todo!(),
-            abi::Instruction::I32FromOwnedHandle { ty: _ } => todo!(),
+            abi::Instruction::I32FromOwnedHandle { ty } => {
+                // An exported resource object returned from JS.
+                let js = pop_js(operands);
+                let local = self.new_local(wasm_encoder::ValType::I32);
+
+                // Find or make the object's entry in its handle table, and
+                // create a new handle for it.
+                //
+                // []
+                self.inst(Instruction::I32Const(ty.index() as i32));
+                // [i32]
+                self.inst(Instruction::I32Const(js as i32));
+                // [i32 i32]
+                self.inst(Instruction::Call(
+                    self.gen.spidermonkey_import("SMW_resource_rep"),
+                ));
+                // [i32]
+                self.inst(Instruction::Call(self.gen.resource_new_import(*ty)));
+                // [i32]
+                self.inst(Instruction::LocalSet(local));
+                // []
+
+                results.push(Operand::Wasm(local));
+            }
             abi::Instruction::HandleOwnedFromI32 { ty: _ } => todo!(),
-            abi::Instruction::HandleBorrowedFromI32 { ty: _ } => todo!(),
+            abi::Instruction::HandleBorrowedFromI32 { ty } => {
+                // A handle to an exported resource passed to JS.
+                let handle = pop_wasm(operands);
+                let result = self.next_js();
+
+                // Resolve the handle to its entry in the handle table, and
+                // load the entry's object.
+                //
+                // []
+                self.inst(Instruction::LocalGet(handle));
+                // [i32]
+                self.inst(Instruction::Call(self.gen.resource_get_import(*ty)));
+                // [i32]
+                self.inst(Instruction::I32Const(ty.index() as i32));
+                // [i32 i32]
+                self.inst(Instruction::I32Const(result.unwrap_js() as i32));
+                // [i32 i32 i32]
+                self.inst(Instruction::Call(
+                    self.gen.spidermonkey_import("SMW_resource_get"),
+                ));
+                // []
+
+                results.push(result);
+            }
             abi::Instruction::ListCanonLower { .. } => todo!(),
             abi::Instruction::StringLower { realloc } => {
                 let js = pop_js(operands);
@@ -2018,19 +2462,7 @@ impl abi::Bindgen for Bindgen<'_, '_> {
 
                 results.extend(locals.into_iter().map(Operand::Wasm));
 
-                for (ptr, len, alignment) in std::mem::take(&mut self.to_free) {
-                    // []
-                    self.inst(Instruction::LocalGet(ptr));
-                    // [i32]
-                    self.inst(Instruction::LocalGet(len));
-                    // [i32 i32]
-                    self.inst(Instruction::I32Const(alignment as _));
-                    // [i32 i32 i32]
-                    self.inst(Instruction::Call(
-                        self.gen.spidermonkey_import("canonical_abi_free"),
-                    ));
-                    // []
-                }
+                self.free_after_call();
             }
             abi::Instruction::CallInterface { module: _, func } => {
                 // TODO: Rather than always dynamically pushing all of our JS
@@ -2056,11 +2488,14 @@ impl abi::Bindgen for Bindgen<'_, '_> {
                 // pre-`malloc`ed name for each exported function, and then
                 // reuse those that pre-`malloc`ed name on each call.
 
-                // Malloc space for the function name.
+                // Malloc space for the function name. Note that the name of
+                // the JS function can be shorter than the WAI name, e.g. for
+                // resource methods named `resource::method`.
+                let js_func_name = func.name.to_snake_case();
                 let func_name_local = self.new_local(wasm_encoder::ValType::I32);
                 self.gen.malloc_static_size(
                     self.blocks.last_mut().unwrap(),
-                    u32::try_from(func.name.len()).unwrap() + 1,
+                    u32::try_from(js_func_name.len()).unwrap() + 1,
                     func_name_local,
                 );
 
@@ -2070,12 +2505,12 @@ impl abi::Bindgen for Bindgen<'_, '_> {
                 let func_name_offset = self
                     .gen
                     .data_segments
-                    .add(func.name.to_snake_case().as_bytes().iter().copied());
+                    .add(js_func_name.as_bytes().iter().copied());
                 self.gen.copy_to_smw(
                     self.blocks.last_mut().unwrap(),
                     func_name_offset,
                     func_name_local,
-                    u32::try_from(func.name.len()).unwrap(),
+                    u32::try_from(js_func_name.len()).unwrap(),
                 );
 
                 let (first_result, num_results) = match &func.result {
@@ -2100,7 +2535,7 @@ impl abi::Bindgen for Bindgen<'_, '_> {
                 self.inst(Instruction::LocalGet(func_name_local));
                 // [i32]
                 self.inst(Instruction::I32Const(
-                    i32::try_from(func.name.len()).unwrap(),
+                    i32::try_from(js_func_name.len()).unwrap(),
                 ));
                 // [i32 i32]
                 self.inst(Instruction::I32Const(num_results));
@@ -2112,10 +2547,140 @@ impl abi::Bindgen for Bindgen<'_, '_> {
             }
 
             abi::Instruction::CallWasmAsyncExport { .. } => todo!(),
-            abi::Instruction::CallWasmAsyncImport { .. } => todo!(),
+            abi::Instruction::CallWasmAsyncImport {
+                iface,
+                name,
+                params,
+                results: wasm_results,
+            } => {
+                // The JS caller gets a promise for the results, which is
+                // resolved by the completion callback.
+                let promise = self.next_js();
+                let ctx = self.new_local(wasm_encoder::ValType::I32);
+
+                // []
+                self.inst(Instruction::I32Const(promise.unwrap_js() as i32));
+                // [i32]
+                self.inst(Instruction::Call(
+                    self.gen.spidermonkey_import("SMW_new_promise"),
+                ));
+                // [i32]
+                self.inst(Instruction::LocalSet(ctx));
+                // []
+
+                // Attach the promise to the `JS::CallArgs` right away, since
+                // the host may call the completion callback, which clobbers
+                // the JS operands, before returning.
+                //
+                // []
+                self.inst(Instruction::I32Const(promise.unwrap_js() as i32));
+                // [i32]
+                self.inst(Instruction::Call(
+                    self.gen.spidermonkey_import("SMW_push_return_value"),
+                ));
+                // []
+
+                // Push the Wasm arguments, followed by the completion
+                // callback's index in the function table and its context.
+                //
+                // []
+                let locals: Vec<_> = params[..params.len() - 2]
+                    .iter()
+                    .map(|_| pop_wasm(operands))
+                    .collect();
+                for local in locals.into_iter().rev() {
+                    self.inst(Instruction::LocalGet(local));
+                }
+                // [A...]
+                let callback = u32::try_from(self.gen.async_callbacks.len()).unwrap();
+                self.inst(Instruction::GlobalGet(ASYNC_CALLBACKS_GLOBAL));
+                // [A... i32]
+                self.inst(Instruction::I32Const(callback as i32));
+                // [A... i32 i32]
+                self.inst(Instruction::I32Add);
+                // [A... i32]
+                self.inst(Instruction::LocalGet(ctx));
+                // [A... i32 i32]
+
+                let func_index = self
+                    .gen
+                    .import_fn_name_to_index
+                    .get(&iface.name)
+                    .unwrap()
+                    .get(*name)
+                    .unwrap()
+                    .0;
+                self.inst(Instruction::Call(func_index));
+                // []
+
+                self.free_after_call();
+
+                // Return the promise, and `true` meaning that a JS exception
+                // was not thrown.
+                //
+                // []
+                self.gen.clear_js_operands(self.blocks.last_mut().unwrap());
+                // []
+                self.inst(Instruction::LocalGet(1));
+                // [i32]
+                self.inst(Instruction::LocalGet(2));
+                // [i32 i32]
+                self.inst(Instruction::Call(
+                    self.gen.spidermonkey_import("SMW_finish_returns"),
+                ));
+                // []
+                self.inst(Instruction::I32Const(1));
+                // [i32]
+                self.inst(Instruction::Return);
+                // []
+
+                // The rest of the instructions lift the results in the
+                // completion callback, which is given the context followed by
+                // the results.
+                assert_eq!(self.blocks.len(), 1);
+                let glue = std::mem::replace(&mut self.blocks, vec![vec![]])
+                    .pop()
+                    .unwrap();
+                let glue_locals = std::mem::take(&mut self.locals);
+                let callback_sig = WasmSignature {
+                    params: std::iter::once(WasmType::I32)
+                        .chain(wasm_results.iter().copied())
+                        .collect(),
+                    results: vec![],
+                    retptr: false,
+                    indirect_params: false,
+                };
+                self.async_import = Some((glue, glue_locals, callback_sig));
+                self.js_count = 0;
+
+                results.extend(
+                    (1..=wasm_results.len()).map(|i| Operand::Wasm(u32::try_from(i).unwrap())),
+                );
+            }
 
             abi::Instruction::Return { func, amt } => {
                 match self.lift_lower {
+                    abi::LiftLower::LowerArgsLiftResults if self.async_import.is_some() => {
+                        // Resolve the async import's promise with the result,
+                        // or `undefined` if there isn't one.
+                        let val = match &func.result {
+                            Type::Unit => {
+                                operands[0].unwrap_unit();
+                                u32::MAX
+                            }
+                            _ => pop_js(operands),
+                        };
+                        // []
+                        self.inst(Instruction::LocalGet(0));
+                        // [i32]
+                        self.inst(Instruction::I32Const(val as i32));
+                        // [i32 i32]
+                        self.inst(Instruction::Call(
+                            self.gen.spidermonkey_import("SMW_resolve_promise"),
+                        ));
+                        // []
+                        self.gen.clear_js_operands(self.blocks.last_mut().unwrap());
+                    }
                     abi::LiftLower::LowerArgsLiftResults => {
                         match &func.result {
                             Type::Unit => {
@@ -1,4 +1,7 @@
+use std::collections::HashMap;
 use std::path::Path;
+use wai_bindgen_gen_core::{wai_parser::Interface, Files, Generator};
+use wasmparser::{ExternalKind, FuncType, ImportSectionEntryType, Parser, Payload, TypeDef};
 
 mod imports {
     test_helpers::codegen_spidermonkey_import!(
@@ -15,11 +18,74 @@ mod exports {
         "strings.wai"
         "simple-lists.wai"
         "simple-functions.wai"
+        "resource.wai"
     );
 }
 
 fn verify(dir: &str, _name: &str) {
     let wasm = std::fs::read(Path::new(dir).join("foo.wasm")).unwrap();
+    validate(&wasm);
+}
+
+#[test]
+fn async_imports_with_imported_memory() {
+    let iface = Interface::parse(
+        "host",
+        "
+            get-name: async func() -> string
+            notify: async func(message: string)
+        ",
+    )
+    .unwrap();
+    let opts = wai_bindgen_gen_spidermonkey::Opts {
+        js: "foo.js".into(),
+        import_spidermonkey: true,
+        import_memory: true,
+    };
+    let mut files = Files::default();
+    opts.build("").generate_all(&[iface], &[], &mut files);
+    let (_, wasm) = files.iter().find(|(name, _)| *name == "foo.wasm").unwrap();
+    validate(wasm);
+}
+
+#[test]
+fn async_imports_link_against_spidermonkey_wasm() {
+    let iface = Interface::parse(
+        "host",
+        "
+            get-name: async func() -> string
+        ",
+    )
+    .unwrap();
+    let mut files = Files::default();
+    let mut gen = wai_bindgen_gen_spidermonkey::SpiderMonkeyWasm::new("foo.js", "");
+    gen.import_spidermonkey(true);
+    gen.generate_all(&[iface], &[], &mut files);
+    let (_, wasm) = files.iter().find(|(name, _)| *name == "foo.wasm").unwrap();
+    link(wasm);
+}
+
+#[test]
+fn exported_resources_link_against_spidermonkey_wasm() {
+    let iface = Interface::parse(
+        "exports",
+        "
+            resource blob {
+                size: func() -> u32
+            }
+            open: func(name: string) -> blob
+        ",
+    )
+    .unwrap();
+    let mut files = Files::default();
+    let mut gen = wai_bindgen_gen_spidermonkey::SpiderMonkeyWasm::new("foo.js", "");
+    gen.import_spidermonkey(true);
+    gen.generate_all(&[], &[iface], &mut files);
+    let (_, wasm) = files.iter().find(|(name, _)| *name == "foo.wasm").unwrap();
+    link(wasm);
+}
+
+fn validate(wasm: &[u8]) {
     let mut validator = wasmparser::Validator::new();
     validator.wasm_features(wasmparser::WasmFeatures {
         bulk_memory: true,
@@ -27,5 +93,108 @@ fn verify(dir: &str, _name: &str) {
         multi_memory: true,
         ..wasmparser::WasmFeatures::default()
     });
-    validator.validate_all(&wasm).expect("wasm isn't valid");
+    validator.validate_all(wasm).expect("wasm isn't valid");
+}
+
+/// Checks that the committed `spidermonkey.wasm` provides every export, with
+/// the expected signature, that the glue module's imported `spidermonkey`
+/// instance asks for.
+fn link(glue: &[u8]) {
+    let smw = std::fs::read(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("spidermonkey-wasm/spidermonkey.wasm"),
+    )
+    .unwrap();
+
+    let mut smw_types = Vec::new();
+    let mut smw_funcs = Vec::new();
+    let mut smw_exports = HashMap::new();
+    for payload in Parser::new(0).parse_all(&smw) {
+        match payload.unwrap() {
+            Payload::TypeSection(s) => {
+                for ty in s {
+                    match ty.unwrap() {
+                        TypeDef::Func(f) => smw_types.push(f),
+                        other => panic!("unexpected type in spidermonkey.wasm: {:?}", other),
+                    }
+                }
+            }
+            Payload::ImportSection(s) => {
+                for import in s {
+                    if let ImportSectionEntryType::Function(ty) = import.unwrap().ty {
+                        smw_funcs.push(ty);
+                    }
+                }
+            }
+            Payload::FunctionSection(s) => {
+                for ty in s {
+                    smw_funcs.push(ty.unwrap());
+                }
+            }
+            Payload::ExportSection(s) => {
+                for export in s {
+                    let export = export.unwrap();
+                    smw_exports.insert(export.field, (export.kind, export.index));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut glue_types = Vec::new();
+    let mut expected = Vec::new();
+    for payload in Parser::new(0).parse_all(glue) {
+        match payload.unwrap() {
+            Payload::TypeSection(s) => {
+                for ty in s {
+                    glue_types.push(ty.unwrap());
+                }
+            }
+            Payload::ImportSection(s) => {
+                for import in s {
+                    let import = import.unwrap();
+                    if let ("spidermonkey", ImportSectionEntryType::Instance(ty)) =
+                        (import.module, import.ty)
+                    {
+                        match &glue_types[ty as usize] {
+                            TypeDef::Instance(i) => expected.extend(i.exports.iter().cloned()),
+                            other => panic!("`spidermonkey` isn't an instance: {:?}", other),
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    assert!(!expected.is_empty(), "glue doesn't import `spidermonkey`");
+
+    let mut errors = Vec::new();
+    for export in expected {
+        let found = smw_exports.get(export.name);
+        match (export.ty, found) {
+            (ImportSectionEntryType::Function(ty), Some((ExternalKind::Function, idx))) => {
+                let want = match &glue_types[ty as usize] {
+                    TypeDef::Func(f) => f,
+                    other => panic!("`{}` isn't a function type: {:?}", export.name, other),
+                };
+                let have: &FuncType = &smw_types[smw_funcs[*idx as usize] as usize];
+                if want != have {
+                    errors.push(format!(
+                        "`{}` has type {:?}, expected {:?}",
+                        export.name, have, want
+                    ));
+                }
+            }
+            (ImportSectionEntryType::Function(_), _) => {
+                errors.push(format!("missing function `{}`", export.name))
+            }
+            (ImportSectionEntryType::Memory(_), Some((ExternalKind::Memory, _)))
+            | (ImportSectionEntryType::Table(_), Some((ExternalKind::Table, _))) => {}
+            (ty, _) => errors.push(format!("missing {:?} `{}`", ty, export.name)),
+        }
+    }
+    assert!(
+        errors.is_empty(),
+        "glue doesn't link against spidermonkey.wasm:\n{}",
+        errors.join("\n")
+    );
 }
@@ -20,6 +20,8 @@ pub struct Dependencies {
     pub needs_list_canon_lower: bool,
     pub needs_t_typevar: bool,
     pub needs_resources: bool,
+    /// The number of strings `_decode_utf8` caches, see `Opts::string_cache`.
+    pub string_cache: Option<usize>,
     pub pyimports: BTreeMap<String, Option<BTreeSet<String>>>,
 }
 
@@ -175,20 +177,46 @@ impl Dependencies {
                 );
             }
         }
-        if self.needs_decode_utf8 {
-            src.push_str(
+        // Strings are decoded straight out of a `memoryview` of the memory
+        // rather than a copy of their bytes.
+        match (self.needs_decode_utf8, self.string_cache) {
+            (false, _) => {}
+            (true, None) => src.push_str(
                 "
                     def _decode_utf8(mem: wasmer.Memory, ptr: int, len: int) -> str:
                         ptr = ptr & 0xffffffff
                         len = len & 0xffffffff
                         if ptr + len > mem.data_size:
                             raise IndexError('string out of bounds')
-                        view = mem.uint8_view()
-                        bytes = bytearray(view[ptr:ptr+len])
-                        x = bytes.decode('utf8')
+                        view = memoryview(mem.buffer).cast('B')
+                        return str(view[ptr:ptr+len], 'utf8')
+                ",
+            ),
+            // Short strings are looked up by their bytes, so that strings the
+            // guest passes over and over are only decoded once and share one
+            // `str`. Longer ones would take about as long to hash as to decode.
+            (true, Some(entries)) => src.push_str(&format!(
+                "
+                    _string_cache: dict[bytes, str] = {{}}
+
+                    def _decode_utf8(mem: wasmer.Memory, ptr: int, size: int) -> str:
+                        ptr = ptr & 0xffffffff
+                        size = size & 0xffffffff
+                        if ptr + size > mem.data_size:
+                            raise IndexError('string out of bounds')
+                        view = memoryview(mem.buffer).cast('B')[ptr:ptr+size]
+                        if size > 256:
+                            return str(view, 'utf8')
+                        key = view.tobytes()
+                        x = _string_cache.get(key)
+                        if x is None:
+                            x = key.decode('utf8')
+                            if len(_string_cache) >= {entries}:
+                                del _string_cache[next(iter(_string_cache))]
+                            _string_cache[key] = x
                         return x
                 ",
-            );
+            )),
         }
         if self.needs_encode_utf8 {
             self.pyimport("typing", "Tuple");
@@ -342,6 +370,20 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_string_cache() {
+        let mut deps = Dependencies {
+            needs_decode_utf8: true,
+            ..Dependencies::default()
+        };
+        assert!(!deps.intrinsics().contains("_string_cache"));
+
+        deps.string_cache = Some(128);
+        let src = deps.intrinsics();
+        assert!(src.contains("_string_cache: dict[bytes, str] = {}"));
+        assert!(src.contains("if len(_string_cache) >= 128:"));
+    }
+
     #[test]
     #[should_panic]
     fn test_pyimport_conflicting() {
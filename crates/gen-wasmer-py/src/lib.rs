@@ -4,7 +4,7 @@ use std::mem;
 use wai_bindgen_gen_core::wai_parser::abi::{
     AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmType,
 };
-use wai_bindgen_gen_core::{wai_parser::*, Direction, Files, Generator, Ns};
+use wai_bindgen_gen_core::{union_case_names, wai_parser::*, Direction, Files, Generator, Ns};
 
 pub mod dependencies;
 pub mod source;
@@ -24,6 +24,8 @@ pub struct WasmerPy {
     deps: Dependencies,
     /// Whether the Python Union being emitted will wrap its cases with dataclasses
     union_representation: HashMap<String, PyUnionRepresentation>,
+    /// The modules of the package being generated with `Opts::package`.
+    package_modules: Vec<String>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -59,11 +61,28 @@ struct Exports {
 pub struct Opts {
     #[cfg_attr(feature = "structopt", structopt(long = "no-typescript"))]
     pub no_typescript: bool,
+
+    /// Emit an installable Python package with this name, with a module per
+    /// interface in the `<name>` directory along with an `__init__.py` and a
+    /// `py.typed` marker, and a `pyproject.toml`, rather than a single file.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub package: Option<String>,
+
+    /// The version of the package emitted with `--package`, `0.1.0` by
+    /// default.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub package_version: Option<String>,
+
+    /// Cache up to this many of the short strings decoded from guest memory,
+    /// so that strings passed repeatedly are only decoded once.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub string_cache: Option<usize>,
 }
 
 impl Opts {
     pub fn build(self) -> WasmerPy {
         let mut r = WasmerPy::new();
+        r.deps.string_cache = self.string_cache;
         r.opts = self;
         r
     }
@@ -181,9 +200,9 @@ impl Generator for WasmerPy {
         docs: &Docs,
     ) {
         let mut builder = self.src.builder(&mut self.deps, iface);
-        builder.pyimport("enum", "Flag");
+        builder.pyimport("enum", "IntFlag");
         builder.pyimport("enum", "auto");
-        builder.push_str(&format!("class {}(Flag):\n", name.to_camel_case()));
+        builder.push_str(&format!("class {}(IntFlag):\n", name.to_camel_case()));
         builder.indent();
         builder.docstring(docs);
         for flag in flags.flags.iter() {
@@ -222,6 +241,24 @@ impl Generator for WasmerPy {
             builder.push_str("\n");
             cases.push(case_name);
         }
+        if variant.non_exhaustive {
+            let case_name = format!("{}Unknown", name.to_camel_case());
+            builder.push_str("@dataclass\n");
+            builder.push_str(&format!("class {case_name}:\n"));
+            builder.indent();
+            builder.docstring(&Docs {
+                contents: Some(
+                    "A case added by a newer version of the interface, along with its\n\
+                     discriminant. It can't be passed back to wasm.\n"
+                        .to_string(),
+                ),
+                examples: Vec::new(),
+            });
+            builder.push_str("value: int\n");
+            builder.dedent();
+            builder.push_str("\n");
+            cases.push(case_name);
+        }
 
         builder.deps.pyimport("typing", "Union");
         builder.comment(docs);
@@ -318,6 +355,22 @@ impl Generator for WasmerPy {
             }
             builder.push_str(&format!("{} = {}\n", name, i));
         }
+        if enum_.non_exhaustive {
+            // Cases added by newer versions of the interface become pseudo
+            // members named `UNKNOWN`, which keep their discriminant.
+            builder.push_str("@classmethod\n");
+            builder.push_str("def _missing_(cls, value):\n");
+            builder.indent();
+            builder.push_str("if not isinstance(value, int):\n");
+            builder.indent();
+            builder.push_str("return None\n");
+            builder.dedent();
+            builder.push_str("unknown = object.__new__(cls)\n");
+            builder.push_str("unknown._name_ = \"UNKNOWN\"\n");
+            builder.push_str("unknown._value_ = value\n");
+            builder.push_str("return unknown\n");
+            builder.dedent();
+        }
         builder.dedent();
         builder.push_str("\n");
     }
@@ -551,6 +604,11 @@ impl Generator for WasmerPy {
         self.deps.pyimport("typing", "Any");
         self.deps.pyimport("abc", "abstractmethod");
         self.deps.pyimport("typing", "Callable");
+        if !self.guest_exports.is_empty() {
+            self.deps.pyimport("typing", "Iterable");
+            self.deps.pyimport("typing", "Optional");
+            self.deps.pyimport("typing", "Union");
+        }
 
         let types = mem::take(&mut self.src);
         let intrinsics = self.intrinsics(iface);
@@ -822,6 +880,74 @@ impl Generator for WasmerPy {
             }
             self.src.dedent();
 
+            // Instantiating a module takes a store, the WASI imports, the
+            // imports of every interface the module imports, whose functions
+            // look up the exports of the instance created afterwards, and a
+            // call to `_initialize` once it's created, in that order.
+            self.src.push_str(&format!(
+                "
+                    @classmethod
+                    def instantiate(
+                        cls,
+                        module: Union[wasmer.Module, bytes, str],
+                        store: Optional[wasmer.Store] = None,
+                        *,
+                        engine: Any = None,
+                        compiler: Any = None,
+                        wasi: Any = None,
+                        imports: Optional[dict[str, dict[str, Any]]] = None,
+                        add_imports: Iterable[Callable[[wasmer.Store, dict[str, dict[str, Any]], Callable[[str], Any]], None]] = (),
+                    ) -> '{module}':
+                        \"\"\"
+                        Compiles `module` if it isn't compiled yet and instantiates it.
+
+                        A new store is created unless `store` is given, using `engine` or a
+                        universal engine with `compiler` if either is given. If `wasi` is a
+                        WASI environment its imports are provided to the module, along with
+                        `imports`. Each of `add_imports` is called with the store, the
+                        imports and a function looking up the exports of the instance, to
+                        add the imports of an interface, such as with:
+
+                            lambda store, imports, get_export: add_host_to_imports(store, imports, MyHost(), get_export)
+
+                        The module's `_initialize` function is called if it exports one.
+                        \"\"\"
+                        if store is None:
+                            if engine is None and compiler is not None:
+                                engine = wasmer.engine.Universal(compiler)
+                            store = wasmer.Store() if engine is None else wasmer.Store(engine)
+                        elif engine is not None or compiler is not None:
+                            raise ValueError('`engine` and `compiler` only configure new stores')
+                        if not isinstance(module, wasmer.Module):
+                            module = wasmer.Module(store, module)
+
+                        import_object: dict[str, dict[str, Any]] = {{}}
+                        if wasi is not None:
+                            version = wasmer.wasi.get_version(module, strict=False)
+                            if version is None:
+                                raise ValueError('module does not import WASI')
+                            for name, items in wasi.generate_imports(store, version).items():
+                                import_object.setdefault(name, {{}}).update(items)
+                        for name, items in (imports or {{}}).items():
+                            import_object.setdefault(name, {{}}).update(items)
+
+                        wasm: '{module}'
+                        def get_export(name: str) -> Any:
+                            return wasm.instance.exports.__getattribute__(name)
+                        for add in add_imports:
+                            add(store, import_object, get_export)
+
+                        wasm = cls(store, import_object, module)
+                        try:
+                            initialize = wasm.instance.exports.__getattribute__('_initialize')
+                        except (AttributeError, LookupError):
+                            initialize = None
+                        if isinstance(initialize, wasmer.Function):
+                            initialize()
+                        return wasm
+                ",
+            ));
+
             for func in exports.freestanding_funcs.iter() {
                 self.src.push_str(func);
             }
@@ -829,7 +955,63 @@ impl Generator for WasmerPy {
             self.src.dedent();
         }
 
-        files.push("bindings.py", self.src.as_bytes());
+        let module = iface.name.to_snake_case();
+        let src = mem::take(&mut self.src);
+        match &self.opts.package {
+            Some(package) => {
+                let path = format!("{}/{}.py", package.to_snake_case(), module);
+                files.push(&path, src.as_bytes());
+                self.package_modules.push(module);
+            }
+            None => files.push_for_interface(&module, "py", src.as_bytes()),
+        }
+    }
+
+    fn finish_all(&mut self, files: &mut Files) {
+        let package = match &self.opts.package {
+            Some(package) => package,
+            None => return,
+        };
+        let dir = package.to_snake_case();
+        let version = self.opts.package_version.as_deref().unwrap_or("0.1.0");
+
+        let mut init = Source::default();
+        init.push_str("\"\"\"Wasmer bindings generated by `wai-bindgen`.\"\"\"\n");
+        self.package_modules.sort();
+        self.package_modules.dedup();
+        for module in self.package_modules.iter() {
+            init.push_str(&format!("from . import {}\n", module));
+        }
+        let all = self
+            .package_modules
+            .iter()
+            .map(|m| format!("'{}'", m))
+            .collect::<Vec<_>>();
+        init.push_str(&format!("__all__ = [{}]\n", all.join(", ")));
+        files.push(&format!("{}/__init__.py", dir), init.as_bytes());
+        files.push(&format!("{}/py.typed", dir), b"");
+
+        let pyproject = format!(
+            "\
+[build-system]
+requires = [\"setuptools>=61\"]
+build-backend = \"setuptools.build_meta\"
+
+[project]
+name = \"{package}\"
+version = \"{version}\"
+description = \"Wasmer bindings generated by wai-bindgen\"
+requires-python = \">=3.9\"
+dependencies = [\"wasmer>=1.0\"]
+
+[tool.setuptools]
+packages = [\"{dir}\"]
+
+[tool.setuptools.package-data]
+{dir} = [\"py.typed\"]
+"
+        );
+        files.push("pyproject.toml", pyproject.as_bytes());
     }
 }
 
@@ -1259,10 +1441,17 @@ impl Bindgen for FunctionBindgen<'_> {
                 builder.push_str("else:\n");
                 builder.indent();
                 let variant_name = name.to_camel_case();
-                builder.push_str(&format!(
-                    "raise TypeError(\"invalid variant discriminant for {}\")\n",
-                    variant_name
-                ));
+                if variant.non_exhaustive {
+                    builder.push_str(&format!(
+                        "{} = {}Unknown({})\n",
+                        result, variant_name, operands[0]
+                    ));
+                } else {
+                    builder.push_str(&format!(
+                        "raise TypeError(\"invalid variant discriminant for {}\")\n",
+                        variant_name
+                    ));
+                }
                 builder.dedent();
                 results.push(result);
             }
@@ -1293,6 +1482,7 @@ impl Bindgen for FunctionBindgen<'_> {
                     .get(&name.to_string())
                     .unwrap();
                 let name = name.to_camel_case();
+                let case_names = union_case_names(iface, union);
                 let op0 = &operands[0];
                 for (i, ((case, (block, block_results)), payload)) in
                     union.cases.iter().zip(blocks).zip(payloads).enumerate()
@@ -1306,7 +1496,7 @@ impl Bindgen for FunctionBindgen<'_> {
                         }
                         // Prints the name of this union cases dataclass
                         PyUnionRepresentation::Wrapped => {
-                            builder.push_str(&format!("{name}{i}"));
+                            builder.push_str(&format!("{name}{}", case_names[i]));
                         }
                     }
                     builder.push_str("):\n");
@@ -1352,6 +1542,7 @@ impl Bindgen for FunctionBindgen<'_> {
                     .get(&name.to_string())
                     .unwrap();
                 let name = name.to_camel_case();
+                let case_names = union_case_names(iface, union);
                 let op0 = &operands[0];
                 for (i, (_case, (block, block_results))) in
                     union.cases.iter().zip(blocks).enumerate()
@@ -1368,7 +1559,7 @@ impl Bindgen for FunctionBindgen<'_> {
                         PyUnionRepresentation::Raw => builder.push_str(block_result),
                         // Constructs an instance of the union cases dataclass
                         PyUnionRepresentation::Wrapped => {
-                            builder.push_str(&format!("{name}{i}({block_result})"))
+                            builder.push_str(&format!("{name}{}({block_result})", case_names[i]))
                         }
                     }
                     builder.newline();
@@ -1,15 +1,20 @@
 use heck::*;
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::io::{Read, Write};
+use std::iter::zip;
 use std::mem;
 use std::process::{Command, Stdio};
 use std::str::FromStr;
 use wai_bindgen_gen_core::wai_parser::abi::{
-    AbiVariant, Bindgen, Instruction, LiftLower, WasmType,
+    AbiVariant, Bindgen, Instruction, LiftLower, WasmType, ABI_VERSION,
+};
+use wai_bindgen_gen_core::{
+    interface_hash, wai_parser::*, Direction, Files, Generator, NamespaceMap, Source, TypeInfo,
+    Types,
 };
-use wai_bindgen_gen_core::{wai_parser::*, Direction, Files, Generator, Source, TypeInfo, Types};
 use wai_bindgen_gen_rust::{
-    to_rust_ident, wasm_type, FnSig, RustFlagsRepr, RustFunctionGenerator, RustGenerator, TypeMode,
+    is_list_canonical, to_rust_ident, wasm_type, FnSig, RustFlagsRepr, RustFunctionGenerator,
+    RustGenerator, TypeMode,
 };
 
 #[derive(Default)]
@@ -29,8 +34,34 @@ pub struct Wasmer {
     needs_custom_error_to_trap: bool,
     needs_custom_error_to_types: BTreeSet<String>,
     needs_lazy_initialized: bool,
+    /// Whether a function of the current interface is annotated with
+    /// `@requires`, giving its trait a `capabilities` method.
+    needs_capabilities: bool,
+    /// Whether a function of the current interface has interned parameters,
+    /// giving its `EnvWrapper` an `Interner`.
+    needs_interner: bool,
+    /// Whether a function of the current interface lifts lists into an
+    /// arena, giving its `EnvWrapper` an `Arena`.
+    needs_arena: bool,
     all_needed_handles: BTreeSet<String>,
     exported_resources: BTreeSet<ResourceId>,
+    /// Resources of the current interface which are `use`d from another
+    /// interface generated alongside it, and so live in that interface's
+    /// handle table, mapped to the name of that interface.
+    foreign_handles: BTreeMap<String, String>,
+    /// Interfaces whose handle tables are shared with other interfaces.
+    shared_tables: BTreeSet<String>,
+    /// The names of the interfaces being generated in each direction.
+    import_names: BTreeSet<String>,
+    export_names: BTreeSet<String>,
+    /// The interfaces owning the handle tables used by each interface which
+    /// the host implements, for the `plugin` option.
+    plugin_tables: BTreeMap<String, BTreeSet<String>>,
+    /// The interfaces the host wraps the instance's exports of, for the
+    /// `plugin` and `imports_builder` options.
+    plugin_guests: BTreeSet<String>,
+    /// The `FunctionDesc` of every function of the current interface.
+    function_descs: Vec<String>,
     types: Types,
     guest_imports: HashMap<String, Vec<Import>>,
     guest_exports: HashMap<String, Exports>,
@@ -50,12 +81,55 @@ struct Import {
     name: String,
     trait_signature: String,
     closure: String,
+    params: Vec<String>,
+    record: Option<RecordedCall>,
+}
+
+/// Everything needed to capture and replay one call of an import trait
+/// method or an export, only generated when the `record` option is enabled.
+struct RecordedCall {
+    variant: String,
+    /// Name and owned type of each captured parameter.
+    fields: Vec<(String, String)>,
+    /// Expressions turning each (possibly borrowed) parameter into its owned
+    /// captured form.
+    captures: Vec<String>,
+    /// Expressions turning each captured field back into an argument.
+    replay_args: Vec<String>,
+    result_ty: String,
+}
+
+/// A function of an exported interface, wrapped by the `*Recorder` of the
+/// interface's exports struct.
+struct RecordedExport {
+    name: String,
+    /// The signature of the recorder's method, which takes `&mut self`.
+    signature: String,
+    params: Vec<String>,
+    record: Option<RecordedCall>,
+}
+
+/// How a single parameter is turned into an owned value by the recorder.
+enum Capture {
+    Clone,
+    Str,
+    Slice,
+    LeSlice,
 }
 
 #[derive(Default)]
 struct Exports {
     fields: BTreeMap<String, (String, String)>,
+    /// The `fields` holding the functions of the interface, which are a
+    /// `Result` so that bindings can be created for instances missing some
+    /// of them.
+    optional: BTreeSet<String>,
     funcs: Vec<String>,
+    /// Inherent methods forwarding to `funcs`, printed on each resource's
+    /// handle type.
+    resource_methods: BTreeMap<ResourceId, Vec<String>>,
+    /// The functions wrapped by the recorder, with the `record` option.
+    recorded: Vec<RecordedExport>,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -69,6 +143,22 @@ pub struct Opts {
     #[cfg_attr(feature = "structopt", structopt(long))]
     pub tracing: bool,
 
+    /// The level of the spans and events emitted with `--tracing`: `trace`,
+    /// `debug`, `info`, `warn` or `error`.
+    #[cfg_attr(feature = "structopt", structopt(long, default_value = "trace"))]
+    pub tracing_level: TracingLevel,
+
+    /// Parameters to leave out of `tracing` events, given as either the
+    /// parameter's name or `function.param`. The return value is named
+    /// `result`.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub tracing_skip: Vec<String>,
+
+    /// Parameters to record as `"<redacted>"` in `tracing` events instead of
+    /// their value, named the same way as for `--tracing-skip`.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub tracing_redact: Vec<String>,
+
     /// Indicates which functions should be `async`: `all`, `none`, or a
     /// comma-separated list.
     #[cfg_attr(
@@ -81,6 +171,153 @@ pub struct Opts {
     /// custom trait-defined error. Applicable for import bindings.
     #[cfg_attr(feature = "structopt", structopt(long))]
     pub custom_error: bool,
+
+    /// Whether or not to generate a `*Recorder` wrapper for each import trait
+    /// which logs every call made by the guest, and for each exports struct
+    /// which logs every call made by the host, along with a `replay_*`
+    /// function which feeds such a log back into a host implementation or an
+    /// instance.
+    ///
+    /// Calls to functions taking or returning handles or borrowed aggregates
+    /// are logged as `Unrecorded` entries holding only the function's name.
+    /// Recording isn't supported together with `async`, `custom_error`,
+    /// `shared_host` or `intern`.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub record: bool,
+
+    /// Whether or not to generate an `Unimplemented*` type for each import
+    /// trait which implements it with `todo!()` for every function, as a
+    /// starting point for a host implementation.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub stubs: bool,
+
+    /// Whether or not to report the duration and outcome of every call
+    /// through the bindings to the `wai_bindgen_wasmer::Metrics`
+    /// implementation installed with `wai_bindgen_wasmer::set_metrics`.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub metrics: bool,
+
+    /// Whether or not to generate a `*_with_deadline` variant of each export
+    /// which aborts the call with `CallError::TimedOut` once it has used up
+    /// the given number of metering points. Applicable for export bindings.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub deadlines: bool,
+
+    /// Whether or not to give the generated exports struct `memory_size` and
+    /// `set_grow_hook` functions, the latter being called whenever the
+    /// guest's memory grows past a threshold while allocating arguments.
+    /// Applicable for export bindings.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub memory_usage: bool,
+
+    /// Whether or not to give the generated exports struct a `guest_panic`
+    /// function, which retrieves the message of the panic a call trapped
+    /// with from guests built with the `panic_hook` option of the Rust guest
+    /// bindings. Applicable for export bindings.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub guest_panics: bool,
+
+    /// Whether or not import trait methods receive strings and lists as owned
+    /// `String`s and `Vec`s copied out of guest memory, rather than borrowing
+    /// them from guest memory for the duration of the call. Applicable for
+    /// import bindings.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub owned_params: bool,
+
+    /// String parameters of import trait methods to intern, given as either
+    /// a function's name, for all of its `string` parameters, or
+    /// `function.param`. Interned parameters are passed as `Arc<str>`s which
+    /// a `wai_bindgen_wasmer::Interner` kept per instance deduplicates, so
+    /// hosts keeping them allocate each distinct string once. Applicable for
+    /// import bindings.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub intern: Vec<String>,
+
+    /// Whether or not import trait methods receive lists which can't be
+    /// borrowed from guest memory, like a `list<string>`, as slices allocated
+    /// in a per-instance `wai_bindgen_wasmer::Arena` rather than as `Vec`s,
+    /// so calls stop allocating once the arena has grown to fit them.
+    /// Applicable for import bindings.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub arena: bool,
+
+    /// Whether or not values lifted out of guest memory are trusted to be
+    /// well-formed, skipping the validation of discriminants and flags and
+    /// truncating integers rather than checking their range. Malformed
+    /// values are lifted as some other valid value instead of trapping, so
+    /// this is only meant for guests built with a trusted toolchain. Chars
+    /// and strings are still validated.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub unchecked: bool,
+
+    /// Whether or not to generate a `Plugin` type owning the store, the
+    /// instance and the bindings of every interface generated alongside it,
+    /// which loads a module with a single `Plugin::load` call.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub plugin: bool,
+
+    /// Whether or not to generate an `ImportsBuilder` which adds the imports
+    /// of every interface generated alongside it, each with its own host
+    /// implementation, then instantiates a module, runs the initializers and
+    /// wraps all of its exported interfaces at once.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub imports_builder: bool,
+
+    /// Whether or not to generate `instantiate_wasi` constructors, which
+    /// also provide the module with WASI imports built from a
+    /// `WasiStateBuilder`. Requires the `wasi` feature of
+    /// `wai-bindgen-wasmer`.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub wasi: bool,
+
+    /// Whether or not import trait methods take `&self`, so that a single
+    /// host implementation can be shared through an `Arc` by any number of
+    /// instances, each with its own handle tables. Applicable for import
+    /// bindings.
+    #[cfg_attr(feature = "structopt", structopt(long))]
+    pub shared_host: bool,
+
+    /// Provides the functions of the interface `module` to the guest in the
+    /// import namespace `namespace`, and looks its exports up as
+    /// `namespace#<func>`, rather than using the name of the interface, given
+    /// as `module=namespace`. Can be specified multiple times.
+    #[cfg_attr(feature = "structopt", structopt(long = "map"))]
+    pub map: Vec<NamespaceMap>,
+
+    /// The prefix the guest's bindings prepend to all exported symbols, as
+    /// set by the `symbol_namespace` option of `wai-bindgen-gen-rust-wasm`.
+    /// Note that this is only intended for testing because it breaks the
+    /// general form of the ABI.
+    #[cfg_attr(feature = "structopt", structopt(skip))]
+    pub symbol_namespace: String,
+
+    /// The name of the module generated for an interface, given as
+    /// `[interface=]name`, instead of the interface's name in snake case.
+    /// Can be specified multiple times.
+    #[cfg_attr(feature = "structopt", structopt(long = "module-name"))]
+    pub module_name: Vec<NameOverride>,
+
+    /// The name of the trait generated for an imported interface, given as
+    /// `[interface=]Name`, instead of the interface's name in camel case. It
+    /// also prefixes the names of the types generated alongside the trait,
+    /// like its `*Tables`. Can be specified multiple times.
+    #[cfg_attr(feature = "structopt", structopt(long = "trait-name"))]
+    pub trait_name: Vec<NameOverride>,
+
+    /// The name of the struct generated for an exported interface, given as
+    /// `[interface=]Name`, instead of the interface's name in camel case.
+    /// Can be specified multiple times.
+    #[cfg_attr(feature = "structopt", structopt(long = "struct-name"))]
+    pub struct_name: Vec<NameOverride>,
+
+    /// Wraps the module generated for an interface in
+    /// `#[cfg(feature = "feature")]`, given as `[interface=]feature`, so
+    /// crates using the bindings can compile out the interfaces they don't
+    /// use. Interfaces sharing resources should be gated by the same feature,
+    /// and the `Plugin` type of the `plugin` option requires the features of
+    /// all of its interfaces. Can be specified multiple times.
+    #[cfg_attr(feature = "structopt", structopt(long = "cfg-feature"))]
+    pub cfg_feature: Vec<NameOverride>,
 }
 
 #[derive(Debug, Clone)]
@@ -126,30 +363,1103 @@ impl FromStr for Async {
     }
 }
 
+/// A name used for what's generated for `interface`, such as the name of an
+/// item or of the feature gating it, or for every interface if `interface` is
+/// `None`. Given as `[interface=]name`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameOverride {
+    pub interface: Option<String>,
+    pub name: String,
+}
+
+impl NameOverride {
+    /// Returns the name the first matching entry of `overrides` gives to the
+    /// item generated for `interface`.
+    fn find<'a>(overrides: &'a [NameOverride], interface: &str) -> Option<&'a str> {
+        overrides
+            .iter()
+            .find(|o| o.interface.as_deref().map_or(true, |i| i == interface))
+            .map(|o| o.name.as_str())
+    }
+}
+
+impl FromStr for NameOverride {
+    type Err = String;
+    fn from_str(s: &str) -> Result<NameOverride, String> {
+        let (interface, name) = match s.split_once('=') {
+            Some((interface, name)) => (Some(interface.to_string()), name),
+            None => (None, s),
+        };
+        if name.is_empty() {
+            return Err(format!("expected `[interface=]name`, found `{}`", s));
+        }
+        Ok(NameOverride {
+            interface,
+            name: name.to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TracingLevel {
+    #[default]
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl TracingLevel {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TracingLevel::Trace => "TRACE",
+            TracingLevel::Debug => "DEBUG",
+            TracingLevel::Info => "INFO",
+            TracingLevel::Warn => "WARN",
+            TracingLevel::Error => "ERROR",
+        }
+    }
+}
+
+impl FromStr for TracingLevel {
+    type Err = String;
+    fn from_str(s: &str) -> Result<TracingLevel, String> {
+        Ok(match s {
+            "trace" => TracingLevel::Trace,
+            "debug" => TracingLevel::Debug,
+            "info" => TracingLevel::Info,
+            "warn" => TracingLevel::Warn,
+            "error" => TracingLevel::Error,
+            _ => return Err(format!("unknown tracing level `{}`", s)),
+        })
+    }
+}
+
 impl Opts {
     pub fn build(self) -> Wasmer {
         let mut r = Wasmer::new();
         r.opts = self;
         r
     }
+
+    /// Checks that these options can be used together, returning a message
+    /// describing the conflict if not.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.record {
+            let conflict = if !self.async_.is_none() {
+                "async"
+            } else if self.custom_error {
+                "custom_error"
+            } else if self.shared_host {
+                "shared_host"
+            } else if !self.intern.is_empty() {
+                "intern"
+            } else {
+                return Ok(());
+            };
+            return Err(format!(
+                "recording is not supported together with `{conflict}`"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The documentation of the `add_to_imports` function generated for each
+/// interface implemented by the host.
+const ADD_TO_IMPORTS_DOC: &str = "
+    /// Adds the host's implementation of this interface to `imports`.
+    ///
+    /// The returned initializer connects it to the instance of the module.
+    /// It can be called again with another instance created from the same
+    /// `imports`, for example when reloading the module, to switch over to
+    /// that one.
+";
+
+enum FunctionRet {
+    /// The function return is normal and needs to extra handling.
+    Normal,
+    /// The function return was wrapped in a `Result` in Rust. The `Ok` variant
+    /// is the actual value that will be lowered, and the `Err`, if present,
+    /// means that a trap has occurred.
+    CustomToTrap,
+    /// The function returns a `Result` in both wasm and in Rust, but the
+    /// Rust error type is a custom error and must be converted to `err`. The
+    /// `ok` variant payload is provided here too.
+    CustomToError { ok: Type, err: String },
 }
 
-enum FunctionRet {
-    /// The function return is normal and needs to extra handling.
-    Normal,
-    /// The function return was wrapped in a `Result` in Rust. The `Ok` variant
-    /// is the actual value that will be lowered, and the `Err`, if present,
-    /// means that a trap has occurred.
-    CustomToTrap,
-    /// The function returns a `Result` in both wasm and in Rust, but the
-    /// Rust error type is a custom error and must be converted to `err`. The
-    /// `ok` variant payload is provided here too.
-    CustomToError { ok: Type, err: String },
-}
+impl Wasmer {
+    pub fn new() -> Wasmer {
+        Wasmer::default()
+    }
+
+    /// Runs `f` with the `Bindgen` that `import` or `export` would drive for
+    /// `func`, depending on the direction last passed to `preprocess_one`.
+    ///
+    /// This lets the ABI fuzzer execute this generator's own lifting and
+    /// lowering decisions without compiling the generated source.
+    #[doc(hidden)]
+    pub fn with_function_bindgen<R>(
+        &mut self,
+        iface: &Interface,
+        func: &Function,
+        f: impl FnOnce(&mut dyn Bindgen<Operand = String>) -> R,
+    ) -> R {
+        // Host exports are called by the guest and lift their arguments from
+        // wasm values, host imports lower them from Rust ones.
+        let params = if self.in_import {
+            let sig = iface.wasm_signature(AbiVariant::GuestImport, func);
+            (0..sig.params.len()).map(|i| format!("arg{}", i)).collect()
+        } else {
+            func.params
+                .iter()
+                .map(|(name, _)| to_rust_ident(name))
+                .collect()
+        };
+        f(&mut FunctionBindgen::new(self, params))
+    }
+
+    /// Returns the `tracing` event field recording `value` as the parameter
+    /// `name` of `func`, or `None` if it's skipped.
+    fn tracing_field(&self, func: &str, name: &str, value: &str) -> Option<String> {
+        let qualified = format!("{}.{}", func, name);
+        let matches = |list: &[String]| list.iter().any(|p| *p == name || *p == qualified);
+        let field = to_rust_ident(name);
+        if matches(&self.opts.tracing_skip) {
+            None
+        } else if matches(&self.opts.tracing_redact) {
+            Some(format!("{} = \"<redacted>\"", field))
+        } else {
+            Some(format!(
+                "{} = wai_bindgen_wasmer::tracing::field::debug(&{})",
+                field, value
+            ))
+        }
+    }
+
+    /// Returns whether the parameter `name` of `func`, of type `ty`, is
+    /// interned.
+    fn interned(&self, func: &str, name: &str, ty: &Type) -> bool {
+        let qualified = format!("{}.{}", func, name);
+        *ty == Type::String
+            && self
+                .opts
+                .intern
+                .iter()
+                .any(|p| *p == func || *p == qualified)
+    }
+
+    /// Emits `{func}_with_deadline`, which calls the export `func` with its
+    /// metering points limited to a `Deadline`.
+    fn print_with_deadline(&mut self, iface: &Interface, func: &Function) {
+        assert!(
+            self.opts.async_.is_none(),
+            "deadlines are not supported for async functions"
+        );
+        let prev = mem::take(&mut self.src);
+        let sig = FnSig {
+            self_arg: Some(
+                "&self, store: &mut wasmer::Store, deadline: wai_bindgen_wasmer::Deadline"
+                    .to_string(),
+            ),
+            ..Default::default()
+        };
+        let params = self.print_docs_and_params(iface, func, TypeMode::AllBorrowed("'_"), &sig);
+        self.push_str("-> Result<");
+        self.print_ty(iface, &func.result, TypeMode::Owned);
+        self.push_str(", wai_bindgen_wasmer::CallError> {\n");
+        let name = to_rust_ident(&func.name);
+        self.push_str(&format!(
+            "wai_bindgen_wasmer::rt::with_deadline(
+                store,
+                self.metering.as_ref(),
+                deadline,
+                |store| self.{name}(store, {}),
+            )
+            }}\n",
+            params.join(", "),
+        ));
+        let src = String::from(mem::replace(&mut self.src, prev));
+        let src = src.replacen(
+            &format!("fn {}(", name),
+            &format!("fn {}_with_deadline(", name),
+            1,
+        );
+
+        let exports = self
+            .guest_exports
+            .get_mut(&iface.name)
+            .expect("export functions are printed first");
+        exports.funcs.push(src);
+        exports.fields.insert(
+            "metering".to_string(),
+            (
+                "Option<wai_bindgen_wasmer::rt::Metering>".to_string(),
+                "wai_bindgen_wasmer::rt::Metering::from_instance(_instance)".to_string(),
+            ),
+        );
+    }
+
+    /// Returns the interface defining `resource` if it's generated alongside
+    /// the current one in the same direction, in which case that interface
+    /// owns the resource's handle table.
+    fn resource_owner<'a>(&self, resource: &'a Resource) -> Option<&'a str> {
+        let names = if self.in_import {
+            &self.export_names
+        } else {
+            &self.import_names
+        };
+        resource
+            .foreign_module
+            .as_deref()
+            .filter(|module| names.contains(*module))
+    }
+
+    /// Returns the name of the module generated for the interface `iface`.
+    fn module_name(&self, iface: &str) -> String {
+        match NameOverride::find(&self.opts.module_name, iface) {
+            Some(name) => name.to_string(),
+            None => iface.to_snake_case(),
+        }
+    }
+
+    /// Returns the name of the trait generated for the imported interface
+    /// `iface`.
+    fn trait_name(&self, iface: &str) -> String {
+        match NameOverride::find(&self.opts.trait_name, iface) {
+            Some(name) => name.to_string(),
+            None => iface.to_camel_case(),
+        }
+    }
+
+    /// Returns the name of the struct generated for the exported interface
+    /// `iface`.
+    fn struct_name(&self, iface: &str) -> String {
+        match NameOverride::find(&self.opts.struct_name, iface) {
+            Some(name) => name.to_string(),
+            None => iface.to_camel_case(),
+        }
+    }
+
+    /// Returns the `#[cfg]` attribute gating the module generated for the
+    /// interface `iface`, if it's gated.
+    fn cfg_attr(&self, iface: &str) -> String {
+        match NameOverride::find(&self.opts.cfg_feature, iface) {
+            Some(feature) => format!("#[cfg(feature = \"{}\")]\n", feature),
+            None => String::new(),
+        }
+    }
+
+    /// Returns the `#[cfg]` attribute gating an item which uses all of the
+    /// interfaces `ifaces`.
+    fn all_cfg_attr<'a>(&self, ifaces: impl Iterator<Item = &'a String>) -> String {
+        let features = ifaces
+            .filter_map(|name| NameOverride::find(&self.opts.cfg_feature, name))
+            .collect::<BTreeSet<_>>();
+        if features.is_empty() {
+            return String::new();
+        }
+        let features = features
+            .iter()
+            .map(|feature| format!("feature = \"{}\"", feature))
+            .collect::<Vec<_>>();
+        format!("#[cfg(all({}))]\n", features.join(", "))
+    }
+
+    /// Returns the local variable holding the borrowed table of `handle`.
+    fn tables_var(&self, handle: &str) -> String {
+        match self.foreign_handles.get(handle) {
+            Some(owner) => format!("{}_tables", owner.to_snake_case()),
+            None => "tables".to_string(),
+        }
+    }
+
+    /// Returns the interfaces owning the tables of this interface's foreign
+    /// handles.
+    fn foreign_tables(&self) -> BTreeSet<String> {
+        self.foreign_handles.values().cloned().collect()
+    }
+
+    /// Emits an inherent method on the handle type of the resource `func`
+    /// belongs to, which calls the export through the exports struct. This
+    /// lets `counter.get(&mut store, &exports)` stand in for
+    /// `exports.counter_get(&mut store, &counter)`.
+    fn print_resource_method(&mut self, iface: &Interface, func: &Function) {
+        let resource = match &func.kind {
+            FunctionKind::Freestanding => return,
+            FunctionKind::Static { resource, .. } | FunctionKind::Method { resource, .. } => {
+                *resource
+            }
+        };
+        let prev = mem::take(&mut self.src);
+        let is_async = !self.opts.async_.is_none();
+        let is_method = matches!(func.kind, FunctionKind::Method { .. });
+        let exports_ty = self.struct_name(&iface.name);
+        let sig = FnSig {
+            async_: is_async,
+            use_item_name: true,
+            self_is_first_param: is_method,
+            self_arg: Some(if is_method {
+                format!("&self, store: &mut wasmer::Store, exports: &{exports_ty}")
+            } else {
+                format!("store: &mut wasmer::Store, exports: &{exports_ty}")
+            }),
+            ..Default::default()
+        };
+        let params = self.print_docs_and_params(iface, func, TypeMode::AllBorrowed("'_"), &sig);
+        self.push_str("-> Result<");
+        self.print_ty(iface, &func.result, TypeMode::Owned);
+        self.push_str(", wasmer::RuntimeError> {\n");
+        self.push_str(&format!(
+            "exports.{}(store, {}){}\n}}\n",
+            to_rust_ident(&func.name),
+            params.join(", "),
+            if is_async { ".await" } else { "" },
+        ));
+        let src = String::from(mem::replace(&mut self.src, prev));
+        self.guest_exports
+            .get_mut(&iface.name)
+            .expect("export functions are printed first")
+            .resource_methods
+            .entry(resource)
+            .or_default()
+            .push(src);
+    }
+
+    /// Adds `memory_size` and `set_grow_hook` to the exports struct of `iface`,
+    /// unless they already were.
+    fn print_memory_usage(&mut self, iface: &Interface) {
+        let exports = self
+            .guest_exports
+            .get_mut(&iface.name)
+            .expect("export functions are printed first");
+        if exports.fields.contains_key("memory_watch") {
+            return;
+        }
+        exports.fields.insert(
+            "memory".to_string(),
+            (
+                "wasmer::Memory".to_string(),
+                "wai_bindgen_wasmer::memory_export(_instance, \"memory\")?".to_string(),
+            ),
+        );
+        exports.fields.insert(
+            "memory_watch".to_string(),
+            (
+                "wai_bindgen_wasmer::MemoryWatch".to_string(),
+                "Default::default()".to_string(),
+            ),
+        );
+        exports.funcs.push(
+            "
+                /// Returns the current size in bytes of the guest's linear
+                /// memory.
+                pub fn memory_size(&self, store: &impl wasmer::AsStoreRef) -> u64 {
+                    self.memory.view(store).data_size()
+                }
+
+                /// Calls `hook` with the size of the guest's memory in bytes
+                /// whenever allocating space for arguments leaves it larger
+                /// than both `threshold` and the size last reported. An error
+                /// returned by `hook` aborts the call being made.
+                pub fn set_grow_hook(
+                    &self,
+                    threshold: u64,
+                    hook: impl FnMut(u64) -> Result<(), wasmer::RuntimeError> + Send + 'static,
+                ) {
+                    self.memory_watch.set(threshold, hook)
+                }
+            "
+            .to_string(),
+        );
+    }
+
+    /// Adds `guest_panic` to the exports struct of `iface`, unless it already
+    /// was.
+    fn print_guest_panic(&mut self, iface: &Interface) {
+        let exports = self
+            .guest_exports
+            .get_mut(&iface.name)
+            .expect("export functions are printed first");
+        if exports.fields.contains_key("panic_context") {
+            return;
+        }
+        exports.fields.insert(
+            "panic_context".to_string(),
+            (
+                "Option<wai_bindgen_wasmer::rt::PanicContext>".to_string(),
+                "wai_bindgen_wasmer::rt::PanicContext::from_instance(&store, _instance)"
+                    .to_string(),
+            ),
+        );
+        exports.funcs.push(
+            "
+                /// Returns the panic of the guest which made a call fail with
+                /// `error`, or `None` if the call trapped for another reason
+                /// or the guest doesn't record its panics.
+                ///
+                /// Each panic is only returned once, so this should be called
+                /// right after the call failed.
+                pub fn guest_panic(
+                    &self,
+                    store: &mut wasmer::Store,
+                    error: &wasmer::RuntimeError,
+                ) -> Option<wai_bindgen_wasmer::GuestPanic> {
+                    self.panic_context.as_ref()?.take(store, error)
+                }
+            "
+            .to_string(),
+        );
+    }
+
+    /// Starts measuring a call to `func` for the `metrics` option. The call is
+    /// reported when the guard is dropped, as failed unless the function
+    /// reached its `Return`.
+    fn print_metrics_guard(&mut self, iface: &Interface, func: &Function) {
+        if self.opts.metrics {
+            self.src.push_str(&format!(
+                "let mut _metrics = wai_bindgen_wasmer::rt::CallMetrics::new(\"{}\", \"{}\");\n",
+                iface.name, func.name,
+            ));
+        }
+    }
+
+    /// Checks that the host granted every capability `func` requires before
+    /// calling it, trapping with `PermissionDenied` otherwise.
+    fn print_capability_check(&mut self, func: &Function) {
+        if func.requires.is_empty() {
+            return;
+        }
+        self.needs_capabilities = true;
+        self.src.push_str(&format!(
+            "store.data().data.capabilities().check({:?}, &{:?})?;\n",
+            func.name, func.requires,
+        ));
+    }
+
+    /// Checks that the arguments of `func`, which are in the variables
+    /// `params`, are within the bounds of their `@range` annotations.
+    fn print_range_checks(&mut self, func: &Function, params: &[String]) {
+        for ((name, _), param) in func.params.iter().zip(params) {
+            if let Some(range) = func.range(name) {
+                self.src.push_str(&range_check(func, name, param, range));
+            }
+        }
+    }
+
+    /// Takes the source generated so far, formatting it if requested.
+    fn finish_src(&mut self) -> Source {
+        let mut src = mem::take(&mut self.src);
+        if self.opts.rustfmt {
+            let mut child = Command::new("rustfmt")
+                .arg("--edition=2018")
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+                .expect("failed to spawn `rustfmt`");
+            child
+                .stdin
+                .take()
+                .unwrap()
+                .write_all(src.as_bytes())
+                .unwrap();
+            src.as_mut_string().truncate(0);
+            child
+                .stdout
+                .take()
+                .unwrap()
+                .read_to_string(src.as_mut_string())
+                .unwrap();
+            let status = child.wait().unwrap();
+            assert!(status.success());
+        }
+        src
+    }
+
+    /// Emits the `Plugin` type for the `plugin` option, which instantiates a
+    /// module with the host implementing every interface in `plugin_tables`
+    /// and wraps every interface in `plugin_guests`.
+    fn print_plugin(&mut self) {
+        let host = mem::take(&mut self.plugin_tables);
+        let guest = mem::take(&mut self.plugin_guests);
+        let cfg = self.all_cfg_attr(host.keys().chain(guest.iter()));
+
+        if !host.is_empty() {
+            self.push_str(&cfg);
+            self.push_str(
+                "
+                    type Initializer = Box<
+                        dyn Fn(
+                            &wai_bindgen_wasmer::wasmer::Instance,
+                            &dyn wai_bindgen_wasmer::wasmer::AsStoreRef,
+                        ) -> wai_bindgen_wasmer::anyhow::Result<()>,
+                    >;
+                ",
+            );
+        }
+        self.push_str(&cfg);
+        self.push_str(
+            "
+                /// A wasm module instantiated together with the bindings of
+                /// every interface it imports and exports.
+                pub struct Plugin {
+                    pub store: wai_bindgen_wasmer::wasmer::Store,
+                    pub instance: wai_bindgen_wasmer::wasmer::Instance,
+            ",
+        );
+        for name in guest.iter() {
+            self.push_str(&format!(
+                "pub {}: {}::{},\n",
+                name.to_snake_case(),
+                self.module_name(name),
+                self.struct_name(name),
+            ));
+        }
+        self.push_str("imports: wai_bindgen_wasmer::wasmer::Imports,\n");
+        if !host.is_empty() {
+            self.push_str("initializers: Vec<Initializer>,\n");
+        }
+        self.push_str("}\n");
+
+        let data_doc = if host.is_empty() {
+            ""
+        } else {
+            ", with `data` implementing the\n/// interfaces the module imports"
+        };
+        let (generics, data_param, data_arg) = if host.is_empty() {
+            (String::new(), String::new(), String::new())
+        } else {
+            let mut bounds = host
+                .keys()
+                .map(|name| format!("{}::{}", self.module_name(name), self.trait_name(name)))
+                .collect::<Vec<_>>();
+            if host.len() > 1 && !self.opts.shared_host {
+                bounds.push("Clone".to_string());
+            }
+            (
+                format!("<T: {}>", bounds.join(" + ")),
+                format!(", data: {}", self.host_data_ty()),
+                ", data".to_string(),
+            )
+        };
+
+        self.push_str(&format!(
+            "
+                {cfg}impl Plugin {{
+                    /// Compiles the module in `bytes` and instantiates it in a
+                    /// new store, see [`Plugin::instantiate`].
+                    pub fn load{generics}(
+                        bytes: impl AsRef<[u8]>{data_param}
+                    ) -> wai_bindgen_wasmer::anyhow::Result<Self> {{
+                        let store = wai_bindgen_wasmer::wasmer::Store::default();
+                        let module = wai_bindgen_wasmer::wasmer::Module::new(&store, bytes)?;
+                        Self::instantiate(store, &module{data_arg})
+                    }}
+
+                    /// Instantiates `module` in `store`{data_doc}.
+                    pub fn instantiate{generics}(
+                        store: wai_bindgen_wasmer::wasmer::Store,
+                        module: &wai_bindgen_wasmer::wasmer::Module{data_param}
+                    ) -> wai_bindgen_wasmer::anyhow::Result<Self> {{
+                        let import_object = wai_bindgen_wasmer::wasmer::Imports::new();
+                        Self::instantiate_with_imports(store, module, import_object{data_arg})
+                    }}
+            "
+        ));
+        if self.opts.wasi {
+            self.push_str(&format!(
+                "
+                    /// Compiles the module in `bytes` and instantiates it in a
+                    /// new store, see [`Plugin::instantiate_wasi`].
+                    pub fn load_wasi{generics}(
+                        bytes: impl AsRef<[u8]>,
+                        wasi: &mut wai_bindgen_wasmer::wasmer_wasi::WasiStateBuilder{data_param}
+                    ) -> wai_bindgen_wasmer::anyhow::Result<(Self, wai_bindgen_wasmer::wasmer_wasi::WasiFunctionEnv)> {{
+                        let store = wai_bindgen_wasmer::wasmer::Store::default();
+                        let module = wai_bindgen_wasmer::wasmer::Module::new(&store, bytes)?;
+                        Self::instantiate_wasi(store, &module, wasi{data_arg})
+                    }}
+
+                    /// Instantiates `module` in `store` like
+                    /// [`Plugin::instantiate`], also providing the WASI
+                    /// imports of the environment built by `wasi`.
+                    ///
+                    /// The returned WASI environment is already connected to
+                    /// the instance's memory.
+                    pub fn instantiate_wasi{generics}(
+                        mut store: wai_bindgen_wasmer::wasmer::Store,
+                        module: &wai_bindgen_wasmer::wasmer::Module,
+                        wasi: &mut wai_bindgen_wasmer::wasmer_wasi::WasiStateBuilder{data_param}
+                    ) -> wai_bindgen_wasmer::anyhow::Result<(Self, wai_bindgen_wasmer::wasmer_wasi::WasiFunctionEnv)> {{
+                        let wasi_env = wasi.finalize(&mut store)?;
+                        let import_object = wasi_env.import_object(&mut store, module)?;
+                        let mut plugin = Self::instantiate_uninitialized(store, module, import_object{data_arg})?;
+                        let memory = wai_bindgen_wasmer::memory_export(&plugin.instance, \"memory\")?;
+                        wasi_env.data_mut(&mut plugin.store).set_memory(memory);
+                        wai_bindgen_wasmer::initialize(&mut plugin.store, &plugin.instance)?;
+                        Ok((plugin, wasi_env))
+                    }}
+                "
+            ));
+        }
+        self.push_str(&format!(
+            "
+                /// Instantiates `module` in `store` like
+                /// [`Plugin::instantiate`], adding the bindings' imports to
+                /// `import_object`, which may already contain other imports
+                /// of the module.
+                pub fn instantiate_with_imports{generics}(
+                    store: wai_bindgen_wasmer::wasmer::Store,
+                    module: &wai_bindgen_wasmer::wasmer::Module,
+                    import_object: wai_bindgen_wasmer::wasmer::Imports{data_param}
+                ) -> wai_bindgen_wasmer::anyhow::Result<Self> {{
+                    let mut plugin = Self::instantiate_uninitialized(store, module, import_object{data_arg})?;
+                    wai_bindgen_wasmer::initialize(&mut plugin.store, &plugin.instance)?;
+                    Ok(plugin)
+                }}
+
+                /// Like [`Plugin::instantiate_with_imports`], but without
+                /// running the module's `_initialize` function.
+                fn instantiate_uninitialized{generics}(
+                    mut store: wai_bindgen_wasmer::wasmer::Store,
+                    module: &wai_bindgen_wasmer::wasmer::Module,
+                    mut import_object: wai_bindgen_wasmer::wasmer::Imports{data_param}
+                ) -> wai_bindgen_wasmer::anyhow::Result<Self> {{
+            "
+        ));
+        for owner in host.keys() {
+            if self.shared_tables.contains(owner) {
+                self.push_str(&format!(
+                    "let {}_tables = std::rc::Rc::default();\n",
+                    owner.to_snake_case()
+                ));
+            }
+        }
+        for (i, (name, owners)) in host.iter().enumerate() {
+            let snake = name.to_snake_case();
+            let module = self.module_name(name);
+            let data = if i + 1 < host.len() {
+                "data.clone()"
+            } else {
+                "data"
+            };
+            let mut tables = String::new();
+            if self.shared_tables.contains(name) {
+                tables.push_str(&format!(", std::rc::Rc::clone(&{snake}_tables)"));
+            }
+            for owner in owners {
+                tables.push_str(&format!(
+                    ", std::rc::Rc::clone(&{}_tables)",
+                    owner.to_snake_case()
+                ));
+            }
+            let func = if self.shared_tables.contains(name) {
+                "add_to_imports_with_tables"
+            } else {
+                "add_to_imports"
+            };
+            self.push_str(&format!(
+                "let {snake}_init = {module}::{func}(&mut store, &mut import_object, {data}{tables});\n",
+            ));
+        }
+        for name in guest.iter() {
+            self.push_str(&format!(
+                "let {}_env = {}::{}::add_to_imports(&mut store, &mut import_object);\n",
+                name.to_snake_case(),
+                self.module_name(name),
+                self.struct_name(name),
+            ));
+        }
+        self.push_str(
+            "
+                let instance = wai_bindgen_wasmer::wasmer::Instance::new(&mut store, module, &import_object)?;
+                Self::check_abi_version(&mut store, &instance)?;
+            ",
+        );
+        self.print_interface_hash_checks(&guest);
+        for name in host.keys() {
+            self.push_str(&format!(
+                "{}_init(&instance, &store)?;\n",
+                name.to_snake_case()
+            ));
+        }
+        for name in guest.iter() {
+            self.push_str(&format!(
+                "let {snake} = {module}::{camel}::new(&mut store, &instance, {snake}_env)?;\n",
+                snake = name.to_snake_case(),
+                module = self.module_name(name),
+                camel = self.struct_name(name),
+            ));
+        }
+        self.push_str("Ok(Plugin {\nstore,\ninstance,\n");
+        for name in guest.iter() {
+            self.push_str(&format!("{},\n", name.to_snake_case()));
+        }
+        self.push_str("imports: import_object,\n");
+        if !host.is_empty() {
+            self.push_str("initializers: vec![\n");
+            for name in host.keys() {
+                self.push_str(&format!("Box::new({}_init),\n", name.to_snake_case()));
+            }
+            self.push_str("],\n");
+        }
+        self.push_str("})\n}\n");
+
+        let wasi_doc = if self.opts.wasi {
+            "
+                ///
+                /// A WASI environment the module was instantiated with has to
+                /// be connected to the memory of the new instance afterwards,
+                /// after which a module exporting an `_initialize` function
+                /// has to be set up with `wai_bindgen_wasmer::initialize`.
+            "
+        } else {
+            ""
+        };
+        self.push_str(&format!(
+            "
+                /// Replaces the instance with one of `module`, typically a new
+                /// version of the module, which has to import and export the
+                /// same interfaces.
+                ///
+                /// The state of the host, including the resources it handed
+                /// out, is kept. Handles to resources of the previous instance
+                /// can't be used with the new one, and calls passing them fail
+                /// with [`wai_bindgen_wasmer::StaleHandle`]. If an error is
+                /// returned the plugin may be left between the two instances,
+                /// and should be reloaded again or dropped.{wasi_doc}
+                pub fn reload(
+                    &mut self,
+                    module: &wai_bindgen_wasmer::wasmer::Module,
+                ) -> wai_bindgen_wasmer::anyhow::Result<()> {{
+                    let instance = wai_bindgen_wasmer::wasmer::Instance::new(&mut self.store, module, &self.imports)?;
+                    Self::check_abi_version(&mut self.store, &instance)?;
+            "
+        ));
+        self.print_interface_hash_checks(&guest);
+        for name in guest.iter() {
+            self.push_str(&format!(
+                "self.{}.reload(&mut self.store, &instance)?;\n",
+                name.to_snake_case()
+            ));
+        }
+        if !host.is_empty() {
+            self.push_str(
+                "
+                    for init in self.initializers.iter() {
+                        init(&instance, &self.store)?;
+                    }
+                ",
+            );
+        }
+        if !self.opts.wasi {
+            self.push_str("wai_bindgen_wasmer::initialize(&mut self.store, &instance)?;\n");
+        }
+        self.push_str(&format!(
+            "
+                    self.instance = instance;
+                    Ok(())
+                }}
+
+                fn check_abi_version(
+                    store: &mut wai_bindgen_wasmer::wasmer::Store,
+                    instance: &wai_bindgen_wasmer::wasmer::Instance,
+                ) -> wai_bindgen_wasmer::anyhow::Result<()> {{
+                    wai_bindgen_wasmer::check_abi_version(store, instance, \"{}\")
+                }}
+            }}
+            ",
+            self.opts.symbol_namespace,
+        ));
+    }
+
+    /// Emits checks that `module` was built against the same versions of the
+    /// interfaces in `guest` as the bindings. The interfaces the host
+    /// implements are checked by their initializers.
+    fn print_interface_hash_checks(&mut self, guest: &BTreeSet<String>) {
+        for name in guest.iter() {
+            self.push_str(&format!(
+                "wai_bindgen_wasmer::check_interface_hash(module, \"{name}\", {}::INTERFACE_HASH)?;\n",
+                self.module_name(name),
+            ));
+        }
+    }
+
+    /// Emits the `ImportsBuilder` type for the `imports_builder` option, which
+    /// has a method adding the imports of every interface in `plugin_tables`
+    /// and wraps every interface in `plugin_guests` once instantiated.
+    ///
+    /// Interfaces using the resources of other interfaces share their handle
+    /// tables, and so their host implementation, so they're left for
+    /// `add_to_imports_with_tables` and `ImportsBuilder::initializer`.
+    fn print_imports_builder(&mut self) {
+        let host = self.plugin_tables.clone();
+        let guest = self.plugin_guests.clone();
+        let cfg = self.all_cfg_attr(host.keys().chain(guest.iter()));
+        let wasi = self.opts.wasi;
+
+        self.push_str(&format!(
+            "
+                {cfg}/// Assembles the imports of a module from a host
+                /// implementation of each interface it imports, then
+                /// instantiates it, runs the initializers of those imports and
+                /// wraps the interfaces it exports.
+                pub struct ImportsBuilder<'a> {{
+                    store: &'a mut wai_bindgen_wasmer::wasmer::Store,
+                    imports: wai_bindgen_wasmer::wasmer::Imports,
+                    initializers: Vec<
+                        Box<
+                            dyn Fn(
+                                &wai_bindgen_wasmer::wasmer::Instance,
+                                &dyn wai_bindgen_wasmer::wasmer::AsStoreRef,
+                            ) -> wai_bindgen_wasmer::anyhow::Result<()>,
+                        >,
+                    >,
+            "
+        ));
+        if wasi {
+            self.push_str("wasi: Option<wai_bindgen_wasmer::wasmer_wasi::WasiFunctionEnv>,\n");
+        }
+        self.push_str("}\n");
+
+        self.push_str(&format!(
+            "
+                {cfg}/// A module instantiated by [`ImportsBuilder::instantiate`],
+                /// with the bindings of every interface it exports.
+                pub struct Instantiated {{
+                    pub instance: wai_bindgen_wasmer::wasmer::Instance,
+            "
+        ));
+        if wasi {
+            self.push_str(
+                "
+                    /// The WASI environment added with [`ImportsBuilder::wasi`],
+                    /// already connected to the instance's memory.
+                    pub wasi: Option<wai_bindgen_wasmer::wasmer_wasi::WasiFunctionEnv>,
+                ",
+            );
+        }
+        for name in guest.iter() {
+            self.push_str(&format!(
+                "pub {}: {}::{},\n",
+                name.to_snake_case(),
+                self.module_name(name),
+                self.struct_name(name),
+            ));
+        }
+        self.push_str("}\n");
+
+        self.push_str(&format!(
+            "
+                {cfg}impl<'a> ImportsBuilder<'a> {{
+                    /// Creates a builder of an instance in `store`.
+                    pub fn new(store: &'a mut wai_bindgen_wasmer::wasmer::Store) -> Self {{
+                        Self::with_imports(store, wai_bindgen_wasmer::wasmer::Imports::new())
+                    }}
+
+                    /// Creates a builder of an instance in `store` starting from
+                    /// `imports`, which may already contain other imports of the
+                    /// module.
+                    pub fn with_imports(
+                        store: &'a mut wai_bindgen_wasmer::wasmer::Store,
+                        imports: wai_bindgen_wasmer::wasmer::Imports,
+                    ) -> Self {{
+                        Self {{
+                            store,
+                            imports,
+                            initializers: Vec::new(),
+                            {wasi_field}
+                        }}
+                    }}
+            ",
+            wasi_field = if wasi { "wasi: None," } else { "" },
+        ));
+        for (name, owners) in host.iter() {
+            if !owners.is_empty() {
+                continue;
+            }
+            self.push_str(&format!(
+                "
+                    /// Adds the imports of the `{name}` interface, implemented by
+                    /// `data`.
+                    pub fn {snake}<T: {module}::{trait_}>(mut self, data: {data_ty}) -> Self {{
+                        let init = {module}::add_to_imports(self.store, &mut self.imports, data);
+                        self.initializers.push(Box::new(init));
+                        self
+                    }}
+                ",
+                snake = to_rust_ident(&name.to_snake_case()),
+                module = self.module_name(name),
+                trait_ = self.trait_name(name),
+                data_ty = self.host_data_ty(),
+            ));
+        }
+        if wasi {
+            self.push_str(
+                "
+                    /// Adds the WASI imports of the environment built by `wasi`
+                    /// for `module`, which is connected to the memory of the
+                    /// instance once it's instantiated.
+                    pub fn wasi(
+                        mut self,
+                        module: &wai_bindgen_wasmer::wasmer::Module,
+                        wasi: &mut wai_bindgen_wasmer::wasmer_wasi::WasiStateBuilder,
+                    ) -> wai_bindgen_wasmer::anyhow::Result<Self> {
+                        let wasi_env = wasi.finalize(&mut *self.store)?;
+                        let imports = wasi_env.import_object(&mut *self.store, module)?;
+                        self.imports.extend(&imports);
+                        self.wasi = Some(wasi_env);
+                        Ok(self)
+                    }
+                ",
+            );
+        }
+        self.push_str(
+            "
+                /// Returns the imports added so far, to add imports of the
+                /// module which don't come from these bindings.
+                pub fn imports_mut(&mut self) -> &mut wai_bindgen_wasmer::wasmer::Imports {
+                    &mut self.imports
+                }
+
+                /// Adds a function to run with the new instance before it's
+                /// used, like the initializers returned by `add_to_imports`.
+                pub fn initializer(
+                    mut self,
+                    init: impl Fn(
+                            &wai_bindgen_wasmer::wasmer::Instance,
+                            &dyn wai_bindgen_wasmer::wasmer::AsStoreRef,
+                        ) -> wai_bindgen_wasmer::anyhow::Result<()>
+                        + 'static,
+                ) -> Self {
+                    self.initializers.push(Box::new(init));
+                    self
+                }
+
+                /// Instantiates `module` with the imports, runs the
+                /// initializers and wraps the interfaces it exports.
+                pub fn instantiate(
+                    self,
+                    module: &wai_bindgen_wasmer::wasmer::Module,
+                ) -> wai_bindgen_wasmer::anyhow::Result<Instantiated> {
+                    let _store = self.store;
+                    let mut _imports = self.imports;
+            ",
+        );
+        for name in guest.iter() {
+            self.push_str(&format!(
+                "let {}_env = {}::{}::add_to_imports(&mut *_store, &mut _imports);\n",
+                name.to_snake_case(),
+                self.module_name(name),
+                self.struct_name(name),
+            ));
+        }
+        self.push_str(
+            "let _instance = wai_bindgen_wasmer::wasmer::Instance::new(&mut *_store, module, &_imports)?;\n",
+        );
+        self.print_interface_hash_checks(&guest);
+        if wasi {
+            self.push_str(
+                "
+                    if let Some(wasi) = &self.wasi {
+                        let memory = wai_bindgen_wasmer::memory_export(&_instance, \"memory\")?;
+                        wasi.data_mut(&mut *_store).set_memory(memory);
+                    }
+                ",
+            );
+        }
+        self.push_str(
+            "
+                for init in self.initializers.iter() {
+                    init(&_instance, &*_store)?;
+                }
+                wai_bindgen_wasmer::initialize(&mut *_store, &_instance)?;
+            ",
+        );
+        for name in guest.iter() {
+            self.push_str(&format!(
+                "let {snake} = {module}::{camel}::new(&mut *_store, &_instance, {snake}_env)?;\n",
+                snake = name.to_snake_case(),
+                module = self.module_name(name),
+                camel = self.struct_name(name),
+            ));
+        }
+        self.push_str("Ok(Instantiated {\ninstance: _instance,\n");
+        if wasi {
+            self.push_str("wasi: self.wasi,\n");
+        }
+        for name in guest.iter() {
+            self.push_str(&format!("{},\n", name.to_snake_case()));
+        }
+        self.push_str("})\n}\n}\n");
+    }
+
+    /// Records the `FunctionDesc` of `func` for the `FUNCTIONS` table.
+    fn push_function_desc(&mut self, iface: &Interface, func: &Function, is_async: bool) {
+        let params = func
+            .params
+            .iter()
+            .map(|(name, ty)| format!("({:?}, {:?}), ", name, wai_type(iface, ty)))
+            .collect::<String>();
+        self.function_descs.push(format!(
+            "wai_bindgen_wasmer::FunctionDesc {{
+                name: {:?},
+                params: &[{}],
+                result: {:?},
+                requires: &{:?},
+                is_async: {},
+            }},\n",
+            func.name,
+            params,
+            wai_type(iface, &func.result),
+            func.requires,
+            is_async,
+        ));
+    }
+
+    fn print_function_descs(&mut self) {
+        let descs = mem::take(&mut self.function_descs);
+        self.push_str(
+            "
+                /// The functions of this interface, in the order they're
+                /// declared in.
+                pub const FUNCTIONS: &[wai_bindgen_wasmer::FunctionDesc] = &[
+            ",
+        );
+        for desc in descs {
+            self.push_str(&desc);
+        }
+        self.push_str("];\n");
+    }
+
+    /// The receiver of the methods of the trait implemented by the host.
+    fn trait_self_arg(&self) -> &'static str {
+        if self.opts.shared_host {
+            "&self"
+        } else {
+            "&mut self"
+        }
+    }
+
+    /// The type of the host's data passed to `add_to_imports`, stored in
+    /// the environment of each instance.
+    fn host_data_ty(&self) -> &'static str {
+        if self.opts.shared_host {
+            "std::sync::Arc<T>"
+        } else {
+            "T"
+        }
+    }
 
-impl Wasmer {
-    pub fn new() -> Wasmer {
-        Wasmer::default()
+    /// How import glue borrows the host's data from the environment.
+    fn host_ref(&self) -> &'static str {
+        if self.opts.shared_host {
+            "&*data_mut.data"
+        } else {
+            "&mut data_mut.data"
+        }
     }
 
     fn abi_variant(dir: Direction) -> AbiVariant {
@@ -168,7 +1478,7 @@ impl Wasmer {
     }
 
     fn print_intrinsics(&mut self) {
-        if self.needs_lazy_initialized || !self.exported_resources.is_empty() {
+        if !self.exported_resources.is_empty() {
             self.push_str("use wai_bindgen_wasmer::once_cell::unsync::OnceCell;\n");
         }
 
@@ -224,11 +1534,668 @@ impl Wasmer {
         self.needs_custom_error_to_trap = true;
         FunctionRet::CustomToTrap
     }
+
+    /// Determines how a parameter of type `ty` is captured by the recorder,
+    /// returning `None` if it can't be turned into an owned value.
+    fn capture_of(&self, iface: &Interface, ty: &Type) -> Option<Capture> {
+        // Only host functions take their parameters owned or as `Le` slices;
+        // the methods calling exports borrow them as plain Rust values.
+        let owned = self.in_import && self.opts.owned_params;
+        let id = match ty {
+            Type::String if owned => return Some(Capture::Clone),
+            Type::String => return Some(Capture::Str),
+            Type::Handle(_) => return None,
+            Type::Id(id) => *id,
+            _ => return Some(Capture::Clone),
+        };
+        let info = self.types.get(id);
+        if owned {
+            // Parameters are already owned unless they contain handles.
+            return (!info.has_handle).then_some(Capture::Clone);
+        }
+        if !info.has_list && !info.has_handle {
+            return Some(Capture::Clone);
+        }
+        let ty = &iface.types[id];
+        if ty.name.is_some() {
+            return None;
+        }
+        match &ty.kind {
+            TypeDefKind::List(t) if is_list_canonical(iface, t) => {
+                if self.sizes.align(t) > 1 && self.in_import {
+                    Some(Capture::LeSlice)
+                } else {
+                    Some(Capture::Slice)
+                }
+            }
+            TypeDefKind::Type(t) => self.capture_of(iface, t),
+            _ => None,
+        }
+    }
+
+    /// Builds the recorder pieces for `func`, or `None` if any of its
+    /// parameters or its result can't be captured.
+    fn recorded_call(&mut self, iface: &Interface, func: &Function) -> Option<RecordedCall> {
+        if self.types.type_info(iface, &func.result).has_handle {
+            return None;
+        }
+        let mut call = RecordedCall {
+            variant: func.name.to_camel_case(),
+            fields: Vec::new(),
+            captures: Vec::new(),
+            replay_args: Vec::new(),
+            result_ty: String::new(),
+        };
+        for (name, ty) in func.params.iter() {
+            let name = to_rust_ident(name);
+            let (capture, replay) = match self.capture_of(iface, ty)? {
+                Capture::Clone => (format!("{name}.clone()"), format!("{name}.clone()")),
+                Capture::Str => (format!("{name}.to_string()"), name.clone()),
+                Capture::Slice => (format!("{name}.to_vec()"), name.clone()),
+                Capture::LeSlice => (
+                    format!("{name}.iter().map(|v| v.get()).collect()"),
+                    format!(
+                        "&{name}.iter().map(|v| wai_bindgen_wasmer::Le::new(*v)).collect::<Vec<_>>()"
+                    ),
+                ),
+            };
+            let prev = mem::take(&mut self.src);
+            self.print_ty(iface, ty, TypeMode::Owned);
+            let owned = mem::replace(&mut self.src, prev);
+            call.fields.push((name, owned.into()));
+            call.captures.push(capture);
+            call.replay_args.push(replay);
+        }
+        let prev = mem::take(&mut self.src);
+        self.print_ty(iface, &func.result, TypeMode::Owned);
+        call.result_ty = mem::replace(&mut self.src, prev).into();
+        Some(call)
+    }
+
+    /// Emits `Unimplemented*`, an implementation of the import trait `module`
+    /// where every function panics with `todo!()`.
+    fn print_stubs(&mut self, module: &str, funcs: &[Import]) {
+        let camel = self.trait_name(module);
+        self.src.push_str(&format!(
+            "
+                /// An implementation of `{camel}` where every function is
+                /// still `todo!()`, to be filled in by the host.
+                #[derive(Debug, Default, Clone, Copy)]
+                pub struct Unimplemented{camel};
+
+            "
+        ));
+        if !self.opts.async_.is_none() {
+            self.src.push_str("#[wai_bindgen_wasmer::async_trait]\n");
+        }
+        self.src.push_str(&format!(
+            "
+                #[allow(unused_variables)]
+                impl {camel} for Unimplemented{camel} {{
+            "
+        ));
+        for handle in self.all_needed_handles.iter() {
+            self.src
+                .push_str(&format!("type {} = ();\n", handle.to_camel_case()));
+        }
+        if self.opts.custom_error {
+            self.src.push_str("type Error = wasmer::RuntimeError;\n");
+            if self.needs_custom_error_to_trap {
+                self.src.push_str(&format!(
+                    "fn error_to_trap({}, err: Self::Error) -> wasmer::RuntimeError {{
+                        err
+                    }}\n",
+                    self.trait_self_arg(),
+                ));
+            }
+            for ty in self.needs_custom_error_to_types.iter() {
+                self.src.push_str(&format!(
+                    "fn error_to_{}({}, err: Self::Error) -> Result<{}, wasmer::RuntimeError> {{
+                        Err(err)
+                    }}\n",
+                    ty.to_snake_case(),
+                    self.trait_self_arg(),
+                    ty.to_camel_case(),
+                ));
+            }
+        }
+        if self.needs_capabilities {
+            self.src.push_str(&format!(
+                "fn capabilities(&self) -> &wai_bindgen_wasmer::CapabilitySet {{
+                    todo!(\"{module}::capabilities\")
+                }}\n",
+            ));
+        }
+        for f in funcs {
+            self.src.push_str(&f.trait_signature);
+            self.src.push_str(" {\n");
+            self.src
+                .push_str(&format!("todo!(\"{}::{}\")\n", module, f.name));
+            self.src.push_str("}\n");
+        }
+        self.src.push_str("}\n\n");
+    }
+
+    /// Emits the `*Call` log entry type, the `*Recorder` wrapper and the
+    /// `replay_*` function for the import trait `module`.
+    ///
+    /// The options the recorder can't be combined with are rejected by
+    /// `Opts::validate`.
+    fn print_recorder(&mut self, module: &str, funcs: &[Import]) {
+        let camel = self.trait_name(module);
+        let calls = funcs
+            .iter()
+            .map(|f| (f.name.as_str(), f.record.as_ref()))
+            .collect::<Vec<_>>();
+        self.print_call_enum(&camel, &calls);
+
+        self.src.push_str(&format!(
+            "
+                /// Wraps an implementation of `{camel}`, logging every call
+                /// made by the guest into `calls`.
+                pub struct {camel}Recorder<T: {camel}> {{
+                    pub inner: T,
+                    pub calls: Vec<{camel}Call>,
+                }}
+
+                impl<T: {camel}> {camel}Recorder<T> {{
+                    pub fn new(inner: T) -> Self {{
+                        Self {{ inner, calls: Vec::new() }}
+                    }}
+                }}
+
+                impl<T: {camel}> {camel} for {camel}Recorder<T> {{
+            "
+        ));
+        for handle in self.all_needed_handles.iter() {
+            self.src
+                .push_str(&format!("type {0} = T::{0};\n", handle.to_camel_case()));
+        }
+        if self.needs_capabilities {
+            self.src.push_str(
+                "fn capabilities(&self) -> &wai_bindgen_wasmer::CapabilitySet {
+                    self.inner.capabilities()
+                }\n",
+            );
+        }
+        for f in funcs {
+            self.src.push_str(&f.trait_signature);
+            self.src.push_str(" {\n");
+            let call = format!(
+                "self.inner.{}({})",
+                to_rust_ident(&f.name),
+                f.params.join(", ")
+            );
+            self.print_recorded_call(&camel, &f.name, &call, f.record.as_ref(), "result.clone()");
+            self.src.push_str("}\n");
+        }
+        for handle in self.all_needed_handles.iter() {
+            self.src.push_str(&format!(
+                "fn drop_{0}(&mut self, state: Self::{1}) {{
+                    self.inner.drop_{0}(state)
+                }}\n",
+                handle.to_snake_case(),
+                handle.to_camel_case(),
+            ));
+        }
+        self.src.push_str("}\n\n");
+
+        self.src.push_str(&format!(
+            "
+                /// Feeds the `calls` logged by a `{camel}Recorder` back into
+                /// `host`, failing at the first call whose result differs
+                /// from the recorded one. Calls logged as `Unrecorded` are
+                /// skipped.
+                #[allow(unused_variables)]
+                pub fn replay_{snake}<T: {camel}>(
+                    host: &mut T,
+                    calls: &[{camel}Call],
+                ) -> Result<(), wai_bindgen_wasmer::ReplayMismatch> {{
+            ",
+            snake = module.to_snake_case(),
+        ));
+        self.print_replay_loop(&camel, &calls, |f, args| {
+            format!("host.{}({args})", to_rust_ident(f))
+        });
+        self.src.push_str("}\n\n");
+    }
+
+    /// Adds `func` to the functions wrapped by the recorder of the exports
+    /// struct of `iface`.
+    fn push_recorded_export(&mut self, iface: &Interface, func: &Function) {
+        let prev = mem::take(&mut self.src);
+        let sig = FnSig {
+            self_arg: Some("&mut self, store: &mut wasmer::Store".to_string()),
+            ..FnSig::default()
+        };
+        let params = self.print_docs_and_params(iface, func, TypeMode::AllBorrowed("'_"), &sig);
+        self.push_str("-> Result<");
+        self.print_ty(iface, &func.result, TypeMode::Owned);
+        self.push_str(", wasmer::RuntimeError>");
+        let signature = mem::replace(&mut self.src, prev).into();
+
+        let record = self.recorded_call(iface, func).map(|mut call| {
+            call.result_ty = format!("Result<{}, String>", call.result_ty);
+            call
+        });
+        self.guest_exports
+            .get_mut(&iface.name)
+            .expect("export functions are printed first")
+            .recorded
+            .push(RecordedExport {
+                name: func.name.to_string(),
+                signature,
+                params,
+                record,
+            });
+    }
+
+    /// Emits the `*Call` log entry type, the `*Recorder` wrapper and the
+    /// `replay_*` function for the exports struct `name` of the interface
+    /// `module`.
+    fn print_export_recorder(&mut self, module: &str, name: &str, funcs: &[RecordedExport]) {
+        let calls = funcs
+            .iter()
+            .map(|f| (f.name.as_str(), f.record.as_ref()))
+            .collect::<Vec<_>>();
+        self.print_call_enum(name, &calls);
+
+        self.src.push_str(&format!(
+            "
+                /// Wraps the exports of an instance, logging every call made
+                /// by the host into `calls`. A call which traps is logged
+                /// with the message of its error as the result.
+                pub struct {name}Recorder {{
+                    pub inner: {name},
+                    pub calls: Vec<{name}Call>,
+                }}
+
+                impl {name}Recorder {{
+                    pub fn new(inner: {name}) -> Self {{
+                        Self {{ inner, calls: Vec::new() }}
+                    }}
+
+            "
+        ));
+        for f in funcs {
+            self.src.push_str(&f.signature);
+            self.src.push_str(" {\n");
+            let call = format!(
+                "self.inner.{}(store, {})",
+                to_rust_ident(&f.name),
+                f.params.join(", ")
+            );
+            self.print_recorded_call(
+                name,
+                &f.name,
+                &call,
+                f.record.as_ref(),
+                "result.clone().map_err(|e| e.message())",
+            );
+            self.src.push_str("}\n");
+        }
+        self.src.push_str("}\n\n");
+
+        self.src.push_str(&format!(
+            "
+                /// Makes the `calls` logged by a `{name}Recorder` again
+                /// through `exports`, failing at the first call whose result
+                /// differs from the recorded one. Calls logged as
+                /// `Unrecorded` are skipped.
+                #[allow(unused_variables)]
+                pub fn replay_{snake}(
+                    exports: &{name},
+                    store: &mut wasmer::Store,
+                    calls: &[{name}Call],
+                ) -> Result<(), wai_bindgen_wasmer::ReplayMismatch> {{
+            ",
+            snake = module.to_snake_case(),
+        ));
+        self.print_replay_loop(name, &calls, |f, args| {
+            format!(
+                "exports.{}(store, {args}).map_err(|e| e.message())",
+                to_rust_ident(f)
+            )
+        });
+        self.src.push_str("}\n\n");
+    }
+
+    /// Emits the body of a recorder method making `call`, which logs it as a
+    /// `{camel}Call` with the result turned into its logged form by `result`,
+    /// or as `Unrecorded` if it can't be captured.
+    fn print_recorded_call(
+        &mut self,
+        camel: &str,
+        func: &str,
+        call: &str,
+        record: Option<&RecordedCall>,
+        result: &str,
+    ) {
+        let record = match record {
+            Some(record) => record,
+            None => {
+                self.src.push_str(&format!(
+                    "
+                        let result = {call};
+                        self.calls.push({camel}Call::Unrecorded {{ function: \"{func}\" }});
+                        result
+                    "
+                ));
+                return;
+            }
+        };
+        let fields = record
+            .fields
+            .iter()
+            .map(|(name, _)| format!("{name}, "))
+            .collect::<String>();
+        let captures = record
+            .captures
+            .iter()
+            .map(|c| format!("{c}, "))
+            .collect::<String>();
+        self.src.push_str(&format!(
+            "
+                let captured = ({captures});
+                let result = {call};
+                let ({fields}) = captured;
+                self.calls.push({camel}Call::{variant} {{ {fields}result: {result} }});
+                result
+            ",
+            variant = record.variant,
+        ));
+    }
+
+    /// Emits the loop of a `replay_*` function, which makes each recorded
+    /// call in `calls` with the expression returned by `call` for the
+    /// function's name and arguments.
+    fn print_replay_loop(
+        &mut self,
+        camel: &str,
+        funcs: &[(&str, Option<&RecordedCall>)],
+        call: impl Fn(&str, &str) -> String,
+    ) {
+        self.src
+            .push_str("for (index, call) in calls.iter().enumerate() {\n");
+        // An empty `match` is only exhaustive on the place itself rather than
+        // a reference to it.
+        if funcs.is_empty() {
+            self.src.push_str("match *call {\n");
+        } else {
+            self.src.push_str("match call {\n");
+        }
+        for (func, record) in funcs {
+            let record = match record {
+                Some(record) => record,
+                None => continue,
+            };
+            let fields = record
+                .fields
+                .iter()
+                .map(|(name, _)| format!("{name}, "))
+                .collect::<String>();
+            self.src.push_str(&format!(
+                "{camel}Call::{variant} {{ {fields}result }} => {{
+                    let actual = {call};
+                    wai_bindgen_wasmer::ReplayMismatch::check(index, \"{func}\", result, &actual)?;
+                }}\n",
+                variant = record.variant,
+                call = call(func, &record.replay_args.join(", ")),
+            ));
+        }
+        if funcs.iter().any(|(_, record)| record.is_none()) {
+            self.src
+                .push_str(&format!("{camel}Call::Unrecorded {{ .. }} => {{}}\n"));
+        }
+        self.src.push_str("}\n}\nOk(())\n");
+    }
+
+    /// Emits the `{camel}Call` type logging the calls of `funcs`, with an
+    /// `Unrecorded` case for those which can't be captured, along with its
+    /// `LogValue` implementation and the functions writing and reading whole
+    /// logs.
+    ///
+    /// Each call is logged as the name of its function followed by its
+    /// fields, and unrecorded calls as the name alone.
+    fn print_call_enum(&mut self, camel: &str, funcs: &[(&str, Option<&RecordedCall>)]) {
+        self.src.push_str(&format!(
+            "
+                /// A single call made through `{camel}`, as captured by
+                /// `{camel}Recorder`.
+                #[derive(Debug, Clone)]
+                pub enum {camel}Call {{
+            "
+        ));
+        let mut encode = String::new();
+        let mut decode = String::new();
+        for (func, record) in funcs {
+            let record = match record {
+                Some(record) => record,
+                None => {
+                    decode.push_str(&format!(
+                        "\"{func}\" => Ok(Self::Unrecorded {{ function: \"{func}\" }}),\n"
+                    ));
+                    continue;
+                }
+            };
+            self.src.push_str(&format!("{} {{\n", record.variant));
+            for (name, ty) in record.fields.iter() {
+                self.src.push_str(&format!("{name}: {ty},\n"));
+            }
+            self.src
+                .push_str(&format!("result: {},\n", record.result_ty));
+            self.src.push_str("},\n");
+
+            // Fields are bound to their own names so that parameters named
+            // like the arguments of `encode` don't shadow them.
+            let bindings = record
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(i, (name, _))| format!("{name}: f{i}, "))
+                .collect::<String>();
+            encode.push_str(&format!(
+                "Self::{variant} {{ {bindings}result }} => {{
+                    wai_bindgen_wasmer::LogValue::encode(&String::from(\"{func}\"), out);\n",
+                variant = record.variant,
+            ));
+            for i in 0..record.fields.len() {
+                encode.push_str(&format!(
+                    "wai_bindgen_wasmer::LogValue::encode(f{i}, out);\n"
+                ));
+            }
+            encode.push_str("wai_bindgen_wasmer::LogValue::encode(result, out);\n}\n");
+
+            decode.push_str(&format!("\"{}\" => Ok(Self::{} {{\n", func, record.variant));
+            for (name, _) in record.fields.iter() {
+                decode.push_str(&format!(
+                    "{name}: wai_bindgen_wasmer::LogValue::decode(input)?,\n"
+                ));
+            }
+            decode.push_str("result: wai_bindgen_wasmer::LogValue::decode(input)?,\n}),\n");
+        }
+        if funcs.iter().any(|(_, record)| record.is_none()) {
+            self.src.push_str(
+                "
+                    /// A call to a function whose arguments or result can't
+                    /// be captured, such as one taking a handle.
+                    Unrecorded { function: &'static str },
+                ",
+            );
+            encode.push_str(
+                "Self::Unrecorded { function } => {
+                    wai_bindgen_wasmer::LogValue::encode(&String::from(*function), out);
+                }\n",
+            );
+        }
+        self.src.push_str("}\n\n");
+
+        // As in `replay_*`, an empty `match` has to be on the place itself.
+        let scrutinee = if funcs.is_empty() { "*self" } else { "self" };
+        self.src.push_str(&format!(
+            "
+                #[allow(unused_variables)]
+                impl wai_bindgen_wasmer::LogValue for {camel}Call {{
+                    fn encode(&self, out: &mut Vec<u8>) {{
+                        match {scrutinee} {{
+                            {encode}
+                        }}
+                    }}
+
+                    fn decode(input: &mut &[u8]) -> Result<Self, wai_bindgen_wasmer::LogError> {{
+                        let function = <String as wai_bindgen_wasmer::LogValue>::decode(input)?;
+                        match function.as_str() {{
+                            {decode}
+                            _ => Err(wai_bindgen_wasmer::LogError::UnknownFunction(function)),
+                        }}
+                    }}
+                }}
+
+                impl {camel}Call {{
+                    /// Encodes `calls` into a log which can be saved and
+                    /// read back with `decode_log`.
+                    pub fn encode_log(calls: &[Self]) -> Vec<u8> {{
+                        wai_bindgen_wasmer::encode_log(INTERFACE_HASH, calls)
+                    }}
+
+                    /// Decodes a log written by `encode_log` through this
+                    /// version of the interface.
+                    pub fn decode_log(bytes: &[u8]) -> Result<Vec<Self>, wai_bindgen_wasmer::LogError> {{
+                        wai_bindgen_wasmer::decode_log(INTERFACE_HASH, bytes)
+                    }}
+                }}
+            "
+        ));
+    }
+
+    /// Emits the `LogValue` implementation of the named type `id` when it
+    /// can be captured by a recorder, encoding it as documented in
+    /// `wai_bindgen_wasmer::replay`.
+    fn print_log_value(&mut self, iface: &Interface, id: TypeId) {
+        let info = self.types.get(id);
+        if !self.opts.record || info.has_handle {
+            return;
+        }
+        let ty = &iface.types[id];
+        let wai_name = ty.name.as_deref().unwrap();
+        let name = match &ty.kind {
+            TypeDefKind::Flags(_) | TypeDefKind::Enum(_) => wai_name.to_camel_case(),
+            // Recorders only hold the owned form of the type, which isn't
+            // generated if it's only ever borrowed.
+            _ => match self
+                .modes_of(iface, id)
+                .into_iter()
+                .find(|(_, mode)| self.lifetime_for(&info, *mode).is_none())
+            {
+                Some((name, _)) => name,
+                None => return,
+            },
+        };
+        let (encode, decode) = match &ty.kind {
+            TypeDefKind::Record(record) => {
+                let mut encode = String::new();
+                let mut decode = String::from("Ok(Self {\n");
+                for field in record.fields.iter() {
+                    let field = to_rust_ident(&field.name);
+                    encode.push_str(&format!(
+                        "wai_bindgen_wasmer::LogValue::encode(&self.{field}, out);\n"
+                    ));
+                    decode.push_str(&format!(
+                        "{field}: wai_bindgen_wasmer::LogValue::decode(input)?,\n"
+                    ));
+                }
+                decode.push_str("})");
+                (encode, decode)
+            }
+            TypeDefKind::Variant(variant) => log_value_cases(
+                wai_name,
+                variant
+                    .cases
+                    .iter()
+                    .map(|c| (c.name.to_camel_case(), &c.ty)),
+            ),
+            TypeDefKind::Union(union) => log_value_cases(
+                wai_name,
+                zip(self.union_case_names(iface, union), &union.cases)
+                    .map(|(name, case)| (name, &case.ty)),
+            ),
+            TypeDefKind::Enum(enum_) => log_value_cases(
+                wai_name,
+                enum_
+                    .cases
+                    .iter()
+                    .map(|c| (c.name.to_camel_case(), &Type::Unit)),
+            ),
+            TypeDefKind::Flags(flags) => (
+                "wai_bindgen_wasmer::LogValue::encode(&self.bits(), out);\n".to_string(),
+                format!(
+                    "let bits = <{} as wai_bindgen_wasmer::LogValue>::decode(input)?;
+                    Self::from_bits(bits).ok_or(wai_bindgen_wasmer::LogError::Invalid(\"{wai_name}\"))",
+                    RustFlagsRepr::new(flags),
+                ),
+            ),
+            // Everything else is an alias of a type which is already a
+            // `LogValue`.
+            _ => return,
+        };
+        self.src.push_str(&format!(
+            "
+                impl wai_bindgen_wasmer::LogValue for {name} {{
+                    fn encode(&self, out: &mut Vec<u8>) {{
+                        {encode}
+                    }}
+
+                    fn decode(input: &mut &[u8]) -> Result<Self, wai_bindgen_wasmer::LogError> {{
+                        {decode}
+                    }}
+                }}
+            "
+        ));
+    }
+}
+
+/// Returns the bodies of `LogValue::encode` and `LogValue::decode` for an
+/// enum of the Rust `cases` of the type `wai_name`, logged as the index of
+/// the case followed by its payload.
+fn log_value_cases<'a>(
+    wai_name: &str,
+    cases: impl Iterator<Item = (String, &'a Type)>,
+) -> (String, String) {
+    let mut encode = String::from("match self {\n");
+    let mut decode =
+        String::from("match <u32 as wai_bindgen_wasmer::LogValue>::decode(input)? {\n");
+    for (i, (case, ty)) in cases.enumerate() {
+        if *ty == Type::Unit {
+            encode.push_str(&format!(
+                "Self::{case} => wai_bindgen_wasmer::LogValue::encode(&{i}u32, out),\n"
+            ));
+            decode.push_str(&format!("{i} => Ok(Self::{case}),\n"));
+        } else {
+            encode.push_str(&format!(
+                "Self::{case}(e) => {{
+                    wai_bindgen_wasmer::LogValue::encode(&{i}u32, out);
+                    wai_bindgen_wasmer::LogValue::encode(e, out);
+                }}\n"
+            ));
+            decode.push_str(&format!(
+                "{i} => Ok(Self::{case}(wai_bindgen_wasmer::LogValue::decode(input)?)),\n"
+            ));
+        }
+    }
+    encode.push_str("}\n");
+    decode.push_str(&format!(
+        "_ => Err(wai_bindgen_wasmer::LogError::Invalid(\"{wai_name}\")),\n}}"
+    ));
+    (encode, decode)
 }
 
 impl RustGenerator for Wasmer {
     fn default_param_mode(&self) -> TypeMode {
-        if self.in_import {
+        if self.in_import && self.opts.owned_params {
+            // Everything is copied out of guest memory except for handles,
+            // which always live in the host's tables.
+            TypeMode::HandlesBorrowed("'a")
+        } else if self.in_import {
             // The default here is that only leaf values can be borrowed because
             // otherwise lists and such need to be copied into our own memory.
             TypeMode::LeafBorrowed("'a")
@@ -311,20 +2278,91 @@ impl RustGenerator for Wasmer {
         }
         self.push_str(" str");
     }
+
+    fn print_lifted_list(&mut self, iface: &Interface, ty: &Type, mode: TypeMode) {
+        match mode {
+            TypeMode::LeafBorrowed(lt) if self.in_import && self.opts.arena => {
+                self.push_str("&");
+                if lt != "'_" {
+                    self.push_str(lt);
+                    self.push_str(" ");
+                }
+                self.push_str("[");
+                self.print_ty(iface, ty, mode);
+                self.push_str("]");
+            }
+            _ => {
+                self.push_str("Vec<");
+                self.print_ty(iface, ty, mode);
+                self.push_str(">");
+            }
+        }
+    }
+
+    fn print_param_ty(
+        &mut self,
+        iface: &Interface,
+        func: &Function,
+        name: &str,
+        ty: &Type,
+        mode: TypeMode,
+    ) {
+        if self.in_trait && self.interned(&func.name, name, ty) {
+            self.push_str("std::sync::Arc<str>");
+        } else {
+            self.print_ty(iface, ty, mode);
+        }
+    }
 }
 
 impl Generator for Wasmer {
+    fn preprocess_all(&mut self, imports: &[Interface], exports: &[Interface]) {
+        self.import_names = imports.iter().map(|i| i.name.clone()).collect();
+        self.export_names = exports.iter().map(|i| i.name.clone()).collect();
+        for iface in exports {
+            for (_, resource) in iface.resources.iter() {
+                match &resource.foreign_module {
+                    Some(module) if self.export_names.contains(module) => {
+                        self.shared_tables.insert(module.clone());
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
     fn preprocess_one(&mut self, iface: &Interface, dir: Direction) {
         let variant = Self::abi_variant(dir);
         self.types.analyze(iface);
         self.in_import = variant == AbiVariant::GuestImport;
-        self.trait_name = iface.name.to_camel_case();
+        self.all_needed_handles.clear();
+        self.exported_resources.clear();
+        self.foreign_handles.clear();
+        self.needs_capabilities = false;
+        self.needs_interner = false;
+        self.needs_arena = false;
+        self.trait_name = self.trait_name(&iface.name);
+        self.src.push_str(&self.cfg_attr(&iface.name));
         self.src.push_str(&format!(
             "#[allow(clippy::all)]\npub mod {} {{\n",
-            iface.name.to_snake_case()
+            self.module_name(&iface.name)
         ));
         self.src
             .push_str("#[allow(unused_imports)]\nuse wai_bindgen_wasmer::{anyhow, wasmer};\n");
+        self.src.push_str(&format!(
+            "const _: () = assert!(
+                wai_bindgen_wasmer::ABI_VERSION == {ABI_VERSION},
+                \"these bindings were generated by wai-bindgen {} for revision \
+                 {ABI_VERSION} of the canonical ABI, which doesn't match the \
+                 version of `wai-bindgen-wasmer` in use\",
+            );
+            /// The hash of the interface these bindings were generated from,
+            /// which is checked against the one a module embeds when it's
+            /// instantiated.
+            pub const INTERFACE_HASH: u64 = {:#018x};\n",
+            env!("CARGO_PKG_VERSION"),
+            interface_hash(iface),
+        ));
         self.sizes.fill(iface);
     }
 
@@ -337,12 +2375,16 @@ impl Generator for Wasmer {
         docs: &Docs,
     ) {
         self.print_typedef_record(iface, id, record, docs);
+        self.print_log_value(iface, id);
 
         // If this record might be used as a slice type in various places then
         // we synthesize an `Endian` implementation for it so `&[Le<ThisType>]`
         // is usable.
         if !self.modes_of(iface, id).is_empty()
-            && record.fields.iter().all(|f| iface.all_bits_valid(&f.ty))
+            && record
+                .fields
+                .iter()
+                .all(|f| is_list_canonical(iface, &f.ty))
         {
             self.src.push_str("impl wai_bindgen_wasmer::Endian for ");
             self.src.push_str(&name.to_camel_case());
@@ -373,7 +2415,7 @@ impl Generator for Wasmer {
             self.src.push_str("}\n");
 
             // Also add an `AllBytesValid` valid impl since this structure's
-            // byte representations are valid (guarded by the `all_bits_valid`
+            // byte representations are valid (guarded by the `is_list_canonical`
             // predicate).
             self.src
                 .push_str("unsafe impl wai_bindgen_wasmer::AllBytesValid for ");
@@ -395,8 +2437,8 @@ impl Generator for Wasmer {
 
     fn type_flags(
         &mut self,
-        _iface: &Interface,
-        _id: TypeId,
+        iface: &Interface,
+        id: TypeId,
         name: &str,
         flags: &Flags,
         docs: &Docs,
@@ -436,6 +2478,8 @@ impl Generator for Wasmer {
 
         self.src.push_str("}\n");
         self.src.push_str("}\n\n");
+
+        self.print_log_value(iface, id);
     }
 
     fn type_variant(
@@ -447,10 +2491,12 @@ impl Generator for Wasmer {
         docs: &Docs,
     ) {
         self.print_typedef_variant(iface, id, variant, docs);
+        self.print_log_value(iface, id);
     }
 
-    fn type_enum(&mut self, _iface: &Interface, id: TypeId, name: &str, enum_: &Enum, docs: &Docs) {
+    fn type_enum(&mut self, iface: &Interface, id: TypeId, name: &str, enum_: &Enum, docs: &Docs) {
         self.print_typedef_enum(id, name, enum_, docs);
+        self.print_log_value(iface, id);
     }
 
     fn type_union(
@@ -462,6 +2508,7 @@ impl Generator for Wasmer {
         docs: &Docs,
     ) {
         self.print_typedef_union(iface, id, union, docs);
+        self.print_log_value(iface, id);
     }
 
     fn type_option(
@@ -488,6 +2535,19 @@ impl Generator for Wasmer {
 
     fn type_resource(&mut self, iface: &Interface, ty: ResourceId) {
         let name = &iface.resources[ty].name;
+        if let Some(owner) = self.resource_owner(&iface.resources[ty]) {
+            if !self.in_import {
+                unimplemented!(
+                    "resource `{}` is shared with `{}`, which is only supported for \
+                     resources defined by the host",
+                    name,
+                    owner,
+                );
+            }
+            self.foreign_handles
+                .insert(name.to_string(), owner.to_string());
+            return;
+        }
         self.all_needed_handles.insert(name.to_string());
 
         // If we're binding imports then all handles are associated types so
@@ -531,7 +2591,17 @@ impl Generator for Wasmer {
     // this `Generator` implementation.
     fn export(&mut self, iface: &Interface, func: &Function) {
         assert!(!func.is_async, "async not supported yet");
+        let is_async = self.opts.async_.includes(&func.name);
+        self.push_function_desc(iface, func, is_async);
         let prev = mem::take(&mut self.src);
+        assert!(
+            !(self.opts.arena && self.opts.owned_params),
+            "`arena` is not supported together with `owned_params`"
+        );
+        self.needs_interner |= func
+            .params
+            .iter()
+            .any(|(name, ty)| self.interned(&func.name, name, ty));
 
         // Generate the closure that's passed to a `Linker`, the final piece of
         // codegen here.
@@ -550,6 +2620,7 @@ impl Generator for Wasmer {
             src,
             cleanup,
             needs_borrow_checker,
+            needs_arena,
             needs_memory,
             needs_buffer_transaction,
             needs_functions,
@@ -561,13 +2632,18 @@ impl Generator for Wasmer {
         assert!(!needs_buffer_transaction);
 
         // Generate the signature this function will have in the final trait
-        let self_arg = "&mut self".to_string();
+        let self_arg = self.trait_self_arg().to_string();
         self.in_trait = true;
 
         let mut fnsig = FnSig::default();
         fnsig.private = true;
         fnsig.self_arg = Some(self_arg);
-        self.print_docs_and_params(iface, func, TypeMode::LeafBorrowed("'_"), &fnsig);
+        let mode = if self.opts.owned_params {
+            TypeMode::HandlesBorrowed("'_")
+        } else {
+            TypeMode::LeafBorrowed("'_")
+        };
+        let params = self.print_docs_and_params(iface, func, mode, &fnsig);
         // The Rust return type may differ from the wasm return type based on
         // the `custom_error` configuration of this code generator.
         match self.classify_fn_ret(iface, func) {
@@ -588,6 +2664,11 @@ impl Generator for Wasmer {
         }
         self.in_trait = false;
         let trait_signature = mem::take(&mut self.src).into();
+        let record = if self.opts.record {
+            self.recorded_call(iface, func)
+        } else {
+            None
+        };
 
         // Generate the closure that's passed to a `Linker`, the final piece of
         // codegen here.
@@ -634,36 +2715,38 @@ impl Generator for Wasmer {
             self.src.push_str(&format!(
                 "
                     let span = wai_bindgen_wasmer::tracing::span!(
-                        wai_bindgen_wasmer::tracing::Level::TRACE,
+                        wai_bindgen_wasmer::tracing::Level::{},
                         \"wai-bindgen abi\",
                         module = \"{}\",
                         function = \"{}\",
                     );
                     let _enter = span.enter();
                 ",
-                iface.name, func.name,
+                self.opts.tracing_level.as_str(),
+                iface.name,
+                func.name,
             ));
         }
+        self.print_metrics_guard(iface, func);
+        self.print_capability_check(func);
         self.src.push_str(&closures);
 
+        // The instance's memory and functions are borrowed through our own
+        // handle on `lazy` so they don't hold a borrow of `store`.
+        let needs_memory = needs_memory || needs_borrow_checker;
+        if needs_memory || !needs_functions.is_empty() {
+            self.src
+                .push_str("let lazy = store.data().lazy.borrow().clone().unwrap();\n");
+        }
         for name in needs_functions.keys() {
-            self.src.push_str(&format!(
-                "let func_{name} = store
-                    .data()
-                    .lazy
-                    .get()
-                    .unwrap()
-                    .func_{name}
-                    .clone();\n"
-            ));
+            self.src
+                .push_str(&format!("let func_{name} = &lazy.func_{name};\n"));
         }
         self.needs_functions.extend(needs_functions);
-        self.needs_memory |= needs_memory || needs_borrow_checker;
+        self.needs_memory |= needs_memory;
 
-        if self.needs_memory {
-            self.src.push_str(
-                "let _memory: wasmer::Memory = store.data().lazy.get().unwrap().memory.clone();\n",
-            );
+        if needs_memory {
+            self.src.push_str("let _memory = &lazy.memory;\n");
         }
 
         if needs_borrow_checker {
@@ -677,12 +2760,29 @@ impl Generator for Wasmer {
             );
         }
 
+        // The arena is scoped through its own handle since the lifted
+        // parameters outlive `data_mut`, across the calls into wasm that
+        // lower the results.
+        if needs_arena {
+            self.src.push_str(
+                "let arena = store.data().arena.clone();
+                let _arena = arena.scope();\n",
+            );
+        }
+        self.needs_arena |= needs_arena;
+
         self.src.push_str("let data_mut = store.data_mut();\n");
 
         if !self.all_needed_handles.is_empty() {
             self.src
                 .push_str("let tables = data_mut.tables.borrow_mut();\n");
         }
+        for owner in self.foreign_tables() {
+            self.src.push_str(&format!(
+                "let {owner}_tables = data_mut.{owner}_tables.borrow_mut();\n",
+                owner = owner.to_snake_case(),
+            ));
+        }
 
         self.src.push_str(&String::from(src));
 
@@ -700,6 +2800,8 @@ impl Generator for Wasmer {
                 name: func.name.to_string(),
                 closure,
                 trait_signature,
+                params,
+                record,
             });
     }
 
@@ -714,6 +2816,7 @@ impl Generator for Wasmer {
         // asynchronous, we can't intermix async and sync calls because
         // it's unknown whether the wasm module will make an async host call.
         let is_async = !self.opts.async_.is_none();
+        self.push_function_desc(iface, func, is_async);
         let mut sig = FnSig::default();
         sig.async_ = is_async;
 
@@ -725,12 +2828,14 @@ impl Generator for Wasmer {
         self.push_str("-> Result<");
         self.print_ty(iface, &func.result, TypeMode::Owned);
         self.push_str(", wasmer::RuntimeError> {\n");
+        self.print_metrics_guard(iface, func);
 
         let params = func
             .params
             .iter()
             .map(|(name, _)| to_rust_ident(name))
-            .collect();
+            .collect::<Vec<_>>();
+        self.print_range_checks(func, &params);
         let mut f = FunctionBindgen::new(self, params);
         iface.call(
             AbiVariant::GuestExport,
@@ -756,7 +2861,7 @@ impl Generator for Wasmer {
         for (name, func) in needs_functions {
             self.src
                 .push_str(&format!("let func_{name} = &self.func_{name};\n"));
-            let get = format!("_instance.exports.get_typed_function(&store, \"{name}\")?",);
+            let get = format!("wai_bindgen_wasmer::typed_export(&store, _instance, \"{name}\")?");
             exports
                 .fields
                 .insert(format!("func_{name}"), (func.ty(), get));
@@ -771,7 +2876,7 @@ impl Generator for Wasmer {
                 "memory".to_string(),
                 (
                     "wasmer::Memory".to_string(),
-                    "_instance.exports.get_memory(\"memory\")?.clone()".to_string(),
+                    "wai_bindgen_wasmer::memory_export(_instance, \"memory\")?".to_string(),
                 ),
             );
         }
@@ -813,28 +2918,66 @@ impl Generator for Wasmer {
             }
             cvt.push(')');
         }
+        let export_name = match NamespaceMap::lookup(&self.opts.map, iface) {
+            Some(namespace) => format!("{}#{}", namespace, func.name),
+            None => format!("{}{}", self.opts.symbol_namespace, func.name),
+        };
+        let field = format!("func_{}", to_rust_ident(&func.name));
         exports.fields.insert(
-            format!("func_{}", to_rust_ident(&func.name)),
+            field.clone(),
             (
                 format!("wasmer::TypedFunction<{cvt}>"),
                 format!(
-                    "_instance.exports.get_typed_function(&store, \"{}\")?",
-                    func.name,
+                    "wai_bindgen_wasmer::typed_export(&store, _instance, \"{}\")",
+                    export_name,
                 ),
             ),
         );
+        exports.optional.insert(field);
+
+        self.print_resource_method(iface, func);
+        if self.opts.deadlines {
+            self.print_with_deadline(iface, func);
+        }
+        if self.opts.memory_usage {
+            self.print_memory_usage(iface);
+        }
+        if self.opts.guest_panics {
+            self.print_guest_panic(iface);
+        }
+        if self.opts.record {
+            self.push_recorded_export(iface, func);
+        }
     }
 
     fn finish_one(&mut self, iface: &Interface, files: &mut Files) {
+        // Interfaces without any functions get neither a trait nor a struct,
+        // so there's nothing for the `Plugin` to implement or wrap.
+        if self.in_import {
+            if self.guest_imports.contains_key(&iface.name) {
+                self.plugin_tables
+                    .insert(iface.name.clone(), self.foreign_tables());
+            }
+        } else if self.guest_exports.contains_key(&iface.name) {
+            self.plugin_guests.insert(iface.name.clone());
+        }
         for (module, funcs) in sorted_iter(&self.guest_imports) {
-            let module_camel = module.to_camel_case();
+            let module_camel = self.trait_name(module);
             let is_async = !self.opts.async_.is_none();
             if is_async {
                 self.src.push_str("#[wai_bindgen_wasmer::async_trait]\n");
             }
             self.src.push_str("pub trait ");
             self.src.push_str(&module_camel);
-            self.src.push_str(": Sized + Send + Sync + 'static");
+            self.src.push_str(": ");
+            for owner in self.foreign_tables() {
+                self.src.push_str(&format!(
+                    "super::{}::{} + ",
+                    self.module_name(&owner),
+                    self.trait_name(&owner)
+                ));
+            }
+            self.src.push_str("Sized + Send + Sync + 'static");
             self.src.push_str("{\n");
             if !self.all_needed_handles.is_empty() {
                 for handle in self.all_needed_handles.iter() {
@@ -850,28 +2993,38 @@ impl Generator for Wasmer {
             if self.opts.custom_error {
                 self.src.push_str("type Error;\n");
                 if self.needs_custom_error_to_trap {
-                    self.src.push_str(
-                        "fn error_to_trap(&mut self, err: Self::Error) -> wasmer::RuntimeError;\n",
-                    );
+                    self.src.push_str(&format!(
+                        "fn error_to_trap({}, err: Self::Error) -> wasmer::RuntimeError;\n",
+                        self.trait_self_arg(),
+                    ));
                 }
                 for ty in self.needs_custom_error_to_types.iter() {
                     self.src.push_str(&format!(
-                        "fn error_to_{}(&mut self, err: Self::Error) -> Result<{}, wasmer::RuntimeError>;\n",
+                        "fn error_to_{}({}, err: Self::Error) -> Result<{}, wasmer::RuntimeError>;\n",
                         ty.to_snake_case(),
+                        self.trait_self_arg(),
                         ty.to_camel_case(),
                     ));
                 }
             }
+            if self.needs_capabilities {
+                self.src.push_str(
+                    "/// The capabilities granted to the guest, checked before
+                    /// calling any function annotated with `@requires`.
+                    fn capabilities(&self) -> &wai_bindgen_wasmer::CapabilitySet;\n\n",
+                );
+            }
             for f in funcs {
                 self.src.push_str(&f.trait_signature);
                 self.src.push_str(";\n\n");
             }
             for handle in self.all_needed_handles.iter() {
                 self.src.push_str(&format!(
-                    "fn drop_{}(&mut self, state: Self::{}) {{
+                    "fn drop_{}({}, state: Self::{}) {{
                         drop(state);
                     }}\n",
                     handle.to_snake_case(),
+                    self.trait_self_arg(),
                     handle.to_camel_case(),
                 ));
             }
@@ -913,10 +3066,26 @@ impl Generator for Wasmer {
             }
         }
 
+        if self.opts.record {
+            let guest_imports = mem::take(&mut self.guest_imports);
+            for (module, funcs) in sorted_iter(&guest_imports) {
+                self.print_recorder(module, funcs);
+            }
+            self.guest_imports = guest_imports;
+        }
+
+        if self.opts.stubs {
+            let guest_imports = mem::take(&mut self.guest_imports);
+            for (module, funcs) in sorted_iter(&guest_imports) {
+                self.print_stubs(module, funcs);
+            }
+            self.guest_imports = guest_imports;
+        }
+
         self.needs_lazy_initialized |= self.needs_memory;
         self.needs_lazy_initialized |= !self.needs_functions.is_empty();
         for (module, funcs) in mem::take(&mut self.guest_imports) {
-            let module_camel = module.to_camel_case();
+            let module_camel = self.trait_name(&module);
 
             if self.needs_lazy_initialized {
                 self.push_str("pub struct LazyInitialized {\n");
@@ -933,10 +3102,56 @@ impl Generator for Wasmer {
                 self.push_str("}\n");
             }
 
+            let shares_tables = self.shared_tables.contains(&module);
+            let mut tables_params = String::new();
+            if shares_tables {
+                tables_params.push_str(&format!(
+                    ", tables: std::rc::Rc<core::cell::RefCell<{}Tables<T>>>",
+                    module_camel
+                ));
+                self.push_str(&format!(
+                    "
+                        {ADD_TO_IMPORTS_DOC}
+                        #[must_use = \"The returned initializer function must be called
+                        with the instance and the store before starting the runtime\"]
+                        pub fn add_to_imports<T>(store: &mut wasmer::Store, imports: &mut wasmer::Imports, data: {data_ty})
+                        -> impl Fn(&wasmer::Instance, &dyn wasmer::AsStoreRef) -> Result<(), anyhow::Error>
+                        where T: {module_camel}
+                        {{
+                            add_to_imports_with_tables(store, imports, data, Default::default())
+                        }}
+
+                        /// Like [`add_to_imports`], but with handle tables which
+                        /// are shared with the bindings of other interfaces using
+                        /// this interface's resources.
+                    ",
+                    data_ty = self.host_data_ty(),
+                ));
+            }
+            for owner in self.foreign_tables() {
+                tables_params.push_str(&format!(
+                    ", {}_tables: std::rc::Rc<core::cell::RefCell<super::{}::{}Tables<T>>>",
+                    owner.to_snake_case(),
+                    self.module_name(&owner),
+                    self.trait_name(&owner),
+                ));
+            }
+
+            if !shares_tables {
+                self.push_str(ADD_TO_IMPORTS_DOC);
+            }
             self.push_str("\n#[must_use = \"The returned initializer function must be called\n");
             self.push_str("with the instance and the store before starting the runtime\"]\n");
-            self.push_str("pub fn add_to_imports<T>(store: &mut wasmer::Store, imports: &mut wasmer::Imports, data: T)\n");
-            self.push_str("-> impl FnOnce(&wasmer::Instance, &dyn wasmer::AsStoreRef) -> Result<(), anyhow::Error>\n");
+            self.push_str(if shares_tables {
+                "pub fn add_to_imports_with_tables<T>("
+            } else {
+                "pub fn add_to_imports<T>("
+            });
+            self.push_str("store: &mut wasmer::Store, imports: &mut wasmer::Imports, data: ");
+            self.push_str(self.host_data_ty());
+            self.push_str(&tables_params);
+            self.push_str(")\n");
+            self.push_str("-> impl Fn(&wasmer::Instance, &dyn wasmer::AsStoreRef) -> Result<(), anyhow::Error>\n");
             self.push_str("where T: ");
             self.push_str(&module_camel);
             self.push_str("\n{\n");
@@ -945,14 +3160,32 @@ impl Generator for Wasmer {
             self.push_str("struct EnvWrapper<T: ");
             self.push_str(&module_camel);
             self.push_str("> {\n");
-            self.push_str("data: T,\n");
+            self.push_str(&format!("data: {},\n", self.host_data_ty()));
             if !self.all_needed_handles.is_empty() {
                 self.push_str("tables: std::rc::Rc<core::cell::RefCell<");
                 self.push_str(&module_camel);
                 self.push_str("Tables<T>>>,\n");
             }
+            for owner in self.foreign_tables() {
+                self.push_str(&format!(
+                    "{}_tables: std::rc::Rc<core::cell::RefCell<super::{}::{}Tables<T>>>,\n",
+                    owner.to_snake_case(),
+                    self.module_name(&owner),
+                    self.trait_name(&owner),
+                ));
+            }
             if self.needs_lazy_initialized {
-                self.push_str("lazy: std::rc::Rc<OnceCell<LazyInitialized>>,\n");
+                self.push_str(
+                    "lazy: std::rc::Rc<core::cell::RefCell<Option<std::rc::Rc<LazyInitialized>>>>,\n",
+                );
+            }
+            if self.needs_interner {
+                self.push_str(
+                    "interner: std::rc::Rc<core::cell::RefCell<wai_bindgen_wasmer::Interner>>,\n",
+                );
+            }
+            if self.needs_arena {
+                self.push_str("arena: std::rc::Rc<wai_bindgen_wasmer::Arena>,\n");
             }
             self.push_str("}\n");
             self.push_str("unsafe impl<T: ");
@@ -963,17 +3196,28 @@ impl Generator for Wasmer {
             self.push_str("> Sync for EnvWrapper<T> {}\n");
 
             if self.needs_lazy_initialized {
-                self.push_str("let lazy = std::rc::Rc::new(OnceCell::new());\n");
+                self.push_str("let lazy = std::rc::Rc::default();\n");
             }
 
             self.push_str("let env = EnvWrapper {\n");
             self.push_str("data,\n");
-            if !self.all_needed_handles.is_empty() {
+            if shares_tables {
+                self.push_str("tables,\n");
+            } else if !self.all_needed_handles.is_empty() {
                 self.push_str("tables: std::rc::Rc::default(),\n");
             }
+            for owner in self.foreign_tables() {
+                self.push_str(&format!("{}_tables,\n", owner.to_snake_case()));
+            }
             if self.needs_lazy_initialized {
                 self.push_str("lazy: std::rc::Rc::clone(&lazy),\n");
             }
+            if self.needs_interner {
+                self.push_str("interner: std::rc::Rc::default(),\n");
+            }
+            if self.needs_arena {
+                self.push_str("arena: std::rc::Rc::default(),\n");
+            }
             self.push_str("};\n");
             self.push_str("let env = wasmer::FunctionEnv::new(&mut *store, env);\n");
             self.push_str("let mut exports = wasmer::Exports::new();\n");
@@ -994,9 +3238,10 @@ impl Generator for Wasmer {
                     f.name, f.closure,
                 ));
             }
+            let namespace = NamespaceMap::lookup(&self.opts.map, iface).unwrap_or(&module);
             self.push_str(&format!(
                 "imports.register_namespace(\"{}\", exports);\n",
-                module
+                namespace
             ));
 
             if !self.all_needed_handles.is_empty() {
@@ -1017,7 +3262,7 @@ impl Generator for Wasmer {
                                         .map_err(|e| {{
                                             wasmer::RuntimeError::new(format!(\"failed to remove handle: {{}}\", e))
                                         }})?;
-                                    let host = &mut data_mut.data;
+                                    let host = {host};
                                     host.drop_{snake}(handle);
                                     Ok(())
                                 }}
@@ -1025,6 +3270,7 @@ impl Generator for Wasmer {
                         );\n",
                         name = handle,
                         snake = handle.to_snake_case(),
+                        host = self.host_ref(),
                     ));
                 }
                 self.push_str("imports.register_namespace(\"canonical_abi\", canonical_abi);\n");
@@ -1033,35 +3279,33 @@ impl Generator for Wasmer {
             self.push_str(
                 "move |_instance: &wasmer::Instance, _store: &dyn wasmer::AsStoreRef| {\n",
             );
+            self.push_str(&format!(
+                "wai_bindgen_wasmer::check_interface_hash(_instance.module(), \"{}\", INTERFACE_HASH)?;\n",
+                iface.name,
+            ));
             if self.needs_lazy_initialized {
                 if self.needs_memory {
                     self.push_str(
-                        "let memory = _instance.exports.get_memory(\"memory\")?.clone();\n",
+                        "let memory = wai_bindgen_wasmer::memory_export(_instance, \"memory\")?;\n",
                     );
                 }
                 for name in self.needs_functions.keys() {
                     self.src.push_str(&format!(
-                        "let func_{name} = _instance
-                        .exports
-                        .get_typed_function(
-                            &_store.as_store_ref(),
-                            \"{name}\",
-                        )
-                        .unwrap()
-                        .clone();\n"
+                        "let func_{name} = wai_bindgen_wasmer::typed_export(
+                        &_store.as_store_ref(),
+                        _instance,
+                        \"{name}\",
+                    )?;\n"
                     ));
                 }
-                self.push_str("lazy.set(LazyInitialized {\n");
+                self.push_str("*lazy.borrow_mut() = Some(std::rc::Rc::new(LazyInitialized {\n");
                 if self.needs_memory {
                     self.push_str("memory,\n");
                 }
                 for name in self.needs_functions.keys() {
                     self.src.push_str(&format!("func_{name},\n"));
                 }
-                self.push_str("})\n");
-                self.push_str(
-                    ".map_err(|_e| anyhow::anyhow!(\"Couldn't set lazy initialized data\"))?;\n",
-                );
+                self.push_str("}));\n");
             }
             self.push_str("Ok(())\n");
             self.push_str("}\n");
@@ -1070,7 +3314,7 @@ impl Generator for Wasmer {
         }
 
         for (module, exports) in sorted_iter(&mem::take(&mut self.guest_exports)) {
-            let name = module.to_camel_case();
+            let name = self.struct_name(module);
 
             // Generate a struct that is the "state" of this exported module
             // which is held internally.
@@ -1103,7 +3347,11 @@ impl Generator for Wasmer {
             for (name, (ty, _)) in exports.fields.iter() {
                 self.push_str(name);
                 self.push_str(": ");
-                self.push_str(ty);
+                if exports.optional.contains(name) {
+                    self.push_str(&format!("Result<{ty}, wai_bindgen_wasmer::ExportMismatch>"));
+                } else {
+                    self.push_str(ty);
+                }
                 self.push_str(",\n");
             }
             self.push_str("}\n");
@@ -1119,7 +3367,7 @@ impl Generator for Wasmer {
                     ///
                     /// This function returns the `{0}Data` which needs to be
                     /// passed through to `{0}::new`.
-                    fn add_to_imports(
+                    pub fn add_to_imports(
                         mut store: impl wasmer::AsStoreMut,
                         imports: &mut wasmer::Imports,
                     ) -> wasmer::FunctionEnv<{0}Data> {{
@@ -1217,6 +3465,10 @@ impl Generator for Wasmer {
                     /// instantiate the `module` otherwise using `imports`, and
                     /// both an instance of this structure and the underlying
                     /// `wasmer::Instance` will be returned.
+                    ///
+                    /// Modules exporting an `_initialize` function have to be
+                    /// set up with `wai_bindgen_wasmer::initialize` once the
+                    /// initializers of their other imports have run.
                     pub fn instantiate(
                         mut store: impl wasmer::AsStoreMut,
                         module: &wasmer::Module,
@@ -1227,44 +3479,47 @@ impl Generator for Wasmer {
                             &mut store, module, &*imports)?;
                         ",
             );
-            if !self.exported_resources.is_empty() {
-                self.push_str("{\n");
-                for r in self.exported_resources.iter() {
-                    self.src.push_str(&format!(
-                        "let dtor{idx} = instance
-                                .exports
-                                .get_typed_function(
-                                    &store,
-                                    \"canonical_abi_drop_{name}\",
-                                )?
-                                .clone();
-                                ",
-                        name = iface.resources[*r].name,
-                        idx = r.index(),
-                    ));
-                }
-                self.push_str("\n");
-
-                for r in self.exported_resources.iter() {
-                    self.src.push_str(&format!(
-                            "env
-                                .as_mut(&mut store)
-                                .dtor{idx}
-                                .set(dtor{idx})
-                                .map_err(|_e| anyhow::anyhow!(\"Couldn't set canonical_abi_drop_{name}\"))?;
-                                ",
-                                name = iface.resources[*r].name,
-                                idx = r.index(),
-                                ));
-                }
-                self.push_str("}\n");
-            }
+            self.push_str(&format!(
+                "wai_bindgen_wasmer::check_abi_version(&mut store, &instance, \"{}\")?;\n",
+                self.opts.symbol_namespace,
+            ));
+            self.push_str(&format!(
+                "wai_bindgen_wasmer::check_interface_hash(module, \"{}\", INTERFACE_HASH)?;\n",
+                iface.name,
+            ));
             self.push_str(
                 "
                         Ok((Self::new(store, &instance, env)?, instance))
                     }
                 ",
             );
+            if self.opts.wasi {
+                self.push_str(
+                    "
+                        /// Like [`Self::instantiate`], but also providing the
+                        /// WASI imports of the environment built by `wasi`,
+                        /// which are added to `imports`.
+                        ///
+                        /// The returned WASI environment is already connected
+                        /// to the instance's memory.
+                        pub fn instantiate_wasi(
+                            mut store: impl wasmer::AsStoreMut,
+                            module: &wasmer::Module,
+                            imports: &mut wasmer::Imports,
+                            wasi: &mut wai_bindgen_wasmer::wasmer_wasi::WasiStateBuilder,
+                        ) -> anyhow::Result<(Self, wasmer::Instance, wai_bindgen_wasmer::wasmer_wasi::WasiFunctionEnv)> {
+                            let wasi_env = wasi.finalize(&mut store)?;
+                            let mut import_object = wasi_env.import_object(&mut store, module)?;
+                            import_object.extend(&*imports);
+                            *imports = import_object;
+                            let (exports, instance) = Self::instantiate(&mut store, module, imports)?;
+                            let memory = wai_bindgen_wasmer::memory_export(&instance, \"memory\")?;
+                            wasi_env.data_mut(&mut store).set_memory(memory);
+                            Ok((exports, instance, wasi_env))
+                        }
+                    ",
+                );
+            }
 
             self.push_str(&format!(
                 "
@@ -1274,15 +3529,58 @@ impl Generator for Wasmer {
                     ///
                     /// This function will extract exports from the `instance`
                     /// and wrap them all up in the returned structure which can
-                    /// be used to interact with the wasm module.
+                    /// be used to interact with the wasm module, failing with
+                    /// an error naming any export that's missing or has the
+                    /// wrong type.
                     pub fn new(
                         store: impl wasmer::AsStoreMut,
+                        instance: &wasmer::Instance,
+                        env: wasmer::FunctionEnv<{name}Data>,
+                    ) -> Result<Self, wai_bindgen_wasmer::ExportMismatch> {{
+                        let exports = Self::new_partial(store, instance, env)?;
+                        if let Some(err) = exports.missing().first() {{
+                            return Err((*err).clone());
+                        }}
+                        Ok(exports)
+                    }}
+
+                    /// Like [`Self::new`], but only failing if the `instance`
+                    /// lacks the memory or the canonical ABI functions the
+                    /// bindings need.
+                    ///
+                    /// Calls to functions of the interface which the instance
+                    /// doesn't export, or exports with the wrong type, fail
+                    /// with the [`wai_bindgen_wasmer::ExportMismatch`] found
+                    /// looking them up, and [`Self::missing`] lists them, so
+                    /// guests can be tried out before they implement the
+                    /// whole interface.
+                    pub fn new_partial(
+                        {mut_}store: impl wasmer::AsStoreMut,
                         _instance: &wasmer::Instance,
-                        env: wasmer::FunctionEnv<{}Data>,
-                    ) -> Result<Self, wasmer::ExportError> {{
+                        env: wasmer::FunctionEnv<{name}Data>,
+                    ) -> Result<Self, wai_bindgen_wasmer::ExportMismatch> {{
                 ",
-                name,
+                mut_ = if self.exported_resources.is_empty() {
+                    ""
+                } else {
+                    "mut "
+                },
             ));
+            for r in self.exported_resources.iter() {
+                // The destructor is only missing if `env` is being reused for
+                // another instance, in which case the first one is kept.
+                self.src.push_str(&format!(
+                    "let dtor{idx} = wai_bindgen_wasmer::typed_export(
+                        &store,
+                        _instance,
+                        \"canonical_abi_drop_{name}\",
+                    )?;
+                    let _ = env.as_mut(&mut store).dtor{idx}.set(dtor{idx});
+                    ",
+                    name = iface.resources[*r].name,
+                    idx = r.index(),
+                ));
+            }
             //assert!(!self.needs_get_func);
             for (name, (_, get)) in exports.fields.iter() {
                 self.push_str("let ");
@@ -1302,6 +3600,71 @@ impl Generator for Wasmer {
             self.push_str("})\n");
             self.push_str("}\n");
 
+            let optional = exports
+                .optional
+                .iter()
+                .map(|name| format!("self.{name}.as_ref().err(),\n"))
+                .collect::<String>();
+            self.push_str(&format!(
+                "
+                    /// Returns why each function of the interface which the
+                    /// instance doesn't export, or exports with the wrong
+                    /// type, can't be called, which is only ever the case for
+                    /// bindings created with [`Self::new_partial`].
+                    pub fn missing(&self) -> Vec<&wai_bindgen_wasmer::ExportMismatch> {{
+                        let funcs: [Option<&wai_bindgen_wasmer::ExportMismatch>; {}] = [
+                            {optional}
+                        ];
+                        funcs.into_iter().flatten().collect()
+                    }}
+                ",
+                exports.optional.len(),
+            ));
+
+            self.push_str(
+                "
+                    /// Switches these bindings over to `instance`, a new
+                    /// instance of a module with the same exports, created
+                    /// from the imports these bindings were added to.
+                    ///
+                    /// Handles to resources of the previous instance can't be
+                    /// used with the new one, and calls passing them fail with
+                    /// [`wai_bindgen_wasmer::StaleHandle`]. If an error is
+                    /// returned the bindings still use the previous instance.
+                    pub fn reload(
+                        &mut self,
+                        mut store: impl wasmer::AsStoreMut,
+                        instance: &wasmer::Instance,
+                    ) -> Result<(), wai_bindgen_wasmer::ExportMismatch> {
+                ",
+            );
+            for r in self.exported_resources.iter() {
+                self.src.push_str(&format!(
+                    "let dtor{idx} = wai_bindgen_wasmer::typed_export(
+                        &store,
+                        instance,
+                        \"canonical_abi_drop_{name}\",
+                    )?;
+                    ",
+                    name = iface.resources[*r].name,
+                    idx = r.index(),
+                ));
+            }
+            self.push_str("let exports = Self::new(&mut store, instance, self.env.clone())?;\n");
+            if !self.exported_resources.is_empty() {
+                self.push_str("let state = self.env.as_mut(&mut store);\n");
+            }
+            for r in self.exported_resources.iter() {
+                self.src.push_str(&format!(
+                    "state.index_slab{idx} = Default::default();
+                    state.resource_slab{idx}.invalidate();
+                    state.dtor{idx} = OnceCell::from(dtor{idx});
+                    ",
+                    idx = r.index(),
+                ));
+            }
+            self.push_str("*self = exports;\nOk(())\n}\n");
+
             for func in exports.funcs.iter() {
                 self.push_str(func);
             }
@@ -1341,38 +3704,99 @@ impl Generator for Wasmer {
             }
 
             self.push_str("}\n");
+
+            for (r, methods) in exports.resource_methods.iter() {
+                self.push_str(&format!(
+                    "impl {} {{\n",
+                    iface.resources[*r].name.to_camel_case()
+                ));
+                for method in methods {
+                    self.push_str(method);
+                }
+                self.push_str("}\n");
+            }
+
+            if self.opts.record {
+                self.print_export_recorder(module, &name, &exports.recorded);
+            }
         }
+        self.print_function_descs();
         self.print_intrinsics();
 
         // Close the opening `mod`.
         self.push_str("}\n");
 
-        let mut src = mem::take(&mut self.src);
-        if self.opts.rustfmt {
-            let mut child = Command::new("rustfmt")
-                .arg("--edition=2018")
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped())
-                .spawn()
-                .expect("failed to spawn `rustfmt`");
-            child
-                .stdin
-                .take()
-                .unwrap()
-                .write_all(src.as_bytes())
-                .unwrap();
-            src.as_mut_string().truncate(0);
-            child
-                .stdout
-                .take()
-                .unwrap()
-                .read_to_string(src.as_mut_string())
-                .unwrap();
-            let status = child.wait().unwrap();
-            assert!(status.success());
+        let src = self.finish_src();
+        files.push_for_interface(&iface.name.to_snake_case(), "rs", src.as_bytes());
+    }
+
+    fn finish_all(&mut self, files: &mut Files) {
+        if self.opts.imports_builder {
+            self.print_imports_builder();
+            let src = self.finish_src();
+            files.push_for_interface("imports_builder", "rs", src.as_bytes());
         }
+        if self.opts.plugin {
+            self.print_plugin();
+            let src = self.finish_src();
+            files.push_for_interface("plugin", "rs", src.as_bytes());
+        }
+    }
+}
 
-        files.push("bindings.rs", src.as_bytes());
+/// Writes `ty` the way it's written in a `*.wai` file.
+fn wai_type(iface: &Interface, ty: &Type) -> String {
+    match ty {
+        Type::Unit => "unit".to_string(),
+        Type::Bool => "bool".to_string(),
+        Type::U8 => "u8".to_string(),
+        Type::S8 => "s8".to_string(),
+        Type::U16 => "u16".to_string(),
+        Type::S16 => "s16".to_string(),
+        Type::U32 => "u32".to_string(),
+        Type::S32 => "s32".to_string(),
+        Type::U64 => "u64".to_string(),
+        Type::S64 => "s64".to_string(),
+        Type::Float32 => "float32".to_string(),
+        Type::Float64 => "float64".to_string(),
+        Type::Char => "char".to_string(),
+        Type::String => "string".to_string(),
+        Type::Handle(r) => iface.resources[*r].name.clone(),
+        Type::Id(id) => {
+            let ty = &iface.types[*id];
+            if let Some(name) = &ty.name {
+                return name.clone();
+            }
+            match &ty.kind {
+                TypeDefKind::Type(t) => wai_type(iface, t),
+                TypeDefKind::Tuple(t) => format!(
+                    "tuple<{}>",
+                    t.types
+                        .iter()
+                        .map(|t| wai_type(iface, t))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                TypeDefKind::Option(t) => format!("option<{}>", wai_type(iface, t)),
+                TypeDefKind::Expected(e) => format!(
+                    "expected<{}, {}>",
+                    wai_type(iface, &e.ok),
+                    wai_type(iface, &e.err)
+                ),
+                TypeDefKind::List(t) => format!("list<{}>", wai_type(iface, t)),
+                TypeDefKind::Future(t) => format!("future<{}>", wai_type(iface, t)),
+                TypeDefKind::Stream(s) => format!(
+                    "stream<{}, {}>",
+                    wai_type(iface, &s.element),
+                    wai_type(iface, &s.end)
+                ),
+                TypeDefKind::Record(_)
+                | TypeDefKind::Flags(_)
+                | TypeDefKind::Enum(_)
+                | TypeDefKind::Variant(_)
+                | TypeDefKind::Union(_) => unreachable!(),
+            }
+        }
     }
 }
 
@@ -1396,7 +3820,8 @@ struct FunctionBindgen<'a> {
     // host, used for knowing where to acquire memory from.
     after_call: bool,
     // Whether or not the `caller_memory` variable has been defined and is
-    // available for use.
+    // available for use. It's reused by every access to linear memory until
+    // a call or block boundary invalidates it.
     caller_memory_available: bool,
     // Whether or not a helper function was called in an async fashion. If so
     // and this is an import, then the import must be defined asynchronously as
@@ -1414,6 +3839,7 @@ struct FunctionBindgen<'a> {
     // satisfied in the function header if any are set.
     needs_buffer_transaction: bool,
     needs_borrow_checker: bool,
+    needs_arena: bool,
     needs_memory: bool,
     needs_functions: HashMap<String, NeededFunction>,
 }
@@ -1433,6 +3859,7 @@ impl FunctionBindgen<'_> {
             closures: Source::default(),
             needs_buffer_transaction: false,
             needs_borrow_checker: false,
+            needs_arena: false,
             needs_memory: false,
             needs_functions: HashMap::new(),
             params,
@@ -1440,28 +3867,20 @@ impl FunctionBindgen<'_> {
     }
 
     fn memory_src(&mut self) -> String {
-        if self.gen.in_import {
-            if !self.after_call {
-                // Before calls we use `_bc` which is a borrow checker used for
-                // getting long-lasting borrows into memory.
-                self.needs_borrow_checker = true;
-                return "_bc".to_string();
-            }
+        if self.gen.in_import && !self.after_call {
+            // Before calls we use `_bc` which is a borrow checker used for
+            // getting long-lasting borrows into memory.
+            self.needs_borrow_checker = true;
+            return "_bc".to_string();
+        }
 
-            if !self.caller_memory_available {
-                self.needs_memory = true;
-                self.caller_memory_available = true;
-                self.push_str("let _memory_view = _memory.view(&store);\n");
-                self.push_str(
-                    "let caller_memory = unsafe { _memory_view.data_unchecked_mut() };\n",
-                );
-            }
-            "caller_memory".to_string()
-        } else {
+        if !self.caller_memory_available {
             self.needs_memory = true;
+            self.caller_memory_available = true;
             self.push_str("let _memory_view = _memory.view(&store);\n");
-            "unsafe { _memory_view.data_unchecked_mut() }".to_string()
+            self.push_str("let caller_memory = unsafe { _memory_view.data_unchecked_mut() };\n");
         }
+        "caller_memory".to_string()
     }
 
     fn call_intrinsic(&mut self, name: &str, args: String) {
@@ -1471,6 +3890,18 @@ impl FunctionBindgen<'_> {
         };
         self.push_str(&format!("func_{name}.call({args})?;\n"));
         self.caller_memory_available = false; // invalidated by call
+
+        // Allocations made while calling into the guest from the exports
+        // struct are reported to its grow hook.
+        if self.gen.opts.memory_usage
+            && !self.gen.in_import
+            && matches!(
+                self.needs_functions.get(name),
+                Some(NeededFunction::Realloc)
+            )
+        {
+            self.push_str("self.memory_watch.check(&*store, &self.memory)?;\n");
+        }
     }
 
     fn load(&mut self, offset: i32, ty: &str, operands: &[String]) -> String {
@@ -1492,6 +3923,27 @@ impl FunctionBindgen<'_> {
             mem, operands[1], offset, method, operands[0], extra
         ));
     }
+
+    /// Emits a `tracing` event recording `fields`. The event is skipped when
+    /// the surrounding span is disabled, so the fields are only evaluated if
+    /// a subscriber is interested in this function.
+    fn print_tracing_event(&mut self, fields: &[String]) {
+        if fields.is_empty() {
+            return;
+        }
+        self.push_str("if !span.is_disabled() {\n");
+        self.push_str("wai_bindgen_wasmer::tracing::event!(\n");
+        self.push_str(&format!(
+            "wai_bindgen_wasmer::tracing::Level::{},\n",
+            self.gen.opts.tracing_level.as_str()
+        ));
+        for field in fields {
+            self.push_str(field);
+            self.push_str(",\n");
+        }
+        self.push_str(");\n");
+        self.push_str("}\n");
+    }
 }
 
 impl RustFunctionGenerator for FunctionBindgen<'_> {
@@ -1553,7 +4005,7 @@ impl Bindgen for FunctionBindgen<'_> {
     }
 
     fn is_list_canonical(&self, iface: &Interface, ty: &Type) -> bool {
-        iface.all_bits_valid(ty)
+        is_list_canonical(iface, ty)
     }
 
     fn emit(
@@ -1570,7 +4022,12 @@ impl Bindgen for FunctionBindgen<'_> {
             results.push(s);
         };
 
+        let unchecked = self.gen.opts.unchecked;
         let mut try_from = |cvt: &str, operands: &[String], results: &mut Vec<String>| {
+            if unchecked {
+                results.push(format!("{} as {}", operands[0], cvt));
+                return;
+            }
             self.gen.needs_bad_int = true;
             let result = format!("{}::try_from({}).map_err(bad_int)?", cvt, operands[0]);
             results.push(result);
@@ -1615,9 +4072,9 @@ impl Bindgen for FunctionBindgen<'_> {
             }
 
             // Downcasts from `i32` into smaller integers are checked to ensure
-            // that they fit within the valid range. While not strictly
-            // necessary since we could chop bits off this should be more
-            // forward-compatible with any future changes.
+            // that they fit within the valid range, unless in unchecked mode.
+            // While not strictly necessary since we could chop bits off this
+            // should be more forward-compatible with any future changes.
             Instruction::S8FromI32 => try_from("i8", operands, results),
             Instruction::U8FromI32 => try_from("u8", operands, results),
             Instruction::S16FromI32 => try_from("i16", operands, results),
@@ -1647,6 +4104,9 @@ impl Bindgen for FunctionBindgen<'_> {
             Instruction::I32FromBool => {
                 results.push(format!("match {} {{ true => 1, false => 0 }}", operands[0]));
             }
+            Instruction::BoolFromI32 if unchecked => {
+                results.push(format!("{} != 0", operands[0]));
+            }
             Instruction::BoolFromI32 => {
                 self.gen.needs_invalid_variant = true;
                 results.push(format!(
@@ -1664,9 +4124,10 @@ impl Bindgen for FunctionBindgen<'_> {
                 results.push(format!(
                     "{{
                         let data_mut = store.data_mut();
-                        let mut tables = data_mut.tables.borrow_mut();
+                        let mut tables = data_mut.{}.borrow_mut();
                         tables.{}_table.insert({}) as i32
                     }}",
+                    self.gen.tables_var(name),
                     name.to_snake_case(),
                     operands[0]
                 ));
@@ -1674,12 +4135,13 @@ impl Bindgen for FunctionBindgen<'_> {
             Instruction::HandleBorrowedFromI32 { ty } => {
                 let name = &iface.resources[*ty].name;
                 results.push(format!(
-                    "tables
+                    "{}
                         .{}_table
                         .get(({}) as u32)
                         .ok_or_else(|| {{
                             wasmer::RuntimeError::new(\"invalid handle index\")
                         }})?",
+                    self.gen.tables_var(name),
                     name.to_snake_case(),
                     operands[0]
                 ));
@@ -1738,22 +4200,25 @@ impl Bindgen for FunctionBindgen<'_> {
                 }
             }
             Instruction::FlagsLift { flags, name, .. } => {
-                self.gen.needs_validate_flags = true;
                 let repr = RustFlagsRepr::new(flags);
                 let mut flags = String::from("0");
                 for (i, op) in operands.iter().enumerate() {
                     flags.push_str(&format!("| (({} as {repr}) << {})", op, i * 32));
                 }
-                results.push(format!(
-                    "validate_flags(
-                        {},
-                        {name}::all().bits(),
-                        \"{name}\",
-                        |bits| {name} {{ bits }}
-                    )?",
-                    flags,
-                    name = name.to_camel_case(),
-                ));
+                let name = name.to_camel_case();
+                if unchecked {
+                    results.push(format!("{name} {{ bits: {flags} }}"));
+                } else {
+                    self.gen.needs_validate_flags = true;
+                    results.push(format!(
+                        "validate_flags(
+                            {flags},
+                            {name}::all().bits(),
+                            \"{name}\",
+                            |bits| {name} {{ bits }}
+                        )?",
+                    ));
+                }
             }
 
             Instruction::VariantPayloadName => results.push("e".to_string()),
@@ -1799,12 +4264,15 @@ impl Bindgen for FunctionBindgen<'_> {
                         String::new()
                     };
                     let case = case.name.to_camel_case();
-                    result.push_str(&format!("{i} => {name}::{case}{block},\n"));
+                    let pat = last_case_pattern(i, variant.cases.len(), unchecked);
+                    result.push_str(&format!("{pat} => {name}::{case}{block},\n"));
+                }
+                if !unchecked {
+                    result.push_str(&format!("_ => return Err(invalid_variant(\"{name}\")),\n"));
+                    self.gen.needs_invalid_variant = true;
                 }
-                result.push_str(&format!("_ => return Err(invalid_variant(\"{name}\")),\n"));
                 result.push('}');
                 results.push(result);
-                self.gen.needs_invalid_variant = true;
             }
 
             Instruction::UnionLower {
@@ -1847,9 +4315,13 @@ impl Bindgen for FunctionBindgen<'_> {
                     .zip(blocks)
                     .enumerate()
                 {
-                    result.push_str(&format!("{i} => {name}::{case_name}({block}),\n"));
+                    let pat = last_case_pattern(i, union.cases.len(), unchecked);
+                    result.push_str(&format!("{pat} => {name}::{case_name}({block}),\n"));
+                }
+                if !unchecked {
+                    result.push_str(&format!("_ => return Err(invalid_variant(\"{name}\")),\n"));
+                    self.gen.needs_invalid_variant = true;
                 }
-                result.push_str(&format!("_ => return Err(invalid_variant(\"{name}\")),\n"));
                 result.push('}');
                 results.push(result);
             }
@@ -1875,14 +4347,23 @@ impl Bindgen for FunctionBindgen<'_> {
                 let none = self.blocks.pop().unwrap();
                 assert_eq!(none, "()");
                 let operand = &operands[0];
-                results.push(format!(
-                    "match {operand} {{
-                        0 => None,
-                        1 => Some({some}),
-                        _ => return Err(invalid_variant(\"option\")),
-                    }}"
-                ));
-                self.gen.needs_invalid_variant = true;
+                if unchecked {
+                    results.push(format!(
+                        "match {operand} {{
+                            0 => None,
+                            _ => Some({some}),
+                        }}"
+                    ));
+                } else {
+                    results.push(format!(
+                        "match {operand} {{
+                            0 => None,
+                            1 => Some({some}),
+                            _ => return Err(invalid_variant(\"option\")),
+                        }}"
+                    ));
+                    self.gen.needs_invalid_variant = true;
+                }
             }
 
             Instruction::ExpectedLower {
@@ -1905,14 +4386,23 @@ impl Bindgen for FunctionBindgen<'_> {
                 let err = self.blocks.pop().unwrap();
                 let ok = self.blocks.pop().unwrap();
                 let operand = &operands[0];
-                results.push(format!(
-                    "match {operand} {{
-                        0 => Ok({ok}),
-                        1 => Err({err}),
-                        _ => return Err(invalid_variant(\"expected\")),
-                    }}"
-                ));
-                self.gen.needs_invalid_variant = true;
+                if unchecked {
+                    results.push(format!(
+                        "match {operand} {{
+                            0 => Ok({ok}),
+                            _ => Err({err}),
+                        }}"
+                    ));
+                } else {
+                    results.push(format!(
+                        "match {operand} {{
+                            0 => Ok({ok}),
+                            1 => Err({err}),
+                            _ => return Err(invalid_variant(\"expected\")),
+                        }}"
+                    ));
+                    self.gen.needs_invalid_variant = true;
+                }
             }
 
             Instruction::EnumLower { .. } => {
@@ -1925,12 +4415,15 @@ impl Bindgen for FunctionBindgen<'_> {
                 let name = name.to_camel_case();
                 for (i, case) in enum_.cases.iter().enumerate() {
                     let case = case.name.to_camel_case();
-                    result.push_str(&format!("{i} => {name}::{case},\n"));
+                    let pat = last_case_pattern(i, enum_.cases.len(), unchecked);
+                    result.push_str(&format!("{pat} => {name}::{case},\n"));
+                }
+                if !unchecked {
+                    result.push_str(&format!("_ => return Err(invalid_variant(\"{name}\")),\n"));
+                    self.gen.needs_invalid_variant = true;
                 }
-                result.push_str(&format!("_ => return Err(invalid_variant(\"{name}\")),\n"));
                 result.push('}');
                 results.push(result);
-                self.gen.needs_invalid_variant = true;
             }
 
             Instruction::ListCanonLower { element, realloc } => {
@@ -1998,7 +4491,11 @@ impl Bindgen for FunctionBindgen<'_> {
                     self.push_str(&format!("let ptr{} = {};\n", tmp, operands[0]));
                     self.push_str(&format!("let len{} = {};\n", tmp, operands[1]));
                     let slice = format!("_bc.slice(ptr{0}, len{0})?", tmp);
-                    results.push(slice);
+                    if self.gen.opts.owned_params {
+                        results.push(format!("wai_bindgen_wasmer::rt::copy_le({})", slice));
+                    } else {
+                        results.push(slice);
+                    }
                 }
             },
 
@@ -2055,8 +4552,7 @@ impl Bindgen for FunctionBindgen<'_> {
                         tmp = tmp,
                     ));
                     results.push(format!(
-                        "String::from_utf8(data{})
-                            .map_err(|_| wasmer::RuntimeError::new(\"invalid utf-8\"))?",
+                        "wai_bindgen_wasmer::rt::string_from_utf8(data{})?",
                         tmp,
                     ));
                 }
@@ -2066,7 +4562,11 @@ impl Bindgen for FunctionBindgen<'_> {
                     self.push_str(&format!("let ptr{} = {};\n", tmp, operands[0]));
                     self.push_str(&format!("let len{} = {};\n", tmp, operands[1]));
                     let slice = format!("_bc.slice_str(ptr{0}, len{0})?", tmp);
-                    results.push(slice);
+                    if self.gen.opts.owned_params {
+                        results.push(format!("{}.to_string()", slice));
+                    } else {
+                        results.push(slice);
+                    }
                 }
             },
 
@@ -2121,24 +4621,36 @@ impl Bindgen for FunctionBindgen<'_> {
                 let base = format!("base{}", tmp);
                 self.push_str(&format!("let {} = {};\n", base, operands[0]));
                 let result = format!("result{}", tmp);
-                self.push_str(&format!(
-                    "let mut {} = Vec::with_capacity({} as usize);\n",
-                    result, len,
-                ));
+                if free.is_none() && self.gen.opts.arena {
+                    // Lifted parameters of host functions go into the arena,
+                    // which is reused by the next call.
+                    self.needs_arena = true;
+                    self.push_str(&format!(
+                        "let {result} = _arena.try_alloc_slice((0..{len}).map(|i| -> Result<_, wasmer::RuntimeError> {{
+                            let base = {base} + i * {size};
+                            Ok({body})
+                        }}))?;\n",
+                    ));
+                } else {
+                    self.push_str(&format!(
+                        "let mut {} = Vec::with_capacity({} as usize);\n",
+                        result, len,
+                    ));
 
-                self.push_str("for i in 0..");
-                self.push_str(&len);
-                self.push_str(" {\n");
-                self.push_str("let base = ");
-                self.push_str(&base);
-                self.push_str(" + i *");
-                self.push_str(&size.to_string());
-                self.push_str(";\n");
-                self.push_str(&result);
-                self.push_str(".push(");
-                self.push_str(&body);
-                self.push_str(");\n");
-                self.push_str("}\n");
+                    self.push_str("for i in 0..");
+                    self.push_str(&len);
+                    self.push_str(" {\n");
+                    self.push_str("let base = ");
+                    self.push_str(&base);
+                    self.push_str(" + i *");
+                    self.push_str(&size.to_string());
+                    self.push_str(";\n");
+                    self.push_str(&result);
+                    self.push_str(".push(");
+                    self.push_str(&body);
+                    self.push_str(");\n");
+                    self.push_str("}\n");
+                }
                 results.push(result);
 
                 if let Some(free) = free {
@@ -2187,6 +4699,7 @@ impl Bindgen for FunctionBindgen<'_> {
                 }
                 self.push_str("self.func_");
                 self.push_str(&to_rust_ident(name));
+                self.push_str(".as_ref().map_err(Clone::clone)?");
                 if self.gen.opts.async_.includes(name) {
                     self.push_str(".call_async(store, ");
                 } else {
@@ -2212,17 +4725,29 @@ impl Bindgen for FunctionBindgen<'_> {
                 for (i, operand) in operands.iter().enumerate() {
                     self.push_str(&format!("let param{} = {};\n", i, operand));
                 }
-                if self.gen.opts.tracing && !func.params.is_empty() {
-                    self.push_str("wai_bindgen_wasmer::tracing::event!(\n");
-                    self.push_str("wai_bindgen_wasmer::tracing::Level::TRACE,\n");
-                    for (i, (name, _ty)) in func.params.iter().enumerate() {
+                for (i, (name, _)) in func.params.iter().enumerate() {
+                    if let Some(range) = func.range(name) {
+                        self.push_str(&range_check(func, name, &format!("param{}", i), range));
+                    }
+                }
+                for (i, (name, ty)) in func.params.iter().enumerate() {
+                    if self.gen.interned(&func.name, name, ty) {
                         self.push_str(&format!(
-                            "{} = wai_bindgen_wasmer::tracing::field::debug(&param{}),\n",
-                            to_rust_ident(name),
-                            i
+                            "let param{i} = data_mut.interner.borrow_mut().intern(&param{i});\n"
                         ));
                     }
-                    self.push_str(");\n");
+                }
+                if self.gen.opts.tracing {
+                    let fields = func
+                        .params
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(i, (name, _ty))| {
+                            self.gen
+                                .tracing_field(&func.name, name, &format!("param{}", i))
+                        })
+                        .collect::<Vec<_>>();
+                    self.print_tracing_event(&fields);
                 }
 
                 let mut call = format!("host.{}(", func.name.to_snake_case());
@@ -2234,7 +4759,7 @@ impl Bindgen for FunctionBindgen<'_> {
                     call.push_str(".await");
                 }
 
-                self.push_str("let host = &mut data_mut.data;\n");
+                self.push_str(&format!("let host = {};\n", self.gen.host_ref()));
                 self.push_str("let result = ");
                 results.push("result".to_string());
                 match self.gen.classify_fn_ret(iface, func) {
@@ -2266,19 +4791,21 @@ impl Bindgen for FunctionBindgen<'_> {
                 if !self.gen.all_needed_handles.is_empty() {
                     self.push_str("drop(tables);\n");
                 }
+                for owner in self.gen.foreign_tables() {
+                    self.push_str(&format!("drop({}_tables);\n", owner.to_snake_case()));
+                }
 
                 self.after_call = true;
 
                 match &func.result {
                     Type::Unit => {}
                     _ if self.gen.opts.tracing => {
-                        self.push_str("wai_bindgen_wasmer::tracing::event!(\n");
-                        self.push_str("wai_bindgen_wasmer::tracing::Level::TRACE,\n");
-                        self.push_str(&format!(
-                            "{} = wai_bindgen_wasmer::tracing::field::debug(&{0}),\n",
-                            results[0],
-                        ));
-                        self.push_str(");\n");
+                        let fields = self
+                            .gen
+                            .tracing_field(&func.name, "result", &results[0])
+                            .into_iter()
+                            .collect::<Vec<_>>();
+                        self.print_tracing_event(&fields);
                     }
                     _ => {}
                 }
@@ -2296,6 +4823,18 @@ impl Bindgen for FunctionBindgen<'_> {
                         self.push_str(&result);
                         self.push_str(";\n");
                         self.push_str(&cleanup);
+                        if self.gen.opts.metrics {
+                            self.push_str("_metrics.succeeded();\n");
+                        }
+                        self.push_str("ret");
+                    }
+                    // The result may still fail to be lifted, so only count
+                    // the call as successful once it has been.
+                    None if self.gen.opts.metrics => {
+                        self.push_str("let ret = ");
+                        self.push_str(&result);
+                        self.push_str(";\n");
+                        self.push_str("_metrics.succeeded();\n");
                         self.push_str("ret");
                     }
                     None => self.push_str(&result),
@@ -2369,6 +4908,26 @@ impl NeededFunction {
     }
 }
 
+/// Returns the statement failing the call with `OutOfRange` if `value`, the
+/// argument `param` of `func`, isn't within `range`.
+fn range_check(func: &Function, param: &str, value: &str, range: Range) -> String {
+    format!(
+        "wai_bindgen_wasmer::OutOfRange::check({:?}, {:?}, {}, {}, {})?;\n",
+        func.name, param, value, range.min, range.max,
+    )
+}
+
+/// Returns the pattern matching the discriminant of case `i` of `n` when
+/// lifting, which in unchecked mode is `_` for the last case so that invalid
+/// discriminants don't need to be handled.
+fn last_case_pattern(i: usize, n: usize, unchecked: bool) -> String {
+    if unchecked && i == n - 1 {
+        String::from("_")
+    } else {
+        i.to_string()
+    }
+}
+
 fn sorted_iter<K: Ord, V>(map: &HashMap<K, V>) -> impl Iterator<Item = (&K, &V)> {
     let mut list = map.iter().collect::<Vec<_>>();
     list.sort_by_key(|p| p.0);
@@ -30,11 +30,6 @@ mod exports {
 
         // TODO: implement async support
         "!async-functions.wai"
-
-        // TODO: these use push/pull buffer which isn't implemented in the test
-        // generator just yet
-        "!wasi-next.wai"
-        "!host.wai"
     );
 }
 
@@ -108,3 +103,555 @@ mod custom_errors {
         custom_error: true,
     });
 }
+
+mod stubs {
+    wai_bindgen_wasmer::export!({
+        src["x"]: "
+            resource y {
+                z: func() -> string
+            }
+            foo: func(a: list<u8>, b: y) -> u32
+            bar: func() -> expected<unit, u32>
+        ",
+        custom_error: true,
+        stubs: true,
+    });
+
+    fn assert_impl<T: x::X>() {}
+
+    fn check() {
+        assert_impl::<x::UnimplementedX>();
+    }
+}
+
+mod deadlines_and_metrics {
+    wai_bindgen_wasmer::import!({
+        src["x"]: "
+            foo: func(a: string) -> u32
+            bar: func()
+        ",
+        deadlines: true,
+        metrics: true,
+    });
+}
+
+mod memory_usage {
+    wai_bindgen_wasmer::import!({
+        src["x"]: "
+            foo: func(a: list<string>) -> u32
+            bar: func()
+        ",
+        memory_usage: true,
+    });
+}
+
+mod owned_params {
+    wai_bindgen_wasmer::export!({
+        src["x"]: "
+            record entry { key: string, values: list<u32> }
+            foo: func(a: string, b: list<string>, c: entry) -> u32
+        ",
+        owned_params: true,
+        record: true,
+    });
+
+    struct X;
+
+    impl x::X for X {
+        fn foo(&mut self, a: String, b: Vec<String>, c: x::Entry) -> u32 {
+            (a.len() + b.len() + c.values.len()) as u32
+        }
+    }
+}
+
+mod record {
+    use wai_bindgen_wasmer::{Le, LogError};
+
+    wai_bindgen_wasmer::export!({
+        src["x"]: "
+            record point { x: float32, y: float64 }
+            variant shape { circle(float32), empty }
+            enum color { red, green }
+            flags access { read, write }
+            union number { u32, float64 }
+            resource blob
+            scale: func(p: point, by: list<float64>) -> point
+            paint: func(s: shape, c: color, a: access, n: number) -> list<string>
+            name: func(a: string) -> option<string>
+            open: func() -> blob
+        ",
+        record: true,
+    });
+
+    struct X;
+
+    impl x::X for X {
+        type Blob = ();
+
+        fn scale(&mut self, p: x::Point, by: &[Le<f64>]) -> x::Point {
+            let by = by.iter().map(|v| v.get()).product::<f64>();
+            x::Point {
+                x: p.x * by as f32,
+                y: p.y * by,
+            }
+        }
+
+        fn paint(&mut self, s: x::Shape, c: x::Color, a: x::Access, n: x::Number) -> Vec<String> {
+            vec![
+                format!("{:?}", s),
+                format!("{:?}", c),
+                a.to_string(),
+                format!("{:?}", n),
+            ]
+        }
+
+        fn name(&mut self, a: &str) -> Option<String> {
+            (!a.is_empty()).then(|| a.to_uppercase())
+        }
+
+        fn open(&mut self) {}
+    }
+
+    #[test]
+    fn log_round_trip() {
+        use x::X as _;
+
+        let mut recorder = x::XRecorder::new(X);
+        let nan = f32::from_bits(0x7fc0_1234);
+        recorder.scale(x::Point { x: nan, y: 2.0 }, &[Le::new(0.5), Le::new(-1.0)]);
+        recorder.paint(
+            x::Shape::Circle(-0.0),
+            x::Color::Green,
+            x::Access::READ | x::Access::WRITE,
+            x::Number::F64(f64::NAN),
+        );
+        recorder.paint(
+            x::Shape::Empty,
+            x::Color::Red,
+            x::Access::empty(),
+            x::Number::U32(7),
+        );
+        recorder.name("");
+        recorder.open();
+
+        let log = x::XCall::encode_log(&recorder.calls);
+        let calls = x::XCall::decode_log(&log).unwrap();
+        assert_eq!(calls.len(), 5);
+        match &calls[0] {
+            x::XCall::Scale { p, by, result } => {
+                assert_eq!(p.x.to_bits(), nan.to_bits());
+                assert_eq!(*by, [0.5, -1.0]);
+                assert_eq!(result.y, -1.0);
+            }
+            call => panic!("unexpected call {:?}", call),
+        }
+        match &calls[4] {
+            x::XCall::Unrecorded { function } => assert_eq!(*function, "open"),
+            call => panic!("unexpected call {:?}", call),
+        }
+        assert_eq!(x::XCall::encode_log(&calls), log);
+        x::replay_x(&mut X, &calls).unwrap();
+
+        assert_eq!(
+            x::XCall::decode_log(&log[..log.len() - 1]).unwrap_err(),
+            LogError::Truncated
+        );
+    }
+}
+
+mod record_exports {
+    wai_bindgen_wasmer::import!({
+        src["x"]: "
+            resource blob
+            record entry { key: u32, weight: float32 }
+            put: func(e: entry, data: list<u64>) -> u32
+            keys: func(prefix: string) -> list<string>
+            open: func(name: string) -> blob
+        ",
+        record: true,
+    });
+
+    #[test]
+    fn log_round_trip() {
+        let calls = vec![
+            x::XCall::Put {
+                e: x::Entry {
+                    key: 1,
+                    weight: -0.5,
+                },
+                data: vec![u64::MAX],
+                result: Ok(3),
+            },
+            x::XCall::Keys {
+                prefix: String::new(),
+                result: Err("unreachable".to_string()),
+            },
+            x::XCall::Unrecorded { function: "open" },
+        ];
+        let log = x::XCall::encode_log(&calls);
+        let decoded = x::XCall::decode_log(&log).unwrap();
+        assert_eq!(format!("{:?}", decoded), format!("{:?}", calls));
+        assert_eq!(x::XCall::encode_log(&decoded), log);
+    }
+
+    #[allow(dead_code)]
+    fn record(
+        store: &mut wai_bindgen_wasmer::wasmer::Store,
+        exports: x::X,
+    ) -> Result<Vec<x::XCall>, wai_bindgen_wasmer::wasmer::RuntimeError> {
+        let mut recorder = x::XRecorder::new(exports);
+        recorder.put(
+            store,
+            x::Entry {
+                key: 1,
+                weight: 2.0,
+            },
+            &[2],
+        )?;
+        recorder.keys(store, "")?;
+        recorder.open(store, "b")?;
+        Ok(recorder.calls)
+    }
+
+    #[allow(dead_code)]
+    fn replay(
+        store: &mut wai_bindgen_wasmer::wasmer::Store,
+        exports: &x::X,
+        calls: &[x::XCall],
+    ) -> Result<(), wai_bindgen_wasmer::ReplayMismatch> {
+        x::replay_x(exports, store, calls)
+    }
+}
+
+mod arena {
+    wai_bindgen_wasmer::export!({
+        src["x"]: "
+            record entry { key: string, values: list<string> }
+            foo: func(a: list<string>, b: list<entry>, c: list<list<u32>>) -> list<string>
+            bar: func(a: list<entry>) -> list<entry>
+            baz: func(a: string, b: list<string>) -> string
+        ",
+        arena: true,
+    });
+
+    struct X;
+
+    impl x::X for X {
+        fn foo(
+            &mut self,
+            a: &[&str],
+            b: &[x::EntryParam<'_>],
+            c: &[&[wai_bindgen_wasmer::Le<u32>]],
+        ) -> Vec<String> {
+            a.iter()
+                .chain(b.iter().map(|e| &e.key))
+                .map(|s| format!("{}{}", s, c.len()))
+                .collect()
+        }
+
+        fn bar(&mut self, a: &[x::EntryParam<'_>]) -> Vec<x::EntryResult> {
+            a.iter()
+                .map(|e| x::EntryResult {
+                    key: e.key.to_string(),
+                    values: e.values.iter().map(|v| v.to_string()).collect(),
+                })
+                .collect()
+        }
+
+        fn baz(&mut self, a: &str, b: &[&str]) -> String {
+            b.iter().fold(a.to_string(), |acc, s| acc + s)
+        }
+    }
+}
+
+mod intern {
+    use std::sync::Arc;
+
+    wai_bindgen_wasmer::export!({
+        src["x"]: "
+            log: func(level: string, message: string)
+            tag: func(a: string, b: string)
+        ",
+        intern: ["log.level", "tag"],
+    });
+
+    #[derive(Default)]
+    struct X {
+        levels: Vec<Arc<str>>,
+    }
+
+    impl x::X for X {
+        fn log(&mut self, level: Arc<str>, _message: &str) {
+            self.levels.push(level);
+        }
+
+        fn tag(&mut self, a: Arc<str>, b: Arc<str>) {
+            self.levels.push(a);
+            self.levels.push(b);
+        }
+    }
+}
+
+mod plugin {
+    wai_bindgen_wasmer::import!({
+        src["x"]: "
+            foo: func(a: string) -> u32
+        ",
+        src["empty"]: "",
+        plugin: true,
+    });
+
+    #[allow(dead_code)]
+    fn load(bytes: &[u8]) -> wai_bindgen_wasmer::anyhow::Result<u32> {
+        let mut plugin = Plugin::load(bytes)?;
+        Ok(plugin.x.foo(&mut plugin.store, "hello")?)
+    }
+
+    #[allow(dead_code)]
+    fn reload(plugin: &mut Plugin, bytes: &[u8]) -> wai_bindgen_wasmer::anyhow::Result<u32> {
+        let module = wai_bindgen_wasmer::wasmer::Module::new(&plugin.store, bytes)?;
+        plugin.reload(&module)?;
+        Ok(plugin.x.foo(&mut plugin.store, "hello")?)
+    }
+}
+
+mod wasi {
+    wai_bindgen_wasmer::import!({
+        src["x"]: "
+            foo: func(a: string) -> u32
+        ",
+        plugin: true,
+        wasi: true,
+    });
+
+    #[allow(dead_code)]
+    fn load(bytes: &[u8]) -> wai_bindgen_wasmer::anyhow::Result<u32> {
+        let mut wasi = wasmer_wasi::WasiState::new("x");
+        let (mut plugin, _wasi_env) = Plugin::load_wasi(bytes, &mut wasi)?;
+        Ok(plugin.x.foo(&mut plugin.store, "hello")?)
+    }
+}
+
+mod imports_builder {
+    wai_bindgen_wasmer::export!({
+        src["x"]: "
+            foo: func(a: string) -> u32
+        ",
+        src["empty"]: "",
+        imports_builder: true,
+        wasi: true,
+    });
+
+    struct X;
+
+    impl x::X for X {
+        fn foo(&mut self, a: &str) -> u32 {
+            a.len() as u32
+        }
+    }
+
+    #[allow(dead_code)]
+    fn instantiate(bytes: &[u8]) -> wai_bindgen_wasmer::anyhow::Result<Instantiated> {
+        let mut store = wai_bindgen_wasmer::wasmer::Store::default();
+        let module = wai_bindgen_wasmer::wasmer::Module::new(&store, bytes)?;
+        let mut wasi = wasmer_wasi::WasiState::new("x");
+        ImportsBuilder::new(&mut store)
+            .x(X)
+            .wasi(&module, &mut wasi)?
+            .instantiate(&module)
+    }
+}
+
+mod imports_builder_guest {
+    wai_bindgen_wasmer::import!({
+        src["x"]: "
+            foo: func(a: string) -> u32
+        ",
+        src["empty"]: "",
+        imports_builder: true,
+    });
+
+    #[allow(dead_code)]
+    fn instantiate(bytes: &[u8]) -> wai_bindgen_wasmer::anyhow::Result<u32> {
+        let mut store = wai_bindgen_wasmer::wasmer::Store::default();
+        let module = wai_bindgen_wasmer::wasmer::Module::new(&store, bytes)?;
+        let instantiated = ImportsBuilder::new(&mut store).instantiate(&module)?;
+        Ok(instantiated.x.foo(&mut store, "hello")?)
+    }
+}
+
+mod custom_names {
+    wai_bindgen_wasmer::export!({
+        src["wasi-next"]: "foo: func(a: string) -> u32",
+        module_name: "host",
+        trait_name: "HostApi",
+    });
+
+    struct Host;
+
+    impl host::HostApi for Host {
+        fn foo(&mut self, a: &str) -> u32 {
+            a.len() as u32
+        }
+    }
+
+    mod guest {
+        wai_bindgen_wasmer::import!({
+            src["wasi-next"]: "foo: func(a: string) -> u32",
+            struct_name: "wasi-next=Guest",
+        });
+
+        fn check(_: wasi_next::Guest) {}
+    }
+}
+
+mod shared_host {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    wai_bindgen_wasmer::export!({
+        src["x"]: "
+            resource y {
+                z: func() -> u32
+            }
+            foo: func(a: string) -> y
+        ",
+        shared_host: true,
+    });
+
+    #[derive(Default)]
+    struct X {
+        calls: AtomicU32,
+    }
+
+    impl x::X for X {
+        type Y = u32;
+
+        fn y_z(&self, y: &u32) -> u32 {
+            *y
+        }
+
+        fn foo(&self, a: &str) -> u32 {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            a.len() as u32
+        }
+    }
+
+    #[allow(dead_code)]
+    fn add_to_imports(
+        stores: &mut [wai_bindgen_wasmer::wasmer::Store],
+        host: Arc<X>,
+    ) -> Vec<wai_bindgen_wasmer::wasmer::Imports> {
+        stores
+            .iter_mut()
+            .map(|store| {
+                let mut imports = wai_bindgen_wasmer::wasmer::Imports::new();
+                let _init = x::add_to_imports(store, &mut imports, Arc::clone(&host));
+                imports
+            })
+            .collect()
+    }
+}
+
+mod function_descs {
+    wai_bindgen_wasmer::import!({
+        src["x"]: "
+            record point { x: s32, y: s32 }
+            foo: func(a: list<point>, b: option<string>) -> expected<u32, string>
+        ",
+    });
+
+    fn check() {
+        assert_eq!(
+            x::FUNCTIONS,
+            &[wai_bindgen_wasmer::FunctionDesc {
+                name: "foo",
+                params: &[("a", "list<point>"), ("b", "option<string>")],
+                result: "expected<u32, string>",
+                requires: &[],
+                is_async: false,
+            }],
+        );
+    }
+}
+
+mod capabilities {
+    use wai_bindgen_wasmer::CapabilitySet;
+
+    wai_bindgen_wasmer::export!({
+        src["x"]: "
+            @requires(\"fs.read\")
+            read: func(path: string) -> list<u8>
+            @requires(\"fs.read\", \"fs.write\")
+            copy: func(from: string, to: string)
+            now: func() -> u64
+        ",
+        stubs: true,
+    });
+
+    struct X {
+        granted: CapabilitySet,
+    }
+
+    impl x::X for X {
+        fn capabilities(&self) -> &CapabilitySet {
+            &self.granted
+        }
+
+        fn read(&mut self, _path: &str) -> Vec<u8> {
+            Vec::new()
+        }
+
+        fn copy(&mut self, _from: &str, _to: &str) {}
+
+        fn now(&mut self) -> u64 {
+            0
+        }
+    }
+
+    fn check() {
+        let granted = ["fs.read"].into_iter().collect::<CapabilitySet>();
+        assert!(granted.check("read", &["fs.read"]).is_ok());
+        let err = granted.check("copy", &["fs.read", "fs.write"]).unwrap_err();
+        assert_eq!(err.capability, "fs.write");
+        assert_eq!(x::FUNCTIONS[1].requires, &["fs.read", "fs.write"]);
+        let _ = X { granted };
+    }
+}
+
+mod ranges {
+    use wai_bindgen_wasmer::OutOfRange;
+
+    wai_bindgen_wasmer::export!({
+        src["x"]: "
+            type port = u16
+            connect: func(host: string, @range(1, 65535) port: port)
+            scale: func(@range(-100, 100) percent: s8)
+        ",
+    });
+
+    wai_bindgen_wasmer::import!({
+        src["y"]: "
+            resize: func(@range(1, 64) size: u32) -> u32
+        ",
+    });
+
+    struct X;
+
+    impl x::X for X {
+        fn connect(&mut self, _host: &str, _port: u16) {}
+
+        fn scale(&mut self, _percent: i8) {}
+    }
+
+    fn check() {
+        assert!(OutOfRange::check("connect", "port", 80u16, 1, 65535).is_ok());
+        let err = OutOfRange::check("connect", "port", 0u16, 1, 65535).unwrap_err();
+        assert_eq!(err.value, 0);
+        assert_eq!(
+            err.to_string(),
+            "argument `port` of `connect` is 0, outside of 1..=65535"
+        );
+        assert!(OutOfRange::check("scale", "percent", -101i16, -100, 100).is_err());
+    }
+}
@@ -4,7 +4,7 @@ use std::mem;
 use wai_bindgen_gen_core::wai_parser::abi::{
     AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmType,
 };
-use wai_bindgen_gen_core::{wai_parser::*, Direction, Files, Generator, Ns};
+use wai_bindgen_gen_core::{union_case_names, wai_parser::*, Direction, Files, Generator, Ns};
 
 pub mod dependencies;
 pub mod source;
@@ -181,9 +181,9 @@ impl Generator for WasmtimePy {
         docs: &Docs,
     ) {
         let mut builder = self.src.builder(&mut self.deps, iface);
-        builder.pyimport("enum", "Flag");
+        builder.pyimport("enum", "IntFlag");
         builder.pyimport("enum", "auto");
-        builder.push_str(&format!("class {}(Flag):\n", name.to_camel_case()));
+        builder.push_str(&format!("class {}(IntFlag):\n", name.to_camel_case()));
         builder.indent();
         builder.docstring(docs);
         for flag in flags.flags.iter() {
@@ -222,6 +222,24 @@ impl Generator for WasmtimePy {
             builder.push_str("\n");
             cases.push(case_name);
         }
+        if variant.non_exhaustive {
+            let case_name = format!("{}Unknown", name.to_camel_case());
+            builder.push_str("@dataclass\n");
+            builder.push_str(&format!("class {case_name}:\n"));
+            builder.indent();
+            builder.docstring(&Docs {
+                contents: Some(
+                    "A case added by a newer version of the interface, along with its\n\
+                     discriminant. It can't be passed back to wasm.\n"
+                        .to_string(),
+                ),
+                examples: Vec::new(),
+            });
+            builder.push_str("value: int\n");
+            builder.dedent();
+            builder.push_str("\n");
+            cases.push(case_name);
+        }
 
         builder.deps.pyimport("typing", "Union");
         builder.comment(docs);
@@ -318,6 +336,22 @@ impl Generator for WasmtimePy {
             }
             builder.push_str(&format!("{} = {}\n", name, i));
         }
+        if enum_.non_exhaustive {
+            // Cases added by newer versions of the interface become pseudo
+            // members named `UNKNOWN`, which keep their discriminant.
+            builder.push_str("@classmethod\n");
+            builder.push_str("def _missing_(cls, value):\n");
+            builder.indent();
+            builder.push_str("if not isinstance(value, int):\n");
+            builder.indent();
+            builder.push_str("return None\n");
+            builder.dedent();
+            builder.push_str("unknown = object.__new__(cls)\n");
+            builder.push_str("unknown._name_ = \"UNKNOWN\"\n");
+            builder.push_str("unknown._value_ = value\n");
+            builder.push_str("return unknown\n");
+            builder.dedent();
+        }
         builder.dedent();
         builder.push_str("\n");
     }
@@ -836,7 +870,11 @@ impl Generator for WasmtimePy {
             self.src.dedent();
         }
 
-        files.push("bindings.py", self.src.as_bytes());
+        files.push_for_interface(
+            &iface.name.to_snake_case(),
+            "py",
+            mem::take(&mut self.src).as_bytes(),
+        );
     }
 }
 
@@ -1266,10 +1304,17 @@ impl Bindgen for FunctionBindgen<'_> {
                 builder.push_str("else:\n");
                 builder.indent();
                 let variant_name = name.to_camel_case();
-                builder.push_str(&format!(
-                    "raise TypeError(\"invalid variant discriminant for {}\")\n",
-                    variant_name
-                ));
+                if variant.non_exhaustive {
+                    builder.push_str(&format!(
+                        "{} = {}Unknown({})\n",
+                        result, variant_name, operands[0]
+                    ));
+                } else {
+                    builder.push_str(&format!(
+                        "raise TypeError(\"invalid variant discriminant for {}\")\n",
+                        variant_name
+                    ));
+                }
                 builder.dedent();
                 results.push(result);
             }
@@ -1300,6 +1345,7 @@ impl Bindgen for FunctionBindgen<'_> {
                     .get(&name.to_string())
                     .unwrap();
                 let name = name.to_camel_case();
+                let case_names = union_case_names(iface, union);
                 let op0 = &operands[0];
                 for (i, ((case, (block, block_results)), payload)) in
                     union.cases.iter().zip(blocks).zip(payloads).enumerate()
@@ -1313,7 +1359,7 @@ impl Bindgen for FunctionBindgen<'_> {
                         }
                         // Prints the name of this union cases dataclass
                         PyUnionRepresentation::Wrapped => {
-                            builder.push_str(&format!("{name}{i}"));
+                            builder.push_str(&format!("{name}{}", case_names[i]));
                         }
                     }
                     builder.push_str("):\n");
@@ -1359,6 +1405,7 @@ impl Bindgen for FunctionBindgen<'_> {
                     .get(&name.to_string())
                     .unwrap();
                 let name = name.to_camel_case();
+                let case_names = union_case_names(iface, union);
                 let op0 = &operands[0];
                 for (i, (_case, (block, block_results))) in
                     union.cases.iter().zip(blocks).enumerate()
@@ -1375,7 +1422,7 @@ impl Bindgen for FunctionBindgen<'_> {
                         PyUnionRepresentation::Raw => builder.push_str(block_result),
                         // Constructs an instance of the union cases dataclass
                         PyUnionRepresentation::Wrapped => {
-                            builder.push_str(&format!("{name}{i}({block_result})"))
+                            builder.push_str(&format!("{name}{}({block_result})", case_names[i]))
                         }
                     }
                     builder.newline();
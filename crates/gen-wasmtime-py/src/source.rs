@@ -1,5 +1,5 @@
 use heck::*;
-use wai_bindgen_gen_core::wai_parser::*;
+use wai_bindgen_gen_core::{union_case_names, wai_parser::*};
 
 use crate::dependencies::Dependencies;
 
@@ -346,9 +346,9 @@ impl<'s, 'd, 'i> SourceBuilder<'s, 'd, 'i> {
         self.deps.pyimport("dataclasses", "dataclass");
         let mut cases = Vec::new();
         let name = name.to_camel_case();
-        for (i, case) in union.cases.iter().enumerate() {
+        for (case, case_name) in union.cases.iter().zip(union_case_names(self.iface, union)) {
             self.source.push_str("@dataclass\n");
-            let name = format!("{name}{i}");
+            let name = format!("{name}{case_name}");
             self.source.push_str(&format!("class {name}:\n"));
             self.source.indent();
             self.source.docstring(&case.docs);
@@ -9,7 +9,8 @@ use wai_bindgen_gen_core::wai_parser::abi::{
 };
 use wai_bindgen_gen_core::{wai_parser::*, Direction, Files, Generator, Source, TypeInfo, Types};
 use wai_bindgen_gen_rust::{
-    to_rust_ident, wasm_type, FnSig, RustFlagsRepr, RustFunctionGenerator, RustGenerator, TypeMode,
+    is_list_canonical, to_rust_ident, wasm_type, FnSig, RustFlagsRepr, RustFunctionGenerator,
+    RustGenerator, TypeMode,
 };
 
 #[derive(Default)]
@@ -48,7 +49,6 @@ struct Import {
     is_async: bool,
     name: String,
     trait_signature: String,
-    num_wasm_params: usize,
     closure: String,
 }
 
@@ -334,7 +334,10 @@ impl Generator for Wasmtime {
         // we synthesize an `Endian` implementation for it so `&[Le<ThisType>]`
         // is usable.
         if !self.modes_of(iface, id).is_empty()
-            && record.fields.iter().all(|f| iface.all_bits_valid(&f.ty))
+            && record
+                .fields
+                .iter()
+                .all(|f| is_list_canonical(iface, &f.ty))
         {
             self.src.push_str("impl wai_bindgen_wasmtime::Endian for ");
             self.src.push_str(&name.to_camel_case());
@@ -365,7 +368,7 @@ impl Generator for Wasmtime {
             self.src.push_str("}\n");
 
             // Also add an `AllBytesValid` valid impl since this structure's
-            // byte representations are valid (guarded by the `all_bits_valid`
+            // byte representations are valid (guarded by the `is_list_canonical`
             // predicate).
             self.src
                 .push_str("unsafe impl wai_bindgen_wasmtime::AllBytesValid for ");
@@ -582,19 +585,6 @@ impl Generator for Wasmtime {
         self.in_trait = false;
         let trait_signature = mem::take(&mut self.src).into();
 
-        // Generate the closure that's passed to a `Linker`, the final piece of
-        // codegen here.
-        self.src
-            .push_str("move |mut caller: wasmtime::Caller<'_, T>");
-        for (i, param) in sig.params.iter().enumerate() {
-            let arg = format!("arg{}", i);
-            self.src.push_str(",");
-            self.src.push_str(&arg);
-            self.src.push_str(":");
-            self.wasm_type(*param);
-        }
-        self.src.push_str("| {\n");
-
         // If an intrinsic was called asynchronously, which happens if anything
         // in the module could be asynchronous, then we must wrap this host
         // import with an async block. Otherwise if the function is itself
@@ -602,12 +592,32 @@ impl Generator for Wasmtime {
         //
         // If none of that happens, then this is fine to be sync because
         // everything is sync.
-        let is_async = if async_intrinsic_called || self.opts.async_.includes(&func.name) {
+        let is_async = async_intrinsic_called || self.opts.async_.includes(&func.name);
+
+        // Generate the closure that's passed to a `Linker`, the final piece of
+        // codegen here. Async closures receive their parameters as a single
+        // tuple, as `Linker::func_wrap_async` expects.
+        self.src
+            .push_str("move |mut caller: wasmtime::Caller<'_, T>");
+        if is_async {
+            self.src.push_str(", (");
+            for i in 0..sig.params.len() {
+                self.src.push_str(&format!("arg{},", i));
+            }
+            self.src.push_str("): (");
+            for param in sig.params.iter() {
+                self.wasm_type(*param);
+                self.src.push_str(",");
+            }
+            self.src.push_str(")| {\n");
             self.src.push_str("Box::new(async move {\n");
-            true
         } else {
-            false
-        };
+            for (i, param) in sig.params.iter().enumerate() {
+                self.src.push_str(&format!(", arg{}: ", i));
+                self.wasm_type(*param);
+            }
+            self.src.push_str("| {\n");
+        }
 
         if self.opts.tracing {
             self.src.push_str(&format!(
@@ -629,7 +639,7 @@ impl Generator for Wasmtime {
             self.src.push_str(&format!(
                 "
                     let func = get_func(&mut caller, \"{name}\")?;
-                    let func_{name} = func.typed::<{cvt}, _>(&caller)?;
+                    let func_{name} = func.typed::<{cvt}>(&caller)?;
                 ",
                 name = name,
                 cvt = func.cvt(),
@@ -670,7 +680,6 @@ impl Generator for Wasmtime {
             .or_default()
             .push(Import {
                 is_async,
-                num_wasm_params: sig.params.len(),
                 name: func.name.to_string(),
                 closure,
                 trait_signature,
@@ -694,7 +703,7 @@ impl Generator for Wasmtime {
         self.print_docs_and_params(iface, func, TypeMode::AllBorrowed("'_"), &sig);
         self.push_str("-> Result<");
         self.print_ty(iface, &func.result, TypeMode::Owned);
-        self.push_str(", wasmtime::Trap> {\n");
+        self.push_str(", wasmtime::Error> {\n");
 
         let params = func
             .params
@@ -726,7 +735,7 @@ impl Generator for Wasmtime {
             self.src
                 .push_str(&format!("let func_{0} = &self.{0};\n", name));
             let get = format!(
-                "instance.get_typed_func::<{}, _>(&mut store, \"{}\")?",
+                "instance.get_typed_func::<{}>(&mut store, \"{}\")?",
                 func.cvt(),
                 name
             );
@@ -784,7 +793,7 @@ impl Generator for Wasmtime {
             (
                 format!("wasmtime::TypedFunc<{}>", cvt),
                 format!(
-                    "instance.get_typed_func::<{}, _>(&mut store, \"{}\")?",
+                    "instance.get_typed_func::<{}>(&mut store, \"{}\")?",
                     cvt, func.name,
                 ),
             ),
@@ -820,12 +829,12 @@ impl Generator for Wasmtime {
                 self.src.push_str("type Error;\n");
                 if self.needs_custom_error_to_trap {
                     self.src.push_str(
-                        "fn error_to_trap(&mut self, err: Self::Error) -> wasmtime::Trap;\n",
+                        "fn error_to_trap(&mut self, err: Self::Error) -> wasmtime::Error;\n",
                     );
                 }
                 for ty in self.needs_custom_error_to_types.iter() {
                     self.src.push_str(&format!(
-                        "fn error_to_{}(&mut self, err: Self::Error) -> Result<{}, wasmtime::Trap>;\n",
+                        "fn error_to_{}(&mut self, err: Self::Error) -> Result<{}, wasmtime::Error>;\n",
                         ty.to_snake_case(),
                         ty.to_camel_case(),
                     ));
@@ -899,9 +908,9 @@ impl Generator for Wasmtime {
             }
             for f in funcs {
                 let method = if f.is_async {
-                    format!("func_wrap{}_async", f.num_wasm_params)
+                    "func_wrap_async"
                 } else {
-                    String::from("func_wrap")
+                    "func_wrap"
                 };
                 self.push_str(&format!(
                     "linker.{}(\"{}\", \"{}\", {})?;\n",
@@ -919,7 +928,7 @@ impl Generator for Wasmtime {
                                 .{snake}_table
                                 .remove(handle)
                                 .map_err(|e| {{
-                                    wasmtime::Trap::new(format!(\"failed to remove handle: {{}}\", e))
+                                    wasmtime::Error::msg(format!(\"failed to remove handle: {{}}\", e))
                                 }})?;
                             host.drop_{snake}(handle);
                             Ok(())
@@ -1003,11 +1012,12 @@ impl Generator for Wasmtime {
                 name,
             ));
             for r in self.exported_resources.iter() {
-                let (func_wrap, call, wait, prefix, suffix) = if self.opts.async_.is_none() {
-                    ("func_wrap", "call", "", "", "")
+                let (func_wrap, param, call, wait, prefix, suffix) = if self.opts.async_.is_none() {
+                    ("func_wrap", "idx: u32", "call", "", "", "")
                 } else {
                     (
-                        "func_wrap1_async",
+                        "func_wrap_async",
+                        "(idx,): (u32,)",
                         "call_async",
                         ".await",
                         "Box::new(async move {",
@@ -1019,7 +1029,7 @@ impl Generator for Wasmtime {
                         linker.{func_wrap}(
                             \"canonical_abi\",
                             \"resource_drop_{name}\",
-                            move |mut caller: wasmtime::Caller<'_, T>, idx: u32| {prefix}{{
+                            move |mut caller: wasmtime::Caller<'_, T>, {param}| {prefix}{{
                                 let state = get_state(caller.data_mut());
                                 let resource_idx = state.index_slab{idx}.remove(idx)?;
                                 let wasm = match state.resource_slab{idx}.drop(resource_idx) {{
@@ -1063,6 +1073,7 @@ impl Generator for Wasmtime {
                     name = iface.resources[*r].name,
                     idx = r.index(),
                     func_wrap = func_wrap,
+                    param = param,
                     call = call,
                     wait = wait,
                     prefix = prefix,
@@ -1138,7 +1149,7 @@ impl Generator for Wasmtime {
                 self.src.push_str(&format!(
                     "
                         get_state(store.data_mut()).dtor{} = \
-                            Some(instance.get_typed_func::<i32, (), _>(\
+                            Some(instance.get_typed_func::<i32, ()>(\
                                 &mut store, \
                                 \"canonical_abi_drop_{}\", \
                             )?);\n
@@ -1181,7 +1192,7 @@ impl Generator for Wasmtime {
                             &self,
                             mut store: impl wasmtime::AsContextMut<Data = T>,
                             val: {name_camel},
-                        ) -> Result<(), wasmtime::Trap> {{
+                        ) -> Result<(), wasmtime::Error> {{
                             let mut store = store.as_context_mut();
                             let data = (self.get_state)(store.data_mut());
                             let wasm = match data.resource_slab{idx}.drop(val.0) {{
@@ -1233,7 +1244,7 @@ impl Generator for Wasmtime {
             assert!(status.success());
         }
 
-        files.push("bindings.rs", src.as_bytes());
+        files.push_for_interface(&iface.name.to_snake_case(), "rs", src.as_bytes());
     }
 }
 
@@ -1425,7 +1436,7 @@ impl Bindgen for FunctionBindgen<'_> {
     }
 
     fn is_list_canonical(&self, iface: &Interface, ty: &Type) -> bool {
-        iface.all_bits_valid(ty)
+        is_list_canonical(iface, ty)
     }
 
     fn emit(
@@ -1543,7 +1554,7 @@ impl Bindgen for FunctionBindgen<'_> {
                 let name = &iface.resources[*ty].name;
                 results.push(format!(
                     "_tables.{}_table.get(({}) as u32).ok_or_else(|| {{
-                            wasmtime::Trap::new(\"invalid handle index\")
+                            wasmtime::Error::msg(\"invalid handle index\")
                         }})?",
                     name.to_snake_case(),
                     operands[0]
@@ -1920,7 +1931,7 @@ impl Bindgen for FunctionBindgen<'_> {
                     );
                     results.push(format!(
                         "String::from_utf8(data{})
-                            .map_err(|_| wasmtime::Trap::new(\"invalid utf-8\"))?",
+                            .map_err(|_| wasmtime::Error::msg(\"invalid utf-8\"))?",
                         tmp,
                     ));
                 }
@@ -1,5 +1,6 @@
 use anyhow::{Context as _, Result};
 use wasmtime::{Config, Engine, Instance, Linker, Module, Store};
+use wasmtime_wasi::preview1::{add_to_linker_sync, WasiP1Ctx};
 
 test_helpers::runtime_tests_wasmtime!();
 
@@ -9,17 +10,48 @@ fn default_config() -> Result<Config> {
     let mut config = Config::new();
     config.cache_config_load_default()?;
     config.wasm_backtrace_details(wasmtime::WasmBacktraceDetails::Enable);
+    config.epoch_interruption(true);
     Ok(config)
 }
 
-fn default_wasi() -> wasmtime_wasi::WasiCtx {
-    wasmtime_wasi::sync::WasiCtxBuilder::new()
+/// How many seconds a test may run wasm for before it's interrupted, so that a
+/// guest stuck in a loop fails its test rather than hanging the suite.
+const EPOCH_DEADLINE: u64 = 60;
+
+fn default_engine(config: &Config) -> Result<Engine> {
+    let engine = Engine::new(config)?;
+    let ticker = engine.weak();
+    std::thread::spawn(move || {
+        while let Some(engine) = ticker.upgrade() {
+            engine.increment_epoch();
+            drop(engine);
+            std::thread::sleep(std::time::Duration::from_secs(1));
+        }
+    });
+    Ok(engine)
+}
+
+fn default_store<I: Default, E: Default>(engine: &Engine) -> Store<Context<I, E>> {
+    let mut store = Store::new(
+        engine,
+        Context {
+            wasi: default_wasi(),
+            imports: I::default(),
+            exports: E::default(),
+        },
+    );
+    store.set_epoch_deadline(EPOCH_DEADLINE);
+    store
+}
+
+fn default_wasi() -> WasiP1Ctx {
+    wasmtime_wasi::WasiCtxBuilder::new()
         .inherit_stdio()
-        .build()
+        .build_p1()
 }
 
 struct Context<I, E> {
-    wasi: wasmtime_wasi::WasiCtx,
+    wasi: WasiP1Ctx,
     imports: I,
     exports: E,
 }
@@ -33,21 +65,14 @@ fn instantiate<I: Default, E: Default, T>(
         &mut Linker<Context<I, E>>,
     ) -> Result<(T, Instance)>,
 ) -> Result<(T, Store<Context<I, E>>)> {
-    let engine = Engine::new(&default_config()?)?;
+    let engine = default_engine(&default_config()?)?;
     let module = Module::from_file(&engine, wasm)?;
 
     let mut linker = Linker::new(&engine);
     add_imports(&mut linker)?;
-    wasmtime_wasi::add_to_linker(&mut linker, |cx| &mut cx.wasi)?;
+    add_to_linker_sync(&mut linker, |cx| &mut cx.wasi)?;
 
-    let mut store = Store::new(
-        &engine,
-        Context {
-            wasi: default_wasi(),
-            imports: I::default(),
-            exports: E::default(),
-        },
-    );
+    let mut store = default_store(&engine);
     let (exports, _instance) = mk_exports(&mut store, &module, &mut linker)?;
     Ok((exports, store))
 }
@@ -68,7 +93,7 @@ fn _instantiate_smw<I: Default, E: Default, T>(
 ) -> Result<(T, Store<Context<I, E>>)> {
     let mut config = default_config()?;
     config.wasm_multi_memory(true);
-    let engine = Engine::new(&config)?;
+    let engine = default_engine(&config)?;
 
     println!("reading wasms...");
     let wasm = std::fs::read(wasm).context(format!("failed to read {}", wasm))?;
@@ -81,16 +106,9 @@ fn _instantiate_smw<I: Default, E: Default, T>(
 
     let mut linker = Linker::new(&engine);
     add_imports(&mut linker)?;
-    wasmtime_wasi::add_to_linker(&mut linker, |cx| &mut cx.wasi)?;
+    add_to_linker_sync(&mut linker, |cx| &mut cx.wasi)?;
 
-    let mut store = Store::new(
-        &engine,
-        Context {
-            wasi: default_wasi(),
-            imports: I::default(),
-            exports: E::default(),
-        },
-    );
+    let mut store = default_store(&engine);
 
     println!("instantiating spidermonkey.wasm...");
     let _smw_instance = linker
@@ -103,7 +121,7 @@ fn _instantiate_smw<I: Default, E: Default, T>(
     let (exports, instance) = mk_exports(&mut store, &module, &mut linker)?;
 
     println!("running wizer.initialize");
-    let init = instance.get_typed_func::<(), (), _>(&mut store, "wizer.initialize")?;
+    let init = instance.get_typed_func::<(), ()>(&mut store, "wizer.initialize")?;
     init.call(&mut store, ())
         .context("failed to call wizer.initialize")?;
     Ok((exports, store))
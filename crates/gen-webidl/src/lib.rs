@@ -0,0 +1,472 @@
+use heck::*;
+use std::collections::HashMap;
+use std::mem;
+use wai_bindgen_gen_core::{wai_parser::*, Direction, Files, Generator, Source};
+
+/// Generates WebIDL definitions for an interface, describing the JavaScript
+/// values of the `js` bindings, so browser embedders can review how the API
+/// fits the web platform and feed it to existing WebIDL tooling.
+///
+/// * strings are `USVString`s and lists are `sequence`s, or typed arrays
+///   for lists of numbers;
+/// * records are dictionaries, where fields of option types aren't
+///   `required`, and enums are enums of the names of their cases;
+/// * flags are an integer with a bit per flag, as a `bigint` past 32 flags;
+/// * variants, unions and `expected` are dictionaries with a `tag`, the
+///   name or index of the case, and a `val` with its payload;
+/// * the functions of the interface and the methods of its resources are
+///   the operations of interfaces named after them.
+///
+/// WebIDL has no tuples nor payload types depending on a tag, which are
+/// `any` instead, with the actual type given in a comment. The same goes
+/// for options which can't be a nullable type, such as options of
+/// dictionaries.
+#[derive(Default)]
+pub struct WebIdl {
+    src: Source,
+    opts: Opts,
+    /// Operations of the interface, and of each of its resources.
+    funcs: Vec<String>,
+    methods: HashMap<ResourceId, Vec<String>>,
+    needs_result: bool,
+    needs_option: bool,
+}
+
+#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "structopt", derive(structopt::StructOpt))]
+pub struct Opts {
+    // ...
+}
+
+impl Opts {
+    pub fn build(&self) -> WebIdl {
+        let mut r = WebIdl::new();
+        r.opts = self.clone();
+        r
+    }
+}
+
+impl WebIdl {
+    pub fn new() -> WebIdl {
+        WebIdl::default()
+    }
+
+    fn ty(&mut self, iface: &Interface, ty: &Type) -> String {
+        match ty {
+            Type::Unit => "undefined".to_string(),
+            Type::Bool => "boolean".to_string(),
+            Type::U8 => "octet".to_string(),
+            Type::S8 => "byte".to_string(),
+            Type::U16 => "unsigned short".to_string(),
+            Type::S16 => "short".to_string(),
+            Type::U32 => "unsigned long".to_string(),
+            Type::S32 => "long".to_string(),
+            Type::U64 | Type::S64 => "bigint".to_string(),
+            Type::Float32 => "unrestricted float".to_string(),
+            Type::Float64 => "unrestricted double".to_string(),
+            Type::Char | Type::String => "USVString".to_string(),
+            Type::Handle(r) => iface.resources[*r].name.to_camel_case(),
+            Type::Id(id) => {
+                let ty = &iface.types[*id];
+                if let Some(name) = &ty.name {
+                    return name.to_camel_case();
+                }
+                match &ty.kind {
+                    TypeDefKind::Type(t) => self.ty(iface, t),
+                    TypeDefKind::List(t) => self.list(iface, t),
+                    TypeDefKind::Option(t) => self.option(iface, t),
+                    TypeDefKind::Tuple(t) => {
+                        format!("sequence<any> /* {} */", self.tuple(iface, t))
+                    }
+                    TypeDefKind::Expected(e) => {
+                        self.needs_result = true;
+                        format!(
+                            "Result /* <{}, {}> */",
+                            self.ty(iface, &e.ok),
+                            self.ty(iface, &e.err)
+                        )
+                    }
+                    TypeDefKind::Record(_)
+                    | TypeDefKind::Flags(_)
+                    | TypeDefKind::Enum(_)
+                    | TypeDefKind::Variant(_)
+                    | TypeDefKind::Union(_) => unreachable!("anonymous {:?}", ty.kind),
+                    TypeDefKind::Future(_) | TypeDefKind::Stream(_) => {
+                        unimplemented!("webidl for futures and streams")
+                    }
+                }
+            }
+        }
+    }
+
+    fn list(&mut self, iface: &Interface, ty: &Type) -> String {
+        match array_ty(iface, ty) {
+            Some(ty) => ty.to_string(),
+            None => format!("sequence<{}>", self.ty(iface, ty)),
+        }
+    }
+
+    fn tuple(&mut self, iface: &Interface, tuple: &Tuple) -> String {
+        let types = tuple
+            .types
+            .iter()
+            .map(|ty| self.ty(iface, ty))
+            .collect::<Vec<_>>();
+        format!("[{}]", types.join(", "))
+    }
+
+    fn option(&mut self, iface: &Interface, ty: &Type) -> String {
+        let payload = self.ty(iface, ty);
+        match nullable(iface, ty) {
+            Nullable::Yes => format!("{payload}?"),
+            Nullable::NotInWebIdl => format!("any /* {payload}? */"),
+            // Like the `js` bindings, `option<option<T>>` is tagged, since
+            // `null` would be ambiguous.
+            Nullable::Ambiguous => {
+                self.needs_option = true;
+                format!("Option /* <{payload}> */")
+            }
+        }
+    }
+
+    /// Emits the dictionary of a type whose values are a case and its
+    /// payload, with the tag given by `tag` and the payloads in `cases`.
+    fn print_tagged(&mut self, name: &str, docs: &Docs, tag: &str, cases: &[(String, String)]) {
+        self.src.push_str(&comment(docs));
+        self.src.push_str(&format!("dictionary {name} {{\n"));
+        self.src.push_str(&format!("required {tag} tag;\n"));
+        self.src.push_str("// The payload of the case, which is:\n");
+        for (case, payload) in cases {
+            self.src.push_str(&format!("// * {case}: {payload}\n"));
+        }
+        self.src.push_str("any val;\n");
+        self.src.push_str("};\n\n");
+    }
+
+    fn operation(&mut self, iface: &Interface, func: &Function) -> String {
+        let mut op = comment(&func.docs);
+        let params = match &func.kind {
+            FunctionKind::Freestanding => &func.params[..],
+            FunctionKind::Static { .. } => {
+                op.push_str("static ");
+                &func.params[..]
+            }
+            // The first parameter of methods is the resource itself.
+            FunctionKind::Method { .. } => &func.params[1..],
+        };
+        let mut result = self.ty(iface, &func.result);
+        if func.is_async {
+            result = format!("Promise<{result}>");
+        }
+        let params = params
+            .iter()
+            .map(|(name, ty)| format!("{} {}", self.ty(iface, ty), name.to_mixed_case()))
+            .collect::<Vec<_>>();
+        op.push_str(&format!(
+            "{result} {}({});\n",
+            func.item_name().to_mixed_case(),
+            params.join(", ")
+        ));
+        op
+    }
+}
+
+/// Returns the typed array the `js` bindings use for lists of `ty`, if any.
+fn array_ty(iface: &Interface, ty: &Type) -> Option<&'static str> {
+    match ty {
+        Type::U8 => Some("Uint8Array"),
+        Type::S8 => Some("Int8Array"),
+        Type::U16 => Some("Uint16Array"),
+        Type::S16 => Some("Int16Array"),
+        Type::U32 => Some("Uint32Array"),
+        Type::S32 => Some("Int32Array"),
+        Type::U64 => Some("BigUint64Array"),
+        Type::S64 => Some("BigInt64Array"),
+        Type::Float32 => Some("Float32Array"),
+        Type::Float64 => Some("Float64Array"),
+        Type::Id(id) => match &iface.types[*id].kind {
+            TypeDefKind::Type(t) => array_ty(iface, t),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+enum Nullable {
+    Yes,
+    /// WebIDL doesn't allow this type to be nullable, such as dictionaries.
+    NotInWebIdl,
+    /// `null` already is a value of this type.
+    Ambiguous,
+}
+
+/// Returns whether `option<ty>` can be `ty?`.
+fn nullable(iface: &Interface, ty: &Type) -> Nullable {
+    match ty {
+        Type::Unit => Nullable::Ambiguous,
+        Type::Id(id) => match &iface.types[*id].kind {
+            TypeDefKind::Type(t) => nullable(iface, t),
+            TypeDefKind::Option(_) => Nullable::Ambiguous,
+            TypeDefKind::Record(_)
+            | TypeDefKind::Variant(_)
+            | TypeDefKind::Union(_)
+            | TypeDefKind::Expected(_)
+            | TypeDefKind::Tuple(_) => Nullable::NotInWebIdl,
+            _ => Nullable::Yes,
+        },
+        _ => Nullable::Yes,
+    }
+}
+
+/// Returns the type of a record field which is optional because `ty` is an
+/// option, if it is one.
+fn optional_field<'a>(iface: &'a Interface, ty: &'a Type) -> Option<&'a Type> {
+    match ty {
+        Type::Id(id) => match &iface.types[*id].kind {
+            TypeDefKind::Type(t) => optional_field(iface, t),
+            TypeDefKind::Option(t) => match nullable(iface, t) {
+                Nullable::Ambiguous => None,
+                _ => Some(t),
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn comment(docs: &Docs) -> String {
+    match &docs.contents {
+        Some(docs) => docs.trim().lines().map(|l| format!("// {l}\n")).collect(),
+        None => String::new(),
+    }
+}
+
+impl Generator for WebIdl {
+    fn preprocess_one(&mut self, iface: &Interface, _dir: Direction) {
+        self.src.push_str(&format!(
+            "// Generated by `wai-bindgen` from the `{}` interface.\n\n",
+            iface.name
+        ));
+    }
+
+    fn type_record(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        record: &Record,
+        docs: &Docs,
+    ) {
+        let mut members = String::new();
+        for field in record.fields.iter() {
+            members.push_str(&comment(&field.docs));
+            let name = field.name.to_mixed_case();
+            match optional_field(iface, &field.ty) {
+                Some(ty) => members.push_str(&format!("{} {name};\n", self.ty(iface, ty))),
+                None => {
+                    members.push_str(&format!("required {} {name};\n", self.ty(iface, &field.ty)))
+                }
+            }
+        }
+        self.src.push_str(&comment(docs));
+        self.src
+            .push_str(&format!("dictionary {} {{\n", name.to_camel_case()));
+        self.src.push_str(&members);
+        self.src.push_str("};\n\n");
+    }
+
+    fn type_tuple(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        tuple: &Tuple,
+        docs: &Docs,
+    ) {
+        let tuple = self.tuple(iface, tuple);
+        self.src.push_str(&comment(docs));
+        self.src.push_str(&format!(
+            "typedef sequence<any> {}; // {tuple}\n\n",
+            name.to_camel_case()
+        ));
+    }
+
+    fn type_flags(&mut self, _: &Interface, _id: TypeId, name: &str, flags: &Flags, docs: &Docs) {
+        let ty = match flags.repr() {
+            FlagsRepr::U8 | FlagsRepr::U16 | FlagsRepr::U32(1) => "unsigned long",
+            FlagsRepr::U32(_) => "bigint",
+        };
+        self.src.push_str(&comment(docs));
+        self.src
+            .push_str("// A set of flags, where each one is a bit:\n");
+        for (i, flag) in flags.flags.iter().enumerate() {
+            self.src
+                .push_str(&format!("// * {}: 1 << {i}\n", flag.name));
+        }
+        self.src
+            .push_str(&format!("typedef {ty} {};\n\n", name.to_camel_case()));
+    }
+
+    fn type_variant(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        variant: &Variant,
+        docs: &Docs,
+    ) {
+        let name = name.to_camel_case();
+        let cases = variant
+            .cases
+            .iter()
+            .map(|case| (format!("\"{}\"", case.name), self.ty(iface, &case.ty)))
+            .collect::<Vec<_>>();
+        self.src.push_str(&format!("enum {name}Tag {{\n"));
+        for case in variant.cases.iter() {
+            self.src.push_str(&comment(&case.docs));
+            self.src.push_str(&format!("\"{}\",\n", case.name));
+        }
+        self.src.push_str("};\n\n");
+        self.print_tagged(&name, docs, &format!("{name}Tag"), &cases);
+    }
+
+    fn type_union(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        union: &Union,
+        docs: &Docs,
+    ) {
+        let cases = union
+            .cases
+            .iter()
+            .enumerate()
+            .map(|(i, case)| (i.to_string(), self.ty(iface, &case.ty)))
+            .collect::<Vec<_>>();
+        self.print_tagged(&name.to_camel_case(), docs, "octet", &cases);
+    }
+
+    fn type_option(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        payload: &Type,
+        docs: &Docs,
+    ) {
+        let ty = self.option(iface, payload);
+        self.src.push_str(&comment(docs));
+        self.src
+            .push_str(&format!("typedef {ty} {};\n\n", name.to_camel_case()));
+    }
+
+    fn type_expected(
+        &mut self,
+        iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        expected: &Expected,
+        docs: &Docs,
+    ) {
+        self.needs_result = true;
+        let cases = [
+            ("\"ok\"".to_string(), self.ty(iface, &expected.ok)),
+            ("\"err\"".to_string(), self.ty(iface, &expected.err)),
+        ];
+        self.print_tagged(&name.to_camel_case(), docs, "ResultTag", &cases);
+    }
+
+    fn type_enum(&mut self, _: &Interface, _id: TypeId, name: &str, enum_: &Enum, docs: &Docs) {
+        self.src.push_str(&comment(docs));
+        self.src
+            .push_str(&format!("enum {} {{\n", name.to_camel_case()));
+        for case in enum_.cases.iter() {
+            self.src.push_str(&comment(&case.docs));
+            self.src.push_str(&format!("\"{}\",\n", case.name));
+        }
+        self.src.push_str("};\n\n");
+    }
+
+    fn type_resource(&mut self, _: &Interface, ty: ResourceId) {
+        self.methods.entry(ty).or_default();
+    }
+
+    fn type_alias(&mut self, iface: &Interface, _id: TypeId, name: &str, ty: &Type, docs: &Docs) {
+        let ty = self.ty(iface, ty);
+        self.src.push_str(&comment(docs));
+        self.src
+            .push_str(&format!("typedef {ty} {};\n\n", name.to_camel_case()));
+    }
+
+    fn type_list(&mut self, iface: &Interface, _id: TypeId, name: &str, ty: &Type, docs: &Docs) {
+        let ty = self.list(iface, ty);
+        self.src.push_str(&comment(docs));
+        self.src
+            .push_str(&format!("typedef {ty} {};\n\n", name.to_camel_case()));
+    }
+
+    fn type_builtin(&mut self, iface: &Interface, id: TypeId, name: &str, ty: &Type, docs: &Docs) {
+        self.type_alias(iface, id, name, ty, docs);
+    }
+
+    fn import(&mut self, iface: &Interface, func: &Function) {
+        self.export(iface, func);
+    }
+
+    fn export(&mut self, iface: &Interface, func: &Function) {
+        let op = self.operation(iface, func);
+        match &func.kind {
+            FunctionKind::Freestanding => self.funcs.push(op),
+            FunctionKind::Static { resource, .. } | FunctionKind::Method { resource, .. } => {
+                self.methods.entry(*resource).or_default().push(op)
+            }
+        }
+    }
+
+    fn finish_one(&mut self, iface: &Interface, files: &mut Files) {
+        let mut src = mem::take(&mut self.src);
+
+        if mem::take(&mut self.needs_result) {
+            src.push_str("enum ResultTag { \"ok\", \"err\" };\n\n");
+            src.push_str("// The result of an operation which can fail.\n");
+            src.push_str("dictionary Result {\n");
+            src.push_str("required ResultTag tag;\n");
+            src.push_str("// The value, or the error if the tag is `err`.\n");
+            src.push_str("any val;\n");
+            src.push_str("};\n\n");
+        }
+        if mem::take(&mut self.needs_option) {
+            src.push_str("enum OptionTag { \"none\", \"some\" };\n\n");
+            src.push_str("// An option whose value can be `null` itself.\n");
+            src.push_str("dictionary Option {\n");
+            src.push_str("required OptionTag tag;\n");
+            src.push_str("any val;\n");
+            src.push_str("};\n\n");
+        }
+
+        let mut methods = mem::take(&mut self.methods);
+        for (id, resource) in iface.resources.iter() {
+            let methods = match methods.remove(&id) {
+                Some(methods) => methods,
+                None => continue,
+            };
+            src.push_str(&comment(&resource.docs));
+            src.push_str("[Exposed=*]\n");
+            src.push_str(&format!("interface {} {{\n", resource.name.to_camel_case()));
+            for method in methods {
+                src.push_str(&method);
+            }
+            src.push_str("};\n\n");
+        }
+
+        src.push_str("[Exposed=*]\n");
+        src.push_str(&format!("interface {} {{\n", iface.name.to_camel_case()));
+        for func in mem::take(&mut self.funcs) {
+            src.push_str(&func);
+        }
+        src.push_str("};\n");
+
+        files.push_for_interface(&iface.name.to_kebab_case(), "webidl", src.as_bytes());
+    }
+}
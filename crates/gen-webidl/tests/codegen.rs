@@ -0,0 +1,54 @@
+use std::path::Path;
+use wai_bindgen_gen_core::{wai_parser::Interface, Files, Generator};
+
+fn generate(iface: Interface) -> String {
+    let mut files = Files::default();
+    wai_bindgen_gen_webidl::Opts::default()
+        .build()
+        .generate_all(&[], &[iface], &mut files);
+    let (_, contents) = files.iter().next().unwrap();
+    String::from_utf8(contents.to_vec()).unwrap()
+}
+
+#[test]
+fn runtime_interfaces() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/runtime");
+    for entry in std::fs::read_dir(&root).unwrap() {
+        let path = entry.unwrap().path().join("exports.wai");
+        if !path.exists() {
+            continue;
+        }
+        let iface = Interface::parse_file(&path).unwrap();
+        let webidl = generate(iface);
+        assert!(
+            webidl.contains("[Exposed=*]\ninterface Exports {"),
+            "{}",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn resources_and_optional_fields() {
+    let iface = Interface::parse(
+        "api",
+        "
+            record r { a: option<string>, b: list<u8>, c: option<r2> }
+            record r2 { x: float64 }
+            resource blob {
+                static create: func() -> blob
+                size: func() -> u64
+            }
+        ",
+    )
+    .unwrap();
+
+    let webidl = generate(iface);
+    assert!(webidl.contains("  USVString a;\n"), "{webidl}");
+    assert!(webidl.contains("  required Uint8Array b;\n"), "{webidl}");
+    assert!(webidl.contains("  R2 c;\n"), "{webidl}");
+    assert!(
+        webidl.contains("interface Blob {\n  static Blob create();\n  bigint size();\n};"),
+        "{webidl}"
+    );
+}
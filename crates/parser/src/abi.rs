@@ -5,6 +5,13 @@ use crate::{
 };
 use std::mem;
 
+/// The revision of the canonical ABI implemented here.
+///
+/// This is bumped whenever values are lowered or lifted in a way that's
+/// incompatible with bindings generated for an earlier revision. Generated
+/// bindings check it against the `ABI_VERSION` of the runtime crate they use.
+pub const ABI_VERSION: u32 = 1;
+
 /// A raw WebAssembly signature with params and results.
 #[derive(Clone, Debug, Hash, Eq, PartialEq, PartialOrd, Ord)]
 pub struct WasmSignature {
@@ -1883,15 +1890,11 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                         ty: id,
                         name: self.iface.types[id].name.as_deref().unwrap(),
                     });
-                    self.write_fields_to_memory(
-                        &record.fields.iter().map(|f| f.ty).collect::<Vec<_>>(),
-                        addr,
-                        offset,
-                    );
+                    self.write_fields_to_memory(id, record.fields.len(), addr, offset);
                 }
                 TypeDefKind::Tuple(tuple) => {
                     self.emit(&TupleLower { tuple, ty: id });
-                    self.write_fields_to_memory(&tuple.types, addr, offset);
+                    self.write_fields_to_memory(id, tuple.types.len(), addr, offset);
                 }
 
                 TypeDefKind::Flags(f) => {
@@ -1922,6 +1925,7 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                 // to the type's alignment.
                 TypeDefKind::Variant(v) => {
                     self.write_variant_arms_to_memory(
+                        id,
                         offset,
                         addr,
                         v.tag(),
@@ -1936,7 +1940,7 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                 }
 
                 TypeDefKind::Option(t) => {
-                    self.write_variant_arms_to_memory(offset, addr, Int::U8, [&Type::Unit, t]);
+                    self.write_variant_arms_to_memory(id, offset, addr, Int::U8, [&Type::Unit, t]);
                     self.emit(&OptionLower {
                         payload: t,
                         ty: id,
@@ -1945,7 +1949,7 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                 }
 
                 TypeDefKind::Expected(e) => {
-                    self.write_variant_arms_to_memory(offset, addr, Int::U8, [&e.ok, &e.err]);
+                    self.write_variant_arms_to_memory(id, offset, addr, Int::U8, [&e.ok, &e.err]);
                     self.emit(&ExpectedLower {
                         expected: e,
                         ty: id,
@@ -1961,6 +1965,7 @@ impl<'a, B: Bindgen> Generator<'a, B> {
 
                 TypeDefKind::Union(union) => {
                     self.write_variant_arms_to_memory(
+                        id,
                         offset,
                         addr,
                         union.tag(),
@@ -1982,13 +1987,13 @@ impl<'a, B: Bindgen> Generator<'a, B> {
 
     fn write_variant_arms_to_memory<'b>(
         &mut self,
+        id: TypeId,
         offset: i32,
         addr: B::Operand,
         tag: Int,
-        cases: impl IntoIterator<Item = &'b Type> + Clone,
+        cases: impl IntoIterator<Item = &'b Type>,
     ) {
-        let payload_offset =
-            offset + (self.bindgen.sizes().payload_offset(tag, cases.clone()) as i32);
+        let payload_offset = offset + (self.bindgen.sizes().payload_offset_of(id) as i32);
         for (i, ty) in cases.into_iter().enumerate() {
             self.push_block();
             self.emit(&Instruction::VariantPayloadName);
@@ -2013,21 +2018,24 @@ impl<'a, B: Bindgen> Generator<'a, B> {
         self.emit(&Instruction::I32Store { offset });
     }
 
-    fn write_fields_to_memory(&mut self, tys: &[Type], addr: B::Operand, offset: i32) {
+    fn write_fields_to_memory(&mut self, id: TypeId, len: usize, addr: B::Operand, offset: i32) {
         let fields = self
             .stack
-            .drain(self.stack.len() - tys.len()..)
+            .drain(self.stack.len() - len..)
             .collect::<Vec<_>>();
-        for ((field_offset, op), ty) in self
-            .bindgen
-            .sizes()
-            .field_offsets(tys.iter())
-            .into_iter()
-            .zip(fields)
-            .zip(tys)
-        {
+        for (i, op) in fields.into_iter().enumerate() {
+            let ty = self.field_type(id, i);
+            let field_offset = self.bindgen.sizes().fields_of(id)[i];
             self.stack.push(op);
-            self.write_to_memory(ty, addr.clone(), offset + (field_offset as i32));
+            self.write_to_memory(&ty, addr.clone(), offset + (field_offset as i32));
+        }
+    }
+
+    fn field_type(&self, id: TypeId, i: usize) -> Type {
+        match &self.iface.types[id].kind {
+            TypeDefKind::Record(r) => r.fields[i].ty,
+            TypeDefKind::Tuple(t) => t.types[i],
+            _ => unreachable!(),
         }
     }
 
@@ -2064,11 +2072,7 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                 // as we go along, then aggregate all the fields into the
                 // record.
                 TypeDefKind::Record(record) => {
-                    self.read_fields_from_memory(
-                        &record.fields.iter().map(|f| f.ty).collect::<Vec<_>>(),
-                        addr,
-                        offset,
-                    );
+                    self.read_fields_from_memory(id, record.fields.len(), addr, offset);
                     self.emit(&RecordLift {
                         record,
                         ty: id,
@@ -2076,7 +2080,7 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                     });
                 }
                 TypeDefKind::Tuple(tuple) => {
-                    self.read_fields_from_memory(&tuple.types, addr, offset);
+                    self.read_fields_from_memory(id, tuple.types.len(), addr, offset);
                     self.emit(&TupleLift { tuple, ty: id });
                 }
 
@@ -2108,6 +2112,7 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                 // from the corresponding offset if one is available.
                 TypeDefKind::Variant(variant) => {
                     self.read_variant_arms_from_memory(
+                        id,
                         offset,
                         addr,
                         variant.tag(),
@@ -2121,12 +2126,12 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                 }
 
                 TypeDefKind::Option(t) => {
-                    self.read_variant_arms_from_memory(offset, addr, Int::U8, [&Type::Unit, t]);
+                    self.read_variant_arms_from_memory(id, offset, addr, Int::U8, [&Type::Unit, t]);
                     self.emit(&OptionLift { payload: t, ty: id });
                 }
 
                 TypeDefKind::Expected(e) => {
-                    self.read_variant_arms_from_memory(offset, addr, Int::U8, [&e.ok, &e.err]);
+                    self.read_variant_arms_from_memory(id, offset, addr, Int::U8, [&e.ok, &e.err]);
                     self.emit(&ExpectedLift {
                         expected: e,
                         ty: id,
@@ -2141,6 +2146,7 @@ impl<'a, B: Bindgen> Generator<'a, B> {
 
                 TypeDefKind::Union(union) => {
                     self.read_variant_arms_from_memory(
+                        id,
                         offset,
                         addr,
                         union.tag(),
@@ -2161,15 +2167,15 @@ impl<'a, B: Bindgen> Generator<'a, B> {
 
     fn read_variant_arms_from_memory<'b>(
         &mut self,
+        id: TypeId,
         offset: i32,
         addr: B::Operand,
         tag: Int,
-        cases: impl IntoIterator<Item = &'b Type> + Clone,
+        cases: impl IntoIterator<Item = &'b Type>,
     ) {
         self.stack.push(addr.clone());
         self.load_intrepr(offset, tag);
-        let payload_offset =
-            offset + (self.bindgen.sizes().payload_offset(tag, cases.clone()) as i32);
+        let payload_offset = offset + (self.bindgen.sizes().payload_offset_of(id) as i32);
         for ty in cases {
             self.push_block();
             self.read_from_memory(ty, addr.clone(), payload_offset);
@@ -2187,9 +2193,11 @@ impl<'a, B: Bindgen> Generator<'a, B> {
         self.lift(ty);
     }
 
-    fn read_fields_from_memory(&mut self, tys: &[Type], addr: B::Operand, offset: i32) {
-        for (field_offset, ty) in self.bindgen.sizes().field_offsets(tys).into_iter().zip(tys) {
-            self.read_from_memory(ty, addr.clone(), offset + (field_offset as i32));
+    fn read_fields_from_memory(&mut self, id: TypeId, len: usize, addr: B::Operand, offset: i32) {
+        for i in 0..len {
+            let ty = self.field_type(id, i);
+            let field_offset = self.bindgen.sizes().fields_of(id)[i];
+            self.read_from_memory(&ty, addr.clone(), offset + (field_offset as i32));
         }
     }
 
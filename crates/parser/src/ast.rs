@@ -1,3 +1,4 @@
+use crate::Cfg;
 use anyhow::Result;
 use lex::{Span, Token, Tokenizer};
 use std::borrow::Cow;
@@ -7,15 +8,23 @@ use std::fmt;
 
 mod lex;
 mod resolve;
+pub mod syntax;
 
 pub use lex::validate_id;
 
 pub struct Ast<'a> {
+    /// The `@!` annotations at the top of the file, which apply to the whole
+    /// interface.
+    annotations: Vec<Annotation<'a>>,
     pub items: Vec<Item<'a>>,
+    /// The span of each item, from its first comment or token to its last
+    /// token.
+    pub spans: Vec<Span>,
 }
 
 pub enum Item<'a> {
     Use(Use<'a>),
+    Include(Include),
     Resource(Resource<'a>),
     TypeDef(TypeDef<'a>),
     Value(Value<'a>),
@@ -50,6 +59,11 @@ pub struct Use<'a> {
     names: Option<Vec<UseName<'a>>>,
 }
 
+pub struct Include {
+    pub span: Span,
+    pub path: String,
+}
+
 struct UseName<'a> {
     name: Id<'a>,
     as_: Option<Id<'a>>,
@@ -60,6 +74,7 @@ pub struct Resource<'a> {
     name: Id<'a>,
     supertype: Option<Id<'a>>,
     values: Vec<(bool, Value<'a>)>,
+    spans: Vec<Span>,
 }
 
 #[derive(Default)]
@@ -125,6 +140,7 @@ struct Flag<'a> {
 struct Variant<'a> {
     span: Span,
     cases: Vec<Case<'a>>,
+    non_exhaustive: bool,
 }
 
 struct Case<'a> {
@@ -136,6 +152,7 @@ struct Case<'a> {
 struct Enum<'a> {
     span: Span,
     cases: Vec<EnumCase<'a>>,
+    non_exhaustive: bool,
 }
 
 struct EnumCase<'a> {
@@ -155,10 +172,55 @@ struct Stream<'a> {
 
 pub struct Value<'a> {
     docs: Docs<'a>,
+    annotations: Vec<Annotation<'a>>,
     name: Id<'a>,
     kind: ValueKind<'a>,
 }
 
+/// An annotation such as `@requires("fs.read")` in front of a value,
+/// `@cfg(host = "wasmer")` in front of any item, `@non-exhaustive` in front
+/// of an enum or variant, or `@range(1, 65535)` in front of a parameter, or
+/// an inner annotation such as `@!string-encoding(utf16)` at the top of a
+/// file.
+struct Annotation<'a> {
+    name: Id<'a>,
+    args: Vec<AnnotationArg<'a>>,
+}
+
+/// An argument of an annotation, a string, an integer or a name optionally
+/// preceded by `key =`.
+struct AnnotationArg<'a> {
+    key: Option<Id<'a>>,
+    span: Span,
+    value: AnnotationValue,
+}
+
+enum AnnotationValue {
+    String(String),
+    Integer(i128),
+    Name(String),
+}
+
+impl AnnotationArg<'_> {
+    fn string(&self) -> Result<&str> {
+        match &self.value {
+            AnnotationValue::String(s) => Ok(s),
+            AnnotationValue::Integer(_) | AnnotationValue::Name(_) => Err(Error {
+                span: self.span,
+                code: "invalid-annotation",
+                msg: "expected a string".to_string(),
+            }
+            .into()),
+        }
+    }
+}
+
+struct Param<'a> {
+    annotations: Vec<Annotation<'a>>,
+    name: Id<'a>,
+    ty: Type<'a>,
+}
+
 struct Union<'a> {
     span: Span,
     cases: Vec<UnionCase<'a>>,
@@ -172,7 +234,7 @@ struct UnionCase<'a> {
 enum ValueKind<'a> {
     Function {
         is_async: bool,
-        params: Vec<(Id<'a>, Type<'a>)>,
+        params: Vec<Param<'a>>,
         result: Type<'a>,
     },
     Global(Type<'a>),
@@ -183,49 +245,120 @@ pub struct Interface<'a> {
     docs: Docs<'a>,
     name: Id<'a>,
     items: Vec<Item<'a>>,
+    spans: Vec<Span>,
 }
 
 impl<'a> Ast<'a> {
-    pub fn parse(input: &'a str) -> Result<Ast<'a>> {
+    /// Parses `input`, leaving out the items whose `@cfg` annotations
+    /// aren't satisfied by `cfg`. Without a `cfg` every item is kept.
+    pub fn parse(input: &'a str, cfg: Option<&Cfg>) -> Result<Ast<'a>> {
         let mut lexer = Tokenizer::new(input)?;
+        let annotations = parse_inner_annotations(&mut lexer)?;
         let mut items = Vec::new();
+        let mut spans = Vec::new();
         while lexer.clone().next()?.is_some() {
+            let start = item_start(&lexer)?;
             let docs = parse_docs(&mut lexer)?;
-            items.push(Item::parse(&mut lexer, docs)?);
+            if let Some(item) = Item::parse(&mut lexer, docs, cfg)? {
+                items.push(item);
+                spans.push(Span {
+                    start,
+                    end: lexer.offset(),
+                });
+            }
         }
-        Ok(Ast { items })
+        Ok(Ast {
+            annotations,
+            items,
+            spans,
+        })
     }
 
     pub fn resolve(
         &self,
         name: &str,
         map: &HashMap<String, crate::Interface>,
+        includes: &HashMap<String, crate::Interface>,
     ) -> Result<crate::Interface> {
         let mut resolver = resolve::Resolver::default();
-        let instance = resolver.resolve(name, &self.items, map)?;
+        let instance = resolver.resolve(name, &self.annotations, &self.items, map, includes)?;
         Ok(instance)
     }
 }
 
 impl<'a> Item<'a> {
-    fn parse(tokens: &mut Tokenizer<'a>, docs: Docs<'a>) -> Result<Item<'a>> {
-        match tokens.clone().next()? {
+    /// Parses an item, returning `None` if its `@cfg` annotations aren't
+    /// satisfied by `cfg`.
+    fn parse(
+        tokens: &mut Tokenizer<'a>,
+        docs: Docs<'a>,
+        cfg: Option<&Cfg>,
+    ) -> Result<Option<Item<'a>>> {
+        let annotations = parse_annotations(tokens)?;
+        let (enabled, annotations) = check_cfg(annotations, cfg)?;
+        let is_value = matches!(
+            tokens.clone().next()?,
+            Some((_, Token::Id)) | Some((_, Token::ExplicitId))
+        ) && !Include::starts(tokens)?;
+        let is_cased = matches!(
+            tokens.clone().next()?,
+            Some((_, Token::Enum)) | Some((_, Token::Variant))
+        );
+        let mut non_exhaustive = false;
+        if !is_value {
+            for annotation in annotations.iter() {
+                if annotation.name.name != "non-exhaustive" {
+                    return Err(Error {
+                        span: annotation.name.span,
+                        code: "unsupported",
+                        msg: "annotations are only allowed on functions".to_string(),
+                    }
+                    .into());
+                }
+                if !is_cased {
+                    return Err(Error {
+                        span: annotation.name.span,
+                        code: "unsupported",
+                        msg: "`@non-exhaustive` is only allowed on enums and variants".to_string(),
+                    }
+                    .into());
+                }
+                if !annotation.args.is_empty() {
+                    return Err(Error {
+                        span: annotation.name.span,
+                        code: "invalid-annotation",
+                        msg: "`@non-exhaustive` takes no arguments".to_string(),
+                    }
+                    .into());
+                }
+                non_exhaustive = true;
+            }
+        }
+        let item = match tokens.clone().next()? {
+            _ if Include::starts(tokens)? => Include::parse(tokens).map(Item::Include),
             Some((_span, Token::Use)) => Use::parse(tokens, docs).map(Item::Use),
             Some((_span, Token::Type)) => TypeDef::parse(tokens, docs).map(Item::TypeDef),
             Some((_span, Token::Flags)) => TypeDef::parse_flags(tokens, docs).map(Item::TypeDef),
-            Some((_span, Token::Enum)) => TypeDef::parse_enum(tokens, docs).map(Item::TypeDef),
+            Some((_span, Token::Enum)) => {
+                TypeDef::parse_enum(tokens, docs, non_exhaustive).map(Item::TypeDef)
+            }
             Some((_span, Token::Variant)) => {
-                TypeDef::parse_variant(tokens, docs).map(Item::TypeDef)
+                TypeDef::parse_variant(tokens, docs, non_exhaustive).map(Item::TypeDef)
             }
             Some((_span, Token::Record)) => TypeDef::parse_record(tokens, docs).map(Item::TypeDef),
             Some((_span, Token::Union)) => TypeDef::parse_union(tokens, docs).map(Item::TypeDef),
-            Some((_span, Token::Resource)) => Resource::parse(tokens, docs).map(Item::Resource),
-            Some((_span, Token::Interface)) => Interface::parse(tokens, docs).map(Item::Interface),
+            Some((_span, Token::Resource)) => {
+                Resource::parse(tokens, docs, cfg).map(Item::Resource)
+            }
+            Some((_span, Token::Interface)) => {
+                Interface::parse(tokens, docs, cfg).map(Item::Interface)
+            }
             Some((_span, Token::Id)) | Some((_span, Token::ExplicitId)) => {
-                Value::parse(tokens, docs).map(Item::Value)
+                Value::parse(tokens, docs, annotations).map(Item::Value)
             }
             other => Err(err_expected(tokens, "`type`, `resource`, or `func`", other).into()),
-        }
+        }?;
+        Ok(if enabled { Some(item) } else { None })
     }
 }
 
@@ -267,6 +400,27 @@ impl<'a> Use<'a> {
     }
 }
 
+impl Include {
+    /// `include` isn't a keyword, so it only starts an item if it's followed
+    /// by a string.
+    fn starts(tokens: &Tokenizer<'_>) -> Result<bool> {
+        let mut clone = tokens.clone();
+        Ok(
+            matches!(clone.next()?, Some((span, Token::Id)) if clone.get_span(span) == "include")
+                && matches!(clone.next()?, Some((_, Token::StrLit))),
+        )
+    }
+
+    fn parse(tokens: &mut Tokenizer<'_>) -> Result<Self> {
+        tokens.expect(Token::Id)?;
+        let span = tokens.expect(Token::StrLit)?;
+        Ok(Include {
+            span,
+            path: tokens.parse_str(span),
+        })
+    }
+}
+
 impl<'a> TypeDef<'a> {
     fn parse(tokens: &mut Tokenizer<'a>, docs: Docs<'a>) -> Result<Self> {
         tokens.expect(Token::Type)?;
@@ -312,7 +466,11 @@ impl<'a> TypeDef<'a> {
         Ok(TypeDef { docs, name, ty })
     }
 
-    fn parse_variant(tokens: &mut Tokenizer<'a>, docs: Docs<'a>) -> Result<Self> {
+    fn parse_variant(
+        tokens: &mut Tokenizer<'a>,
+        docs: Docs<'a>,
+        non_exhaustive: bool,
+    ) -> Result<Self> {
         tokens.expect(Token::Variant)?;
         let name = parse_id(tokens)?;
         let ty = Type::Variant(Variant {
@@ -333,6 +491,7 @@ impl<'a> TypeDef<'a> {
                     Ok(Case { docs, name, ty })
                 },
             )?,
+            non_exhaustive,
         });
         Ok(TypeDef { docs, name, ty })
     }
@@ -355,7 +514,11 @@ impl<'a> TypeDef<'a> {
         Ok(TypeDef { docs, name, ty })
     }
 
-    fn parse_enum(tokens: &mut Tokenizer<'a>, docs: Docs<'a>) -> Result<Self> {
+    fn parse_enum(
+        tokens: &mut Tokenizer<'a>,
+        docs: Docs<'a>,
+        non_exhaustive: bool,
+    ) -> Result<Self> {
         tokens.expect(Token::Enum)?;
         let name = parse_id(tokens)?;
         let ty = Type::Enum(Enum {
@@ -369,13 +532,14 @@ impl<'a> TypeDef<'a> {
                     Ok(EnumCase { docs, name })
                 },
             )?,
+            non_exhaustive,
         });
         Ok(TypeDef { docs, name, ty })
     }
 }
 
 impl<'a> Resource<'a> {
-    fn parse(tokens: &mut Tokenizer<'a>, docs: Docs<'a>) -> Result<Self> {
+    fn parse(tokens: &mut Tokenizer<'a>, docs: Docs<'a>, cfg: Option<&Cfg>) -> Result<Self> {
         tokens.expect(Token::Resource)?;
         let name = parse_id(tokens)?;
         let supertype = if tokens.eat(Token::Implements)? {
@@ -384,14 +548,24 @@ impl<'a> Resource<'a> {
             None
         };
         let mut values = Vec::new();
+        let mut spans = Vec::new();
         if tokens.eat(Token::LeftBrace)? {
             loop {
+                let start = item_start(tokens)?;
                 let docs = parse_docs(tokens)?;
                 if tokens.eat(Token::RightBrace)? {
                     break;
                 }
+                let (enabled, annotations) = check_cfg(parse_annotations(tokens)?, cfg)?;
                 let statik = tokens.eat(Token::Static)?;
-                values.push((statik, Value::parse(tokens, docs)?));
+                let value = Value::parse(tokens, docs, annotations)?;
+                if enabled {
+                    values.push((statik, value));
+                    spans.push(Span {
+                        start,
+                        end: tokens.offset(),
+                    });
+                }
             }
         }
         Ok(Resource {
@@ -399,12 +573,17 @@ impl<'a> Resource<'a> {
             name,
             supertype,
             values,
+            spans,
         })
     }
 }
 
 impl<'a> Value<'a> {
-    fn parse(tokens: &mut Tokenizer<'a>, docs: Docs<'a>) -> Result<Self> {
+    fn parse(
+        tokens: &mut Tokenizer<'a>,
+        docs: Docs<'a>,
+        annotations: Vec<Annotation<'a>>,
+    ) -> Result<Self> {
         let name = parse_id(tokens)?;
         tokens.expect(Token::Colon)?;
 
@@ -416,7 +595,12 @@ impl<'a> Value<'a> {
         } else {
             ValueKind::Global(Type::parse(tokens)?)
         };
-        return Ok(Value { docs, name, kind });
+        return Ok(Value {
+            docs,
+            annotations,
+            name,
+            kind,
+        });
 
         fn parse_func<'a>(tokens: &mut Tokenizer<'a>, is_async: bool) -> Result<ValueKind<'a>> {
             let params = parse_list(
@@ -424,10 +608,15 @@ impl<'a> Value<'a> {
                 Token::LeftParen,
                 Token::RightParen,
                 |_docs, tokens| {
+                    let annotations = parse_annotations(tokens)?;
                     let name = parse_id(tokens)?;
                     tokens.expect(Token::Colon)?;
                     let ty = Type::parse(tokens)?;
-                    Ok((name, ty))
+                    Ok(Param {
+                        annotations,
+                        name,
+                        ty,
+                    })
                 },
             )?;
             let result = if tokens.eat(Token::RArrow)? {
@@ -444,6 +633,119 @@ impl<'a> Value<'a> {
     }
 }
 
+fn parse_annotations<'a>(tokens: &mut Tokenizer<'a>) -> Result<Vec<Annotation<'a>>> {
+    let mut annotations = Vec::new();
+    while tokens.eat(Token::At)? {
+        annotations.push(parse_annotation(tokens)?);
+    }
+    Ok(annotations)
+}
+
+/// Parses the `@!` annotations at the top of a file.
+fn parse_inner_annotations<'a>(tokens: &mut Tokenizer<'a>) -> Result<Vec<Annotation<'a>>> {
+    let mut annotations = Vec::new();
+    loop {
+        let mut clone = tokens.clone();
+        if !clone.eat(Token::At)? || !clone.eat(Token::Bang)? {
+            break;
+        }
+        *tokens = clone;
+        annotations.push(parse_annotation(tokens)?);
+    }
+    Ok(annotations)
+}
+
+/// Parses an annotation after its `@` or `@!`.
+fn parse_annotation<'a>(tokens: &mut Tokenizer<'a>) -> Result<Annotation<'a>> {
+    let name = parse_id(tokens)?;
+    let mut args = Vec::new();
+    if tokens.eat(Token::LeftParen)? {
+        loop {
+            if tokens.eat(Token::RightParen)? {
+                break;
+            }
+            let mut clone = tokens.clone();
+            let key = match (clone.next()?, clone.next()?) {
+                (Some((_, Token::Id)), Some((_, Token::Equals)))
+                | (Some((_, Token::ExplicitId)), Some((_, Token::Equals))) => {
+                    let key = parse_id(tokens)?;
+                    tokens.expect(Token::Equals)?;
+                    Some(key)
+                }
+                _ => None,
+            };
+            let (span, value) = match tokens.next()? {
+                Some((span, Token::StrLit)) => {
+                    (span, AnnotationValue::String(tokens.parse_str(span)))
+                }
+                Some((span, Token::Id)) => (span, AnnotationValue::Name(tokens.parse_id(span)?)),
+                Some((span, Token::Integer)) => match tokens.get_span(span).parse() {
+                    Ok(n) => (span, AnnotationValue::Integer(n)),
+                    Err(_) => {
+                        return Err(Error {
+                            span,
+                            code: "invalid-annotation",
+                            msg: "integer is too large".to_string(),
+                        }
+                        .into())
+                    }
+                },
+                other => {
+                    return Err(err_expected(tokens, "a string, integer or name", other).into())
+                }
+            };
+            args.push(AnnotationArg { key, span, value });
+            if !tokens.eat(Token::Comma)? {
+                tokens.expect(Token::RightParen)?;
+                break;
+            }
+        }
+    }
+    Ok(Annotation { name, args })
+}
+
+/// Checks the `@cfg` annotations among `annotations` against `cfg`,
+/// returning whether they're satisfied along with the other annotations.
+fn check_cfg<'a>(
+    annotations: Vec<Annotation<'a>>,
+    cfg: Option<&Cfg>,
+) -> Result<(bool, Vec<Annotation<'a>>)> {
+    let mut enabled = true;
+    let mut rest = Vec::new();
+    for annotation in annotations {
+        if annotation.name.name != "cfg" {
+            rest.push(annotation);
+            continue;
+        }
+        if annotation.args.is_empty() {
+            return Err(Error {
+                span: annotation.name.span,
+                code: "invalid-annotation",
+                msg: "`@cfg` needs at least one `key = \"value\"` setting".to_string(),
+            }
+            .into());
+        }
+        let mut any = false;
+        for arg in annotation.args.iter() {
+            let key = match &arg.key {
+                Some(key) => key,
+                None => {
+                    return Err(Error {
+                        span: arg.span,
+                        code: "invalid-annotation",
+                        msg: "expected a `key = \"value\"` setting".to_string(),
+                    }
+                    .into())
+                }
+            };
+            let value = arg.string()?;
+            any |= cfg.map_or(true, |cfg| cfg.is_set(&key.name, value));
+        }
+        enabled &= any;
+    }
+    Ok((enabled, rest))
+}
+
 fn parse_id<'a>(tokens: &mut Tokenizer<'a>) -> Result<Id<'a>> {
     match tokens.next()? {
         Some((span, Token::Id)) => Ok(Id {
@@ -458,6 +760,19 @@ fn parse_id<'a>(tokens: &mut Tokenizer<'a>) -> Result<Id<'a>> {
     }
 }
 
+/// Returns where the item at the front of `tokens` starts, which is at its
+/// first comment, doc comment or not, or otherwise its first token.
+fn item_start(tokens: &Tokenizer<'_>) -> Result<u32> {
+    let mut clone = tokens.clone();
+    loop {
+        match clone.next_raw()? {
+            Some((_, Token::Whitespace)) => {}
+            Some((span, _)) => return Ok(span.start),
+            None => return Ok(clone.offset()),
+        }
+    }
+}
+
 fn parse_docs<'a>(tokens: &mut Tokenizer<'a>) -> Result<Docs<'a>> {
     let mut docs = Docs::default();
     let mut clone = tokens.clone();
@@ -472,9 +787,29 @@ fn parse_docs<'a>(tokens: &mut Tokenizer<'a>) -> Result<Docs<'a>> {
     Ok(docs)
 }
 
+/// Maximum nesting of anonymous types such as `list<list<...>>`, to bound the
+/// recursion of the parser and everything consuming its output.
+const MAX_TYPE_DEPTH: u32 = 100;
+
 impl<'a> Type<'a> {
     fn parse(tokens: &mut Tokenizer<'a>) -> Result<Self> {
-        match tokens.next()? {
+        Type::parse_nested(tokens, 0)
+    }
+
+    fn parse_nested(tokens: &mut Tokenizer<'a>, depth: u32) -> Result<Self> {
+        let next = tokens.next()?;
+        if let Some((span, _)) = next {
+            if depth > MAX_TYPE_DEPTH {
+                return Err(Error {
+                    span,
+                    code: "nested-too-deeply",
+                    msg: "type is nested too deeply".to_string(),
+                }
+                .into());
+            }
+        }
+        let inner = |tokens: &mut Tokenizer<'a>| Type::parse_nested(tokens, depth + 1);
+        match next {
             Some((_span, Token::U8)) => Ok(Type::U8),
             Some((_span, Token::U16)) => Ok(Type::U16),
             Some((_span, Token::U32)) => Ok(Type::U32),
@@ -497,7 +832,7 @@ impl<'a> Type<'a> {
                     tokens,
                     Token::LessThan,
                     Token::GreaterThan,
-                    |_docs, tokens| Type::parse(tokens),
+                    |_docs, tokens| inner(tokens),
                 )?;
                 Ok(Type::Tuple(types))
             }
@@ -509,7 +844,7 @@ impl<'a> Type<'a> {
             // list<T>
             Some((_span, Token::List)) => {
                 tokens.expect(Token::LessThan)?;
-                let ty = Type::parse(tokens)?;
+                let ty = inner(tokens)?;
                 tokens.expect(Token::GreaterThan)?;
                 Ok(Type::List(Box::new(ty)))
             }
@@ -517,7 +852,7 @@ impl<'a> Type<'a> {
             // option<T>
             Some((_span, Token::Option_)) => {
                 tokens.expect(Token::LessThan)?;
-                let ty = Type::parse(tokens)?;
+                let ty = inner(tokens)?;
                 tokens.expect(Token::GreaterThan)?;
                 Ok(Type::Option(Box::new(ty)))
             }
@@ -525,9 +860,9 @@ impl<'a> Type<'a> {
             // expected<T, E>
             Some((_span, Token::Expected)) => {
                 tokens.expect(Token::LessThan)?;
-                let ok = Box::new(Type::parse(tokens)?);
+                let ok = Box::new(inner(tokens)?);
                 tokens.expect(Token::Comma)?;
-                let err = Box::new(Type::parse(tokens)?);
+                let err = Box::new(inner(tokens)?);
                 tokens.expect(Token::GreaterThan)?;
                 Ok(Type::Expected(Expected { ok, err }))
             }
@@ -535,7 +870,7 @@ impl<'a> Type<'a> {
             // future<T>
             Some((_span, Token::Future)) => {
                 tokens.expect(Token::LessThan)?;
-                let ty = Box::new(Type::parse(tokens)?);
+                let ty = Box::new(inner(tokens)?);
                 tokens.expect(Token::GreaterThan)?;
                 Ok(Type::Future(ty))
             }
@@ -543,13 +878,34 @@ impl<'a> Type<'a> {
             // stream<T, Z>
             Some((_span, Token::Stream)) => {
                 tokens.expect(Token::LessThan)?;
-                let element = Box::new(Type::parse(tokens)?);
+                let element = Box::new(inner(tokens)?);
                 tokens.expect(Token::Comma)?;
-                let end = Box::new(Type::parse(tokens)?);
+                let end = Box::new(inner(tokens)?);
                 tokens.expect(Token::GreaterThan)?;
                 Ok(Type::Stream(Stream { element, end }))
             }
 
+            // result<T, E>, as upstream WIT spells `expected<T, E>`, where
+            // either type may be `_` for `unit`. `result` isn't a keyword,
+            // so it's only this if it's followed by `<`.
+            Some((span, Token::Id))
+                if tokens.get_span(span) == "result" && tokens.clone().eat(Token::LessThan)? =>
+            {
+                let inner_or_unit = |tokens: &mut Tokenizer<'a>| {
+                    if tokens.eat(Token::Underscore)? {
+                        Ok(Type::Unit)
+                    } else {
+                        inner(tokens)
+                    }
+                };
+                tokens.expect(Token::LessThan)?;
+                let ok = Box::new(inner_or_unit(tokens)?);
+                tokens.expect(Token::Comma)?;
+                let err = Box::new(inner_or_unit(tokens)?);
+                tokens.expect(Token::GreaterThan)?;
+                Ok(Type::Expected(Expected { ok, err }))
+            }
+
             // `foo`
             Some((span, Token::Id)) => Ok(Type::Name(Id {
                 name: tokens.parse_id(span)?.into(),
@@ -567,19 +923,32 @@ impl<'a> Type<'a> {
 }
 
 impl<'a> Interface<'a> {
-    fn parse(tokens: &mut Tokenizer<'a>, docs: Docs<'a>) -> Result<Self> {
+    fn parse(tokens: &mut Tokenizer<'a>, docs: Docs<'a>, cfg: Option<&Cfg>) -> Result<Self> {
         tokens.expect(Token::Interface)?;
         let name = parse_id(tokens)?;
         tokens.expect(Token::LeftBrace)?;
         let mut items = Vec::new();
+        let mut spans = Vec::new();
         loop {
+            let start = item_start(tokens)?;
             let docs = parse_docs(tokens)?;
             if tokens.eat(Token::RightBrace)? {
                 break;
             }
-            items.push(Item::parse(tokens, docs)?);
+            if let Some(item) = Item::parse(tokens, docs, cfg)? {
+                items.push(item);
+                spans.push(Span {
+                    start,
+                    end: tokens.offset(),
+                });
+            }
         }
-        Ok(Interface { docs, name, items })
+        Ok(Interface {
+            docs,
+            name,
+            items,
+            spans,
+        })
     }
 }
 
@@ -621,6 +990,7 @@ fn err_expected(
     match found {
         Some((span, token)) => Error {
             span,
+            code: "unexpected-token",
             msg: format!("expected {}, found {}", expected, token.describe()),
         },
         None => Error {
@@ -628,6 +998,7 @@ fn err_expected(
                 start: u32::try_from(tokens.input().len()).unwrap(),
                 end: u32::try_from(tokens.input().len()).unwrap(),
             },
+            code: "unexpected-token",
             msg: format!("expected {}, found eof", expected),
         },
     }
@@ -636,6 +1007,7 @@ fn err_expected(
 #[derive(Debug)]
 struct Error {
     span: Span,
+    code: &'static str,
     msg: String,
 }
 
@@ -647,19 +1019,73 @@ impl fmt::Display for Error {
 
 impl std::error::Error for Error {}
 
+/// An error in a `*.wai` file along with where in the file it happened.
+///
+/// Errors from [`Interface::parse`](crate::Interface::parse) and friends can
+/// be downcast to this when they point into the source. Displaying it renders
+/// the message together with the offending line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The name of the file the error is in.
+    pub file: String,
+    /// The byte offsets within the file that the error covers.
+    pub span: std::ops::Range<usize>,
+    /// The 1-based line the error starts on.
+    pub line: usize,
+    /// The 1-based column, in bytes, the error starts at.
+    pub column: usize,
+    /// A short kebab-case identifier for the kind of error, such as
+    /// `undefined-name`, which stays the same when the message is reworded.
+    pub code: &'static str,
+    /// The error message, without the source snippet.
+    pub message: String,
+    rendered: String,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(
+        start: usize,
+        end: Option<usize>,
+        file: &str,
+        input: &str,
+        code: &'static str,
+        message: String,
+    ) -> Diagnostic {
+        let (line, col) = linecol_in(start, input);
+        Diagnostic {
+            file: file.to_string(),
+            span: start..end.unwrap_or(start),
+            line: line + 1,
+            column: col + 1,
+            code,
+            rendered: highlight_err(start, end, file, input, &message),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.rendered.fmt(f)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
 pub fn rewrite_error(err: &mut anyhow::Error, file: &str, contents: &str) {
     let parse = match err.downcast_mut::<Error>() {
         Some(err) => err,
         None => return lex::rewrite_error(err, file, contents),
     };
-    let msg = highlight_err(
+    *err = Diagnostic::new(
         parse.span.start as usize,
         Some(parse.span.end as usize),
         file,
         contents,
-        &parse.msg,
-    );
-    *err = anyhow::anyhow!("{}", msg);
+        parse.code,
+        parse.msg.clone(),
+    )
+    .into();
 }
 
 fn highlight_err(
@@ -693,19 +1119,102 @@ fn highlight_err(
             }
         }
     }
-    return msg;
+    msg
+}
 
-    fn linecol_in(pos: usize, text: &str) -> (usize, usize) {
-        let mut cur = 0;
-        // Use split_terminator instead of lines so that if there is a `\r`,
-        // it is included in the offset calculation. The `+1` values below
-        // account for the `\n`.
-        for (i, line) in text.split_terminator('\n').enumerate() {
-            if cur + line.len() + 1 > pos {
-                return (i, pos - cur);
-            }
-            cur += line.len() + 1;
+fn linecol_in(pos: usize, text: &str) -> (usize, usize) {
+    let mut cur = 0;
+    // Use split_terminator instead of lines so that if there is a `\r`,
+    // it is included in the offset calculation. The `+1` values below
+    // account for the `\n`.
+    for (i, line) in text.split_terminator('\n').enumerate() {
+        if cur + line.len() + 1 > pos {
+            return (i, pos - cur);
         }
-        (text.lines().count(), 0)
+        cur += line.len() + 1;
     }
+    (text.lines().count(), 0)
+}
+
+#[test]
+fn test_diagnostic_location() {
+    let err = crate::Interface::parse("foo.wai", "record a {}\n\nfoo: func() -> b").unwrap_err();
+    let diag = err.downcast_ref::<Diagnostic>().unwrap();
+    assert_eq!(diag.file, "foo.wai");
+    assert_eq!(diag.span, 28..29);
+    assert_eq!((diag.line, diag.column), (3, 16));
+    assert_eq!(diag.code, "undefined-name");
+    assert_eq!(diag.message, "no type named `b`");
+    assert!(diag
+        .to_string()
+        .starts_with("no type named `b`\n     --> foo.wai:3:16"));
+
+    let err = crate::Interface::parse("foo.wai", "type x = $").unwrap_err();
+    let diag = err.downcast_ref::<Diagnostic>().unwrap();
+    assert_eq!((diag.line, diag.column), (1, 10));
+    assert_eq!(diag.code, "invalid-character");
+}
+
+#[test]
+fn test_doc_examples() {
+    let iface = crate::Interface::parse(
+        "foo",
+        "/// Adds one.\n///\n/// ```rust\n/// if add_one(1) == 2 {\n///     println!(\"ok\");\n/// }\n/// ```\n///\n/// ```\n/// add-one(1)\n/// ```\nadd-one: func(x: u32) -> u32\n",
+    )
+    .unwrap();
+    let docs = &iface.functions[0].docs;
+    assert_eq!(docs.contents.as_deref(), Some("Adds one.\n"));
+    assert_eq!(docs.examples.len(), 2);
+    assert_eq!(docs.examples[0].lang.as_deref(), Some("rust"));
+    assert_eq!(
+        docs.examples[0].code,
+        "if add_one(1) == 2 {\n    println!(\"ok\");\n}\n"
+    );
+    assert!(docs.examples[0].is_in(&["rust"]));
+    assert_eq!(docs.examples[1].lang, None);
+    assert_eq!(docs.examples[1].code, "add-one(1)\n");
+}
+
+#[test]
+fn test_cfg() {
+    let input = "\
+@cfg(host = \"wasmer\")
+type id = u32
+@cfg(host = \"js\")
+type id = string
+
+resource gpu {
+  @cfg(feature = \"gpu\")
+  static new: func() -> gpu
+}
+
+@cfg(host = \"wasmer\", host = \"js\")
+@cfg(feature = \"gpu\")
+draw: func(h: id) -> gpu
+";
+    let parse = |cfg: &Cfg| {
+        crate::Interface::parse_with_cfg("foo.wai", input, cfg, |_| unreachable!()).unwrap()
+    };
+
+    let iface = parse(&Cfg::default());
+    assert!(!iface.type_lookup.contains_key("id"));
+    assert!(iface.functions.is_empty());
+
+    let mut cfg = Cfg::new();
+    cfg.set("host", "js");
+    let iface = parse(&cfg);
+    let id = iface.type_lookup["id"];
+    assert_eq!(
+        iface.types[id].kind,
+        crate::TypeDefKind::Type(crate::Type::String)
+    );
+    assert!(iface.functions.is_empty());
+
+    cfg.set("feature", "gpu");
+    let names = parse(&cfg)
+        .functions
+        .iter()
+        .map(|f| f.name.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["gpu::new", "draw"]);
 }
@@ -45,6 +45,8 @@ pub enum Token {
     GreaterThan,
     RArrow,
     Star,
+    At,
+    Bang,
 
     Use,
     Type,
@@ -86,6 +88,8 @@ pub enum Token {
 
     Id,
     ExplicitId,
+    StrLit,
+    Integer,
 }
 
 #[derive(Eq, PartialEq, Debug)]
@@ -128,6 +132,15 @@ impl<'a> Tokenizer<'a> {
         self.input
     }
 
+    /// Returns the offset just past the last character consumed.
+    pub fn offset(&self) -> u32 {
+        let offset = match self.chars.clone().next() {
+            Some((i, _)) => i,
+            None => self.input.len(),
+        };
+        u32::try_from(offset).unwrap()
+    }
+
     pub fn get_span(&self, span: Span) -> &'a str {
         &self.input[span.start as usize..span.end as usize]
     }
@@ -145,6 +158,22 @@ impl<'a> Tokenizer<'a> {
         Ok(id_part.to_owned())
     }
 
+    /// Returns the contents of the string literal at `span`, without its
+    /// quotes and with escapes resolved.
+    pub fn parse_str(&self, span: Span) -> String {
+        let token = self.get_span(span);
+        let contents = &token[1..token.len() - 1];
+        let mut ret = String::with_capacity(contents.len());
+        let mut chars = contents.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\\' => ret.extend(chars.next()),
+                ch => ret.push(ch),
+            }
+        }
+        ret
+    }
+
     pub fn next(&mut self) -> Result<Option<(Span, Token)>, Error> {
         loop {
             match self.next_raw()? {
@@ -204,13 +233,40 @@ impl<'a> Tokenizer<'a> {
             '<' => LessThan,
             '>' => GreaterThan,
             '*' => Star,
+            '@' => At,
+            '!' => Bang,
+            '"' => {
+                loop {
+                    match self.chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match self.chars.next() {
+                            Some((_, '"')) | Some((_, '\\')) => {}
+                            Some((i, ch)) => return Err(Error::InvalidEscape(i, ch)),
+                            None => return Err(Error::UnterminatedString(start)),
+                        },
+                        Some((i, '\n')) => return Err(Error::NewlineInString(i)),
+                        Some((i, ch)) if ch.is_control() => {
+                            return Err(Error::InvalidCharInString(i, ch))
+                        }
+                        Some(_) => {}
+                        None => return Err(Error::UnterminatedString(start)),
+                    }
+                }
+                StrLit
+            }
             '-' => {
                 if self.eatc('>') {
                     RArrow
+                } else if self.eat_digits() {
+                    Integer
                 } else {
                     return Err(Error::Unexpected(start, '-'));
                 }
             }
+            '0'..='9' => {
+                self.eat_digits();
+                Integer
+            }
             '%' => {
                 let mut iter = self.chars.clone();
                 if let Some((_, ch)) = iter.next() {
@@ -343,6 +399,17 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
+    /// Eats a run of ASCII digits, returning whether there were any.
+    fn eat_digits(&mut self) -> bool {
+        let mut any = false;
+        let mut iter = self.chars.clone();
+        while let Some((_, '0'..='9')) = iter.next() {
+            self.chars = iter.clone();
+            any = true;
+        }
+        any
+    }
+
     fn eatc(&mut self, ch: char) -> bool {
         let mut iter = self.chars.clone();
         match iter.next() {
@@ -535,6 +602,10 @@ impl Token {
             ExplicitId => "an '%' identifier",
             RArrow => "`->`",
             Star => "`*`",
+            At => "'@'",
+            Bang => "'!'",
+            StrLit => "a string",
+            Integer => "an integer",
             As => "keyword `as`",
             From_ => "keyword `from`",
             Static => "keyword `static`",
@@ -585,8 +656,17 @@ pub fn rewrite_error(err: &mut anyhow::Error, file: &str, contents: &str) {
         | Error::IdPartEmpty(at)
         | Error::InvalidEscape(at, _) => *at,
     };
-    let msg = super::highlight_err(pos, None, file, contents, lex);
-    *err = anyhow::anyhow!("{}", msg);
+    let code = match lex {
+        Error::Unexpected(..) | Error::InvalidCharInString(..) | Error::InvalidCharInId(..) => {
+            "invalid-character"
+        }
+        Error::IdNotSSNFC(_) | Error::IdPartEmpty(_) => "invalid-identifier",
+        Error::InvalidEscape(..) => "invalid-escape",
+        Error::UnterminatedComment(_) => "unterminated-comment",
+        Error::UnterminatedString(_) | Error::NewlineInString(_) => "unterminated-string",
+        Error::Wanted { .. } => "unexpected-token",
+    };
+    *err = super::Diagnostic::new(pos, None, file, contents, code, lex.to_string()).into();
 }
 
 #[test]
@@ -696,6 +776,30 @@ fn test_tokenizer() {
         ]
     );
 
+    assert_eq!(
+        collect("@a(\"b.c\")").unwrap(),
+        vec![
+            Token::At,
+            Token::Id,
+            Token::LeftParen,
+            Token::StrLit,
+            Token::RightParen
+        ]
+    );
+    assert_eq!(
+        collect("@!a").unwrap(),
+        vec![Token::At, Token::Bang, Token::Id]
+    );
+    assert_eq!(collect("\"a\\\"b\\\\\"").unwrap(), vec![Token::StrLit]);
+    assert_eq!(
+        collect("0 65535 -12").unwrap(),
+        vec![Token::Integer, Token::Integer, Token::Integer]
+    );
+    assert!(collect("- 1").is_err());
+    assert!(collect("\"a").is_err(), "unterminated string");
+    assert!(collect("\"a\nb\"").is_err(), "newline in string");
+    assert!(collect("\"\\n\"").is_err(), "invalid escape");
+
     assert!(collect("\u{149}").is_err(), "strongly discouraged");
     assert!(collect("\u{673}").is_err(), "strongly discouraged");
     assert!(collect("\u{17a3}").is_err(), "strongly discouraged");
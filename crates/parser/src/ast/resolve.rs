@@ -1,4 +1,4 @@
-use super::{Error, Item, Span, Value, ValueKind};
+use super::{Annotation, AnnotationValue, Error, Item, Param, Span, Value, ValueKind};
 use crate::*;
 use anyhow::Result;
 use std::collections::{HashMap, HashSet};
@@ -11,6 +11,7 @@ pub struct Resolver {
     resource_lookup: HashMap<String, ResourceId>,
     resources_copied: HashMap<(String, ResourceId), ResourceId>,
     types_copied: HashMap<(String, TypeId), TypeId>,
+    included: HashSet<String>,
     resources: Arena<Resource>,
     anon_types: HashMap<Key, TypeId>,
     functions: Vec<Function>,
@@ -36,10 +37,27 @@ impl Resolver {
     pub(super) fn resolve(
         &mut self,
         name: &str,
+        annotations: &[Annotation<'_>],
         fields: &[Item<'_>],
         deps: &HashMap<String, Interface>,
+        includes: &HashMap<String, Interface>,
     ) -> Result<Interface> {
-        // First pull in any names from our dependencies
+        let mut string_encoding = None;
+        for annotation in annotations {
+            if annotation.name.name != "string-encoding" {
+                return Err(Error {
+                    span: annotation.name.span,
+                    code: "unknown-annotation",
+                    msg: format!("unknown annotation `@!{}`", annotation.name.name),
+                }
+                .into());
+            }
+            set_string_encoding(&mut string_encoding, annotation)?;
+        }
+
+        // First pull in everything from included files, and any names from
+        // our dependencies
+        self.process_include(fields, includes)?;
         self.process_use(fields, deps)?;
         // ... then register our own names
         self.register_names(fields)?;
@@ -87,6 +105,7 @@ impl Resolver {
             interfaces: Default::default(),
             functions: mem::take(&mut self.functions),
             globals: mem::take(&mut self.globals),
+            string_encoding,
         })
     }
 
@@ -108,6 +127,7 @@ impl Resolver {
                     None => {
                         return Err(Error {
                             span: name.span,
+                            code: "undefined-name",
                             msg: format!("`{}` not defined in `{}`", name.name, prev),
                         }
                         .into())
@@ -142,6 +162,7 @@ impl Resolver {
                         if !found {
                             return Err(Error {
                                 span: name.name.span,
+                                code: "undefined-name",
                                 msg: "name not defined in submodule".to_string(),
                             }
                             .into());
@@ -165,7 +186,96 @@ impl Resolver {
         Ok(())
     }
 
+    fn process_include(
+        &mut self,
+        fields: &[Item<'_>],
+        includes: &HashMap<String, Interface>,
+    ) -> Result<()> {
+        for field in fields {
+            let i = match field {
+                Item::Include(i) => i,
+                _ => continue,
+            };
+            let dep = &includes[&i.path];
+            self.included.insert(i.path.clone());
+
+            for (id, resource) in dep.resources.iter() {
+                let id = self.copy_resource(&i.path, dep, id);
+                self.define_resource(&resource.name, i.span, id)?;
+            }
+            let mut names = dep.type_lookup.iter().collect::<Vec<_>>();
+            names.sort(); // produce a stable order by which to add names
+            for (name, id) in names {
+                let ty = self.copy_type_def(&i.path, dep, *id);
+                self.define_type(name, i.span, ty)?;
+            }
+
+            for func in dep.functions.iter() {
+                if let FunctionKind::Freestanding = func.kind {
+                    self.define_included_value(&func.name, i.span)?;
+                }
+                let kind = match &func.kind {
+                    FunctionKind::Freestanding => FunctionKind::Freestanding,
+                    FunctionKind::Static { resource, name } => FunctionKind::Static {
+                        resource: self.copy_resource(&i.path, dep, *resource),
+                        name: name.clone(),
+                    },
+                    FunctionKind::Method { resource, name } => FunctionKind::Method {
+                        resource: self.copy_resource(&i.path, dep, *resource),
+                        name: name.clone(),
+                    },
+                };
+                let function = Function {
+                    is_async: func.is_async,
+                    docs: func.docs.clone(),
+                    name: func.name.clone(),
+                    kind,
+                    params: func
+                        .params
+                        .iter()
+                        .map(|(name, ty)| (name.clone(), self.copy_type(&i.path, dep, *ty)))
+                        .collect(),
+                    result: self.copy_type(&i.path, dep, func.result),
+                    requires: func.requires.clone(),
+                    ranges: func.ranges.clone(),
+                    string_encoding: dep.string_encoding_of(func),
+                };
+                self.functions.push(function);
+            }
+            for global in dep.globals.iter() {
+                self.define_included_value(&global.name, i.span)?;
+                let global = Global {
+                    docs: global.docs.clone(),
+                    name: global.name.clone(),
+                    ty: self.copy_type(&i.path, dep, global.ty),
+                };
+                self.globals.push(global);
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that no function or global named `name` was included already.
+    fn define_included_value(&self, name: &str, span: Span) -> Result<()> {
+        if self.has_value(name) {
+            return Err(Error {
+                span,
+                code: "duplicate-definition",
+                msg: format!("{:?} defined twice", name),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    fn has_value(&self, name: &str) -> bool {
+        let is_function =
+            |f: &Function| matches!(f.kind, FunctionKind::Freestanding) && f.name == name;
+        self.functions.iter().any(is_function) || self.globals.iter().any(|g| g.name == name)
+    }
+
     fn copy_resource(&mut self, dep_name: &str, dep: &Interface, r: ResourceId) -> ResourceId {
+        let included = self.included.contains(dep_name);
         let resources = &mut self.resources;
         *self
             .resources_copied
@@ -176,11 +286,11 @@ impl Resolver {
                     docs: r.docs.clone(),
                     name: r.name.clone(),
                     supertype: r.supertype.clone(),
-                    foreign_module: Some(
-                        r.foreign_module
-                            .clone()
-                            .unwrap_or_else(|| dep_name.to_string()),
-                    ),
+                    foreign_module: match &r.foreign_module {
+                        Some(module) => Some(module.clone()),
+                        None if included => None,
+                        None => Some(dep_name.to_string()),
+                    },
                 };
                 resources.alloc(resource)
             })
@@ -195,11 +305,11 @@ impl Resolver {
         let ty = TypeDef {
             docs: ty.docs.clone(),
             name: ty.name.clone(),
-            foreign_module: Some(
-                ty.foreign_module
-                    .clone()
-                    .unwrap_or_else(|| dep_name.to_string()),
-            ),
+            foreign_module: match &ty.foreign_module {
+                Some(module) => Some(module.clone()),
+                None if self.included.contains(dep_name) => None,
+                None => Some(dep_name.to_string()),
+            },
             kind: match &ty.kind {
                 TypeDefKind::Type(t) => TypeDefKind::Type(self.copy_type(dep_name, dep, *t)),
                 TypeDefKind::Record(r) => TypeDefKind::Record(Record {
@@ -231,10 +341,9 @@ impl Resolver {
                             ty: self.copy_type(dep_name, dep, case.ty),
                         })
                         .collect(),
+                    non_exhaustive: v.non_exhaustive,
                 }),
-                TypeDefKind::Enum(e) => TypeDefKind::Enum(Enum {
-                    cases: e.cases.clone(),
-                }),
+                TypeDefKind::Enum(e) => TypeDefKind::Enum(e.clone()),
                 TypeDefKind::List(t) => TypeDefKind::List(self.copy_type(dep_name, dep, *t)),
                 TypeDefKind::Option(t) => TypeDefKind::Option(self.copy_type(dep_name, dep, *t)),
                 TypeDefKind::Expected(e) => TypeDefKind::Expected(Expected {
@@ -308,17 +417,25 @@ impl Resolver {
                     self.define_type(&t.name.name, t.name.span, id)?;
                 }
                 Item::Value(f) => {
-                    if !values.insert(&f.name.name) {
+                    if !values.insert(&f.name.name) || self.has_value(&f.name.name) {
                         return Err(Error {
                             span: f.name.span,
+                            code: "duplicate-definition",
                             msg: format!("{:?} defined twice", f.name.name),
                         }
                         .into());
                     }
                 }
-                Item::Use(_) => {}
+                Item::Use(_) | Item::Include(_) => {}
 
-                Item::Interface(_) => unimplemented!(),
+                Item::Interface(i) => {
+                    return Err(Error {
+                        span: i.name.span,
+                        code: "unsupported",
+                        msg: "nested interfaces are not supported yet".to_string(),
+                    }
+                    .into());
+                }
             }
         }
 
@@ -329,6 +446,7 @@ impl Resolver {
         if self.resource_lookup.insert(name.to_string(), id).is_some() {
             Err(Error {
                 span,
+                code: "duplicate-definition",
                 msg: format!("resource {:?} defined twice", name),
             }
             .into())
@@ -341,6 +459,7 @@ impl Resolver {
         if self.type_lookup.insert(name.to_string(), id).is_some() {
             Err(Error {
                 span,
+                code: "duplicate-definition",
                 msg: format!("type {:?} defined twice", name),
             }
             .into())
@@ -371,6 +490,7 @@ impl Resolver {
                     None => {
                         return Err(Error {
                             span: resource.span,
+                            code: "undefined-name",
                             msg: format!("no resource named `{}`", resource.name),
                         }
                         .into())
@@ -384,6 +504,7 @@ impl Resolver {
                     None => {
                         return Err(Error {
                             span: name.span,
+                            code: "undefined-name",
                             msg: format!("no type named `{}`", name.name),
                         }
                         .into())
@@ -431,6 +552,7 @@ impl Resolver {
                 if variant.cases.is_empty() {
                     return Err(Error {
                         span: variant.span,
+                        code: "empty-type",
                         msg: "empty variant".to_string(),
                     }
                     .into());
@@ -449,12 +571,19 @@ impl Resolver {
                         })
                     })
                     .collect::<Result<Vec<_>>>()?;
-                TypeDefKind::Variant(Variant { cases })
+                if variant.non_exhaustive {
+                    check_unknown_case(variant.cases.iter().map(|c| &c.name))?;
+                }
+                TypeDefKind::Variant(Variant {
+                    cases,
+                    non_exhaustive: variant.non_exhaustive,
+                })
             }
             super::Type::Enum(e) => {
                 if e.cases.is_empty() {
                     return Err(Error {
                         span: e.span,
+                        code: "empty-type",
                         msg: "empty enum".to_string(),
                     }
                     .into());
@@ -469,7 +598,13 @@ impl Resolver {
                         })
                     })
                     .collect::<Result<Vec<_>>>()?;
-                TypeDefKind::Enum(Enum { cases })
+                if e.non_exhaustive {
+                    check_unknown_case(e.cases.iter().map(|c| &c.name))?;
+                }
+                TypeDefKind::Enum(Enum {
+                    cases,
+                    non_exhaustive: e.non_exhaustive,
+                })
             }
             super::Type::Option(ty) => TypeDefKind::Option(self.resolve_type(ty)?),
             super::Type::Expected(e) => TypeDefKind::Expected(Expected {
@@ -480,6 +615,7 @@ impl Resolver {
                 if e.cases.is_empty() {
                     return Err(Error {
                         span: e.span,
+                        code: "empty-type",
                         msg: "empty union".to_string(),
                     }
                     .into());
@@ -552,28 +688,67 @@ impl Resolver {
     }
 
     fn docs(&mut self, doc: &super::Docs<'_>) -> Docs {
-        let mut docs = None;
+        // Each line of the docs as it goes in the contents, along with the
+        // line as it goes in an example, which keeps its indentation.
+        let mut lines = None;
         for doc in doc.docs.iter() {
             // Comments which are not doc-comments are silently ignored
             if let Some(doc) = doc.strip_prefix("///") {
-                let docs = docs.get_or_insert_with(String::new);
-                docs.push_str(doc.trim_start_matches('/').trim());
-                docs.push('\n');
+                let doc = doc.trim_start_matches('/');
+                let code = doc.strip_prefix(' ').unwrap_or(doc).trim_end();
+                lines.get_or_insert_with(Vec::new).push((doc.trim(), code));
             } else if let Some(doc) = doc.strip_prefix("/*") {
                 // We have to strip this before checking if this is a doc
                 // comment to avoid breaking on empty block comments, `/**/`.
                 let doc = doc.strip_suffix("*/").unwrap();
 
                 if let Some(doc) = doc.strip_prefix('*') {
-                    let docs = docs.get_or_insert_with(String::new);
-                    for line in doc.lines() {
-                        docs.push_str(line);
-                        docs.push('\n');
-                    }
+                    let lines = lines.get_or_insert_with(Vec::new);
+                    lines.extend(doc.lines().map(|line| (line, line)));
                 }
             }
         }
-        Docs { contents: docs }
+        let lines = match lines {
+            Some(lines) => lines,
+            None => return Docs::default(),
+        };
+
+        let mut contents = String::new();
+        let mut examples = Vec::new();
+        let mut example: Option<Example> = None;
+        for (line, code) in lines {
+            let fence = line.trim().strip_prefix("```");
+            match (&mut example, fence) {
+                (Some(_), Some("")) => examples.extend(example.take()),
+                (Some(example), _) => {
+                    example.code.push_str(code);
+                    example.code.push('\n');
+                }
+                (None, Some(lang)) => {
+                    example = Some(Example {
+                        lang: Some(lang.trim().to_string()).filter(|l| !l.is_empty()),
+                        code: String::new(),
+                    });
+                }
+                (None, None) => {
+                    contents.push_str(line);
+                    contents.push('\n');
+                }
+            }
+        }
+        // An unterminated block runs to the end of the docs, as in markdown.
+        examples.extend(example);
+
+        // Drop the blank lines left where examples were, and the contents
+        // altogether if the docs are only examples.
+        let contents = if examples.is_empty() {
+            Some(contents)
+        } else if contents.trim().is_empty() {
+            None
+        } else {
+            Some(format!("{}\n", contents.trim_end()))
+        };
+        Docs { contents, examples }
     }
 
     fn resolve_value(&mut self, value: &Value<'_>) -> Result<()> {
@@ -584,11 +759,9 @@ impl Resolver {
                 params,
                 result,
             } => {
-                let params = params
-                    .iter()
-                    .map(|(name, ty)| Ok((name.name.to_string(), self.resolve_type(ty)?)))
-                    .collect::<Result<_>>()?;
+                let (params, ranges) = self.resolve_params(params)?;
                 let result = self.resolve_type(result)?;
+                let (requires, string_encoding) = self.resolve_annotations(value)?;
                 self.functions.push(Function {
                     docs,
                     name: value.name.name.to_string(),
@@ -596,9 +769,20 @@ impl Resolver {
                     params,
                     result,
                     is_async: *is_async,
+                    requires,
+                    ranges,
+                    string_encoding,
                 });
             }
             ValueKind::Global(ty) => {
+                if let Some(annotation) = value.annotations.first() {
+                    return Err(Error {
+                        span: annotation.name.span,
+                        code: "unsupported",
+                        msg: "annotations are only allowed on functions".to_string(),
+                    }
+                    .into());
+                }
                 let ty = self.resolve_type(ty)?;
                 self.globals.push(Global {
                     docs,
@@ -623,6 +807,7 @@ impl Resolver {
                 ValueKind::Global(_) => {
                     return Err(Error {
                         span: value.name.span,
+                        code: "unsupported",
                         msg: "globals not allowed in resources".to_string(),
                     }
                     .into());
@@ -631,16 +816,15 @@ impl Resolver {
             if !names.insert(&value.name.name) {
                 return Err(Error {
                     span: value.name.span,
+                    code: "duplicate-definition",
                     msg: format!("{:?} defined twice in this resource", value.name.name),
                 }
                 .into());
             }
             let docs = self.docs(&value.docs);
-            let mut params = params
-                .iter()
-                .map(|(name, ty)| Ok((name.name.to_string(), self.resolve_type(ty)?)))
-                .collect::<Result<Vec<_>>>()?;
+            let (mut params, ranges) = self.resolve_params(params)?;
             let result = self.resolve_type(result)?;
+            let (requires, string_encoding) = self.resolve_annotations(value)?;
             let kind = if *statik {
                 FunctionKind::Static {
                     resource: id,
@@ -660,11 +844,178 @@ impl Resolver {
                 kind,
                 params,
                 result,
+                requires,
+                ranges,
+                string_encoding,
             });
         }
         Ok(())
     }
 
+    /// Resolves the types of `params`, along with the bounds their `@range`
+    /// annotations give them.
+    #[allow(clippy::type_complexity)]
+    fn resolve_params(
+        &mut self,
+        params: &[Param<'_>],
+    ) -> Result<(Vec<(String, Type)>, Vec<(String, Range)>)> {
+        let mut types = Vec::new();
+        let mut ranges = Vec::new();
+        for param in params {
+            let name = param.name.name.to_string();
+            let ty = self.resolve_type(&param.ty)?;
+            for annotation in param.annotations.iter() {
+                if annotation.name.name != "range" {
+                    return Err(Error {
+                        span: annotation.name.span,
+                        code: "unknown-annotation",
+                        msg: format!("unknown annotation `@{}`", annotation.name.name),
+                    }
+                    .into());
+                }
+                let range = self.resolve_range(annotation, ty)?;
+                if ranges.iter().any(|(n, _)| *n == name) {
+                    return Err(Error {
+                        span: annotation.name.span,
+                        code: "invalid-annotation",
+                        msg: format!("parameter `{}` has several `@range`s", name),
+                    }
+                    .into());
+                }
+                ranges.push((name.clone(), range));
+            }
+            types.push((name, ty));
+        }
+        Ok((types, ranges))
+    }
+
+    fn resolve_range(&self, annotation: &super::Annotation<'_>, ty: Type) -> Result<Range> {
+        let err = |span, msg: &str| -> anyhow::Error {
+            Error {
+                span,
+                code: "invalid-annotation",
+                msg: msg.to_string(),
+            }
+            .into()
+        };
+        let bounds = match self.integer_bounds(ty) {
+            Some(bounds) => bounds,
+            None => {
+                return Err(err(
+                    annotation.name.span,
+                    "`@range` only applies to integer parameters",
+                ))
+            }
+        };
+        let (min, max) = match &annotation.args[..] {
+            [min, max] if min.key.is_none() && max.key.is_none() => (min, max),
+            _ => {
+                return Err(err(
+                    annotation.name.span,
+                    "`@range` needs a minimum and a maximum",
+                ))
+            }
+        };
+        let mut range = Range { min: 0, max: 0 };
+        for (arg, bound) in [(min, &mut range.min), (max, &mut range.max)] {
+            *bound = match arg.value {
+                AnnotationValue::Integer(n) => n,
+                _ => return Err(err(arg.span, "expected an integer")),
+            };
+            if !bounds.contains(*bound) {
+                return Err(err(arg.span, "bound doesn't fit in the parameter's type"));
+            }
+        }
+        if range.min > range.max {
+            return Err(err(min.span, "minimum is larger than the maximum"));
+        }
+        Ok(range)
+    }
+
+    /// Returns the values of the integer type `ty` refers to, looking through
+    /// aliases, or `None` if it isn't an integer.
+    fn integer_bounds(&self, mut ty: Type) -> Option<Range> {
+        // Aliases haven't been checked for cycles yet, so only follow as many
+        // as there are types.
+        for _ in 0..=self.types.len() {
+            let (min, max) = match ty {
+                Type::U8 => (0, u8::MAX.into()),
+                Type::U16 => (0, u16::MAX.into()),
+                Type::U32 => (0, u32::MAX.into()),
+                Type::U64 => (0, u64::MAX.into()),
+                Type::S8 => (i8::MIN.into(), i8::MAX.into()),
+                Type::S16 => (i16::MIN.into(), i16::MAX.into()),
+                Type::S32 => (i32::MIN.into(), i32::MAX.into()),
+                Type::S64 => (i64::MIN.into(), i64::MAX.into()),
+                Type::Id(id) => match &self.types[id].kind {
+                    TypeDefKind::Type(t) => {
+                        ty = *t;
+                        continue;
+                    }
+                    _ => return None,
+                },
+                _ => return None,
+            };
+            return Some(Range { min, max });
+        }
+        None
+    }
+
+    /// Validates the annotations of the function `value`, returning the
+    /// capabilities it requires and the encoding of its strings.
+    fn resolve_annotations(
+        &self,
+        value: &Value<'_>,
+    ) -> Result<(Vec<String>, Option<StringEncoding>)> {
+        let mut requires = Vec::new();
+        let mut string_encoding = None;
+        for annotation in value.annotations.iter() {
+            if annotation.name.name == "string-encoding" {
+                set_string_encoding(&mut string_encoding, annotation)?;
+                continue;
+            }
+            if annotation.name.name != "requires" {
+                return Err(Error {
+                    span: annotation.name.span,
+                    code: "unknown-annotation",
+                    msg: format!("unknown annotation `@{}`", annotation.name.name),
+                }
+                .into());
+            }
+            if annotation.args.is_empty() {
+                return Err(Error {
+                    span: annotation.name.span,
+                    code: "invalid-annotation",
+                    msg: "`@requires` needs at least one capability".to_string(),
+                }
+                .into());
+            }
+            for arg in annotation.args.iter() {
+                if let Some(key) = &arg.key {
+                    return Err(Error {
+                        span: key.span,
+                        code: "invalid-annotation",
+                        msg: "expected a capability".to_string(),
+                    }
+                    .into());
+                }
+                let capability = arg.string()?;
+                if capability.is_empty() {
+                    return Err(Error {
+                        span: arg.span,
+                        code: "invalid-annotation",
+                        msg: "capabilities can't be empty".to_string(),
+                    }
+                    .into());
+                }
+                if !requires.iter().any(|c| c == capability) {
+                    requires.push(capability.to_string());
+                }
+            }
+        }
+        Ok((requires, string_encoding))
+    }
+
     fn validate_type_not_recursive(
         &self,
         span: Span,
@@ -678,10 +1029,19 @@ impl Resolver {
         if !visiting.insert(ty) {
             return Err(Error {
                 span,
+                code: "recursive-type",
                 msg: "type can recursively refer to itself".to_string(),
             }
             .into());
         }
+        if visiting.len() > super::MAX_TYPE_DEPTH as usize {
+            return Err(Error {
+                span,
+                code: "nested-too-deeply",
+                msg: "type is nested too deeply".to_string(),
+            }
+            .into());
+        }
 
         match &self.types[ty].kind {
             TypeDefKind::List(Type::Id(id)) | TypeDefKind::Type(Type::Id(id)) => {
@@ -754,3 +1114,63 @@ impl Resolver {
         Ok(())
     }
 }
+
+/// Checks that a `@non-exhaustive` enum or variant doesn't have a case named
+/// `unknown`, which bindings generate for discriminants they don't know.
+fn check_unknown_case<'a, 'b: 'a>(names: impl Iterator<Item = &'a super::Id<'b>>) -> Result<()> {
+    for name in names {
+        if name.name == "unknown" {
+            return Err(Error {
+                span: name.span,
+                code: "duplicate-definition",
+                msg: "`unknown` is reserved for the unknown cases of `@non-exhaustive` types"
+                    .to_string(),
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Sets `encoding` to the one given by the `@string-encoding` annotation
+/// `annotation`, which must be the only one of its item.
+fn set_string_encoding(
+    encoding: &mut Option<StringEncoding>,
+    annotation: &Annotation<'_>,
+) -> Result<()> {
+    if encoding.is_some() {
+        return Err(Error {
+            span: annotation.name.span,
+            code: "invalid-annotation",
+            msg: "`@string-encoding` given more than once".to_string(),
+        }
+        .into());
+    }
+    let arg = match annotation.args.as_slice() {
+        [arg] if arg.key.is_none() => arg,
+        _ => {
+            return Err(Error {
+                span: annotation.name.span,
+                code: "invalid-annotation",
+                msg: "`@string-encoding` needs one encoding".to_string(),
+            }
+            .into())
+        }
+    };
+    let parsed = match &arg.value {
+        AnnotationValue::Name(name) => name.parse().ok(),
+        _ => None,
+    };
+    match parsed {
+        Some(parsed) => {
+            *encoding = Some(parsed);
+            Ok(())
+        }
+        None => Err(Error {
+            span: arg.span,
+            code: "invalid-annotation",
+            msg: "expected `utf8`, `utf16` or `compact-utf16`".to_string(),
+        }
+        .into()),
+    }
+}
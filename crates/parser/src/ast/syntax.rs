@@ -0,0 +1,247 @@
+//! A lossless syntax tree of `*.wai` files, for tools such as formatters,
+//! linters and language servers which need to know how a file is written
+//! rather than only what it means.
+//!
+//! The [`tokens`](SyntaxTree::tokens) of a tree include whitespace and
+//! comments and cover the whole file after its byte order mark, if it has
+//! one, so concatenating the text of their spans gives back the file. Its
+//! [`items`](SyntaxTree::items) are the definitions of the file along with
+//! the spans of their names and of their whole text.
+//!
+//! Spans are byte offsets into the parsed text.
+
+use super::lex::Tokenizer;
+use super::{rewrite_error, Ast};
+use anyhow::Result;
+
+pub use super::lex::{Span, Token as TokenKind};
+
+/// A parsed `*.wai` file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxTree {
+    /// Every token of the file in order, including whitespace and comments.
+    pub tokens: Vec<Token>,
+    /// The items of the file in order.
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+impl Token {
+    /// Returns whether this token is whitespace or a comment.
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.kind, TokenKind::Whitespace | TokenKind::Comment)
+    }
+}
+
+/// An item of a file, resource or interface.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub kind: ItemKind,
+    /// The name the item defines, which `use` and `include` items don't
+    /// have.
+    pub name: Option<Name>,
+    /// The span of the item, from its first comment, doc comment or not, or
+    /// otherwise its first token, to its last token.
+    pub span: Span,
+    /// The names of the fields, flags, cases or parameters of the item, or
+    /// the names imported by a `use` item.
+    pub members: Vec<Name>,
+    /// The functions of a resource, or the items of an interface.
+    pub items: Vec<Item>,
+    /// Whether the item is an enum or variant marked `@non-exhaustive`.
+    pub non_exhaustive: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemKind {
+    Use,
+    Include,
+    /// A `type` item.
+    Type,
+    Record,
+    Flags,
+    Variant,
+    Enum,
+    Union,
+    Resource,
+    Interface,
+    Function,
+    Global,
+}
+
+/// A name along with where it's written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Name {
+    pub name: String,
+    pub span: Span,
+}
+
+impl SyntaxTree {
+    /// Parses `input` into a syntax tree, reporting errors like
+    /// [`Interface::parse`](crate::Interface::parse) does.
+    pub fn parse(filename: &str, input: &str) -> Result<SyntaxTree> {
+        SyntaxTree::_parse(input).map_err(|mut e| {
+            rewrite_error(&mut e, filename, input);
+            e
+        })
+    }
+
+    fn _parse(input: &str) -> Result<SyntaxTree> {
+        let ast = Ast::parse(input, None)?;
+        let mut lexer = Tokenizer::new(input)?;
+        let mut tokens = Vec::new();
+        while let Some((span, kind)) = lexer.next_raw()? {
+            tokens.push(Token { kind, span });
+        }
+        Ok(SyntaxTree {
+            tokens,
+            items: items(&ast.items, &ast.spans),
+        })
+    }
+}
+
+fn items(items: &[super::Item<'_>], spans: &[Span]) -> Vec<Item> {
+    items
+        .iter()
+        .zip(spans)
+        .map(|(item, span)| Item::new(item, *span))
+        .collect()
+}
+
+impl Item {
+    fn new(item: &super::Item<'_>, span: Span) -> Item {
+        let mut ret = Item {
+            kind: ItemKind::Use,
+            name: None,
+            span,
+            members: Vec::new(),
+            items: Vec::new(),
+            non_exhaustive: false,
+        };
+        match item {
+            super::Item::Use(u) => {
+                ret.members = u.names.iter().flatten().map(|n| name(&n.name)).collect();
+            }
+            super::Item::Include(_) => ret.kind = ItemKind::Include,
+            super::Item::Resource(r) => {
+                ret.kind = ItemKind::Resource;
+                ret.name = Some(name(&r.name));
+                ret.items = r
+                    .values
+                    .iter()
+                    .zip(&r.spans)
+                    .map(|((_, value), span)| Item::value(value, *span))
+                    .collect();
+            }
+            super::Item::TypeDef(t) => {
+                ret.name = Some(name(&t.name));
+                (ret.kind, ret.members) = match &t.ty {
+                    super::Type::Record(r) => (
+                        ItemKind::Record,
+                        r.fields.iter().map(|f| name(&f.name)).collect(),
+                    ),
+                    super::Type::Flags(f) => (
+                        ItemKind::Flags,
+                        f.flags.iter().map(|f| name(&f.name)).collect(),
+                    ),
+                    super::Type::Variant(v) => {
+                        ret.non_exhaustive = v.non_exhaustive;
+                        (
+                            ItemKind::Variant,
+                            v.cases.iter().map(|c| name(&c.name)).collect(),
+                        )
+                    }
+                    super::Type::Enum(e) => {
+                        ret.non_exhaustive = e.non_exhaustive;
+                        (
+                            ItemKind::Enum,
+                            e.cases.iter().map(|c| name(&c.name)).collect(),
+                        )
+                    }
+                    super::Type::Union(_) => (ItemKind::Union, Vec::new()),
+                    _ => (ItemKind::Type, Vec::new()),
+                };
+            }
+            super::Item::Value(v) => return Item::value(v, span),
+            super::Item::Interface(i) => {
+                ret.kind = ItemKind::Interface;
+                ret.name = Some(name(&i.name));
+                ret.items = items(&i.items, &i.spans);
+            }
+        }
+        ret
+    }
+
+    fn value(value: &super::Value<'_>, span: Span) -> Item {
+        let (kind, members) = match &value.kind {
+            super::ValueKind::Function { params, .. } => (
+                ItemKind::Function,
+                params.iter().map(|param| name(&param.name)).collect(),
+            ),
+            super::ValueKind::Global(_) => (ItemKind::Global, Vec::new()),
+        };
+        Item {
+            kind,
+            name: Some(name(&value.name)),
+            span,
+            members,
+            items: Vec::new(),
+            non_exhaustive: false,
+        }
+    }
+}
+
+fn name(id: &super::Id<'_>) -> Name {
+    Name {
+        name: id.name.to_string(),
+        span: id.span,
+    }
+}
+
+#[test]
+fn test_syntax_tree() {
+    let input = "\u{feff}// header\n\n/// A point.\nrecord point { x: u32, y: u32 }\n\nresource r {\n  static new: func() -> r\n}\nuse { a as b } from other\nf: func(p: point) -> %type // trailing\n";
+    let tree = SyntaxTree::parse("foo.wai", input).unwrap();
+
+    let text = tree
+        .tokens
+        .iter()
+        .map(|t| &input[t.span.start as usize..t.span.end as usize])
+        .collect::<String>();
+    assert_eq!(text, input.trim_start_matches('\u{feff}'));
+
+    let text = |span: Span| &input[span.start as usize..span.end as usize];
+    let kinds = tree.items.iter().map(|i| i.kind).collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            ItemKind::Record,
+            ItemKind::Resource,
+            ItemKind::Use,
+            ItemKind::Function
+        ]
+    );
+    let point = &tree.items[0];
+    assert_eq!(
+        text(point.span),
+        "// header\n\n/// A point.\nrecord point { x: u32, y: u32 }"
+    );
+    assert_eq!(text(point.name.as_ref().unwrap().span), "point");
+    assert_eq!(point.members[1].name, "y");
+    assert_eq!(text(point.members[1].span), "y");
+    let new = &tree.items[1].items[0];
+    assert_eq!(text(new.span), "static new: func() -> r");
+    assert_eq!(tree.items[2].members[0].name, "a");
+    let f = &tree.items[3];
+    assert_eq!(text(f.span), "f: func(p: point) -> %type");
+    assert_eq!(f.members[0].name, "p");
+
+    let err = SyntaxTree::parse("foo.wai", "record {").unwrap_err();
+    let diag = err.downcast_ref::<super::Diagnostic>().unwrap();
+    assert_eq!((diag.line, diag.column), (1, 8));
+}
@@ -0,0 +1,678 @@
+//! A compact binary encoding of [`Interface`], so that parsed interfaces can
+//! be cached by build systems and read by tools which don't link this crate.
+//!
+//! An encoding starts with the four bytes [`MAGIC`] followed by the
+//! [`VERSION`] of the format as a little-endian `u32`, and then the
+//! interface. Version numbers are bumped whenever the format changes, and
+//! decoding any other version is an error.
+//!
+//! Integers are unsigned LEB128, strings are their length followed by their
+//! UTF-8 bytes, optional values are a `0` byte for `None` or a `1` byte
+//! followed by the value, and lists are their length followed by their
+//! elements. Docs are an optional string followed by the list of their
+//! examples, each an optional language and its code. Types and resources are referred
+//! to by their index in the lists of the interface that defines them.
+//!
+//! * An interface is its name, its optional module, its string encoding,
+//!   and the lists of its resources, types, used interfaces, functions and
+//!   globals. String encodings are a byte for none (0), `utf8` (1), `utf16`
+//!   (2) or `compact-utf16` (3).
+//! * A resource is its docs, name, optional supertype and optional foreign
+//!   module.
+//! * A type definition is its docs, optional name, optional foreign module
+//!   and its kind: a byte for `record` (0), `flags` (1), `tuple` (2),
+//!   `variant` (3), `enum` (4), `option` (5), `expected` (6), `union` (7),
+//!   `list` (8), `future` (9), `stream` (10) or an alias (11), followed by
+//!   its contents in the order they're written in `*.wai`. Fields and cases
+//!   are their docs and name, then their type if they have one, and union
+//!   cases are their docs and type. Variants and enums start with a byte
+//!   which is `1` if they're non-exhaustive.
+//! * A used interface is the name it's used by, followed by the interface.
+//! * A function is a byte which is `1` if it's async, its docs and name, its
+//!   kind, its list of parameters as name and type, its result type, its
+//!   list of required capabilities, its list of parameter ranges, each the
+//!   name of the parameter followed by its minimum and maximum as 16-byte
+//!   little-endian signed integers, and its string encoding. Its kind is `0` for freestanding
+//!   functions, or `1` for static functions and `2` for methods followed by
+//!   the index of the resource and the name of the function in it.
+//! * A global is its docs, name and type.
+//! * A type is a byte for `unit` (0), `bool` (1), `u8` (2), `u16` (3),
+//!   `u32` (4), `u64` (5), `s8` (6), `s16` (7), `s32` (8), `s64` (9),
+//!   `float32` (10), `float64` (11), `char` (12) and `string` (13), or a
+//!   handle (14) or type definition (15) followed by its index.
+
+use crate::*;
+
+/// The bytes every encoded interface starts with.
+pub const MAGIC: [u8; 4] = *b"WAIB";
+
+/// The version of the format written by [`Interface::to_binary`].
+pub const VERSION: u32 = 5;
+
+impl Interface {
+    /// Encodes this interface in the binary format described in the
+    /// [`binary`](crate::binary) module.
+    pub fn to_binary(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        encode_interface(self, &mut out);
+        out
+    }
+
+    /// Decodes an interface encoded by [`Interface::to_binary`].
+    pub fn from_binary(bytes: &[u8]) -> Result<Interface> {
+        let mut input = bytes
+            .strip_prefix(&MAGIC[..])
+            .context("not a binary wai interface")?;
+        let version = u32::from_le_bytes(take(&mut input, 4)?.try_into().unwrap());
+        if version != VERSION {
+            bail!(
+                "unsupported binary wai interface version {}, expected {}",
+                version,
+                VERSION
+            );
+        }
+        let iface = decode_interface(&mut input)?;
+        if !input.is_empty() {
+            bail!("trailing bytes after binary wai interface");
+        }
+        Ok(iface)
+    }
+}
+
+fn encode_interface(iface: &Interface, out: &mut Vec<u8>) {
+    encode_str(&iface.name, out);
+    encode_opt_str(&iface.module, out);
+    encode_string_encoding(iface.string_encoding, out);
+
+    encode_len(iface.resources.len(), out);
+    for (_, resource) in iface.resources.iter() {
+        encode_docs(&resource.docs, out);
+        encode_str(&resource.name, out);
+        encode_opt_str(&resource.supertype, out);
+        encode_opt_str(&resource.foreign_module, out);
+    }
+
+    encode_len(iface.types.len(), out);
+    for (_, ty) in iface.types.iter() {
+        encode_docs(&ty.docs, out);
+        encode_opt_str(&ty.name, out);
+        encode_opt_str(&ty.foreign_module, out);
+        encode_kind(&ty.kind, out);
+    }
+
+    // Used interfaces are written in the order of their index so that the
+    // decoded arena matches.
+    let mut names = vec![None; iface.interfaces.len()];
+    for (name, id) in iface.interface_lookup.iter() {
+        names[id.index()] = Some(name);
+    }
+    encode_len(iface.interfaces.len(), out);
+    for ((_, used), name) in iface.interfaces.iter().zip(names) {
+        encode_str(name.expect("used interface without a name"), out);
+        encode_interface(used, out);
+    }
+
+    encode_len(iface.functions.len(), out);
+    for func in iface.functions.iter() {
+        out.push(func.is_async as u8);
+        encode_docs(&func.docs, out);
+        encode_str(&func.name, out);
+        match &func.kind {
+            FunctionKind::Freestanding => out.push(0),
+            FunctionKind::Static { resource, name } => {
+                out.push(1);
+                encode_len(resource.index(), out);
+                encode_str(name, out);
+            }
+            FunctionKind::Method { resource, name } => {
+                out.push(2);
+                encode_len(resource.index(), out);
+                encode_str(name, out);
+            }
+        }
+        encode_len(func.params.len(), out);
+        for (name, ty) in func.params.iter() {
+            encode_str(name, out);
+            encode_ty(ty, out);
+        }
+        encode_ty(&func.result, out);
+        encode_len(func.requires.len(), out);
+        for capability in func.requires.iter() {
+            encode_str(capability, out);
+        }
+        encode_len(func.ranges.len(), out);
+        for (name, range) in func.ranges.iter() {
+            encode_str(name, out);
+            out.extend_from_slice(&range.min.to_le_bytes());
+            out.extend_from_slice(&range.max.to_le_bytes());
+        }
+        encode_string_encoding(func.string_encoding, out);
+    }
+
+    encode_len(iface.globals.len(), out);
+    for global in iface.globals.iter() {
+        encode_docs(&global.docs, out);
+        encode_str(&global.name, out);
+        encode_ty(&global.ty, out);
+    }
+}
+
+fn encode_kind(kind: &TypeDefKind, out: &mut Vec<u8>) {
+    match kind {
+        TypeDefKind::Record(r) => {
+            out.push(0);
+            encode_len(r.fields.len(), out);
+            for field in r.fields.iter() {
+                encode_docs(&field.docs, out);
+                encode_str(&field.name, out);
+                encode_ty(&field.ty, out);
+            }
+        }
+        TypeDefKind::Flags(f) => {
+            out.push(1);
+            encode_len(f.flags.len(), out);
+            for flag in f.flags.iter() {
+                encode_docs(&flag.docs, out);
+                encode_str(&flag.name, out);
+            }
+        }
+        TypeDefKind::Tuple(t) => {
+            out.push(2);
+            encode_len(t.types.len(), out);
+            for ty in t.types.iter() {
+                encode_ty(ty, out);
+            }
+        }
+        TypeDefKind::Variant(v) => {
+            out.push(3);
+            out.push(v.non_exhaustive as u8);
+            encode_len(v.cases.len(), out);
+            for case in v.cases.iter() {
+                encode_docs(&case.docs, out);
+                encode_str(&case.name, out);
+                encode_ty(&case.ty, out);
+            }
+        }
+        TypeDefKind::Enum(e) => {
+            out.push(4);
+            out.push(e.non_exhaustive as u8);
+            encode_len(e.cases.len(), out);
+            for case in e.cases.iter() {
+                encode_docs(&case.docs, out);
+                encode_str(&case.name, out);
+            }
+        }
+        TypeDefKind::Option(t) => {
+            out.push(5);
+            encode_ty(t, out);
+        }
+        TypeDefKind::Expected(e) => {
+            out.push(6);
+            encode_ty(&e.ok, out);
+            encode_ty(&e.err, out);
+        }
+        TypeDefKind::Union(u) => {
+            out.push(7);
+            encode_len(u.cases.len(), out);
+            for case in u.cases.iter() {
+                encode_docs(&case.docs, out);
+                encode_ty(&case.ty, out);
+            }
+        }
+        TypeDefKind::List(t) => {
+            out.push(8);
+            encode_ty(t, out);
+        }
+        TypeDefKind::Future(t) => {
+            out.push(9);
+            encode_ty(t, out);
+        }
+        TypeDefKind::Stream(s) => {
+            out.push(10);
+            encode_ty(&s.element, out);
+            encode_ty(&s.end, out);
+        }
+        TypeDefKind::Type(t) => {
+            out.push(11);
+            encode_ty(t, out);
+        }
+    }
+}
+
+fn encode_ty(ty: &Type, out: &mut Vec<u8>) {
+    match ty {
+        Type::Unit => out.push(0),
+        Type::Bool => out.push(1),
+        Type::U8 => out.push(2),
+        Type::U16 => out.push(3),
+        Type::U32 => out.push(4),
+        Type::U64 => out.push(5),
+        Type::S8 => out.push(6),
+        Type::S16 => out.push(7),
+        Type::S32 => out.push(8),
+        Type::S64 => out.push(9),
+        Type::Float32 => out.push(10),
+        Type::Float64 => out.push(11),
+        Type::Char => out.push(12),
+        Type::String => out.push(13),
+        Type::Handle(r) => {
+            out.push(14);
+            encode_len(r.index(), out);
+        }
+        Type::Id(id) => {
+            out.push(15);
+            encode_len(id.index(), out);
+        }
+    }
+}
+
+fn encode_len(mut n: usize, out: &mut Vec<u8>) {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn encode_str(s: &str, out: &mut Vec<u8>) {
+    encode_len(s.len(), out);
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn encode_opt_str(s: &Option<String>, out: &mut Vec<u8>) {
+    match s {
+        None => out.push(0),
+        Some(s) => {
+            out.push(1);
+            encode_str(s, out);
+        }
+    }
+}
+
+fn encode_string_encoding(encoding: Option<StringEncoding>, out: &mut Vec<u8>) {
+    out.push(match encoding {
+        None => 0,
+        Some(StringEncoding::UTF8) => 1,
+        Some(StringEncoding::UTF16) => 2,
+        Some(StringEncoding::CompactUTF16) => 3,
+    });
+}
+
+fn encode_docs(docs: &Docs, out: &mut Vec<u8>) {
+    encode_opt_str(&docs.contents, out);
+    encode_len(docs.examples.len(), out);
+    for example in docs.examples.iter() {
+        encode_opt_str(&example.lang, out);
+        encode_str(&example.code, out);
+    }
+}
+
+fn decode_interface(input: &mut &[u8]) -> Result<Interface> {
+    let mut iface = Interface {
+        name: decode_str(input)?,
+        module: decode_opt_str(input)?,
+        string_encoding: decode_string_encoding(input)?,
+        ..Interface::default()
+    };
+
+    for _ in 0..decode_len(input)? {
+        let resource = Resource {
+            docs: decode_docs(input)?,
+            name: decode_str(input)?,
+            supertype: decode_opt_str(input)?,
+            foreign_module: decode_opt_str(input)?,
+        };
+        let name = resource.name.clone();
+        let id = iface.resources.alloc(resource);
+        iface.resource_lookup.insert(name, id);
+    }
+    let resources = iface.resources.iter().map(|(id, _)| id).collect::<Vec<_>>();
+
+    // Types may refer to types after them, so allocate all of them before
+    // decoding any.
+    let types = (0..decode_len(input)?)
+        .map(|_| {
+            iface.types.alloc(TypeDef {
+                docs: Docs::default(),
+                kind: TypeDefKind::Type(Type::Unit),
+                name: None,
+                foreign_module: None,
+            })
+        })
+        .collect::<Vec<_>>();
+    let ids = Ids {
+        types: &types,
+        resources: &resources,
+    };
+    for id in types.iter() {
+        let ty = TypeDef {
+            docs: decode_docs(input)?,
+            name: decode_opt_str(input)?,
+            foreign_module: decode_opt_str(input)?,
+            kind: ids.kind(input)?,
+        };
+        if let Some(name) = &ty.name {
+            iface.type_lookup.insert(name.clone(), *id);
+        }
+        iface.types[*id] = ty;
+    }
+
+    for _ in 0..decode_len(input)? {
+        let name = decode_str(input)?;
+        let id = iface.interfaces.alloc(decode_interface(input)?);
+        iface.interface_lookup.insert(name, id);
+    }
+
+    for _ in 0..decode_len(input)? {
+        let is_async = match decode_byte(input)? {
+            0 => false,
+            1 => true,
+            n => bail!("invalid async flag {}", n),
+        };
+        let docs = decode_docs(input)?;
+        let name = decode_str(input)?;
+        let kind = match decode_byte(input)? {
+            0 => FunctionKind::Freestanding,
+            1 => FunctionKind::Static {
+                resource: ids.resource(input)?,
+                name: decode_str(input)?,
+            },
+            2 => FunctionKind::Method {
+                resource: ids.resource(input)?,
+                name: decode_str(input)?,
+            },
+            n => bail!("invalid function kind {}", n),
+        };
+        let params = (0..decode_len(input)?)
+            .map(|_| Ok((decode_str(input)?, ids.ty(input)?)))
+            .collect::<Result<_>>()?;
+        let result = ids.ty(input)?;
+        let requires = (0..decode_len(input)?)
+            .map(|_| decode_str(input))
+            .collect::<Result<_>>()?;
+        let ranges = (0..decode_len(input)?)
+            .map(|_| {
+                let name = decode_str(input)?;
+                let min = decode_i128(input)?;
+                let max = decode_i128(input)?;
+                Ok((name, Range { min, max }))
+            })
+            .collect::<Result<_>>()?;
+        let string_encoding = decode_string_encoding(input)?;
+        iface.functions.push(Function {
+            is_async,
+            docs,
+            name,
+            kind,
+            params,
+            result,
+            requires,
+            ranges,
+            string_encoding,
+        });
+    }
+
+    for _ in 0..decode_len(input)? {
+        iface.globals.push(Global {
+            docs: decode_docs(input)?,
+            name: decode_str(input)?,
+            ty: ids.ty(input)?,
+        });
+    }
+
+    Ok(iface)
+}
+
+/// The ids of the types and resources of the interface being decoded, by
+/// index.
+struct Ids<'a> {
+    types: &'a [TypeId],
+    resources: &'a [ResourceId],
+}
+
+impl Ids<'_> {
+    fn resource(&self, input: &mut &[u8]) -> Result<ResourceId> {
+        let index = decode_len(input)?;
+        match self.resources.get(index) {
+            Some(id) => Ok(*id),
+            None => bail!("resource index {} out of bounds", index),
+        }
+    }
+
+    fn ty(&self, input: &mut &[u8]) -> Result<Type> {
+        Ok(match decode_byte(input)? {
+            0 => Type::Unit,
+            1 => Type::Bool,
+            2 => Type::U8,
+            3 => Type::U16,
+            4 => Type::U32,
+            5 => Type::U64,
+            6 => Type::S8,
+            7 => Type::S16,
+            8 => Type::S32,
+            9 => Type::S64,
+            10 => Type::Float32,
+            11 => Type::Float64,
+            12 => Type::Char,
+            13 => Type::String,
+            14 => Type::Handle(self.resource(input)?),
+            15 => {
+                let index = decode_len(input)?;
+                match self.types.get(index) {
+                    Some(id) => Type::Id(*id),
+                    None => bail!("type index {} out of bounds", index),
+                }
+            }
+            n => bail!("invalid type {}", n),
+        })
+    }
+
+    fn kind(&self, input: &mut &[u8]) -> Result<TypeDefKind> {
+        Ok(match decode_byte(input)? {
+            0 => TypeDefKind::Record(Record {
+                fields: (0..decode_len(input)?)
+                    .map(|_| {
+                        Ok(Field {
+                            docs: decode_docs(input)?,
+                            name: decode_str(input)?,
+                            ty: self.ty(input)?,
+                        })
+                    })
+                    .collect::<Result<_>>()?,
+            }),
+            1 => TypeDefKind::Flags(Flags {
+                flags: (0..decode_len(input)?)
+                    .map(|_| {
+                        Ok(Flag {
+                            docs: decode_docs(input)?,
+                            name: decode_str(input)?,
+                        })
+                    })
+                    .collect::<Result<_>>()?,
+            }),
+            2 => TypeDefKind::Tuple(Tuple {
+                types: (0..decode_len(input)?)
+                    .map(|_| self.ty(input))
+                    .collect::<Result<_>>()?,
+            }),
+            3 => TypeDefKind::Variant(Variant {
+                non_exhaustive: decode_non_exhaustive(input)?,
+                cases: (0..decode_len(input)?)
+                    .map(|_| {
+                        Ok(Case {
+                            docs: decode_docs(input)?,
+                            name: decode_str(input)?,
+                            ty: self.ty(input)?,
+                        })
+                    })
+                    .collect::<Result<_>>()?,
+            }),
+            4 => TypeDefKind::Enum(Enum {
+                non_exhaustive: decode_non_exhaustive(input)?,
+                cases: (0..decode_len(input)?)
+                    .map(|_| {
+                        Ok(EnumCase {
+                            docs: decode_docs(input)?,
+                            name: decode_str(input)?,
+                        })
+                    })
+                    .collect::<Result<_>>()?,
+            }),
+            5 => TypeDefKind::Option(self.ty(input)?),
+            6 => TypeDefKind::Expected(Expected {
+                ok: self.ty(input)?,
+                err: self.ty(input)?,
+            }),
+            7 => TypeDefKind::Union(Union {
+                cases: (0..decode_len(input)?)
+                    .map(|_| {
+                        Ok(UnionCase {
+                            docs: decode_docs(input)?,
+                            ty: self.ty(input)?,
+                        })
+                    })
+                    .collect::<Result<_>>()?,
+            }),
+            8 => TypeDefKind::List(self.ty(input)?),
+            9 => TypeDefKind::Future(self.ty(input)?),
+            10 => TypeDefKind::Stream(Stream {
+                element: self.ty(input)?,
+                end: self.ty(input)?,
+            }),
+            11 => TypeDefKind::Type(self.ty(input)?),
+            n => bail!("invalid type definition kind {}", n),
+        })
+    }
+}
+
+fn take<'a>(input: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+    if input.len() < n {
+        bail!("unexpected end of binary wai interface");
+    }
+    let (head, tail) = input.split_at(n);
+    *input = tail;
+    Ok(head)
+}
+
+fn decode_byte(input: &mut &[u8]) -> Result<u8> {
+    Ok(take(input, 1)?[0])
+}
+
+fn decode_non_exhaustive(input: &mut &[u8]) -> Result<bool> {
+    match decode_byte(input)? {
+        0 => Ok(false),
+        1 => Ok(true),
+        n => bail!("invalid non-exhaustive flag {}", n),
+    }
+}
+
+fn decode_len(input: &mut &[u8]) -> Result<usize> {
+    let mut n = 0usize;
+    let mut shift = 0;
+    loop {
+        let byte = decode_byte(input)?;
+        let bits = usize::from(byte & 0x7f);
+        if shift >= usize::BITS || (bits << shift) >> shift != bits {
+            bail!("integer too large in binary wai interface");
+        }
+        n |= bits << shift;
+        if byte & 0x80 == 0 {
+            return Ok(n);
+        }
+        shift += 7;
+    }
+}
+
+fn decode_i128(input: &mut &[u8]) -> Result<i128> {
+    Ok(i128::from_le_bytes(take(input, 16)?.try_into().unwrap()))
+}
+
+fn decode_str(input: &mut &[u8]) -> Result<String> {
+    let len = decode_len(input)?;
+    let bytes = take(input, len)?;
+    Ok(std::str::from_utf8(bytes)
+        .context("invalid utf-8 in binary wai interface")?
+        .to_string())
+}
+
+fn decode_opt_str(input: &mut &[u8]) -> Result<Option<String>> {
+    match decode_byte(input)? {
+        0 => Ok(None),
+        1 => Ok(Some(decode_str(input)?)),
+        n => bail!("invalid option tag {}", n),
+    }
+}
+
+fn decode_string_encoding(input: &mut &[u8]) -> Result<Option<StringEncoding>> {
+    Ok(match decode_byte(input)? {
+        0 => None,
+        1 => Some(StringEncoding::UTF8),
+        2 => Some(StringEncoding::UTF16),
+        3 => Some(StringEncoding::CompactUTF16),
+        n => bail!("invalid string encoding {}", n),
+    })
+}
+
+fn decode_docs(input: &mut &[u8]) -> Result<Docs> {
+    let contents = decode_opt_str(input)?;
+    let mut examples = Vec::new();
+    for _ in 0..decode_len(input)? {
+        examples.push(Example {
+            lang: decode_opt_str(input)?,
+            code: decode_str(input)?,
+        });
+    }
+    Ok(Docs { contents, examples })
+}
+
+#[test]
+fn test_binary_docs_and_errors() {
+    let iface = Interface::parse(
+        "foo",
+        "@!string-encoding(utf16)\n/// a record\nrecord r {\n  /// a field\n  a: list<r2>,\n}\nrecord r2 {}\n/// a function\n/// ```rust\n/// f(r);\n/// ```\nf: func(x: r, @range(-1, 1) y: s8) -> option<r>\n@non-exhaustive\nenum e { a }\n@string-encoding(compact-utf16)\ng: func()\n",
+    )
+    .unwrap();
+    let bytes = iface.to_binary();
+    let decoded = Interface::from_binary(&bytes).unwrap();
+    let r = &decoded.types[decoded.type_lookup["r"]];
+    assert_eq!(r.docs.contents.as_deref(), Some("a record\n"));
+    match &r.kind {
+        TypeDefKind::Record(r) => {
+            assert_eq!(r.fields[0].docs.contents.as_deref(), Some("a field\n"))
+        }
+        _ => panic!("expected a record"),
+    }
+    match &decoded.types[decoded.type_lookup["e"]].kind {
+        TypeDefKind::Enum(e) => assert!(e.non_exhaustive),
+        _ => panic!("expected an enum"),
+    }
+    assert_eq!(
+        decoded.functions[0].docs.contents.as_deref(),
+        Some("a function\n")
+    );
+    assert_eq!(decoded.functions[0].docs.examples[0].code, "f(r);\n");
+    assert_eq!(
+        decoded.functions[0].range("y"),
+        Some(Range { min: -1, max: 1 })
+    );
+    assert_eq!(decoded.string_encoding, Some(StringEncoding::UTF16));
+    assert_eq!(
+        decoded.functions[1].string_encoding,
+        Some(StringEncoding::CompactUTF16)
+    );
+    assert_eq!(decoded.to_binary(), bytes);
+
+    let mut wrong_version = bytes.clone();
+    wrong_version[4] = 6;
+    let err = Interface::from_binary(&wrong_version).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("unsupported binary wai interface version 6"));
+    assert!(Interface::from_binary(&bytes[..bytes.len() - 1]).is_err());
+    assert!(Interface::from_binary(b"\0asm").is_err());
+}
@@ -0,0 +1,31 @@
+use std::collections::BTreeSet;
+
+/// The settings which `@cfg` annotations are checked against, such as
+/// `host = "wasmer"` or `feature = "gpu"`.
+///
+/// An item annotated with `@cfg` is only part of an interface if each of its
+/// `@cfg` annotations names at least one setting which is set here, so that
+/// `@cfg(host = "wasmer", host = "wasmtime")` keeps an item for either host
+/// and `@cfg(host = "wasmer") @cfg(feature = "gpu")` only for wasmer with the
+/// `gpu` feature.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cfg {
+    settings: BTreeSet<(String, String)>,
+}
+
+impl Cfg {
+    pub fn new() -> Cfg {
+        Cfg::default()
+    }
+
+    /// Sets `key` to `value`. A key can be set to several values at once,
+    /// like several `feature`s.
+    pub fn set(&mut self, key: &str, value: &str) -> &mut Self {
+        self.settings.insert((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn is_set(&self, key: &str, value: &str) -> bool {
+        self.settings.iter().any(|(k, v)| k == key && v == value)
+    }
+}
@@ -7,7 +7,14 @@ use std::path::{Path, PathBuf};
 
 pub mod abi;
 mod ast;
+pub mod binary;
+mod cfg;
+pub mod lint;
+mod merge;
 mod sizealign;
+pub use ast::{syntax, Diagnostic};
+pub use cfg::Cfg;
+pub use merge::{ItemKind, Rename};
 pub use sizealign::*;
 
 /// Checks if the given string is a legal identifier in WAI.
@@ -35,6 +42,11 @@ pub struct Interface {
     pub interface_lookup: HashMap<String, InterfaceId>,
     pub functions: Vec<Function>,
     pub globals: Vec<Global>,
+    /// The encoding of strings passed to and from the functions of the
+    /// interface, given by a `@!string-encoding` annotation at the top of
+    /// the file. Functions can override it with their own
+    /// `@string-encoding`.
+    pub string_encoding: Option<StringEncoding>,
 }
 
 pub type TypeId = Id<TypeDef>;
@@ -153,6 +165,9 @@ pub struct Tuple {
 #[derive(Debug, Clone, PartialEq)]
 pub struct Variant {
     pub cases: Vec<Case>,
+    /// Whether the variant is marked `@non-exhaustive`, meaning that newer
+    /// versions of the interface may add cases to it.
+    pub non_exhaustive: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -176,6 +191,9 @@ impl Variant {
 #[derive(Debug, Clone, PartialEq)]
 pub struct Enum {
     pub cases: Vec<EnumCase>,
+    /// Whether the enum is marked `@non-exhaustive`, meaning that newer
+    /// versions of the interface may add cases to it.
+    pub non_exhaustive: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -232,6 +250,26 @@ pub struct Stream {
 #[derive(Clone, Default, Debug, PartialEq)]
 pub struct Docs {
     pub contents: Option<String>,
+    /// The fenced code blocks of the docs, which aren't part of `contents`.
+    pub examples: Vec<Example>,
+}
+
+/// A fenced code block in docs, written between lines of three backticks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Example {
+    /// The language given after the opening backticks, if any.
+    pub lang: Option<String>,
+    pub code: String,
+}
+
+impl Example {
+    /// Returns whether this example is written in one of `langs`.
+    pub fn is_in(&self, langs: &[&str]) -> bool {
+        match &self.lang {
+            Some(lang) => langs.contains(&lang.as_str()),
+            None => false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -259,6 +297,54 @@ pub struct Function {
     pub kind: FunctionKind,
     pub params: Vec<(String, Type)>,
     pub result: Type,
+    /// The capabilities listed in `@requires` annotations of the function,
+    /// which the host has to grant before the function may be called.
+    pub requires: Vec<String>,
+    /// The bounds given to integer parameters by `@range` annotations, by
+    /// parameter name and in the order of `params`.
+    pub ranges: Vec<(String, Range)>,
+    /// The encoding of strings passed to and from the function, given by a
+    /// `@string-encoding` annotation.
+    pub string_encoding: Option<StringEncoding>,
+}
+
+/// The ways strings can be encoded in linear memory.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum StringEncoding {
+    /// Strings are encoded with UTF-8.
+    #[default]
+    UTF8,
+    /// Strings are encoded with UTF-16.
+    UTF16,
+    /// Strings are encoded with compact UTF-16 (i.e. Latin1+UTF-16).
+    CompactUTF16,
+}
+
+impl std::str::FromStr for StringEncoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "utf8" => Ok(StringEncoding::UTF8),
+            "utf16" => Ok(StringEncoding::UTF16),
+            "compact-utf16" => Ok(StringEncoding::CompactUTF16),
+            _ => bail!("unknown string encoding `{}`", s),
+        }
+    }
+}
+
+/// The inclusive bounds an integer parameter is limited to by a
+/// `@range(min, max)` annotation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Range {
+    pub min: i128,
+    pub max: i128,
+}
+
+impl Range {
+    pub fn contains(&self, value: i128) -> bool {
+        self.min <= value && value <= self.max
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -269,6 +355,14 @@ pub enum FunctionKind {
 }
 
 impl Function {
+    /// Returns the bounds of the parameter `param`, if it has any.
+    pub fn range(&self, param: &str) -> Option<Range> {
+        self.ranges
+            .iter()
+            .find(|(name, _)| name == param)
+            .map(|(_, range)| *range)
+    }
+
     pub fn item_name(&self) -> &str {
         match &self.kind {
             FunctionKind::Freestanding => &self.name,
@@ -313,21 +407,39 @@ impl Interface {
     }
 
     pub fn parse_file(path: impl AsRef<Path>) -> Result<Interface> {
+        Interface::parse_file_with_cfg(path, &Cfg::default())
+    }
+
+    /// Like [`Interface::parse_file`], but keeps the items annotated with
+    /// `@cfg` whose settings are set in `cfg`.
+    pub fn parse_file_with_cfg(path: impl AsRef<Path>, cfg: &Cfg) -> Result<Interface> {
         let path = path.as_ref();
         let parent = path.parent().unwrap();
         let contents = std::fs::read_to_string(path)
             .with_context(|| format!("failed to read: {}", path.display()))?;
-        Interface::parse_with(path, &contents, |path| load_fs(parent, path))
+        Interface::parse_with_cfg(path, &contents, cfg, |path| load_fs(parent, path))
     }
 
     pub fn parse_with(
         filename: impl AsRef<Path>,
         contents: &str,
+        load: impl FnMut(&str) -> Result<(PathBuf, String)>,
+    ) -> Result<Interface> {
+        Interface::parse_with_cfg(filename, contents, &Cfg::default(), load)
+    }
+
+    /// Like [`Interface::parse_with`], but keeps the items annotated with
+    /// `@cfg` whose settings are set in `cfg`.
+    pub fn parse_with_cfg(
+        filename: impl AsRef<Path>,
+        contents: &str,
+        cfg: &Cfg,
         mut load: impl FnMut(&str) -> Result<(PathBuf, String)>,
     ) -> Result<Interface> {
         Interface::_parse_with(
             filename.as_ref(),
             contents,
+            cfg,
             &mut load,
             &mut HashSet::new(),
             &mut HashMap::new(),
@@ -337,6 +449,7 @@ impl Interface {
     fn _parse_with(
         filename: &Path,
         contents: &str,
+        cfg: &Cfg,
         load: &mut dyn FnMut(&str) -> Result<(PathBuf, String)>,
         visiting: &mut HashSet<PathBuf>,
         map: &mut HashMap<String, Interface>,
@@ -361,7 +474,7 @@ impl Interface {
         }
 
         // Parse the `contents `into an AST
-        let ast = match ast::Ast::parse(contents) {
+        let ast = match ast::Ast::parse(contents, Some(cfg)) {
             Ok(ast) => ast,
             Err(mut e) => {
                 let file = filename.display().to_string();
@@ -385,13 +498,31 @@ impl Interface {
             let (filename, contents) = load(&u.from[0].name)
                 // TODO: insert context here about `u.name.span` and `filename`
                 ?;
-            let instance = Interface::_parse_with(&filename, &contents, load, visiting, map)?;
+            let instance = Interface::_parse_with(&filename, &contents, cfg, load, visiting, map)?;
             map.insert(u.from[0].name.to_string(), instance);
         }
+
+        // Load up any included files too, which are found the same way as the
+        // modules of `use` items.
+        let mut includes = HashMap::new();
+        for item in ast.items.iter() {
+            let i = match item {
+                ast::Item::Include(i) => i,
+                _ => continue,
+            };
+            if includes.contains_key(&i.path) {
+                continue;
+            }
+            let module = i.path.strip_suffix(".md").unwrap_or(&i.path);
+            let module = module.strip_suffix(".wai").unwrap_or(module);
+            let (filename, contents) = load(module)?;
+            let instance = Interface::_parse_with(&filename, &contents, cfg, load, visiting, map)?;
+            includes.insert(i.path.clone(), instance);
+        }
         visiting.remove(filename);
 
         // and finally resolve everything into our final instance
-        match ast.resolve(name, map) {
+        match ast.resolve(name, map, &includes) {
             Ok(i) => Ok(i),
             Err(mut e) => {
                 let file = filename.display().to_string();
@@ -506,6 +637,13 @@ impl Interface {
             None
         }
     }
+
+    /// Returns the encoding of strings passed to and from `func`, which is
+    /// its own `@string-encoding` or otherwise the interface's, if either is
+    /// given.
+    pub fn string_encoding_of(&self, func: &Function) -> Option<StringEncoding> {
+        func.string_encoding.or(self.string_encoding)
+    }
 }
 
 fn load_fs(root: &Path, name: &str) -> Result<(PathBuf, String)> {
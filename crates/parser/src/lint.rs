@@ -0,0 +1,270 @@
+//! Lints for `*.wai` files, which flag interfaces that are valid but likely
+//! to be awkward to use from some languages or to evolve.
+//!
+//! Lints are [`Diagnostic`]s whose code is the name of the rule that found
+//! them:
+//!
+//! * `kebab-case`: names should only use lowercase ASCII letters and digits,
+//!   since not every language can spell other characters in identifiers.
+//!   The parser already rejects uppercase letters and underscores.
+//! * `large-record`: records should have at most
+//!   [`Config::max_record_fields`] fields.
+//! * `enum-unknown-case`: enums should have an `unknown` case, so that
+//!   there's somewhere to put cases added by newer versions of an interface,
+//!   unless they're `@non-exhaustive`.
+//! * `too-many-params`: functions should take at most [`Config::max_params`]
+//!   parameters.
+//! * `missing-docs`: types, resources, functions, globals and interfaces
+//!   should have doc comments.
+
+use crate::syntax::{Item, ItemKind, Name, Span, SyntaxTree};
+use crate::Diagnostic;
+use anyhow::{bail, Result};
+
+/// The name of every rule, along with what it checks.
+pub const RULES: &[(&str, &str)] = &[
+    (
+        "kebab-case",
+        "names only use lowercase ASCII letters and digits",
+    ),
+    ("large-record", "records don't have too many fields"),
+    ("enum-unknown-case", "enums have an `unknown` case"),
+    (
+        "too-many-params",
+        "functions don't take too many parameters",
+    ),
+    ("missing-docs", "items have doc comments"),
+];
+
+/// Which rules to check and how.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// The names of rules not to check.
+    pub allow: Vec<String>,
+    /// The most fields a record can have before `large-record` flags it.
+    pub max_record_fields: usize,
+    /// The most parameters a function can take before `too-many-params`
+    /// flags it.
+    pub max_params: usize,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            allow: Vec::new(),
+            max_record_fields: 16,
+            max_params: 8,
+        }
+    }
+}
+
+/// Checks the `*.wai` file `filename`, whose contents are `contents`, and
+/// returns its lints in the order they appear in the file.
+///
+/// Like [`Interface::parse_file`](crate::Interface::parse_file), files
+/// ending in `.md` are markdown whose `wai` code blocks are checked.
+pub fn lint(filename: &str, contents: &str, config: &Config) -> Result<Vec<Diagnostic>> {
+    for rule in config.allow.iter() {
+        if !RULES.iter().any(|(name, _)| name == rule) {
+            bail!("unknown lint rule `{}`", rule);
+        }
+    }
+
+    let md_contents;
+    let mut contents = contents;
+    if filename.ends_with(".md") {
+        md_contents = crate::unwrap_md(contents);
+        contents = &md_contents;
+    }
+
+    let tree = SyntaxTree::parse(filename, contents)?;
+    let mut linter = Linter {
+        filename,
+        contents,
+        config,
+        tree: &tree,
+        lints: Vec::new(),
+    };
+    for item in tree.items.iter() {
+        linter.item(item);
+    }
+    let mut lints = linter.lints;
+    lints.sort_by_key(|lint| lint.span.start);
+    Ok(lints)
+}
+
+struct Linter<'a> {
+    filename: &'a str,
+    contents: &'a str,
+    config: &'a Config,
+    tree: &'a SyntaxTree,
+    lints: Vec<Diagnostic>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, rule: &'static str, span: Span, message: String) {
+        if self.config.allow.iter().any(|r| r == rule) {
+            return;
+        }
+        self.lints.push(Diagnostic::new(
+            span.start as usize,
+            Some(span.end as usize),
+            self.filename,
+            self.contents,
+            rule,
+            message,
+        ));
+    }
+
+    fn item(&mut self, item: &Item) {
+        // The names imported by `use` items are checked where they're
+        // defined.
+        let name = match &item.name {
+            Some(name) => name,
+            None => return,
+        };
+        self.name(name);
+        for member in item.members.iter() {
+            self.name(member);
+        }
+
+        match item.kind {
+            ItemKind::Record if item.members.len() > self.config.max_record_fields => {
+                self.report(
+                    "large-record",
+                    name.span,
+                    format!(
+                        "record `{}` has {} fields, more than the {} allowed",
+                        name.name,
+                        item.members.len(),
+                        self.config.max_record_fields
+                    ),
+                );
+            }
+            ItemKind::Enum
+                if !item.non_exhaustive && !item.members.iter().any(|m| m.name == "unknown") =>
+            {
+                self.report(
+                    "enum-unknown-case",
+                    name.span,
+                    format!("enum `{}` has no `unknown` case", name.name),
+                );
+            }
+            ItemKind::Function if item.members.len() > self.config.max_params => {
+                self.report(
+                    "too-many-params",
+                    name.span,
+                    format!(
+                        "function `{}` takes {} parameters, more than the {} allowed",
+                        name.name,
+                        item.members.len(),
+                        self.config.max_params
+                    ),
+                );
+            }
+            _ => {}
+        }
+
+        if !self.has_docs(item) {
+            self.report(
+                "missing-docs",
+                name.span,
+                format!("`{}` has no doc comment", name.name),
+            );
+        }
+
+        for item in item.items.iter() {
+            self.item(item);
+        }
+    }
+
+    fn name(&mut self, name: &Name) {
+        let valid = name
+            .name
+            .chars()
+            .all(|c| c == '-' || c.is_ascii_lowercase() || c.is_ascii_digit());
+        if !valid {
+            self.report(
+                "kebab-case",
+                name.span,
+                format!(
+                    "`{}` should only use lowercase ASCII letters and digits",
+                    name.name
+                ),
+            );
+        }
+    }
+
+    /// Returns whether any of the comments in front of `item` is a doc
+    /// comment.
+    fn has_docs(&self, item: &Item) -> bool {
+        let tokens = &self.tree.tokens;
+        let first = tokens.partition_point(|t| t.span.start < item.span.start);
+        tokens[first..]
+            .iter()
+            .take_while(|t| t.is_trivia())
+            .any(|t| {
+                let text = &self.contents[t.span.start as usize..t.span.end as usize];
+                text.starts_with("///") || (text.starts_with("/**") && text != "/**/")
+            })
+    }
+}
+
+#[test]
+fn test_lint() {
+    let input = "\
+/// A color.
+enum color { red, grün }
+
+/// A size.
+@non-exhaustive
+enum size { small, large }
+
+/// A point.
+record point { x: u32, y: u32, z: u32 }
+
+resource r {
+  /// Makes one.
+  static new: func() -> r
+}
+
+/// Draws.
+draw: func(a: u32, b: u32, c: u32)
+";
+    let config = Config {
+        max_record_fields: 2,
+        max_params: 2,
+        ..Config::default()
+    };
+    let lints = lint("foo.wai", input, &config).unwrap();
+    let codes = lints.iter().map(|l| l.code).collect::<Vec<_>>();
+    assert_eq!(
+        codes,
+        [
+            "enum-unknown-case",
+            "kebab-case",
+            "large-record",
+            "missing-docs",
+            "too-many-params"
+        ]
+    );
+    assert_eq!(
+        lints[1].message,
+        "`grün` should only use lowercase ASCII letters and digits"
+    );
+    assert_eq!((lints[3].line, lints[3].column), (11, 10));
+
+    let config = Config {
+        allow: vec!["missing-docs".to_string(), "enum-unknown-case".to_string()],
+        ..Config::default()
+    };
+    let lints = lint("foo.wai", input, &config).unwrap();
+    let codes = lints.iter().map(|l| l.code).collect::<Vec<_>>();
+    assert_eq!(codes, ["kebab-case"]);
+
+    let config = Config {
+        allow: vec!["nope".to_string()],
+        ..Config::default()
+    };
+    assert!(lint("foo.wai", input, &config).is_err());
+}
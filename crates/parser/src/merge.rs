@@ -0,0 +1,427 @@
+use crate::*;
+
+/// The kind of definition named by a [`Rename`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemKind {
+    Type,
+    Resource,
+    Function,
+    Global,
+}
+
+/// A definition of the interface given to [`Interface::merge`] which was
+/// renamed because the interface merged into already has a different
+/// definition of the same name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rename {
+    pub kind: ItemKind,
+    pub from: String,
+    pub to: String,
+}
+
+impl Interface {
+    /// Merges the definitions of `other` into this interface, so that many
+    /// small interfaces can be combined into one.
+    ///
+    /// Definitions which are the same in both interfaces, ignoring their
+    /// documentation, are only kept once, as are identical anonymous types.
+    /// Any other definition of `other` whose name is already taken is
+    /// renamed to `{name}-v2`, `{name}-v3` and so on, and the renames made
+    /// are returned. Types and resources which both interfaces `use` from
+    /// the same module can't be renamed, so differing definitions of those
+    /// are an error, and this interface is left unchanged.
+    pub fn merge(&mut self, other: &Interface) -> Result<Vec<Rename>> {
+        let mut merged = self.clone();
+        let mut merger = Merger::default();
+        merger.merge(&mut merged, other)?;
+        *self = merged;
+        Ok(merger.renames)
+    }
+}
+
+#[derive(Default)]
+struct Merger {
+    types: HashMap<TypeId, TypeId>,
+    resources: HashMap<ResourceId, ResourceId>,
+    renames: Vec<Rename>,
+}
+
+impl Merger {
+    fn merge(&mut self, dst: &mut Interface, src: &Interface) -> Result<()> {
+        let mut interfaces = src.interface_lookup.iter().collect::<Vec<_>>();
+        interfaces.sort();
+        for (name, id) in interfaces {
+            match dst.interface_lookup.get(name) {
+                Some(dst_id) => {
+                    let renames = dst.interfaces[*dst_id].merge(&src.interfaces[*id])?;
+                    if let Some(rename) = renames.first() {
+                        bail!(
+                            "conflicting definitions of `{}` used from `{}`",
+                            rename.from,
+                            name
+                        );
+                    }
+                }
+                None => {
+                    let id = dst.interfaces.alloc(src.interfaces[*id].clone());
+                    dst.interface_lookup.insert(name.clone(), id);
+                }
+            }
+        }
+
+        for (id, resource) in src.resources.iter() {
+            let dst_id = match dst.resource_lookup.get(&resource.name) {
+                Some(dst_id) if same_resource(&dst.resources[*dst_id], resource) => *dst_id,
+                existing => {
+                    let mut resource = resource.clone();
+                    if existing.is_some() {
+                        if let Some(module) = &resource.foreign_module {
+                            bail!(
+                                "conflicting definitions of resource `{}` used from `{}`",
+                                resource.name,
+                                module
+                            );
+                        }
+                        resource.name = self.rename(dst, ItemKind::Resource, &resource.name);
+                    }
+                    let name = resource.name.clone();
+                    let dst_id = dst.resources.alloc(resource);
+                    dst.resource_lookup.insert(name, dst_id);
+                    dst_id
+                }
+            };
+            self.resources.insert(id, dst_id);
+        }
+
+        for id in src.topological_types() {
+            let def = &src.types[id];
+            let kind = self.map_kind(&def.kind);
+            let existing = match &def.name {
+                Some(name) => dst.type_lookup.get(name).copied(),
+                None => dst
+                    .types
+                    .iter()
+                    .find(|(_, t)| t.name.is_none() && same_kind(&t.kind, &kind))
+                    .map(|(id, _)| id),
+            };
+            let dst_id = match existing {
+                Some(dst_id)
+                    if same_kind(&dst.types[dst_id].kind, &kind)
+                        && dst.types[dst_id].foreign_module == def.foreign_module =>
+                {
+                    dst_id
+                }
+                _ => {
+                    let mut name = def.name.clone();
+                    if let (Some(_), Some(n)) = (existing, &name) {
+                        if let Some(module) = &def.foreign_module {
+                            bail!(
+                                "conflicting definitions of type `{}` used from `{}`",
+                                n,
+                                module
+                            );
+                        }
+                        name = Some(self.rename(dst, ItemKind::Type, n));
+                    }
+                    let dst_id = dst.types.alloc(TypeDef {
+                        docs: def.docs.clone(),
+                        kind,
+                        name: name.clone(),
+                        foreign_module: def.foreign_module.clone(),
+                    });
+                    if let Some(name) = name {
+                        dst.type_lookup.insert(name, dst_id);
+                    }
+                    dst_id
+                }
+            };
+            self.types.insert(id, dst_id);
+        }
+
+        for func in src.functions.iter() {
+            let mut func = Function {
+                kind: match &func.kind {
+                    FunctionKind::Freestanding => FunctionKind::Freestanding,
+                    FunctionKind::Static { resource, name } => FunctionKind::Static {
+                        resource: self.resources[resource],
+                        name: name.clone(),
+                    },
+                    FunctionKind::Method { resource, name } => FunctionKind::Method {
+                        resource: self.resources[resource],
+                        name: name.clone(),
+                    },
+                },
+                params: func
+                    .params
+                    .iter()
+                    .map(|(name, ty)| (name.clone(), self.map_ty(ty)))
+                    .collect(),
+                result: self.map_ty(&func.result),
+                ..func.clone()
+            };
+            // The names of methods include the name of their resource, which
+            // may have been renamed.
+            if let FunctionKind::Static { resource, name }
+            | FunctionKind::Method { resource, name } = &func.kind
+            {
+                func.name = format!("{}::{}", dst.resources[*resource].name, name);
+            }
+            match dst.functions.iter().find(|f| f.name == func.name) {
+                Some(existing) if same_function(existing, &func) => continue,
+                Some(_) => {
+                    let to = self.rename(dst, ItemKind::Function, func.item_name());
+                    match &mut func.kind {
+                        FunctionKind::Freestanding => func.name = to,
+                        FunctionKind::Static { resource, name }
+                        | FunctionKind::Method { resource, name } => {
+                            func.name = format!("{}::{}", dst.resources[*resource].name, to);
+                            *name = to;
+                        }
+                    }
+                }
+                None => {}
+            }
+            dst.functions.push(func);
+        }
+
+        for global in src.globals.iter() {
+            let mut global = Global {
+                ty: self.map_ty(&global.ty),
+                ..global.clone()
+            };
+            match dst.globals.iter().find(|g| g.name == global.name) {
+                Some(existing) if existing.ty == global.ty => continue,
+                Some(_) => global.name = self.rename(dst, ItemKind::Global, &global.name),
+                None => {}
+            }
+            dst.globals.push(global);
+        }
+
+        Ok(())
+    }
+
+    /// Picks a name for a `kind` named `name` which isn't taken in `dst`,
+    /// recording the rename.
+    fn rename(&mut self, dst: &Interface, kind: ItemKind, name: &str) -> String {
+        let taken = |n: &str| match kind {
+            ItemKind::Type | ItemKind::Resource => {
+                dst.type_lookup.contains_key(n) || dst.resource_lookup.contains_key(n)
+            }
+            ItemKind::Function => dst.functions.iter().any(|f| f.item_name() == n),
+            ItemKind::Global => dst.globals.iter().any(|g| g.name == n),
+        };
+        let to = (2..)
+            .map(|i| format!("{}-v{}", name, i))
+            .find(|n| !taken(n))
+            .unwrap();
+        self.renames.push(Rename {
+            kind,
+            from: name.to_string(),
+            to: to.clone(),
+        });
+        to
+    }
+
+    fn map_ty(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Id(id) => Type::Id(self.types[id]),
+            Type::Handle(r) => Type::Handle(self.resources[r]),
+            other => *other,
+        }
+    }
+
+    fn map_kind(&self, kind: &TypeDefKind) -> TypeDefKind {
+        match kind {
+            TypeDefKind::Record(r) => TypeDefKind::Record(Record {
+                fields: r
+                    .fields
+                    .iter()
+                    .map(|f| Field {
+                        ty: self.map_ty(&f.ty),
+                        ..f.clone()
+                    })
+                    .collect(),
+            }),
+            TypeDefKind::Flags(f) => TypeDefKind::Flags(f.clone()),
+            TypeDefKind::Tuple(t) => TypeDefKind::Tuple(Tuple {
+                types: t.types.iter().map(|t| self.map_ty(t)).collect(),
+            }),
+            TypeDefKind::Variant(v) => TypeDefKind::Variant(Variant {
+                cases: v
+                    .cases
+                    .iter()
+                    .map(|c| Case {
+                        ty: self.map_ty(&c.ty),
+                        ..c.clone()
+                    })
+                    .collect(),
+                non_exhaustive: v.non_exhaustive,
+            }),
+            TypeDefKind::Enum(e) => TypeDefKind::Enum(e.clone()),
+            TypeDefKind::Option(t) => TypeDefKind::Option(self.map_ty(t)),
+            TypeDefKind::Expected(e) => TypeDefKind::Expected(Expected {
+                ok: self.map_ty(&e.ok),
+                err: self.map_ty(&e.err),
+            }),
+            TypeDefKind::Union(u) => TypeDefKind::Union(Union {
+                cases: u
+                    .cases
+                    .iter()
+                    .map(|c| UnionCase {
+                        docs: c.docs.clone(),
+                        ty: self.map_ty(&c.ty),
+                    })
+                    .collect(),
+            }),
+            TypeDefKind::List(t) => TypeDefKind::List(self.map_ty(t)),
+            TypeDefKind::Future(t) => TypeDefKind::Future(self.map_ty(t)),
+            TypeDefKind::Stream(s) => TypeDefKind::Stream(Stream {
+                element: self.map_ty(&s.element),
+                end: self.map_ty(&s.end),
+            }),
+            TypeDefKind::Type(t) => TypeDefKind::Type(self.map_ty(t)),
+        }
+    }
+}
+
+fn same_resource(a: &Resource, b: &Resource) -> bool {
+    a.name == b.name && a.supertype == b.supertype && a.foreign_module == b.foreign_module
+}
+
+/// Returns whether `a` and `b`, whose types are in the same interface, are
+/// the same apart from their documentation.
+fn same_kind(a: &TypeDefKind, b: &TypeDefKind) -> bool {
+    fn names<'a>(names: impl Iterator<Item = &'a String>) -> Vec<&'a String> {
+        names.collect()
+    }
+    match (a, b) {
+        (TypeDefKind::Record(a), TypeDefKind::Record(b)) => {
+            a.fields.len() == b.fields.len()
+                && a.fields
+                    .iter()
+                    .zip(&b.fields)
+                    .all(|(a, b)| a.name == b.name && a.ty == b.ty)
+        }
+        (TypeDefKind::Flags(a), TypeDefKind::Flags(b)) => {
+            names(a.flags.iter().map(|f| &f.name)) == names(b.flags.iter().map(|f| &f.name))
+        }
+        (TypeDefKind::Variant(a), TypeDefKind::Variant(b)) => {
+            a.cases.len() == b.cases.len()
+                && a.cases
+                    .iter()
+                    .zip(&b.cases)
+                    .all(|(a, b)| a.name == b.name && a.ty == b.ty)
+        }
+        (TypeDefKind::Enum(a), TypeDefKind::Enum(b)) => {
+            names(a.cases.iter().map(|c| &c.name)) == names(b.cases.iter().map(|c| &c.name))
+        }
+        (TypeDefKind::Union(a), TypeDefKind::Union(b)) => a
+            .cases
+            .iter()
+            .map(|c| c.ty)
+            .eq(b.cases.iter().map(|c| c.ty)),
+        (a, b) => a == b,
+    }
+}
+
+fn same_function(a: &Function, b: &Function) -> bool {
+    a.is_async == b.is_async
+        && a.kind == b.kind
+        && a.params == b.params
+        && a.result == b.result
+        && a.requires == b.requires
+        && a.ranges == b.ranges
+}
+
+#[test]
+fn test_merge() {
+    let mut a = Interface::parse(
+        "a.wai",
+        "
+            record point { x: u32, y: u32 }
+            enum mode { fast, slow }
+            resource blob { size: func() -> u32 }
+            area: func(p: point) -> u32
+            run: func(m: mode, points: list<point>)
+        ",
+    )
+    .unwrap();
+    let b = Interface::parse(
+        "b.wai",
+        "
+            /// The same point, documented.
+            record point { x: u32, y: u32 }
+            enum mode { on, off }
+            resource blob { size: func() -> u32 }
+            area: func(p: point) -> u32
+            run: func(m: mode, points: list<point>)
+            toggle: func(m: mode) -> list<point>
+        ",
+    )
+    .unwrap();
+
+    let anonymous = |i: &Interface| i.types.iter().filter(|(_, t)| t.name.is_none()).count();
+    let before = anonymous(&a);
+    let renames = a.merge(&b).unwrap();
+    assert_eq!(
+        renames,
+        [
+            Rename {
+                kind: ItemKind::Type,
+                from: "mode".to_string(),
+                to: "mode-v2".to_string(),
+            },
+            Rename {
+                kind: ItemKind::Function,
+                from: "run".to_string(),
+                to: "run-v2".to_string(),
+            },
+        ]
+    );
+
+    let names = a
+        .functions
+        .iter()
+        .map(|f| f.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["blob::size", "area", "run", "run-v2", "toggle"]);
+    assert_eq!(a.resources.len(), 1);
+    let named = |name: &str| Type::Id(a.type_lookup[name]);
+    let run_v2 = &a.functions[3];
+    assert_eq!(run_v2.params[0].1, named("mode-v2"));
+    // Anonymous types like `list<point>` are shared by both interfaces'
+    // functions.
+    assert_eq!(run_v2.params[1].1, a.functions[2].params[1].1);
+    assert_eq!(a.functions[4].result, a.functions[2].params[1].1);
+    assert_eq!(anonymous(&a), before);
+}
+
+#[test]
+fn test_merge_conflicting_use() {
+    let load = |src: &'static str| {
+        move |name: &str| {
+            assert_eq!(name, "shared");
+            Ok((PathBuf::from("shared.wai"), src.to_string()))
+        }
+    };
+    let mut a = Interface::parse_with(
+        "a.wai",
+        "use { config } from shared\nf: func(c: config)",
+        load("record config { a: u32 }"),
+    )
+    .unwrap();
+    let b = Interface::parse_with(
+        "b.wai",
+        "use { config } from shared\ng: func(c: config)",
+        load("record config { a: string }"),
+    )
+    .unwrap();
+    let before = a.clone();
+    let err = a.merge(&b).unwrap_err();
+    assert!(
+        err.to_string().contains("conflicting definitions"),
+        "{}",
+        err
+    );
+    assert_eq!(a, before);
+}
@@ -1,19 +1,60 @@
-use crate::{FlagsRepr, Int, Interface, Type, TypeDef, TypeDefKind};
+use crate::{FlagsRepr, Int, Interface, Type, TypeDef, TypeDefKind, TypeId};
 
 #[derive(Default)]
 pub struct SizeAlign {
     map: Vec<(usize, usize)>,
+    /// The offset of each field of records and tuples, computed once by
+    /// `fill` since they're looked up every time one is read or written.
+    fields: Vec<Vec<usize>>,
+    /// The offset of the payload of variant-like types.
+    payloads: Vec<usize>,
 }
 
 impl SizeAlign {
     pub fn fill(&mut self, iface: &Interface) {
         self.map = vec![(0, 0); iface.types.len()];
+        self.fields = vec![Vec::new(); iface.types.len()];
+        self.payloads = vec![0; iface.types.len()];
         for ty in iface.topological_types() {
-            let pair = self.calculate(&iface.types[ty]);
-            self.map[ty.index()] = pair;
+            let def = &iface.types[ty];
+            self.map[ty.index()] = self.calculate(def);
+            match &def.kind {
+                TypeDefKind::Record(r) => {
+                    self.fields[ty.index()] = self.field_offsets(r.fields.iter().map(|f| &f.ty));
+                }
+                TypeDefKind::Tuple(t) => {
+                    self.fields[ty.index()] = self.field_offsets(t.types.iter());
+                }
+                TypeDefKind::Variant(v) => {
+                    self.payloads[ty.index()] =
+                        self.payload_offset(v.tag(), v.cases.iter().map(|c| &c.ty));
+                }
+                TypeDefKind::Option(t) => {
+                    self.payloads[ty.index()] = self.payload_offset(Int::U8, [&Type::Unit, t]);
+                }
+                TypeDefKind::Expected(e) => {
+                    self.payloads[ty.index()] = self.payload_offset(Int::U8, [&e.ok, &e.err]);
+                }
+                TypeDefKind::Union(u) => {
+                    self.payloads[ty.index()] =
+                        self.payload_offset(u.tag(), u.cases.iter().map(|c| &c.ty));
+                }
+                _ => {}
+            }
         }
     }
 
+    /// Returns the offsets of the fields of the record or tuple `id`.
+    pub fn fields_of(&self, id: TypeId) -> &[usize] {
+        &self.fields[id.index()]
+    }
+
+    /// Returns the offset of the payload of the variant, option, expected or
+    /// union `id`.
+    pub fn payload_offset_of(&self, id: TypeId) -> usize {
+        self.payloads[id.index()]
+    }
+
     fn calculate(&self, ty: &TypeDef) -> (usize, usize) {
         match &ty.kind {
             TypeDefKind::Type(t) => (self.size(t), self.align(t)),
@@ -95,15 +136,19 @@ impl SizeAlign {
 
     fn variant<'a>(&self, tag: Int, types: impl IntoIterator<Item = &'a Type>) -> (usize, usize) {
         let (discrim_size, discrim_align) = int_size_align(tag);
-        let mut size = discrim_size;
+        let mut case_size = 0;
         let mut align = discrim_align;
         for ty in types {
-            let case_size = self.size(ty);
-            let case_align = self.align(ty);
-            align = align.max(case_align);
-            size = size.max(align_to(discrim_size, case_align) + case_size);
+            case_size = case_size.max(self.size(ty));
+            align = align.max(self.align(ty));
         }
-        (size, align)
+        // All payloads start at the same offset, just past the discriminant
+        // and aligned for the most-aligned case, and the whole variant is
+        // padded so consecutive elements of a list stay aligned.
+        (
+            align_to(align_to(discrim_size, align) + case_size, align),
+            align,
+        )
     }
 }
 
@@ -119,3 +164,40 @@ fn int_size_align(i: Int) -> (usize, usize) {
 pub(crate) fn align_to(val: usize, align: usize) -> usize {
     (val + align - 1) & !(align - 1)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variant_payload_fits() {
+        let iface = Interface::parse(
+            "input.wai",
+            "
+                type bytes = tuple<u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8>
+                variant v { a(bytes), b(u64) }
+                type e = expected<expected<u32, bool>, u64>
+            ",
+        )
+        .unwrap();
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&iface);
+        for (id, ty) in iface.types.iter() {
+            let cases = match &ty.kind {
+                TypeDefKind::Variant(v) => v.cases.iter().map(|c| c.ty).collect::<Vec<_>>(),
+                TypeDefKind::Expected(e) => vec![e.ok, e.err],
+                _ => continue,
+            };
+            let tag = match &ty.kind {
+                TypeDefKind::Variant(v) => v.tag(),
+                _ => Int::U8,
+            };
+            let ty = Type::Id(id);
+            let offset = sizes.payload_offset(tag, cases.iter());
+            for case in cases.iter() {
+                assert!(offset + sizes.size(case) <= sizes.size(&ty));
+            }
+            assert_eq!(sizes.size(&ty) % sizes.align(&ty), 0);
+        }
+    }
+}
@@ -116,7 +116,13 @@ impl Runner<'_> {
             }
         } else {
             let instance = result?;
-            to_json(&instance)
+            let decoded = Interface::from_binary(&instance.to_binary())
+                .context("failed to decode binary interface")?;
+            let json = to_json(&instance);
+            if to_json(&decoded) != json {
+                bail!("interface changed after a binary round trip");
+            }
+            json
         };
 
         // "foo.wai" => "foo.wai.result"
@@ -168,6 +174,8 @@ impl Runner<'_> {
 fn to_json(i: &Interface) -> String {
     #[derive(Serialize)]
     struct Interface {
+        #[serde(rename = "string-encoding", skip_serializing_if = "Option::is_none")]
+        string_encoding: Option<&'static str>,
         #[serde(skip_serializing_if = "Vec::is_empty")]
         resources: Vec<Resource>,
         #[serde(skip_serializing_if = "Vec::is_empty")]
@@ -202,17 +210,39 @@ fn to_json(i: &Interface) -> String {
     #[serde(rename_all = "kebab-case")]
     enum Type {
         Primitive(String),
-        Record { fields: Vec<(String, String)> },
-        Flags { flags: Vec<String> },
-        Enum { cases: Vec<String> },
-        Variant { cases: Vec<(String, String)> },
-        Tuple { types: Vec<String> },
+        Record {
+            fields: Vec<(String, String)>,
+        },
+        Flags {
+            flags: Vec<String>,
+        },
+        Enum {
+            cases: Vec<String>,
+            #[serde(rename = "non-exhaustive", skip_serializing_if = "is_false")]
+            non_exhaustive: bool,
+        },
+        Variant {
+            cases: Vec<(String, String)>,
+            #[serde(rename = "non-exhaustive", skip_serializing_if = "is_false")]
+            non_exhaustive: bool,
+        },
+        Tuple {
+            types: Vec<String>,
+        },
         Option(String),
-        Expected { ok: String, err: String },
+        Expected {
+            ok: String,
+            err: String,
+        },
         Future(String),
-        Stream { element: String, end: String },
+        Stream {
+            element: String,
+            end: String,
+        },
         List(String),
-        Union { cases: Vec<String> },
+        Union {
+            cases: Vec<String>,
+        },
     }
 
     #[derive(Serialize)]
@@ -222,6 +252,12 @@ fn to_json(i: &Interface) -> String {
         is_async: Option<bool>,
         params: Vec<String>,
         result: String,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        requires: Vec<String>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        ranges: Vec<String>,
+        #[serde(rename = "string-encoding", skip_serializing_if = "Option::is_none")]
+        string_encoding: Option<&'static str>,
     }
 
     #[derive(Serialize)]
@@ -258,6 +294,13 @@ fn to_json(i: &Interface) -> String {
             is_async: if f.is_async { Some(f.is_async) } else { None },
             params: f.params.iter().map(|(_, ty)| translate_type(ty)).collect(),
             result: translate_type(&f.result),
+            requires: f.requires.clone(),
+            ranges: f
+                .ranges
+                .iter()
+                .map(|(name, r)| format!("{}: {}..={}", name, r.min, r.max))
+                .collect(),
+            string_encoding: f.string_encoding.map(translate_string_encoding),
         })
         .collect::<Vec<_>>();
     let globals = i
@@ -270,6 +313,7 @@ fn to_json(i: &Interface) -> String {
         .collect::<Vec<_>>();
 
     let iface = Interface {
+        string_encoding: i.string_encoding.map(translate_string_encoding),
         resources,
         types,
         functions,
@@ -277,6 +321,14 @@ fn to_json(i: &Interface) -> String {
     };
     return serde_json::to_string_pretty(&iface).unwrap();
 
+    fn translate_string_encoding(encoding: StringEncoding) -> &'static str {
+        match encoding {
+            StringEncoding::UTF8 => "utf8",
+            StringEncoding::UTF16 => "utf16",
+            StringEncoding::CompactUTF16 => "compact-utf16",
+        }
+    }
+
     fn translate_typedef(ty: &wai_parser::TypeDef) -> Type {
         match &ty.kind {
             TypeDefKind::Type(t) => Type::Primitive(translate_type(t)),
@@ -295,6 +347,7 @@ fn to_json(i: &Interface) -> String {
             },
             TypeDefKind::Enum(r) => Type::Enum {
                 cases: r.cases.iter().map(|f| f.name.clone()).collect(),
+                non_exhaustive: r.non_exhaustive,
             },
             TypeDefKind::Variant(v) => Type::Variant {
                 cases: v
@@ -302,6 +355,7 @@ fn to_json(i: &Interface) -> String {
                     .iter()
                     .map(|f| (f.name.clone(), translate_type(&f.ty)))
                     .collect(),
+                non_exhaustive: v.non_exhaustive,
             },
             TypeDefKind::Option(t) => Type::Option(translate_type(t)),
             TypeDefKind::Expected(e) => Type::Expected {
@@ -341,4 +395,8 @@ fn to_json(i: &Interface) -> String {
             Type::Id(id) => format!("type-{}", id.index()),
         }
     }
+
+    fn is_false(b: &bool) -> bool {
+        !b
+    }
 }
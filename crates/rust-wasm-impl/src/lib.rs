@@ -1,10 +1,15 @@
 use std::path::{Path, PathBuf};
 
+use heck::*;
 use proc_macro::TokenStream;
+use quote::{format_ident, quote};
 use syn::parse::{Error, Parse, ParseStream, Result};
 use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
 use syn::{token, Token};
-use wai_bindgen_gen_core::{wai_parser::Interface, Direction, Files, Generator};
+use wai_bindgen_gen_core::wai_parser::{Function, FunctionKind, Interface};
+use wai_bindgen_gen_core::{parse_interface_arg, Direction, Files, Filter, Generator};
+use wai_bindgen_gen_rust::to_rust_ident;
 
 #[proc_macro]
 pub fn import(input: TokenStream) -> TokenStream {
@@ -16,13 +21,36 @@ pub fn export(input: TokenStream) -> TokenStream {
     run(input, Direction::Export)
 }
 
+/// Exports an interface from the functions of an inherent `impl` block, as an
+/// alternative to `export!` and implementing its trait by hand.
+///
+/// Takes the same arguments as `export!` but only a single interface. The
+/// `impl` block is checked against the interface's freestanding functions, so
+/// missing, unknown or mismatched functions are reported on the `impl` block
+/// itself. Resources are still implemented through their generated traits.
+#[proc_macro_attribute]
+pub fn export_impl(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(attr as Opts);
+    let item = syn::parse_macro_input!(item as syn::ItemImpl);
+    match expand_export_impl(input, item) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
 fn run(input: TokenStream, dir: Direction) -> TokenStream {
     let input = syn::parse_macro_input!(input as Opts);
+    generate(input, dir).parse().unwrap()
+}
+
+fn generate(mut input: Opts, dir: Direction) -> String {
     let mut gen = input.opts.build();
     let mut files = Files::default();
+    let mut interfaces = std::mem::take(&mut input.interfaces);
+    input.filter.apply(&mut interfaces);
     let (imports, exports) = match dir {
-        Direction::Import => (input.interfaces, vec![]),
-        Direction::Export => (vec![], input.interfaces),
+        Direction::Import => (interfaces, vec![]),
+        Direction::Export => (vec![], interfaces),
     };
     gen.generate_all(&imports, &exports, &mut files);
     let (_, contents) = files.iter().next().unwrap();
@@ -38,20 +66,197 @@ fn run(input: TokenStream, dir: Direction) -> TokenStream {
         ));
     }
 
-    contents.parse().unwrap()
+    contents
+}
+
+fn expand_export_impl(
+    mut input: Opts,
+    mut item: syn::ItemImpl,
+) -> Result<proc_macro2::TokenStream> {
+    if let Some((_, path, _)) = &item.trait_ {
+        return Err(Error::new_spanned(
+            path,
+            "`#[wai::export]` must be used on an inherent `impl` block",
+        ));
+    }
+    if !item.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &item.generics,
+            "`#[wai::export]` can't be used on a generic `impl` block",
+        ));
+    }
+    input.filter.apply(&mut input.interfaces);
+    let iface = match &input.interfaces[..] {
+        [iface] => iface.clone(),
+        _ => {
+            return Err(Error::new(
+                proc_macro2::Span::call_site(),
+                "`#[wai::export]` takes exactly one interface",
+            ))
+        }
+    };
+    check_impl(&iface, &item)?;
+
+    // The generated bindings call into `super::{Trait}`, so name the type of
+    // the `impl` block that way unless it already is.
+    let module = format_ident!("{}", iface.name.to_snake_case());
+    let trait_ = format_ident!("{}", iface.name.to_camel_case());
+    let self_ty = &item.self_ty;
+    let alias = match &**self_ty {
+        syn::Type::Path(p) if p.qself.is_none() && p.path.is_ident(&trait_) => None,
+        _ => Some(quote!(type #trait_ = #self_ty;)),
+    };
+    let async_trait = if iface.functions.iter().any(|f| f.is_async) {
+        Some(quote!(#[wai_bindgen_rust::async_trait(?Send)]))
+    } else {
+        None
+    };
+    for impl_item in item.items.iter_mut() {
+        if let syn::ImplItem::Method(method) = impl_item {
+            method.vis = syn::Visibility::Inherited;
+        }
+    }
+    let attrs = &item.attrs;
+    let items = &item.items;
+    let bindings = generate(input, Direction::Export)
+        .parse::<proc_macro2::TokenStream>()
+        .unwrap();
+
+    Ok(quote! {
+        #bindings
+        #alias
+        #async_trait
+        #(#attrs)*
+        impl #module::#trait_ for #self_ty {
+            #(#items)*
+        }
+    })
+}
+
+/// Checks that the methods of `item` are exactly the freestanding functions
+/// of `iface`, with the same number of parameters and no `self`.
+fn check_impl(iface: &Interface, item: &syn::ItemImpl) -> Result<()> {
+    let expected = iface
+        .functions
+        .iter()
+        .filter(|f| matches!(f.kind, FunctionKind::Freestanding))
+        .map(|f| (to_rust_ident(&f.name), f))
+        .collect::<Vec<_>>();
+    let drops = iface
+        .resources
+        .iter()
+        .map(|(_, r)| format!("drop_{}", r.name.to_snake_case()))
+        .collect::<Vec<_>>();
+
+    let mut errors = Vec::new();
+    let mut found = Vec::new();
+    for impl_item in item.items.iter() {
+        let method = match impl_item {
+            syn::ImplItem::Method(method) => method,
+            _ => continue,
+        };
+        let sig = &method.sig;
+        let name = sig.ident.to_string();
+        let name = name.trim_start_matches("r#");
+        if drops.iter().any(|d| d == name) {
+            continue;
+        }
+        let func = match expected.iter().find(|(n, _)| n == name) {
+            Some((_, func)) => func,
+            None => {
+                errors.push(Error::new_spanned(
+                    &sig.ident,
+                    format!("`{}` is not a function of interface `{}`", name, iface.name),
+                ));
+                continue;
+            }
+        };
+        found.push(name.to_string());
+        if let Some(receiver) = sig.receiver() {
+            errors.push(Error::new_spanned(
+                receiver,
+                "exported functions are called without `self`",
+            ));
+            continue;
+        }
+        if sig.asyncness.is_some() != func.is_async {
+            errors.push(Error::new(
+                sig.fn_token.span(),
+                format!(
+                    "`{}` is {}async in interface `{}`",
+                    name,
+                    if func.is_async { "" } else { "not " },
+                    iface.name
+                ),
+            ));
+        }
+        if sig.inputs.len() != func.params.len() {
+            errors.push(Error::new(
+                sig.paren_token.span,
+                format!(
+                    "`{}` takes {} parameter(s) in interface `{}` ({}), found {}",
+                    name,
+                    func.params.len(),
+                    iface.name,
+                    param_list(func),
+                    sig.inputs.len()
+                ),
+            ));
+        }
+    }
+    for (name, func) in expected.iter() {
+        if !found.contains(name) {
+            errors.push(Error::new_spanned(
+                &item.self_ty,
+                format!(
+                    "missing function `{}` of interface `{}`: `fn {}({})`",
+                    func.name,
+                    iface.name,
+                    name,
+                    param_list(func)
+                ),
+            ));
+        }
+    }
+
+    let mut errors = errors.into_iter();
+    match errors.next() {
+        Some(mut first) => {
+            first.extend(errors);
+            Err(first)
+        }
+        None => Ok(()),
+    }
+}
+
+fn param_list(func: &Function) -> String {
+    func.params
+        .iter()
+        .map(|(name, _)| to_rust_ident(name))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 struct Opts {
     opts: wai_bindgen_gen_rust_wasm::Opts,
     interfaces: Vec<Interface>,
     files: Vec<String>,
+    filter: Filter,
 }
 
 mod kw {
     syn::custom_keyword!(src);
     syn::custom_keyword!(paths);
+    syn::custom_keyword!(only);
+    syn::custom_keyword!(skip);
     syn::custom_keyword!(unchecked);
     syn::custom_keyword!(multi_module);
+    syn::custom_keyword!(export_abi_version);
+    syn::custom_keyword!(embed_interface_hash);
+    syn::custom_keyword!(export_abi_descriptor);
+    syn::custom_keyword!(return_arena);
+    syn::custom_keyword!(panic_hook);
+    syn::custom_keyword!(wasm_bindgen);
 }
 
 impl Parse for Opts {
@@ -59,6 +264,7 @@ impl Parse for Opts {
         let mut opts = wai_bindgen_gen_rust_wasm::Opts::default();
         let call_site = proc_macro2::Span::call_site();
         let mut files = Vec::new();
+        let mut filter = Filter::default();
         let interfaces = if input.peek(token::Brace) {
             let content;
             syn::braced!(content in input);
@@ -68,7 +274,15 @@ impl Parse for Opts {
                 match field.into_value() {
                     ConfigField::Unchecked => opts.unchecked = true,
                     ConfigField::MultiModule => opts.multi_module = true,
-                    ConfigField::Interfaces(v) => interfaces = v,
+                    ConfigField::ExportAbiVersion => opts.export_abi_version = true,
+                    ConfigField::EmbedInterfaceHash => opts.embed_interface_hash = true,
+                    ConfigField::ExportAbiDescriptor => opts.export_abi_descriptor = true,
+                    ConfigField::ReturnArena => opts.return_arena = true,
+                    ConfigField::PanicHook => opts.panic_hook = true,
+                    ConfigField::WasmBindgen => opts.wasm_bindgen = true,
+                    ConfigField::Interfaces(v) => interfaces.extend(v),
+                    ConfigField::Only(v) => filter.only = v,
+                    ConfigField::Skip(v) => filter.skip = v,
                 }
             }
             if interfaces.is_empty() {
@@ -79,31 +293,48 @@ impl Parse for Opts {
             }
             interfaces
         } else {
-            while !input.is_empty() {
-                let s = input.parse::<syn::LitStr>()?;
-                files.push(s.value());
-            }
             let mut interfaces = Vec::new();
             let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
-            for path in files.iter() {
-                let path = manifest_dir.join(path);
-                let iface = Interface::parse_file(path).map_err(|e| Error::new(call_site, e))?;
-                interfaces.push(iface);
+            while !input.is_empty() {
+                let s = input.parse::<syn::LitStr>()?;
+                let parsed = parse_interface_arg(&manifest_dir, &s.value())
+                    .map_err(|e| Error::new(s.span(), e))?;
+                for (path, iface) in parsed {
+                    interfaces.push(iface);
+                    files.push(path.display().to_string());
+                }
             }
             interfaces
         };
+        for (i, iface) in interfaces.iter().enumerate() {
+            if interfaces[..i].iter().any(|prev| prev.name == iface.name) {
+                return Err(Error::new(
+                    call_site,
+                    format!("interface `{}` is specified more than once", iface.name),
+                ));
+            }
+        }
         Ok(Opts {
             files,
             opts,
             interfaces,
+            filter,
         })
     }
 }
 
 enum ConfigField {
     Interfaces(Vec<Interface>),
+    Only(Vec<String>),
+    Skip(Vec<String>),
     Unchecked,
     MultiModule,
+    ExportAbiVersion,
+    EmbedInterfaceHash,
+    ExportAbiDescriptor,
+    ReturnArena,
+    PanicHook,
+    WasmBindgen,
 }
 
 impl Parse for ConfigField {
@@ -123,16 +354,14 @@ impl Parse for ConfigField {
             input.parse::<kw::paths>()?;
             input.parse::<Token![:]>()?;
             let paths;
-            let bracket = syn::bracketed!(paths in input);
+            syn::bracketed!(paths in input);
             let paths = Punctuated::<syn::LitStr, Token![,]>::parse_terminated(&paths)?;
-            let values = paths.iter().map(|s| s.value()).collect::<Vec<_>>();
             let mut interfaces = Vec::new();
             let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
-            for value in &values {
-                let value = manifest_dir.join(value);
-                let interface =
-                    Interface::parse_file(value).map_err(|e| Error::new(bracket.span, e))?;
-                interfaces.push(interface);
+            for path in paths.iter() {
+                let parsed = parse_interface_arg(&manifest_dir, &path.value())
+                    .map_err(|e| Error::new(path.span(), e))?;
+                interfaces.extend(parsed.into_iter().map(|(_, iface)| iface));
             }
             Ok(ConfigField::Interfaces(interfaces))
         } else if l.peek(kw::unchecked) {
@@ -141,8 +370,41 @@ impl Parse for ConfigField {
         } else if l.peek(kw::multi_module) {
             input.parse::<kw::multi_module>()?;
             Ok(ConfigField::MultiModule)
+        } else if l.peek(kw::export_abi_version) {
+            input.parse::<kw::export_abi_version>()?;
+            Ok(ConfigField::ExportAbiVersion)
+        } else if l.peek(kw::embed_interface_hash) {
+            input.parse::<kw::embed_interface_hash>()?;
+            Ok(ConfigField::EmbedInterfaceHash)
+        } else if l.peek(kw::export_abi_descriptor) {
+            input.parse::<kw::export_abi_descriptor>()?;
+            Ok(ConfigField::ExportAbiDescriptor)
+        } else if l.peek(kw::return_arena) {
+            input.parse::<kw::return_arena>()?;
+            Ok(ConfigField::ReturnArena)
+        } else if l.peek(kw::panic_hook) {
+            input.parse::<kw::panic_hook>()?;
+            Ok(ConfigField::PanicHook)
+        } else if l.peek(kw::wasm_bindgen) {
+            input.parse::<kw::wasm_bindgen>()?;
+            Ok(ConfigField::WasmBindgen)
+        } else if l.peek(kw::only) {
+            input.parse::<kw::only>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::Only(parse_patterns(input)?))
+        } else if l.peek(kw::skip) {
+            input.parse::<kw::skip>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::Skip(parse_patterns(input)?))
         } else {
             Err(l.error())
         }
     }
 }
+
+fn parse_patterns(input: ParseStream<'_>) -> Result<Vec<String>> {
+    let patterns;
+    syn::bracketed!(patterns in input);
+    let patterns = Punctuated::<syn::LitStr, Token![,]>::parse_terminated(&patterns)?;
+    Ok(patterns.iter().map(|s| s.value()).collect())
+}
@@ -0,0 +1,126 @@
+//! A bump allocator for data returned from exported functions.
+//!
+//! Bindings generated with the `return_arena` option lower the strings and
+//! lists returned by exports into this arena rather than allocating a fresh
+//! buffer for each one. Hosts either free each pointer as usual through
+//! `canonical_abi_free`, which resets the arena once nothing in it is live,
+//! or release everything at once with `canonical_abi_free_return_area`.
+
+use std::alloc::{self, Layout};
+use std::cell::RefCell;
+use std::mem;
+use std::ptr;
+
+/// The smallest chunk the arena allocates.
+const MIN_CHUNK: usize = 1024;
+/// The alignment of every chunk, which covers all canonical ABI types.
+const CHUNK_ALIGN: usize = 8;
+
+#[derive(Default)]
+struct Arena {
+    /// Every chunk allocated since the last reset, the last of which is the
+    /// one currently being bumped into.
+    chunks: Vec<(*mut u8, Layout)>,
+    /// The number of bytes used in the last chunk.
+    used: usize,
+    /// The number of allocations which haven't been freed yet.
+    live: usize,
+}
+
+thread_local! {
+    static ARENA: RefCell<Arena> = RefCell::default();
+}
+
+impl Arena {
+    fn alloc(&mut self, size: usize, align: usize) -> *mut u8 {
+        if let Some(&(base, layout)) = self.chunks.last() {
+            let start = align_up(base as usize + self.used, align) - base as usize;
+            if start + size <= layout.size() {
+                self.used = start + size;
+                self.live += 1;
+                return unsafe { base.add(start) };
+            }
+        }
+
+        let prev = self.chunks.last().map_or(0, |(_, l)| l.size());
+        let chunk_size = (prev * 2).max(size + align).max(MIN_CHUNK);
+        let layout = Layout::from_size_align(chunk_size, CHUNK_ALIGN.max(align)).unwrap();
+        let base = unsafe { alloc::alloc(layout) };
+        if base.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+        self.chunks.push((base, layout));
+        self.used = size;
+        self.live += 1;
+        base
+    }
+
+    fn contains(&self, ptr: *mut u8) -> bool {
+        self.chunks.iter().any(|&(base, layout)| {
+            let offset = (ptr as usize).wrapping_sub(base as usize);
+            offset < layout.size()
+        })
+    }
+
+    /// Frees every chunk but the last one, so the space used by the last
+    /// burst of calls is kept around for the next one.
+    fn reset(&mut self) {
+        let last = self.chunks.pop();
+        for (base, layout) in self.chunks.drain(..) {
+            unsafe { alloc::dealloc(base, layout) };
+        }
+        self.chunks.extend(last);
+        self.used = 0;
+        self.live = 0;
+    }
+}
+
+fn align_up(addr: usize, align: usize) -> usize {
+    (addr + align - 1) & !(align - 1)
+}
+
+/// Copies `data` into the return arena, returning a pointer to the copy.
+pub fn return_area_copy<T>(data: &[T]) -> *mut u8 {
+    let ret = return_area_alloc(mem::size_of_val(data), mem::align_of::<T>());
+    unsafe {
+        ptr::copy_nonoverlapping(data.as_ptr().cast::<u8>(), ret, mem::size_of_val(data));
+    }
+    ret
+}
+
+/// Allocates `size` bytes aligned to `align` in the return arena.
+///
+/// Like `canonical_abi_realloc`, empty allocations don't take any space and
+/// return `align` as a dangling pointer.
+pub fn return_area_alloc(size: usize, align: usize) -> *mut u8 {
+    if size == 0 {
+        return align as *mut u8;
+    }
+    ARENA.with(|a| a.borrow_mut().alloc(size, align))
+}
+
+/// Called by `canonical_abi_free`, returning whether `ptr` belongs to the
+/// arena and so mustn't be passed to the global allocator.
+pub(crate) fn release(ptr: *mut u8) -> bool {
+    ARENA.with(|a| {
+        let mut a = a.borrow_mut();
+        if !a.contains(ptr) {
+            return false;
+        }
+        a.live = a.live.saturating_sub(1);
+        if a.live == 0 {
+            a.reset();
+        }
+        true
+    })
+}
+
+/// Releases everything in the return arena at once.
+///
+/// Hosts which know about the arena call this after lifting the results of
+/// an exported function instead of freeing each string and list. Pointers
+/// into the arena must not be passed to `canonical_abi_free` afterwards.
+#[no_mangle]
+pub extern "C" fn canonical_abi_free_return_area() {
+    ARENA.with(|a| a.borrow_mut().reset());
+}
@@ -6,11 +6,37 @@ use std::ops::Deref;
 #[cfg(feature = "macros")]
 pub use wai_bindgen_rust_impl::{export, import};
 
+/// Attribute macros, meant to be used as `#[wai::export("api.wai")]` after a
+/// `use wai_bindgen_rust::wai;`.
+#[cfg(feature = "macros")]
+pub mod wai {
+    pub use wai_bindgen_rust_impl::export_impl as export;
+}
+
 #[cfg(feature = "async")]
 pub use async_trait::async_trait;
 #[cfg(feature = "async")]
 mod futures;
 
+mod arena;
+mod panic;
+
+pub use panic::install_panic_hook;
+
+#[cfg(feature = "rpc")]
+pub mod rpc;
+
+#[cfg(feature = "logging")]
+pub mod logging;
+
+// Lets the `logging` module use the bindings macros on this crate itself.
+#[cfg(feature = "logging")]
+extern crate self as wai_bindgen_rust;
+
+/// The revision of the canonical ABI implemented by this crate, which
+/// generated bindings check at compile time.
+pub const ABI_VERSION: u32 = 1;
+
 // Re-export `bitflags` so that we can reference it from macros.
 #[doc(hidden)]
 pub use bitflags;
@@ -130,6 +156,7 @@ pub unsafe trait LocalHandle: HandleType {
 pub mod rt {
     use std::alloc::{self, Layout};
 
+    pub use crate::arena::{return_area_alloc, return_area_copy};
     #[cfg(feature = "async")]
     pub use crate::futures::*;
 
@@ -159,7 +186,7 @@ pub mod rt {
 
     #[no_mangle]
     pub unsafe extern "C" fn canonical_abi_free(ptr: *mut u8, len: usize, align: usize) {
-        if len == 0 {
+        if len == 0 || crate::arena::release(ptr) {
             return;
         }
         let layout = Layout::from_size_align_unchecked(len, align);
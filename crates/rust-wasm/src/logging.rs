@@ -0,0 +1,50 @@
+//! Forwarding of the records of the `log` crate to the host.
+//!
+//! The records are passed to the host through the built-in `wai-logging`
+//! interface, which hosts implement with the `logging` module of
+//! `wai-bindgen-wasmer` or their own bindings of `builtin:wai-logging`.
+
+use std::borrow::Cow;
+
+crate::import!("builtin:wai-logging");
+
+/// A logger passing every record to the host, which filters them.
+pub struct HostLogger;
+
+impl log::Log for HostLogger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        wai_logging::enabled(level(metadata.level()), metadata.target())
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let message = match record.args().as_str() {
+            Some(message) => Cow::Borrowed(message),
+            None => Cow::Owned(record.args().to_string()),
+        };
+        wai_logging::log(level(record.level()), record.target(), &message);
+    }
+
+    fn flush(&self) {}
+}
+
+fn level(level: log::Level) -> wai_logging::Level {
+    match level {
+        log::Level::Error => wai_logging::Level::Error,
+        log::Level::Warn => wai_logging::Level::Warn,
+        log::Level::Info => wai_logging::Level::Info,
+        log::Level::Debug => wai_logging::Level::Debug,
+        log::Level::Trace => wai_logging::Level::Trace,
+    }
+}
+
+/// Installs [`HostLogger`] as the logger of the `log` crate.
+///
+/// The maximum level of the `log` crate is raised to `Trace`, leaving the
+/// filtering of records to the host. Fails if a logger was already
+/// installed.
+pub fn init() -> Result<(), log::SetLoggerError> {
+    static LOGGER: HostLogger = HostLogger;
+    log::set_logger(&LOGGER)?;
+    log::set_max_level(log::LevelFilter::Trace);
+    Ok(())
+}
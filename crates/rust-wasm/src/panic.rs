@@ -0,0 +1,62 @@
+//! Reporting of panics to the host.
+//!
+//! A panic aborts the guest with a trap, which tells the host nothing about
+//! why. Once [`install_panic_hook`] has been called, which bindings generated
+//! with the `panic_hook` option do on the first call of an export, the
+//! message and location of the last panic are kept around and hosts retrieve
+//! them through `canonical_abi_take_panic` after a call trapped.
+
+use std::cell::{Cell, RefCell};
+use std::panic;
+use std::sync::Once;
+
+thread_local! {
+    /// The message of the last panic, if it wasn't taken yet.
+    static LAST_PANIC: RefCell<Option<String>> = RefCell::default();
+    /// The message last handed out by `canonical_abi_take_panic`, kept alive
+    /// until the next call so the host can read it.
+    static TAKEN: RefCell<String> = RefCell::default();
+    /// The pointer and length of `TAKEN` returned to the host.
+    static TAKEN_AREA: Cell<[u32; 2]> = Cell::default();
+}
+
+/// Installs a panic hook which records the message of each panic for the
+/// host, then runs the previously installed hook.
+///
+/// Calling this again has no effect.
+pub fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let prev = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let message = info.to_string();
+            LAST_PANIC.with(|last| {
+                if let Ok(mut last) = last.try_borrow_mut() {
+                    *last = Some(message);
+                }
+            });
+            prev(info);
+        }));
+    });
+}
+
+/// Takes the message of the last panic, returning a pointer to its pointer
+/// and length, which are both zero if the guest didn't panic since the last
+/// call.
+#[no_mangle]
+pub extern "C" fn canonical_abi_take_panic() -> *const u32 {
+    let message = LAST_PANIC.with(|last| last.borrow_mut().take());
+    TAKEN.with(|taken| {
+        let mut taken = taken.borrow_mut();
+        *taken = message.unwrap_or_default();
+        let area = if taken.is_empty() {
+            [0; 2]
+        } else {
+            [taken.as_ptr() as u32, taken.len() as u32]
+        };
+        TAKEN_AREA.with(|a| {
+            a.set(area);
+            a.as_ptr().cast()
+        })
+    })
+}
@@ -0,0 +1,339 @@
+//! Support for bindings generated by `wai-bindgen rpc`, which run the two
+//! sides of an interface in separate processes talking over a byte stream.
+//!
+//! Every message is a frame made of its length as a little-endian `u32`
+//! followed by its contents. A call is the index of the function in the
+//! interface as a `u32` followed by its arguments, and its reply is a `0`
+//! byte followed by the result, or a `1` byte followed by an error message
+//! if the server couldn't decode the call. Values are encoded with
+//! [`Wire`].
+
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// An error making or serving a call.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading from or writing to the stream failed.
+    Io(io::Error),
+    /// A message didn't match the signature of the function it's for.
+    Decode(String),
+    /// The server rejected a call, with the reason it gave.
+    Remote(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "rpc stream failed: {}", e),
+            Error::Decode(msg) => write!(f, "malformed rpc message: {}", msg),
+            Error::Remote(msg) => write!(f, "rpc call rejected: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+/// A value which can be sent over an rpc stream.
+///
+/// Integers and floats are little-endian, `bool`s are a byte and `char`s a
+/// `u32`. Strings and lists are their length as a `u32` followed by their
+/// contents, options and results a tag byte followed by their payload, and
+/// tuples their elements in order. Generated bindings implement this for
+/// the records, variants and flags of the interface likewise.
+pub trait Wire: Sized {
+    /// Appends the encoding of `self` to `out`.
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// Decodes a value from the start of `input`, advancing it past it.
+    fn decode(input: &mut &[u8]) -> Result<Self, Error>;
+}
+
+/// Splits the first `n` bytes off of `input`.
+pub fn take<'a>(input: &mut &'a [u8], n: usize) -> Result<&'a [u8], Error> {
+    if input.len() < n {
+        return Err(Error::Decode("unexpected end of message".to_string()));
+    }
+    let (head, tail) = input.split_at(n);
+    *input = tail;
+    Ok(head)
+}
+
+/// Checks that all of a message was decoded.
+pub fn finish(input: &[u8]) -> Result<(), Error> {
+    if input.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Decode("trailing bytes in message".to_string()))
+    }
+}
+
+/// The error for an out-of-range case or tag of a `ty`.
+pub fn invalid_case(ty: &str, case: u32) -> Error {
+    Error::Decode(format!("invalid case {} for `{}`", case, ty))
+}
+
+macro_rules! wire_numbers {
+    ($($t:ty)*) => ($(
+        impl Wire for $t {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+                let bytes = take(input, std::mem::size_of::<$t>())?;
+                Ok(<$t>::from_le_bytes(bytes.try_into().unwrap()))
+            }
+        }
+    )*)
+}
+
+wire_numbers!(u8 u16 u32 u64 u128 i8 i16 i32 i64 f32 f64);
+
+impl Wire for () {
+    fn encode(&self, _out: &mut Vec<u8>) {}
+
+    fn decode(_input: &mut &[u8]) -> Result<Self, Error> {
+        Ok(())
+    }
+}
+
+impl Wire for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+        match u8::decode(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            n => Err(invalid_case("bool", n.into())),
+        }
+    }
+}
+
+impl Wire for char {
+    fn encode(&self, out: &mut Vec<u8>) {
+        u32::from(*self).encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+        let n = u32::decode(input)?;
+        char::from_u32(n).ok_or_else(|| Error::Decode(format!("invalid char {:#x}", n)))
+    }
+}
+
+fn encode_len(len: usize, out: &mut Vec<u8>) {
+    u32::try_from(len)
+        .expect("length doesn't fit in a u32")
+        .encode(out);
+}
+
+impl Wire for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        encode_len(self.len(), out);
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+        let len = u32::decode(input)? as usize;
+        let bytes = take(input, len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|e| Error::Decode(e.to_string()))
+    }
+}
+
+impl<T: Wire> Wire for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        encode_len(self.len(), out);
+        for elem in self {
+            elem.encode(out);
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+        let len = u32::decode(input)? as usize;
+        // Don't trust `len` for the allocation: every element takes at least
+        // a byte, except for empty types.
+        let mut elems = Vec::with_capacity(len.min(input.len()));
+        for _ in 0..len {
+            elems.push(T::decode(input)?);
+        }
+        Ok(elems)
+    }
+}
+
+impl<T: Wire> Wire for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            None => out.push(0),
+            Some(val) => {
+                out.push(1);
+                val.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+        match u8::decode(input)? {
+            0 => Ok(None),
+            1 => Ok(Some(T::decode(input)?)),
+            n => Err(invalid_case("option", n.into())),
+        }
+    }
+}
+
+impl<T: Wire, E: Wire> Wire for Result<T, E> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Ok(val) => {
+                out.push(0);
+                val.encode(out);
+            }
+            Err(err) => {
+                out.push(1);
+                err.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+        match u8::decode(input)? {
+            0 => Ok(Ok(T::decode(input)?)),
+            1 => Ok(Err(E::decode(input)?)),
+            n => Err(invalid_case("expected", n.into())),
+        }
+    }
+}
+
+macro_rules! wire_tuples {
+    ($(($($t:ident)*))*) => ($(
+        #[allow(non_snake_case)]
+        impl<$($t: Wire,)*> Wire for ($($t,)*) {
+            fn encode(&self, out: &mut Vec<u8>) {
+                let ($($t,)*) = self;
+                $($t.encode(out);)*
+            }
+
+            fn decode(input: &mut &[u8]) -> Result<Self, Error> {
+                Ok(($($t::decode(input)?,)*))
+            }
+        }
+    )*)
+}
+
+wire_tuples! {
+    (A)
+    (A B)
+    (A B C)
+    (A B C D)
+    (A B C D E)
+    (A B C D E F)
+    (A B C D E F G)
+    (A B C D E F G H)
+    (A B C D E F G H I)
+    (A B C D E F G H I J)
+    (A B C D E F G H I J K)
+    (A B C D E F G H I J K L)
+}
+
+/// Writes `payload` to `stream` as a single frame.
+pub fn write_frame(stream: &mut impl Write, payload: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(payload.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "rpc message too large"))?;
+    stream.write_all(&len.to_le_bytes())?;
+    stream.write_all(payload)?;
+    stream.flush()
+}
+
+/// Reads a frame from `stream`, returning `None` if it was closed before the
+/// start of one.
+pub fn read_frame(stream: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0; 4];
+    match stream.read(&mut len[..1])? {
+        0 => return Ok(None),
+        _ => stream.read_exact(&mut len[1..])?,
+    }
+    let mut payload = vec![0; u32::from_le_bytes(len) as usize];
+    stream.read_exact(&mut payload)?;
+    Ok(Some(payload))
+}
+
+/// The calling side of an rpc stream, wrapped by the generated `*Client`
+/// types.
+pub struct Client<S> {
+    stream: S,
+}
+
+impl<S: Read + Write> Client<S> {
+    pub fn new(stream: S) -> Client<S> {
+        Client { stream }
+    }
+
+    /// Returns the stream the client was created with.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Calls the function at index `func` of the interface with the encoded
+    /// `args`, waiting for its result.
+    pub fn call<R: Wire>(&mut self, func: u32, args: &[u8]) -> Result<R, Error> {
+        let mut call = Vec::with_capacity(4 + args.len());
+        func.encode(&mut call);
+        call.extend_from_slice(args);
+        write_frame(&mut self.stream, &call)?;
+
+        let reply = read_frame(&mut self.stream)?.ok_or_else(|| {
+            Error::Io(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "rpc server closed the stream",
+            ))
+        })?;
+        let mut reply = &reply[..];
+        match u8::decode(&mut reply)? {
+            0 => {
+                let result = R::decode(&mut reply)?;
+                finish(reply)?;
+                Ok(result)
+            }
+            1 => Err(Error::Remote(String::decode(&mut reply)?)),
+            n => Err(invalid_case("reply", n.into())),
+        }
+    }
+}
+
+/// Serves the calls made over `stream` until the client closes it.
+///
+/// `dispatch` is given the index of the function called and its encoded
+/// arguments, which it checks with [`finish`] once decoded, and appends the
+/// encoded result to the reply. Calls it fails to decode are rejected with
+/// the error, leaving the stream usable.
+pub fn serve<S: Read + Write>(
+    mut stream: S,
+    mut dispatch: impl FnMut(u32, &mut &[u8], &mut Vec<u8>) -> Result<(), Error>,
+) -> Result<(), Error> {
+    while let Some(call) = read_frame(&mut stream)? {
+        let mut call = &call[..];
+        let mut reply = vec![0];
+        let result = u32::decode(&mut call).and_then(|func| dispatch(func, &mut call, &mut reply));
+        if let Err(e) = result {
+            reply.clear();
+            reply.push(1);
+            e.to_string().encode(&mut reply);
+        }
+        write_frame(&mut stream, &reply)?;
+    }
+    Ok(())
+}
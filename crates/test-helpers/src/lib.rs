@@ -295,6 +295,36 @@ pub fn codegen_c_export(input: TokenStream) -> TokenStream {
     })
 }
 
+#[proc_macro]
+#[cfg(feature = "wai-bindgen-gen-c")]
+pub fn codegen_c_export_dispatch_table(input: TokenStream) -> TokenStream {
+    gen_verify(input, Direction::Export, "export-dispatch-table", || {
+        let mut opts = wai_bindgen_gen_c::Opts::default();
+        opts.dispatch_table = true;
+        opts.build()
+    })
+}
+
+#[proc_macro]
+#[cfg(feature = "wai-bindgen-gen-c")]
+pub fn codegen_c_import_freestanding(input: TokenStream) -> TokenStream {
+    gen_verify(input, Direction::Import, "import-freestanding", || {
+        let mut opts = wai_bindgen_gen_c::Opts::default();
+        opts.freestanding = true;
+        opts.build()
+    })
+}
+
+#[proc_macro]
+#[cfg(feature = "wai-bindgen-gen-c")]
+pub fn codegen_c_export_freestanding(input: TokenStream) -> TokenStream {
+    gen_verify(input, Direction::Export, "export-freestanding", || {
+        let mut opts = wai_bindgen_gen_c::Opts::default();
+        opts.freestanding = true;
+        opts.build()
+    })
+}
+
 #[proc_macro]
 #[cfg(feature = "wai-bindgen-gen-wasmtime-py")]
 pub fn codegen_wasmtime_py_export(input: TokenStream) -> TokenStream {
@@ -353,6 +383,24 @@ pub fn codegen_wasmer_import(input: TokenStream) -> TokenStream {
                 },
                 |_| quote::quote!(),
             ),
+            (
+                "import-record",
+                || {
+                    let mut opts = wai_bindgen_gen_wasmer::Opts::default();
+                    opts.record = true;
+                    opts.build()
+                },
+                |_| quote::quote!(),
+            ),
+            (
+                "import-unchecked",
+                || {
+                    let mut opts = wai_bindgen_gen_wasmer::Opts::default();
+                    opts.unchecked = true;
+                    opts.build()
+                },
+                |_| quote::quote!(),
+            ),
         ],
     )
 }
@@ -363,11 +411,40 @@ pub fn codegen_wasmer_export(input: TokenStream) -> TokenStream {
     gen_rust(
         input,
         Direction::Export,
-        &[(
-            "export",
-            || wai_bindgen_gen_wasmer::Opts::default().build(),
-            |_| quote::quote!(),
-        )],
+        &[
+            (
+                "export",
+                || wai_bindgen_gen_wasmer::Opts::default().build(),
+                |_| quote::quote!(),
+            ),
+            (
+                "export-record",
+                || {
+                    let mut opts = wai_bindgen_gen_wasmer::Opts::default();
+                    opts.record = true;
+                    opts.build()
+                },
+                |_| quote::quote!(),
+            ),
+            (
+                "export-unchecked",
+                || {
+                    let mut opts = wai_bindgen_gen_wasmer::Opts::default();
+                    opts.unchecked = true;
+                    opts.build()
+                },
+                |_| quote::quote!(),
+            ),
+            (
+                "export-arena",
+                || {
+                    let mut opts = wai_bindgen_gen_wasmer::Opts::default();
+                    opts.arena = true;
+                    opts.build()
+                },
+                |_| quote::quote!(),
+            ),
+        ],
     )
 }
 
@@ -387,6 +464,59 @@ pub fn codegen_wasmer_py_import(input: TokenStream) -> TokenStream {
     })
 }
 
+/// Invoked as `codegen_conformance!()` to generate the conformance suite of
+/// every `tests/runtime/*/exports.wai` the generator supports and include the
+/// host half of each, which loads the wasm through a top-level `instantiate`
+/// function.
+#[proc_macro]
+#[cfg(feature = "wai-bindgen-gen-conformance")]
+pub fn codegen_conformance(_input: TokenStream) -> TokenStream {
+    use heck::*;
+
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").expect("OUT_DIR not set"));
+    let out_dir = out_dir.join("conformance");
+    drop(fs::remove_dir_all(&out_dir));
+    let mut tests = Vec::new();
+    let cwd = env::current_dir().unwrap();
+    for entry in fs::read_dir(cwd.join("tests/runtime")).unwrap() {
+        let entry = entry.unwrap().path();
+        let wai = entry.join("exports.wai");
+        if !wai.exists() {
+            continue;
+        }
+        let iface = wai_parser::Interface::parse_file(&wai).unwrap();
+        if iface.resources.len() > 0 || iface.functions.iter().any(|f| f.is_async) {
+            continue;
+        }
+
+        let mut opts = wai_bindgen_gen_conformance::Opts::default();
+        opts.wai_path = Some(wai.display().to_string());
+        let mut files = Default::default();
+        opts.build().generate_all(&[], &[iface.clone()], &mut files);
+        // Every test names its interface `exports`, so each one gets its own
+        // directory.
+        let name_str = entry.file_name().unwrap().to_str().unwrap();
+        let dst = out_dir.join(name_str);
+        for (file, contents) in files.iter() {
+            let dst = dst.join(file);
+            fs::create_dir_all(dst.parent().unwrap()).unwrap();
+            write_old_file(dst, contents);
+        }
+
+        let name = quote::format_ident!("{}", name_str.to_snake_case());
+        let host = dst.join(&iface.name).join("host-wasmer.rs");
+        let host = host.display().to_string();
+        let wai = wai.display().to_string();
+        tests.push(quote::quote! {
+            mod #name {
+                const _: &str = include_str!(#wai);
+                include!(#host);
+            }
+        });
+    }
+    (quote::quote!(#(#tests)*)).into()
+}
+
 fn generate_tests<G>(
     input: TokenStream,
     dir: &str,
@@ -616,11 +746,56 @@ pub fn runtime_tests_wasmer_py(_input: TokenStream) -> TokenStream {
     (quote::quote!(#(#tests)*)).into()
 }
 
+/// Runtime tests which only have a host for one of the Rust host backends,
+/// named by the host file they do have.
+#[cfg(any(
+    feature = "wai-bindgen-gen-wasmtime",
+    feature = "wai-bindgen-gen-wasmer"
+))]
+const SINGLE_BACKEND_TESTS: &[(&str, &str)] = &[
+    // Resource tables shared between interfaces are only implemented by
+    // gen-wasmer.
+    ("shared_resources", "host-wasmer.rs"),
+];
+
+/// Checks that each runtime test in `runtime` has both a `host.rs` for
+/// Wasmtime and a `host-wasmer.rs` for Wasmer, unless it is listed in
+/// `SINGLE_BACKEND_TESTS`, so that the two backends are tested alike.
+#[cfg(any(
+    feature = "wai-bindgen-gen-wasmtime",
+    feature = "wai-bindgen-gen-wasmer"
+))]
+fn check_host_parity(runtime: &Path) {
+    for entry in std::fs::read_dir(runtime).unwrap() {
+        let entry = entry.unwrap().path();
+        let name = entry.file_name().unwrap().to_str().unwrap();
+        let host = match (
+            entry.join("host.rs").exists(),
+            entry.join("host-wasmer.rs").exists(),
+        ) {
+            (true, false) => "host.rs",
+            (false, true) => "host-wasmer.rs",
+            _ => continue,
+        };
+        if !SINGLE_BACKEND_TESTS.contains(&(name, host)) {
+            panic!(
+                "runtime test `{}` only has a `{}`; add a host for the other \
+                 backend or list it in `SINGLE_BACKEND_TESTS`",
+                name, host,
+            );
+        }
+    }
+}
+
+/// Invoked as `runtime_tests_wasmtime!()` to generate a `#[test]` running each
+/// `host.rs` against each wasm, which the host loads through a top-level
+/// `instantiate` function.
 #[proc_macro]
 #[cfg(feature = "wai-bindgen-gen-wasmtime")]
 pub fn runtime_tests_wasmtime(_input: TokenStream) -> TokenStream {
     let mut tests = Vec::new();
     let cwd = std::env::current_dir().unwrap();
+    check_host_parity(&cwd.join("tests/runtime"));
     for entry in std::fs::read_dir(cwd.join("tests/runtime")).unwrap() {
         let entry = entry.unwrap().path();
         if !entry.join("host.rs").exists() {
@@ -649,12 +824,34 @@ pub fn runtime_tests_wasmtime(_input: TokenStream) -> TokenStream {
     (quote::quote!(#(#tests)*)).into()
 }
 
+/// Invoked as `runtime_tests_wasmer!()` to generate a `#[test]` running each
+/// `host-wasmer.rs` against each wasm, which the host loads through a
+/// top-level `instantiate` function.
+///
+/// Invoked as `runtime_tests_wasmer!("js")` the tests are instead
+/// `#[wasm_bindgen_test]`s for a host compiled with wasmer's `js` feature.
+/// Browsers can't read the wasms from disk, so this also generates a top-level
+/// `wasm_bytes` function which `instantiate` uses to look up the embedded
+/// bytes of the path it was given.
 #[proc_macro]
 #[cfg(feature = "wai-bindgen-gen-wasmer")]
-pub fn runtime_tests_wasmer(_input: TokenStream) -> TokenStream {
+pub fn runtime_tests_wasmer(input: TokenStream) -> TokenStream {
+    let js = match input.to_string().trim_matches('"') {
+        "" => false,
+        "js" => true,
+        other => panic!("unknown wasmer runtime test variant `{}`", other),
+    };
     let mut tests = Vec::new();
-    let cwd = std::env::current_dir().unwrap();
-    for entry in std::fs::read_dir(cwd.join("tests/runtime")).unwrap() {
+    let mut wasms = Vec::new();
+    // The browser tests live outside of the main workspace, so they can't rely
+    // on being compiled from the workspace root.
+    let root = if js {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../..")
+    } else {
+        std::env::current_dir().unwrap()
+    };
+    check_host_parity(&root.join("tests/runtime"));
+    for entry in std::fs::read_dir(root.join("tests/runtime")).unwrap() {
         let entry = entry.unwrap().path();
         if !entry.join("host-wasmer.rs").exists() {
             continue;
@@ -666,18 +863,43 @@ pub fn runtime_tests_wasmer(_input: TokenStream) -> TokenStream {
             }
             let name = quote::format_ident!("{}_{}", name_str, lang);
             let host_file = entry.join("host-wasmer.rs").to_str().unwrap().to_string();
-            tests.push(quote::quote! {
-                mod #name {
-                    include!(#host_file);
-
-                    #[test]
-                    fn test() -> anyhow::Result<()> {
-                        run(#wasm)
+            if js {
+                wasms.push(wasm);
+                tests.push(quote::quote! {
+                    mod #name {
+                        include!(#host_file);
+
+                        #[wasm_bindgen_test::wasm_bindgen_test]
+                        fn test() {
+                            run(#wasm).unwrap()
+                        }
                     }
-                }
-            });
+                });
+            } else {
+                tests.push(quote::quote! {
+                    mod #name {
+                        include!(#host_file);
+
+                        #[test]
+                        fn test() -> anyhow::Result<()> {
+                            run(#wasm)
+                        }
+                    }
+                });
+            }
         }
     }
 
+    if js {
+        tests.push(quote::quote! {
+            fn wasm_bytes(wasm: &str) -> &'static [u8] {
+                match wasm {
+                    #(#wasms => include_bytes!(#wasms),)*
+                    _ => panic!("no wasm was embedded for `{}`", wasm),
+                }
+            }
+        });
+    }
+
     (quote::quote!(#(#tests)*)).into()
 }
@@ -0,0 +1,3 @@
+include!("../../../../tests/runtime/differential/wasm.rs");
+
+fn main() {}
@@ -0,0 +1,3 @@
+include!("../../../../tests/runtime/nested_shapes/wasm.rs");
+
+fn main() {}
@@ -0,0 +1,3 @@
+include!("../../../../tests/runtime/shared_resources/wasm.rs");
+
+fn main() {}
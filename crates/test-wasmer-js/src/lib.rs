@@ -0,0 +1 @@
+// Intentionally empty, everything here is in `tests/runtime.rs`.
@@ -1,4 +1,4 @@
-import { Demo, Config } from './demo.js';
+import { Demo, Config, Lang, WasmtimeAsync } from './demo.js';
 import * as browser from './browser.js';
 
 class Editor {
@@ -6,13 +6,7 @@ class Editor {
   language: HTMLSelectElement;
   mode: HTMLSelectElement;
   files: HTMLSelectElement
-  rustUnchecked: HTMLInputElement;
-  wasmtimeTracing: HTMLInputElement;
-  wasmtimeAsync: HTMLInputElement;
-  wasmtimeCustomError: HTMLInputElement;
-  wasmerTracing: HTMLInputElement;
-  wasmerAsync: HTMLInputElement;
-  wasmerCustomError: HTMLInputElement;
+  options: NodeListOf<HTMLInputElement | HTMLSelectElement>;
   generatedFiles: Record<string, string>;
   demo: Demo;
   config: Config | null;
@@ -26,15 +20,12 @@ class Editor {
     this.language = document.getElementById('language-select') as HTMLSelectElement;
     this.mode = document.getElementById('mode-select') as HTMLSelectElement;
     this.files = document.getElementById('file-select') as HTMLSelectElement;
-    this.rustUnchecked = document.getElementById('rust-unchecked') as HTMLInputElement;
-    this.wasmtimeTracing = document.getElementById('wasmtime-tracing') as HTMLInputElement;
-    this.wasmtimeAsync = document.getElementById('wasmtime-async') as HTMLInputElement;
-    this.wasmtimeCustomError = document.getElementById('wasmtime-custom-error') as HTMLInputElement;
-    this.wasmerTracing = document.getElementById('wasmer-tracing') as HTMLInputElement;
-    this.wasmerAsync = document.getElementById('wasmer-async') as HTMLInputElement;
-    this.wasmerCustomError = document.getElementById('wasmer-custom-error') as HTMLInputElement;
+    this.options = document.querySelectorAll<HTMLInputElement | HTMLSelectElement>(
+      '.lang-configure input, .lang-configure select');
     this.outputHtml = document.getElementById('html-output') as HTMLDivElement;
 
+    this.loadState();
+
     this.inputEditor = ace.edit("input");
     this.outputEditor = ace.edit("output");
     this.inputEditor.setValue(this.input.value);
@@ -58,6 +49,8 @@ class Editor {
     browser.addBrowserToImports(imports, obj, name => this.demo.instance.exports[name]);
     await this.demo.instantiate(fetch('./demo.wasm'), imports);
     this.config = Config.new(this.demo);
+    for (let option of this.options)
+      this.applyOption(option);
     this.installListeners();
     this.render();
   }
@@ -73,46 +66,94 @@ class Editor {
     this.language.addEventListener('change', () => this.render());
     this.mode.addEventListener('change', () => this.render());
 
-    this.rustUnchecked.addEventListener('change', () => {
-      this.config.setRustUnchecked(this.rustUnchecked.checked);
-      this.render();
-    });
+    for (let option of this.options) {
+      option.addEventListener('change', () => {
+        this.applyOption(option);
+        this.render();
+      });
+    }
+    this.files.addEventListener('change', () => this.updateSelectedFile());
 
-    this.wasmtimeTracing.addEventListener('change', () => {
-      this.config.setWasmtimeTracing(this.wasmtimeTracing.checked);
-      this.render();
-    });
-    this.wasmtimeAsync.addEventListener('change', () => {
-      let async_;
-      if (this.wasmtimeAsync.checked)
-        async_ = { tag: 'all' };
-      else
-        async_ = { tag: 'none' };
-      this.config.setWasmtimeAsync(async_);
-      this.render();
+    document.getElementById('download-file').addEventListener('click', () => {
+      if (this.files.value in this.generatedFiles)
+        download(this.files.value, this.generatedFiles[this.files.value]);
     });
-    this.wasmtimeCustomError.addEventListener('change', () => {
-      this.config.setWasmtimeCustomError(this.wasmtimeCustomError.checked);
-      this.render();
+    document.getElementById('download-all').addEventListener('click', () => {
+      for (let name in this.generatedFiles)
+        download(name, this.generatedFiles[name]);
     });
-    this.wasmerTracing.addEventListener('change', () => {
-      this.config.setWasmerTracing(this.wasmerTracing.checked);
-      this.render();
+    document.getElementById('share').addEventListener('click', () => {
+      this.saveState();
+      navigator.clipboard.writeText(location.href);
     });
-    this.wasmerAsync.addEventListener('change', () => {
-      let async_;
-      if (this.wasmerAsync.checked)
-        async_ = { tag: 'all' };
+  }
+
+  // Passes the value of one of the inputs in a `configure-*` div on to the
+  // options of its generator.
+  applyOption(option: HTMLInputElement | HTMLSelectElement) {
+    const lang = option.closest('.lang-configure').id.slice('configure-'.length) as Lang;
+    if (option.dataset.async !== undefined) {
+      const async_: WasmtimeAsync = (option as HTMLInputElement).checked ? { tag: 'all' } : { tag: 'none' };
+      if (lang === 'wasmtime')
+        this.config.setWasmtimeAsync(async_);
       else
-        async_ = { tag: 'none' };
-      this.config.setWasmerAsync(async_);
-      this.render();
-    });
-    this.wasmerCustomError.addEventListener('change', () => {
-      this.config.setWasmerCustomError(this.wasmerCustomError.checked);
-      this.render();
-    });
-    this.files.addEventListener('change', () => this.updateSelectedFile());
+        this.config.setWasmerAsync(async_);
+    } else if (option.dataset.flag !== undefined) {
+      this.config.setFlag(lang, option.dataset.flag, (option as HTMLInputElement).checked);
+    } else if (option.dataset.option !== undefined) {
+      this.config.setOption(lang, option.dataset.option, option.value);
+    }
+  }
+
+  // The whole state of the playground is kept in the URL's fragment, as
+  // JSON, so that a link to the page reproduces what's shown. Options are
+  // keyed by the id of their input and only recorded when they aren't
+  // their default.
+  saveState() {
+    const options: Record<string, boolean | string> = {};
+    for (let option of this.options) {
+      if (option instanceof HTMLInputElement && option.type === 'checkbox') {
+        if (option.checked)
+          options[option.id] = true;
+      } else if (option.value !== '') {
+        options[option.id] = option.value;
+      }
+    }
+    const state = {
+      wai: this.inputEditor.getValue(),
+      lang: this.language.value,
+      mode: this.mode.value,
+      file: this.files.value,
+      options,
+    };
+    history.replaceState(null, '', '#' + encodeURIComponent(JSON.stringify(state)));
+  }
+
+  loadState() {
+    if (location.hash.length <= 1)
+      return;
+    let state;
+    try {
+      state = JSON.parse(decodeURIComponent(location.hash.slice(1)));
+    } catch (e) {
+      console.error('ignoring malformed state in URL', e);
+      return;
+    }
+    if (typeof state.wai === 'string')
+      this.input.value = state.wai;
+    if (typeof state.lang === 'string')
+      this.language.value = state.lang;
+    if (typeof state.mode === 'string')
+      this.mode.value = state.mode;
+    if (typeof state.file === 'string')
+      this.files.options[0] = new Option(state.file, state.file);
+    for (let option of this.options) {
+      const value = (state.options || {})[option.id];
+      if (option instanceof HTMLInputElement && option.type === 'checkbox')
+        option.checked = value === true;
+      else
+        option.value = typeof value === 'string' ? value : '';
+    }
   }
 
 
@@ -137,6 +178,13 @@ class Editor {
       case "spidermonkey":
       case "wasmer":
       case "wasmer-py":
+      case "rpc":
+      case "json-schema":
+      case "proto":
+      case "webidl":
+      case "rest-gateway":
+      case "conformance":
+      case "rust-types":
         lang = this.language.value;
         break;
       default: return;
@@ -146,6 +194,7 @@ class Editor {
       this.outputEditor.setValue(result.val);
       this.outputEditor.clearSelection();
       this.showOutputEditor();
+      this.saveState();
       return;
     }
     this.generatedFiles = {};
@@ -174,6 +223,7 @@ class Editor {
   }
 
   updateSelectedFile() {
+    this.saveState();
     if (this.files.value.endsWith('.html')) {
       const html = this.generatedFiles[this.files.value];
       this.outputHtml.innerHTML = html;
@@ -198,10 +248,23 @@ class Editor {
       this.outputEditor.session.setMode("ace/mode/markdown");
     else if (this.files.value.endsWith('.py'))
       this.outputEditor.session.setMode("ace/mode/python");
+    else if (this.files.value.endsWith('.json'))
+      this.outputEditor.session.setMode("ace/mode/json");
+    else if (this.files.value.endsWith('.proto'))
+      this.outputEditor.session.setMode("ace/mode/protobuf");
+    else if (this.files.value.endsWith('.toml'))
+      this.outputEditor.session.setMode("ace/mode/toml");
     else
       this.outputEditor.session.setMode(null);
   }
 }
 
+function download(name: string, contents: string) {
+  const link = document.createElement('a');
+  link.href = URL.createObjectURL(new Blob([contents], { type: 'text/plain' }));
+  link.download = name;
+  link.click();
+  URL.revokeObjectURL(link.href);
+}
 
 (new Editor()).instantiate()
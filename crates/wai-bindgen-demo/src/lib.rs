@@ -22,6 +22,13 @@ pub struct Config {
     spidermonkey: RefCell<wai_bindgen_gen_spidermonkey::Opts>,
     wasmer: RefCell<wai_bindgen_gen_wasmer::Opts>,
     wasmer_py: RefCell<wai_bindgen_gen_wasmer_py::Opts>,
+    rpc: RefCell<wai_bindgen_gen_rpc::Opts>,
+    json_schema: RefCell<wai_bindgen_gen_json_schema::Opts>,
+    proto: RefCell<wai_bindgen_gen_proto::Opts>,
+    webidl: RefCell<wai_bindgen_gen_webidl::Opts>,
+    rest_gateway: RefCell<wai_bindgen_gen_rest_gateway::Opts>,
+    conformance: RefCell<wai_bindgen_gen_conformance::Opts>,
+    rust_types: RefCell<wai_bindgen_gen_rust_types::Opts>,
 }
 
 impl demo::Config for Config {
@@ -60,6 +67,13 @@ impl demo::Config for Config {
             }
             demo::Lang::Wasmer => Box::new(self.wasmer.borrow().clone().build()),
             demo::Lang::WasmerPy => Box::new(self.wasmer_py.borrow().clone().build()),
+            demo::Lang::Rpc => Box::new(self.rpc.borrow().clone().build()),
+            demo::Lang::JsonSchema => Box::new(self.json_schema.borrow().clone().build()),
+            demo::Lang::Proto => Box::new(self.proto.borrow().clone().build()),
+            demo::Lang::Webidl => Box::new(self.webidl.borrow().build()),
+            demo::Lang::RestGateway => Box::new(self.rest_gateway.borrow().clone().build()),
+            demo::Lang::Conformance => Box::new(self.conformance.borrow().clone().build()),
+            demo::Lang::RustTypes => Box::new(self.rust_types.borrow().clone().build()),
         };
         let iface = Interface::parse("input", &wai).map_err(|e| format!("{:?}", e))?;
         let mut files = Default::default();
@@ -72,27 +86,86 @@ impl demo::Config for Config {
         Ok(files
             .iter()
             .map(|(name, contents)| {
-                let contents = if contents.starts_with(b"\0asm") {
-                    wasmprinter::print_bytes(contents).unwrap()
+                // Binaries are shown, and downloaded, as their text format.
+                if contents.starts_with(b"\0asm") {
+                    let name = match name.strip_suffix(".wasm") {
+                        Some(stem) => format!("{}.wat", stem),
+                        None => format!("{}.wat", name),
+                    };
+                    (name, wasmprinter::print_bytes(contents).unwrap())
                 } else {
-                    String::from_utf8_lossy(contents).into()
-                };
-                (name.to_string(), contents)
+                    (name.to_string(), String::from_utf8_lossy(contents).into())
+                }
             })
             .collect())
     }
 
-    fn set_rust_unchecked(&self, unchecked: bool) {
-        self.rust.borrow_mut().unchecked = unchecked;
+    fn set_flag(&self, lang: demo::Lang, name: String, enabled: bool) {
+        match (lang, name.as_str()) {
+            (demo::Lang::Js, "no-typescript") => self.js.borrow_mut().no_typescript = enabled,
+            (demo::Lang::Rust, "unchecked") => self.rust.borrow_mut().unchecked = enabled,
+            (demo::Lang::Rust, "multi-module") => self.rust.borrow_mut().multi_module = enabled,
+            (demo::Lang::Rust, "standalone") => self.rust.borrow_mut().standalone = enabled,
+            (demo::Lang::Rust, "force-generate-structs") => {
+                self.rust.borrow_mut().force_generate_structs = enabled
+            }
+            (demo::Lang::Rust, "export-abi-version") => {
+                self.rust.borrow_mut().export_abi_version = enabled
+            }
+            (demo::Lang::Rust, "return-arena") => self.rust.borrow_mut().return_arena = enabled,
+            (demo::Lang::Rust, "wasm-bindgen") => self.rust.borrow_mut().wasm_bindgen = enabled,
+            (demo::Lang::Wasmtime, "tracing") => self.wasmtime.borrow_mut().tracing = enabled,
+            (demo::Lang::Wasmtime, "custom-error") => {
+                self.wasmtime.borrow_mut().custom_error = enabled
+            }
+            (demo::Lang::WasmtimePy, "no-typescript") => {
+                self.wasmtime_py.borrow_mut().no_typescript = enabled
+            }
+            (demo::Lang::Spidermonkey, "import-memory") => {
+                self.spidermonkey.borrow_mut().import_memory = enabled
+            }
+            (demo::Lang::Wasmer, "tracing") => self.wasmer.borrow_mut().tracing = enabled,
+            (demo::Lang::Wasmer, "custom-error") => self.wasmer.borrow_mut().custom_error = enabled,
+            (demo::Lang::Wasmer, "record") => self.wasmer.borrow_mut().record = enabled,
+            (demo::Lang::Wasmer, "stubs") => self.wasmer.borrow_mut().stubs = enabled,
+            (demo::Lang::Wasmer, "metrics") => self.wasmer.borrow_mut().metrics = enabled,
+            (demo::Lang::Wasmer, "deadlines") => self.wasmer.borrow_mut().deadlines = enabled,
+            (demo::Lang::Wasmer, "memory-usage") => self.wasmer.borrow_mut().memory_usage = enabled,
+            (demo::Lang::Wasmer, "owned-params") => self.wasmer.borrow_mut().owned_params = enabled,
+            (demo::Lang::Wasmer, "plugin") => self.wasmer.borrow_mut().plugin = enabled,
+            (demo::Lang::Wasmer, "wasi") => self.wasmer.borrow_mut().wasi = enabled,
+            (demo::Lang::Wasmer, "shared-host") => self.wasmer.borrow_mut().shared_host = enabled,
+            (demo::Lang::WasmerPy, "no-typescript") => {
+                self.wasmer_py.borrow_mut().no_typescript = enabled
+            }
+            (demo::Lang::JsonSchema, "openapi") => self.json_schema.borrow_mut().openapi = enabled,
+            (lang, name) => panic!("unknown flag `{}` for {:?}", name, lang),
+        }
     }
 
-    fn set_wasmtime_tracing(&self, tracing: bool) {
-        self.wasmtime.borrow_mut().tracing = tracing;
-    }
-    fn set_wasmtime_custom_error(&self, custom_error: bool) {
-        browser::log("custom error");
-        self.wasmtime.borrow_mut().custom_error = custom_error;
+    fn set_option(&self, lang: demo::Lang, name: String, value: String) {
+        let value = Some(value).filter(|v| !v.is_empty());
+        match (lang, name.as_str()) {
+            (demo::Lang::Wasmer, "tracing-level") => {
+                self.wasmer.borrow_mut().tracing_level = match value {
+                    Some(level) => level.parse().unwrap(),
+                    None => Default::default(),
+                };
+            }
+            (demo::Lang::Proto, "package") => self.proto.borrow_mut().package = value,
+            (demo::Lang::RestGateway, "wai-path") => {
+                self.rest_gateway.borrow_mut().wai_path = value;
+            }
+            (demo::Lang::Conformance, "wai-path") => {
+                self.conformance.borrow_mut().wai_path = value;
+            }
+            (demo::Lang::RustTypes, "crate-name") => {
+                self.rust_types.borrow_mut().crate_name = value
+            }
+            (lang, name) => panic!("unknown option `{}` for {:?}", name, lang),
+        }
     }
+
     fn set_wasmtime_async(&self, async_: demo::WasmtimeAsync) {
         use wai_bindgen_gen_wasmtime::Async;
 
@@ -102,13 +175,6 @@ impl demo::Config for Config {
             demo::WasmtimeAsync::Only(list) => Async::Only(list.into_iter().collect()),
         };
     }
-    fn set_wasmer_tracing(&self, tracing: bool) {
-        self.wasmer.borrow_mut().tracing = tracing;
-    }
-    fn set_wasmer_custom_error(&self, custom_error: bool) {
-        browser::log("custom error");
-        self.wasmer.borrow_mut().custom_error = custom_error;
-    }
     fn set_wasmer_async(&self, async_: demo::WasmtimeAsync) {
         use wai_bindgen_gen_wasmer::Async;
 
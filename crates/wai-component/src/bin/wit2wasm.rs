@@ -6,8 +6,10 @@ fn main() {
         .format_target(false)
         .init();
 
-    if let Err(e) = WaiToWasmApp::parse().execute() {
-        log::error!("{:?}", e);
+    let app = WaiToWasmApp::parse();
+    let message_format = app.message_format;
+    if let Err(e) = app.execute() {
+        message_format.report_error(&e);
         std::process::exit(1);
     }
 }
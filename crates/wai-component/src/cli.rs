@@ -3,11 +3,16 @@
 #![deny(missing_docs)]
 
 use crate::{
-    decode_interface_component, ComponentEncoder, InterfaceEncoder, InterfacePrinter,
-    StringEncoding,
+    add_custom_section, check_link, decode_interface_component, extract_custom_section,
+    extract_module_interfaces, is_metadata_section, strip_custom_sections, ComponentEncoder,
+    InterfaceEncoder, InterfacePrinter, LinkProblem, StringEncoding,
 };
 use anyhow::{bail, Context, Result};
-use clap::Parser;
+use clap::{ArgEnum, Parser, Subcommand};
+use rayon::prelude::*;
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use wai_parser::Interface;
 
@@ -19,10 +24,6 @@ fn parse_named_interface(s: &str) -> Result<Interface> {
     parse_interface(Some(name.to_string()), Path::new(path))
 }
 
-fn parse_unnamed_interface(s: &str) -> Result<Interface> {
-    parse_interface(None, Path::new(s))
-}
-
 fn parse_interface(name: Option<String>, path: &Path) -> Result<Interface> {
     if !path.is_file() {
         bail!("interface file `{}` does not exist", path.display(),);
@@ -36,68 +37,179 @@ fn parse_interface(name: Option<String>, path: &Path) -> Result<Interface> {
     Ok(interface)
 }
 
+/// How the command line tools print their results and errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum MessageFormat {
+    /// Human-readable messages, with errors logged to stderr.
+    Human,
+    /// One JSON object per line on stdout, for editors and CI tooling.
+    ///
+    /// Errors have a `"reason"` of `"diagnostic"` along with the `file`,
+    /// `span`, `code` and `message` of the error, where all but the message
+    /// are `null` if the error isn't in an interface file. Warnings have a
+    /// `"reason"` of `"warning"` and a `message`. Written files have a
+    /// `"reason"` of `"generated-file"` and their `path`.
+    Json,
+}
+
+impl MessageFormat {
+    /// Reports that `path` was written, described by `msg` for humans.
+    pub fn report_output(self, msg: &str, path: &Path) {
+        match self {
+            MessageFormat::Human => println!("{}", msg),
+            MessageFormat::Json => println!(
+                "{}",
+                json!({
+                    "reason": "generated-file",
+                    "path": path,
+                })
+            ),
+        }
+    }
+
+    /// Reports a problem which didn't stop a command from succeeding.
+    pub fn report_warning(self, msg: &str) {
+        match self {
+            MessageFormat::Human => log::warn!("{}", msg),
+            MessageFormat::Json => println!(
+                "{}",
+                json!({
+                    "reason": "warning",
+                    "message": msg,
+                })
+            ),
+        }
+    }
+
+    /// Reports the error that caused a command to fail.
+    pub fn report_error(self, err: &anyhow::Error) {
+        match self {
+            MessageFormat::Human => log::error!("{:?}", err),
+            MessageFormat::Json => {
+                let msg = match err.downcast_ref::<wai_parser::Diagnostic>() {
+                    Some(diag) => json!({
+                        "reason": "diagnostic",
+                        "file": diag.file,
+                        "span": {
+                            "start": diag.span.start,
+                            "end": diag.span.end,
+                            "line": diag.line,
+                            "column": diag.column,
+                        },
+                        "code": diag.code,
+                        "message": diag.message,
+                    }),
+                    None => json!({
+                        "reason": "diagnostic",
+                        "file": null,
+                        "span": null,
+                        "code": null,
+                        "message": format!("{:#}", err),
+                    }),
+                };
+                println!("{}", msg);
+            }
+        }
+    }
+}
+
 /// WebAssembly component encoder.
 ///
-/// Encodes a WebAssembly component from a core WebAssembly module.
+/// Encodes a WebAssembly component from a core WebAssembly module, or runs a
+/// subcommand managing the custom sections of a module or component or
+/// checking whether a module fits a host.
 #[derive(Debug, Parser)]
-#[clap(name = "component-encoder", version = env!("CARGO_PKG_VERSION"))]
+#[clap(
+    name = "component-encoder",
+    version = env!("CARGO_PKG_VERSION"),
+    subcommand_negates_reqs = true
+)]
 pub struct WaiComponentApp {
+    /// The subcommand to run instead of encoding a component.
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
     /// The path to an interface definition file the component imports.
-    #[clap(long = "import", value_name = "NAME=INTERFACE", parse(try_from_str = parse_named_interface))]
-    pub imports: Vec<Interface>,
+    #[clap(long = "import", value_name = "NAME=INTERFACE")]
+    pub imports: Vec<String>,
 
     /// The path to an interface definition file the component exports.
-    #[clap(long = "export", value_name = "NAME=INTERFACE", parse(try_from_str = parse_named_interface))]
-    pub exports: Vec<Interface>,
+    #[clap(long = "export", value_name = "NAME=INTERFACE")]
+    pub exports: Vec<String>,
 
     /// The path of the output WebAssembly component.
     #[clap(long, short = 'o', value_name = "OUTPUT")]
     pub output: Option<PathBuf>,
 
+    /// The directory to write output components to, named after their
+    /// modules.
+    #[clap(long, value_name = "DIR", conflicts_with = "output")]
+    pub out_dir: Option<PathBuf>,
+
     /// The default interface the component exports.
-    #[clap(long, short = 'i', value_name = "INTERFACE", parse(try_from_str = parse_unnamed_interface))]
-    pub interface: Option<Interface>,
+    #[clap(long, short = 'i', value_name = "INTERFACE")]
+    pub interface: Option<PathBuf>,
 
     /// Skip validation of the output component.
+    ///
+    /// The component is then written out as it's encoded rather than kept in
+    /// memory, which helps with large modules.
     #[clap(long)]
     pub skip_validation: bool,
 
-    /// The expected string encoding format for the component.
+    /// The expected string encoding format for functions whose interface
+    /// doesn't give one with `@string-encoding`.
     /// Supported values are: `utf8` (default), `utf16`, and `compact-utf16`.
     #[clap(long, value_name = "ENCODING")]
     pub encoding: Option<StringEncoding>,
 
     /// Path to the WebAssembly module to encode.
-    #[clap(index = 1, value_name = "MODULE")]
-    pub module: PathBuf,
+    ///
+    /// A directory or glob pattern encodes each of the `.wasm` and `.wat`
+    /// modules it names in parallel.
+    #[clap(index = 1, value_name = "MODULE", required = true)]
+    pub module: Option<PathBuf>,
+
+    /// How to print results and errors.
+    #[clap(
+        long,
+        arg_enum,
+        value_name = "FMT",
+        default_value = "human",
+        global = true
+    )]
+    pub message_format: MessageFormat,
 }
 
 impl WaiComponentApp {
     /// Executes the application.
     pub fn execute(self) -> Result<()> {
-        if !self.module.is_file() {
-            bail!(
-                "module `{}` does not exist as a file",
-                self.module.display()
-            );
-        }
-
-        let output = self.output.unwrap_or_else(|| {
-            let mut stem: PathBuf = self.module.file_stem().unwrap().into();
-            stem.set_extension("wasm");
-            stem
-        });
-
-        let module = wat::parse_file(&self.module)
-            .with_context(|| format!("failed to parse module `{}`", self.module.display()))?;
+        let module_path = match &self.command {
+            Some(_) => return self.command.unwrap().execute(self.message_format),
+            None => self.module.as_ref().unwrap(),
+        };
+
+        let imports = self
+            .imports
+            .iter()
+            .map(|s| parse_named_interface(s))
+            .collect::<Result<Vec<_>>>()?;
+        let exports = self
+            .exports
+            .iter()
+            .map(|s| parse_named_interface(s))
+            .collect::<Result<Vec<_>>>()?;
+        let interface = match &self.interface {
+            Some(path) => Some(parse_interface(None, path)?),
+            None => None,
+        };
 
         let mut encoder = ComponentEncoder::default()
-            .module(&module)
-            .imports(&self.imports)
-            .exports(&self.exports)
+            .imports(&imports)
+            .exports(&exports)
             .validate(!self.skip_validation);
 
-        if let Some(interface) = &self.interface {
+        if let Some(interface) = &interface {
             encoder = encoder.interface(interface);
         }
 
@@ -105,22 +217,462 @@ impl WaiComponentApp {
             encoder = encoder.encoding(*encoding);
         }
 
-        let bytes = encoder.encode().with_context(|| {
+        create_out_dir(self.out_dir.as_deref())?;
+        match batch_inputs(module_path, &["wasm", "wat"])? {
+            Some(inputs) => {
+                let jobs = batch_outputs(
+                    inputs,
+                    self.output.as_deref(),
+                    self.out_dir.as_deref(),
+                    "wasm",
+                )?;
+                run_batch(&jobs, self.message_format, |input, output| {
+                    self.encode(&encoder, input, output)
+                })
+            }
+            None => {
+                let output = output_path(
+                    self.output.as_deref(),
+                    self.out_dir.as_deref(),
+                    module_path,
+                    "wasm",
+                );
+                self.encode(&encoder, module_path, &output)
+            }
+        }
+    }
+
+    fn encode(&self, encoder: &ComponentEncoder, module_path: &Path, output: &Path) -> Result<()> {
+        if !module_path.is_file() {
+            bail!(
+                "module `{}` does not exist as a file",
+                module_path.display()
+            );
+        }
+
+        let module = wat::parse_file(module_path)
+            .with_context(|| format!("failed to parse module `{}`", module_path.display()))?;
+        let encoder = encoder.clone().module(&module);
+
+        let context = || {
             format!(
                 "failed to encode a component from module `{}`",
-                self.module.display()
+                module_path.display()
+            )
+        };
+        if self.skip_validation {
+            // Without validation the component doesn't need to be kept in
+            // memory, so write it out as it's encoded.
+            let file = std::fs::File::create(output)
+                .with_context(|| format!("failed to create output file `{}`", output.display()))?;
+            let mut file = std::io::BufWriter::new(file);
+            encoder.encode_to(&mut file).with_context(context)?;
+            file.flush()
+                .with_context(|| format!("failed to write output file `{}`", output.display()))?;
+        } else {
+            let bytes = encoder.encode().with_context(context)?;
+            std::fs::write(output, bytes)
+                .with_context(|| format!("failed to write output file `{}`", output.display()))?;
+        }
+
+        self.message_format
+            .report_output(&format!("encoded component `{}`", output.display()), output);
+
+        Ok(())
+    }
+}
+
+/// Returns the inputs named by `input` if it's a directory, in which case
+/// they're its files with one of `extensions`, or a glob pattern, or `None`
+/// if it names a single file.
+fn batch_inputs(input: &Path, extensions: &[&str]) -> Result<Option<Vec<PathBuf>>> {
+    let mut inputs = Vec::new();
+    if input.is_dir() {
+        let entries = std::fs::read_dir(input)
+            .with_context(|| format!("failed to read directory `{}`", input.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            let ext = path.extension().and_then(|e| e.to_str());
+            if path.is_file() && ext.map_or(false, |e| extensions.contains(&e)) {
+                inputs.push(path);
+            }
+        }
+    } else if !input.exists()
+        && input
+            .to_str()
+            .map_or(false, |s| s.contains(['*', '?', '[']))
+    {
+        let pattern = input.to_str().unwrap();
+        let paths =
+            glob::glob(pattern).with_context(|| format!("invalid glob pattern `{}`", pattern))?;
+        for path in paths {
+            let path = path?;
+            if path.is_file() {
+                inputs.push(path);
+            }
+        }
+    } else {
+        return Ok(None);
+    }
+
+    if inputs.is_empty() {
+        bail!("no inputs found in `{}`", input.display());
+    }
+    inputs.sort();
+    Ok(Some(inputs))
+}
+
+fn create_out_dir(out_dir: Option<&Path>) -> Result<()> {
+    match out_dir {
+        Some(dir) => std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create output directory `{}`", dir.display())),
+        None => Ok(()),
+    }
+}
+
+/// Pairs each of the batch `inputs` with the path to write its output to.
+///
+/// Fails if `output` is given, since a single output file can't hold the
+/// results of several inputs, or if two inputs would be written to the same
+/// file, such as `a.wasm` and `a.wat`.
+fn batch_outputs(
+    inputs: Vec<PathBuf>,
+    output: Option<&Path>,
+    out_dir: Option<&Path>,
+    ext: &str,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
+    if output.is_some() {
+        bail!("`--output` can't be used with several inputs; use `--out-dir` instead");
+    }
+
+    let mut seen = HashMap::new();
+    let mut jobs = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let output = output_path(None, out_dir, &input, ext);
+        if let Some(prev) = seen.insert(output.clone(), input.clone()) {
+            bail!(
+                "inputs `{}` and `{}` would both be written to `{}`",
+                prev.display(),
+                input.display(),
+                output.display()
+            );
+        }
+        jobs.push((input, output));
+    }
+    Ok(jobs)
+}
+
+/// Calls `f` with each input and output of `jobs` in parallel, reporting the
+/// errors it returns and then failing with a summary if there were any.
+fn run_batch(
+    jobs: &[(PathBuf, PathBuf)],
+    message_format: MessageFormat,
+    f: impl Fn(&Path, &Path) -> Result<()> + Sync,
+) -> Result<()> {
+    let errors = jobs
+        .par_iter()
+        .filter_map(|(input, output)| f(input, output).err())
+        .collect::<Vec<_>>();
+    for err in errors.iter() {
+        message_format.report_error(err);
+    }
+    if !errors.is_empty() {
+        bail!(
+            "failed to process {} of {} inputs",
+            errors.len(),
+            jobs.len()
+        );
+    }
+    Ok(())
+}
+
+/// Returns where to write the output for `input`: `output` if it's given, or
+/// otherwise a file named after `input` with the extension `ext` in
+/// `out_dir`, or in the current directory.
+fn output_path(output: Option<&Path>, out_dir: Option<&Path>, input: &Path, ext: &str) -> PathBuf {
+    if let Some(output) = output {
+        return output.to_path_buf();
+    }
+    let mut stem: PathBuf = input.file_stem().unwrap().into();
+    stem.set_extension(ext);
+    match out_dir {
+        Some(dir) => dir.join(stem),
+        None => stem,
+    }
+}
+
+/// A subcommand of `wai-component`.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Removes custom sections, such as debug information.
+    Strip(StripCommand),
+    /// Adds a custom section, replacing any with the same name.
+    AddSection(AddSectionCommand),
+    /// Writes the contents of a custom section to a file.
+    ExtractSection(ExtractSectionCommand),
+    /// Reports why a module doesn't fit the interfaces of a host.
+    #[clap(alias = "why")]
+    LinkCheck(LinkCheckCommand),
+}
+
+impl Command {
+    /// Executes the subcommand.
+    pub fn execute(self, message_format: MessageFormat) -> Result<()> {
+        match self {
+            Command::Strip(cmd) => cmd.execute(message_format),
+            Command::AddSection(cmd) => cmd.execute(message_format),
+            Command::ExtractSection(cmd) => cmd.execute(message_format),
+            Command::LinkCheck(cmd) => cmd.execute(message_format),
+        }
+    }
+}
+
+/// Removes the custom sections of a module or component, including those of
+/// the modules and components nested in a component.
+#[derive(Debug, Parser)]
+pub struct StripCommand {
+    /// Keep the `name`, `producers` and `wai-interface` sections.
+    #[clap(long)]
+    pub keep_metadata: bool,
+
+    /// The name of a custom section to keep.
+    #[clap(long = "keep", value_name = "NAME")]
+    pub keep: Vec<String>,
+
+    /// The path of the output file, which defaults to the input file.
+    #[clap(long, short = 'o', value_name = "OUTPUT")]
+    pub output: Option<PathBuf>,
+
+    /// The path to the WebAssembly module or component to strip.
+    #[clap(index = 1, value_name = "INPUT")]
+    pub input: PathBuf,
+}
+
+impl StripCommand {
+    fn execute(self, message_format: MessageFormat) -> Result<()> {
+        let bytes = read_binary(&self.input)?;
+        let bytes = strip_custom_sections(&bytes, |name| {
+            (self.keep_metadata && is_metadata_section(name)) || self.keep.iter().any(|k| k == name)
+        })
+        .with_context(|| format!("failed to strip `{}`", self.input.display()))?;
+
+        let output = self.output.as_ref().unwrap_or(&self.input);
+        write_output(output, &bytes)?;
+        message_format.report_output(&format!("stripped `{}`", output.display()), output);
+        Ok(())
+    }
+}
+
+/// Adds a custom section to the end of a module or component, replacing any
+/// sections it already has with the same name.
+#[derive(Debug, Parser)]
+pub struct AddSectionCommand {
+    /// The name of the section.
+    #[clap(long, value_name = "NAME")]
+    pub name: String,
+
+    /// The path to the file holding the contents of the section.
+    #[clap(long, value_name = "FILE")]
+    pub data: PathBuf,
+
+    /// The path of the output file, which defaults to the input file.
+    #[clap(long, short = 'o', value_name = "OUTPUT")]
+    pub output: Option<PathBuf>,
+
+    /// The path to the WebAssembly module or component to add the section to.
+    #[clap(index = 1, value_name = "INPUT")]
+    pub input: PathBuf,
+}
+
+impl AddSectionCommand {
+    fn execute(self, message_format: MessageFormat) -> Result<()> {
+        let bytes = read_binary(&self.input)?;
+        let data = std::fs::read(&self.data)
+            .with_context(|| format!("failed to read `{}`", self.data.display()))?;
+        let bytes = add_custom_section(&bytes, &self.name, &data).with_context(|| {
+            format!(
+                "failed to add section `{}` to `{}`",
+                self.name,
+                self.input.display()
             )
         })?;
 
-        std::fs::write(&output, bytes)
-            .with_context(|| format!("failed to write output file `{}`", output.display()))?;
+        let output = self.output.as_ref().unwrap_or(&self.input);
+        write_output(output, &bytes)?;
+        message_format.report_output(
+            &format!("added section `{}` to `{}`", self.name, output.display()),
+            output,
+        );
+        Ok(())
+    }
+}
+
+/// Writes the contents of the first custom section with a given name of a
+/// module or component to a file.
+#[derive(Debug, Parser)]
+pub struct ExtractSectionCommand {
+    /// The name of the section.
+    #[clap(long, value_name = "NAME")]
+    pub name: String,
 
-        println!("encoded component `{}`", output.display());
+    /// The path of the output file.
+    #[clap(long, short = 'o', value_name = "OUTPUT")]
+    pub output: PathBuf,
+
+    /// The path to the WebAssembly module or component to read the section
+    /// from.
+    #[clap(index = 1, value_name = "INPUT")]
+    pub input: PathBuf,
+}
 
+impl ExtractSectionCommand {
+    fn execute(self, message_format: MessageFormat) -> Result<()> {
+        let bytes = read_binary(&self.input)?;
+        let data = extract_custom_section(&bytes, &self.name)
+            .with_context(|| format!("failed to read sections of `{}`", self.input.display()))?
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "`{}` has no custom section named `{}`",
+                    self.input.display(),
+                    self.name
+                )
+            })?;
+
+        write_output(&self.output, data)?;
+        message_format.report_output(
+            &format!(
+                "extracted section `{}` to `{}`",
+                self.name,
+                self.output.display()
+            ),
+            &self.output,
+        );
         Ok(())
     }
 }
 
+/// Reports every import of a module the host doesn't provide, and every
+/// export the host expects which the module lacks or exports with another
+/// signature, along with the exports the host doesn't use.
+///
+/// With `--message-format json` each problem is printed as an object with a
+/// `"reason"` of `"link-problem"`, its `kind`, `message` and whether it's an
+/// `error`, along with the `module` and `name` of the function and the
+/// `expected` and `found` signatures where they apply.
+#[derive(Debug, Parser)]
+pub struct LinkCheckCommand {
+    /// The path to an interface definition file the host provides to the
+    /// module.
+    #[clap(long = "import", value_name = "NAME=INTERFACE")]
+    pub imports: Vec<String>,
+
+    /// The path to an interface definition file the host expects the module
+    /// to export.
+    #[clap(long = "export", value_name = "NAME=INTERFACE")]
+    pub exports: Vec<String>,
+
+    /// The default interface the host expects the module to export.
+    #[clap(long, short = 'i', value_name = "INTERFACE")]
+    pub interface: Option<PathBuf>,
+
+    /// The path to the WebAssembly module to check.
+    #[clap(index = 1, value_name = "MODULE")]
+    pub module: PathBuf,
+}
+
+impl LinkCheckCommand {
+    fn execute(self, message_format: MessageFormat) -> Result<()> {
+        let module = read_binary(&self.module)?;
+        let imports = self
+            .imports
+            .iter()
+            .map(|s| parse_named_interface(s))
+            .collect::<Result<Vec<_>>>()?;
+        let exports = self
+            .exports
+            .iter()
+            .map(|s| parse_named_interface(s))
+            .collect::<Result<Vec<_>>>()?;
+        let interface = match &self.interface {
+            Some(path) => Some(parse_interface(None, path)?),
+            None => None,
+        };
+
+        let problems = check_link(&module, interface.as_ref(), &imports, &exports)
+            .with_context(|| format!("failed to check module `{}`", self.module.display()))?;
+
+        for problem in problems.iter() {
+            match message_format {
+                MessageFormat::Human if problem.is_error() => log::error!("{}", problem),
+                MessageFormat::Human => log::warn!("{}", problem),
+                MessageFormat::Json => {
+                    let (module, name, expected, found) = match problem {
+                        LinkProblem::MissingImport { module, name } => {
+                            (Some(module), name, None, None)
+                        }
+                        LinkProblem::ImportMismatch {
+                            module,
+                            name,
+                            expected,
+                            found,
+                        } => (Some(module), name, Some(expected), Some(found)),
+                        LinkProblem::MissingExport { name } | LinkProblem::ExtraExport { name } => {
+                            (None, name, None, None)
+                        }
+                        LinkProblem::ExportMismatch {
+                            name,
+                            expected,
+                            found,
+                        } => (None, name, Some(expected), Some(found)),
+                    };
+                    println!(
+                        "{}",
+                        json!({
+                            "reason": "link-problem",
+                            "kind": problem.kind(),
+                            "error": problem.is_error(),
+                            "module": module,
+                            "name": name,
+                            "expected": expected,
+                            "found": found,
+                            "message": problem.to_string(),
+                        })
+                    );
+                }
+            }
+        }
+
+        let errors = problems.iter().filter(|p| p.is_error()).count();
+        if errors > 0 {
+            bail!(
+                "module `{}` doesn't fit the host's interfaces: found {} problem{}",
+                self.module.display(),
+                errors,
+                if errors == 1 { "" } else { "s" }
+            );
+        }
+        if message_format == MessageFormat::Human {
+            println!(
+                "module `{}` fits the host's interfaces",
+                self.module.display()
+            );
+        }
+        Ok(())
+    }
+}
+
+fn read_binary(path: &Path) -> Result<Vec<u8>> {
+    if !path.is_file() {
+        bail!("`{}` does not exist as a file", path.display());
+    }
+    wat::parse_file(path).with_context(|| format!("failed to parse `{}`", path.display()))
+}
+
+fn write_output(path: &Path, bytes: &[u8]) -> Result<()> {
+    std::fs::write(path, bytes)
+        .with_context(|| format!("failed to write output file `{}`", path.display()))
+}
+
 /// WebAssembly interface encoder.
 ///
 /// Encodes a WebAssembly interface as a WebAssembly component.
@@ -131,35 +683,70 @@ pub struct WaiToWasmApp {
     #[clap(long, short = 'o', value_name = "OUTPUT")]
     pub output: Option<PathBuf>,
 
+    /// The directory to write output components to, named after their
+    /// interfaces.
+    #[clap(long, value_name = "DIR", conflicts_with = "output")]
+    pub out_dir: Option<PathBuf>,
+
     /// The path to the WebAssembly interface file to encode.
+    ///
+    /// A directory or glob pattern encodes each of the `.wai` files it names
+    /// in parallel.
     #[clap(index = 1, value_name = "INTERFACE")]
     pub interface: PathBuf,
+
+    /// How to print results and errors.
+    #[clap(long, arg_enum, value_name = "FMT", default_value = "human")]
+    pub message_format: MessageFormat,
 }
 
 impl WaiToWasmApp {
     /// Executes the application.
     pub fn execute(self) -> Result<()> {
-        let output = self.output.unwrap_or_else(|| {
-            let mut stem: PathBuf = self.interface.file_stem().unwrap().into();
-            stem.set_extension("wasm");
-            stem
-        });
+        create_out_dir(self.out_dir.as_deref())?;
+        match batch_inputs(&self.interface, &["wai"])? {
+            Some(inputs) => {
+                let jobs = batch_outputs(
+                    inputs,
+                    self.output.as_deref(),
+                    self.out_dir.as_deref(),
+                    "wasm",
+                )?;
+                run_batch(&jobs, self.message_format, |input, output| {
+                    self.encode(input, output)
+                })
+            }
+            None => {
+                let output = output_path(
+                    self.output.as_deref(),
+                    self.out_dir.as_deref(),
+                    &self.interface,
+                    "wasm",
+                );
+                self.encode(&self.interface, &output)
+            }
+        }
+    }
 
-        let interface = parse_interface(None, &self.interface)?;
+    fn encode(&self, path: &Path, output: &Path) -> Result<()> {
+        let interface = parse_interface(None, path)?;
 
         let encoder = InterfaceEncoder::new(&interface).validate(true);
 
         let bytes = encoder.encode().with_context(|| {
             format!(
                 "failed to encode a component from interface `{}`",
-                self.interface.display()
+                path.display()
             )
         })?;
 
-        std::fs::write(&output, bytes)
+        std::fs::write(output, bytes)
             .with_context(|| format!("failed to write output file `{}`", output.display()))?;
 
-        println!("encoded interface as component `{}`", output.display());
+        self.message_format.report_output(
+            &format!("encoded interface as component `{}`", output.display()),
+            output,
+        );
 
         Ok(())
     }
@@ -167,7 +754,9 @@ impl WaiToWasmApp {
 
 /// WebAssembly interface decoder.
 ///
-/// Decodes a WebAssembly interface from a WebAssembly component.
+/// Decodes a WebAssembly interface from a WebAssembly component, or
+/// reconstructs the interfaces of a core WebAssembly module as well as
+/// possible.
 #[derive(Debug, Parser)]
 #[clap(name = "wasm2wai", version = env!("CARGO_PKG_VERSION"))]
 pub struct WasmToWaiApp {
@@ -175,41 +764,159 @@ pub struct WasmToWaiApp {
     #[clap(long, short = 'o', value_name = "OUTPUT")]
     pub output: Option<PathBuf>,
 
+    /// The directory to write output interface files to, named after their
+    /// components.
+    #[clap(long, value_name = "DIR", conflicts_with = "output")]
+    pub out_dir: Option<PathBuf>,
+
     /// The path to the WebAssembly component to decode.
+    ///
+    /// If this is a core module instead, the interface of its exports is
+    /// written to the output, and the interfaces it imports and exports as
+    /// `<name>#<func>` to `import-<name>.wai` and `export-<name>.wai` files
+    /// next to it. Signatures which had to be guessed are listed in comments
+    /// at the top of each file.
+    ///
+    /// A directory or glob pattern decodes each of the `.wasm` and `.wat`
+    /// files it names in parallel, and the interfaces of core modules are
+    /// then written to `<module>-import-<name>.wai` and
+    /// `<module>-export-<name>.wai` files instead.
     #[clap(index = 1, value_name = "COMPONENT")]
     pub component: PathBuf,
+
+    /// Keep the comments of the output file, if it already exists, next to
+    /// the types and functions they were written next to.
+    #[clap(long)]
+    pub keep_comments: bool,
+
+    /// How to print results and errors.
+    #[clap(long, arg_enum, value_name = "FMT", default_value = "human")]
+    pub message_format: MessageFormat,
 }
 
 impl WasmToWaiApp {
     /// Executes the application.
     pub fn execute(self) -> Result<()> {
-        let output = self.output.unwrap_or_else(|| {
-            let mut stem: PathBuf = self.component.file_stem().unwrap().into();
-            stem.set_extension("wai");
-            stem
-        });
+        create_out_dir(self.out_dir.as_deref())?;
+        match batch_inputs(&self.component, &["wasm", "wat"])? {
+            Some(inputs) => {
+                let jobs = batch_outputs(
+                    inputs,
+                    self.output.as_deref(),
+                    self.out_dir.as_deref(),
+                    "wai",
+                )?;
+                run_batch(&jobs, self.message_format, |input, output| {
+                    let stem = input.file_stem().unwrap().to_string_lossy();
+                    self.decode(input, output, &format!("{}-", stem))
+                })
+            }
+            None => {
+                let output = output_path(
+                    self.output.as_deref(),
+                    self.out_dir.as_deref(),
+                    &self.component,
+                    "wai",
+                );
+                self.decode(&self.component, &output, "")
+            }
+        }
+    }
 
-        if !self.component.is_file() {
-            bail!(
-                "component `{}` does not exist as a file",
-                self.component.display()
-            );
+    /// Decodes the component at `path` to `output`, naming the other files
+    /// written for core modules with `prefix`.
+    fn decode(&self, path: &Path, output: &Path, prefix: &str) -> Result<()> {
+        if !path.is_file() {
+            bail!("component `{}` does not exist as a file", path.display());
         }
 
-        let bytes = wat::parse_file(&self.component)
-            .with_context(|| format!("failed to parse component `{}`", self.component.display()))?;
+        let bytes = wat::parse_file(path)
+            .with_context(|| format!("failed to parse component `{}`", path.display()))?;
 
-        let interface = decode_interface_component(&bytes).with_context(|| {
-            format!("failed to decode component `{}`", self.component.display())
-        })?;
+        if is_core_module(&bytes) {
+            return self.extract(path, &bytes, output, prefix);
+        }
+
+        let interface = decode_interface_component(&bytes)
+            .with_context(|| format!("failed to decode component `{}`", path.display()))?;
 
         let mut printer = InterfacePrinter::default();
+        if self.keep_comments && output.is_file() {
+            let source = std::fs::read_to_string(output)
+                .with_context(|| format!("failed to read output file `{}`", output.display()))?;
+            printer
+                .keep_comments(&source)
+                .with_context(|| format!("failed to parse output file `{}`", output.display()))?;
+        }
 
-        std::fs::write(&output, printer.print(&interface)?)
+        std::fs::write(output, printer.print(&interface)?)
             .with_context(|| format!("failed to write output file `{}`", output.display()))?;
 
-        println!("decoded interface to `{}`", output.display());
+        self.message_format.report_output(
+            &format!("decoded interface to `{}`", output.display()),
+            output,
+        );
 
         Ok(())
     }
+
+    fn extract(&self, path: &Path, bytes: &[u8], output: &Path, prefix: &str) -> Result<()> {
+        let interfaces = extract_module_interfaces(bytes).with_context(|| {
+            format!(
+                "failed to reconstruct interfaces of module `{}`",
+                path.display()
+            )
+        })?;
+
+        let dir = output.parent().unwrap_or_else(|| Path::new(""));
+        let files = std::iter::once((output.to_path_buf(), &interfaces.default))
+            .chain(interfaces.exports.iter().map(|i| {
+                let path = dir.join(format!("{}export-{}.wai", prefix, i.interface.name));
+                (path, i)
+            }))
+            .chain(interfaces.imports.iter().map(|i| {
+                let path = dir.join(format!("{}import-{}.wai", prefix, i.interface.name));
+                (path, i)
+            }));
+
+        for (path, extracted) in files {
+            let mut contents = String::new();
+            for warning in &extracted.warnings {
+                self.message_format
+                    .report_warning(&format!("{}: {}", path.display(), warning));
+                contents.push_str(&format!("// warning: {}\n", warning));
+            }
+            if !contents.is_empty() {
+                contents.push('\n');
+            }
+            contents.push_str(&InterfacePrinter::default().print(&extracted.interface)?);
+
+            std::fs::write(&path, contents)
+                .with_context(|| format!("failed to write output file `{}`", path.display()))?;
+
+            let how = if extracted.embedded {
+                "extracted embedded"
+            } else {
+                "reconstructed"
+            };
+            self.message_format
+                .report_output(&format!("{} interface to `{}`", how, path.display()), &path);
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns whether `bytes` are a core module rather than a component.
+fn is_core_module(bytes: &[u8]) -> bool {
+    matches!(
+        wasmparser::Parser::new(0).parse(bytes, true),
+        Ok(wasmparser::Chunk::Parsed {
+            payload: wasmparser::Payload::Version {
+                encoding: wasmparser::Encoding::Module,
+                ..
+            },
+            ..
+        })
+    )
 }
@@ -255,6 +255,9 @@ impl<'a> InterfaceDecoder<'a> {
             kind: FunctionKind::Freestanding,
             params,
             result,
+            requires: Vec::new(),
+            ranges: Vec::new(),
+            string_encoding: None,
         });
 
         Ok(())
@@ -410,6 +413,7 @@ impl<'a> InterfaceDecoder<'a> {
                     })
                 })
                 .collect::<Result<_>>()?,
+            non_exhaustive: false,
         };
 
         Ok(Type::Id(self.alloc_type(
@@ -486,6 +490,7 @@ impl<'a> InterfaceDecoder<'a> {
                     })
                 })
                 .collect::<Result<_>>()?,
+            non_exhaustive: false,
         };
 
         Ok(Type::Id(
@@ -1,11 +1,15 @@
 use crate::{
-    validation::{expected_export_name, validate_module},
+    canonical_option,
+    validation::{expected_export_name, validate_module, ModuleInfo, INITIALIZE_EXPORT},
     StringEncoding,
 };
 use anyhow::{anyhow, bail, Context, Result};
 use indexmap::{map::Entry, IndexMap, IndexSet};
 use std::{
+    collections::{BTreeMap, HashMap},
     hash::{Hash, Hasher},
+    io::Write,
+    mem,
     ops::{BitOr, BitOrAssign},
 };
 use wai_parser::{
@@ -428,11 +432,11 @@ impl<'a> TypeEncoder<'a> {
 
     fn encode_instance_imports(
         &mut self,
-        interfaces: &'a [Interface],
+        interfaces: &[&'a Interface],
         required_imports: &IndexSet<&'a str>,
         imports: &mut ImportEncoder<'a>,
     ) -> Result<()> {
-        for import in interfaces {
+        for import in interfaces.iter().copied() {
             if !required_imports.contains(import.name.as_str()) {
                 continue;
             }
@@ -479,6 +483,17 @@ impl<'a> TypeEncoder<'a> {
         Ok(())
     }
 
+    /// Encodes the type of the core module's `_initialize` function, which
+    /// takes and returns nothing.
+    fn encode_initialize_func_type(&mut self) -> u32 {
+        let index = self.types.len();
+        self.types.function(
+            [] as [(Option<&str>, ComponentValType); 0],
+            PrimitiveValType::Unit,
+        );
+        index
+    }
+
     fn encode_instance_type(&mut self, ty: &InstanceType) -> u32 {
         let index = self.types.len();
         self.types.instance(ty);
@@ -869,10 +884,11 @@ impl RequiredOptions {
         encoding: StringEncoding,
         memory_index: Option<u32>,
         realloc_index: Option<u32>,
+        post_return_index: Option<u32>,
     ) -> Result<impl Iterator<Item = CanonicalOption> + ExactSizeIterator> {
         #[derive(Default)]
         struct Iter {
-            options: [Option<CanonicalOption>; 3],
+            options: [Option<CanonicalOption>; 4],
             current: usize,
             count: usize,
         }
@@ -906,29 +922,26 @@ impl RequiredOptions {
 
         let mut iter = Iter::default();
 
-        if self == RequiredOptions::None {
-            return Ok(iter);
+        if self != RequiredOptions::None {
+            iter.push(CanonicalOption::Memory(memory_index.ok_or_else(|| {
+                anyhow!("module does not export a memory named `memory`")
+            })?));
         }
 
-        iter.push(CanonicalOption::Memory(memory_index.ok_or_else(|| {
-            anyhow!("module does not export a memory named `memory`")
-        })?));
-
-        if self == RequiredOptions::Memory {
-            return Ok(iter);
+        if matches!(self, RequiredOptions::Realloc | RequiredOptions::All) {
+            iter.push(CanonicalOption::Realloc(realloc_index.ok_or_else(
+                || anyhow!("module does not export a function named `canonical_abi_realloc`"),
+            )?));
         }
 
-        iter.push(CanonicalOption::Realloc(realloc_index.ok_or_else(
-            || anyhow!("module does not export a function named `canonical_abi_realloc`"),
-        )?));
-
-        if self == RequiredOptions::Realloc {
-            return Ok(iter);
+        if self == RequiredOptions::All {
+            iter.push(canonical_option(encoding));
         }
 
-        assert_eq!(self, RequiredOptions::All);
+        if let Some(index) = post_return_index {
+            iter.push(CanonicalOption::PostReturn(index));
+        }
 
-        iter.push(encoding.into());
         Ok(iter)
     }
 }
@@ -975,6 +988,9 @@ struct EncodingState {
     ///
     /// If `None`, then the realloc function has not yet been aliased.
     realloc_index: Option<u32>,
+    /// The indexes in the core function index space of the realloc and
+    /// post-return functions named by the options of individual functions.
+    option_func_indexes: HashMap<String, u32>,
     /// The index of the shim instance used for lowering imports into the core instance.
     ///
     /// If `None`, then the shim instance how not yet been encoded.
@@ -985,27 +1001,35 @@ struct EncodingState {
     fixups_module_index: Option<u32>,
 }
 
-impl EncodingState {
-    fn encode_core_module(&mut self, module: &[u8]) -> u32 {
-        *self.module_index.get_or_insert_with(|| {
-            self.component.section(&wasm_encoder::RawSection {
-                id: ComponentSectionId::CoreModule.into(),
-                data: module,
-            });
-
-            self.indexes.alloc_core_module()
-        })
+/// Writes a section with the given id and contents, the same way
+/// `wasm_encoder::RawSection` encodes it.
+fn write_section(out: &mut impl Write, id: u8, data: &[u8]) -> Result<()> {
+    out.write_all(&[id])?;
+    let mut len = data.len();
+    loop {
+        let byte = (len & 0x7f) as u8;
+        len >>= 7;
+        if len == 0 {
+            out.write_all(&[byte])?;
+            break;
+        }
+        out.write_all(&[byte | 0x80])?;
     }
+    out.write_all(data)?;
+    Ok(())
+}
 
+impl EncodingState {
     fn encode_core_instantiation(
         &mut self,
-        encoding: StringEncoding,
+        encoder: &ComponentEncoder,
         imports: &ImportEncoder,
         has_memory: bool,
         has_realloc: bool,
     ) -> Result<()> {
+        let option_funcs = encoder.option_funcs();
         if imports.map.is_empty() {
-            self.instantiate_core_module([], has_memory, has_realloc);
+            self.instantiate_core_module([], has_memory, has_realloc, &option_funcs);
             return Ok(());
         }
 
@@ -1053,8 +1077,8 @@ impl EncodingState {
 
         self.component.section(&instances);
 
-        self.instantiate_core_module(args, has_memory, has_realloc);
-        self.encode_indirect_lowerings(encoding, imports)
+        self.instantiate_core_module(args, has_memory, has_realloc, &option_funcs);
+        self.encode_indirect_lowerings(encoder, imports)
     }
 
     fn encode_imports(&mut self, imports: &ImportEncoder) {
@@ -1072,7 +1096,7 @@ impl EncodingState {
 
     fn encode_exports<'a>(
         &mut self,
-        encoding: StringEncoding,
+        encoder: &ComponentEncoder,
         exports: impl Iterator<Item = (&'a Interface, bool)>,
         func_types: &IndexMap<FunctionKey<'a>, u32>,
     ) -> Result<()> {
@@ -1112,12 +1136,15 @@ impl EncodingState {
                         RequiredOptions::None
                     });
 
-                // TODO: support the post-return option somehow (not yet supported in wai-bindgen)
                 let func_index = self.lift_func(
                     &mut functions,
                     core_func_index,
                     ty,
-                    options.into_iter(encoding, self.memory_index, self.realloc_index)?,
+                    self.canonical_options(
+                        options,
+                        export.string_encoding_of(func).unwrap_or(encoder.encoding),
+                        encoder.export_options.get(name.as_ref()),
+                    )?,
                 );
 
                 if is_default {
@@ -1271,9 +1298,34 @@ impl EncodingState {
         code.function(&func);
     }
 
+    /// Lifts the core module's `_initialize` function and calls it when the
+    /// component is instantiated, which is after the imports have been
+    /// lowered into the core instance.
+    fn encode_initialize(&mut self, type_index: u32) {
+        let core_instance_index = self.instance_index.expect("must be instantiated");
+
+        let mut aliases = AliasSection::new();
+        let core_func_index = self.alias_core_item(
+            &mut aliases,
+            core_instance_index,
+            ExportKind::Func,
+            INITIALIZE_EXPORT,
+        );
+        self.component.section(&aliases);
+
+        let mut functions = CanonicalFunctionSection::new();
+        let func_index = self.lift_func(&mut functions, core_func_index, type_index, []);
+        self.component.section(&functions);
+
+        self.component.section(&ComponentStartSection {
+            function_index: func_index,
+            args: [],
+        });
+    }
+
     fn encode_indirect_lowerings(
         &mut self,
-        encoding: StringEncoding,
+        encoder: &ComponentEncoder,
         imports: &ImportEncoder,
     ) -> Result<()> {
         if imports.indirect_count == 0 {
@@ -1298,17 +1350,22 @@ impl EncodingState {
 
         let mut aliases = ComponentAliasSection::new();
         let mut functions = CanonicalFunctionSection::new();
-        for (instance_index, import) in imports.map.values().enumerate() {
+        for (instance_index, (name, import)) in imports.map.iter().enumerate() {
             for lowering in &import.indirect {
                 let func_index =
                     self.alias_func(&mut aliases, instance_index as u32, lowering.name);
 
+                let options = encoder
+                    .import_options
+                    .get(&(name.to_string(), lowering.name.to_string()));
                 let core_func_index = self.lower_func(
                     &mut functions,
                     func_index,
-                    lowering
-                        .options
-                        .into_iter(encoding, self.memory_index, self.realloc_index)?,
+                    self.canonical_options(
+                        lowering.options,
+                        lowering.encoding.unwrap_or(encoder.encoding),
+                        options,
+                    )?,
                 );
 
                 exports.push((
@@ -1333,8 +1390,13 @@ impl EncodingState {
         Ok(())
     }
 
-    fn instantiate_core_module<'a, A>(&mut self, args: A, has_memory: bool, has_realloc: bool)
-    where
+    fn instantiate_core_module<'a, A>(
+        &mut self,
+        args: A,
+        has_memory: bool,
+        has_realloc: bool,
+        option_funcs: &[&str],
+    ) where
         A: IntoIterator<Item = (&'a str, ModuleArg)>,
         A::IntoIter: ExactSizeIterator,
     {
@@ -1367,12 +1429,45 @@ impl EncodingState {
             ));
         }
 
+        for name in option_funcs {
+            let index = self.alias_core_item(&mut aliases, instance_index, ExportKind::Func, name);
+            self.option_func_indexes.insert(name.to_string(), index);
+        }
+
         self.component.section(&instances);
         self.component.section(&aliases);
 
         self.instance_index = Some(instance_index);
     }
 
+    /// Returns the canonical options of a function which needs `required`,
+    /// using the functions named by its `options` over the defaults.
+    fn canonical_options(
+        &self,
+        required: RequiredOptions,
+        encoding: StringEncoding,
+        options: Option<&FunctionOptions>,
+    ) -> Result<impl ExactSizeIterator<Item = CanonicalOption>> {
+        let option_func = |name: &Option<String>| {
+            name.as_ref()
+                .map(|name| self.option_func_indexes[name.as_str()])
+        };
+        let (encoding, realloc_index, post_return_index) = match options {
+            Some(options) => (
+                options.encoding.unwrap_or(encoding),
+                option_func(&options.realloc).or(self.realloc_index),
+                option_func(&options.post_return),
+            ),
+            None => (encoding, self.realloc_index, None),
+        };
+        required.into_iter(
+            encoding,
+            self.memory_index,
+            realloc_index,
+            post_return_index,
+        )
+    }
+
     fn instantiate<'a, A>(
         &mut self,
         instances: &mut InstanceSection,
@@ -1479,6 +1574,9 @@ struct IndirectLowering<'a> {
     name: &'a str,
     sig: WasmSignature,
     options: RequiredOptions,
+    /// The encoding of the function's strings, if it overrides the
+    /// encoder's.
+    encoding: Option<StringEncoding>,
     export_name: String,
 }
 
@@ -1538,6 +1636,7 @@ impl<'a> ImportEncoder<'a> {
                                 name: &f.name,
                                 sig,
                                 options,
+                                encoding: interface.string_encoding_of(f),
                                 export_name: element_index.to_string(),
                             });
                         }
@@ -1618,18 +1717,47 @@ impl Indexes {
     }
 }
 
+fn sorted_by_name(interfaces: &[Interface]) -> Vec<&Interface> {
+    let mut sorted = interfaces.iter().collect::<Vec<_>>();
+    sorted.sort_by(|a, b| a.name.cmp(&b.name));
+    sorted
+}
+
 /// An encoder of components based on `wai` interface definitions.
-#[derive(Default)]
+///
+/// Encoding is deterministic: the same module and interfaces always encode to
+/// the same bytes, whatever order the imported and exported interfaces are
+/// given in, so hashes of components can be used as cache keys or attested.
+#[derive(Default, Clone)]
 pub struct ComponentEncoder<'a> {
     module: &'a [u8],
     encoding: StringEncoding,
     interface: Option<&'a Interface>,
     imports: &'a [Interface],
     exports: &'a [Interface],
+    import_options: BTreeMap<(String, String), FunctionOptions>,
+    export_options: BTreeMap<String, FunctionOptions>,
     validate: bool,
     types_only: bool,
 }
 
+/// Canonical options of a single imported or exported function, which
+/// override the ones the encoder gives every function.
+///
+/// Functions are named by their export from the core module.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FunctionOptions {
+    /// The encoding of the function's strings, which takes precedence over
+    /// its `@string-encoding` annotation.
+    pub encoding: Option<StringEncoding>,
+    /// The function to allocate memory with instead of
+    /// `canonical_abi_realloc`.
+    pub realloc: Option<String>,
+    /// The function to call after an exported function returns, to free
+    /// what it returned. Imported functions can't have one.
+    pub post_return: Option<String>,
+}
+
 impl<'a> ComponentEncoder<'a> {
     /// Set the core module to encode as a component.
     pub fn module(mut self, module: &'a [u8]) -> Self {
@@ -1637,12 +1765,29 @@ impl<'a> ComponentEncoder<'a> {
         self
     }
 
-    /// Set the string encoding expected by the core module.
+    /// Set the string encoding expected by the core module for functions
+    /// whose interface doesn't give one with `@string-encoding`.
     pub fn encoding(mut self, encoding: StringEncoding) -> Self {
         self.encoding = encoding;
         self
     }
 
+    /// Set the canonical options of the function `func` of the imported
+    /// interface `interface`.
+    pub fn import_options(mut self, interface: &str, func: &str, options: FunctionOptions) -> Self {
+        self.import_options
+            .insert((interface.to_string(), func.to_string()), options);
+        self
+    }
+
+    /// Set the canonical options of the function the core module exports as
+    /// `export`, which is the name of the function for the default interface
+    /// and `<interface>#<func>` for other exported interfaces.
+    pub fn export_options(mut self, export: &str, options: FunctionOptions) -> Self {
+        self.export_options.insert(export.to_string(), options);
+        self
+    }
+
     /// Sets whether or not the encoder will validate its output.
     pub fn validate(mut self, validate: bool) -> Self {
         self.validate = validate;
@@ -1662,6 +1807,12 @@ impl<'a> ComponentEncoder<'a> {
     }
 
     /// Set the interfaces the component exports.
+    ///
+    /// Each interface is exported as an instance named after it. To export
+    /// the same interface several times, such as for a module implementing
+    /// one API for two consumers, pass copies of it with distinct names, in
+    /// which case the module exports each function once per name as
+    /// `<name>#<func>`.
     pub fn exports(mut self, exports: &'a [Interface]) -> Self {
         self.exports = exports;
         self
@@ -1669,26 +1820,177 @@ impl<'a> ComponentEncoder<'a> {
 
     /// Encode the component and return the bytes.
     pub fn encode(&self) -> Result<Vec<u8>> {
-        let (required_imports, has_memory, has_realloc) = if !self.module.is_empty() {
-            validate_module(self.module, &self.interface, self.imports, self.exports)?
+        let mut bytes = Vec::new();
+        self.write_component(&mut bytes)?;
+
+        if self.validate {
+            let mut validator = Validator::new_with_features(WasmFeatures {
+                component_model: true,
+                ..Default::default()
+            });
+
+            validator
+                .validate_all(&bytes)
+                .context("failed to validate component output")?;
+        }
+
+        Ok(bytes)
+    }
+
+    /// Encode the component, writing its sections to `out` as they're
+    /// encoded.
+    ///
+    /// Unlike [`ComponentEncoder::encode`], the core module is written
+    /// straight from the slice it was given in rather than copied into the
+    /// component, so encoding a large module doesn't need memory for another
+    /// copy of it. Validating the output requires all of it in memory, so
+    /// this fails if validation is enabled.
+    pub fn encode_to(&self, out: &mut impl Write) -> Result<()> {
+        if self.validate {
+            bail!("a component can only be validated when it's encoded in memory");
+        }
+        self.write_component(out)
+    }
+
+    /// Checks that the options of individual functions are given for
+    /// functions of the component, and returns the signatures the core
+    /// module's functions named by them need.
+    fn option_signatures(&self) -> Result<Vec<(&str, WasmSignature)>> {
+        let mut sigs = Vec::new();
+        let realloc_sig = |name| {
+            let sig = WasmSignature {
+                params: vec![WasmType::I32; 4],
+                results: vec![WasmType::I32],
+                indirect_params: false,
+                retptr: false,
+            };
+            (name, sig)
+        };
+
+        for ((interface, func), options) in &self.import_options {
+            let known = self
+                .imports
+                .iter()
+                .any(|i| i.name == *interface && i.functions.iter().any(|f| f.name == *func));
+            if !known {
+                bail!(
+                    "options given for function `{}` of interface `{}`, which isn't imported",
+                    func,
+                    interface
+                );
+            }
+            if options.post_return.is_some() {
+                bail!(
+                    "imported function `{}` of interface `{}` can't have a post-return option",
+                    func,
+                    interface
+                );
+            }
+            sigs.extend(options.realloc.as_deref().map(realloc_sig));
+        }
+
+        let exports = self
+            .interface
+            .iter()
+            .map(|i| (*i, None))
+            .chain(self.exports.iter().map(|i| (i, Some(i.name.as_str()))));
+        for (export, options) in &self.export_options {
+            let (interface, func) = exports
+                .clone()
+                .flat_map(|(i, name)| i.functions.iter().map(move |f| (i, name, f)))
+                .find(|(_, name, f)| expected_export_name(*name, &f.name) == export.as_str())
+                .map(|(i, _, f)| (i, f))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "options given for `{}`, which isn't an exported function",
+                        export
+                    )
+                })?;
+            sigs.extend(options.realloc.as_deref().map(realloc_sig));
+            if let Some(post_return) = &options.post_return {
+                let results = interface
+                    .wasm_signature(AbiVariant::GuestExport, func)
+                    .results;
+                let sig = WasmSignature {
+                    params: results,
+                    results: Vec::new(),
+                    indirect_params: false,
+                    retptr: false,
+                };
+                sigs.push((post_return.as_str(), sig));
+            }
+        }
+
+        Ok(sigs)
+    }
+
+    /// Returns the names of the functions the options of individual
+    /// functions refer to, in order and without duplicates.
+    fn option_funcs(&self) -> Vec<&str> {
+        let mut funcs = self
+            .import_options
+            .values()
+            .chain(self.export_options.values())
+            .flat_map(|o| o.realloc.iter().chain(&o.post_return))
+            .map(|name| name.as_str())
+            .collect::<Vec<_>>();
+        funcs.sort_unstable();
+        funcs.dedup();
+        funcs
+    }
+
+    fn write_component(&self, out: &mut impl Write) -> Result<()> {
+        for (i, export) in self.exports.iter().enumerate() {
+            if self.exports[..i].iter().any(|e| e.name == export.name) {
+                bail!("duplicate export `{}`", export.name);
+            }
+        }
+
+        let option_sigs = self.option_signatures()?;
+        let info = if !self.module.is_empty() {
+            validate_module(
+                self.module,
+                &self.interface,
+                self.imports,
+                self.exports,
+                &option_sigs,
+            )?
         } else {
-            (Default::default(), false, false)
+            ModuleInfo::default()
         };
 
+        // Interfaces are encoded in the order of their names rather than the
+        // order they were given in, which may be that of a directory listing.
+        let import_interfaces = sorted_by_name(self.imports);
+        let export_interfaces = sorted_by_name(self.exports);
         let exports = self
             .interface
             .iter()
             .copied()
             .map(|i| (i, true))
-            .chain(self.exports.iter().map(|i| (i, false)));
+            .chain(export_interfaces.iter().map(|i| (*i, false)));
 
         let mut state = EncodingState::default();
         let mut types = TypeEncoder::default();
         let mut imports = ImportEncoder::default();
-        types.encode_instance_imports(self.imports, &required_imports, &mut imports)?;
+        types.encode_instance_imports(&import_interfaces, &info.required_imports, &mut imports)?;
         types.encode_func_types(exports.clone(), false)?;
+        let initialize_type = info
+            .has_initialize
+            .then(|| types.encode_initialize_func_type());
         types.finish(&mut state.component);
 
+        // Imports which need canonical options are only lowered into the core
+        // instance after it's been instantiated, so its `start` function
+        // can't call them.
+        if info.has_start && imports.indirect_count > 0 {
+            bail!(
+                "module has a start function, which can't call imports that use memory; \
+                 export it as `{}` to run it when the component is instantiated",
+                INITIALIZE_EXPORT
+            );
+        }
+
         if self.types_only {
             if !self.module.is_empty() {
                 bail!("a module cannot be specified for a types-only encoding");
@@ -1699,25 +2001,27 @@ impl<'a> ComponentEncoder<'a> {
             }
 
             state.encode_imports(&imports);
-            state.encode_core_module(self.module);
-            state.encode_core_instantiation(self.encoding, &imports, has_memory, has_realloc)?;
-            state.encode_exports(self.encoding, exports, &types.func_type_map)?;
-        }
 
-        let bytes = state.component.finish();
+            // Flush the sections before the core module, which is written
+            // directly to avoid copying it.
+            out.write_all(&mem::take(&mut state.component).finish())?;
+            write_section(out, ComponentSectionId::CoreModule.into(), self.module)?;
+            state.module_index = Some(state.indexes.alloc_core_module());
 
-        if self.validate {
-            let mut validator = Validator::new_with_features(WasmFeatures {
-                component_model: true,
-                ..Default::default()
-            });
+            state.encode_core_instantiation(self, &imports, info.has_memory, info.has_realloc)?;
+            state.encode_exports(self, exports, &types.func_type_map)?;
+            if let Some(type_index) = initialize_type {
+                state.encode_initialize(type_index);
+            }
 
-            validator
-                .validate_all(&bytes)
-                .context("failed to validate component output")?;
+            // The header was written along with the first sections.
+            let header = Component::default().finish().len();
+            out.write_all(&state.component.finish()[header..])?;
+            return Ok(());
         }
 
-        Ok(bytes)
+        out.write_all(&state.component.finish())?;
+        Ok(())
     }
 }
 
@@ -0,0 +1,359 @@
+use crate::decode_interface_component;
+use anyhow::{bail, Context, Result};
+use indexmap::IndexMap;
+use wai_parser::{
+    abi::{AbiVariant, WasmType},
+    validate_id, Docs, Function, FunctionKind, Interface, Tuple, Type, TypeDef, TypeDefKind,
+};
+use wasmparser::{
+    Encoding, ExternalKind, FuncType, Parser, Payload, TypeRef, ValType, ValidPayload, Validator,
+};
+
+/// The name of the custom sections which embed interfaces in a core module.
+///
+/// A section named `wai-interface` holds the default interface of the module,
+/// and one named `wai-interface:<name>` the exported interface `<name>`, each
+/// encoded as an interface-only component like `wit2wasm` produces.
+pub const INTERFACE_SECTION: &str = "wai-interface";
+
+/// An interface reconstructed from a core module.
+#[derive(Default)]
+pub struct ExtractedInterface {
+    /// The reconstructed interface.
+    pub interface: Interface,
+    /// Whether the interface was embedded in the module, in which case it's
+    /// exact, rather than guessed from the module's functions.
+    pub embedded: bool,
+    /// Why parts of the interface may not be what the module was built from,
+    /// such as functions whose signatures were guessed.
+    pub warnings: Vec<String>,
+}
+
+/// The interfaces reconstructed from a core module by
+/// [`extract_module_interfaces`].
+#[derive(Default)]
+pub struct ModuleInterfaces {
+    /// The interface of the functions the module exports under their own
+    /// names.
+    pub default: ExtractedInterface,
+    /// The interfaces of the functions the module exports as `<name>#<func>`.
+    pub exports: Vec<ExtractedInterface>,
+    /// The interfaces of the functions the module imports, one per module
+    /// they're imported from.
+    pub imports: Vec<ExtractedInterface>,
+}
+
+/// Reconstructs the interfaces of a plain core module on a best-effort
+/// basis, to bootstrap interface files for existing binaries.
+///
+/// Interfaces embedded in [`INTERFACE_SECTION`] custom sections are used
+/// as-is. Otherwise interfaces are guessed from the names and core signatures
+/// of the module's imports and exports: each `i32`, `i64`, `f32` and `f64`
+/// becomes an `s32`, `s64`, `float32` and `float64` parameter or result.
+/// Functions whose signatures can't be written that way, or which may
+/// exchange strings or lists through memory, are reported in the warnings of
+/// their interface.
+pub fn extract_module_interfaces(bytes: &[u8]) -> Result<ModuleInterfaces> {
+    let mut validator = Validator::new();
+    let mut types = None;
+    let mut imports = Vec::new();
+    let mut exports = Vec::new();
+    let mut sections = Vec::new();
+    let mut has_realloc = false;
+
+    for payload in Parser::new(0).parse_all(bytes) {
+        let payload = payload?;
+        if let ValidPayload::End(tys) = validator.payload(&payload)? {
+            types = Some(tys);
+            break;
+        }
+
+        match payload {
+            Payload::Version { encoding, .. } if encoding != Encoding::Module => {
+                bail!("data is not a WebAssembly module");
+            }
+            Payload::ImportSection(s) => {
+                for import in s {
+                    let import = import?;
+                    if let TypeRef::Func(ty) = import.ty {
+                        imports.push((import.module, import.name, ty));
+                    }
+                }
+            }
+            Payload::ExportSection(s) => {
+                for export in s {
+                    let export = export?;
+                    if export.kind == ExternalKind::Func {
+                        has_realloc |= export.name == "canonical_abi_realloc";
+                        exports.push((export.name, export.index));
+                    }
+                }
+            }
+            Payload::CustomSection(s) => {
+                let data = s.data();
+                if let Some(rest) = s.name().strip_prefix(INTERFACE_SECTION) {
+                    let name = match rest.strip_prefix(':') {
+                        Some(name) => name,
+                        None if rest.is_empty() => "",
+                        None => continue,
+                    };
+                    sections.push((name, data));
+                }
+            }
+            _ => continue,
+        }
+    }
+
+    let types = types.unwrap();
+    let mut extractor = Extractor::default();
+
+    for (name, data) in sections {
+        let mut interface = decode_interface_component(data)
+            .with_context(|| format!("failed to decode custom section `{}`", name))?;
+        interface.name = name.to_string();
+        *extractor.export_interface(name) = ExtractedInterface {
+            interface,
+            embedded: true,
+            warnings: Vec::new(),
+        };
+    }
+
+    for (name, index) in exports {
+        if is_runtime_export(name) {
+            continue;
+        }
+        let (interface, func) = match name.split_once('#') {
+            Some((interface, func)) => (interface, func),
+            None => ("", name),
+        };
+        let ty = types.function_at(index).unwrap();
+        let extracted = extractor.export_interface(interface);
+        if !extracted.embedded {
+            Extractor::add_function(extracted, func, ty, AbiVariant::GuestExport, has_realloc);
+        }
+    }
+
+    for (module, name, ty) in imports {
+        if is_runtime_module(module) {
+            if module == "canonical_abi" {
+                let resource = ["resource_drop_", "resource_clone_", "resource_new_"]
+                    .iter()
+                    .find_map(|prefix| name.strip_prefix(prefix));
+                if let Some(resource) = resource {
+                    extractor.resource(resource);
+                }
+            }
+            continue;
+        }
+        let ty = types.func_type_at(ty).unwrap();
+        let extracted = extractor.imports.entry(module).or_insert_with(|| {
+            let mut extracted = ExtractedInterface::default();
+            extracted.interface.name = interface_name(module, &mut extracted.warnings);
+            extracted
+        });
+        Extractor::add_function(extracted, name, ty, AbiVariant::GuestImport, has_realloc);
+    }
+
+    Ok(ModuleInterfaces {
+        default: extractor.default,
+        exports: extractor.exports.into_iter().map(|(_, i)| i).collect(),
+        imports: extractor.imports.into_iter().map(|(_, i)| i).collect(),
+    })
+}
+
+#[derive(Default)]
+struct Extractor<'a> {
+    default: ExtractedInterface,
+    exports: IndexMap<&'a str, ExtractedInterface>,
+    imports: IndexMap<&'a str, ExtractedInterface>,
+    resources: Vec<&'a str>,
+}
+
+impl<'a> Extractor<'a> {
+    fn export_interface(&mut self, name: &'a str) -> &mut ExtractedInterface {
+        if name.is_empty() {
+            return &mut self.default;
+        }
+        self.exports.entry(name).or_insert_with(|| {
+            let mut extracted = ExtractedInterface::default();
+            extracted.interface.name = interface_name(name, &mut extracted.warnings);
+            extracted
+        })
+    }
+
+    fn resource(&mut self, name: &'a str) {
+        if self.resources.contains(&name) {
+            return;
+        }
+        self.resources.push(name);
+        self.default.warnings.push(format!(
+            "the module uses a resource named `{}`, which isn't reconstructed",
+            name
+        ));
+    }
+
+    fn add_function(
+        extracted: &mut ExtractedInterface,
+        name: &str,
+        ty: &FuncType,
+        variant: AbiVariant,
+        has_realloc: bool,
+    ) {
+        let warnings = &mut extracted.warnings;
+        let func_name = match to_id(name) {
+            Some(id) if id == name => id,
+            Some(id) => {
+                warnings.push(format!(
+                    "`{}` was renamed to `{}`, which the module's function must be renamed to as well",
+                    name, id
+                ));
+                id
+            }
+            None => {
+                warnings.push(format!(
+                    "skipped `{}`, which can't be made into a valid name",
+                    name
+                ));
+                return;
+            }
+        };
+
+        let core_params = ty.params.iter().map(wasm_type).collect::<Option<Vec<_>>>();
+        let core_results = ty.returns.iter().map(wasm_type).collect::<Option<Vec<_>>>();
+        let (core_params, core_results) = match (core_params, core_results) {
+            (Some(params), Some(results)) => (params, results),
+            _ => {
+                warnings.push(format!(
+                    "skipped `{}`, which has a signature without an interface equivalent",
+                    func_name
+                ));
+                return;
+            }
+        };
+
+        let interface = &mut extracted.interface;
+        let params = core_params
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| (format!("arg{}", i), interface_type(*ty)))
+            .collect();
+        let mut results = core_results.iter().map(|ty| interface_type(*ty));
+        let result = match core_results.len() {
+            0 => Type::Unit,
+            1 => results.next().unwrap(),
+            _ => Type::Id(interface.types.alloc(TypeDef {
+                docs: Docs::default(),
+                kind: TypeDefKind::Tuple(Tuple {
+                    types: results.collect(),
+                }),
+                name: None,
+                foreign_module: None,
+            })),
+        };
+        let func = Function {
+            is_async: false,
+            docs: Docs::default(),
+            name: func_name,
+            kind: FunctionKind::Freestanding,
+            params,
+            result,
+            requires: Vec::new(),
+            ranges: Vec::new(),
+            string_encoding: None,
+        };
+
+        // Check that the guess lowers to the module's signature, which it
+        // doesn't when the function returns multiple values, say.
+        let sig = interface.wasm_signature(variant, &func);
+        if sig.params != core_params || sig.results != core_results {
+            warnings.push(format!(
+                "the signature of `{}` is unknown, `{:?} -> {:?}` has no interface equivalent",
+                func.name, core_params, core_results
+            ));
+        } else if has_realloc && core_params.contains(&WasmType::I32) {
+            warnings.push(format!(
+                "the signature of `{}` was guessed, its `i32`s may also be strings or lists",
+                func.name
+            ));
+        }
+        interface.functions.push(func);
+    }
+}
+
+/// Returns whether `name` is an export of the runtime of a module rather
+/// than part of an interface.
+fn is_runtime_export(name: &str) -> bool {
+    name.starts_with("canonical_abi_")
+        || name.starts_with("cabi_")
+        || name.starts_with('_')
+        || name.ends_with("wai_abi_version")
+}
+
+/// Returns whether `module` is imported by the runtime of a module rather
+/// than an interface.
+fn is_runtime_module(module: &str) -> bool {
+    module == "canonical_abi" || module == "wasi_unstable" || module == "wasi_snapshot_preview1"
+}
+
+fn interface_name(name: &str, warnings: &mut Vec<String>) -> String {
+    match to_id(name) {
+        Some(id) => id,
+        None => {
+            warnings.push(format!(
+                "`{}` can't be made into a valid interface name",
+                name
+            ));
+            name.to_string()
+        }
+    }
+}
+
+/// Converts a name such as `add_two` or `addTwo` into an identifier such as
+/// `add-two`, if possible.
+fn to_id(name: &str) -> Option<String> {
+    let mut id = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        match c {
+            'A'..='Z' => {
+                if prev_lower {
+                    id.push('-');
+                }
+                id.push(c.to_ascii_lowercase());
+                prev_lower = false;
+            }
+            'a'..='z' | '0'..='9' => {
+                id.push(c);
+                prev_lower = true;
+            }
+            '_' | '-' | '.' => {
+                if !id.is_empty() && !id.ends_with('-') {
+                    id.push('-');
+                }
+                prev_lower = false;
+            }
+            _ => return None,
+        }
+    }
+    let id = id.trim_end_matches('-').to_string();
+    validate_id(&id).ok().map(|()| id)
+}
+
+fn wasm_type(ty: &ValType) -> Option<WasmType> {
+    match ty {
+        ValType::I32 => Some(WasmType::I32),
+        ValType::I64 => Some(WasmType::I64),
+        ValType::F32 => Some(WasmType::F32),
+        ValType::F64 => Some(WasmType::F64),
+        _ => None,
+    }
+}
+
+fn interface_type(ty: WasmType) -> Type {
+    match ty {
+        WasmType::I32 => Type::S32,
+        WasmType::I64 => Type::S64,
+        WasmType::F32 => Type::Float32,
+        WasmType::F64 => Type::Float64,
+    }
+}
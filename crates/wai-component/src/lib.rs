@@ -2,8 +2,7 @@
 
 #![deny(missing_docs)]
 
-use anyhow::{bail, Result};
-use std::str::FromStr;
+use anyhow::Result;
 use wai_parser::Interface;
 use wasm_encoder::CanonicalOption;
 
@@ -11,49 +10,25 @@ use wasm_encoder::CanonicalOption;
 pub mod cli;
 mod decoding;
 mod encoding;
+mod extraction;
+mod linking;
 mod printing;
+mod sections;
 mod validation;
 
 pub use encoding::*;
+pub use extraction::*;
+pub use linking::*;
 pub use printing::*;
+pub use sections::*;
 
-/// Supported string encoding formats.
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-pub enum StringEncoding {
-    /// Strings are encoded with UTF-8.
-    UTF8,
-    /// Strings are encoded with UTF-16.
-    UTF16,
-    /// Strings are encoded with compact UTF-16 (i.e. Latin1+UTF-16).
-    CompactUTF16,
-}
-
-impl Default for StringEncoding {
-    fn default() -> Self {
-        StringEncoding::UTF8
-    }
-}
-
-impl FromStr for StringEncoding {
-    type Err = anyhow::Error;
-
-    fn from_str(s: &str) -> Result<Self> {
-        match s {
-            "utf8" => Ok(StringEncoding::UTF8),
-            "utf16" => Ok(StringEncoding::UTF16),
-            "compact-utf16" => Ok(StringEncoding::CompactUTF16),
-            _ => bail!("unknown string encoding `{}`", s),
-        }
-    }
-}
+pub use wai_parser::StringEncoding;
 
-impl From<StringEncoding> for wasm_encoder::CanonicalOption {
-    fn from(e: StringEncoding) -> wasm_encoder::CanonicalOption {
-        match e {
-            StringEncoding::UTF8 => CanonicalOption::UTF8,
-            StringEncoding::UTF16 => CanonicalOption::UTF16,
-            StringEncoding::CompactUTF16 => CanonicalOption::CompactUTF16,
-        }
+fn canonical_option(encoding: StringEncoding) -> CanonicalOption {
+    match encoding {
+        StringEncoding::UTF8 => CanonicalOption::UTF8,
+        StringEncoding::UTF16 => CanonicalOption::UTF16,
+        StringEncoding::CompactUTF16 => CanonicalOption::CompactUTF16,
     }
 }
 
@@ -0,0 +1,241 @@
+use crate::validation::{
+    expected_export_name, is_canonical_function, is_wasi, wasm_sig_to_func_type, INITIALIZE_EXPORT,
+};
+use anyhow::{bail, Result};
+use indexmap::{IndexMap, IndexSet};
+use std::fmt;
+use wai_parser::{abi::AbiVariant, Interface};
+use wasmparser::{
+    Encoding, ExternalKind, FuncType, Parser, Payload, TypeRef, ValidPayload, Validator,
+};
+
+/// A way in which a module doesn't fit the interfaces of a host, found by
+/// [`check_link`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkProblem {
+    /// The module imports a function the host doesn't provide.
+    MissingImport {
+        /// The module the function is imported from.
+        module: String,
+        /// The name of the function.
+        name: String,
+    },
+    /// The module imports a function with another signature than the host
+    /// provides it with.
+    ImportMismatch {
+        /// The module the function is imported from.
+        module: String,
+        /// The name of the function.
+        name: String,
+        /// The core signature the host provides.
+        expected: String,
+        /// The core signature the module imports.
+        found: String,
+    },
+    /// The module doesn't export a function the host expects.
+    MissingExport {
+        /// The name of the export.
+        name: String,
+    },
+    /// The module exports a function with another signature than the host
+    /// expects.
+    ExportMismatch {
+        /// The name of the export.
+        name: String,
+        /// The core signature the host expects.
+        expected: String,
+        /// The core signature the module exports.
+        found: String,
+    },
+    /// The module exports a function which none of the host's interfaces
+    /// describe, which doesn't stop them linking.
+    ExtraExport {
+        /// The name of the export.
+        name: String,
+    },
+}
+
+impl LinkProblem {
+    /// Returns whether the problem stops the module from being instantiated
+    /// by the host.
+    pub fn is_error(&self) -> bool {
+        !matches!(self, LinkProblem::ExtraExport { .. })
+    }
+
+    /// Returns the kebab-case name of the kind of problem.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            LinkProblem::MissingImport { .. } => "missing-import",
+            LinkProblem::ImportMismatch { .. } => "import-mismatch",
+            LinkProblem::MissingExport { .. } => "missing-export",
+            LinkProblem::ExportMismatch { .. } => "export-mismatch",
+            LinkProblem::ExtraExport { .. } => "extra-export",
+        }
+    }
+}
+
+impl fmt::Display for LinkProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkProblem::MissingImport { module, name } => write!(
+                f,
+                "module imports function `{}` from `{}`, which the host doesn't provide",
+                name, module
+            ),
+            LinkProblem::ImportMismatch {
+                module,
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "module imports function `{}` from `{}` as `{}`, but the host provides `{}`",
+                name, module, found, expected
+            ),
+            LinkProblem::MissingExport { name } => write!(
+                f,
+                "module doesn't export function `{}`, which the host expects",
+                name
+            ),
+            LinkProblem::ExportMismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "module exports function `{}` as `{}`, but the host expects `{}`",
+                name, found, expected
+            ),
+            LinkProblem::ExtraExport { name } => write!(
+                f,
+                "module exports function `{}`, which the host doesn't use",
+                name
+            ),
+        }
+    }
+}
+
+/// Checks whether the core module `bytes` fits a host which provides the
+/// `imports` interfaces and expects the module to export the `exports`
+/// interfaces, along with `interface` as its default interface, returning
+/// every problem found rather than only the first.
+///
+/// WASI imports and the canonical ABI functions the bindings generators
+/// import and export aren't checked.
+pub fn check_link(
+    bytes: &[u8],
+    interface: Option<&Interface>,
+    imports: &[Interface],
+    exports: &[Interface],
+) -> Result<Vec<LinkProblem>> {
+    let mut validator = Validator::new();
+    let mut types = None;
+    let mut import_funcs = Vec::new();
+    let mut export_funcs = IndexMap::new();
+
+    for payload in Parser::new(0).parse_all(bytes) {
+        let payload = payload?;
+        if let ValidPayload::End(tys) = validator.payload(&payload)? {
+            types = Some(tys);
+            break;
+        }
+
+        match payload {
+            Payload::Version { encoding, .. } if encoding != Encoding::Module => {
+                bail!("data is not a WebAssembly module");
+            }
+            Payload::ImportSection(s) => {
+                for import in s {
+                    let import = import?;
+                    if let TypeRef::Func(ty) = import.ty {
+                        import_funcs.push((import.module, import.name, ty));
+                    }
+                }
+            }
+            Payload::ExportSection(s) => {
+                for export in s {
+                    let export = export?;
+                    if export.kind == ExternalKind::Func {
+                        export_funcs.insert(export.name, export.index);
+                    }
+                }
+            }
+            _ => continue,
+        }
+    }
+
+    let types = types.unwrap();
+    let mut problems = Vec::new();
+
+    for (module, name, ty) in import_funcs {
+        if is_wasi(module) || module == "canonical_abi" {
+            continue;
+        }
+        let found = types.func_type_at(ty).unwrap();
+        let func = imports
+            .iter()
+            .filter(|i| i.name == module)
+            .find_map(|i| i.functions.iter().find(|f| f.name == name).map(|f| (i, f)));
+        match func {
+            Some((i, f)) => {
+                let expected = wasm_sig_to_func_type(i.wasm_signature(AbiVariant::GuestImport, f));
+                if found != &expected {
+                    problems.push(LinkProblem::ImportMismatch {
+                        module: module.to_string(),
+                        name: name.to_string(),
+                        expected: signature(&expected),
+                        found: signature(found),
+                    });
+                }
+            }
+            None => problems.push(LinkProblem::MissingImport {
+                module: module.to_string(),
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    let mut described = IndexSet::new();
+    let interfaces = interface
+        .map(|i| (None, i))
+        .into_iter()
+        .chain(exports.iter().map(|i| (Some(i.name.as_str()), i)));
+    for (name, interface) in interfaces {
+        for f in interface.functions.iter() {
+            let export = expected_export_name(name, &f.name).into_owned();
+            match export_funcs.get(export.as_str()) {
+                Some(func_index) => {
+                    let expected =
+                        wasm_sig_to_func_type(interface.wasm_signature(AbiVariant::GuestExport, f));
+                    let found = types.function_at(*func_index).unwrap();
+                    if found != &expected {
+                        problems.push(LinkProblem::ExportMismatch {
+                            name: export.clone(),
+                            expected: signature(&expected),
+                            found: signature(found),
+                        });
+                    }
+                }
+                None => problems.push(LinkProblem::MissingExport {
+                    name: export.clone(),
+                }),
+            }
+            described.insert(export);
+        }
+    }
+
+    for name in export_funcs.keys() {
+        if !described.contains(*name) && !is_canonical_function(name) && *name != INITIALIZE_EXPORT
+        {
+            problems.push(LinkProblem::ExtraExport {
+                name: name.to_string(),
+            });
+        }
+    }
+
+    Ok(problems)
+}
+
+fn signature(ty: &FuncType) -> String {
+    format!("{:?} -> {:?}", ty.params, ty.returns)
+}
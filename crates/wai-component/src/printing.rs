@@ -1,8 +1,11 @@
 use anyhow::{bail, Result};
 use indexmap::IndexSet;
+use std::collections::HashMap;
 use std::fmt::Write;
+use wai_parser::syntax::{ItemKind, SyntaxTree, TokenKind};
 use wai_parser::{
-    Enum, Expected, Flags, Interface, Record, Tuple, Type, TypeDefKind, TypeId, Union, Variant,
+    Docs, Enum, Expected, Flags, Interface, Record, Tuple, Type, TypeDefKind, TypeId, Union,
+    Variant,
 };
 
 /// A utility for printing WebAssembly interface definitions to a string.
@@ -10,9 +13,45 @@ use wai_parser::{
 pub struct InterfacePrinter {
     output: String,
     declared: IndexSet<TypeId>,
+    comments: Option<Comments>,
+}
+
+/// The comments of a `*.wai` file, by what they were written next to.
+#[derive(Default)]
+struct Comments {
+    /// Comments on the lines before something.
+    leading: HashMap<Anchor, Vec<String>>,
+    /// Comments after something on the same line.
+    trailing: HashMap<Anchor, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Anchor {
+    /// A type or function, by name.
+    Item(String),
+    /// A field, flag or case of a type.
+    Member(String, String),
+    /// The closing brace of a type.
+    Close(String),
+    /// The end of the file.
+    End,
 }
 
 impl InterfacePrinter {
+    /// Prints the comments of `source`, the `*.wai` file the interface was
+    /// parsed from or encoded from, instead of the doc comments of the
+    /// interface, so that re-printing the file keeps its comments.
+    ///
+    /// Comments are matched by name to the type, function, field or case
+    /// they were on the lines before or at the end of the line of, and are
+    /// printed in the same place. Comments before the closing brace of a
+    /// type or at the end of the file stay there, and comments next to
+    /// anything else are dropped.
+    pub fn keep_comments(&mut self, source: &str) -> Result<&mut Self> {
+        self.comments = Some(Comments::parse(source)?);
+        Ok(self)
+    }
+
     /// Print the given WebAssembly interface to a string.
     pub fn print(&mut self, interface: &Interface) -> Result<String> {
         for func in &interface.functions {
@@ -22,6 +61,8 @@ impl InterfacePrinter {
         }
 
         for func in &interface.functions {
+            let anchor = Anchor::Item(func.name.clone());
+            self.leading(&anchor, "", &func.docs);
             write!(&mut self.output, "{}: func(", func.name)?;
             for (i, (name, ty)) in func.params.iter().enumerate() {
                 if i > 0 {
@@ -39,11 +80,81 @@ impl InterfacePrinter {
                     self.print_type_name(interface, other)?;
                 }
             }
+            self.trailing(&anchor);
             self.output.push_str("\n\n");
         }
 
+        self.leading(&Anchor::End, "", &Docs::default());
         self.declared.clear();
-        Ok(std::mem::take(&mut self.output))
+        let mut output = std::mem::take(&mut self.output);
+        output.truncate(output.trim_end().len());
+        if !output.is_empty() {
+            output.push_str("\n\n");
+        }
+        Ok(output)
+    }
+
+    /// Prints the comments before `anchor`, or otherwise `docs`, each line
+    /// indented by `indent`.
+    fn leading(&mut self, anchor: &Anchor, indent: &str, docs: &Docs) {
+        if let Some(comments) = &self.comments {
+            for comment in comments.leading.get(anchor).into_iter().flatten() {
+                self.output.push_str(indent);
+                self.output.push_str(comment);
+                self.output.push('\n');
+            }
+            return;
+        }
+        let mut lines = Vec::new();
+        if let Some(contents) = &docs.contents {
+            lines.extend(contents.trim_end().lines().map(str::to_string));
+        }
+        for example in docs.examples.iter() {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.push(format!("```{}", example.lang.as_deref().unwrap_or("")));
+            lines.extend(example.code.lines().map(str::to_string));
+            lines.push("```".to_string());
+        }
+        for line in lines {
+            self.output.push_str(indent);
+            self.output.push_str("///");
+            if !line.is_empty() {
+                self.output.push(' ');
+                self.output.push_str(&line);
+            }
+            self.output.push('\n');
+        }
+    }
+
+    /// Prints the comment after `anchor` on the same line, if any.
+    fn trailing(&mut self, anchor: &Anchor) {
+        if let Some(comment) = self.comments.as_ref().and_then(|c| c.trailing.get(anchor)) {
+            self.output.push(' ');
+            self.output.push_str(comment);
+        }
+    }
+
+    /// Prints the opening line of the type `name`, which is a `keyword`.
+    fn open(&mut self, keyword: &str, name: &str, docs: &Docs) {
+        let anchor = Anchor::Item(name.to_string());
+        self.leading(&anchor, "", docs);
+        self.output.push_str(keyword);
+        self.output.push(' ');
+        self.output.push_str(name);
+        self.output.push_str(" {");
+        self.trailing(&anchor);
+        self.output.push('\n');
+    }
+
+    /// Prints the closing brace of the type `name`.
+    fn close(&mut self, name: &str) {
+        let anchor = Anchor::Close(name.to_string());
+        self.leading(&anchor, "  ", &Docs::default());
+        self.output.push('}');
+        self.trailing(&anchor);
+        self.output.push_str("\n\n");
     }
 
     fn print_type_name(&mut self, interface: &Interface, ty: &Type) -> Result<()> {
@@ -170,32 +281,35 @@ impl InterfacePrinter {
                 let ty = &interface.types[*id];
                 match &ty.kind {
                     TypeDefKind::Record(r) => {
-                        self.declare_record(interface, ty.name.as_deref(), r)?
+                        self.declare_record(interface, ty.name.as_deref(), &ty.docs, r)?
                     }
                     TypeDefKind::Tuple(t) => {
-                        self.declare_tuple(interface, ty.name.as_deref(), t)?
+                        self.declare_tuple(interface, ty.name.as_deref(), &ty.docs, t)?
                     }
-                    TypeDefKind::Flags(f) => self.declare_flags(ty.name.as_deref(), f)?,
+                    TypeDefKind::Flags(f) => self.declare_flags(ty.name.as_deref(), &ty.docs, f)?,
                     TypeDefKind::Variant(v) => {
-                        self.declare_variant(interface, ty.name.as_deref(), v)?
+                        self.declare_variant(interface, ty.name.as_deref(), &ty.docs, v)?
                     }
                     TypeDefKind::Union(u) => {
-                        self.declare_union(interface, ty.name.as_deref(), u)?
+                        self.declare_union(interface, ty.name.as_deref(), &ty.docs, u)?
                     }
                     TypeDefKind::Option(t) => {
-                        self.declare_option(interface, ty.name.as_deref(), t)?
+                        self.declare_option(interface, ty.name.as_deref(), &ty.docs, t)?
                     }
                     TypeDefKind::Expected(e) => {
-                        self.declare_expected(interface, ty.name.as_deref(), e)?
+                        self.declare_expected(interface, ty.name.as_deref(), &ty.docs, e)?
                     }
-                    TypeDefKind::Enum(e) => self.declare_enum(ty.name.as_deref(), e)?,
+                    TypeDefKind::Enum(e) => self.declare_enum(ty.name.as_deref(), &ty.docs, e)?,
                     TypeDefKind::List(inner) => {
-                        self.declare_list(interface, ty.name.as_deref(), inner)?
+                        self.declare_list(interface, ty.name.as_deref(), &ty.docs, inner)?
                     }
                     TypeDefKind::Type(inner) => match ty.name.as_deref() {
                         Some(name) => {
+                            let anchor = Anchor::Item(name.to_string());
+                            self.leading(&anchor, "", &ty.docs);
                             write!(&mut self.output, "type {} = ", name)?;
                             self.print_type_name(interface, inner)?;
+                            self.trailing(&anchor);
                             self.output.push_str("\n\n");
                         }
                         None => bail!("unnamed type in interface"),
@@ -214,6 +328,7 @@ impl InterfacePrinter {
         &mut self,
         interface: &Interface,
         name: Option<&str>,
+        docs: &Docs,
         record: &Record,
     ) -> Result<()> {
         for field in record.fields.iter() {
@@ -222,14 +337,18 @@ impl InterfacePrinter {
 
         match name {
             Some(name) => {
-                writeln!(&mut self.output, "record {} {{", name)?;
+                self.open("record", name, docs);
                 for field in &record.fields {
+                    let anchor = Anchor::Member(name.to_string(), field.name.clone());
+                    self.leading(&anchor, "  ", &field.docs);
                     write!(&mut self.output, "  {}: ", field.name)?;
                     self.declare_type(interface, &field.ty)?;
                     self.print_type_name(interface, &field.ty)?;
-                    self.output.push_str(",\n");
+                    self.output.push(',');
+                    self.trailing(&anchor);
+                    self.output.push('\n');
                 }
-                self.output.push_str("}\n\n");
+                self.close(name);
                 Ok(())
             }
             None => bail!("interface has unnamed record type"),
@@ -240,6 +359,7 @@ impl InterfacePrinter {
         &mut self,
         interface: &Interface,
         name: Option<&str>,
+        docs: &Docs,
         tuple: &Tuple,
     ) -> Result<()> {
         for ty in tuple.types.iter() {
@@ -247,21 +367,28 @@ impl InterfacePrinter {
         }
 
         if let Some(name) = name {
+            let anchor = Anchor::Item(name.to_string());
+            self.leading(&anchor, "", docs);
             write!(&mut self.output, "type {} = ", name)?;
             self.print_tuple_type(interface, tuple)?;
+            self.trailing(&anchor);
             self.output.push_str("\n\n");
         }
         Ok(())
     }
 
-    fn declare_flags(&mut self, name: Option<&str>, flags: &Flags) -> Result<()> {
+    fn declare_flags(&mut self, name: Option<&str>, docs: &Docs, flags: &Flags) -> Result<()> {
         match name {
             Some(name) => {
-                writeln!(&mut self.output, "flags {} {{", name)?;
+                self.open("flags", name, docs);
                 for flag in &flags.flags {
-                    writeln!(&mut self.output, "  {},", flag.name)?;
+                    let anchor = Anchor::Member(name.to_string(), flag.name.clone());
+                    self.leading(&anchor, "  ", &flag.docs);
+                    write!(&mut self.output, "  {},", flag.name)?;
+                    self.trailing(&anchor);
+                    self.output.push('\n');
                 }
-                self.output.push_str("}\n\n");
+                self.close(name);
             }
             None => bail!("interface has unnamed flags type"),
         }
@@ -272,6 +399,7 @@ impl InterfacePrinter {
         &mut self,
         interface: &Interface,
         name: Option<&str>,
+        docs: &Docs,
         variant: &Variant,
     ) -> Result<()> {
         for case in variant.cases.iter() {
@@ -282,17 +410,26 @@ impl InterfacePrinter {
             Some(name) => name,
             None => bail!("interface has unnamed union type"),
         };
-        writeln!(&mut self.output, "variant {} {{", name)?;
+        let keyword = if variant.non_exhaustive {
+            "@non-exhaustive variant"
+        } else {
+            "variant"
+        };
+        self.open(keyword, name, docs);
         for case in &variant.cases {
+            let anchor = Anchor::Member(name.to_string(), case.name.clone());
+            self.leading(&anchor, "  ", &case.docs);
             write!(&mut self.output, "  {}", case.name)?;
             if case.ty != Type::Unit {
                 self.output.push('(');
                 self.print_type_name(interface, &case.ty)?;
                 self.output.push(')');
             }
-            self.output.push_str(",\n");
+            self.output.push(',');
+            self.trailing(&anchor);
+            self.output.push('\n');
         }
-        self.output.push_str("}\n\n");
+        self.close(name);
         Ok(())
     }
 
@@ -300,6 +437,7 @@ impl InterfacePrinter {
         &mut self,
         interface: &Interface,
         name: Option<&str>,
+        docs: &Docs,
         union: &Union,
     ) -> Result<()> {
         for case in union.cases.iter() {
@@ -310,13 +448,17 @@ impl InterfacePrinter {
             Some(name) => name,
             None => bail!("interface has unnamed union type"),
         };
-        writeln!(&mut self.output, "union {} {{", name)?;
+        self.open("union", name, docs);
         for case in &union.cases {
+            // Union cases have no names for comments to be matched by.
+            if self.comments.is_none() {
+                self.leading(&Anchor::End, "  ", &case.docs);
+            }
             self.output.push_str("  ");
             self.print_type_name(interface, &case.ty)?;
             self.output.push_str(",\n");
         }
-        self.output.push_str("}\n\n");
+        self.close(name);
         Ok(())
     }
 
@@ -324,13 +466,17 @@ impl InterfacePrinter {
         &mut self,
         interface: &Interface,
         name: Option<&str>,
+        docs: &Docs,
         payload: &Type,
     ) -> Result<()> {
         self.declare_type(interface, payload)?;
 
         if let Some(name) = name {
+            let anchor = Anchor::Item(name.to_string());
+            self.leading(&anchor, "", docs);
             write!(&mut self.output, "type {} = ", name)?;
             self.print_option_type(interface, payload)?;
+            self.trailing(&anchor);
             self.output.push_str("\n\n");
         }
         Ok(())
@@ -340,42 +486,153 @@ impl InterfacePrinter {
         &mut self,
         interface: &Interface,
         name: Option<&str>,
+        docs: &Docs,
         expected: &Expected,
     ) -> Result<()> {
         self.declare_type(interface, &expected.ok)?;
         self.declare_type(interface, &expected.err)?;
 
         if let Some(name) = name {
+            let anchor = Anchor::Item(name.to_string());
+            self.leading(&anchor, "", docs);
             write!(&mut self.output, "type {} = ", name)?;
             self.print_expected_type(interface, expected)?;
+            self.trailing(&anchor);
             self.output.push_str("\n\n");
         }
         Ok(())
     }
 
-    fn declare_enum(&mut self, name: Option<&str>, enum_: &Enum) -> Result<()> {
+    fn declare_enum(&mut self, name: Option<&str>, docs: &Docs, enum_: &Enum) -> Result<()> {
         let name = match name {
             Some(name) => name,
             None => bail!("interface has unnamed enum type"),
         };
-        writeln!(&mut self.output, "enum {} {{", name)?;
+        let keyword = if enum_.non_exhaustive {
+            "@non-exhaustive enum"
+        } else {
+            "enum"
+        };
+        self.open(keyword, name, docs);
         for case in &enum_.cases {
-            writeln!(&mut self.output, "  {},", case.name)?;
+            let anchor = Anchor::Member(name.to_string(), case.name.clone());
+            self.leading(&anchor, "  ", &case.docs);
+            write!(&mut self.output, "  {},", case.name)?;
+            self.trailing(&anchor);
+            self.output.push('\n');
         }
-        self.output.push_str("}\n\n");
+        self.close(name);
         Ok(())
     }
 
-    fn declare_list(&mut self, interface: &Interface, name: Option<&str>, ty: &Type) -> Result<()> {
+    fn declare_list(
+        &mut self,
+        interface: &Interface,
+        name: Option<&str>,
+        docs: &Docs,
+        ty: &Type,
+    ) -> Result<()> {
         self.declare_type(interface, ty)?;
 
         if let Some(name) = name {
+            let anchor = Anchor::Item(name.to_string());
+            self.leading(&anchor, "", docs);
             write!(&mut self.output, "type {} = list<", name)?;
             self.print_type_name(interface, ty)?;
-            self.output.push_str(">\n\n");
+            self.output.push('>');
+            self.trailing(&anchor);
+            self.output.push_str("\n\n");
             return Ok(());
         }
 
         Ok(())
     }
 }
+
+impl Comments {
+    fn parse(source: &str) -> Result<Comments> {
+        let tree = SyntaxTree::parse("<comments>", source)?;
+        let tokens = &tree.tokens;
+        let text = |i: usize| &source[tokens[i].span.start as usize..tokens[i].span.end as usize];
+
+        // What each token belongs to, if it's part of something comments
+        // can be printed next to.
+        let mut anchors = vec![None; tokens.len()];
+        for item in tree.items.iter() {
+            let name = match &item.name {
+                Some(name) => &name.name,
+                None => continue,
+            };
+            let braced = match item.kind {
+                ItemKind::Record
+                | ItemKind::Flags
+                | ItemKind::Variant
+                | ItemKind::Enum
+                | ItemKind::Union => true,
+                ItemKind::Type | ItemKind::Function | ItemKind::Global => false,
+                ItemKind::Use | ItemKind::Include | ItemKind::Resource | ItemKind::Interface => {
+                    continue
+                }
+            };
+            for (i, token) in tokens.iter().enumerate() {
+                if token.is_trivia()
+                    || token.span.start < item.span.start
+                    || token.span.end > item.span.end
+                {
+                    continue;
+                }
+                let member = match braced {
+                    true => item
+                        .members
+                        .iter()
+                        .rev()
+                        .find(|m| m.span.start <= token.span.start),
+                    false => None,
+                };
+                anchors[i] = Some(match member {
+                    _ if braced && token.span.end == item.span.end => Anchor::Close(name.clone()),
+                    Some(member) => Anchor::Member(name.clone(), member.name.clone()),
+                    None => Anchor::Item(name.clone()),
+                });
+            }
+        }
+
+        let mut comments = Comments::default();
+        for (i, token) in tokens.iter().enumerate() {
+            if token.kind != TokenKind::Comment {
+                continue;
+            }
+            let comment = text(i).trim_end().to_string();
+
+            // A comment after a token on the same line trails what the
+            // token belongs to, and other comments lead what the next token
+            // belongs to.
+            let prev = tokens[..i].iter().rposition(|t| !t.is_trivia());
+            if let Some(prev) = prev {
+                let between = &source[tokens[prev].span.end as usize..token.span.start as usize];
+                if !between.contains('\n') {
+                    if let Some(anchor) = anchors[prev].clone() {
+                        let trailing = comments.trailing.entry(anchor).or_default();
+                        if !trailing.is_empty() {
+                            trailing.push(' ');
+                        }
+                        trailing.push_str(&comment);
+                    }
+                    continue;
+                }
+            }
+            let next = tokens[i + 1..]
+                .iter()
+                .position(|t| !t.is_trivia())
+                .map(|n| i + 1 + n);
+            let anchor = match next {
+                Some(next) => anchors[next].clone(),
+                None => Some(Anchor::End),
+            };
+            if let Some(anchor) = anchor {
+                comments.leading.entry(anchor).or_default().push(comment);
+            }
+        }
+        Ok(comments)
+    }
+}
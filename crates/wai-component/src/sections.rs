@@ -0,0 +1,99 @@
+use crate::INTERFACE_SECTION;
+use anyhow::{bail, Result};
+use wasm_encoder::{CustomSection, Encode};
+use wasmparser::BinaryReader;
+
+/// The ids of the sections of a component which hold a nested core module or
+/// component.
+const NESTED_SECTIONS: [u8; 2] = [1, 5];
+
+/// Returns whether the custom section `name` describes a module or component
+/// rather than being used by it, which [`strip_custom_sections`] keeps when
+/// asked to keep metadata.
+///
+/// These are the `name` and `producers` sections and the
+/// [`INTERFACE_SECTION`]s.
+pub fn is_metadata_section(name: &str) -> bool {
+    name == "name"
+        || name == "producers"
+        || name == INTERFACE_SECTION
+        || name
+            .strip_prefix(INTERFACE_SECTION)
+            .map_or(false, |rest| rest.starts_with(':'))
+}
+
+/// Removes the custom sections of a module or component whose names `keep`
+/// returns false for, including those of the modules and components nested
+/// in a component.
+pub fn strip_custom_sections(bytes: &[u8], keep: impl Fn(&str) -> bool) -> Result<Vec<u8>> {
+    rewrite(bytes, &keep, true)
+}
+
+/// Adds a custom section named `name` holding `data` to the end of a module
+/// or component, replacing any sections it already has with that name.
+pub fn add_custom_section(bytes: &[u8], name: &str, data: &[u8]) -> Result<Vec<u8>> {
+    let mut ret = rewrite(bytes, &|n| n != name, false)?;
+    ret.push(0);
+    CustomSection { name, data }.encode(&mut ret);
+    Ok(ret)
+}
+
+/// Returns the contents of the first custom section named `name` of a module
+/// or component, not counting those of nested modules and components.
+pub fn extract_custom_section<'a>(bytes: &'a [u8], name: &str) -> Result<Option<&'a [u8]>> {
+    let mut ret = None;
+    for_each_section(bytes, |id, body| {
+        if ret.is_none() && id == 0 {
+            let mut reader = BinaryReader::new(body);
+            if reader.read_string()? == name {
+                ret = Some(&body[reader.current_position()..]);
+            }
+        }
+        Ok(())
+    })?;
+    Ok(ret)
+}
+
+/// Copies `bytes` without the custom sections `keep` returns false for,
+/// recursing into nested modules and components if `recurse` is set.
+fn rewrite(bytes: &[u8], keep: &dyn Fn(&str) -> bool, recurse: bool) -> Result<Vec<u8>> {
+    let is_component = is_component(bytes)?;
+    let mut ret = bytes[..8].to_vec();
+    for_each_section(bytes, |id, body| {
+        if id == 0 && !keep(BinaryReader::new(body).read_string()?) {
+            return Ok(());
+        }
+        ret.push(id);
+        if recurse && is_component && NESTED_SECTIONS.contains(&id) {
+            let nested = rewrite(body, keep, true)?;
+            nested.as_slice().encode(&mut ret);
+        } else {
+            body.encode(&mut ret);
+        }
+        Ok(())
+    })?;
+    Ok(ret)
+}
+
+/// Calls `f` with the id and contents of each top-level section of `bytes`.
+fn for_each_section<'a>(
+    bytes: &'a [u8],
+    mut f: impl FnMut(u8, &'a [u8]) -> Result<()>,
+) -> Result<()> {
+    is_component(bytes)?;
+    let mut reader = BinaryReader::new_with_offset(&bytes[8..], 8);
+    while !reader.eof() {
+        let id = reader.read_u8()?;
+        let len = reader.read_var_u32()?;
+        f(id, reader.read_bytes(len as usize)?)?;
+    }
+    Ok(())
+}
+
+/// Returns whether `bytes` are a component rather than a core module.
+fn is_component(bytes: &[u8]) -> Result<bool> {
+    if bytes.len() < 8 || &bytes[..4] != b"\0asm" {
+        bail!("input is not a WebAssembly module or component");
+    }
+    Ok(bytes[6..8] != [0, 0])
+}
@@ -10,11 +10,15 @@ use wasmparser::{
     ValidPayload, Validator,
 };
 
-fn is_wasi(name: &str) -> bool {
+pub(crate) fn is_wasi(name: &str) -> bool {
     name == "wasi_unstable" || name == "wasi_snapshot_preview1"
 }
 
-fn is_canonical_function(name: &str) -> bool {
+/// The function reactor modules export to set themselves up, which
+/// components call when they're instantiated.
+pub const INITIALIZE_EXPORT: &str = "_initialize";
+
+pub(crate) fn is_canonical_function(name: &str) -> bool {
     name.starts_with("canonical_abi_")
 }
 
@@ -27,7 +31,7 @@ pub fn expected_export_name<'a>(interface: Option<&str>, func: &'a str) -> Cow<'
     }
 }
 
-fn wasm_sig_to_func_type(signature: WasmSignature) -> FuncType {
+pub(crate) fn wasm_sig_to_func_type(signature: WasmSignature) -> FuncType {
     fn from_wasm_type(ty: &WasmType) -> ValType {
         match ty {
             WasmType::I32 => ValType::I32,
@@ -53,19 +57,35 @@ fn wasm_sig_to_func_type(signature: WasmSignature) -> FuncType {
     }
 }
 
+/// What [`validate_module`] found out about a module.
+#[derive(Default)]
+pub struct ModuleInfo<'a> {
+    /// The imported interfaces required by the module.
+    pub required_imports: IndexSet<&'a str>,
+    /// Whether the module exports a memory.
+    pub has_memory: bool,
+    /// Whether the module exports a realloc function.
+    pub has_realloc: bool,
+    /// Whether the module has a `start` function.
+    pub has_start: bool,
+    /// Whether the module exports an `_initialize` function.
+    pub has_initialize: bool,
+}
+
 /// This function validates the following:
 /// * The bytes represent a core WebAssembly module.
 /// * The module's imports are all satisfied by the given import interfaces.
 /// * The given default and exported interfaces are satisfied by the module's exports.
-///
-/// Returns a tuple of the set of imported interfaces required by the module, whether
-/// the module exports a memory, and whether the module exports a realloc function.
+/// * The module exports the functions named by canonical options, with the
+///   signatures in `option_funcs`.
+/// * An `_initialize` function the module exports takes and returns nothing.
 pub fn validate_module<'a>(
     bytes: &'a [u8],
     interface: &Option<&Interface>,
     imports: &[Interface],
     exports: &[Interface],
-) -> Result<(IndexSet<&'a str>, bool, bool)> {
+    option_funcs: &[(&str, WasmSignature)],
+) -> Result<ModuleInfo<'a>> {
     let imports: IndexMap<&str, &Interface> =
         imports.iter().map(|i| (i.name.as_str(), i)).collect();
     let exports: IndexMap<&str, &Interface> =
@@ -75,8 +95,10 @@ pub fn validate_module<'a>(
     let mut types = None;
     let mut import_funcs = IndexMap::new();
     let mut export_funcs = IndexMap::new();
+    let mut all_export_funcs = IndexMap::new();
     let mut has_memory = false;
     let mut has_realloc = false;
+    let mut has_start = false;
 
     for payload in Parser::new(0).parse_all(bytes) {
         let payload = payload?;
@@ -89,6 +111,7 @@ pub fn validate_module<'a>(
             Payload::Version { encoding, .. } if encoding != Encoding::Module => {
                 bail!("data is not a WebAssembly module");
             }
+            Payload::StartSection { .. } => has_start = true,
             Payload::ImportSection(s) => {
                 for import in s {
                     let import = import?;
@@ -114,6 +137,7 @@ pub fn validate_module<'a>(
 
                     match export.kind {
                         ExternalKind::Func => {
+                            all_export_funcs.insert(export.name, export.index);
                             if is_canonical_function(export.name) {
                                 if export.name == "canonical_abi_realloc" {
                                     // TODO: validate that the canonical_abi_realloc function is [i32, i32, i32, i32] -> [i32]
@@ -164,11 +188,50 @@ pub fn validate_module<'a>(
         validate_exported_interface(interface, Some(name), &export_funcs, &types)?;
     }
 
-    Ok((
-        import_funcs.keys().cloned().collect(),
+    let has_initialize = match all_export_funcs.get(INITIALIZE_EXPORT) {
+        Some(func_index) => {
+            let ty = types.function_at(*func_index).unwrap();
+            if !ty.params.is_empty() || !ty.returns.is_empty() {
+                bail!(
+                    "function `{}` must take and return nothing, but has type `{:?} -> {:?}`",
+                    INITIALIZE_EXPORT,
+                    ty.params,
+                    ty.returns
+                );
+            }
+            true
+        }
+        None => false,
+    };
+
+    for (name, sig) in option_funcs {
+        let func_index = all_export_funcs.get(name).ok_or_else(|| {
+            anyhow!(
+                "module does not export function `{}` named by a canonical option",
+                name
+            )
+        })?;
+        let expected = wasm_sig_to_func_type(sig.clone());
+        let ty = types.function_at(*func_index).unwrap();
+        if ty != &expected {
+            bail!(
+                "type mismatch for function `{}` named by a canonical option: expected `{:?} -> {:?}` but found `{:?} -> {:?}`",
+                name,
+                expected.params,
+                expected.returns,
+                ty.params,
+                ty.returns
+            );
+        }
+    }
+
+    Ok(ModuleInfo {
+        required_imports: import_funcs.keys().cloned().collect(),
         has_memory,
         has_realloc,
-    ))
+        has_start,
+        has_initialize,
+    })
 }
 
 fn validate_imported_interface(
@@ -0,0 +1,106 @@
+use anyhow::Result;
+use clap::Parser;
+use std::fs;
+use std::path::{Path, PathBuf};
+use wai_component::cli::{WaiToWasmApp, WasmToWaiApp};
+
+/// Returns an empty scratch directory named `name` for a test.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join("cli")
+        .join(name);
+    if dir.exists() {
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn wai_to_wasm(args: &[&Path]) -> Result<()> {
+    WaiToWasmApp::parse_from(std::iter::once(Path::new("wai2wasm")).chain(args.iter().copied()))
+        .execute()
+}
+
+#[test]
+fn batch_directory() -> Result<()> {
+    let dir = scratch_dir("batch-directory");
+    let inputs = dir.join("inputs");
+    let out_dir = dir.join("out");
+    fs::create_dir(&inputs)?;
+    fs::write(inputs.join("a.wai"), "a: func()")?;
+    fs::write(inputs.join("b.wai"), "b: func(x: u32) -> string")?;
+    fs::write(inputs.join("notes.txt"), "not an interface")?;
+
+    wai_to_wasm(&[&inputs, Path::new("--out-dir"), &out_dir])?;
+
+    let mut outputs = fs::read_dir(&out_dir)?
+        .map(|e| Ok(e?.file_name().into_string().unwrap()))
+        .collect::<Result<Vec<_>>>()?;
+    outputs.sort();
+    assert_eq!(outputs, ["a.wasm", "b.wasm"]);
+
+    // The components decode back to the interfaces they were encoded from.
+    let wai_dir = dir.join("wai");
+    WasmToWaiApp::parse_from([
+        Path::new("wasm2wai"),
+        &out_dir,
+        Path::new("--out-dir"),
+        &wai_dir,
+    ])
+    .execute()?;
+    let b = fs::read_to_string(wai_dir.join("b.wai"))?;
+    assert!(b.contains("b: func(x: u32) -> string"), "{}", b);
+
+    Ok(())
+}
+
+#[test]
+fn batch_rejects_output() -> Result<()> {
+    let dir = scratch_dir("batch-rejects-output");
+    fs::write(dir.join("a.wai"), "a: func()")?;
+
+    let err = wai_to_wasm(&[&dir, Path::new("--output"), &dir.join("a.wasm")]).unwrap_err();
+    assert!(err.to_string().contains("`--output`"), "{}", err);
+    assert!(!dir.join("a.wasm").exists());
+
+    Ok(())
+}
+
+#[test]
+fn batch_rejects_duplicate_outputs() -> Result<()> {
+    // Inputs with the same stem but different extensions.
+    let dir = scratch_dir("batch-duplicate-extensions");
+    let out_dir = dir.join("out");
+    fs::write(dir.join("a.wat"), "(module)")?;
+    fs::write(dir.join("a.wasm"), wat::parse_str("(module)")?)?;
+
+    let err = WasmToWaiApp::parse_from([
+        Path::new("wasm2wai"),
+        &dir,
+        Path::new("--out-dir"),
+        &out_dir,
+    ])
+    .execute()
+    .unwrap_err();
+    assert!(err.to_string().contains("would both be written"), "{}", err);
+    assert_eq!(fs::read_dir(&out_dir)?.count(), 0);
+
+    // Inputs with the same name in different directories.
+    let dir = scratch_dir("batch-duplicate-names");
+    let out_dir = dir.join("out");
+    for sub in ["x", "y"] {
+        fs::create_dir(dir.join(sub))?;
+        fs::write(dir.join(sub).join("a.wai"), "a: func()")?;
+    }
+
+    let err = wai_to_wasm(&[
+        &dir.join("*").join("a.wai"),
+        Path::new("--out-dir"),
+        &out_dir,
+    ])
+    .unwrap_err();
+    assert!(err.to_string().contains("would both be written"), "{}", err);
+    assert!(!out_dir.join("a.wasm").exists());
+
+    Ok(())
+}
@@ -1,8 +1,9 @@
 use anyhow::{bail, Context, Result};
 use pretty_assertions::assert_eq;
 use std::{fs, path::Path};
-use wai_component::ComponentEncoder;
+use wai_component::{ComponentEncoder, FunctionOptions, StringEncoding};
 use wai_parser::Interface;
+use wasmparser::{CanonicalFunction, CanonicalOption, Parser, Payload};
 
 fn read_interface(path: &Path) -> Result<Interface> {
     wai_parser::Interface::parse_file(path)
@@ -121,3 +122,254 @@ fn component_encoding() -> Result<()> {
 
     Ok(())
 }
+
+/// Encodes a component, either in memory or by streaming it into a buffer.
+fn encode(
+    module: &[u8],
+    interface: Option<&Interface>,
+    imports: &[Interface],
+    exports: &[Interface],
+    stream: bool,
+) -> Result<Vec<u8>> {
+    let mut encoder = ComponentEncoder::default()
+        .module(module)
+        .imports(imports)
+        .exports(exports);
+    if let Some(interface) = interface {
+        encoder = encoder.interface(interface);
+    }
+    if !stream {
+        return encoder.encode();
+    }
+    let mut bytes = Vec::new();
+    encoder.encode_to(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Tests that encoding the components in `components/` is reproducible, both
+/// when encoding the same inputs again and when the imported and exported
+/// interfaces are given in a different order, and that streaming the encoding
+/// gives the same bytes.
+#[test]
+fn reproducible_encoding() -> Result<()> {
+    for entry in fs::read_dir("tests/components")? {
+        let path = entry?.path();
+        if !path.is_dir() || path.join("error.txt").is_file() {
+            continue;
+        }
+
+        let module = wat::parse_file(path.join("module.wat"))?;
+        let interface_path = path.join("default.wai");
+        let interface = interface_path
+            .is_file()
+            .then(|| read_interface(&interface_path))
+            .transpose()?;
+        let interface = interface.as_ref();
+        let mut imports = read_interfaces(&path, "import-*.wai")?;
+        let mut exports = read_interfaces(&path, "export-*.wai")?;
+
+        let first = encode(&module, interface, &imports, &exports, false)?;
+        let again = encode(&module, interface, &imports, &exports, false)?;
+        assert!(first == again, "{}", path.display());
+        let streamed = encode(&module, interface, &imports, &exports, true)?;
+        assert!(first == streamed, "{}", path.display());
+
+        imports.reverse();
+        exports.reverse();
+        let reordered = encode(&module, interface, &imports, &exports, false)?;
+        assert!(first == reordered, "{}", path.display());
+    }
+
+    Ok(())
+}
+
+/// Tests exporting the same interface several times under distinct names.
+#[test]
+fn interface_exported_under_several_names() -> Result<()> {
+    let module = wat::parse_str(
+        r#"
+        (module
+          (func (export "logger-a#log") (param i32) unreachable)
+          (func (export "logger-b#log") (param i32) unreachable)
+        )
+        "#,
+    )?;
+    let logger = Interface::parse("logger", "log: func(level: u32)")?;
+    let named = |name: &str| Interface {
+        name: name.to_string(),
+        ..logger.clone()
+    };
+
+    let exports = [named("logger-a"), named("logger-b")];
+    let bytes = ComponentEncoder::default()
+        .module(&module)
+        .exports(&exports)
+        .validate(true)
+        .encode()?;
+    let printed = wasmprinter::print_bytes(&bytes)?;
+    assert!(printed.contains(r#"(export "logger-a" (instance 0))"#));
+    assert!(printed.contains(r#"(export "logger-b" (instance 1))"#));
+
+    let exports = [named("logger-a"), named("logger-a")];
+    let err = ComponentEncoder::default()
+        .module(&module)
+        .exports(&exports)
+        .encode()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "duplicate export `logger-a`");
+
+    Ok(())
+}
+
+/// Tests giving individual imported and exported functions their own
+/// canonical options.
+#[test]
+fn per_function_options() -> Result<()> {
+    let module = wat::parse_str(
+        r#"
+        (module
+          (import "host" "log" (func (param i32 i32)))
+          (memory (export "memory") 1)
+          (func (export "canonical_abi_realloc") (param i32 i32 i32 i32) (result i32) unreachable)
+          (func (export "utf16_realloc") (param i32 i32 i32 i32) (result i32) unreachable)
+          (func (export "name") (result i32) unreachable)
+          (func (export "cabi_post_name") (param i32) unreachable)
+        )
+        "#,
+    )?;
+    let imports = [Interface::parse("host", "log: func(msg: string)")?];
+    let interface = Interface::parse("default", "name: func() -> string")?;
+    let utf16 = FunctionOptions {
+        encoding: Some(StringEncoding::UTF16),
+        realloc: Some("utf16_realloc".to_string()),
+        ..FunctionOptions::default()
+    };
+    let encoder = ComponentEncoder::default()
+        .module(&module)
+        .interface(&interface)
+        .imports(&imports)
+        .validate(true);
+
+    let bytes = encoder
+        .clone()
+        .import_options("host", "log", utf16.clone())
+        .export_options(
+            "name",
+            FunctionOptions {
+                post_return: Some("cabi_post_name".to_string()),
+                ..FunctionOptions::default()
+            },
+        )
+        .encode()?;
+    let mut lowered = Vec::new();
+    let mut lifted = Vec::new();
+    for payload in Parser::new(0).parse_all(&bytes) {
+        if let Payload::ComponentCanonicalSection(s) = payload? {
+            for func in s {
+                match func? {
+                    CanonicalFunction::Lower { options, .. } => lowered.push(options),
+                    CanonicalFunction::Lift { options, .. } => lifted.push(options),
+                }
+            }
+        }
+    }
+    let realloc = |options: &[CanonicalOption]| {
+        options.iter().find_map(|o| match o {
+            CanonicalOption::Realloc(index) => Some(*index),
+            _ => None,
+        })
+    };
+    assert!(lowered[0].contains(&CanonicalOption::UTF16));
+    assert!(lifted[0].contains(&CanonicalOption::UTF8));
+    assert_ne!(realloc(&lowered[0]), realloc(&lifted[0]));
+    assert!(lifted[0]
+        .iter()
+        .any(|o| matches!(o, CanonicalOption::PostReturn(_))));
+
+    let err = encoder
+        .clone()
+        .import_options("host", "nope", utf16.clone())
+        .encode()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "options given for function `nope` of interface `host`, which isn't imported"
+    );
+
+    let err = encoder
+        .clone()
+        .export_options(
+            "name",
+            FunctionOptions {
+                post_return: Some("name".to_string()),
+                ..FunctionOptions::default()
+            },
+        )
+        .encode()
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("type mismatch for function `name` named by a canonical option"));
+
+    let err = encoder
+        .export_options(
+            "name",
+            FunctionOptions {
+                realloc: Some("missing".to_string()),
+                ..FunctionOptions::default()
+            },
+        )
+        .encode()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "module does not export function `missing` named by a canonical option"
+    );
+
+    Ok(())
+}
+
+/// Tests that a module's `_initialize` function runs when the component is
+/// instantiated, and that `start` functions which can't call the imports
+/// are rejected.
+#[test]
+fn initialize_and_start_functions() -> Result<()> {
+    let imports = [Interface::parse("host", "log: func(msg: string)")?];
+    let encode = |wat: &str| {
+        let module = wat::parse_str(format!(
+            r#"
+            (module
+              (import "host" "log" (func $log (param i32 i32)))
+              (memory (export "memory") 1)
+              (func (export "canonical_abi_realloc") (param i32 i32 i32 i32) (result i32) unreachable)
+              {}
+            )
+            "#,
+            wat
+        ))?;
+        ComponentEncoder::default()
+            .module(&module)
+            .imports(&imports)
+            .validate(true)
+            .encode()
+    };
+
+    let bytes = encode(r#"(func (export "_initialize") unreachable)"#)?;
+    let has_start = Parser::new(0)
+        .parse_all(&bytes)
+        .any(|p| matches!(p, Ok(Payload::ComponentStartSection(_))));
+    assert!(has_start);
+
+    let err = encode(r#"(func (export "_initialize") (param i32) unreachable)"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "function `_initialize` must take and return nothing, but has type `[I32] -> []`"
+    );
+
+    let err = encode("(func $start unreachable) (start $start)").unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("module has a start function, which can't call imports that use memory"));
+
+    Ok(())
+}
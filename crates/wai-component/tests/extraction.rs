@@ -0,0 +1,91 @@
+use anyhow::Result;
+use wai_component::{extract_module_interfaces, InterfacePrinter};
+
+fn print(interface: &wai_parser::Interface) -> Result<String> {
+    InterfacePrinter::default().print(interface)
+}
+
+/// Tests reconstructing the interfaces of core modules without embedded
+/// interfaces.
+#[test]
+fn guessed_interfaces() -> Result<()> {
+    let bytes = wat::parse_str(
+        r#"
+        (module
+          (import "host" "logMessage" (func (param i32 i32)))
+          (import "wasi_snapshot_preview1" "fd_write" (func (param i32 i32 i32 i32) (result i32)))
+          (import "canonical_abi" "resource_drop_file" (func (param i32)))
+          (memory (export "memory") 1)
+          (func (export "add_two") (param i64 i64) (result i64) unreachable)
+          (func (export "scale") (param f32 f64) (result f64) unreachable)
+          (func (export "div-mod") (param i32 i32) (result i32 i32) unreachable)
+          (func (export "math#sqrt") (param f64) (result f64) unreachable)
+          (func (export "canonical_abi_realloc") (param i32 i32 i32 i32) (result i32) unreachable)
+          (func (export "_start") unreachable)
+        )
+        "#,
+    )?;
+
+    let interfaces = extract_module_interfaces(&bytes)?;
+
+    let default = &interfaces.default;
+    assert!(!default.embedded);
+    assert_eq!(
+        print(&default.interface)?,
+        "add-two: func(arg0: s64, arg1: s64) -> s64\n\n\
+         scale: func(arg0: float32, arg1: float64) -> float64\n\n\
+         div-mod: func(arg0: s32, arg1: s32) -> tuple<s32, s32>\n\n"
+    );
+    assert_eq!(default.warnings.len(), 3);
+    assert!(default.warnings[0].contains("renamed to `add-two`"));
+    assert!(default.warnings[1].contains("signature of `div-mod` is unknown"));
+    assert!(default.warnings[2].contains("resource named `file`"));
+
+    assert_eq!(interfaces.exports.len(), 1);
+    let math = &interfaces.exports[0];
+    assert_eq!(math.interface.name, "math");
+    assert_eq!(
+        print(&math.interface)?,
+        "sqrt: func(arg0: float64) -> float64\n\n"
+    );
+    assert!(math.warnings.is_empty());
+
+    assert_eq!(interfaces.imports.len(), 1);
+    let host = &interfaces.imports[0];
+    assert_eq!(host.interface.name, "host");
+    assert_eq!(
+        print(&host.interface)?,
+        "log-message: func(arg0: s32, arg1: s32)\n\n"
+    );
+    assert_eq!(host.warnings.len(), 2);
+    assert!(host.warnings[0].contains("renamed to `log-message`"));
+    assert!(host.warnings[1].contains("may also be strings or lists"));
+
+    Ok(())
+}
+
+/// Tests that interfaces embedded in custom sections are used as-is.
+#[test]
+fn embedded_interfaces() -> Result<()> {
+    let interface = wai_parser::Interface::parse("math", "sqrt: func(x: float64) -> float64")?;
+    let section = wai_component::InterfaceEncoder::new(&interface).encode()?;
+
+    let mut module = wasm_encoder::Module::new();
+    module.section(&wasm_encoder::CustomSection {
+        name: "wai-interface:math",
+        data: &section,
+    });
+    let bytes = module.finish();
+
+    let interfaces = extract_module_interfaces(&bytes)?;
+    assert_eq!(interfaces.exports.len(), 1);
+    let math = &interfaces.exports[0];
+    assert!(math.embedded);
+    assert_eq!(math.interface.name, "math");
+    assert_eq!(
+        print(&math.interface)?,
+        "sqrt: func(x: float64) -> float64\n\n"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use anyhow::Result;
+use wai_component::{check_link, LinkProblem};
+use wai_parser::Interface;
+
+#[test]
+fn link_problems() -> Result<()> {
+    let host = Interface::parse(
+        "host",
+        "log: func(msg: string)\nnow: func() -> u64\nstart: func()",
+    )?;
+    let guest = Interface::parse("guest", "run: func(n: u32) -> u32\nstop: func()")?;
+    let module = wat::parse_str(
+        r#"
+        (module
+          (import "host" "log" (func (param i32 i32)))
+          (import "host" "now" (func (result i32)))
+          (import "host" "sleep" (func))
+          (import "wasi_snapshot_preview1" "proc_exit" (func (param i32)))
+          (memory (export "memory") 1)
+          (func (export "run") (param i64) (result i32) unreachable)
+          (func (export "helper") unreachable)
+          (func (export "_initialize"))
+          (func (export "canonical_abi_realloc") (param i32 i32 i32 i32) (result i32) unreachable))
+        "#,
+    )?;
+
+    let problems = check_link(&module, Some(&guest), &[host], &[])?;
+    assert_eq!(
+        problems,
+        [
+            LinkProblem::ImportMismatch {
+                module: "host".to_string(),
+                name: "now".to_string(),
+                expected: "[] -> [I64]".to_string(),
+                found: "[] -> [I32]".to_string(),
+            },
+            LinkProblem::MissingImport {
+                module: "host".to_string(),
+                name: "sleep".to_string(),
+            },
+            LinkProblem::ExportMismatch {
+                name: "run".to_string(),
+                expected: "[I32] -> [I32]".to_string(),
+                found: "[I64] -> [I32]".to_string(),
+            },
+            LinkProblem::MissingExport {
+                name: "stop".to_string(),
+            },
+            LinkProblem::ExtraExport {
+                name: "helper".to_string(),
+            },
+        ]
+    );
+    assert!(!problems[4].is_error());
+    assert_eq!(
+        problems[1].to_string(),
+        "module imports function `sleep` from `host`, which the host doesn't provide"
+    );
+
+    // Exported interfaces are looked for under mangled names.
+    let problems = check_link(&module, None, &[], &[guest])?;
+    assert!(problems.contains(&LinkProblem::MissingExport {
+        name: "guest#run".to_string()
+    }));
+    assert!(problems.contains(&LinkProblem::ExtraExport {
+        name: "run".to_string()
+    }));
+
+    Ok(())
+}
@@ -14,8 +14,9 @@ use wai_parser::Interface;
 /// compares the generated interface definition to the original interface
 /// definition.
 ///
-/// Run the test with the environment variable `BLESS` set to update
-/// the wai file based on the decoded output.
+/// Comments are carried over from the wai file, so run the test with the
+/// environment variable `BLESS` set to update the wai file based on the
+/// decoded output.
 #[test]
 fn roundtrip_interfaces() -> Result<()> {
     for entry in fs::read_dir("tests/interfaces")? {
@@ -27,6 +28,7 @@ fn roundtrip_interfaces() -> Result<()> {
         let test_case = path.file_stem().unwrap().to_str().unwrap();
         let wai_path = path.join(test_case).with_extension("wai");
 
+        let source = fs::read_to_string(&wai_path)?;
         let interface = Interface::parse_file(&wai_path).context("failed to parse `wai` file")?;
 
         let encoder = InterfaceEncoder::new(&interface).validate(true);
@@ -43,6 +45,7 @@ fn roundtrip_interfaces() -> Result<()> {
 
         let mut printer = InterfacePrinter::default();
         let output = printer
+            .keep_comments(&source)?
             .print(&interface)
             .context("failed to print interface")?;
 
@@ -50,7 +53,7 @@ fn roundtrip_interfaces() -> Result<()> {
             fs::write(&wai_path, output)?;
         } else {
             assert_eq!(
-                fs::read_to_string(&wai_path)?.replace("\r\n", "\n"),
+                source.replace("\r\n", "\n"),
                 output,
                 "encoding of wai file `{}` did not match the the decoded interface for test case `{}`",
                 wai_path.display(),
@@ -61,3 +64,17 @@ fn roundtrip_interfaces() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn doc_comments() -> Result<()> {
+    let interface = Interface::parse(
+        "docs",
+        "/// A record.\nrecord r {\n  /// A field.\n  x: u32,\n}\n\n/// A function.\n///\n/// ```rust\n/// f(r { x: 1 });\n/// ```\nf: func(a: r)\n",
+    )?;
+    let output = InterfacePrinter::default().print(&interface)?;
+    assert_eq!(
+        output,
+        "/// A record.\nrecord r {\n  /// A field.\n  x: u32,\n}\n\n/// A function.\n///\n/// ```rust\n/// f(r { x: 1 });\n/// ```\nf: func(a: r)\n\n"
+    );
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use anyhow::Result;
+use wai_component::{
+    add_custom_section, extract_custom_section, is_metadata_section, strip_custom_sections,
+};
+use wasm_encoder::{Component, CustomSection, RawSection};
+use wasmparser::{Parser, Payload, Validator, WasmFeatures};
+
+/// Returns the names of the custom sections of `bytes`, including those of
+/// nested modules.
+fn custom_sections(bytes: &[u8]) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    for payload in Parser::new(0).parse_all(bytes) {
+        if let Payload::CustomSection(s) = payload? {
+            names.push(s.name().to_string());
+        }
+    }
+    Ok(names)
+}
+
+fn validate(bytes: &[u8]) -> Result<()> {
+    Validator::new_with_features(WasmFeatures {
+        component_model: true,
+        ..Default::default()
+    })
+    .validate_all(bytes)?;
+    Ok(())
+}
+
+#[test]
+fn manage_sections() -> Result<()> {
+    let module = wat::parse_str(
+        r#"
+        (module
+          (@custom "name" "")
+          (@custom ".debug_info" "dwarf")
+          (func (export "f")))
+        "#,
+    )?;
+    let mut component = Component::new();
+    component.section(&RawSection {
+        id: 1,
+        data: &module,
+    });
+    component.section(&CustomSection {
+        name: "wai-interface:foo",
+        data: &[],
+    });
+    component.section(&CustomSection {
+        name: "build-id",
+        data: &[1, 2, 3],
+    });
+    let component = component.finish();
+    validate(&component)?;
+    assert_eq!(
+        custom_sections(&component)?,
+        ["name", ".debug_info", "wai-interface:foo", "build-id"]
+    );
+
+    let stripped = strip_custom_sections(&component, |_| false)?;
+    validate(&stripped)?;
+    assert!(custom_sections(&stripped)?.is_empty());
+
+    let stripped = strip_custom_sections(&component, is_metadata_section)?;
+    validate(&stripped)?;
+    assert_eq!(custom_sections(&stripped)?, ["name", "wai-interface:foo"]);
+
+    let added = add_custom_section(&component, "build-id", &[4, 5])?;
+    validate(&added)?;
+    assert_eq!(
+        custom_sections(&added)?,
+        ["name", ".debug_info", "wai-interface:foo", "build-id"]
+    );
+    assert_eq!(
+        extract_custom_section(&added, "build-id")?,
+        Some(&[4, 5][..])
+    );
+    // Only sections of the component itself are extracted.
+    assert_eq!(extract_custom_section(&added, ".debug_info")?, None);
+    assert_eq!(
+        extract_custom_section(&module, ".debug_info")?,
+        Some(&b"dwarf"[..])
+    );
+
+    assert!(strip_custom_sections(b"not wasm", |_| true).is_err());
+
+    Ok(())
+}
@@ -4,8 +4,10 @@ use proc_macro::TokenStream;
 use syn::parse::{Error, Parse, ParseStream, Result};
 use syn::punctuated::Punctuated;
 use syn::{token, Token};
-use wai_bindgen_gen_core::{wai_parser::Interface, Direction, Files, Generator};
-use wai_bindgen_gen_wasmer::Async;
+use wai_bindgen_gen_core::{
+    parse_interface_arg, wai_parser::Interface, Direction, Files, Filter, Generator,
+};
+use wai_bindgen_gen_wasmer::{Async, NameOverride, TracingLevel};
 
 /// Generate code to support consuming the given interfaces, importaing them
 /// from wasm modules.
@@ -25,9 +27,11 @@ fn run(input: TokenStream, dir: Direction) -> TokenStream {
     let input = syn::parse_macro_input!(input as Opts);
     let mut gen = input.opts.build();
     let mut files = Files::default();
+    let mut interfaces = input.interfaces;
+    input.filter.apply(&mut interfaces);
     let (imports, exports) = match dir {
-        Direction::Import => (input.interfaces, vec![]),
-        Direction::Export => (vec![], input.interfaces),
+        Direction::Import => (interfaces, vec![]),
+        Direction::Export => (vec![], interfaces),
     };
     gen.generate_all(&imports, &exports, &mut files);
 
@@ -57,12 +61,36 @@ struct Opts {
     opts: wai_bindgen_gen_wasmer::Opts,
     interfaces: Vec<Interface>,
     files: Vec<String>,
+    filter: Filter,
 }
 
 mod kw {
     syn::custom_keyword!(src);
     syn::custom_keyword!(paths);
+    syn::custom_keyword!(only);
+    syn::custom_keyword!(skip);
     syn::custom_keyword!(custom_error);
+    syn::custom_keyword!(record);
+    syn::custom_keyword!(stubs);
+    syn::custom_keyword!(metrics);
+    syn::custom_keyword!(deadlines);
+    syn::custom_keyword!(memory_usage);
+    syn::custom_keyword!(guest_panics);
+    syn::custom_keyword!(owned_params);
+    syn::custom_keyword!(intern);
+    syn::custom_keyword!(arena);
+    syn::custom_keyword!(unchecked);
+    syn::custom_keyword!(plugin);
+    syn::custom_keyword!(imports_builder);
+    syn::custom_keyword!(wasi);
+    syn::custom_keyword!(shared_host);
+    syn::custom_keyword!(tracing_level);
+    syn::custom_keyword!(tracing_skip);
+    syn::custom_keyword!(tracing_redact);
+    syn::custom_keyword!(module_name);
+    syn::custom_keyword!(trait_name);
+    syn::custom_keyword!(struct_name);
+    syn::custom_keyword!(cfg_feature);
 }
 
 impl Parse for Opts {
@@ -70,6 +98,7 @@ impl Parse for Opts {
         let call_site = proc_macro2::Span::call_site();
         let mut opts = wai_bindgen_gen_wasmer::Opts::default();
         let mut files = Vec::new();
+        let mut filter = Filter::default();
         opts.tracing = cfg!(feature = "tracing");
 
         let interfaces = if input.peek(token::Brace) {
@@ -79,9 +108,32 @@ impl Parse for Opts {
             let fields = Punctuated::<ConfigField, Token![,]>::parse_terminated(&content)?;
             for field in fields.into_pairs() {
                 match field.into_value() {
-                    ConfigField::Interfaces(v) => interfaces = v,
+                    ConfigField::Interfaces(v) => interfaces.extend(v),
+                    ConfigField::Only(v) => filter.only = v,
+                    ConfigField::Skip(v) => filter.skip = v,
                     ConfigField::Async(v) => opts.async_ = v,
                     ConfigField::CustomError(v) => opts.custom_error = v,
+                    ConfigField::Record(v) => opts.record = v,
+                    ConfigField::Stubs(v) => opts.stubs = v,
+                    ConfigField::Metrics(v) => opts.metrics = v,
+                    ConfigField::Deadlines(v) => opts.deadlines = v,
+                    ConfigField::MemoryUsage(v) => opts.memory_usage = v,
+                    ConfigField::GuestPanics(v) => opts.guest_panics = v,
+                    ConfigField::OwnedParams(v) => opts.owned_params = v,
+                    ConfigField::Intern(v) => opts.intern = v,
+                    ConfigField::Arena(v) => opts.arena = v,
+                    ConfigField::Unchecked(v) => opts.unchecked = v,
+                    ConfigField::Plugin(v) => opts.plugin = v,
+                    ConfigField::ImportsBuilder(v) => opts.imports_builder = v,
+                    ConfigField::Wasi(v) => opts.wasi = v,
+                    ConfigField::SharedHost(v) => opts.shared_host = v,
+                    ConfigField::TracingLevel(v) => opts.tracing_level = v,
+                    ConfigField::TracingSkip(v) => opts.tracing_skip = v,
+                    ConfigField::TracingRedact(v) => opts.tracing_redact = v,
+                    ConfigField::ModuleName(v) => opts.module_name.push(v),
+                    ConfigField::TraitName(v) => opts.trait_name.push(v),
+                    ConfigField::StructName(v) => opts.struct_name.push(v),
+                    ConfigField::CfgFeature(v) => opts.cfg_feature.push(v),
                 }
             }
             if interfaces.is_empty() {
@@ -92,31 +144,64 @@ impl Parse for Opts {
             }
             interfaces
         } else {
-            while !input.is_empty() {
-                let s = input.parse::<syn::LitStr>()?;
-                files.push(s.value());
-            }
             let mut interfaces = Vec::new();
             let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
-            for path in files.iter() {
-                let path = manifest_dir.join(path);
-                let iface = Interface::parse_file(path).map_err(|e| Error::new(call_site, e))?;
-                interfaces.push(iface);
+            while !input.is_empty() {
+                let s = input.parse::<syn::LitStr>()?;
+                let parsed = parse_interface_arg(&manifest_dir, &s.value())
+                    .map_err(|e| Error::new(s.span(), e))?;
+                for (path, iface) in parsed {
+                    interfaces.push(iface);
+                    files.push(path.display().to_string());
+                }
             }
             interfaces
         };
+        opts.validate().map_err(|e| Error::new(call_site, e))?;
+        for (i, iface) in interfaces.iter().enumerate() {
+            if interfaces[..i].iter().any(|prev| prev.name == iface.name) {
+                return Err(Error::new(
+                    call_site,
+                    format!("interface `{}` is specified more than once", iface.name),
+                ));
+            }
+        }
         Ok(Opts {
             opts,
             interfaces,
             files,
+            filter,
         })
     }
 }
 
 enum ConfigField {
     Interfaces(Vec<Interface>),
+    Only(Vec<String>),
+    Skip(Vec<String>),
     Async(wai_bindgen_gen_wasmer::Async),
     CustomError(bool),
+    Record(bool),
+    Stubs(bool),
+    Metrics(bool),
+    Deadlines(bool),
+    MemoryUsage(bool),
+    GuestPanics(bool),
+    OwnedParams(bool),
+    Intern(Vec<String>),
+    Arena(bool),
+    Unchecked(bool),
+    Plugin(bool),
+    ImportsBuilder(bool),
+    Wasi(bool),
+    SharedHost(bool),
+    TracingLevel(TracingLevel),
+    TracingSkip(Vec<String>),
+    TracingRedact(Vec<String>),
+    ModuleName(NameOverride),
+    TraitName(NameOverride),
+    StructName(NameOverride),
+    CfgFeature(NameOverride),
 }
 
 impl Parse for ConfigField {
@@ -136,14 +221,13 @@ impl Parse for ConfigField {
             input.parse::<kw::paths>()?;
             input.parse::<Token![:]>()?;
             let paths;
-            let bracket = syn::bracketed!(paths in input);
+            syn::bracketed!(paths in input);
             let paths = Punctuated::<syn::LitStr, Token![,]>::parse_terminated(&paths)?;
-            let values = paths.iter().map(|s| s.value()).collect::<Vec<_>>();
             let mut interfaces = Vec::new();
-            for value in &values {
-                let interface =
-                    Interface::parse_file(value).map_err(|e| Error::new(bracket.span, e))?;
-                interfaces.push(interface);
+            for path in paths.iter() {
+                let parsed = parse_interface_arg(Path::new(""), &path.value())
+                    .map_err(|e| Error::new(path.span(), e))?;
+                interfaces.extend(parsed.into_iter().map(|(_, iface)| iface));
             }
             Ok(ConfigField::Interfaces(interfaces))
         } else if l.peek(token::Async) {
@@ -170,8 +254,127 @@ impl Parse for ConfigField {
             Ok(ConfigField::CustomError(
                 input.parse::<syn::LitBool>()?.value,
             ))
+        } else if l.peek(kw::record) {
+            input.parse::<kw::record>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::Record(input.parse::<syn::LitBool>()?.value))
+        } else if l.peek(kw::stubs) {
+            input.parse::<kw::stubs>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::Stubs(input.parse::<syn::LitBool>()?.value))
+        } else if l.peek(kw::metrics) {
+            input.parse::<kw::metrics>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::Metrics(input.parse::<syn::LitBool>()?.value))
+        } else if l.peek(kw::deadlines) {
+            input.parse::<kw::deadlines>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::Deadlines(input.parse::<syn::LitBool>()?.value))
+        } else if l.peek(kw::guest_panics) {
+            input.parse::<kw::guest_panics>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::GuestPanics(
+                input.parse::<syn::LitBool>()?.value,
+            ))
+        } else if l.peek(kw::memory_usage) {
+            input.parse::<kw::memory_usage>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::MemoryUsage(
+                input.parse::<syn::LitBool>()?.value,
+            ))
+        } else if l.peek(kw::owned_params) {
+            input.parse::<kw::owned_params>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::OwnedParams(
+                input.parse::<syn::LitBool>()?.value,
+            ))
+        } else if l.peek(kw::arena) {
+            input.parse::<kw::arena>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::Arena(input.parse::<syn::LitBool>()?.value))
+        } else if l.peek(kw::unchecked) {
+            input.parse::<kw::unchecked>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::Unchecked(input.parse::<syn::LitBool>()?.value))
+        } else if l.peek(kw::plugin) {
+            input.parse::<kw::plugin>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::Plugin(input.parse::<syn::LitBool>()?.value))
+        } else if l.peek(kw::imports_builder) {
+            input.parse::<kw::imports_builder>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::ImportsBuilder(
+                input.parse::<syn::LitBool>()?.value,
+            ))
+        } else if l.peek(kw::wasi) {
+            input.parse::<kw::wasi>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::Wasi(input.parse::<syn::LitBool>()?.value))
+        } else if l.peek(kw::shared_host) {
+            input.parse::<kw::shared_host>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::SharedHost(
+                input.parse::<syn::LitBool>()?.value,
+            ))
+        } else if l.peek(kw::tracing_level) {
+            input.parse::<kw::tracing_level>()?;
+            input.parse::<Token![:]>()?;
+            let level = input.parse::<syn::LitStr>()?;
+            let level = level
+                .value()
+                .parse()
+                .map_err(|e| Error::new(level.span(), e))?;
+            Ok(ConfigField::TracingLevel(level))
+        } else if l.peek(kw::intern) {
+            input.parse::<kw::intern>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::Intern(parse_patterns(input)?))
+        } else if l.peek(kw::tracing_skip) {
+            input.parse::<kw::tracing_skip>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::TracingSkip(parse_patterns(input)?))
+        } else if l.peek(kw::tracing_redact) {
+            input.parse::<kw::tracing_redact>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::TracingRedact(parse_patterns(input)?))
+        } else if l.peek(kw::module_name) {
+            input.parse::<kw::module_name>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::ModuleName(parse_name_override(input)?))
+        } else if l.peek(kw::trait_name) {
+            input.parse::<kw::trait_name>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::TraitName(parse_name_override(input)?))
+        } else if l.peek(kw::struct_name) {
+            input.parse::<kw::struct_name>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::StructName(parse_name_override(input)?))
+        } else if l.peek(kw::cfg_feature) {
+            input.parse::<kw::cfg_feature>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::CfgFeature(parse_name_override(input)?))
+        } else if l.peek(kw::only) {
+            input.parse::<kw::only>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::Only(parse_patterns(input)?))
+        } else if l.peek(kw::skip) {
+            input.parse::<kw::skip>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::Skip(parse_patterns(input)?))
         } else {
             Err(l.error())
         }
     }
 }
+
+fn parse_name_override(input: ParseStream<'_>) -> Result<NameOverride> {
+    let name = input.parse::<syn::LitStr>()?;
+    name.value().parse().map_err(|e| Error::new(name.span(), e))
+}
+
+fn parse_patterns(input: ParseStream<'_>) -> Result<Vec<String>> {
+    let patterns;
+    syn::bracketed!(patterns in input);
+    let patterns = Punctuated::<syn::LitStr, Token![,]>::parse_terminated(&patterns)?;
+    Ok(patterns.iter().map(|s| s.value()).collect())
+}
@@ -0,0 +1,252 @@
+use crate::{typed_export, ABI_VERSION};
+use anyhow::{bail, Context, Result};
+use wasmer::{AsStoreMut, FunctionType, Instance, Type};
+
+/// The name guests export the revision of the canonical ABI they use under,
+/// after the symbol namespace of their bindings.
+const ABI_VERSION_EXPORT: &str = "wai_abi_version";
+
+/// The name guests export their [`AbiDescriptor`]s under, followed by `:` and
+/// the name of the interface.
+const ABI_DESCRIPTOR_EXPORT: &str = "wai_abi_descriptor";
+
+/// The revision of the descriptor format this crate reads.
+const ABI_DESCRIPTOR_VERSION: u8 = 1;
+
+/// The core ABI of an interface exported by a guest, as described by the
+/// guest itself when its bindings are generated with the
+/// `export_abi_descriptor` option.
+///
+/// This lists the functions the guest exports for the interface along with
+/// their core wasm signatures, which hosts can use to report what a module
+/// was built against or to call its functions without generated bindings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbiDescriptor {
+    /// The hash of the interface the guest was built against, as checked by
+    /// [`check_interface_hash`](crate::check_interface_hash).
+    pub interface_hash: u64,
+    /// The functions of the interface in the order they're declared.
+    pub functions: Vec<AbiFunction>,
+}
+
+/// One function of an [`AbiDescriptor`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbiFunction {
+    /// The name the function is exported under.
+    pub name: String,
+    /// The types of the core parameters of the function.
+    pub params: Vec<Type>,
+    /// The types of the core results of the function.
+    pub results: Vec<Type>,
+}
+
+impl AbiFunction {
+    /// Returns the core type of the function.
+    pub fn ty(&self) -> FunctionType {
+        FunctionType::new(self.params.clone(), self.results.clone())
+    }
+}
+
+impl AbiDescriptor {
+    /// Reads the descriptor `instance` exports for the interface `name`,
+    /// returning `None` if it doesn't export one.
+    pub fn read(
+        store: &mut impl AsStoreMut,
+        instance: &Instance,
+        name: &str,
+    ) -> Result<Option<AbiDescriptor>> {
+        let export = format!("{}:{}", ABI_DESCRIPTOR_EXPORT, name);
+        if instance.exports.get_function(&export).is_err() {
+            return Ok(None);
+        }
+        let func = instance
+            .exports
+            .get_typed_function::<(), i32>(&*store, &export)?;
+        let memory = instance.exports.get_memory("memory")?;
+        let ret = func.call(store)?;
+
+        let view = memory.view(&*store);
+        let mut slice = [0; 8];
+        view.read(ret as u32 as u64, &mut slice)
+            .context("descriptor pointer out of bounds")?;
+        let ptr = u32::from_le_bytes(slice[..4].try_into().unwrap());
+        let len = u32::from_le_bytes(slice[4..].try_into().unwrap());
+        let mut bytes = vec![0; len as usize];
+        view.read(ptr.into(), &mut bytes)
+            .context("descriptor out of bounds")?;
+        AbiDescriptor::parse(&bytes).map(Some)
+    }
+
+    /// Parses the contents of a descriptor.
+    ///
+    /// The descriptor is made up of a version byte, the interface hash as 8
+    /// little-endian bytes and the number of functions as 4 little-endian
+    /// bytes, followed for each function by the length of its name as 4
+    /// little-endian bytes, its name, and its parameter and result types,
+    /// each a count byte followed by the types' bytes in the binary format
+    /// of wasm.
+    pub fn parse(bytes: &[u8]) -> Result<AbiDescriptor> {
+        let mut reader = Reader(bytes);
+        let version = reader.u8()?;
+        if version != ABI_DESCRIPTOR_VERSION {
+            bail!("unsupported ABI descriptor version {}", version);
+        }
+        let interface_hash = u64::from_le_bytes(reader.bytes(8)?.try_into().unwrap());
+        let count = reader.u32()?;
+        let mut functions = Vec::new();
+        for _ in 0..count {
+            let len = reader.u32()?;
+            let name = std::str::from_utf8(reader.bytes(len as usize)?)
+                .context("function name isn't valid UTF-8")?
+                .to_string();
+            let params = reader.types()?;
+            let results = reader.types()?;
+            functions.push(AbiFunction {
+                name,
+                params,
+                results,
+            });
+        }
+        if !reader.0.is_empty() {
+            bail!("trailing bytes after ABI descriptor");
+        }
+        Ok(AbiDescriptor {
+            interface_hash,
+            functions,
+        })
+    }
+
+    /// Returns the function exported as `name`.
+    pub fn function(&self, name: &str) -> Option<&AbiFunction> {
+        self.functions.iter().find(|f| f.name == name)
+    }
+}
+
+/// Checks that the module behind `instance` uses [`ABI_VERSION`] of the
+/// canonical ABI, which generated bindings call when instantiating a module.
+///
+/// Guests report their revision through a `{namespace}wai_abi_version`
+/// export, where `namespace` is the symbol namespace their bindings were
+/// generated with. Modules without that export aren't checked, but exporting
+/// it with another type than `[] -> [I32]` is an error.
+pub fn check_abi_version(
+    store: &mut impl AsStoreMut,
+    instance: &Instance,
+    namespace: &str,
+) -> Result<()> {
+    let name = format!("{}{}", namespace, ABI_VERSION_EXPORT);
+    if instance.exports.get_extern(&name).is_none() {
+        return Ok(());
+    }
+    let func = typed_export::<(), u32>(&*store, instance, &name)?;
+    let version = func.call(store)?;
+    if version != ABI_VERSION {
+        bail!(
+            "the module uses revision {} of the canonical ABI but these bindings use revision {}",
+            version,
+            ABI_VERSION,
+        );
+    }
+    Ok(())
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.0.len() < len {
+            bail!("unexpected end of ABI descriptor");
+        }
+        let (ret, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(ret)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn types(&mut self) -> Result<Vec<Type>> {
+        let count = self.u8()?;
+        (0..count)
+            .map(|_| match self.u8()? {
+                0x7f => Ok(Type::I32),
+                0x7e => Ok(Type::I64),
+                0x7d => Ok(Type::F32),
+                0x7c => Ok(Type::F64),
+                other => bail!("invalid type {:#x} in ABI descriptor", other),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmer::{Imports, Module, Store};
+
+    #[test]
+    fn parse() {
+        let mut bytes = vec![1];
+        bytes.extend_from_slice(&0x0123_4567_89ab_cdefu64.to_le_bytes());
+        bytes.extend_from_slice(&[2, 0, 0, 0]);
+        bytes.extend_from_slice(&[1, 0, 0, 0]);
+        bytes.extend_from_slice(b"a");
+        bytes.extend_from_slice(&[1, 0x7e, 1, 0x7d]);
+        bytes.extend_from_slice(&[1, 0, 0, 0]);
+        bytes.extend_from_slice(b"b");
+        bytes.extend_from_slice(&[2, 0x7f, 0x7f, 0]);
+
+        let descriptor = AbiDescriptor::parse(&bytes).unwrap();
+        assert_eq!(descriptor.interface_hash, 0x0123_4567_89ab_cdef);
+        assert_eq!(
+            descriptor.functions,
+            [
+                AbiFunction {
+                    name: "a".to_string(),
+                    params: vec![Type::I64],
+                    results: vec![Type::F32],
+                },
+                AbiFunction {
+                    name: "b".to_string(),
+                    params: vec![Type::I32, Type::I32],
+                    results: vec![],
+                },
+            ]
+        );
+        let ty = descriptor.function("b").unwrap().ty();
+        assert_eq!(ty.params(), [Type::I32, Type::I32]);
+        assert!(ty.results().is_empty());
+
+        assert!(AbiDescriptor::parse(&bytes[..bytes.len() - 1]).is_err());
+        bytes[0] = 2;
+        assert!(AbiDescriptor::parse(&bytes).is_err());
+    }
+
+    #[test]
+    fn abi_version() {
+        let mut store = Store::default();
+        let mut check = |export: &str, body: &str, namespace: &str| {
+            let wat = format!("(module (func (export \"{}\") {}))", export, body);
+            let module = Module::new(&store, wat).unwrap();
+            let instance = Instance::new(&mut store, &module, &Imports::new()).unwrap();
+            check_abi_version(&mut store, &instance, namespace)
+        };
+        let current = format!("(result i32) i32.const {}", ABI_VERSION);
+        let next = format!("(result i32) i32.const {}", ABI_VERSION + 1);
+
+        check("other", "", "").unwrap();
+        check("wai_abi_version", &current, "").unwrap();
+        check("nswai_abi_version", &current, "ns").unwrap();
+        assert!(check("wai_abi_version", &next, "").is_err());
+        assert!(check("nswai_abi_version", &next, "ns").is_err());
+        check("nswai_abi_version", &next, "").unwrap();
+
+        let err = check("wai_abi_version", "(result i64) i64.const 1", "").unwrap_err();
+        assert!(err.is::<crate::ExportMismatch>(), "{}", err);
+    }
+}
@@ -0,0 +1,247 @@
+use std::alloc::{self, Layout};
+use std::cell::{Cell, RefCell};
+use std::ptr::NonNull;
+use std::slice;
+
+/// The size of the first chunk of memory an [`Arena`] allocates.
+const FIRST_CHUNK_SIZE: usize = 1024;
+
+/// The minimum alignment of the chunks of an [`Arena`].
+const CHUNK_ALIGN: usize = 16;
+
+/// A bump allocator for the lists lifted out of guest memory during a call.
+///
+/// Bindings generated with the `arena` option keep one of these per instance
+/// and pass lists which can't be borrowed from guest memory, like a
+/// `list<string>`, to host functions as slices allocated in it rather than as
+/// `Vec`s. Allocations are only possible through an [`ArenaScope`], and the
+/// memory of the arena is reused once the last scope is dropped, so once the
+/// arena has grown to the size of the largest call it stops allocating
+/// altogether.
+///
+/// Values allocated in an arena are never dropped, which is fine for lifted
+/// parameters since they only borrow from guest memory.
+pub struct Arena {
+    chunks: RefCell<Vec<Chunk>>,
+    /// The offset of the first free byte of the last chunk.
+    offset: Cell<usize>,
+    /// The number of live scopes.
+    scopes: Cell<usize>,
+}
+
+struct Chunk {
+    ptr: NonNull<u8>,
+    layout: Layout,
+}
+
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        // SAFETY: the chunk was allocated with this layout in `Arena::alloc`.
+        unsafe { alloc::dealloc(self.ptr.as_ptr(), self.layout) }
+    }
+}
+
+impl Arena {
+    /// Creates an empty arena, which allocates its first chunk of memory on
+    /// first use.
+    pub fn new() -> Arena {
+        Arena {
+            chunks: RefCell::new(Vec::new()),
+            offset: Cell::new(0),
+            scopes: Cell::new(0),
+        }
+    }
+
+    /// Opens a scope to allocate in, typically for the duration of a call.
+    ///
+    /// Scopes may be nested, the memory of the arena is only reused once all
+    /// of them are dropped.
+    pub fn scope(&self) -> ArenaScope<'_> {
+        self.scopes.set(self.scopes.get() + 1);
+        ArenaScope { arena: self }
+    }
+
+    /// Returns the total size of the chunks of memory held.
+    pub fn capacity(&self) -> usize {
+        self.chunks.borrow().iter().map(|c| c.layout.size()).sum()
+    }
+
+    fn alloc(&self, layout: Layout) -> NonNull<u8> {
+        if layout.size() == 0 {
+            // A dangling pointer is valid for zero-sized accesses as long as
+            // it's aligned.
+            return NonNull::new(layout.align() as *mut u8).unwrap();
+        }
+        let mut chunks = self.chunks.borrow_mut();
+        if let Some(chunk) = chunks.last() {
+            let base = chunk.ptr.as_ptr() as usize;
+            let start = (base + self.offset.get() + layout.align() - 1) & !(layout.align() - 1);
+            let start = start - base;
+            if let Some(end) = start.checked_add(layout.size()) {
+                if end <= chunk.layout.size() {
+                    self.offset.set(end);
+                    // SAFETY: `start` is within the chunk.
+                    return unsafe { NonNull::new_unchecked(chunk.ptr.as_ptr().add(start)) };
+                }
+            }
+        }
+
+        // Start a new chunk, at least twice as large as the previous one and
+        // aligned enough that the allocation can start at its beginning.
+        let size = chunks
+            .last()
+            .map_or(FIRST_CHUNK_SIZE, |c| c.layout.size().saturating_mul(2))
+            .max(layout.size());
+        let chunk_layout = Layout::from_size_align(size, layout.align().max(CHUNK_ALIGN))
+            .expect("arena chunk too large");
+        // SAFETY: the layout has a non-zero size.
+        let ptr = match NonNull::new(unsafe { alloc::alloc(chunk_layout) }) {
+            Some(ptr) => ptr,
+            None => alloc::handle_alloc_error(chunk_layout),
+        };
+        chunks.push(Chunk {
+            ptr,
+            layout: chunk_layout,
+        });
+        self.offset.set(layout.size());
+        ptr
+    }
+
+    /// Makes all of the memory of the arena available again, keeping only
+    /// its largest chunk.
+    fn reset(&self) {
+        let mut chunks = self.chunks.borrow_mut();
+        if chunks.len() > 1 {
+            let last = chunks.pop();
+            chunks.clear();
+            chunks.extend(last);
+        }
+        self.offset.set(0);
+    }
+}
+
+impl Default for Arena {
+    fn default() -> Arena {
+        Arena::new()
+    }
+}
+
+impl std::fmt::Debug for Arena {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Arena")
+            .field("capacity", &self.capacity())
+            .field("scopes", &self.scopes.get())
+            .finish()
+    }
+}
+
+/// A scope of an [`Arena`], which values allocated through it borrow from.
+pub struct ArenaScope<'a> {
+    arena: &'a Arena,
+}
+
+impl ArenaScope<'_> {
+    /// Allocates a slice of the values of `iter`.
+    pub fn alloc_slice<T, I>(&self, iter: I) -> &[T]
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        match self.try_alloc_slice(iter.into_iter().map(Ok::<T, std::convert::Infallible>)) {
+            Ok(slice) => slice,
+            Err(e) => match e {},
+        }
+    }
+
+    /// Allocates a slice of the values of `iter`, stopping at its first
+    /// error.
+    ///
+    /// The values produced before an error aren't dropped, and their memory
+    /// is only reused along with the rest of the arena.
+    pub fn try_alloc_slice<T, E, I>(&self, iter: I) -> Result<&[T], E>
+    where
+        I: IntoIterator<Item = Result<T, E>>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let iter = iter.into_iter();
+        let len = iter.len();
+        let layout = Layout::array::<T>(len).expect("arena slice too large");
+        let ptr = self.arena.alloc(layout).cast::<T>();
+        let mut written = 0;
+        // The length of the iterator can't be trusted, so at most `len`
+        // values are written.
+        for value in iter.take(len) {
+            // SAFETY: `ptr` has room for `len` values.
+            unsafe { ptr.as_ptr().add(written).write(value?) };
+            written += 1;
+        }
+        // SAFETY: the first `written` values were initialized above, and the
+        // arena isn't reset before `self` is dropped.
+        Ok(unsafe { slice::from_raw_parts(ptr.as_ptr(), written) })
+    }
+}
+
+impl Drop for ArenaScope<'_> {
+    fn drop(&mut self) {
+        let scopes = self.arena.scopes.get() - 1;
+        self.arena.scopes.set(scopes);
+        if scopes == 0 {
+            self.arena.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Arena;
+
+    #[test]
+    fn slices() {
+        let arena = Arena::new();
+        let scope = arena.scope();
+        let a = scope.alloc_slice(["a", "b"]);
+        let b = scope.alloc_slice((0..1000u32).map(|i| u64::from(i) * 2));
+        let c = scope.alloc_slice([(); 5]);
+        assert_eq!(a, ["a", "b"]);
+        assert_eq!(b.len(), 1000);
+        assert_eq!(b[999], 1998);
+        assert_eq!(c.len(), 5);
+        assert_eq!(b.as_ptr() as usize % std::mem::align_of::<u64>(), 0);
+    }
+
+    #[test]
+    fn errors() {
+        let arena = Arena::new();
+        let scope = arena.scope();
+        let r = scope.try_alloc_slice((0..4).map(|i| if i < 2 { Ok(i) } else { Err(i) }));
+        assert_eq!(r, Err(2));
+        let r = scope.try_alloc_slice((0..4).map(Ok::<_, ()>));
+        assert_eq!(r, Ok(&[0, 1, 2, 3][..]));
+    }
+
+    #[test]
+    fn reuse() {
+        let arena = Arena::new();
+        let outer = arena.scope();
+        let a = {
+            let inner = arena.scope();
+            inner.alloc_slice(0..2000u32);
+            // Dropping a nested scope keeps the memory of the outer one.
+            outer.alloc_slice([1u8, 2, 3])
+        };
+        outer.alloc_slice(0..2000u32);
+        assert_eq!(a, [1, 2, 3]);
+        let capacity = arena.capacity();
+        drop(outer);
+
+        // Once all scopes are dropped only the largest chunk is kept, which
+        // later calls of the same size fit in.
+        let kept = arena.capacity();
+        assert!(kept < capacity);
+        for _ in 0..10 {
+            let scope = arena.scope();
+            scope.alloc_slice(0..2000u32);
+        }
+        assert_eq!(arena.capacity(), kept);
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::BTreeSet;
+use thiserror::Error;
+use wasmer::RuntimeError;
+
+/// A set of capabilities granted to a module, such as `"fs.read"`.
+///
+/// Host traits with functions annotated with `@requires(...)` in their
+/// `*.wai` file gain a `capabilities` method returning one of these, which
+/// the bindings consult before calling such a function. A call lacking any
+/// of the required capabilities traps with [`PermissionDenied`] instead.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CapabilitySet {
+    granted: BTreeSet<String>,
+}
+
+impl CapabilitySet {
+    /// Creates a set where nothing is granted.
+    pub const fn new() -> CapabilitySet {
+        CapabilitySet {
+            granted: BTreeSet::new(),
+        }
+    }
+
+    /// Grants `capability`.
+    pub fn grant(&mut self, capability: impl Into<String>) -> &mut Self {
+        self.granted.insert(capability.into());
+        self
+    }
+
+    /// Revokes `capability`, returning whether it was granted.
+    pub fn revoke(&mut self, capability: &str) -> bool {
+        self.granted.remove(capability)
+    }
+
+    /// Returns whether `capability` is granted.
+    pub fn contains(&self, capability: &str) -> bool {
+        self.granted.contains(capability)
+    }
+
+    /// Iterates over the granted capabilities in order.
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.granted.iter().map(|s| s.as_str())
+    }
+
+    /// Checks that every capability `function` requires is granted.
+    pub fn check(
+        &self,
+        function: &'static str,
+        requires: &[&'static str],
+    ) -> Result<(), PermissionDenied> {
+        match requires.iter().find(|c| !self.contains(c)) {
+            Some(capability) => Err(PermissionDenied {
+                function,
+                capability,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for CapabilitySet {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        CapabilitySet {
+            granted: iter.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl<S: Into<String>> Extend<S> for CapabilitySet {
+    fn extend<I: IntoIterator<Item = S>>(&mut self, iter: I) {
+        self.granted.extend(iter.into_iter().map(Into::into));
+    }
+}
+
+/// The error a call traps with when the module calls a function without
+/// having been granted a capability it requires.
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("calling `{function}` requires the `{capability}` capability")]
+pub struct PermissionDenied {
+    /// The function which was called, as named in the `*.wai` file.
+    pub function: &'static str,
+    /// The first capability it requires which wasn't granted.
+    pub capability: &'static str,
+}
+
+impl From<PermissionDenied> for RuntimeError {
+    fn from(err: PermissionDenied) -> RuntimeError {
+        RuntimeError::user(Box::new(err))
+    }
+}
@@ -0,0 +1,85 @@
+use thiserror::Error;
+use wasmer::{AsStoreMut, Global, Instance, RuntimeError, Value};
+
+/// How much work a call made through a generated `*_with_deadline` function
+/// may do before it's aborted.
+///
+/// Deadlines are enforced with the metering middleware of
+/// `wasmer-middlewares`, which must be enabled when compiling the module, and
+/// are measured in its metering points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+    points: u64,
+}
+
+impl Deadline {
+    /// A deadline which aborts the call once it has used up `points`
+    /// metering points.
+    pub fn points(points: u64) -> Deadline {
+        Deadline { points }
+    }
+}
+
+/// Error returned by generated `*_with_deadline` functions.
+#[derive(Debug, Error)]
+pub enum CallError {
+    /// The call used up its deadline and was aborted.
+    #[error("the call exceeded its deadline of {points} metering points")]
+    TimedOut { points: u64 },
+    /// The module wasn't compiled with the metering middleware, so the
+    /// deadline couldn't be enforced and the function wasn't called.
+    #[error("deadlines require the module to be compiled with metering")]
+    MeteringUnavailable,
+    /// The call trapped for another reason.
+    #[error(transparent)]
+    Runtime(#[from] RuntimeError),
+}
+
+/// The globals through which the metering middleware tracks the remaining
+/// points of an instance.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct Metering {
+    remaining: Global,
+    exhausted: Global,
+}
+
+impl Metering {
+    pub fn from_instance(instance: &Instance) -> Option<Metering> {
+        let global = |name| instance.exports.get_global(name).ok().cloned();
+        Some(Metering {
+            remaining: global("wasmer_metering_remaining_points")?,
+            exhausted: global("wasmer_metering_points_exhausted")?,
+        })
+    }
+}
+
+/// Runs `call` with the metering points of the instance set to those of
+/// `deadline`, restoring the previous points afterwards.
+#[doc(hidden)]
+pub fn with_deadline<S: AsStoreMut, T>(
+    store: &mut S,
+    metering: Option<&Metering>,
+    deadline: Deadline,
+    call: impl FnOnce(&mut S) -> Result<T, RuntimeError>,
+) -> Result<T, CallError> {
+    let metering = metering.ok_or(CallError::MeteringUnavailable)?;
+    let remaining = metering.remaining.get(store);
+    let exhausted = metering.exhausted.get(store);
+    metering
+        .remaining
+        .set(store, Value::I64(deadline.points as i64))?;
+    metering.exhausted.set(store, Value::I32(0))?;
+
+    let result = call(store);
+
+    let timed_out = !matches!(metering.exhausted.get(store), Value::I32(0));
+    metering.remaining.set(store, remaining)?;
+    metering.exhausted.set(store, exhausted)?;
+    if timed_out {
+        return Err(CallError::TimedOut {
+            points: deadline.points,
+        });
+    }
+    Ok(result?)
+}
@@ -0,0 +1,20 @@
+/// A description of one function of an interface, as listed in the
+/// `FUNCTIONS` table generated in the module of each interface.
+///
+/// Types are written the way they are in the `*.wai` file, with named types
+/// and resources referred to by their name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FunctionDesc {
+    /// The name of the function, which for functions of a resource is
+    /// `resource::function`.
+    pub name: &'static str,
+    /// The name and type of each parameter, including the `self` parameter
+    /// of resource methods.
+    pub params: &'static [(&'static str, &'static str)],
+    /// The type of the result.
+    pub result: &'static str,
+    /// The capabilities listed in the function's `@requires` annotations.
+    pub requires: &'static [&'static str],
+    /// Whether the generated bindings of the function are `async`.
+    pub is_async: bool,
+}
@@ -1,5 +1,6 @@
 use crate::Region;
 use thiserror::Error;
+use wasmer::RuntimeError;
 
 #[derive(Debug, Error, PartialEq, Eq)]
 pub enum GuestError {
@@ -38,3 +39,58 @@ pub enum GuestError {
     #[error("Int conversion error: {0:?}")]
     TryFromIntError(#[from] ::std::num::TryFromIntError),
 }
+
+/// The error a call fails with when it's passed a handle to a resource of an
+/// instance which has since been reloaded.
+///
+/// Handles to resources defined by the module don't survive a reload since
+/// the new instance doesn't know about them. Dropping such a handle is fine
+/// though, and does nothing.
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("handle refers to a resource of an instance which was reloaded")]
+pub struct StaleHandle;
+
+/// The error a call fails with when an integer argument is outside of the
+/// bounds its `@range` annotation in the `*.wai` file allows.
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("argument `{param}` of `{function}` is {value}, outside of {min}..={max}")]
+pub struct OutOfRange {
+    /// The function which was called, as named in the `*.wai` file.
+    pub function: &'static str,
+    /// The parameter which was out of range.
+    pub param: &'static str,
+    pub value: i128,
+    pub min: i128,
+    pub max: i128,
+}
+
+impl OutOfRange {
+    /// Checks that `value`, the argument `param` of `function`, is within
+    /// `min..=max`.
+    pub fn check(
+        function: &'static str,
+        param: &'static str,
+        value: impl Into<i128>,
+        min: i128,
+        max: i128,
+    ) -> Result<(), OutOfRange> {
+        let value = value.into();
+        if min <= value && value <= max {
+            Ok(())
+        } else {
+            Err(OutOfRange {
+                function,
+                param,
+                value,
+                min,
+                max,
+            })
+        }
+    }
+}
+
+impl From<OutOfRange> for RuntimeError {
+    fn from(err: OutOfRange) -> RuntimeError {
+        RuntimeError::user(Box::new(err))
+    }
+}
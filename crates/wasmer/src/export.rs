@@ -0,0 +1,228 @@
+use std::fmt;
+use thiserror::Error;
+use wasmer::{
+    AsStoreRef, Extern, FunctionType, Instance, Memory, RuntimeError, TypedFunction, WasmTypeList,
+};
+
+/// The error creating bindings for an instance fails with when it doesn't
+/// export something the bindings need, or exports it with another type.
+///
+/// Bindings created with `new_partial` are created anyway, and calls to the
+/// functions they couldn't find fail with this error instead.
+///
+/// This is usually down to the module being built from another version of
+/// the `*.wai` file than the bindings, or against other interface names.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub struct ExportMismatch {
+    /// The name of the export.
+    pub name: String,
+    /// What the bindings need the export to be, such as ``a function of type
+    /// `[I32] -> [I32]` ``.
+    pub expected: String,
+    /// What the instance exports under that name, if anything.
+    pub found: Option<String>,
+    /// The exports of the instance with names like `name`, most alike first.
+    pub similar: Vec<String>,
+}
+
+impl fmt::Display for ExportMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.found {
+            Some(found) => write!(
+                f,
+                "the module exports `{}` as {}, but it should be {}",
+                self.name, found, self.expected
+            )?,
+            None => write!(
+                f,
+                "the module doesn't export `{}`, which should be {}",
+                self.name, self.expected
+            )?,
+        }
+        if !self.similar.is_empty() {
+            let names = self
+                .similar
+                .iter()
+                .map(|n| format!("`{}`", n))
+                .collect::<Vec<_>>();
+            write!(f, " (similar exports: {})", names.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl From<ExportMismatch> for RuntimeError {
+    fn from(err: ExportMismatch) -> RuntimeError {
+        RuntimeError::user(Box::new(err))
+    }
+}
+
+impl ExportMismatch {
+    fn new(instance: &Instance, name: &str, expected: String, found: Option<String>) -> Self {
+        let similar = if found.is_some() {
+            Vec::new()
+        } else {
+            similar_names(name, instance.exports.iter().map(|(n, _)| n.as_str()))
+        };
+        ExportMismatch {
+            name: name.to_string(),
+            expected,
+            found,
+            similar,
+        }
+    }
+}
+
+/// Returns the function `instance` exports as `name`, which generated
+/// bindings use to look up the exports they call.
+pub fn typed_export<Args, Rets>(
+    store: &impl AsStoreRef,
+    instance: &Instance,
+    name: &str,
+) -> Result<TypedFunction<Args, Rets>, ExportMismatch>
+where
+    Args: WasmTypeList,
+    Rets: WasmTypeList,
+{
+    let expected = FunctionType::new(Args::wasm_types(), Rets::wasm_types());
+    let found = match instance.exports.get_extern(name) {
+        Some(Extern::Function(func)) => match func.typed(store) {
+            Ok(func) => return Ok(func),
+            Err(_) => Some(describe_func(&func.ty(store))),
+        },
+        other => other.map(|e| describe(e).to_string()),
+    };
+    Err(ExportMismatch::new(
+        instance,
+        name,
+        describe_func(&expected),
+        found,
+    ))
+}
+
+/// Returns the memory `instance` exports as `name`, which generated bindings
+/// use to look up the memory of the instance.
+pub fn memory_export(instance: &Instance, name: &str) -> Result<Memory, ExportMismatch> {
+    match instance.exports.get_extern(name) {
+        Some(Extern::Memory(memory)) => Ok(memory.clone()),
+        found => Err(ExportMismatch::new(
+            instance,
+            name,
+            "a memory".to_string(),
+            found.map(|e| describe(e).to_string()),
+        )),
+    }
+}
+
+fn describe_func(ty: &FunctionType) -> String {
+    format!("a function of type `{}`", ty)
+}
+
+fn describe(export: &Extern) -> &'static str {
+    match export {
+        Extern::Function(_) => "a function",
+        Extern::Global(_) => "a global",
+        Extern::Table(_) => "a table",
+        Extern::Memory(_) => "a memory",
+    }
+}
+
+/// Returns up to three of `candidates` which are spelled like `name` or only
+/// differ from it in the interface they're exported under, most alike first.
+fn similar_names<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Vec<String> {
+    fn unqualified(name: &str) -> &str {
+        name.rsplit('#').next().unwrap()
+    }
+    fn normalize(name: &str) -> String {
+        name.chars()
+            .map(|c| {
+                if c == '_' {
+                    '-'
+                } else {
+                    c.to_ascii_lowercase()
+                }
+            })
+            .collect()
+    }
+
+    let target = normalize(name);
+    let max = (name.len() / 3).max(1);
+    let mut similar = candidates
+        .filter(|candidate| *candidate != name)
+        .filter_map(|candidate| {
+            let distance = if unqualified(candidate) == unqualified(name) {
+                0
+            } else {
+                edit_distance(&normalize(candidate), &target)
+            };
+            (distance <= max).then_some((distance, candidate))
+        })
+        .collect::<Vec<_>>();
+    similar.sort();
+    similar
+        .into_iter()
+        .take(3)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+/// The Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur.push((prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1));
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn similar() {
+        let exports = [
+            "memory",
+            "greet",
+            "greeter#greet",
+            "great_again",
+            "canonical_abi_realloc",
+            "Greets",
+        ];
+        assert_eq!(
+            similar_names("other#greet", exports.iter().copied()),
+            ["greet", "greeter#greet"]
+        );
+        assert_eq!(
+            similar_names("greets", exports.iter().copied()),
+            ["Greets", "greet"]
+        );
+        assert_eq!(
+            similar_names("great-again", exports.iter().copied()),
+            ["great_again"]
+        );
+        assert!(similar_names("realloc", exports.iter().copied()).is_empty());
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn display() {
+        let err = ExportMismatch {
+            name: "run".to_string(),
+            expected: "a function of type `[I32] -> []`".to_string(),
+            found: None,
+            similar: vec!["guest#run".to_string()],
+        };
+        assert_eq!(
+            err.to_string(),
+            "the module doesn't export `run`, which should be a function of type `[I32] -> []` \
+             (similar exports: `guest#run`)"
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use thiserror::Error;
+use wasmer::Module;
+
+/// The error instantiating a module fails with when it was built against a
+/// different version of an interface than the bindings, which would
+/// otherwise exchange garbled data with it.
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error(
+    "interface mismatch: the module was built against a different version of \
+     `{interface}` than these bindings (hash {actual:016x} instead of {expected:016x})"
+)]
+pub struct InterfaceMismatch {
+    /// The name of the interface.
+    pub interface: String,
+    /// The hash of the interface the bindings were generated from.
+    pub expected: u64,
+    /// The hash of the interface the module was built against.
+    pub actual: u64,
+}
+
+/// Checks that `module` was built against the version of the interface
+/// `name` whose hash is `hash`, which generated bindings call when
+/// instantiating a module.
+///
+/// Guests embed the hashes of their interfaces in custom sections named
+/// `wai-interface-hash:<name>`, modules without that section aren't checked.
+pub fn check_interface_hash(
+    module: &Module,
+    name: &str,
+    hash: u64,
+) -> Result<(), InterfaceMismatch> {
+    let section = format!("wai-interface-hash:{}", name);
+    for data in module.custom_sections(&section) {
+        // Every copy of the bindings linked into the module appends its hash
+        // to the section.
+        for chunk in data.chunks(8) {
+            let mut bytes = [0; 8];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            let actual = u64::from_le_bytes(bytes);
+            if chunk.len() != 8 || actual != hash {
+                return Err(InterfaceMismatch {
+                    interface: name.to_string(),
+                    expected: hash,
+                    actual,
+                });
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use anyhow::{bail, Context, Result};
+use wasmer::{AsStoreMut, ExportError, Instance};
+
+/// Calls the `_initialize` function `instance` exports, if any, which
+/// generated bindings do once the initializers of its imports have run.
+///
+/// Reactors, modules which are called into rather than run, export
+/// `_initialize` to set themselves up before any of their other exports is
+/// called. Unlike a wasm `start` function, which runs while the module is
+/// being instantiated, it can call imports which use the instance's memory.
+pub fn initialize(store: &mut impl AsStoreMut, instance: &Instance) -> Result<()> {
+    let func = match instance
+        .exports
+        .get_typed_function::<(), ()>(&*store, "_initialize")
+    {
+        Ok(func) => func,
+        Err(ExportError::Missing(_)) => return Ok(()),
+        Err(ExportError::IncompatibleType) => {
+            bail!(
+                "the module's `_initialize` export isn't a function without parameters or results"
+            )
+        }
+    };
+    func.call(store)
+        .context("the module's `_initialize` function failed")?;
+    Ok(())
+}
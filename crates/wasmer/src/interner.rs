@@ -0,0 +1,114 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// The number of bytes of strings an [`Interner`] holds by default.
+pub const DEFAULT_INTERNER_LIMIT: usize = 1 << 20;
+
+/// Deduplicates strings passed by the guest to the host.
+///
+/// Bindings generated with the `intern` option keep one of these per
+/// instance, and pass the interned string parameters of host functions as
+/// `Arc<str>`s shared by every call which passed the same string. Hosts which
+/// keep such strings around, like the level or module of a log record, then
+/// allocate each distinct string once rather than on every call.
+///
+/// To bound its memory usage an interner forgets every string it holds once
+/// they add up to more than its limit. Strings returned before that remain
+/// valid, later calls just don't share them.
+#[derive(Debug)]
+pub struct Interner {
+    strings: HashSet<Arc<str>>,
+    bytes: usize,
+    limit: usize,
+}
+
+impl Interner {
+    /// Creates an interner holding up to [`DEFAULT_INTERNER_LIMIT`] bytes of
+    /// strings.
+    pub fn new() -> Interner {
+        Interner::with_limit(DEFAULT_INTERNER_LIMIT)
+    }
+
+    /// Creates an interner holding up to `limit` bytes of strings.
+    pub fn with_limit(limit: usize) -> Interner {
+        Interner {
+            strings: HashSet::new(),
+            bytes: 0,
+            limit,
+        }
+    }
+
+    /// Returns the interned copy of `s`, allocating it if this is the first
+    /// time it's seen.
+    pub fn intern(&mut self, s: &str) -> Arc<str> {
+        if let Some(interned) = self.strings.get(s) {
+            return interned.clone();
+        }
+        let interned = Arc::<str>::from(s);
+        if s.len() > self.limit {
+            return interned;
+        }
+        if self.bytes + s.len() > self.limit {
+            self.clear();
+        }
+        self.bytes += s.len();
+        self.strings.insert(interned.clone());
+        interned
+    }
+
+    /// Returns the number of strings held.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Returns whether no strings are held.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Forgets every string held.
+    pub fn clear(&mut self) {
+        self.strings.clear();
+        self.bytes = 0;
+    }
+}
+
+impl Default for Interner {
+    fn default() -> Interner {
+        Interner::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Interner;
+    use std::sync::Arc;
+
+    #[test]
+    fn deduplicates() {
+        let mut interner = Interner::new();
+        let a = interner.intern("info");
+        let b = interner.intern(&String::from("info"));
+        let c = interner.intern("debug");
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &c));
+        assert_eq!(interner.len(), 2);
+    }
+
+    #[test]
+    fn limit() {
+        let mut interner = Interner::with_limit(8);
+        let a = interner.intern("abcd");
+        interner.intern("efgh");
+        assert_eq!(interner.len(), 2);
+
+        // Going over the limit forgets the previous strings.
+        interner.intern("ijkl");
+        assert_eq!(interner.len(), 1);
+        assert!(!Arc::ptr_eq(&a, &interner.intern("abcd")));
+
+        // Strings larger than the limit are never held.
+        assert_eq!(&*interner.intern("0123456789"), "0123456789");
+        assert_eq!(interner.len(), 2);
+    }
+}
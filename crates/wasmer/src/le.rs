@@ -2,6 +2,7 @@ use crate::AllBytesValid;
 use std::cmp::Ordering;
 use std::fmt;
 use std::mem;
+use std::ptr;
 use std::slice;
 
 /// Helper type representing a 1-byte-aligned little-endian value in memory.
@@ -41,6 +42,52 @@ where
         self.0 = val.into_le();
     }
 
+    /// Writes all of `src` into `dst`, which must have the same length.
+    ///
+    /// For primitives on little-endian hosts this is a single `memcpy`.
+    pub(crate) fn store_slice(dst: &mut [Le<T>], src: &[T]) {
+        assert_eq!(dst.len(), src.len());
+        if bulk_copy::<T>() {
+            // SAFETY: `Le<T>` has the same size as `T`, and for the plain
+            // primitives on a little-endian host the bytes of `T` are exactly
+            // the bytes `Le::set` would write. The two slices can't overlap as
+            // `dst` is borrowed mutably.
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    src.as_ptr().cast::<u8>(),
+                    dst.as_mut_ptr().cast::<u8>(),
+                    mem::size_of_val(src),
+                );
+            }
+        } else {
+            for (slot, val) in dst.iter_mut().zip(src) {
+                slot.set(*val);
+            }
+        }
+    }
+
+    /// Reads every value in `src` into a new vector.
+    ///
+    /// For primitives on little-endian hosts this is a single `memcpy`.
+    pub(crate) fn to_vec(src: &[Le<T>]) -> Vec<T> {
+        if !bulk_copy::<T>() {
+            return src.iter().map(|s| s.get()).collect();
+        }
+        let mut ret = Vec::<T>::with_capacity(src.len());
+        // SAFETY: see `store_slice`, and every byte pattern is a valid `T`
+        // due to the `AllBytesValid` supertrait of `Endian`, so the first
+        // `src.len()` elements are initialized after the copy.
+        unsafe {
+            ptr::copy_nonoverlapping(
+                src.as_ptr().cast::<u8>(),
+                ret.as_mut_ptr().cast::<u8>(),
+                mem::size_of_val(src),
+            );
+            ret.set_len(src.len());
+        }
+        ret
+    }
+
     pub(crate) fn from_slice(bytes: &[u8]) -> &[Le<T>] {
         // SAFETY: The invariants we uphold here are:
         //
@@ -136,6 +183,26 @@ pub trait Endian: AllBytesValid + Copy + Sized {
     /// Converts this value and any aggregate fields (if any) from
     /// little-endian byte order
     fn from_le(self) -> Self;
+
+    /// Whether this type has no padding and no fields to convert, meaning
+    /// that on little-endian hosts slices of it can be copied to and from
+    /// wasm memory as raw bytes.
+    ///
+    /// The unsafe code in `Le` relies on this, so it can only be overridden
+    /// by this crate's primitives: the type of the argument can't be named
+    /// by other implementations.
+    #[doc(hidden)]
+    fn plain(_: sealed::Plain) -> bool {
+        false
+    }
+}
+
+mod sealed {
+    pub struct Plain;
+}
+
+fn bulk_copy<T: Endian>() -> bool {
+    cfg!(target_endian = "little") && T::plain(sealed::Plain)
 }
 
 macro_rules! primitives {
@@ -150,6 +217,11 @@ macro_rules! primitives {
             fn from_le(self) -> Self {
                 Self::from_le_bytes(self.to_ne_bytes())
             }
+
+            #[inline]
+            fn plain(_: sealed::Plain) -> bool {
+                true
+            }
         }
     )*)
 }
@@ -192,3 +264,60 @@ tuples! {
     (T1 T2 T3 T4 T5 T6 T7 T8 T9)
     (T1 T2 T3 T4 T5 T6 T7 T8 T9 T10)
 }
+
+#[cfg(test)]
+mod test {
+    use super::Le;
+
+    #[test]
+    fn bulk_copy() {
+        let mut bytes = [0; 12];
+        let vals = [1u32, 0x01020304, u32::MAX];
+        Le::store_slice(Le::from_slice_mut(&mut bytes), &vals);
+        assert_eq!(bytes[4..8], [4, 3, 2, 1]);
+        assert_eq!(Le::<u32>::to_vec(Le::from_slice(&bytes)), vals);
+
+        let mut bytes = [0; 16];
+        let vals = [(1u8, 2u32), (3, 4)];
+        Le::store_slice(Le::from_slice_mut(&mut bytes), &vals);
+        assert_eq!(Le::<(u8, u32)>::to_vec(Le::from_slice(&bytes)), vals);
+
+        // Tuples may have padding so they're always converted field by field.
+        assert_eq!(super::bulk_copy::<u32>(), cfg!(target_endian = "little"));
+        assert!(!super::bulk_copy::<(u32, u32)>());
+    }
+
+    #[test]
+    fn unaligned() {
+        // Every slot below starts one byte past an 8-byte boundary.
+        let mut buf = [0u64; 3];
+        let len = std::mem::size_of_val(&buf);
+        // SAFETY: every byte of a `u64` is initialized.
+        let bytes = unsafe { std::slice::from_raw_parts_mut(buf.as_mut_ptr().cast::<u8>(), len) };
+        let bytes = &mut bytes[1..17];
+
+        let slots = Le::<u64>::from_slice_mut(bytes);
+        slots[0].set(0x0102_0304_0506_0708);
+        slots[1] = Le::new(u64::MAX);
+        assert_eq!(slots[0], 0x0102_0304_0506_0708);
+        assert_eq!(bytes[..8], [8, 7, 6, 5, 4, 3, 2, 1]);
+        assert_eq!(
+            Le::<u64>::to_vec(Le::from_slice(bytes)),
+            [0x0102_0304_0506_0708, u64::MAX]
+        );
+
+        Le::store_slice(Le::from_slice_mut(bytes), &[(1u16, -1i16, 2u32); 2]);
+        assert_eq!(
+            Le::<(u16, i16, u32)>::to_vec(Le::from_slice(bytes)),
+            [(1, -1, 2); 2]
+        );
+
+        assert!(Le::<u32>::to_vec(Le::from_slice(&bytes[3..3])).is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn truncated() {
+        Le::<u32>::from_slice(&[0; 6]);
+    }
+}
@@ -4,20 +4,64 @@ pub use wai_bindgen_wasmer_impl::{export, import};
 pub use async_trait::async_trait;
 #[cfg(feature = "tracing-lib")]
 pub use tracing_lib as tracing;
+#[cfg(feature = "wasi")]
+#[doc(hidden)]
+pub use wasmer_wasi;
 #[doc(hidden)]
 pub use {anyhow, bitflags, once_cell, wasmer};
 
+mod abi;
+mod arena;
+mod capability;
+mod deadline;
+mod desc;
 mod error;
+mod export;
+mod hash;
+mod init;
+mod interner;
 mod le;
+mod memory;
+mod metrics;
+mod panic;
+mod raw;
 mod region;
+mod replay;
 mod slab;
 mod table;
+mod utf8;
+
+#[cfg(feature = "logging")]
+pub mod logging;
 
-pub use error::GuestError;
+// Lets the `logging` module use the bindings macros on this crate itself.
+#[cfg(feature = "logging")]
+extern crate self as wai_bindgen_wasmer;
+
+pub use abi::{check_abi_version, AbiDescriptor, AbiFunction};
+pub use arena::{Arena, ArenaScope};
+pub use capability::{CapabilitySet, PermissionDenied};
+pub use deadline::{CallError, Deadline};
+pub use desc::FunctionDesc;
+pub use error::{GuestError, OutOfRange, StaleHandle};
+pub use export::{memory_export, typed_export, ExportMismatch};
+pub use hash::{check_interface_hash, InterfaceMismatch};
+pub use init::initialize;
+pub use interner::{Interner, DEFAULT_INTERNER_LIMIT};
 pub use le::{Endian, Le};
+pub use memory::MemoryWatch;
+pub use metrics::{set_metrics, Metrics};
+pub use panic::GuestPanic;
 pub use region::{AllBytesValid, BorrowChecker, Region};
+pub use replay::{decode_log, encode_log, LogError, LogValue, ReplayMismatch, LOG_VERSION};
 pub use table::*;
 
+/// The revision of the canonical ABI implemented by this crate, which
+/// generated bindings check at compile time and, if the module exports a
+/// `wai_abi_version` function, when instantiating a module with
+/// [`check_abi_version`].
+pub const ABI_VERSION: u32 = 1;
+
 pub struct RawMemory {
     pub slice: *mut [u8],
 }
@@ -30,9 +74,13 @@ unsafe impl Sync for RawMemory {}
 
 #[doc(hidden)]
 pub mod rt {
+    pub use crate::deadline::{with_deadline, Metering};
+    pub use crate::metrics::CallMetrics;
+    pub use crate::panic::PanicContext;
+    use crate::raw;
     use crate::slab::Slab;
+    pub use crate::utf8::string_from_utf8;
     use crate::{Endian, Le};
-    use std::mem;
     use wasmer::*;
 
     pub trait RawMem {
@@ -43,34 +91,15 @@ pub mod rt {
 
     impl RawMem for [u8] {
         fn store<T: Endian>(&mut self, offset: i32, val: T) -> Result<(), RuntimeError> {
-            let mem = self
-                .get_mut(offset as usize..)
-                .and_then(|m| m.get_mut(..mem::size_of::<T>()))
-                .ok_or_else(|| RuntimeError::new("out of bounds write"))?;
-            Le::from_slice_mut(mem)[0].set(val);
-            Ok(())
+            raw::store(self, offset, val).map_err(RuntimeError::new)
         }
 
         fn store_many<T: Endian>(&mut self, offset: i32, val: &[T]) -> Result<(), RuntimeError> {
-            let mem = self
-                .get_mut(offset as usize..)
-                .and_then(|m| {
-                    let len = mem::size_of::<T>().checked_mul(val.len())?;
-                    m.get_mut(..len)
-                })
-                .ok_or_else(|| RuntimeError::new("out of bounds write"))?;
-            for (slot, val) in Le::from_slice_mut(mem).iter_mut().zip(val) {
-                slot.set(*val);
-            }
-            Ok(())
+            raw::store_many(self, offset, val).map_err(RuntimeError::new)
         }
 
         fn load<T: Endian>(&self, offset: i32) -> Result<T, RuntimeError> {
-            let mem = self
-                .get(offset as usize..)
-                .and_then(|m| m.get(..mem::size_of::<Le<T>>()))
-                .ok_or_else(|| RuntimeError::new("out of bounds read"))?;
-            Ok(Le::from_slice(mem)[0].get())
+            raw::load(self, offset).map_err(RuntimeError::new)
         }
     }
 
@@ -106,6 +135,10 @@ pub mod rt {
         RuntimeError::new(msg)
     }
 
+    pub fn copy_le<T: Endian>(slice: &[Le<T>]) -> Vec<T> {
+        Le::to_vec(slice)
+    }
+
     pub fn copy_slice<T: Endian>(
         store: &mut wasmer::Store,
         memory: &Memory,
@@ -114,18 +147,9 @@ pub mod rt {
         len: i32,
         align: i32,
     ) -> Result<Vec<T>, RuntimeError> {
-        let size = (len as u32)
-            .checked_mul(mem::size_of::<T>() as u32)
-            .ok_or_else(|| RuntimeError::new("array too large to fit in wasm memory"))?;
         let memory_view = memory.view(store);
-        let slice = unsafe {
-            memory_view
-                .data_unchecked()
-                .get(base as usize..)
-                .and_then(|s| s.get(..size as usize))
-                .ok_or_else(|| RuntimeError::new("out of bounds read"))?
-        };
-        let result = Le::from_slice(slice).iter().map(|s| s.get()).collect();
+        let (result, size) = raw::read_slice(unsafe { memory_view.data_unchecked() }, base, len)
+            .map_err(RuntimeError::new)?;
         free.call(store, base, size as i32, align)?;
         Ok(result)
     }
@@ -192,6 +216,7 @@ pub mod rt {
     #[derive(Default, Debug)]
     pub struct ResourceSlab {
         slab: Slab<Resource>,
+        epoch: u32,
     }
 
     #[derive(Debug)]
@@ -201,19 +226,29 @@ pub mod rt {
     }
 
     #[derive(Debug, Copy, Clone)]
-    pub struct ResourceIndex(u32);
+    pub struct ResourceIndex {
+        idx: u32,
+        epoch: u32,
+    }
 
     impl ResourceSlab {
         pub fn insert(&mut self, wasm: i32) -> ResourceIndex {
-            ResourceIndex(self.slab.insert(Resource { wasm, refcnt: 1 }))
+            ResourceIndex {
+                idx: self.slab.insert(Resource { wasm, refcnt: 1 }),
+                epoch: self.epoch,
+            }
         }
 
         pub fn get(&self, idx: ResourceIndex) -> i32 {
-            self.slab.get(idx.0).unwrap().wasm
+            assert_eq!(idx.epoch, self.epoch);
+            self.slab.get(idx.idx).unwrap().wasm
         }
 
         pub fn clone(&mut self, idx: ResourceIndex) -> Result<(), RuntimeError> {
-            let resource = self.slab.get_mut(idx.0).unwrap();
+            if idx.epoch != self.epoch {
+                return Err(RuntimeError::user(Box::new(crate::StaleHandle)));
+            }
+            let resource = self.slab.get_mut(idx.idx).unwrap();
             resource.refcnt = match resource.refcnt.checked_add(1) {
                 Some(cnt) => cnt,
                 None => return Err(RuntimeError::new("resource index count overflow")),
@@ -221,14 +256,25 @@ pub mod rt {
             Ok(())
         }
 
+        /// Forgets every resource, which belonged to an instance that has
+        /// since been replaced. Handles to them fail with `StaleHandle`
+        /// afterwards, and dropping them does nothing.
+        pub fn invalidate(&mut self) {
+            self.slab = Slab::default();
+            self.epoch = self.epoch.wrapping_add(1);
+        }
+
         pub fn drop(&mut self, idx: ResourceIndex) -> Option<i32> {
-            let resource = self.slab.get_mut(idx.0).unwrap();
+            if idx.epoch != self.epoch {
+                return None;
+            }
+            let resource = self.slab.get_mut(idx.idx).unwrap();
             assert!(resource.refcnt > 0);
             resource.refcnt -= 1;
             if resource.refcnt != 0 {
                 return None;
             }
-            let resource = self.slab.remove(idx.0).unwrap();
+            let resource = self.slab.remove(idx.idx).unwrap();
             Some(resource.wasm)
         }
     }
@@ -0,0 +1,122 @@
+//! Host implementations of the built-in `wai-logging` interface, through
+//! which guests built with the `logging` feature of `wai-bindgen-rust`
+//! forward their log records.
+//!
+//! ```ignore
+//! let init = wai_bindgen_wasmer::logging::add_to_imports(
+//!     &mut store,
+//!     &mut imports,
+//!     LogBridge::new().with_prefix("plugin"),
+//! );
+//! let instance = Instance::new(&mut store, &module, &imports)?;
+//! init(&instance, &store)?;
+//! ```
+
+crate::export!("builtin:wai-logging");
+
+pub use self::wai_logging::{add_to_imports, Level, WaiLogging, FUNCTIONS, INTERFACE_HASH};
+
+impl From<Level> for log::Level {
+    fn from(level: Level) -> log::Level {
+        match level {
+            Level::Error => log::Level::Error,
+            Level::Warn => log::Level::Warn,
+            Level::Info => log::Level::Info,
+            Level::Debug => log::Level::Debug,
+            Level::Trace => log::Level::Trace,
+        }
+    }
+}
+
+/// Forwards the records of a guest to the logger of the `log` crate.
+#[derive(Debug, Clone, Default)]
+pub struct LogBridge {
+    prefix: Option<String>,
+}
+
+impl LogBridge {
+    /// Creates a bridge logging records under the targets the guest gave
+    /// them.
+    pub fn new() -> LogBridge {
+        LogBridge::default()
+    }
+
+    /// Logs records under `<prefix>::<target>` instead, to tell apart the
+    /// records of several guests.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> LogBridge {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    fn with_target<R>(&self, target: &str, f: impl FnOnce(&str) -> R) -> R {
+        match &self.prefix {
+            Some(prefix) => f(&format!("{}::{}", prefix, target)),
+            None => f(target),
+        }
+    }
+}
+
+impl WaiLogging for LogBridge {
+    fn log(&mut self, level: Level, target: &str, message: &str) {
+        self.with_target(target, |target| {
+            log::logger().log(
+                &log::Record::builder()
+                    .level(level.into())
+                    .target(target)
+                    .args(format_args!("{}", message))
+                    .build(),
+            )
+        })
+    }
+
+    fn enabled(&mut self, level: Level, target: &str) -> bool {
+        let level = log::Level::from(level);
+        level <= log::max_level()
+            && self.with_target(target, |target| {
+                log::logger().enabled(&log::Metadata::builder().level(level).target(target).build())
+            })
+    }
+}
+
+/// Forwards the records of a guest to `tracing` as events.
+///
+/// The events are emitted under the `wai_logging` target, with the target
+/// the guest gave them recorded in their `guest_target` field.
+#[cfg(feature = "tracing-lib")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingBridge;
+
+#[cfg(feature = "tracing-lib")]
+impl WaiLogging for TracingBridge {
+    fn log(&mut self, level: Level, target: &str, message: &str) {
+        use tracing_lib::{event, Level as L};
+        match level {
+            Level::Error => {
+                event!(target: "wai_logging", L::ERROR, guest_target = target, "{}", message)
+            }
+            Level::Warn => {
+                event!(target: "wai_logging", L::WARN, guest_target = target, "{}", message)
+            }
+            Level::Info => {
+                event!(target: "wai_logging", L::INFO, guest_target = target, "{}", message)
+            }
+            Level::Debug => {
+                event!(target: "wai_logging", L::DEBUG, guest_target = target, "{}", message)
+            }
+            Level::Trace => {
+                event!(target: "wai_logging", L::TRACE, guest_target = target, "{}", message)
+            }
+        }
+    }
+
+    fn enabled(&mut self, level: Level, _target: &str) -> bool {
+        use tracing_lib::{enabled, Level as L};
+        match level {
+            Level::Error => enabled!(target: "wai_logging", L::ERROR),
+            Level::Warn => enabled!(target: "wai_logging", L::WARN),
+            Level::Info => enabled!(target: "wai_logging", L::INFO),
+            Level::Debug => enabled!(target: "wai_logging", L::DEBUG),
+            Level::Trace => enabled!(target: "wai_logging", L::TRACE),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::sync::Mutex;
+use wasmer::{AsStoreRef, Memory, RuntimeError};
+
+type GrowCallback = Box<dyn FnMut(u64) -> Result<(), RuntimeError> + Send>;
+
+/// Watches the size of a guest's linear memory on behalf of the
+/// `set_grow_hook` function of generated exports structs.
+#[derive(Default)]
+pub struct MemoryWatch {
+    hook: Mutex<Option<GrowHook>>,
+}
+
+struct GrowHook {
+    threshold: u64,
+    reported: u64,
+    callback: GrowCallback,
+}
+
+impl MemoryWatch {
+    /// Calls `callback` with the size of the memory in bytes whenever it's
+    /// found to have grown past both `threshold` and the size last reported.
+    pub fn set(
+        &self,
+        threshold: u64,
+        callback: impl FnMut(u64) -> Result<(), RuntimeError> + Send + 'static,
+    ) {
+        *self.hook.lock().unwrap() = Some(GrowHook {
+            threshold,
+            reported: 0,
+            callback: Box::new(callback),
+        });
+    }
+
+    /// Removes the callback installed with [`MemoryWatch::set`].
+    pub fn clear(&self) {
+        *self.hook.lock().unwrap() = None;
+    }
+
+    #[doc(hidden)]
+    pub fn check(&self, store: &impl AsStoreRef, memory: &Memory) -> Result<(), RuntimeError> {
+        let mut hook = self.hook.lock().unwrap();
+        if let Some(hook) = &mut *hook {
+            let size = memory.view(store).data_size();
+            if size > hook.threshold && size > hook.reported {
+                hook.reported = size;
+                (hook.callback)(size)?;
+            }
+        }
+        Ok(())
+    }
+}
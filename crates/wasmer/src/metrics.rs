@@ -0,0 +1,57 @@
+use once_cell::sync::OnceCell;
+use std::time::{Duration, Instant};
+
+/// Receives a measurement for every call made through bindings generated with
+/// the `metrics` option, e.g. to update Prometheus counters and histograms.
+///
+/// Both calls from the host into wasm and calls from wasm into host functions
+/// are reported.
+pub trait Metrics: Send + Sync + 'static {
+    /// Called when a call to the function `func` of the interface `iface`
+    /// returns after `duration`. `result_ok` is `false` if the call trapped.
+    fn on_call(&self, iface: &str, func: &str, duration: Duration, result_ok: bool);
+}
+
+static METRICS: OnceCell<Box<dyn Metrics>> = OnceCell::new();
+
+/// Installs the [`Metrics`] which generated bindings report calls to.
+///
+/// Metrics can only be installed once per process, so if some already are
+/// `metrics` is handed back as the error.
+pub fn set_metrics(metrics: impl Metrics) -> Result<(), Box<dyn Metrics>> {
+    METRICS.set(Box::new(metrics))
+}
+
+/// Measures a single call, reporting it to the installed [`Metrics`] when
+/// dropped.
+#[doc(hidden)]
+pub struct CallMetrics {
+    iface: &'static str,
+    func: &'static str,
+    start: Option<Instant>,
+    ok: bool,
+}
+
+impl CallMetrics {
+    pub fn new(iface: &'static str, func: &'static str) -> CallMetrics {
+        CallMetrics {
+            iface,
+            func,
+            // Don't bother reading the clock if nobody is listening.
+            start: METRICS.get().map(|_| Instant::now()),
+            ok: false,
+        }
+    }
+
+    pub fn succeeded(&mut self) {
+        self.ok = true;
+    }
+}
+
+impl Drop for CallMetrics {
+    fn drop(&mut self) {
+        if let (Some(metrics), Some(start)) = (METRICS.get(), self.start) {
+            metrics.on_call(self.iface, self.func, start.elapsed(), self.ok);
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::fmt;
+use wasmer::{AsStoreMut, AsStoreRef, FrameInfo, Instance, Memory, RuntimeError, TypedFunction};
+
+/// A panic of the guest, retrieved after a call trapped because of it by the
+/// `guest_panic` method generated with the `guest_panics` option.
+///
+/// Guests record their panics when built with the `panic_hook` option of the
+/// Rust guest bindings, or after calling
+/// `wai_bindgen_rust::install_panic_hook`.
+#[derive(Debug, Clone)]
+pub struct GuestPanic {
+    /// The message of the panic, along with where in the guest's source it
+    /// happened.
+    pub message: String,
+    /// The wasm frames the call trapped in, innermost first.
+    pub trace: Vec<FrameInfo>,
+}
+
+impl fmt::Display for GuestPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "guest {}", self.message)?;
+        if !self.trace.is_empty() {
+            write!(f, "\nwasm backtrace:")?;
+        }
+        for (i, frame) in self.trace.iter().enumerate() {
+            write!(f, "\n  {:>3}: {}!", i, frame.module_name())?;
+            match frame.function_name() {
+                Some(name) => write!(f, "{}", name)?,
+                None => write!(f, "<wasm function {}>", frame.func_index())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for GuestPanic {}
+
+/// The export through which guests hand out their last panic.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct PanicContext {
+    take_panic: TypedFunction<(), i32>,
+    memory: Memory,
+}
+
+impl PanicContext {
+    pub fn from_instance(store: &impl AsStoreRef, instance: &Instance) -> Option<PanicContext> {
+        Some(PanicContext {
+            take_panic: instance
+                .exports
+                .get_typed_function(store, "canonical_abi_take_panic")
+                .ok()?,
+            memory: instance.exports.get_memory("memory").ok()?.clone(),
+        })
+    }
+
+    /// Takes the last panic of the guest, which is `None` if it didn't panic
+    /// since the last time, and attaches the trace of `error` to it.
+    pub fn take(&self, store: &mut impl AsStoreMut, error: &RuntimeError) -> Option<GuestPanic> {
+        // The guest returns a pointer to the pointer and length of the
+        // message, which are zero if it didn't panic.
+        let area = self.take_panic.call(store).ok()?;
+        let view = self.memory.view(store);
+        let mut bytes = [0; 8];
+        view.read(area as u32 as u64, &mut bytes).ok()?;
+        let ptr = u32::from_le_bytes(bytes[..4].try_into().unwrap());
+        let len = u32::from_le_bytes(bytes[4..].try_into().unwrap());
+        if len == 0 || u64::from(ptr) + u64::from(len) > view.data_size() {
+            return None;
+        }
+        let mut message = vec![0; len as usize];
+        view.read(u64::from(ptr), &mut message).ok()?;
+        Some(GuestPanic {
+            message: String::from_utf8_lossy(&message).into_owned(),
+            trace: error.trace().to_vec(),
+        })
+    }
+}
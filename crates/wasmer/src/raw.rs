@@ -0,0 +1,130 @@
+//! The reads and writes of guest memory behind [`RawMem`](crate::rt::RawMem)
+//! and [`copy_slice`](crate::rt::copy_slice), written against plain byte
+//! slices rather than a wasmer `Memory`.
+//!
+//! Failures are reported as the message of the `RuntimeError` the callers
+//! turn them into, which keeps this module free of wasmer so its unsafe code
+//! can be run under Miri with a byte array standing in for linear memory:
+//!
+//! ```text
+//! cargo +nightly miri test -p wai-bindgen-wasmer --lib -- le:: raw:: region::
+//! ```
+
+use crate::{Endian, Le};
+use std::mem;
+
+pub(crate) type Result<T> = std::result::Result<T, &'static str>;
+
+/// Returns the `len` bytes of `mem` starting at `offset`.
+fn range(mem: &[u8], offset: i32, len: usize) -> Option<&[u8]> {
+    mem.get(offset as usize..)?.get(..len)
+}
+
+fn range_mut(mem: &mut [u8], offset: i32, len: usize) -> Option<&mut [u8]> {
+    mem.get_mut(offset as usize..)?.get_mut(..len)
+}
+
+/// Writes `val` to `mem` at `offset`, which needn't be aligned.
+pub(crate) fn store<T: Endian>(mem: &mut [u8], offset: i32, val: T) -> Result<()> {
+    let mem = range_mut(mem, offset, mem::size_of::<T>()).ok_or("out of bounds write")?;
+    Le::from_slice_mut(mem)[0].set(val);
+    Ok(())
+}
+
+/// Writes all of `vals` to `mem`, one after another from `offset`.
+pub(crate) fn store_many<T: Endian>(mem: &mut [u8], offset: i32, vals: &[T]) -> Result<()> {
+    let len = mem::size_of::<T>()
+        .checked_mul(vals.len())
+        .ok_or("out of bounds write")?;
+    let mem = range_mut(mem, offset, len).ok_or("out of bounds write")?;
+    Le::store_slice(Le::from_slice_mut(mem), vals);
+    Ok(())
+}
+
+/// Reads the value at `offset` in `mem`, which needn't be aligned.
+pub(crate) fn load<T: Endian>(mem: &[u8], offset: i32) -> Result<T> {
+    let mem = range(mem, offset, mem::size_of::<Le<T>>()).ok_or("out of bounds read")?;
+    Ok(Le::from_slice(mem)[0].get())
+}
+
+/// Reads the list of `len` values at `base` in `mem`, returning it along with
+/// its size in bytes for the guest's free function.
+pub(crate) fn read_slice<T: Endian>(mem: &[u8], base: i32, len: i32) -> Result<(Vec<T>, u32)> {
+    let size = (len as u32)
+        .checked_mul(mem::size_of::<T>() as u32)
+        .ok_or("array too large to fit in wasm memory")?;
+    let slice = range(mem, base, size as usize).ok_or("out of bounds read")?;
+    Ok((Le::to_vec(Le::from_slice(slice)), size))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unaligned() {
+        // Offset the memory by one byte so that every access below is
+        // misaligned for the type it reads or writes.
+        let mut buf = [0u64; 4];
+        let mem = &mut bytes_of(&mut buf)[1..];
+
+        store(mem, 1, 0x0102_0304u32).unwrap();
+        assert_eq!(mem[1..5], [4, 3, 2, 1]);
+        assert_eq!(load::<u32>(mem, 1).unwrap(), 0x0102_0304);
+        assert_eq!(load::<u16>(mem, 2).unwrap(), 0x0203);
+
+        store(mem, 6, -2.5f64).unwrap();
+        assert_eq!(load::<f64>(mem, 6).unwrap(), -2.5);
+
+        store(mem, 14, (7u16, -1i32)).unwrap();
+        assert_eq!(load::<(u16, i32)>(mem, 14).unwrap(), (7, -1));
+    }
+
+    #[test]
+    fn many() {
+        let mut mem = [0; 19];
+        store_many(&mut mem, 3, &[1u32, 0x0a0b_0c0d, u32::MAX, 0]).unwrap();
+        assert_eq!(mem[7..11], [0x0d, 0x0c, 0x0b, 0x0a]);
+        let (vals, size) = read_slice::<u32>(&mem, 3, 4).unwrap();
+        assert_eq!(vals, [1, 0x0a0b_0c0d, u32::MAX, 0]);
+        assert_eq!(size, 16);
+
+        store_many(&mut mem, 1, &[(1u8, 2u8, -3i16); 3]).unwrap();
+        let (vals, size) = read_slice::<(u8, u8, i16)>(&mem, 1, 3).unwrap();
+        assert_eq!(vals, [(1, 2, -3); 3]);
+        assert_eq!(size, 12);
+
+        store_many::<u64>(&mut mem, 19, &[]).unwrap();
+        assert_eq!(read_slice::<u64>(&mem, 19, 0).unwrap(), (vec![], 0));
+    }
+
+    #[test]
+    fn bounds() {
+        let mut mem = [0; 8];
+        assert!(store(&mut mem, 4, 0u32).is_ok());
+        assert_eq!(store(&mut mem, 5, 0u32), Err("out of bounds write"));
+        assert_eq!(store(&mut mem, -1, 0u8), Err("out of bounds write"));
+        assert_eq!(store(&mut mem, i32::MAX, 0u8), Err("out of bounds write"));
+        assert_eq!(
+            store_many(&mut mem, 0, &[0u16; 5]),
+            Err("out of bounds write")
+        );
+        assert_eq!(load::<u64>(&mem, 1), Err("out of bounds read"));
+        assert_eq!(load::<u8>(&mem, 8), Err("out of bounds read"));
+        assert_eq!(load::<u8>(&mem, i32::MIN), Err("out of bounds read"));
+        assert_eq!(read_slice::<u16>(&mem, 2, 4), Err("out of bounds read"));
+        assert_eq!(read_slice::<u8>(&mem, -1, 1), Err("out of bounds read"));
+        assert_eq!(
+            read_slice::<u64>(&mem, 0, i32::MAX),
+            Err("array too large to fit in wasm memory")
+        );
+        assert_eq!(mem, [0; 8]);
+    }
+
+    fn bytes_of(buf: &mut [u64]) -> &mut [u8] {
+        let len = mem::size_of_val(buf);
+        // SAFETY: every byte of a `u64` is initialized and `u8` has no
+        // alignment requirement.
+        unsafe { std::slice::from_raw_parts_mut(buf.as_mut_ptr().cast(), len) }
+    }
+}
@@ -41,7 +41,7 @@ impl<'a> BorrowChecker<'a> {
     }
 
     pub fn slice<T: AllBytesValid>(&mut self, ptr: i32, len: i32) -> Result<&'a [T], RuntimeError> {
-        let (ret, r) = self.get_slice(ptr, len)?;
+        let (ret, r) = self.get_slice(ptr, len).map_err(to_error)?;
         // SAFETY: We're promoting the valid lifetime of `ret` from a temporary
         // borrow on `self` to `'a` on this `BorrowChecker`. At the same time
         // we're recording that this is a persistent shared borrow (until this
@@ -57,7 +57,7 @@ impl<'a> BorrowChecker<'a> {
         ptr: i32,
         len: i32,
     ) -> Result<&'a mut [T], RuntimeError> {
-        let (ret, r) = self.get_slice_mut(ptr, len)?;
+        let (ret, r) = self.get_slice_mut(ptr, len).map_err(to_error)?;
         // SAFETY: see `slice` for how we're extending the lifetime by
         // recording the borrow here. Note that the `mut_borrows` list is
         // checked on both shared and mutable borrows in the future since a
@@ -71,10 +71,10 @@ impl<'a> BorrowChecker<'a> {
         &self,
         ptr: i32,
         len: i32,
-    ) -> Result<(&[T], Region), RuntimeError> {
+    ) -> Result<(&[T], Region), GuestError> {
         let r = self.region::<T>(ptr, len)?;
         if self.is_mut_borrowed(r) {
-            Err(to_error(GuestError::PtrBorrowed(r)))
+            Err(GuestError::PtrBorrowed(r))
         } else {
             Ok((
                 // SAFETY: invariants to uphold:
@@ -100,10 +100,10 @@ impl<'a> BorrowChecker<'a> {
         }
     }
 
-    fn get_slice_mut<T>(&mut self, ptr: i32, len: i32) -> Result<(&mut [T], Region), RuntimeError> {
+    fn get_slice_mut<T>(&mut self, ptr: i32, len: i32) -> Result<(&mut [T], Region), GuestError> {
         let r = self.region::<T>(ptr, len)?;
         if self.is_mut_borrowed(r) || self.is_shared_borrowed(r) {
-            Err(to_error(GuestError::PtrBorrowed(r)))
+            Err(GuestError::PtrBorrowed(r))
         } else {
             Ok((
                 // SAFETY: same as `get_slice`, except for that we're threading
@@ -119,13 +119,13 @@ impl<'a> BorrowChecker<'a> {
         }
     }
 
-    fn region<T>(&self, ptr: i32, len: i32) -> Result<Region, RuntimeError> {
+    fn region<T>(&self, ptr: i32, len: i32) -> Result<Region, GuestError> {
         assert_eq!(std::mem::align_of::<T>(), 1);
         let r = Region {
             start: ptr as u32,
             len: (len as u32)
                 .checked_mul(mem::size_of::<T>() as u32)
-                .ok_or_else(|| to_error(GuestError::PtrOverflow))?,
+                .ok_or(GuestError::PtrOverflow)?,
         };
         self.validate_contains(&r)?;
         Ok(r)
@@ -133,18 +133,18 @@ impl<'a> BorrowChecker<'a> {
 
     pub fn slice_str(&mut self, ptr: i32, len: i32) -> Result<&'a str, RuntimeError> {
         let bytes = self.slice(ptr, len)?;
-        std::str::from_utf8(bytes).map_err(to_error)
+        crate::utf8::str_from_utf8(bytes)
     }
 
-    fn validate_contains(&self, region: &Region) -> Result<(), RuntimeError> {
+    fn validate_contains(&self, region: &Region) -> Result<(), GuestError> {
         let end = region
             .start
             .checked_add(region.len)
-            .ok_or_else(|| to_error(GuestError::PtrOverflow))? as usize;
+            .ok_or(GuestError::PtrOverflow)? as usize;
         if end <= self.len {
             Ok(())
         } else {
-            Err(to_error(GuestError::PtrOutOfBounds(*region)))
+            Err(GuestError::PtrOutOfBounds(*region))
         }
     }
 
@@ -163,26 +163,23 @@ impl<'a> BorrowChecker<'a> {
 
 impl RawMem for BorrowChecker<'_> {
     fn store<T: Endian>(&mut self, offset: i32, val: T) -> Result<(), RuntimeError> {
-        let (slice, _) = self.get_slice_mut::<Le<T>>(offset, 1)?;
+        let (slice, _) = self.get_slice_mut::<Le<T>>(offset, 1).map_err(to_error)?;
         slice[0].set(val);
         Ok(())
     }
 
     fn store_many<T: Endian>(&mut self, offset: i32, val: &[T]) -> Result<(), RuntimeError> {
-        let (slice, _) = self.get_slice_mut::<Le<T>>(
-            offset,
-            val.len()
-                .try_into()
-                .map_err(|_| to_error(GuestError::PtrOverflow))?,
-        )?;
-        for (slot, val) in slice.iter_mut().zip(val) {
-            slot.set(*val);
-        }
+        let len = val
+            .len()
+            .try_into()
+            .map_err(|_| to_error(GuestError::PtrOverflow))?;
+        let (slice, _) = self.get_slice_mut::<Le<T>>(offset, len).map_err(to_error)?;
+        Le::store_slice(slice, val);
         Ok(())
     }
 
     fn load<T: Endian>(&self, offset: i32) -> Result<T, RuntimeError> {
-        let (slice, _) = self.get_slice::<Le<T>>(offset, 1)?;
+        let (slice, _) = self.get_slice::<Le<T>>(offset, 1).map_err(to_error)?;
         Ok(slice[0].get())
     }
 }
@@ -317,4 +314,68 @@ mod test {
         bc.slice_mut::<u8>(0, 0).unwrap();
         bc.slice::<u8>(0, 1).unwrap();
     }
+
+    #[test]
+    fn disjoint_access() {
+        let mut bytes = [0; 32];
+        let mut bc = BorrowChecker::new(&mut bytes);
+        let a = bc.slice_mut::<u8>(0, 8).unwrap();
+        let b = bc.slice_mut::<Le<u32>>(9, 2).unwrap();
+        bc.store(20, 0x0102_0304u32).unwrap();
+        let c = bc.slice::<u8>(20, 4).unwrap();
+
+        // Use every borrow after the others were made, so that Miri checks
+        // they don't invalidate each other.
+        a.fill(1);
+        b[1].set(u32::MAX);
+        assert_eq!(c, [4, 3, 2, 1]);
+        assert_eq!(bc.load::<u8>(8).unwrap(), 0);
+        assert_eq!(b[0].get(), 0);
+        assert_eq!(a, [1; 8]);
+        drop(bc);
+
+        assert_eq!(bytes[..9], [1, 1, 1, 1, 1, 1, 1, 1, 0]);
+        assert_eq!(bytes[13..17], [0xff; 4]);
+    }
+
+    #[test]
+    fn store_many() {
+        let mut bytes = [0; 11];
+        let mut bc = BorrowChecker::new(&mut bytes);
+        bc.store_many(3, &[(1u8, -2i16), (3, 4)]).unwrap();
+        let vals = bc.slice::<Le<(u8, i16)>>(3, 2).unwrap();
+        assert_eq!(vals[0].get(), (1, -2));
+        assert_eq!(vals[1].get(), (3, 4));
+        bc.store_many::<u64>(11, &[]).unwrap();
+    }
+
+    #[test]
+    fn errors() {
+        let mut bytes = [0; 16];
+        let mut bc = BorrowChecker::new(&mut bytes);
+        assert_eq!(
+            bc.get_slice::<u8>(8, 9).unwrap_err(),
+            GuestError::PtrOutOfBounds(Region { start: 8, len: 9 })
+        );
+        assert_eq!(
+            bc.get_slice::<u8>(-1, 2).unwrap_err(),
+            GuestError::PtrOverflow
+        );
+        assert_eq!(
+            bc.get_slice::<Le<u64>>(0, i32::MAX).unwrap_err(),
+            GuestError::PtrOverflow
+        );
+        assert_eq!(
+            bc.get_slice_mut::<Le<u32>>(13, 1).unwrap_err(),
+            GuestError::PtrOutOfBounds(Region { start: 13, len: 4 })
+        );
+
+        bc.slice_mut::<u8>(4, 4).unwrap();
+        assert_eq!(
+            bc.get_slice::<Le<u16>>(7, 1).unwrap_err(),
+            GuestError::PtrBorrowed(Region { start: 7, len: 2 })
+        );
+        bc.get_slice::<u8>(8, 8).unwrap();
+        assert!(bc.get_slice_mut::<u8>(8, 8).is_ok());
+    }
 }
@@ -0,0 +1,426 @@
+//! Call logs written by the `*Recorder` wrappers generated with the `record`
+//! option, and replayed by the generated `replay_*` functions.
+//!
+//! # Log format
+//!
+//! A log is a header followed by the recorded calls, with every integer in
+//! little-endian:
+//!
+//! * the magic bytes `WAIL`, the `u32` version of the format,
+//!   [`LOG_VERSION`], and the `u64` hash of the interface the calls were
+//!   made through, so logs aren't replayed against another version of it;
+//! * the `u32` number of calls, then each call as the name of the function
+//!   as a string, its arguments in order and finally its result. The result
+//!   of a call to an export is an `expected<T, string>`, holding the message
+//!   of the error if the call trapped. Calls to functions whose arguments or
+//!   result can't be recorded, such as those taking handles, are logged as
+//!   the name of the function alone.
+//!
+//! Values are encoded by their interface type:
+//!
+//! * `bool`s as one byte, `0` or `1`, and other integers at their full
+//!   width;
+//! * `float32` and `float64` as the bits of the IEEE 754 value, so NaN
+//!   payloads survive, and `char`s as the `u32` of the scalar value;
+//! * strings as their `u32` length in bytes followed by the UTF-8, and lists
+//!   as their `u32` length followed by each element;
+//! * records and tuples as each field in order, and `unit` as nothing;
+//! * options and expecteds as a byte, `0` for `none` and `ok`, followed by
+//!   the payload, and variants, unions and enums as the `u32` index of the
+//!   case followed by its payload;
+//! * flags as their bits, in the smallest of `u8`, `u16`, `u32`, `u64` and
+//!   `u128` that fits them.
+
+use std::fmt;
+use thiserror::Error;
+
+/// The version of the log format written by [`encode_log`].
+pub const LOG_VERSION: u32 = 1;
+
+const MAGIC: &[u8; 4] = b"WAIL";
+
+/// A value which can be written to and read back from a call log, in the
+/// format described in the [module documentation](self).
+///
+/// This is implemented by the bindings for the calls and types of the
+/// interfaces generated with the `record` option.
+pub trait LogValue: Sized {
+    /// Appends the encoding of `self` to `out`.
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// Reads a value from the front of `input`, advancing it past the value.
+    fn decode(input: &mut &[u8]) -> Result<Self, LogError>;
+}
+
+/// Error reading a call log.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum LogError {
+    #[error("not a call log")]
+    NotALog,
+    #[error("call log has version {0} of the format but version {LOG_VERSION} is supported")]
+    Version(u32),
+    #[error("call log was recorded through a different version of the interface")]
+    InterfaceMismatch,
+    #[error("call log ends in the middle of a value")]
+    Truncated,
+    #[error("call log has trailing data after the last call")]
+    TrailingData,
+    #[error("call log has an invalid {0}")]
+    Invalid(&'static str),
+    #[error("call log has a call to unknown function `{0}`")]
+    UnknownFunction(String),
+}
+
+/// Encodes `calls`, made through the interface with the hash
+/// `interface_hash`, into a log.
+pub fn encode_log<T: LogValue>(interface_hash: u64, calls: &[T]) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    LOG_VERSION.encode(&mut out);
+    interface_hash.encode(&mut out);
+    (calls.len() as u32).encode(&mut out);
+    for call in calls {
+        call.encode(&mut out);
+    }
+    out
+}
+
+/// Decodes the calls of a log written by [`encode_log`], which has to have
+/// been recorded through the interface with the hash `interface_hash`.
+pub fn decode_log<T: LogValue>(interface_hash: u64, bytes: &[u8]) -> Result<Vec<T>, LogError> {
+    let mut input = bytes
+        .strip_prefix(MAGIC.as_slice())
+        .ok_or(LogError::NotALog)?;
+    let version = u32::decode(&mut input)?;
+    if version != LOG_VERSION {
+        return Err(LogError::Version(version));
+    }
+    if u64::decode(&mut input)? != interface_hash {
+        return Err(LogError::InterfaceMismatch);
+    }
+    let len = u32::decode(&mut input)?;
+    // Don't trust the length for the capacity, every call takes at least the
+    // four bytes of the length of its name.
+    let mut calls = Vec::with_capacity((len as usize).min(input.len() / 4));
+    for _ in 0..len {
+        calls.push(T::decode(&mut input)?);
+    }
+    if !input.is_empty() {
+        return Err(LogError::TrailingData);
+    }
+    Ok(calls)
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], LogError> {
+    if input.len() < len {
+        return Err(LogError::Truncated);
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(bytes)
+}
+
+macro_rules! ints {
+    ($($t:ident)*) => ($(
+        impl LogValue for $t {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn decode(input: &mut &[u8]) -> Result<Self, LogError> {
+                let bytes = take(input, std::mem::size_of::<$t>())?;
+                Ok($t::from_le_bytes(bytes.try_into().unwrap()))
+            }
+        }
+    )*)
+}
+
+ints!(u8 u16 u32 u64 u128 i8 i16 i32 i64);
+
+macro_rules! floats {
+    ($($t:ident $bits:ident)*) => ($(
+        impl LogValue for $t {
+            fn encode(&self, out: &mut Vec<u8>) {
+                self.to_bits().encode(out);
+            }
+
+            fn decode(input: &mut &[u8]) -> Result<Self, LogError> {
+                Ok($t::from_bits($bits::decode(input)?))
+            }
+        }
+    )*)
+}
+
+floats!(f32 u32 f64 u64);
+
+impl LogValue for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u8).encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, LogError> {
+        match u8::decode(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(LogError::Invalid("bool")),
+        }
+    }
+}
+
+impl LogValue for char {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u32).encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, LogError> {
+        char::from_u32(u32::decode(input)?).ok_or(LogError::Invalid("char"))
+    }
+}
+
+impl LogValue for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u32).encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, LogError> {
+        let len = u32::decode(input)?;
+        let bytes = take(input, len as usize)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| LogError::Invalid("string"))
+    }
+}
+
+impl<T: LogValue> LogValue for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u32).encode(out);
+        for item in self {
+            item.encode(out);
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, LogError> {
+        let len = u32::decode(input)?;
+        // As in `decode_log`, elements may be as small as a `unit` so the
+        // length only bounds the capacity by what's left of the input.
+        let mut items = Vec::with_capacity((len as usize).min(input.len()));
+        for _ in 0..len {
+            items.push(T::decode(input)?);
+        }
+        Ok(items)
+    }
+}
+
+impl<T: LogValue> LogValue for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            None => 0u8.encode(out),
+            Some(val) => {
+                1u8.encode(out);
+                val.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, LogError> {
+        match u8::decode(input)? {
+            0 => Ok(None),
+            1 => Ok(Some(T::decode(input)?)),
+            _ => Err(LogError::Invalid("option")),
+        }
+    }
+}
+
+impl<T: LogValue, E: LogValue> LogValue for Result<T, E> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Ok(val) => {
+                0u8.encode(out);
+                val.encode(out);
+            }
+            Err(err) => {
+                1u8.encode(out);
+                err.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, LogError> {
+        match u8::decode(input)? {
+            0 => Ok(Ok(T::decode(input)?)),
+            1 => Ok(Err(E::decode(input)?)),
+            _ => Err(LogError::Invalid("expected")),
+        }
+    }
+}
+
+macro_rules! tuples {
+    ($(($($t:ident)*))*) => ($(
+        #[allow(non_snake_case)]
+        impl<$($t: LogValue,)*> LogValue for ($($t,)*) {
+            fn encode(&self, _out: &mut Vec<u8>) {
+                let ($($t,)*) = self;
+                $($t.encode(_out);)*
+            }
+
+            fn decode(_input: &mut &[u8]) -> Result<Self, LogError> {
+                Ok(($($t::decode(_input)?,)*))
+            }
+        }
+    )*)
+}
+
+tuples! {
+    ()
+    (A)
+    (A B)
+    (A B C)
+    (A B C D)
+    (A B C D E)
+    (A B C D E F)
+    (A B C D E F G)
+    (A B C D E F G H)
+    (A B C D E F G H I)
+    (A B C D E F G H I J)
+    (A B C D E F G H I J K)
+    (A B C D E F G H I J K L)
+}
+
+/// Error returned by generated `replay_*` functions when a host
+/// implementation returns something other than what was recorded.
+///
+/// Values are compared through their encoding in the log, so floats are
+/// equal when their bits are, NaNs included. `expected` and `actual` are
+/// their `Debug` representations.
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("call #{index} to `{function}` returned {actual} but {expected} was recorded")]
+pub struct ReplayMismatch {
+    pub index: usize,
+    pub function: &'static str,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl ReplayMismatch {
+    #[doc(hidden)]
+    pub fn check<T: LogValue + fmt::Debug>(
+        index: usize,
+        function: &'static str,
+        expected: &T,
+        actual: &T,
+    ) -> Result<(), ReplayMismatch> {
+        let encode = |val: &T| {
+            let mut out = Vec::new();
+            val.encode(&mut out);
+            out
+        };
+        if encode(expected) == encode(actual) {
+            Ok(())
+        } else {
+            Err(ReplayMismatch {
+                index,
+                function,
+                expected: format!("{:?}", expected),
+                actual: format!("{:?}", actual),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn round_trip<T: LogValue + fmt::Debug + PartialEq>(val: T) {
+        let mut out = Vec::new();
+        val.encode(&mut out);
+        let mut input = &out[..];
+        assert_eq!(T::decode(&mut input).unwrap(), val);
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn values() {
+        round_trip(true);
+        round_trip(-3i8);
+        round_trip(u64::MAX);
+        round_trip(u128::MAX - 1);
+        round_trip(-0.0f64);
+        round_trip('\u{10ffff}');
+        round_trip("h\u{e9}llo".to_string());
+        round_trip(vec![Some(1u16), None]);
+        round_trip(Ok::<_, String>(vec![(); 3]));
+        round_trip(Err::<u8, _>(("x".to_string(), 1.5f32)));
+        round_trip(((), (1u8,), (2u8, 3u32)));
+    }
+
+    #[test]
+    fn format() {
+        let mut out = Vec::new();
+        (true, 0x0102u16, "ab".to_string(), Some(1.0f32)).encode(&mut out);
+        assert_eq!(
+            out,
+            [1, 0x02, 0x01, 2, 0, 0, 0, b'a', b'b', 1, 0x00, 0x00, 0x80, 0x3f]
+        );
+    }
+
+    #[test]
+    fn nan() {
+        let nan = f32::from_bits(0x7fc0_1234);
+        let mut out = Vec::new();
+        nan.encode(&mut out);
+        assert_eq!(f32::decode(&mut &out[..]).unwrap().to_bits(), 0x7fc0_1234);
+
+        assert!(ReplayMismatch::check(0, "f", &nan, &nan).is_ok());
+        assert!(ReplayMismatch::check(0, "f", &f64::NAN, &-f64::NAN).is_err());
+        assert!(ReplayMismatch::check(0, "f", &0.0f64, &-0.0f64).is_err());
+    }
+
+    #[test]
+    fn log() {
+        let calls = vec![(1u32, "a".to_string()), (2, "b".to_string())];
+        let bytes = encode_log(0xfeed, &calls);
+        assert_eq!(&bytes[..8], b"WAIL\x01\0\0\0");
+        assert_eq!(decode_log::<(u32, String)>(0xfeed, &bytes), Ok(calls));
+
+        assert_eq!(
+            decode_log::<(u32, String)>(0xbeef, &bytes),
+            Err(LogError::InterfaceMismatch)
+        );
+        assert_eq!(
+            decode_log::<(u32, String)>(0xfeed, &bytes[..bytes.len() - 1]),
+            Err(LogError::Truncated)
+        );
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            decode_log::<(u32, String)>(0xfeed, &trailing),
+            Err(LogError::TrailingData)
+        );
+        let mut version = bytes;
+        version[4] = 2;
+        assert_eq!(
+            decode_log::<(u32, String)>(0xfeed, &version),
+            Err(LogError::Version(2))
+        );
+        assert_eq!(
+            decode_log::<(u32, String)>(0xfeed, b"wasm"),
+            Err(LogError::NotALog)
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(bool::decode(&mut &[2][..]), Err(LogError::Invalid("bool")));
+        assert_eq!(
+            char::decode(&mut &0xd800u32.to_le_bytes()[..]),
+            Err(LogError::Invalid("char"))
+        );
+        assert_eq!(
+            String::decode(&mut &[1, 0, 0, 0, 0xff][..]),
+            Err(LogError::Invalid("string"))
+        );
+        assert_eq!(
+            Vec::<u64>::decode(&mut &[0xff, 0xff, 0xff, 0xff][..]),
+            Err(LogError::Truncated)
+        );
+    }
+}
@@ -0,0 +1,25 @@
+//! UTF-8 validation of strings read from guest memory.
+//!
+//! With the `simdutf8` feature enabled this uses the SIMD-accelerated
+//! validator from the `simdutf8` crate, which is considerably faster for
+//! string-heavy interfaces, and otherwise the one in the standard library.
+
+use wasmer::RuntimeError;
+
+#[cfg(feature = "simdutf8")]
+use simdutf8::compat::from_utf8 as validate;
+#[cfg(not(feature = "simdutf8"))]
+use std::str::from_utf8 as validate;
+
+pub(crate) fn str_from_utf8(bytes: &[u8]) -> Result<&str, RuntimeError> {
+    validate(bytes).map_err(|e| RuntimeError::new(e.to_string()))
+}
+
+/// Converts bytes copied out of guest memory into a `String`.
+pub fn string_from_utf8(bytes: Vec<u8>) -> Result<String, RuntimeError> {
+    match validate(&bytes) {
+        // SAFETY: the bytes were just validated.
+        Ok(_) => Ok(unsafe { String::from_utf8_unchecked(bytes) }),
+        Err(_) => Err(RuntimeError::new("invalid utf-8")),
+    }
+}
@@ -4,7 +4,9 @@ use proc_macro::TokenStream;
 use syn::parse::{Error, Parse, ParseStream, Result};
 use syn::punctuated::Punctuated;
 use syn::{token, Token};
-use wai_bindgen_gen_core::{wai_parser::Interface, Direction, Files, Generator};
+use wai_bindgen_gen_core::{
+    parse_interface_arg, wai_parser::Interface, Direction, Files, Filter, Generator,
+};
 use wai_bindgen_gen_wasmtime::Async;
 
 /// Generate code to support consuming the given interfaces, importaing them
@@ -25,9 +27,11 @@ fn run(input: TokenStream, dir: Direction) -> TokenStream {
     let input = syn::parse_macro_input!(input as Opts);
     let mut gen = input.opts.build();
     let mut files = Files::default();
+    let mut interfaces = input.interfaces;
+    input.filter.apply(&mut interfaces);
     let (imports, exports) = match dir {
-        Direction::Import => (input.interfaces, vec![]),
-        Direction::Export => (vec![], input.interfaces),
+        Direction::Import => (interfaces, vec![]),
+        Direction::Export => (vec![], interfaces),
     };
     gen.generate_all(&imports, &exports, &mut files);
 
@@ -57,11 +61,14 @@ struct Opts {
     opts: wai_bindgen_gen_wasmtime::Opts,
     interfaces: Vec<Interface>,
     files: Vec<String>,
+    filter: Filter,
 }
 
 mod kw {
     syn::custom_keyword!(src);
     syn::custom_keyword!(paths);
+    syn::custom_keyword!(only);
+    syn::custom_keyword!(skip);
     syn::custom_keyword!(custom_error);
 }
 
@@ -70,6 +77,7 @@ impl Parse for Opts {
         let call_site = proc_macro2::Span::call_site();
         let mut opts = wai_bindgen_gen_wasmtime::Opts::default();
         let mut files = Vec::new();
+        let mut filter = Filter::default();
         opts.tracing = cfg!(feature = "tracing");
 
         let interfaces = if input.peek(token::Brace) {
@@ -79,7 +87,9 @@ impl Parse for Opts {
             let fields = Punctuated::<ConfigField, Token![,]>::parse_terminated(&content)?;
             for field in fields.into_pairs() {
                 match field.into_value() {
-                    ConfigField::Interfaces(v) => interfaces = v,
+                    ConfigField::Interfaces(v) => interfaces.extend(v),
+                    ConfigField::Only(v) => filter.only = v,
+                    ConfigField::Skip(v) => filter.skip = v,
                     ConfigField::Async(v) => opts.async_ = v,
                     ConfigField::CustomError(v) => opts.custom_error = v,
                 }
@@ -92,29 +102,40 @@ impl Parse for Opts {
             }
             interfaces
         } else {
-            while !input.is_empty() {
-                let s = input.parse::<syn::LitStr>()?;
-                files.push(s.value());
-            }
             let mut interfaces = Vec::new();
             let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
-            for path in files.iter() {
-                let path = manifest_dir.join(path);
-                let iface = Interface::parse_file(path).map_err(|e| Error::new(call_site, e))?;
-                interfaces.push(iface);
+            while !input.is_empty() {
+                let s = input.parse::<syn::LitStr>()?;
+                let parsed = parse_interface_arg(&manifest_dir, &s.value())
+                    .map_err(|e| Error::new(s.span(), e))?;
+                for (path, iface) in parsed {
+                    interfaces.push(iface);
+                    files.push(path.display().to_string());
+                }
             }
             interfaces
         };
+        for (i, iface) in interfaces.iter().enumerate() {
+            if interfaces[..i].iter().any(|prev| prev.name == iface.name) {
+                return Err(Error::new(
+                    call_site,
+                    format!("interface `{}` is specified more than once", iface.name),
+                ));
+            }
+        }
         Ok(Opts {
             opts,
             interfaces,
             files,
+            filter,
         })
     }
 }
 
 enum ConfigField {
     Interfaces(Vec<Interface>),
+    Only(Vec<String>),
+    Skip(Vec<String>),
     Async(wai_bindgen_gen_wasmtime::Async),
     CustomError(bool),
 }
@@ -136,16 +157,14 @@ impl Parse for ConfigField {
             input.parse::<kw::paths>()?;
             input.parse::<Token![:]>()?;
             let paths;
-            let bracket = syn::bracketed!(paths in input);
+            syn::bracketed!(paths in input);
             let paths = Punctuated::<syn::LitStr, Token![,]>::parse_terminated(&paths)?;
-            let values = paths.iter().map(|s| s.value()).collect::<Vec<_>>();
             let mut interfaces = Vec::new();
             let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
-            for value in &values {
-                let value = manifest_dir.join(value);
-                let interface =
-                    Interface::parse_file(value).map_err(|e| Error::new(bracket.span, e))?;
-                interfaces.push(interface);
+            for path in paths.iter() {
+                let parsed = parse_interface_arg(&manifest_dir, &path.value())
+                    .map_err(|e| Error::new(path.span(), e))?;
+                interfaces.extend(parsed.into_iter().map(|(_, iface)| iface));
             }
             Ok(ConfigField::Interfaces(interfaces))
         } else if l.peek(token::Async) {
@@ -172,8 +191,23 @@ impl Parse for ConfigField {
             Ok(ConfigField::CustomError(
                 input.parse::<syn::LitBool>()?.value,
             ))
+        } else if l.peek(kw::only) {
+            input.parse::<kw::only>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::Only(parse_patterns(input)?))
+        } else if l.peek(kw::skip) {
+            input.parse::<kw::skip>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::Skip(parse_patterns(input)?))
         } else {
             Err(l.error())
         }
     }
 }
+
+fn parse_patterns(input: ParseStream<'_>) -> Result<Vec<String>> {
+    let patterns;
+    syn::bracketed!(patterns in input);
+    let patterns = Punctuated::<syn::LitStr, Token![,]>::parse_terminated(&patterns)?;
+    Ok(patterns.iter().map(|s| s.value()).collect())
+}
@@ -0,0 +1,52 @@
+#![no_main]
+
+use libfuzzer_sys::arbitrary::{Arbitrary, Unstructured};
+use libfuzzer_sys::fuzz_target;
+use wai_bindgen_gen_core::{Direction, Generator};
+use wai_bindgen_gen_rust_wasm::RustWasm;
+use wai_bindgen_gen_wasmer::Wasmer;
+use wai_parser::Interface;
+use wai_parser_fuzz::abi;
+use wai_parser_fuzz::Document;
+
+fuzz_target!(|data: &[u8]| {
+    let mut u = Unstructured::new(data);
+    let doc = match Document::arbitrary(&mut u) {
+        Ok(doc) => doc,
+        Err(_) => return,
+    };
+    let iface = match Interface::parse("input.wai", &doc.to_string()) {
+        Ok(iface) => iface,
+        Err(_) => return,
+    };
+
+    // The host is `gen-wasmer` calling the interface's exports, which it
+    // generates as imports of the host, and the guest is `gen-rust-wasm`
+    // implementing them.
+    let mut host = Wasmer::new();
+    host.preprocess_one(&iface, Direction::Import);
+    let mut guest = RustWasm::new();
+    guest.preprocess_one(&iface, Direction::Export);
+
+    for func in iface.functions.iter() {
+        if func.is_async
+            || !func.params.iter().all(|(_, ty)| abi::supported(&iface, ty))
+            || !abi::supported(&iface, &func.result)
+        {
+            continue;
+        }
+        let args = func
+            .params
+            .iter()
+            .map(|(_, ty)| abi::arbitrary_val(&iface, ty, &mut u))
+            .collect::<Result<Vec<_>, _>>();
+        let result = abi::arbitrary_val(&iface, &func.result, &mut u);
+        if let (Ok(args), Ok(result)) = (args, result) {
+            host.with_function_bindgen(&iface, func, |host| {
+                guest.with_function_bindgen(&iface, func, |guest| {
+                    abi::round_trip(&iface, func, host, guest, args, result)
+                })
+            });
+        }
+    }
+});
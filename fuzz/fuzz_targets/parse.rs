@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wai_parser::Interface;
+
+fuzz_target!(|data: &[u8]| {
+    let input = match std::str::from_utf8(data) {
+        Ok(s) => s,
+        Err(_) => return,
+    };
+    // The file name picks the format, so also feed the input through the
+    // markdown extraction step.
+    let _ = Interface::parse("input.wai", input);
+    let _ = Interface::parse("input.wai.md", input);
+});
@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wai_parser::Interface;
+use wai_parser_fuzz::Document;
+
+fuzz_target!(|doc: Document| {
+    let input = doc.to_string();
+    // Documents are well-formed syntax-wise but may still reference undefined
+    // names, define duplicates, or be recursive, all of which must be
+    // reported as errors rather than panics.
+    let _ = Interface::parse("input.wai", &input);
+});
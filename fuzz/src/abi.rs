@@ -0,0 +1,1090 @@
+//! An interpreter for the `Instruction` streams produced by
+//! `wai_parser::abi`, used to round-trip values through a host and a guest
+//! sharing a simulated linear memory.
+//!
+//! Code generators are driven through `Bindgen` and mostly translate each
+//! instruction one-to-one into source code. Here each side's generator is
+//! still driven as usual, but the instructions it accepts are also recorded
+//! into a small program and executed, so a host calling a guest export can be
+//! simulated in-process: the host lowers its arguments, the guest lifts them,
+//! lowers its result, and the host lifts that back. Any disagreement in layout
+//! between the two generators shows up as a value that doesn't survive the
+//! trip.
+
+use std::collections::BTreeMap;
+use wai_parser::abi::{AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmType};
+use wai_parser::{Function, Interface, SizeAlign, Type, TypeDefKind, TypeId};
+
+/// A value flowing through an ABI program, either an interface value or a
+/// core wasm value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Val {
+    Unit,
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    S8(i8),
+    S16(i16),
+    S32(i32),
+    S64(i64),
+    /// Stored as bits so that NaN payloads compare equal.
+    Float32(u32),
+    /// Stored as bits so that NaN payloads compare equal.
+    Float64(u64),
+    Char(char),
+    String(String),
+    List(Vec<Val>),
+    /// Records and tuples.
+    Fields(Vec<Val>),
+    Flags(Vec<bool>),
+    Enum(u32),
+    /// Variants, unions, options (`none` is case 0) and expecteds (`ok` is
+    /// case 0).
+    Case(u32, Box<Val>),
+
+    I32(i32),
+    I64(i64),
+    F32(u32),
+    F64(u64),
+}
+
+/// Reason for an ABI program to fail at runtime.
+#[derive(Debug)]
+pub struct Trap(pub String);
+
+macro_rules! trap {
+    ($($arg:tt)*) => { return Err(Trap(format!($($arg)*))) };
+}
+
+type Reg = usize;
+
+/// A block of recorded instructions, plus the registers holding its results.
+#[derive(Default)]
+struct Block {
+    ops: Vec<Op>,
+    results: Vec<Reg>,
+}
+
+struct Op {
+    kind: OpKind,
+    operands: Vec<Reg>,
+    results: Vec<Reg>,
+    /// Blocks consumed by this instruction, e.g. one per variant case.
+    blocks: Vec<Block>,
+}
+
+/// Owned copy of the parts of an `Instruction` needed to execute it.
+enum OpKind {
+    GetArg(usize),
+    I32Const(i32),
+    Bitcasts(Vec<Cast>),
+    ConstZero(Vec<WasmType>),
+    Load(Mem, i32),
+    Store(Mem, i32),
+    Lower,
+    Lift(Type),
+    UnitLower,
+    ListCanonLower(Type),
+    ListCanonLift(Type),
+    StringLower,
+    StringLift,
+    /// Size and alignment of the elements in the recording side's layout.
+    ListLower(usize, usize),
+    /// Size of the elements in the recording side's layout.
+    ListLift(usize),
+    IterElem,
+    IterBasePointer,
+    FieldsLower,
+    FieldsLift,
+    FlagsLower(usize, usize),
+    FlagsLift(usize),
+    VariantPayloadName,
+    VariantLower,
+    VariantLift,
+    EnumLower,
+    EnumLift(usize),
+    CallWasm,
+    CallInterface,
+    Return,
+    Malloc(usize, usize),
+    Free,
+    ReturnPointer(usize, usize),
+}
+
+#[derive(Clone, Copy)]
+enum Mem {
+    I32,
+    I32_8U,
+    I32_8S,
+    I32_16U,
+    I32_16S,
+    I64,
+    F32,
+    F64,
+    I32_8,
+    I32_16,
+}
+
+#[derive(Clone, Copy)]
+enum Cast {
+    F32ToI32,
+    F64ToI64,
+    I32ToI64,
+    F32ToI64,
+    I32ToF32,
+    I64ToF64,
+    I64ToI32,
+    I64ToF32,
+    None,
+}
+
+/// A recorded ABI program for one side of a call.
+pub struct Program {
+    body: Block,
+}
+
+struct Recorder<'a> {
+    /// The generator whose lifting and lowering is being recorded. Its
+    /// layout and list copying decisions are used as is, and it's fed every
+    /// instruction so that it panics on anything it doesn't support.
+    gen: &'a mut dyn Bindgen<Operand = String>,
+    /// The generator's own operand for each register.
+    names: BTreeMap<Reg, String>,
+    next_reg: Reg,
+    blocks: Vec<Block>,
+    finished: Vec<Block>,
+}
+
+impl Program {
+    /// Records the program that `gen` is driven through for `func` with
+    /// `variant`/`lift_lower`.
+    pub fn record(
+        iface: &Interface,
+        variant: AbiVariant,
+        lift_lower: LiftLower,
+        func: &Function,
+        gen: &mut dyn Bindgen<Operand = String>,
+    ) -> Program {
+        let mut recorder = Recorder {
+            gen,
+            names: BTreeMap::new(),
+            next_reg: 0,
+            blocks: vec![Block::default()],
+            finished: Vec::new(),
+        };
+        iface.call(variant, lift_lower, func, &mut recorder);
+        assert!(recorder.finished.is_empty());
+        Program {
+            body: recorder.blocks.pop().unwrap(),
+        }
+    }
+}
+
+impl Recorder<'_> {
+    fn reg(&mut self) -> Reg {
+        self.next_reg += 1;
+        self.next_reg
+    }
+
+    fn names_of(&self, regs: &[Reg]) -> Vec<String> {
+        regs.iter().map(|reg| self.names[reg].clone()).collect()
+    }
+
+    fn push_op(
+        &mut self,
+        kind: OpKind,
+        operands: Vec<Reg>,
+        nresults: usize,
+        nblocks: usize,
+    ) -> Vec<Reg> {
+        let results = (0..nresults).map(|_| self.reg()).collect::<Vec<_>>();
+        let blocks = self.finished.split_off(self.finished.len() - nblocks);
+        self.blocks.last_mut().unwrap().ops.push(Op {
+            kind,
+            operands,
+            results: results.clone(),
+            blocks,
+        });
+        results
+    }
+}
+
+impl Bindgen for Recorder<'_> {
+    type Operand = Reg;
+
+    fn emit(
+        &mut self,
+        iface: &Interface,
+        inst: &Instruction<'_>,
+        operands: &mut Vec<Reg>,
+        results: &mut Vec<Reg>,
+    ) {
+        use Instruction::*;
+
+        let cases = |ty: TypeId| match &iface.types[ty].kind {
+            TypeDefKind::Variant(v) => v.cases.len(),
+            TypeDefKind::Union(u) => u.cases.len(),
+            TypeDefKind::Option(_) | TypeDefKind::Expected(_) => 2,
+            _ => unreachable!(),
+        };
+        let (kind, nblocks) = match inst {
+            GetArg { nth } => (OpKind::GetArg(*nth), 0),
+            I32Const { val } => (OpKind::I32Const(*val), 0),
+            Bitcasts { casts } => (
+                OpKind::Bitcasts(
+                    casts
+                        .iter()
+                        .map(|c| match c {
+                            Bitcast::F32ToI32 => Cast::F32ToI32,
+                            Bitcast::F64ToI64 => Cast::F64ToI64,
+                            Bitcast::I32ToI64 => Cast::I32ToI64,
+                            Bitcast::F32ToI64 => Cast::F32ToI64,
+                            Bitcast::I32ToF32 => Cast::I32ToF32,
+                            Bitcast::I64ToF64 => Cast::I64ToF64,
+                            Bitcast::I64ToI32 => Cast::I64ToI32,
+                            Bitcast::I64ToF32 => Cast::I64ToF32,
+                            Bitcast::None => Cast::None,
+                        })
+                        .collect(),
+                ),
+                0,
+            ),
+            ConstZero { tys } => (OpKind::ConstZero(tys.to_vec()), 0),
+
+            I32Load { offset } => (OpKind::Load(Mem::I32, *offset), 0),
+            I32Load8U { offset } => (OpKind::Load(Mem::I32_8U, *offset), 0),
+            I32Load8S { offset } => (OpKind::Load(Mem::I32_8S, *offset), 0),
+            I32Load16U { offset } => (OpKind::Load(Mem::I32_16U, *offset), 0),
+            I32Load16S { offset } => (OpKind::Load(Mem::I32_16S, *offset), 0),
+            I64Load { offset } => (OpKind::Load(Mem::I64, *offset), 0),
+            F32Load { offset } => (OpKind::Load(Mem::F32, *offset), 0),
+            F64Load { offset } => (OpKind::Load(Mem::F64, *offset), 0),
+            I32Store { offset } => (OpKind::Store(Mem::I32, *offset), 0),
+            I32Store8 { offset } => (OpKind::Store(Mem::I32_8, *offset), 0),
+            I32Store16 { offset } => (OpKind::Store(Mem::I32_16, *offset), 0),
+            I64Store { offset } => (OpKind::Store(Mem::I64, *offset), 0),
+            F32Store { offset } => (OpKind::Store(Mem::F32, *offset), 0),
+            F64Store { offset } => (OpKind::Store(Mem::F64, *offset), 0),
+
+            I32FromChar | I64FromU64 | I64FromS64 | I32FromU32 | I32FromS32 | I32FromU16
+            | I32FromS16 | I32FromU8 | I32FromS8 | F32FromFloat32 | F64FromFloat64
+            | I32FromBool => (OpKind::Lower, 0),
+            S8FromI32 => (OpKind::Lift(Type::S8), 0),
+            U8FromI32 => (OpKind::Lift(Type::U8), 0),
+            S16FromI32 => (OpKind::Lift(Type::S16), 0),
+            U16FromI32 => (OpKind::Lift(Type::U16), 0),
+            S32FromI32 => (OpKind::Lift(Type::S32), 0),
+            U32FromI32 => (OpKind::Lift(Type::U32), 0),
+            S64FromI64 => (OpKind::Lift(Type::S64), 0),
+            U64FromI64 => (OpKind::Lift(Type::U64), 0),
+            CharFromI32 => (OpKind::Lift(Type::Char), 0),
+            Float32FromF32 => (OpKind::Lift(Type::Float32), 0),
+            Float64FromF64 => (OpKind::Lift(Type::Float64), 0),
+            BoolFromI32 => (OpKind::Lift(Type::Bool), 0),
+            UnitLift => (OpKind::Lift(Type::Unit), 0),
+            UnitLower => (OpKind::UnitLower, 0),
+
+            ListCanonLower { element, .. } => (OpKind::ListCanonLower(**element), 0),
+            ListCanonLift { element, .. } => (OpKind::ListCanonLift(**element), 0),
+            StringLower { .. } => (OpKind::StringLower, 0),
+            StringLift { .. } => (OpKind::StringLift, 0),
+            ListLower { element, .. } => {
+                let sizes = self.gen.sizes();
+                (
+                    OpKind::ListLower(sizes.size(element), sizes.align(element)),
+                    1,
+                )
+            }
+            ListLift { element, .. } => (OpKind::ListLift(self.gen.sizes().size(element)), 1),
+            IterElem { .. } => (OpKind::IterElem, 0),
+            IterBasePointer => (OpKind::IterBasePointer, 0),
+
+            RecordLower { .. } | TupleLower { .. } => (OpKind::FieldsLower, 0),
+            RecordLift { .. } | TupleLift { .. } => (OpKind::FieldsLift, 0),
+            FlagsLower { flags, .. } => (
+                OpKind::FlagsLower(flags.flags.len(), flags.repr().count()),
+                0,
+            ),
+            FlagsLift { flags, .. } => (OpKind::FlagsLift(flags.flags.len()), 0),
+
+            VariantPayloadName => (OpKind::VariantPayloadName, 0),
+            VariantLower { ty, .. }
+            | UnionLower { ty, .. }
+            | OptionLower { ty, .. }
+            | ExpectedLower { ty, .. } => (OpKind::VariantLower, cases(*ty)),
+            VariantLift { ty, .. }
+            | UnionLift { ty, .. }
+            | OptionLift { ty, .. }
+            | ExpectedLift { ty, .. } => (OpKind::VariantLift, cases(*ty)),
+            EnumLower { .. } => (OpKind::EnumLower, 0),
+            EnumLift { enum_, .. } => (OpKind::EnumLift(enum_.cases.len()), 0),
+
+            CallWasm { .. } => (OpKind::CallWasm, 0),
+            CallInterface { .. } => (OpKind::CallInterface, 0),
+            Return { .. } => (OpKind::Return, 0),
+            Malloc { size, align, .. } => (OpKind::Malloc(*size, *align), 0),
+            Free { .. } => (OpKind::Free, 0),
+
+            other => panic!("unsupported instruction {:?}", other),
+        };
+        let mut names = self.names_of(operands);
+        let mut gen_results = Vec::new();
+        self.gen.emit(iface, inst, &mut names, &mut gen_results);
+        assert_eq!(
+            gen_results.len(),
+            inst.results_len(),
+            "results of {:?}",
+            inst
+        );
+        let regs = self.push_op(kind, std::mem::take(operands), inst.results_len(), nblocks);
+        self.names.extend(regs.iter().copied().zip(gen_results));
+        results.extend(regs);
+    }
+
+    fn return_pointer(&mut self, iface: &Interface, size: usize, align: usize) -> Reg {
+        let name = self.gen.return_pointer(iface, size, align);
+        let reg = self.push_op(OpKind::ReturnPointer(size, align), Vec::new(), 1, 0)[0];
+        self.names.insert(reg, name);
+        reg
+    }
+
+    fn push_block(&mut self) {
+        self.gen.push_block();
+        self.blocks.push(Block::default());
+    }
+
+    fn finish_block(&mut self, operands: &mut Vec<Reg>) {
+        let mut names = self.names_of(operands);
+        self.gen.finish_block(&mut names);
+        let mut block = self.blocks.pop().unwrap();
+        block.results = std::mem::take(operands);
+        self.finished.push(block);
+    }
+
+    fn sizes(&self) -> &SizeAlign {
+        self.gen.sizes()
+    }
+
+    fn is_list_canonical(&self, iface: &Interface, element: &Type) -> bool {
+        self.gen.is_list_canonical(iface, element)
+    }
+}
+
+/// Maximum size of the simulated linear memory.
+const MEMORY_LIMIT: usize = 16 << 20;
+
+/// Shared state of a simulated call: linear memory and live allocations.
+pub struct Machine<'a> {
+    iface: &'a Interface,
+    sizes: &'a SizeAlign,
+    pub memory: Vec<u8>,
+    allocations: BTreeMap<u32, usize>,
+}
+
+/// Implementation of the `CallWasm` or `CallInterface` instruction of a
+/// program.
+pub type Callee<'a> = dyn FnMut(&mut Machine<'_>, Vec<Val>) -> Result<Vec<Val>, Trap> + 'a;
+
+/// Per-program execution state.
+struct Frame<'a> {
+    regs: Vec<Option<Val>>,
+    args: Vec<Val>,
+    /// Values bound by the enclosing list or variant instruction.
+    iter_elem: Vec<Val>,
+    iter_base: Vec<i32>,
+    payload: Vec<Val>,
+    /// What `CallWasm` or `CallInterface` should do.
+    callee: &'a mut Callee<'a>,
+    returned: Option<Vec<Val>>,
+}
+
+impl<'a> Machine<'a> {
+    pub fn new(iface: &'a Interface, sizes: &'a SizeAlign) -> Machine<'a> {
+        Machine {
+            iface,
+            sizes,
+            // Keep address 0 unused so that it never looks like a valid
+            // allocation.
+            memory: vec![0; 8],
+            allocations: BTreeMap::new(),
+        }
+    }
+
+    /// Runs `program` with `args`, using `callee` for its `CallWasm` or
+    /// `CallInterface` instruction, and returns the values it returned.
+    pub fn run(
+        &mut self,
+        program: &Program,
+        args: Vec<Val>,
+        callee: &mut Callee<'_>,
+    ) -> Result<Vec<Val>, Trap> {
+        let mut frame = Frame {
+            regs: Vec::new(),
+            args,
+            iter_elem: Vec::new(),
+            iter_base: Vec::new(),
+            payload: Vec::new(),
+            callee,
+            returned: None,
+        };
+        self.block(&mut frame, &program.body)?;
+        match frame.returned {
+            Some(vals) => Ok(vals),
+            None => trap!("program did not return"),
+        }
+    }
+
+    /// Returns the allocations which haven't been freed yet.
+    pub fn live_allocations(&self) -> usize {
+        self.allocations.len()
+    }
+
+    fn block(&mut self, frame: &mut Frame<'_>, block: &Block) -> Result<Vec<Val>, Trap> {
+        for op in block.ops.iter() {
+            let operands = op
+                .operands
+                .iter()
+                .map(|r| frame.regs[*r].clone().expect("register not yet defined"))
+                .collect::<Vec<_>>();
+            let results = self.op(frame, op, operands)?;
+            assert_eq!(results.len(), op.results.len());
+            for (reg, val) in op.results.iter().zip(results) {
+                if frame.regs.len() <= *reg {
+                    frame.regs.resize(*reg + 1, None);
+                }
+                frame.regs[*reg] = Some(val);
+            }
+        }
+        Ok(block
+            .results
+            .iter()
+            .map(|r| frame.regs[*r].clone().expect("register not yet defined"))
+            .collect())
+    }
+
+    fn op(
+        &mut self,
+        frame: &mut Frame<'_>,
+        op: &Op,
+        mut operands: Vec<Val>,
+    ) -> Result<Vec<Val>, Trap> {
+        let one = |v: Val| Ok(vec![v]);
+        match &op.kind {
+            OpKind::GetArg(n) => one(frame.args[*n].clone()),
+            OpKind::I32Const(v) => one(Val::I32(*v)),
+            OpKind::Bitcasts(casts) => operands
+                .into_iter()
+                .zip(casts)
+                .map(|(v, c)| bitcast(v, *c))
+                .collect(),
+            OpKind::ConstZero(tys) => Ok(tys
+                .iter()
+                .map(|t| match t {
+                    WasmType::I32 => Val::I32(0),
+                    WasmType::I64 => Val::I64(0),
+                    WasmType::F32 => Val::F32(0),
+                    WasmType::F64 => Val::F64(0),
+                })
+                .collect()),
+            OpKind::Load(mem, offset) => {
+                let addr = address(&operands[0], *offset)?;
+                one(self.load(*mem, addr)?)
+            }
+            OpKind::Store(mem, offset) => {
+                let addr = address(&operands[1], *offset)?;
+                self.store(*mem, addr, &operands[0])?;
+                Ok(Vec::new())
+            }
+            OpKind::Lower => one(lower(operands.pop().unwrap())?),
+            OpKind::Lift(ty) => match ty {
+                Type::Unit => one(Val::Unit),
+                _ => one(lift(ty, operands.pop().unwrap())?),
+            },
+            OpKind::UnitLower => match &operands[0] {
+                Val::Unit => Ok(Vec::new()),
+                other => trap!("expected unit, found {:?}", other),
+            },
+
+            OpKind::StringLower => {
+                let s = match &operands[0] {
+                    Val::String(s) => s.clone(),
+                    other => trap!("expected string, found {:?}", other),
+                };
+                let ptr = self.alloc(s.len(), 1)?;
+                self.slice_mut(ptr, s.len())?.copy_from_slice(s.as_bytes());
+                Ok(vec![Val::I32(ptr as i32), Val::I32(s.len() as i32)])
+            }
+            OpKind::StringLift => {
+                let (ptr, len) = ptr_len(&operands)?;
+                let bytes = self.slice(ptr, len)?.to_vec();
+                self.free(ptr)?;
+                match String::from_utf8(bytes) {
+                    Ok(s) => one(Val::String(s)),
+                    Err(e) => trap!("invalid utf-8: {}", e),
+                }
+            }
+            OpKind::ListCanonLower(element) => {
+                let items = list(&operands[0])?;
+                let (size, align) = self.native_layout(element);
+                let ptr = self.alloc(size * items.len(), align)?;
+                for (i, item) in items.iter().enumerate() {
+                    self.write_native(element, ptr + (i * size) as u32, item)?;
+                }
+                Ok(vec![Val::I32(ptr as i32), Val::I32(items.len() as i32)])
+            }
+            OpKind::ListCanonLift(element) => {
+                let (ptr, len) = ptr_len(&operands)?;
+                let (size, _) = self.native_layout(element);
+                let items = (0..len)
+                    .map(|i| self.read_native(element, ptr + (i * size) as u32))
+                    .collect::<Result<_, _>>()?;
+                self.free(ptr)?;
+                one(Val::List(items))
+            }
+            OpKind::ListLower(size, align) => {
+                let size = *size;
+                let items = list(&operands[0])?;
+                let ptr = self.alloc(size * items.len(), *align)?;
+                for (i, item) in items.iter().enumerate() {
+                    frame.iter_elem.push(item.clone());
+                    frame.iter_base.push((ptr as usize + i * size) as i32);
+                    self.block(frame, &op.blocks[0])?;
+                    frame.iter_elem.pop();
+                    frame.iter_base.pop();
+                }
+                Ok(vec![Val::I32(ptr as i32), Val::I32(items.len() as i32)])
+            }
+            OpKind::ListLift(size) => {
+                let size = *size;
+                let (ptr, len) = ptr_len(&operands)?;
+                let mut items = Vec::new();
+                for i in 0..len {
+                    frame.iter_base.push((ptr as usize + i * size) as i32);
+                    items.extend(self.block(frame, &op.blocks[0])?);
+                    frame.iter_base.pop();
+                }
+                self.free(ptr)?;
+                one(Val::List(items))
+            }
+            OpKind::IterElem => one(frame.iter_elem.last().unwrap().clone()),
+            OpKind::IterBasePointer => one(Val::I32(*frame.iter_base.last().unwrap())),
+
+            OpKind::FieldsLower => match operands.pop().unwrap() {
+                Val::Fields(fields) => Ok(fields),
+                other => trap!("expected record or tuple, found {:?}", other),
+            },
+            OpKind::FieldsLift => one(Val::Fields(operands)),
+            OpKind::FlagsLower(n, words) => {
+                let flags = match &operands[0] {
+                    Val::Flags(f) if f.len() == *n => f,
+                    other => trap!("expected {} flags, found {:?}", n, other),
+                };
+                let mut bits = vec![0u32; *words];
+                for (i, set) in flags.iter().enumerate() {
+                    if *set {
+                        bits[i / 32] |= 1 << (i % 32);
+                    }
+                }
+                Ok(bits.into_iter().map(|b| Val::I32(b as i32)).collect())
+            }
+            OpKind::FlagsLift(n) => {
+                let mut flags = Vec::new();
+                for (i, word) in operands.iter().enumerate() {
+                    let word = match word {
+                        Val::I32(w) => *w as u32,
+                        other => trap!("expected i32, found {:?}", other),
+                    };
+                    for bit in 0..32 {
+                        let set = word & (1 << bit) != 0;
+                        if i * 32 + bit < *n {
+                            flags.push(set);
+                        } else if set {
+                            trap!("flag bit {} set out of range", i * 32 + bit);
+                        }
+                    }
+                }
+                one(Val::Flags(flags))
+            }
+
+            OpKind::VariantPayloadName => one(frame.payload.last().unwrap().clone()),
+            OpKind::VariantLower => {
+                let (case, payload) = match operands.pop().unwrap() {
+                    Val::Case(case, payload) => (case, *payload),
+                    other => trap!("expected variant, found {:?}", other),
+                };
+                let block = match op.blocks.get(case as usize) {
+                    Some(block) => block,
+                    None => trap!("case {} out of range", case),
+                };
+                frame.payload.push(payload);
+                let results = self.block(frame, block);
+                frame.payload.pop();
+                results
+            }
+            OpKind::VariantLift => {
+                let case = match &operands[0] {
+                    Val::I32(c) => *c as u32,
+                    Val::I64(c) => *c as u32,
+                    other => trap!("expected discriminant, found {:?}", other),
+                };
+                let block = match op.blocks.get(case as usize) {
+                    Some(block) => block,
+                    None => trap!("discriminant {} out of range", case),
+                };
+                let payload = self.block(frame, block)?.pop().unwrap();
+                one(Val::Case(case, Box::new(payload)))
+            }
+            OpKind::EnumLower => match &operands[0] {
+                Val::Enum(e) => one(Val::I32(*e as i32)),
+                other => trap!("expected enum, found {:?}", other),
+            },
+            OpKind::EnumLift(n) => match &operands[0] {
+                Val::I32(e) if (*e as u32 as usize) < *n => one(Val::Enum(*e as u32)),
+                other => trap!("invalid enum discriminant {:?}", other),
+            },
+
+            OpKind::CallWasm | OpKind::CallInterface => (frame.callee)(self, operands),
+            OpKind::Return => {
+                frame.returned = Some(operands);
+                Ok(Vec::new())
+            }
+            OpKind::Malloc(size, align) => one(Val::I32(self.alloc(*size, *align)? as i32)),
+            OpKind::Free => {
+                let ptr = match &operands[0] {
+                    Val::I32(p) => *p as u32,
+                    other => trap!("expected pointer, found {:?}", other),
+                };
+                self.free(ptr)?;
+                Ok(Vec::new())
+            }
+            OpKind::ReturnPointer(size, align) => {
+                // Return areas are static, so they're never freed.
+                let ptr = self.alloc(*size, *align)?;
+                self.allocations.remove(&ptr);
+                one(Val::I32(ptr as i32))
+            }
+        }
+    }
+
+    fn alloc(&mut self, size: usize, align: usize) -> Result<u32, Trap> {
+        let ptr = self.memory.len().next_multiple_of(align);
+        if ptr + size > MEMORY_LIMIT {
+            trap!("out of memory");
+        }
+        // Pad allocations so overruns land in memory nobody else owns.
+        self.memory.resize(ptr + size + 8, 0xaa);
+        self.allocations.insert(ptr as u32, size);
+        Ok(ptr as u32)
+    }
+
+    fn free(&mut self, ptr: u32) -> Result<(), Trap> {
+        if self.allocations.remove(&ptr).is_none() {
+            trap!("free of {:#x}, which is not allocated", ptr);
+        }
+        Ok(())
+    }
+
+    fn slice(&self, ptr: u32, len: usize) -> Result<&[u8], Trap> {
+        match self.memory.get(ptr as usize..).and_then(|m| m.get(..len)) {
+            Some(s) => Ok(s),
+            None => trap!("out of bounds access of {} bytes at {:#x}", len, ptr),
+        }
+    }
+
+    fn slice_mut(&mut self, ptr: u32, len: usize) -> Result<&mut [u8], Trap> {
+        match self
+            .memory
+            .get_mut(ptr as usize..)
+            .and_then(|m| m.get_mut(..len))
+        {
+            Some(s) => Ok(s),
+            None => trap!("out of bounds access of {} bytes at {:#x}", len, ptr),
+        }
+    }
+
+    fn load(&self, mem: Mem, addr: u32) -> Result<Val, Trap> {
+        let bytes = |n| self.slice(addr, n);
+        Ok(match mem {
+            Mem::I32 => Val::I32(i32::from_le_bytes(bytes(4)?.try_into().unwrap())),
+            Mem::I32_8U => Val::I32(bytes(1)?[0] as i32),
+            Mem::I32_8S => Val::I32(bytes(1)?[0] as i8 as i32),
+            Mem::I32_16U => Val::I32(u16::from_le_bytes(bytes(2)?.try_into().unwrap()) as i32),
+            Mem::I32_16S => Val::I32(i16::from_le_bytes(bytes(2)?.try_into().unwrap()) as i32),
+            Mem::I64 => Val::I64(i64::from_le_bytes(bytes(8)?.try_into().unwrap())),
+            Mem::F32 => Val::F32(u32::from_le_bytes(bytes(4)?.try_into().unwrap())),
+            Mem::F64 => Val::F64(u64::from_le_bytes(bytes(8)?.try_into().unwrap())),
+            Mem::I32_8 | Mem::I32_16 => unreachable!(),
+        })
+    }
+
+    fn store(&mut self, mem: Mem, addr: u32, val: &Val) -> Result<(), Trap> {
+        let bytes: Vec<u8> = match (mem, val) {
+            (Mem::I32, Val::I32(v)) => v.to_le_bytes().to_vec(),
+            (Mem::I32_8, Val::I32(v)) => vec![*v as u8],
+            (Mem::I32_16, Val::I32(v)) => (*v as u16).to_le_bytes().to_vec(),
+            (Mem::I64, Val::I64(v)) => v.to_le_bytes().to_vec(),
+            (Mem::F32, Val::F32(v)) => v.to_le_bytes().to_vec(),
+            (Mem::F64, Val::F64(v)) => v.to_le_bytes().to_vec(),
+            (_, other) => trap!("mismatched store of {:?}", other),
+        };
+        self.slice_mut(addr, bytes.len())?.copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    /// Layout of `ty` in the host and guest languages, which is what
+    /// canonical lists are copied in bulk with.
+    ///
+    /// This is computed independently from `SizeAlign` following the rules
+    /// of Rust's `#[repr(C)]`, and panics if the language doesn't guarantee a
+    /// layout for `ty` at all.
+    fn native_layout(&self, ty: &Type) -> (usize, usize) {
+        let layout = match ty {
+            Type::Unit => (0, 1),
+            Type::U8 | Type::S8 => (1, 1),
+            Type::U16 | Type::S16 => (2, 2),
+            Type::U32 | Type::S32 | Type::Float32 | Type::Char => (4, 4),
+            Type::U64 | Type::S64 | Type::Float64 => (8, 8),
+            Type::Id(id) => match &self.iface.types[*id].kind {
+                TypeDefKind::Type(t) => self.native_layout(t),
+                TypeDefKind::Record(r) => self.repr_c(r.fields.iter().map(|f| &f.ty)),
+                TypeDefKind::Tuple(t) if t.types.len() <= 1 => self.repr_c(t.types.iter()),
+                _ => panic!(
+                    "list of {:?} copied in bulk but its layout is not guaranteed",
+                    self.iface.types[*id].kind
+                ),
+            },
+            other => panic!("list of {:?} copied in bulk", other),
+        };
+        let canonical = (self.sizes.size(ty), self.sizes.align(ty));
+        assert_eq!(
+            layout, canonical,
+            "native layout of {:?} disagrees with the canonical ABI",
+            ty
+        );
+        layout
+    }
+
+    fn repr_c<'b>(&self, fields: impl Iterator<Item = &'b Type>) -> (usize, usize) {
+        let mut size = 0usize;
+        let mut align = 1;
+        for ty in fields {
+            let (field_size, field_align) = self.native_layout(ty);
+            size = size.next_multiple_of(field_align) + field_size;
+            align = align.max(field_align);
+        }
+        (size.next_multiple_of(align), align)
+    }
+
+    fn native_fields(&self, ty: &Type) -> Vec<(Type, usize)> {
+        let tys = match ty {
+            Type::Id(id) => match &self.iface.types[*id].kind {
+                TypeDefKind::Type(t) => return self.native_fields(t),
+                TypeDefKind::Record(r) => r.fields.iter().map(|f| f.ty).collect::<Vec<_>>(),
+                TypeDefKind::Tuple(t) => t.types.clone(),
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+        let mut offset = 0usize;
+        tys.into_iter()
+            .map(|ty| {
+                let (size, align) = self.native_layout(&ty);
+                offset = offset.next_multiple_of(align);
+                let ret = (ty, offset);
+                offset += size;
+                ret
+            })
+            .collect()
+    }
+
+    fn write_native(&mut self, ty: &Type, addr: u32, val: &Val) -> Result<(), Trap> {
+        let le = match (ty, val) {
+            (Type::Unit, Val::Unit) => Vec::new(),
+            (Type::U8, Val::U8(v)) => v.to_le_bytes().to_vec(),
+            (Type::S8, Val::S8(v)) => v.to_le_bytes().to_vec(),
+            (Type::U16, Val::U16(v)) => v.to_le_bytes().to_vec(),
+            (Type::S16, Val::S16(v)) => v.to_le_bytes().to_vec(),
+            (Type::U32, Val::U32(v)) => v.to_le_bytes().to_vec(),
+            (Type::S32, Val::S32(v)) => v.to_le_bytes().to_vec(),
+            (Type::U64, Val::U64(v)) => v.to_le_bytes().to_vec(),
+            (Type::S64, Val::S64(v)) => v.to_le_bytes().to_vec(),
+            (Type::Float32, Val::Float32(v)) => v.to_le_bytes().to_vec(),
+            (Type::Float64, Val::Float64(v)) => v.to_le_bytes().to_vec(),
+            (Type::Char, Val::Char(c)) => (*c as u32).to_le_bytes().to_vec(),
+            (Type::Id(_), Val::Fields(fields)) => {
+                for ((ty, offset), field) in self.native_fields(ty).into_iter().zip(fields) {
+                    self.write_native(&ty, addr + offset as u32, field)?;
+                }
+                return Ok(());
+            }
+            (Type::Id(id), _) => match &self.iface.types[*id].kind {
+                TypeDefKind::Type(t) => return self.write_native(t, addr, val),
+                _ => trap!("mismatched {:?} for {:?}", val, ty),
+            },
+            _ => trap!("mismatched {:?} for {:?}", val, ty),
+        };
+        self.slice_mut(addr, le.len())?.copy_from_slice(&le);
+        Ok(())
+    }
+
+    fn read_native(&self, ty: &Type, addr: u32) -> Result<Val, Trap> {
+        let bytes = |n| self.slice(addr, n);
+        Ok(match ty {
+            Type::Unit => Val::Unit,
+            Type::U8 => Val::U8(bytes(1)?[0]),
+            Type::S8 => Val::S8(bytes(1)?[0] as i8),
+            Type::U16 => Val::U16(u16::from_le_bytes(bytes(2)?.try_into().unwrap())),
+            Type::S16 => Val::S16(i16::from_le_bytes(bytes(2)?.try_into().unwrap())),
+            Type::U32 => Val::U32(u32::from_le_bytes(bytes(4)?.try_into().unwrap())),
+            Type::S32 => Val::S32(i32::from_le_bytes(bytes(4)?.try_into().unwrap())),
+            Type::U64 => Val::U64(u64::from_le_bytes(bytes(8)?.try_into().unwrap())),
+            Type::S64 => Val::S64(i64::from_le_bytes(bytes(8)?.try_into().unwrap())),
+            Type::Float32 => Val::Float32(u32::from_le_bytes(bytes(4)?.try_into().unwrap())),
+            Type::Float64 => Val::Float64(u64::from_le_bytes(bytes(8)?.try_into().unwrap())),
+            Type::Char => {
+                let c = u32::from_le_bytes(bytes(4)?.try_into().unwrap());
+                match char::from_u32(c) {
+                    Some(c) => Val::Char(c),
+                    None => trap!("invalid char {:#x}", c),
+                }
+            }
+            Type::Id(id) => match &self.iface.types[*id].kind {
+                TypeDefKind::Type(t) => self.read_native(t, addr)?,
+                _ => Val::Fields(
+                    self.native_fields(ty)
+                        .into_iter()
+                        .map(|(ty, offset)| self.read_native(&ty, addr + offset as u32))
+                        .collect::<Result<_, _>>()?,
+                ),
+            },
+            other => trap!("cannot read {:?} natively", other),
+        })
+    }
+}
+
+fn address(base: &Val, offset: i32) -> Result<u32, Trap> {
+    match base {
+        Val::I32(base) => Ok((*base as u32).wrapping_add(offset as u32)),
+        other => trap!("expected address, found {:?}", other),
+    }
+}
+
+fn ptr_len(operands: &[Val]) -> Result<(u32, usize), Trap> {
+    match operands {
+        [Val::I32(ptr), Val::I32(len)] => Ok((*ptr as u32, *len as u32 as usize)),
+        other => trap!("expected pointer and length, found {:?}", other),
+    }
+}
+
+fn list(val: &Val) -> Result<&[Val], Trap> {
+    match val {
+        Val::List(items) => Ok(items),
+        other => trap!("expected list, found {:?}", other),
+    }
+}
+
+fn lower(val: Val) -> Result<Val, Trap> {
+    Ok(match val {
+        Val::Bool(b) => Val::I32(b as i32),
+        Val::U8(v) => Val::I32(v as i32),
+        Val::S8(v) => Val::I32(v as i32),
+        Val::U16(v) => Val::I32(v as i32),
+        Val::S16(v) => Val::I32(v as i32),
+        Val::U32(v) => Val::I32(v as i32),
+        Val::S32(v) => Val::I32(v),
+        Val::U64(v) => Val::I64(v as i64),
+        Val::S64(v) => Val::I64(v),
+        Val::Char(c) => Val::I32(c as i32),
+        Val::Float32(v) => Val::F32(v),
+        Val::Float64(v) => Val::F64(v),
+        other => trap!("cannot lower {:?} to a core wasm value", other),
+    })
+}
+
+/// Lifts a core wasm value, checking its range like the host bindings do.
+fn lift(ty: &Type, val: Val) -> Result<Val, Trap> {
+    Ok(match (ty, val) {
+        (Type::Bool, Val::I32(0)) => Val::Bool(false),
+        (Type::Bool, Val::I32(1)) => Val::Bool(true),
+        (Type::U8, Val::I32(v)) if u8::try_from(v).is_ok() => Val::U8(v as u8),
+        (Type::S8, Val::I32(v)) if i8::try_from(v).is_ok() => Val::S8(v as i8),
+        (Type::U16, Val::I32(v)) if u16::try_from(v).is_ok() => Val::U16(v as u16),
+        (Type::S16, Val::I32(v)) if i16::try_from(v).is_ok() => Val::S16(v as i16),
+        (Type::U32, Val::I32(v)) => Val::U32(v as u32),
+        (Type::S32, Val::I32(v)) => Val::S32(v),
+        (Type::U64, Val::I64(v)) => Val::U64(v as u64),
+        (Type::S64, Val::I64(v)) => Val::S64(v),
+        (Type::Char, Val::I32(v)) if char::from_u32(v as u32).is_some() => {
+            Val::Char(char::from_u32(v as u32).unwrap())
+        }
+        (Type::Float32, Val::F32(v)) => Val::Float32(v),
+        (Type::Float64, Val::F64(v)) => Val::Float64(v),
+        (ty, val) => trap!("cannot lift {:?} as {:?}", val, ty),
+    })
+}
+
+fn bitcast(val: Val, cast: Cast) -> Result<Val, Trap> {
+    Ok(match (cast, val) {
+        (Cast::None, v) => v,
+        (Cast::F32ToI32, Val::F32(v)) => Val::I32(v as i32),
+        (Cast::F64ToI64, Val::F64(v)) => Val::I64(v as i64),
+        (Cast::I32ToI64, Val::I32(v)) => Val::I64(v as u32 as i64),
+        (Cast::F32ToI64, Val::F32(v)) => Val::I64(v as i64),
+        (Cast::I32ToF32, Val::I32(v)) => Val::F32(v as u32),
+        (Cast::I64ToF64, Val::I64(v)) => Val::F64(v as u64),
+        (Cast::I64ToI32, Val::I64(v)) => Val::I32(v as i32),
+        (Cast::I64ToF32, Val::I64(v)) => Val::F32(v as u32),
+        (_, v) => trap!("invalid bitcast of {:?}", v),
+    })
+}
+
+/// Whether values of `ty` can be round-tripped by this interpreter.
+pub fn supported(iface: &Interface, ty: &Type) -> bool {
+    match ty {
+        Type::Handle(_) => false,
+        Type::Id(id) => match &iface.types[*id].kind {
+            TypeDefKind::Type(t) | TypeDefKind::List(t) | TypeDefKind::Option(t) => {
+                supported(iface, t)
+            }
+            TypeDefKind::Record(r) => r.fields.iter().all(|f| supported(iface, &f.ty)),
+            TypeDefKind::Tuple(t) => t.types.iter().all(|t| supported(iface, t)),
+            TypeDefKind::Variant(v) => v.cases.iter().all(|c| supported(iface, &c.ty)),
+            TypeDefKind::Union(u) => u.cases.iter().all(|c| supported(iface, &c.ty)),
+            TypeDefKind::Expected(e) => supported(iface, &e.ok) && supported(iface, &e.err),
+            TypeDefKind::Flags(_) | TypeDefKind::Enum(_) => true,
+            TypeDefKind::Future(_) | TypeDefKind::Stream(_) => false,
+        },
+        _ => true,
+    }
+}
+
+/// Generates an arbitrary value of `ty`.
+pub fn arbitrary_val(
+    iface: &Interface,
+    ty: &Type,
+    u: &mut arbitrary::Unstructured<'_>,
+) -> arbitrary::Result<Val> {
+    Ok(match ty {
+        Type::Unit => Val::Unit,
+        Type::Bool => Val::Bool(u.arbitrary()?),
+        Type::U8 => Val::U8(u.arbitrary()?),
+        Type::U16 => Val::U16(u.arbitrary()?),
+        Type::U32 => Val::U32(u.arbitrary()?),
+        Type::U64 => Val::U64(u.arbitrary()?),
+        Type::S8 => Val::S8(u.arbitrary()?),
+        Type::S16 => Val::S16(u.arbitrary()?),
+        Type::S32 => Val::S32(u.arbitrary()?),
+        Type::S64 => Val::S64(u.arbitrary()?),
+        Type::Float32 => Val::Float32(u.arbitrary()?),
+        Type::Float64 => Val::Float64(u.arbitrary()?),
+        Type::Char => Val::Char(u.arbitrary()?),
+        Type::String => Val::String(u.arbitrary()?),
+        Type::Handle(_) => unreachable!(),
+        Type::Id(id) => match &iface.types[*id].kind {
+            TypeDefKind::Type(t) => arbitrary_val(iface, t, u)?,
+            TypeDefKind::List(t) => {
+                let len = u.arbitrary_len::<u8>()?.min(16);
+                Val::List(
+                    (0..len)
+                        .map(|_| arbitrary_val(iface, t, u))
+                        .collect::<arbitrary::Result<_>>()?,
+                )
+            }
+            TypeDefKind::Record(r) => Val::Fields(
+                r.fields
+                    .iter()
+                    .map(|f| arbitrary_val(iface, &f.ty, u))
+                    .collect::<arbitrary::Result<_>>()?,
+            ),
+            TypeDefKind::Tuple(t) => Val::Fields(
+                t.types
+                    .iter()
+                    .map(|t| arbitrary_val(iface, t, u))
+                    .collect::<arbitrary::Result<_>>()?,
+            ),
+            TypeDefKind::Flags(f) => Val::Flags(
+                f.flags
+                    .iter()
+                    .map(|_| u.arbitrary())
+                    .collect::<arbitrary::Result<_>>()?,
+            ),
+            TypeDefKind::Enum(e) => Val::Enum(u.choose_index(e.cases.len())? as u32),
+            TypeDefKind::Variant(v) => {
+                let case = u.choose_index(v.cases.len())?;
+                let payload = arbitrary_val(iface, &v.cases[case].ty, u)?;
+                Val::Case(case as u32, Box::new(payload))
+            }
+            TypeDefKind::Union(un) => {
+                let case = u.choose_index(un.cases.len())?;
+                let payload = arbitrary_val(iface, &un.cases[case].ty, u)?;
+                Val::Case(case as u32, Box::new(payload))
+            }
+            TypeDefKind::Option(t) => match u.arbitrary()? {
+                false => Val::Case(0, Box::new(Val::Unit)),
+                true => Val::Case(1, Box::new(arbitrary_val(iface, t, u)?)),
+            },
+            TypeDefKind::Expected(e) => match u.arbitrary()? {
+                false => Val::Case(0, Box::new(arbitrary_val(iface, &e.ok, u)?)),
+                true => Val::Case(1, Box::new(arbitrary_val(iface, &e.err, u)?)),
+            },
+            TypeDefKind::Future(_) | TypeDefKind::Stream(_) => unreachable!(),
+        },
+    })
+}
+
+/// Calls `func` from a host into a guest with `args`, with the guest
+/// returning `result`, and asserts that both sides observe the same values.
+///
+/// `host` and `guest` are the bindgens their generators drive for `func`,
+/// lowering the arguments into a guest export and lifting them in it
+/// respectively.
+pub fn round_trip(
+    iface: &Interface,
+    func: &Function,
+    host: &mut dyn Bindgen<Operand = String>,
+    guest: &mut dyn Bindgen<Operand = String>,
+    args: Vec<Val>,
+    result: Val,
+) {
+    let caller = Program::record(
+        iface,
+        AbiVariant::GuestExport,
+        LiftLower::LowerArgsLiftResults,
+        func,
+        host,
+    );
+    let callee = Program::record(
+        iface,
+        AbiVariant::GuestExport,
+        LiftLower::LiftArgsLowerResults,
+        func,
+        guest,
+    );
+
+    // The canonical layout that both sides' bulk copies are checked against.
+    let mut sizes = SizeAlign::default();
+    sizes.fill(iface);
+    let mut machine = Machine::new(iface, &sizes);
+    let mut received = None;
+    let returned = machine.run(&caller, args.clone(), &mut |machine, wasm_args| {
+        machine.run(&callee, wasm_args, &mut |_, lifted| {
+            received = Some(lifted);
+            Ok(vec![result.clone()])
+        })
+    });
+    match returned {
+        Ok(returned) => {
+            assert_eq!(
+                received.as_ref(),
+                Some(&args),
+                "arguments of `{}`",
+                func.name
+            );
+            assert_eq!(returned, [result], "result of `{}`", func.name);
+        }
+        Err(Trap(msg)) if msg == "out of memory" => {}
+        Err(Trap(msg)) => panic!("`{}` trapped: {}", func.name, msg),
+    }
+}
@@ -0,0 +1,218 @@
+//! Generator of syntactically valid `*.wai` documents for structured fuzzing
+//! of `wai-parser`.
+//!
+//! Names are drawn from a tiny pool so that generated documents frequently
+//! reference, shadow, and redefine each other's items, which is where the
+//! resolver has to do the most work.
+
+use arbitrary::Arbitrary;
+use std::fmt;
+
+pub mod abi;
+
+#[derive(Arbitrary, Debug)]
+pub struct Document {
+    items: Vec<Item>,
+}
+
+#[derive(Arbitrary, Debug)]
+pub struct Name(u8);
+
+const NAMES: &[&str] = &["a", "b", "c", "a-b", "%type", "%string", "r"];
+
+#[derive(Arbitrary, Debug)]
+enum Item {
+    Alias(Docs, Name, Type),
+    Record(Docs, Name, Vec<(Docs, Name, Type)>),
+    Flags(Docs, Name, Vec<Name>),
+    Variant(Docs, Name, Vec<(Name, Option<Type>)>),
+    Enum(Docs, Name, Vec<Name>),
+    Union(Docs, Name, Vec<Type>),
+    Resource(Docs, Name, Option<Name>, Option<Vec<(bool, Func)>>),
+    Func(Docs, Func),
+    Global(Docs, Name, Type),
+}
+
+#[derive(Arbitrary, Debug)]
+struct Func {
+    name: Name,
+    is_async: bool,
+    params: Vec<(Name, Type)>,
+    result: Option<Type>,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Docs {
+    None,
+    Line,
+    Block,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Type {
+    Unit,
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    S8,
+    S16,
+    S32,
+    S64,
+    Float32,
+    Float64,
+    Char,
+    String,
+    Handle(Name),
+    Name(Name),
+    List(Box<Type>),
+    Tuple(Vec<Type>),
+    Option(Box<Type>),
+    Expected(Box<Type>, Box<Type>),
+    Future(Box<Type>),
+    Stream(Box<Type>, Box<Type>),
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for item in self.items.iter() {
+            writeln!(f, "{}", item)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(NAMES[usize::from(self.0) % NAMES.len()])
+    }
+}
+
+impl fmt::Display for Docs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Docs::None => Ok(()),
+            Docs::Line => writeln!(f, "/// docs"),
+            Docs::Block => writeln!(f, "/** docs */"),
+        }
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Item::Alias(docs, name, ty) => write!(f, "{docs}type {name} = {ty}"),
+            Item::Record(docs, name, fields) => {
+                writeln!(f, "{docs}record {name} {{")?;
+                for (docs, name, ty) in fields {
+                    writeln!(f, "{docs}{name}: {ty},")?;
+                }
+                write!(f, "}}")
+            }
+            Item::Flags(docs, name, flags) => {
+                write!(f, "{docs}flags {name} {{ ")?;
+                for flag in flags {
+                    write!(f, "{flag}, ")?;
+                }
+                write!(f, "}}")
+            }
+            Item::Variant(docs, name, cases) => {
+                write!(f, "{docs}variant {name} {{ ")?;
+                for (name, ty) in cases {
+                    match ty {
+                        Some(ty) => write!(f, "{name}({ty}), ")?,
+                        None => write!(f, "{name}, ")?,
+                    }
+                }
+                write!(f, "}}")
+            }
+            Item::Enum(docs, name, cases) => {
+                write!(f, "{docs}enum {name} {{ ")?;
+                for case in cases {
+                    write!(f, "{case}, ")?;
+                }
+                write!(f, "}}")
+            }
+            Item::Union(docs, name, cases) => {
+                write!(f, "{docs}union {name} {{ ")?;
+                for ty in cases {
+                    write!(f, "{ty}, ")?;
+                }
+                write!(f, "}}")
+            }
+            Item::Resource(docs, name, supertype, funcs) => {
+                write!(f, "{docs}resource {name}")?;
+                if let Some(supertype) = supertype {
+                    write!(f, " implements {supertype}")?;
+                }
+                if let Some(funcs) = funcs {
+                    writeln!(f, " {{")?;
+                    for (statik, func) in funcs {
+                        if *statik {
+                            write!(f, "static ")?;
+                        }
+                        writeln!(f, "{func}")?;
+                    }
+                    write!(f, "}}")?;
+                }
+                Ok(())
+            }
+            Item::Func(docs, func) => write!(f, "{docs}{func}"),
+            Item::Global(docs, name, ty) => write!(f, "{docs}{name}: {ty}"),
+        }
+    }
+}
+
+impl fmt::Display for Func {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.name)?;
+        if self.is_async {
+            write!(f, "async ")?;
+        }
+        write!(f, "func(")?;
+        for (name, ty) in self.params.iter() {
+            write!(f, "{name}: {ty}, ")?;
+        }
+        write!(f, ")")?;
+        if let Some(result) = &self.result {
+            write!(f, " -> {result}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Unit => write!(f, "unit"),
+            Type::Bool => write!(f, "bool"),
+            Type::U8 => write!(f, "u8"),
+            Type::U16 => write!(f, "u16"),
+            Type::U32 => write!(f, "u32"),
+            Type::U64 => write!(f, "u64"),
+            Type::S8 => write!(f, "s8"),
+            Type::S16 => write!(f, "s16"),
+            Type::S32 => write!(f, "s32"),
+            Type::S64 => write!(f, "s64"),
+            Type::Float32 => write!(f, "float32"),
+            Type::Float64 => write!(f, "float64"),
+            Type::Char => write!(f, "char"),
+            Type::String => write!(f, "string"),
+            Type::Handle(name) => write!(f, "handle {name}"),
+            Type::Name(name) => write!(f, "{name}"),
+            Type::List(ty) => write!(f, "list<{ty}>"),
+            Type::Tuple(types) => {
+                write!(f, "tuple<")?;
+                for ty in types {
+                    write!(f, "{ty}, ")?;
+                }
+                write!(f, ">")
+            }
+            Type::Option(ty) => write!(f, "option<{ty}>"),
+            Type::Expected(ok, err) => write!(f, "expected<{ok}, {err}>"),
+            Type::Future(ty) => write!(f, "future<{ty}>"),
+            Type::Stream(element, end) => write!(f, "stream<{element}, {end}>"),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+from exports.bindings import Exports
+from imports.bindings import add_imports_to_imports, Imports
+from typing import Any, Callable, List, Optional, TypeVar
+import exports.bindings as e
+import imports.bindings as i
+import os
+import struct
+import sys
+import wasmer # type: ignore
+
+class MyImports:
+    def echo_string(self, a: str) -> str:
+        return a
+
+    def echo_list(self, a: List[int]) -> List[int]:
+        return a
+
+    def echo_variant(self, a: i.Expected[Optional[int], str]) -> i.Expected[Optional[int], str]:
+        return a
+
+def string(s: str) -> str:
+    return 'utf8:' + s.encode('utf-8').hex()
+
+T = TypeVar('T')
+
+def list_(items: List[T], f: Callable[[T], str]) -> str:
+    return '[' + ', '.join(f(item) for item in items) + ']'
+
+def float32(x: float) -> str:
+    return '0x{:08x}'.format(struct.unpack('<I', struct.pack('<f', x))[0])
+
+def float64(x: float) -> str:
+    return '0x{:016x}'.format(struct.unpack('<Q', struct.pack('<d', x))[0])
+
+def point(p: e.Point) -> str:
+    return '{x: ' + str(p.x) + ', y: ' + str(p.y) + '}'
+
+def shape(s: e.Shape) -> str:
+    if isinstance(s, e.ShapeCircle):
+        return 'circle(' + float32(s.value) + ')'
+    if isinstance(s, e.ShapeSquare):
+        return 'square(' + point(s.value) + ')'
+    return 'empty'
+
+def color(c: e.Color) -> str:
+    return c.name.lower()
+
+def permissions(p: e.Permissions) -> str:
+    names = [flag.name.lower() for flag in e.Permissions if flag in p]
+    return '{' + '|'.join(names) + '}'
+
+def option(o: Optional[str]) -> str:
+    return 'none' if o is None else 'some(' + string(o) + ')'
+
+def expected(r: e.Expected[int, str]) -> str:
+    if isinstance(r, e.Ok):
+        return 'ok(' + str(r.value) + ')'
+    return 'err(' + string(r.value) + ')'
+
+def run(wasm_file: str) -> None:
+    store = wasmer.Store()
+    module = wasmer.Module(store, open(wasm_file, 'rb').read())
+    wasi_version = wasmer.wasi.get_version(module, strict=False)
+    if wasi_version is None:
+        import_object = {}
+    else:
+        wasi_env = wasmer.wasi.StateBuilder('test').finalize()
+        import_object = wasi_env.generate_imports(store, wasi_version)
+
+    wasm: Exports
+    def get_export(name: str) -> Any:
+        return wasm.instance.exports.__getattribute__(name)
+
+    imports = MyImports()
+    add_imports_to_imports(store, import_object, imports, get_export)
+    wasm = Exports(store, import_object, module)
+
+    t = ''
+
+    wasm.test_imports()
+    t += 'test-imports -> ()\n'
+
+    for r in [
+        wasm.roundtrip_integers(255, -128, 65535, -32768, 4294967295, -2147483648, 18446744073709551615, -9223372036854775808),
+        wasm.roundtrip_integers(0, 127, 0, 32767, 0, 2147483647, 0, 9223372036854775807),
+    ]:
+        t += 'roundtrip-integers -> (' + ', '.join(str(x) for x in r) + ')\n'
+
+    for a, b in [(1.5, -0.0), (float('inf'), float('nan'))]:
+        ra, rb = wasm.roundtrip_floats(a, b)
+        t += 'roundtrip-floats -> (' + float32(ra) + ', ' + float64(rb) + ')\n'
+
+    c = wasm.roundtrip_char('🚀')
+    t += 'roundtrip-char -> U+{:04X}\n'.format(ord(c))
+
+    for s in ['', 'héllo, 世界 🚀']:
+        t += 'roundtrip-string -> ' + string(wasm.roundtrip_string(s)) + '\n'
+
+    for la, lb in [([1, 2, 4294967295], ['a', '', '🚀']), ([], [])]:
+        ra2, rb2 = wasm.roundtrip_lists(la, lb)
+        t += 'roundtrip-lists -> (' + list_(ra2, str) + ', ' + list_(rb2, string) + ')\n'
+
+    t += 'swap-point -> ' + point(wasm.swap_point(e.Point(1, -2))) + '\n'
+
+    shapes: List[e.Shape] = [e.ShapeCircle(2.5), e.ShapeSquare(e.Point(3, 4)), e.ShapeEmpty(None)]
+    for sh in shapes:
+        t += 'roundtrip-variant -> ' + shape(wasm.roundtrip_variant(sh)) + '\n'
+
+    for co in [e.Color.GREEN, e.Color.BLUE]:
+        t += 'roundtrip-enum -> ' + color(wasm.roundtrip_enum(co)) + '\n'
+
+    for p in [e.Permissions(0), e.Permissions.READ | e.Permissions.EXEC]:
+        t += 'roundtrip-flags -> ' + permissions(wasm.roundtrip_flags(p)) + '\n'
+
+    for o in [None, 'x']:
+        t += 'roundtrip-option -> ' + option(wasm.roundtrip_option(o)) + '\n'
+
+    results: List[e.Expected[int, str]] = [e.Ok(7), e.Err('bad')]
+    for r2 in results:
+        t += 'roundtrip-expected -> ' + expected(wasm.roundtrip_expected(r2)) + '\n'
+
+    t += wasm.take_log()
+
+    with open(os.path.join(os.path.dirname(__file__), 'transcript.txt'), encoding='utf-8') as f:
+        expected_lines = f.read().split('\n')
+    actual_lines = t.split('\n')
+    for n, (actual, expected_line) in enumerate(zip(actual_lines, expected_lines)):
+        assert actual == expected_line, 'line {} of the transcript differs: {!r} != {!r}'.format(n + 1, actual, expected_line)
+    assert len(actual_lines) == len(expected_lines), t
+
+if __name__ == '__main__':
+    run(sys.argv[1])
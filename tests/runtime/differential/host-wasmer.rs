@@ -0,0 +1,206 @@
+use anyhow::Result;
+use std::fmt::Write;
+
+wai_bindgen_wasmer::export!("../../tests/runtime/differential/imports.wai");
+
+use imports::*;
+use wai_bindgen_wasmer::Le;
+
+#[derive(Clone)]
+pub struct MyImports;
+
+impl Imports for MyImports {
+    fn echo_string(&mut self, a: &str) -> String {
+        a.to_string()
+    }
+
+    fn echo_list(&mut self, a: &[Le<i64>]) -> Vec<i64> {
+        a.iter().map(|e| e.get()).collect()
+    }
+
+    fn echo_variant(&mut self, a: Result<Option<u8>, &str>) -> Result<Option<u8>, String> {
+        a.map_err(|e| e.to_string())
+    }
+}
+
+wai_bindgen_wasmer::import!("../../tests/runtime/differential/exports.wai");
+
+fn run(wasm: &str) -> Result<()> {
+    use exports::*;
+    use wasmer::AsStoreMut as _;
+
+    let mut store = wasmer::Store::default();
+
+    let exports = crate::instantiate(
+        wasm,
+        &mut store,
+        |store, imports| imports::add_to_imports(store, imports, MyImports),
+        |store, module, imports| {
+            exports::Exports::instantiate(
+                &mut store.as_store_mut().as_store_mut(),
+                &module,
+                imports,
+            )
+        },
+    )?;
+
+    let mut t = String::new();
+
+    exports.test_imports(&mut store)?;
+    writeln!(t, "test-imports -> ()")?;
+
+    for args in [
+        (
+            u8::MAX,
+            i8::MIN,
+            u16::MAX,
+            i16::MIN,
+            u32::MAX,
+            i32::MIN,
+            u64::MAX,
+            i64::MIN,
+        ),
+        (0, i8::MAX, 0, i16::MAX, 0, i32::MAX, 0, i64::MAX),
+    ] {
+        let (a, b, c, d, e, f, g, h) = args;
+        let r = exports.roundtrip_integers(&mut store, a, b, c, d, e, f, g, h)?;
+        writeln!(
+            t,
+            "roundtrip-integers -> ({}, {}, {}, {}, {}, {}, {}, {})",
+            r.0, r.1, r.2, r.3, r.4, r.5, r.6, r.7
+        )?;
+    }
+
+    for (a, b) in [(1.5, -0.0), (f32::INFINITY, f64::NAN)] {
+        let (a, b) = exports.roundtrip_floats(&mut store, a, b)?;
+        writeln!(
+            t,
+            "roundtrip-floats -> ({:#010x}, {:#018x})",
+            a.to_bits(),
+            b.to_bits()
+        )?;
+    }
+
+    let c = exports.roundtrip_char(&mut store, '🚀')?;
+    writeln!(t, "roundtrip-char -> U+{:04X}", c as u32)?;
+
+    for s in ["", "héllo, 世界 🚀"] {
+        let s = exports.roundtrip_string(&mut store, s)?;
+        writeln!(t, "roundtrip-string -> {}", string(&s))?;
+    }
+
+    for (a, b) in [
+        (&[1, 2, u32::MAX][..], &["a", "", "🚀"][..]),
+        (&[][..], &[][..]),
+    ] {
+        let (a, b) = exports.roundtrip_lists(&mut store, a, b)?;
+        writeln!(
+            t,
+            "roundtrip-lists -> ({}, {})",
+            list(&a, |i| i.to_string()),
+            list(&b, |s| string(s)),
+        )?;
+    }
+
+    let p = exports.swap_point(&mut store, Point { x: 1, y: -2 })?;
+    writeln!(t, "swap-point -> {}", point(&p))?;
+
+    for s in [
+        Shape::Circle(2.5),
+        Shape::Square(Point { x: 3, y: 4 }),
+        Shape::Empty,
+    ] {
+        let s = exports.roundtrip_variant(&mut store, s)?;
+        writeln!(t, "roundtrip-variant -> {}", shape(&s))?;
+    }
+
+    for c in [Color::Green, Color::Blue] {
+        let c = exports.roundtrip_enum(&mut store, c)?;
+        writeln!(t, "roundtrip-enum -> {}", color(c))?;
+    }
+
+    for p in [Permissions::empty(), Permissions::READ | Permissions::EXEC] {
+        let p = exports.roundtrip_flags(&mut store, p)?;
+        writeln!(t, "roundtrip-flags -> {}", permissions(p))?;
+    }
+
+    for o in [None, Some("x")] {
+        let o = exports.roundtrip_option(&mut store, o)?;
+        writeln!(t, "roundtrip-option -> {}", option(&o))?;
+    }
+
+    for e in [Ok(7), Err("bad")] {
+        let e = exports.roundtrip_expected(&mut store, e)?;
+        writeln!(t, "roundtrip-expected -> {}", expected(&e))?;
+    }
+
+    t.push_str(&exports.take_log(&mut store)?);
+
+    let expected = include_str!("transcript.txt").replace("\r\n", "\n");
+    for (i, (actual, expected)) in t.lines().zip(expected.lines()).enumerate() {
+        assert_eq!(actual, expected, "line {} of the transcript differs", i + 1);
+    }
+    assert_eq!(t.lines().count(), expected.lines().count(), "{}", t);
+    Ok(())
+}
+
+fn string(s: &str) -> String {
+    let mut ret = "utf8:".to_string();
+    for byte in s.bytes() {
+        write!(ret, "{:02x}", byte).unwrap();
+    }
+    ret
+}
+
+fn list<T>(items: &[T], f: impl Fn(&T) -> String) -> String {
+    let items = items.iter().map(f).collect::<Vec<_>>();
+    format!("[{}]", items.join(", "))
+}
+
+fn point(p: &exports::Point) -> String {
+    format!("{{x: {}, y: {}}}", p.x, p.y)
+}
+
+fn shape(s: &exports::Shape) -> String {
+    match s {
+        exports::Shape::Circle(r) => format!("circle({:#010x})", r.to_bits()),
+        exports::Shape::Square(p) => format!("square({})", point(p)),
+        exports::Shape::Empty => "empty".to_string(),
+    }
+}
+
+fn color(c: exports::Color) -> &'static str {
+    match c {
+        exports::Color::Red => "red",
+        exports::Color::Green => "green",
+        exports::Color::Blue => "blue",
+    }
+}
+
+fn permissions(p: exports::Permissions) -> String {
+    let mut names = Vec::new();
+    for (flag, name) in [
+        (exports::Permissions::READ, "read"),
+        (exports::Permissions::WRITE, "write"),
+        (exports::Permissions::EXEC, "exec"),
+    ] {
+        if p.contains(flag) {
+            names.push(name);
+        }
+    }
+    format!("{{{}}}", names.join("|"))
+}
+
+fn option(o: &Option<String>) -> String {
+    match o {
+        Some(s) => format!("some({})", string(s)),
+        None => "none".to_string(),
+    }
+}
+
+fn expected(e: &Result<u32, String>) -> String {
+    match e {
+        Ok(v) => format!("ok({})", v),
+        Err(s) => format!("err({})", string(s)),
+    }
+}
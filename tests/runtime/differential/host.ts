@@ -0,0 +1,145 @@
+import { addImportsToImports, Imports } from "./imports.js";
+import { Exports, Point, Shape, Color, Permissions, Result } from "./exports.js";
+import { getWasm, getTranscript, addWasiToImports } from "./helpers.js";
+// @ts-ignore
+import * as assert from 'assert';
+
+function string(s: string): string {
+  let ret = 'utf8:';
+  for (const byte of new TextEncoder().encode(s))
+    ret += byte.toString(16).padStart(2, '0');
+  return ret;
+}
+
+function list<T>(items: ArrayLike<T>, f: (item: T) => string): string {
+  return `[${Array.from(items).map(f).join(', ')}]`;
+}
+
+function float32(x: number): string {
+  const view = new DataView(new ArrayBuffer(4));
+  view.setFloat32(0, x);
+  return `0x${view.getUint32(0).toString(16).padStart(8, '0')}`;
+}
+
+function float64(x: number): string {
+  const view = new DataView(new ArrayBuffer(8));
+  view.setFloat64(0, x);
+  return `0x${view.getBigUint64(0).toString(16).padStart(16, '0')}`;
+}
+
+function point(p: Point): string {
+  return `{x: ${p.x}, y: ${p.y}}`;
+}
+
+function shape(s: Shape): string {
+  switch (s.tag) {
+    case 'circle': return `circle(${float32(s.val)})`;
+    case 'square': return `square(${point(s.val)})`;
+    case 'empty': return 'empty';
+  }
+}
+
+function color(c: Color): string {
+  return c;
+}
+
+function permissions(p: Permissions): string {
+  const names = [];
+  if (p.read) names.push('read');
+  if (p.write) names.push('write');
+  if (p.exec) names.push('exec');
+  return `{${names.join('|')}}`;
+}
+
+function option(o: string | null): string {
+  return o === null ? 'none' : `some(${string(o)})`;
+}
+
+function expected(e: Result<number, string>): string {
+  return e.tag === 'ok' ? `ok(${e.val})` : `err(${string(e.val)})`;
+}
+
+async function run() {
+  const importObj = {};
+  const imports: Imports = {
+    echoString(a) { return a; },
+    echoList(a) { return a; },
+    echoVariant(a) { return a; },
+  };
+  let instance: WebAssembly.Instance;
+  addImportsToImports(importObj, imports, name => instance.exports[name]);
+  const wasi = addWasiToImports(importObj);
+
+  const wasm = new Exports();
+  await wasm.instantiate(getWasm(), importObj);
+  wasi.start(wasm.instance);
+  instance = wasm.instance;
+
+  let t = '';
+
+  wasm.testImports();
+  t += 'test-imports -> ()\n';
+
+  for (const r of [
+    wasm.roundtripIntegers(255, -128, 65535, -32768, 4294967295, -2147483648, 18446744073709551615n, -9223372036854775808n),
+    wasm.roundtripIntegers(0, 127, 0, 32767, 0, 2147483647, 0n, 9223372036854775807n),
+  ]) {
+    t += `roundtrip-integers -> (${r.join(', ')})\n`;
+  }
+
+  for (const [a, b] of [[1.5, -0], [Infinity, NaN]]) {
+    const [ra, rb] = wasm.roundtripFloats(a, b);
+    t += `roundtrip-floats -> (${float32(ra)}, ${float64(rb)})\n`;
+  }
+
+  const c = wasm.roundtripChar('🚀');
+  t += `roundtrip-char -> U+${c.codePointAt(0)!.toString(16).toUpperCase().padStart(4, '0')}\n`;
+
+  for (const s of ['', 'héllo, 世界 🚀']) {
+    t += `roundtrip-string -> ${string(wasm.roundtripString(s))}\n`;
+  }
+
+  for (const [a, b] of [
+    [new Uint32Array([1, 2, 4294967295]), ['a', '', '🚀']],
+    [new Uint32Array([]), []],
+  ] as [Uint32Array, string[]][]) {
+    const [ra, rb] = wasm.roundtripLists(a, b);
+    t += `roundtrip-lists -> (${list(ra, i => `${i}`)}, ${list(rb, string)})\n`;
+  }
+
+  t += `swap-point -> ${point(wasm.swapPoint({ x: 1, y: -2 }))}\n`;
+
+  for (const s of [
+    { tag: 'circle', val: 2.5 },
+    { tag: 'square', val: { x: 3, y: 4 } },
+    { tag: 'empty' },
+  ] as Shape[]) {
+    t += `roundtrip-variant -> ${shape(wasm.roundtripVariant(s))}\n`;
+  }
+
+  for (const c of ['green', 'blue'] as Color[]) {
+    t += `roundtrip-enum -> ${color(wasm.roundtripEnum(c))}\n`;
+  }
+
+  for (const p of [{}, { read: true, exec: true }] as Permissions[]) {
+    t += `roundtrip-flags -> ${permissions(wasm.roundtripFlags(p))}\n`;
+  }
+
+  for (const o of [null, 'x']) {
+    t += `roundtrip-option -> ${option(wasm.roundtripOption(o))}\n`;
+  }
+
+  for (const e of [{ tag: 'ok', val: 7 }, { tag: 'err', val: 'bad' }] as Result<number, string>[]) {
+    t += `roundtrip-expected -> ${expected(wasm.roundtripExpected(e))}\n`;
+  }
+
+  t += wasm.takeLog();
+
+  const actual = t.split('\n');
+  const expectedLines = getTranscript().replace(/\r\n/g, '\n').split('\n');
+  for (let i = 0; i < Math.min(actual.length, expectedLines.length); i++)
+    assert.strictEqual(actual[i], expectedLines[i], `line ${i + 1} of the transcript differs`);
+  assert.strictEqual(actual.length, expectedLines.length, t);
+}
+
+run();
@@ -0,0 +1,178 @@
+wai_bindgen_rust::import!("../../tests/runtime/differential/imports.wai");
+wai_bindgen_rust::export!("../../tests/runtime/differential/exports.wai");
+
+use exports::*;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+static LOG: Mutex<String> = Mutex::new(String::new());
+
+fn log(line: String) {
+    let mut log = LOG.lock().unwrap();
+    log.push_str(&line);
+    log.push('\n');
+}
+
+fn string(s: &str) -> String {
+    let mut ret = "utf8:".to_string();
+    for byte in s.bytes() {
+        write!(ret, "{:02x}", byte).unwrap();
+    }
+    ret
+}
+
+fn list<T>(items: &[T], f: impl Fn(&T) -> String) -> String {
+    let items = items.iter().map(f).collect::<Vec<_>>();
+    format!("[{}]", items.join(", "))
+}
+
+fn point(p: &Point) -> String {
+    format!("{{x: {}, y: {}}}", p.x, p.y)
+}
+
+fn shape(s: &Shape) -> String {
+    match s {
+        Shape::Circle(r) => format!("circle({:#010x})", r.to_bits()),
+        Shape::Square(p) => format!("square({})", point(p)),
+        Shape::Empty => "empty".to_string(),
+    }
+}
+
+fn color(c: Color) -> &'static str {
+    match c {
+        Color::Red => "red",
+        Color::Green => "green",
+        Color::Blue => "blue",
+    }
+}
+
+fn permissions(p: Permissions) -> String {
+    let mut names = Vec::new();
+    for (flag, name) in [
+        (Permissions::READ, "read"),
+        (Permissions::WRITE, "write"),
+        (Permissions::EXEC, "exec"),
+    ] {
+        if p.contains(flag) {
+            names.push(name);
+        }
+    }
+    format!("{{{}}}", names.join("|"))
+}
+
+fn option(o: &Option<String>) -> String {
+    match o {
+        Some(s) => format!("some({})", string(s)),
+        None => "none".to_string(),
+    }
+}
+
+fn expected(e: &Result<u32, String>) -> String {
+    match e {
+        Ok(v) => format!("ok({})", v),
+        Err(s) => format!("err({})", string(s)),
+    }
+}
+
+struct Exports;
+
+impl exports::Exports for Exports {
+    fn test_imports() {
+        use imports::*;
+
+        log("test-imports()".to_string());
+
+        log(format!("echo-string -> {}", string(&echo_string("🚀"))));
+
+        let l = echo_list(&[-1, 0, i64::MAX]);
+        log(format!("echo-list -> {}", list(&l, |i| i.to_string())));
+
+        for v in [Ok(Some(255)), Ok(None), Err("e")] {
+            let v = match echo_variant(v) {
+                Ok(Some(v)) => format!("ok(some({}))", v),
+                Ok(None) => "ok(none)".to_string(),
+                Err(s) => format!("err({})", string(&s)),
+            };
+            log(format!("echo-variant -> {}", v));
+        }
+    }
+
+    fn take_log() -> String {
+        std::mem::take(&mut LOG.lock().unwrap())
+    }
+
+    fn roundtrip_integers(
+        a: u8,
+        b: i8,
+        c: u16,
+        d: i16,
+        e: u32,
+        f: i32,
+        g: u64,
+        h: i64,
+    ) -> (u8, i8, u16, i16, u32, i32, u64, i64) {
+        log(format!(
+            "roundtrip-integers({}, {}, {}, {}, {}, {}, {}, {})",
+            a, b, c, d, e, f, g, h
+        ));
+        (a, b, c, d, e, f, g, h)
+    }
+
+    fn roundtrip_floats(a: f32, b: f64) -> (f32, f64) {
+        log(format!(
+            "roundtrip-floats({:#010x}, {:#018x})",
+            a.to_bits(),
+            b.to_bits()
+        ));
+        (a, b)
+    }
+
+    fn roundtrip_char(a: char) -> char {
+        log(format!("roundtrip-char(U+{:04X})", a as u32));
+        a
+    }
+
+    fn roundtrip_string(a: String) -> String {
+        log(format!("roundtrip-string({})", string(&a)));
+        a
+    }
+
+    fn roundtrip_lists(a: Vec<u32>, b: Vec<String>) -> (Vec<u32>, Vec<String>) {
+        log(format!(
+            "roundtrip-lists({}, {})",
+            list(&a, |i| i.to_string()),
+            list(&b, |s| string(s)),
+        ));
+        (a, b)
+    }
+
+    fn swap_point(a: Point) -> Point {
+        log(format!("swap-point({})", point(&a)));
+        Point { x: a.y, y: a.x }
+    }
+
+    fn roundtrip_variant(a: Shape) -> Shape {
+        log(format!("roundtrip-variant({})", shape(&a)));
+        a
+    }
+
+    fn roundtrip_enum(a: Color) -> Color {
+        log(format!("roundtrip-enum({})", color(a)));
+        a
+    }
+
+    fn roundtrip_flags(a: Permissions) -> Permissions {
+        log(format!("roundtrip-flags({})", permissions(a)));
+        a
+    }
+
+    fn roundtrip_option(a: Option<String>) -> Option<String> {
+        log(format!("roundtrip-option({})", option(&a)));
+        a
+    }
+
+    fn roundtrip_expected(a: Result<u32, String>) -> Result<u32, String> {
+        log(format!("roundtrip-expected({})", expected(&a)));
+        a
+    }
+}
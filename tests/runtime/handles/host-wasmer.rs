@@ -155,5 +155,45 @@ fn run(wasm: &str) -> Result<()> {
     for s in exports.wasm_state2_result_list(&mut store)? {
         exports.drop_wasm_state2(&mut store, s)?;
     }
+
+    let md = exports::Markdown::create(&mut store, &exports)?.unwrap();
+    md.append(&mut store, &exports, "red is the best color")?;
+    assert_eq!(md.render(&mut store, &exports)?, "green is the best color");
+    exports.drop_markdown(&mut store, md)?;
+
+    reload(wasm)
+}
+
+fn reload(wasm: &str) -> Result<()> {
+    use exports::*;
+
+    let mut store = wasmer::Store::default();
+    let module = wasmer::Module::from_file(&store, wasm)?;
+    let wasi_env = wasmer_wasi::WasiState::new("test").finalize(&mut store)?;
+    let mut imports = wasi_env.import_object(&mut store, &module)?;
+    let init = imports::add_to_imports(&mut store, &mut imports, MyImports::default());
+    let (mut exports, instance) = Exports::instantiate(&mut store, &module, &mut imports)?;
+    let memory = instance.exports.get_memory("memory")?;
+    wasi_env.data_mut(&mut store).set_memory(memory.clone());
+    init(&instance, &store)?;
+
+    let s = exports.wasm_state_create(&mut store)?;
+
+    let instance = wasmer::Instance::new(&mut store, &module, &imports)?;
+    let memory = instance.exports.get_memory("memory")?;
+    wasi_env.data_mut(&mut store).set_memory(memory.clone());
+    init(&instance, &store)?;
+    exports.reload(&mut store, &instance)?;
+
+    // Handles to the previous instance's resources are rejected, but can
+    // still be dropped.
+    let err = exports.wasm_state_get_val(&mut store, &s).unwrap_err();
+    assert!(err.is::<wai_bindgen_wasmer::StaleHandle>());
+    exports.drop_wasm_state(&mut store, s)?;
+
+    exports.test_imports(&mut store)?;
+    let s = exports.wasm_state_create(&mut store)?;
+    assert_eq!(exports.wasm_state_get_val(&mut store, &s)?, 100);
+    exports.drop_wasm_state(&mut store, s)?;
     Ok(())
 }
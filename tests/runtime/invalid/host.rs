@@ -2,7 +2,6 @@ wai_bindgen_wasmtime::export!("../../tests/runtime/invalid/imports.wai");
 
 use anyhow::Result;
 use imports::*;
-use wasmtime::Trap;
 
 #[derive(Default)]
 pub struct MyImports;
@@ -89,11 +88,13 @@ fn run(wasm: &str) -> Result<()> {
     )?;
     return Ok(());
 
-    fn assert_err(result: Result<(), Trap>, err: &str) -> Result<()> {
+    fn assert_err(result: Result<(), wasmtime::Error>, err: &str) -> Result<()> {
         match result {
             Ok(()) => anyhow::bail!("export didn't trap"),
-            Err(e) if e.to_string().contains(err) => Ok(()),
-            Err(e) => Err(e.into()),
+            // Wasmtime attaches the wasm backtrace as context, so look
+            // through the whole chain of causes for the message.
+            Err(e) if format!("{:?}", e).contains(err) => Ok(()),
+            Err(e) => Err(e),
         }
     }
 }
@@ -0,0 +1,64 @@
+from exports.bindings import Exports
+from imports.bindings import add_imports_to_imports, Imports
+from typing import List, Optional
+import exports.bindings as e
+import imports.bindings as i
+import sys
+import wasmer # type: ignore
+
+class MyImports:
+    def roundtrip_option_expected(self, a: Optional[i.Expected[int, str]]) -> Optional[i.Expected[int, str]]:
+        return a
+
+    def roundtrip_empty(self, a: i.Empty) -> i.Empty:
+        return a
+
+    def roundtrip_wrappers(self, a: List[i.Wrapper]) -> List[i.Wrapper]:
+        return a
+
+    def roundtrip_shapes(self, a: List[i.Shape]) -> List[i.Shape]:
+        return a
+
+    def roundtrip_nested_lists(self, a: List[List[List[str]]]) -> List[List[List[str]]]:
+        return a
+
+def run(wasm_file: str) -> None:
+    store = wasmer.Store()
+    module = wasmer.Module(store, open(wasm_file, 'rb').read())
+    wasi_env = None
+    if wasmer.wasi.get_version(module, strict=False) is not None:
+        wasi_env = wasmer.wasi.StateBuilder('test').finalize()
+
+    imports = MyImports()
+    wasm = Exports.instantiate(
+        module,
+        store,
+        wasi=wasi_env,
+        add_imports=[lambda store, import_object, get_export: add_imports_to_imports(store, import_object, imports, get_export)],
+    )
+
+    wasm.test_imports()
+
+    assert wasm.roundtrip_option_expected(None) is None
+    assert wasm.roundtrip_option_expected(e.Ok(3)) == e.Ok(3)
+    assert wasm.roundtrip_option_expected(e.Err('no')) == e.Err('no')
+
+    assert wasm.roundtrip_empty(e.Empty()) == e.Empty()
+    assert wasm.roundtrip_wrappers([e.Wrapper(e.Empty(), 9)]) == [e.Wrapper(e.Empty(), 9)]
+    assert wasm.roundtrip_wrappers([]) == []
+
+    shapes: List[e.Shape] = [
+        e.ShapeU32(4),
+        e.ShapeString('shape'),
+        e.ShapeU8List(b'\x01\x02'),
+        e.ShapeEmpty(e.Empty()),
+        e.ShapeOptionalI64(-2**63),
+        e.ShapeOptionalI64(None),
+    ]
+    assert wasm.roundtrip_shapes(shapes) == shapes
+
+    assert wasm.roundtrip_nested_lists([[['x'], []], []]) == [[['x'], []], []]
+    assert wasm.roundtrip_nested_lists([]) == []
+
+if __name__ == '__main__':
+    run(sys.argv[1])
@@ -0,0 +1,63 @@
+from exports.bindings import Exports
+from imports.bindings import add_imports_to_linker, Imports
+from typing import List, Optional
+import exports.bindings as e
+import imports.bindings as i
+import sys
+import wasmtime
+
+class MyImports:
+    def roundtrip_option_expected(self, a: Optional[i.Expected[int, str]]) -> Optional[i.Expected[int, str]]:
+        return a
+
+    def roundtrip_empty(self, a: i.Empty) -> i.Empty:
+        return a
+
+    def roundtrip_wrappers(self, a: List[i.Wrapper]) -> List[i.Wrapper]:
+        return a
+
+    def roundtrip_shapes(self, a: List[i.Shape]) -> List[i.Shape]:
+        return a
+
+    def roundtrip_nested_lists(self, a: List[List[List[str]]]) -> List[List[List[str]]]:
+        return a
+
+def run(wasm_file: str) -> None:
+    store = wasmtime.Store()
+    module = wasmtime.Module.from_file(store.engine, wasm_file)
+    linker = wasmtime.Linker(store.engine)
+    linker.define_wasi()
+    wasi = wasmtime.WasiConfig()
+    wasi.inherit_stdout()
+    wasi.inherit_stderr()
+    store.set_wasi(wasi)
+
+    imports = MyImports()
+    add_imports_to_linker(linker, store, imports)
+    wasm = Exports(store, linker, module)
+
+    wasm.test_imports(store)
+
+    assert wasm.roundtrip_option_expected(store, None) is None
+    assert wasm.roundtrip_option_expected(store, e.Ok(3)) == e.Ok(3)
+    assert wasm.roundtrip_option_expected(store, e.Err('no')) == e.Err('no')
+
+    assert wasm.roundtrip_empty(store, e.Empty()) == e.Empty()
+    assert wasm.roundtrip_wrappers(store, [e.Wrapper(e.Empty(), 9)]) == [e.Wrapper(e.Empty(), 9)]
+    assert wasm.roundtrip_wrappers(store, []) == []
+
+    shapes: List[e.Shape] = [
+        e.ShapeU32(4),
+        e.ShapeString('shape'),
+        e.ShapeU8List(b'\x01\x02'),
+        e.ShapeEmpty(e.Empty()),
+        e.ShapeOptionalI64(-2**63),
+        e.ShapeOptionalI64(None),
+    ]
+    assert wasm.roundtrip_shapes(store, shapes) == shapes
+
+    assert wasm.roundtrip_nested_lists(store, [[['x'], []], []]) == [[['x'], []], []]
+    assert wasm.roundtrip_nested_lists(store, []) == []
+
+if __name__ == '__main__':
+    run(sys.argv[1])
@@ -0,0 +1,110 @@
+use anyhow::Result;
+
+wai_bindgen_wasmtime::export!("../../tests/runtime/nested_shapes/imports.wai");
+
+use imports::*;
+
+#[derive(Default)]
+pub struct MyImports;
+
+impl Imports for MyImports {
+    fn roundtrip_option_expected(
+        &mut self,
+        a: Option<Result<u32, &str>>,
+    ) -> Option<Result<u32, String>> {
+        a.map(|r| r.map_err(|s| s.to_string()))
+    }
+
+    fn roundtrip_empty(&mut self, a: Empty) -> Empty {
+        a
+    }
+
+    fn roundtrip_wrappers(&mut self, a: &[Wrapper]) -> Vec<Wrapper> {
+        a.to_vec()
+    }
+
+    fn roundtrip_shapes(&mut self, a: Vec<ShapeParam<'_>>) -> Vec<ShapeResult> {
+        a.into_iter()
+            .map(|shape| match shape {
+                ShapeParam::U32(n) => ShapeResult::U32(n),
+                ShapeParam::String(s) => ShapeResult::String(s.to_string()),
+                ShapeParam::U8List(l) => ShapeResult::U8List(l.to_vec()),
+                ShapeParam::Empty(e) => ShapeResult::Empty(e),
+                ShapeParam::OptionalI64(o) => ShapeResult::OptionalI64(o),
+            })
+            .collect()
+    }
+
+    fn roundtrip_nested_lists(&mut self, a: Vec<Vec<Vec<&str>>>) -> Vec<Vec<Vec<String>>> {
+        a.into_iter()
+            .map(|l| {
+                l.into_iter()
+                    .map(|l| l.into_iter().map(|s| s.to_string()).collect())
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+wai_bindgen_wasmtime::import!("../../tests/runtime/nested_shapes/exports.wai");
+
+fn run(wasm: &str) -> Result<()> {
+    use exports::*;
+
+    let (exports, mut store) = crate::instantiate(
+        wasm,
+        |linker| imports::add_to_linker(linker, |cx| -> &mut MyImports { &mut cx.imports }),
+        |store, module, linker| Exports::instantiate(store, module, linker, |cx| &mut cx.exports),
+    )?;
+
+    exports.test_imports(&mut store)?;
+
+    assert_eq!(exports.roundtrip_option_expected(&mut store, None)?, None);
+    assert_eq!(
+        exports.roundtrip_option_expected(&mut store, Some(Ok(3)))?,
+        Some(Ok(3))
+    );
+    assert_eq!(
+        exports.roundtrip_option_expected(&mut store, Some(Err("no")))?,
+        Some(Err("no".to_string()))
+    );
+
+    let Empty {} = exports.roundtrip_empty(&mut store, Empty {})?;
+    let wrappers = exports.roundtrip_wrappers(
+        &mut store,
+        &[Wrapper {
+            inner: Empty {},
+            tag: 9,
+        }],
+    )?;
+    assert_eq!(wrappers.len(), 1);
+    assert_eq!(wrappers[0].tag, 9);
+
+    let shapes = exports.roundtrip_shapes(
+        &mut store,
+        &[
+            ShapeParam::U32(4),
+            ShapeParam::String("shape"),
+            ShapeParam::U8List(&[1, 2]),
+            ShapeParam::Empty(Empty {}),
+            ShapeParam::OptionalI64(Some(i64::MIN)),
+            ShapeParam::OptionalI64(None),
+        ],
+    )?;
+    assert_eq!(shapes.len(), 6);
+    assert!(matches!(shapes[0], ShapeResult::U32(4)));
+    assert!(matches!(&shapes[1], ShapeResult::String(s) if s == "shape"));
+    assert!(matches!(&shapes[2], ShapeResult::U8List(l) if l == &[1, 2]));
+    assert!(matches!(shapes[3], ShapeResult::Empty(Empty {})));
+    assert!(matches!(
+        shapes[4],
+        ShapeResult::OptionalI64(Some(i64::MIN))
+    ));
+    assert!(matches!(shapes[5], ShapeResult::OptionalI64(None)));
+
+    assert_eq!(
+        exports.roundtrip_nested_lists(&mut store, &[&[&["x"], &[]], &[]])?,
+        [vec![vec!["x".to_string()], vec![]], vec![]]
+    );
+    Ok(())
+}
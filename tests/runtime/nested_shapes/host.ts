@@ -0,0 +1,49 @@
+import { addImportsToImports, Imports } from "./imports.js";
+import { Exports, Shape } from "./exports.js";
+import { getWasm, addWasiToImports } from "./helpers.js";
+// @ts-ignore
+import * as assert from 'assert';
+
+async function run() {
+  const importObj = {};
+  const imports: Imports = {
+    roundtripOptionExpected(x) { return x; },
+    roundtripEmpty(x) { return x; },
+    roundtripWrappers(x) { return x; },
+    roundtripShapes(x) { return x; },
+    roundtripNestedLists(x) { return x; },
+  };
+  let instance: WebAssembly.Instance;
+  addImportsToImports(importObj, imports, name => instance.exports[name]);
+  const wasi = addWasiToImports(importObj);
+
+  const wasm = new Exports();
+  await wasm.instantiate(getWasm(), importObj);
+  wasi.start(wasm.instance);
+  instance = wasm.instance;
+
+  wasm.testImports();
+
+  assert.deepStrictEqual(wasm.roundtripOptionExpected(null), null);
+  assert.deepStrictEqual(wasm.roundtripOptionExpected({ tag: 'ok', val: 3 }), { tag: 'ok', val: 3 });
+  assert.deepStrictEqual(wasm.roundtripOptionExpected({ tag: 'err', val: 'no' }), { tag: 'err', val: 'no' });
+
+  assert.deepStrictEqual(wasm.roundtripEmpty({}), {});
+  assert.deepStrictEqual(wasm.roundtripWrappers([{ inner: {}, tag: 9 }]), [{ inner: {}, tag: 9 }]);
+  assert.deepStrictEqual(wasm.roundtripWrappers([]), []);
+
+  const shapes: Shape[] = [
+    { tag: 0, val: 4 },
+    { tag: 1, val: 'shape' },
+    { tag: 2, val: new Uint8Array([1, 2]) },
+    { tag: 3, val: {} },
+    { tag: 4, val: -(2n ** 63n) },
+    { tag: 4, val: null },
+  ];
+  assert.deepStrictEqual(wasm.roundtripShapes(shapes), shapes);
+
+  assert.deepStrictEqual(wasm.roundtripNestedLists([[['x'], []], []]), [[['x'], []], []]);
+  assert.deepStrictEqual(wasm.roundtripNestedLists([]), []);
+}
+
+run();
@@ -0,0 +1,80 @@
+wai_bindgen_rust::import!("../../tests/runtime/nested_shapes/imports.wai");
+wai_bindgen_rust::export!("../../tests/runtime/nested_shapes/exports.wai");
+
+use exports::*;
+
+struct Exports;
+
+impl exports::Exports for Exports {
+    fn test_imports() {
+        use imports::*;
+
+        assert_eq!(roundtrip_option_expected(None), None);
+        assert_eq!(roundtrip_option_expected(Some(Ok(7))), Some(Ok(7)));
+        assert_eq!(
+            roundtrip_option_expected(Some(Err("x"))),
+            Some(Err("x".to_string()))
+        );
+
+        let Empty {} = roundtrip_empty(Empty {});
+
+        let wrappers = roundtrip_wrappers(&[
+            Wrapper {
+                inner: Empty {},
+                tag: 1,
+            },
+            Wrapper {
+                inner: Empty {},
+                tag: 2,
+            },
+        ]);
+        assert_eq!(wrappers.iter().map(|w| w.tag).collect::<Vec<_>>(), [1, 2]);
+        assert!(roundtrip_wrappers(&[]).is_empty());
+
+        let shapes = roundtrip_shapes(&[
+            ShapeParam::U32(1),
+            ShapeParam::String("two"),
+            ShapeParam::U8List(&[3, 3, 3]),
+            ShapeParam::Empty(Empty {}),
+            ShapeParam::OptionalI64(Some(-5)),
+            ShapeParam::OptionalI64(None),
+        ]);
+        assert_eq!(shapes.len(), 6);
+        assert!(matches!(shapes[0], ShapeResult::U32(1)));
+        assert!(matches!(&shapes[1], ShapeResult::String(s) if s == "two"));
+        assert!(matches!(&shapes[2], ShapeResult::U8List(l) if l == &[3, 3, 3]));
+        assert!(matches!(shapes[3], ShapeResult::Empty(Empty {})));
+        assert!(matches!(shapes[4], ShapeResult::OptionalI64(Some(-5))));
+        assert!(matches!(shapes[5], ShapeResult::OptionalI64(None)));
+
+        assert_eq!(
+            roundtrip_nested_lists(&[&[&["a", "b"], &[]], &[], &[&["c"]]]),
+            [
+                vec![vec!["a".to_string(), "b".to_string()], vec![]],
+                vec![],
+                vec![vec!["c".to_string()]],
+            ]
+        );
+        assert!(roundtrip_nested_lists(&[]).is_empty());
+    }
+
+    fn roundtrip_option_expected(a: Option<Result<u32, String>>) -> Option<Result<u32, String>> {
+        a
+    }
+
+    fn roundtrip_empty(a: Empty) -> Empty {
+        a
+    }
+
+    fn roundtrip_wrappers(a: Vec<Wrapper>) -> Vec<Wrapper> {
+        a
+    }
+
+    fn roundtrip_shapes(a: Vec<Shape>) -> Vec<Shape> {
+        a
+    }
+
+    fn roundtrip_nested_lists(a: Vec<Vec<Vec<String>>>) -> Vec<Vec<Vec<String>>> {
+        a
+    }
+}
@@ -0,0 +1,63 @@
+use anyhow::Result;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+wai_bindgen_wasmer::export!(
+    "../../tests/runtime/shared_resources/blobs.wai"
+    "../../tests/runtime/shared_resources/sizes.wai"
+);
+
+#[derive(Default, Clone)]
+pub struct MyImports;
+
+impl blobs::Blobs for MyImports {
+    type Blob = String;
+
+    fn blob_create(&mut self, data: &str) -> String {
+        data.to_string()
+    }
+
+    fn blob_contents(&mut self, blob: &String) -> String {
+        blob.clone()
+    }
+}
+
+impl sizes::Sizes for MyImports {
+    fn measure(&mut self, blob: &String) -> u32 {
+        blob.len() as u32
+    }
+}
+
+wai_bindgen_wasmer::import!("../../tests/runtime/shared_resources/exports.wai");
+
+fn run(wasm: &str) -> Result<()> {
+    use wasmer::AsStoreMut as _;
+
+    let mut store = wasmer::Store::default();
+
+    let exports = crate::instantiate(
+        wasm,
+        &mut store,
+        |store, imports| {
+            let tables = Rc::<RefCell<blobs::BlobsTables<MyImports>>>::default();
+            let blobs = blobs::add_to_imports_with_tables(
+                store,
+                imports,
+                MyImports,
+                Rc::clone(&tables),
+            );
+            let sizes = sizes::add_to_imports(store, imports, MyImports, tables);
+            move |instance: &wasmer::Instance, store: &dyn wasmer::AsStoreRef| {
+                blobs(instance, store)?;
+                sizes(instance, store)
+            }
+        },
+        |store, module, imports| {
+            exports::Exports::instantiate(&mut store.as_store_mut().as_store_mut(), module, imports)
+        },
+    )?;
+
+    exports.test_imports(&mut store)?;
+
+    Ok(())
+}
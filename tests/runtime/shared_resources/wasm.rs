@@ -0,0 +1,15 @@
+wai_bindgen_rust::import!(
+    "../../tests/runtime/shared_resources/blobs.wai"
+    "../../tests/runtime/shared_resources/sizes.wai"
+);
+wai_bindgen_rust::export!("../../tests/runtime/shared_resources/exports.wai");
+
+struct Exports;
+
+impl exports::Exports for Exports {
+    fn test_imports() {
+        let blob = blobs::Blob::create("hello");
+        assert_eq!(blob.contents(), "hello");
+        assert_eq!(sizes::measure(&blob), 5);
+    }
+}
@@ -10,130 +10,130 @@ class MyImports:
     # Simple uses of unions whose inner values all have the same Python representation
     def add_one_integer(self, num: i.AllIntegers) -> i.AllIntegers:
         # Bool
-        if isinstance(num, i.AllIntegers0):
+        if isinstance(num, i.AllIntegersBool):
             assert num.value in (True, False)
-            return i.AllIntegers0(not num.value)
+            return i.AllIntegersBool(not num.value)
         # The unsigned numbers
-        elif isinstance(num, i.AllIntegers1):
+        elif isinstance(num, i.AllIntegersU8):
             lower_limit = 0
             upper_limit = 2**8
             assert lower_limit <= num.value < upper_limit
-            return i.AllIntegers1(num.value + 1 % upper_limit)
-        elif isinstance(num, i.AllIntegers2):
+            return i.AllIntegersU8(num.value + 1 % upper_limit)
+        elif isinstance(num, i.AllIntegersU16):
             lower_limit = 0
             upper_limit = 2**16
             assert lower_limit <= num.value < upper_limit
-            return i.AllIntegers2(num.value + 1 % upper_limit)
-        elif isinstance(num, i.AllIntegers3):
+            return i.AllIntegersU16(num.value + 1 % upper_limit)
+        elif isinstance(num, i.AllIntegersU32):
             lower_limit = 0
             upper_limit = 2**32
             assert lower_limit <= num.value < upper_limit
-            return i.AllIntegers3(num.value + 1 % upper_limit)
-        elif isinstance(num, i.AllIntegers4):
+            return i.AllIntegersU32(num.value + 1 % upper_limit)
+        elif isinstance(num, i.AllIntegersU64):
             lower_limit = 0
             upper_limit = 2**64
             assert lower_limit <= num.value < upper_limit
-            return i.AllIntegers4(num.value + 1 % upper_limit)
+            return i.AllIntegersU64(num.value + 1 % upper_limit)
         # The signed numbers
-        elif isinstance(num, i.AllIntegers5):
+        elif isinstance(num, i.AllIntegersI8):
             lower_limit = -2**7
             upper_limit = 2**7
             assert lower_limit <= num.value < upper_limit
-            return i.AllIntegers5(num.value + 1 % upper_limit)
-        elif isinstance(num, i.AllIntegers6):
+            return i.AllIntegersI8(num.value + 1 % upper_limit)
+        elif isinstance(num, i.AllIntegersI16):
             lower_limit = -2**15
             upper_limit = 2**15
             assert lower_limit <= num.value < upper_limit
-            return i.AllIntegers6(num.value + 1 % upper_limit)
-        elif isinstance(num, i.AllIntegers7):
+            return i.AllIntegersI16(num.value + 1 % upper_limit)
+        elif isinstance(num, i.AllIntegersI32):
             lower_limit = -2**31
             upper_limit = 2**31
             assert lower_limit <= num.value < upper_limit
-            return i.AllIntegers7(num.value + 1 % upper_limit)
-        elif isinstance(num, i.AllIntegers8):
+            return i.AllIntegersI32(num.value + 1 % upper_limit)
+        elif isinstance(num, i.AllIntegersI64):
             lower_limit = -2**63
             upper_limit = 2**63
             assert lower_limit <= num.value < upper_limit
-            return i.AllIntegers8(num.value + 1 % upper_limit)
+            return i.AllIntegersI64(num.value + 1 % upper_limit)
         else:
             raise ValueError("Invalid input value!")
 
     def add_one_float(self, num: i.AllFloats) -> i.AllFloats:
-        if isinstance(num, i.AllFloats0):
-            return i.AllFloats0(num.value + 1)
-        if isinstance(num, i.AllFloats1):
-            return i.AllFloats1(num.value + 1)
+        if isinstance(num, i.AllFloatsF32):
+            return i.AllFloatsF32(num.value + 1)
+        if isinstance(num, i.AllFloatsF64):
+            return i.AllFloatsF64(num.value + 1)
         else:
             raise ValueError("Invalid input value!")
 
     def replace_first_char(self, text: i.AllText, letter: str) -> i.AllText:
-        if isinstance(text, i.AllText0):
-            return i.AllText0(letter)
-        if isinstance(text, i.AllFloats1):
-            return i.AllText1(letter + text.value[1:])
+        if isinstance(text, i.AllTextChar):
+            return i.AllTextChar(letter)
+        if isinstance(text, i.AllFloatsF64):
+            return i.AllTextString(letter + text.value[1:])
         else:
             raise ValueError("Invalid input value!")
 
     # Identify each case of unions whose inner values all have the same Python representation
     def identify_integer(self, num: i.AllIntegers) -> int:
         # Bool
-        if isinstance(num, i.AllIntegers0):
+        if isinstance(num, i.AllIntegersBool):
             return 0
         # The unsigned numbers
-        elif isinstance(num, i.AllIntegers1):
+        elif isinstance(num, i.AllIntegersU8):
             return 1
-        elif isinstance(num, i.AllIntegers2):
+        elif isinstance(num, i.AllIntegersU16):
             return 2
-        elif isinstance(num, i.AllIntegers3):
+        elif isinstance(num, i.AllIntegersU32):
             return 3
-        elif isinstance(num, i.AllIntegers4):
+        elif isinstance(num, i.AllIntegersU64):
             return 4
         # The signed numbers
-        elif isinstance(num, i.AllIntegers5):
+        elif isinstance(num, i.AllIntegersI8):
             return 5
-        elif isinstance(num, i.AllIntegers6):
+        elif isinstance(num, i.AllIntegersI16):
             return 6
-        elif isinstance(num, i.AllIntegers7):
+        elif isinstance(num, i.AllIntegersI32):
             return 7
-        elif isinstance(num, i.AllIntegers8):
+        elif isinstance(num, i.AllIntegersI64):
             return 8
         else:
             raise ValueError("Invalid input value!")
 
     def identify_float(self, num: i.AllFloats) -> int:
-        if isinstance(num, i.AllFloats0):
+        if isinstance(num, i.AllFloatsF32):
             return 0
-        if isinstance(num, i.AllFloats1):
+        if isinstance(num, i.AllFloatsF64):
             return 1
         else:
             raise ValueError("Invalid input value!")
 
     def identify_text(self, text: i.AllText) -> int:
-        if isinstance(text, i.AllText0):
+        if isinstance(text, i.AllTextChar):
             return 0
-        if isinstance(text, i.AllFloats1):
+        if isinstance(text, i.AllFloatsF64):
             return 1
         else:
             raise ValueError("Invalid input value!")
 
     # A simple use of a union which contains multiple entries of the same type
     def add_one_duplicated(self, num: i.DuplicatedS32) ->  i.DuplicatedS32:
-        if isinstance(num, i.DuplicatedS320):
-            return i.DuplicatedS320(num.value + 1)
-        if isinstance(num, i.DuplicatedS321):
-            return i.DuplicatedS321(num.value + 1)
-        if isinstance(num, i.DuplicatedS322):
-            return i.DuplicatedS322(num.value + 1)
+        if isinstance(num, i.DuplicatedS32I320):
+            return i.DuplicatedS32I320(num.value + 1)
+        if isinstance(num, i.DuplicatedS32I321):
+            return i.DuplicatedS32I321(num.value + 1)
+        if isinstance(num, i.DuplicatedS32I322):
+            return i.DuplicatedS32I322(num.value + 1)
         else:
             raise ValueError("Invalid input value!")
 
     # Identify each case of unions which contains multiple entries of the same type
     def identify_duplicated(self, num: i.DuplicatedS32) -> int:
-        if isinstance(num, i.DuplicatedS320):
+        if isinstance(num, i.DuplicatedS32I320):
             return 0
-        if isinstance(num, i.DuplicatedS321):
+        if isinstance(num, i.DuplicatedS32I321):
             return 1
-        if isinstance(num, i.DuplicatedS322):
+        if isinstance(num, i.DuplicatedS32I322):
             return 2
         else:
             raise ValueError("Invalid input value!")
@@ -169,62 +169,62 @@ def run(wasm_file: str) -> None:
 
     # All-Integers
     # Booleans
-    assert wasm.add_one_integer(store, e.AllIntegers0(False)) == e.AllIntegers0(True)
-    assert wasm.add_one_integer(store, e.AllIntegers0(True)) == e.AllIntegers0(False)
+    assert wasm.add_one_integer(store, e.AllIntegersBool(False)) == e.AllIntegersBool(True)
+    assert wasm.add_one_integer(store, e.AllIntegersBool(True)) == e.AllIntegersBool(False)
     # Unsigned integers
-    assert wasm.add_one_integer(store, e.AllIntegers1(0)) == e.AllIntegers1(1)
-    assert wasm.add_one_integer(store, e.AllIntegers1(2**8-1)) == e.AllIntegers1(0)
-    assert wasm.add_one_integer(store, e.AllIntegers2(0)) == e.AllIntegers2(1)
-    assert wasm.add_one_integer(store, e.AllIntegers2(2**16-1)) == e.AllIntegers2(0)
-    assert wasm.add_one_integer(store, e.AllIntegers3(0)) == e.AllIntegers3(1)
-    assert wasm.add_one_integer(store, e.AllIntegers3(2**32-1)) == e.AllIntegers3(0)
-    assert wasm.add_one_integer(store, e.AllIntegers4(0)) == e.AllIntegers4(1)
-    assert wasm.add_one_integer(store, e.AllIntegers4(2**64-1)) == e.AllIntegers4(0)
+    assert wasm.add_one_integer(store, e.AllIntegersU8(0)) == e.AllIntegersU8(1)
+    assert wasm.add_one_integer(store, e.AllIntegersU8(2**8-1)) == e.AllIntegersU8(0)
+    assert wasm.add_one_integer(store, e.AllIntegersU16(0)) == e.AllIntegersU16(1)
+    assert wasm.add_one_integer(store, e.AllIntegersU16(2**16-1)) == e.AllIntegersU16(0)
+    assert wasm.add_one_integer(store, e.AllIntegersU32(0)) == e.AllIntegersU32(1)
+    assert wasm.add_one_integer(store, e.AllIntegersU32(2**32-1)) == e.AllIntegersU32(0)
+    assert wasm.add_one_integer(store, e.AllIntegersU64(0)) == e.AllIntegersU64(1)
+    assert wasm.add_one_integer(store, e.AllIntegersU64(2**64-1)) == e.AllIntegersU64(0)
     # Signed integers
-    assert wasm.add_one_integer(store, e.AllIntegers5(0)) == e.AllIntegers5(1)
-    assert wasm.add_one_integer(store, e.AllIntegers5(2**7-1)) == e.AllIntegers5(-2**7)
-    assert wasm.add_one_integer(store, e.AllIntegers6(0)) == e.AllIntegers6(1)
-    assert wasm.add_one_integer(store, e.AllIntegers6(2**15-1)) == e.AllIntegers6(-2**15)
-    assert wasm.add_one_integer(store, e.AllIntegers7(0)) == e.AllIntegers7(1)
-    assert wasm.add_one_integer(store, e.AllIntegers7(2**31-1)) == e.AllIntegers7(-2**31)
-    assert wasm.add_one_integer(store, e.AllIntegers8(0)) == e.AllIntegers8(1)
-    assert wasm.add_one_integer(store, e.AllIntegers8(2**63-1)) == e.AllIntegers8(-2**63)
+    assert wasm.add_one_integer(store, e.AllIntegersI8(0)) == e.AllIntegersI8(1)
+    assert wasm.add_one_integer(store, e.AllIntegersI8(2**7-1)) == e.AllIntegersI8(-2**7)
+    assert wasm.add_one_integer(store, e.AllIntegersI16(0)) == e.AllIntegersI16(1)
+    assert wasm.add_one_integer(store, e.AllIntegersI16(2**15-1)) == e.AllIntegersI16(-2**15)
+    assert wasm.add_one_integer(store, e.AllIntegersI32(0)) == e.AllIntegersI32(1)
+    assert wasm.add_one_integer(store, e.AllIntegersI32(2**31-1)) == e.AllIntegersI32(-2**31)
+    assert wasm.add_one_integer(store, e.AllIntegersI64(0)) == e.AllIntegersI64(1)
+    assert wasm.add_one_integer(store, e.AllIntegersI64(2**63-1)) == e.AllIntegersI64(-2**63)
 
     # All-Floats
-    assert wasm.add_one_float(store, e.AllFloats0(0.0)) == e.AllFloats0(1.0)
-    assert wasm.add_one_float(store, e.AllFloats1(0.0)) == e.AllFloats1(1.0)
+    assert wasm.add_one_float(store, e.AllFloatsF32(0.0)) == e.AllFloatsF32(1.0)
+    assert wasm.add_one_float(store, e.AllFloatsF64(0.0)) == e.AllFloatsF64(1.0)
 
     # All-Text
-    assert wasm.replace_first_char(store, e.AllText0('a'), 'z') == e.AllText0('z')
-    assert wasm.replace_first_char(store, e.AllText1('abc'), 'z') == e.AllText1('zbc')
+    assert wasm.replace_first_char(store, e.AllTextChar('a'), 'z') == e.AllTextChar('z')
+    assert wasm.replace_first_char(store, e.AllTextString('abc'), 'z') == e.AllTextString('zbc')
 
     # All-Integers
-    assert wasm.identify_integer(store, e.AllIntegers0(True)) == 0
-    assert wasm.identify_integer(store, e.AllIntegers1(0)) == 1
-    assert wasm.identify_integer(store, e.AllIntegers2(0)) == 2
-    assert wasm.identify_integer(store, e.AllIntegers3(0)) == 3
-    assert wasm.identify_integer(store, e.AllIntegers4(0)) == 4
-    assert wasm.identify_integer(store, e.AllIntegers5(0)) == 5
-    assert wasm.identify_integer(store, e.AllIntegers6(0)) == 6
-    assert wasm.identify_integer(store, e.AllIntegers7(0)) == 7
-    assert wasm.identify_integer(store, e.AllIntegers8(0)) == 8
+    assert wasm.identify_integer(store, e.AllIntegersBool(True)) == 0
+    assert wasm.identify_integer(store, e.AllIntegersU8(0)) == 1
+    assert wasm.identify_integer(store, e.AllIntegersU16(0)) == 2
+    assert wasm.identify_integer(store, e.AllIntegersU32(0)) == 3
+    assert wasm.identify_integer(store, e.AllIntegersU64(0)) == 4
+    assert wasm.identify_integer(store, e.AllIntegersI8(0)) == 5
+    assert wasm.identify_integer(store, e.AllIntegersI16(0)) == 6
+    assert wasm.identify_integer(store, e.AllIntegersI32(0)) == 7
+    assert wasm.identify_integer(store, e.AllIntegersI64(0)) == 8
 
     # All-Floats
-    assert wasm.identify_float(store, e.AllFloats0(0.0)) == 0
-    assert wasm.identify_float(store, e.AllFloats1(0.0)) == 1
+    assert wasm.identify_float(store, e.AllFloatsF32(0.0)) == 0
+    assert wasm.identify_float(store, e.AllFloatsF64(0.0)) == 1
 
     # All-Text
-    assert wasm.identify_text(store, e.AllText0('a')) == 0
-    assert wasm.identify_text(store, e.AllText1('abc')) == 1
+    assert wasm.identify_text(store, e.AllTextChar('a')) == 0
+    assert wasm.identify_text(store, e.AllTextString('abc')) == 1
 
     # Duplicated
-    assert wasm.add_one_duplicated(store, e.DuplicatedS320(0)) == e.DuplicatedS320(1)
-    assert wasm.add_one_duplicated(store, e.DuplicatedS321(1)) == e.DuplicatedS321(2)
-    assert wasm.add_one_duplicated(store, e.DuplicatedS322(2)) == e.DuplicatedS322(3)
+    assert wasm.add_one_duplicated(store, e.DuplicatedS32I320(0)) == e.DuplicatedS32I320(1)
+    assert wasm.add_one_duplicated(store, e.DuplicatedS32I321(1)) == e.DuplicatedS32I321(2)
+    assert wasm.add_one_duplicated(store, e.DuplicatedS32I322(2)) == e.DuplicatedS32I322(3)
 
-    assert wasm.identify_duplicated(store, e.DuplicatedS320(0)) == 0
-    assert wasm.identify_duplicated(store, e.DuplicatedS321(0)) == 1
-    assert wasm.identify_duplicated(store, e.DuplicatedS322(0)) == 2
+    assert wasm.identify_duplicated(store, e.DuplicatedS32I320(0)) == 0
+    assert wasm.identify_duplicated(store, e.DuplicatedS32I321(0)) == 1
+    assert wasm.identify_duplicated(store, e.DuplicatedS32I322(0)) == 2
 
     # Distinguishable
     assert wasm.add_one_distinguishable_num(store, 0.0) == 1.0